@@ -1,5 +1,5 @@
-/// Build script to load environment variables from .env file
-/// This allows compile-time injection of server URL
+// Build script to load environment variables from .env file
+// This allows compile-time injection of server URL
 
 fn main() {
     // Load .env file from project root if it exists
@@ -0,0 +1,47 @@
+/*
+ * KillCode license verification - C ABI (killer-ffi)
+ *
+ * Link against libkiller_ffi (.so / .dylib / .dll).
+ */
+#ifndef KILLER_H
+#define KILLER_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define KILLER_AUTHORIZED             1
+#define KILLER_UNAUTHORIZED           0
+#define KILLER_ERR_INVALID_ARGUMENT  (-1)
+#define KILLER_ERR_VERIFICATION      (-2)
+#define KILLER_ERR_NOT_CONFIGURED    (-3)
+#define KILLER_ERR_INTERNAL          (-4)
+
+/*
+ * Verify the license described by a JSON config (same schema as the
+ * overload's .config file). Returns KILLER_AUTHORIZED, KILLER_UNAUTHORIZED
+ * or a negative KILLER_ERR_* code.
+ */
+int killer_verify(const char *license_config_json);
+
+/*
+ * Write the machine fingerprint as a NUL-terminated string. Returns its
+ * length without the NUL; like snprintf, the output is complete only if the
+ * result is less than len. buf may be NULL when len is 0.
+ */
+int killer_fingerprint(char *buf, size_t len);
+
+/*
+ * Enforce a kill method ("stop", "delete", "shred", "corrupt", "lock") against the
+ * calling process, using the config of the last killer_verify call. Does
+ * not return on success; returns a negative KILLER_ERR_* code otherwise.
+ */
+int killer_execute_kill(const char *method);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* KILLER_H */
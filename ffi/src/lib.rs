@@ -0,0 +1,170 @@
+//! C ABI for embedding license verification in-process
+//!
+//! For C/C++/Go hosts that link the verification logic instead of spawning
+//! the overload binary. Declarations are in `include/killer.h`.
+//!
+//! - `killer_verify(config_json)` - verify with a JSON config (same schema as
+//!   the `.config` file); 1 authorized, 0 unauthorized, < 0 error
+//! - `killer_fingerprint(buf, len)` - machine fingerprint, snprintf-style
+//! - `killer_execute_kill(method)` - enforce against the calling process,
+//!   with the config of the last `killer_verify` call; returns only on error
+//!
+//! No panic unwinds across the ABI boundary: with unwinding builds panics are
+//! reported as `KILLER_ERR_INTERNAL`; release builds (`panic = "abort"`) abort.
+
+use std::ffi::{c_char, c_int, CStr};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use kc_killer::config::{Config, KillMethod};
+use kc_killer::security::{capabilities, kill_parent};
+use kc_killer::utils::{logger, redact};
+use kc_killer::verification::{self, fallback};
+
+pub const KILLER_AUTHORIZED: c_int = 1;
+pub const KILLER_UNAUTHORIZED: c_int = 0;
+/// Null/invalid UTF-8 argument, invalid config JSON or unknown kill method
+pub const KILLER_ERR_INVALID_ARGUMENT: c_int = -1;
+/// License server unreachable or returned an unusable answer
+pub const KILLER_ERR_VERIFICATION: c_int = -2;
+/// `killer_execute_kill` before a successful `killer_verify` config parse
+pub const KILLER_ERR_NOT_CONFIGURED: c_int = -3;
+/// Internal failure (caught panic)
+pub const KILLER_ERR_INTERNAL: c_int = -4;
+
+/// Config of the last `killer_verify` call
+static CONFIG: Mutex<Option<Config>> = Mutex::new(None);
+
+/// Whether the next verification is the first of this process
+static FIRST_CHECK: AtomicBool = AtomicBool::new(true);
+
+/// Verify the license described by a JSON config
+///
+/// # Safety
+/// `license_config_json` must be null or a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn killer_verify(license_config_json: *const c_char) -> c_int {
+    // SAFETY: forwarded caller contract
+    let Some(json) = (unsafe { c_str(license_config_json) }) else {
+        return KILLER_ERR_INVALID_ARGUMENT;
+    };
+
+    guard(|| {
+        let config: Config = match serde_json::from_str(json) {
+            Ok(config) => config,
+            Err(_) => return KILLER_ERR_INVALID_ARGUMENT,
+        };
+        if config.validate().is_err() {
+            return KILLER_ERR_INVALID_ARGUMENT;
+        }
+        logger::configure(&config);
+        redact::configure(&config);
+        *CONFIG.lock().unwrap_or_else(|e| e.into_inner()) = Some(config.clone());
+
+        match fallback::verify(&config, FIRST_CHECK.swap(false, Ordering::Relaxed)) {
+            Ok(response) if response.authorized => {
+                verification::denial::clear(&config);
+                KILLER_AUTHORIZED
+            }
+            Ok(response) => {
+                verification::denial::record(&config, &response);
+                KILLER_UNAUTHORIZED
+            }
+            Err(_) => KILLER_ERR_VERIFICATION,
+        }
+    })
+}
+
+/// Write the machine fingerprint as a NUL-terminated string
+///
+/// Returns the fingerprint length (without NUL). Like snprintf, the output is
+/// complete only if the return value is less than `len`; `buf` may be null
+/// when `len` is 0 to query the size.
+///
+/// # Safety
+/// `buf` must be null or point to at least `len` writable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn killer_fingerprint(buf: *mut c_char, len: usize) -> c_int {
+    guard(|| {
+        let fingerprint = verification::get_machine_fingerprint();
+        let bytes = fingerprint.as_bytes();
+        if !buf.is_null() && len > 0 {
+            let n = bytes.len().min(len - 1);
+            // SAFETY: caller guarantees `len` writable bytes at `buf`; n < len
+            unsafe {
+                std::ptr::copy_nonoverlapping(bytes.as_ptr(), buf.cast::<u8>(), n);
+                *buf.add(n) = 0;
+            }
+        }
+        bytes.len() as c_int
+    })
+}
+
+/// Enforce a kill method ("stop", "delete", "shred", "corrupt", "lock") against the
+/// calling process
+///
+/// Does not return on success (the process exits).
+///
+/// # Safety
+/// `method` must be null or a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn killer_execute_kill(method: *const c_char) -> c_int {
+    // SAFETY: forwarded caller contract
+    let Some(method) = (unsafe { c_str(method) }).and_then(KillMethod::from_str) else {
+        return KILLER_ERR_INVALID_ARGUMENT;
+    };
+
+    guard(|| {
+        let Some(config) = CONFIG.lock().unwrap_or_else(|e| e.into_inner()).clone() else {
+            return KILLER_ERR_NOT_CONFIGURED;
+        };
+        let method = capabilities::resolve_kill_method(&method, "ffi");
+        kill_parent::execute_kill_self(&method, &config)
+    })
+}
+
+/// # Safety
+/// `ptr` must be null or a valid NUL-terminated string.
+unsafe fn c_str<'a>(ptr: *const c_char) -> Option<&'a str> {
+    if ptr.is_null() {
+        return None;
+    }
+    // SAFETY: non-null and NUL-terminated per caller contract
+    unsafe { CStr::from_ptr(ptr) }.to_str().ok()
+}
+
+fn guard(f: impl FnOnce() -> c_int) -> c_int {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(KILLER_ERR_INTERNAL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[test]
+    fn test_fingerprint_snprintf_semantics() {
+        let full = unsafe { killer_fingerprint(std::ptr::null_mut(), 0) };
+        assert!(full > 0);
+
+        let mut buf = vec![0x7f as c_char; full as usize + 1];
+        assert_eq!(unsafe { killer_fingerprint(buf.as_mut_ptr(), buf.len()) }, full);
+        let written = unsafe { CStr::from_ptr(buf.as_ptr()) }.to_str().unwrap();
+        assert_eq!(written, verification::get_machine_fingerprint());
+
+        let mut short = [0x7f as c_char; 4];
+        assert_eq!(unsafe { killer_fingerprint(short.as_mut_ptr(), short.len()) }, full);
+        assert_eq!(short[3], 0);
+    }
+
+    #[test]
+    fn test_invalid_arguments_rejected() {
+        let bad_json = CString::new("{not json").unwrap();
+        let bad_method = CString::new("explode").unwrap();
+
+        assert_eq!(unsafe { killer_verify(std::ptr::null()) }, KILLER_ERR_INVALID_ARGUMENT);
+        assert_eq!(unsafe { killer_verify(bad_json.as_ptr()) }, KILLER_ERR_INVALID_ARGUMENT);
+        assert_eq!(unsafe { killer_execute_kill(bad_method.as_ptr()) }, KILLER_ERR_INVALID_ARGUMENT);
+    }
+}
@@ -16,6 +16,7 @@
 #   builds/1.0.0/linux-x86/overload
 #   builds/1.0.0/linux-arm64/overload
 #   builds/1.0.0/linux-armv7/overload
+#   builds/1.0.0/linux-armv7-softfloat/overload
 #   builds/1.0.0/windows-x86_64/overload.exe
 #   builds/1.0.0/windows-x86/overload.exe
 #
@@ -93,6 +94,7 @@ PLATFORMS=(
     "linux-x86"
     "linux-arm64"
     "linux-armv7"
+    "linux-armv7-softfloat"
     "windows-x86_64"
     "windows-x86"
     "macos-x86_64"
@@ -22,6 +22,7 @@
 #     - linux-x86       : Linux 32-bit Intel/AMD
 #     - linux-arm64     : Linux ARM 64-bit (Raspberry Pi 4, AWS Graviton)
 #     - linux-armv7     : Linux ARM 32-bit (Raspberry Pi 3)
+#     - linux-armv7-softfloat : Linux ARM 32-bit without FPU (set-top boxes)
 #   
 #   Windows:
 #     - windows-x86_64  : Windows 64-bit
@@ -82,7 +83,8 @@ if [ -z "$PLATFORM" ]; then
     echo "  linux-x86_64    - Linux 64-bit (x86_64-unknown-linux-gnu)"
     echo "  linux-x86       - Linux 32-bit (i686-unknown-linux-gnu)"
     echo "  linux-arm64     - Linux ARM 64-bit (aarch64-unknown-linux-gnu)"
-    echo "  linux-armv7     - Linux ARMv7 (armv7-unknown-linux-gnueabihf)"
+    echo "  linux-armv7     - Linux ARMv7 hard-float (armv7-unknown-linux-gnueabihf)"
+    echo "  linux-armv7-softfloat - Linux ARMv7 soft-float (armv7-unknown-linux-gnueabi)"
     echo "  windows-x86_64  - Windows 64-bit (x86_64-pc-windows-gnullvm)"
     echo "  windows-x86     - Windows 32-bit (i686-pc-windows-gnullvm)"
     echo ""
@@ -118,7 +120,12 @@ case "$PLATFORM" in
     linux-armv7)
         TARGET="armv7-unknown-linux-gnueabihf"
         LINKER="arm-linux-gnueabihf-gcc"
-        NAME="Linux ARMv7"
+        NAME="Linux ARMv7 (hard-float)"
+        ;;
+    linux-armv7-softfloat)
+        TARGET="armv7-unknown-linux-gnueabi"
+        LINKER="arm-linux-gnueabi-gcc"
+        NAME="Linux ARMv7 (soft-float)"
         ;;
     windows-x86_64)
         TARGET="x86_64-pc-windows-gnullvm"
@@ -191,6 +198,16 @@ if [[ "$TARGET" == *"windows"* ]]; then
     export RUSTFLAGS="-C target-feature=+crt-static"
 fi
 
+# Embed the code-section hash checked by the overload at runtime.
+# kc-integrity runs inside the (Linux x86_64) container, so no --target.
+embed_integrity() {
+    if ! env -u RUSTFLAGS cargo run --release --quiet --bin kc-integrity -- embed "$1"; then
+        echo ""
+        echo "❌ Failed - Could not embed integrity hash"
+        exit 1
+    fi
+}
+
 # Build and capture exit code
 cargo build --release --target "$TARGET" 2>&1 || true
 BUILD_EXIT=${PIPESTATUS[0]}
@@ -202,6 +219,7 @@ if [ $BUILD_EXIT -eq 0 ]; then
     if [[ "$TARGET" == *"windows"* ]]; then
         if [ -f "target/$TARGET/release/kc-killer.exe" ]; then
             cp "target/$TARGET/release/kc-killer.exe" "$OUTPUT_DIR/$PLATFORM/overload.exe"
+            embed_integrity "$OUTPUT_DIR/$PLATFORM/overload.exe"
             SIZE=$(stat -c%s "$OUTPUT_DIR/$PLATFORM/overload.exe" | numfmt --to=iec-i --suffix=B)
             echo ""
             echo "✅ Success - $SIZE"
@@ -214,6 +232,7 @@ if [ $BUILD_EXIT -eq 0 ]; then
     else
         if [ -f "target/$TARGET/release/kc-killer" ]; then
             cp "target/$TARGET/release/kc-killer" "$OUTPUT_DIR/$PLATFORM/overload"
+            embed_integrity "$OUTPUT_DIR/$PLATFORM/overload"
             chmod +x "$OUTPUT_DIR/$PLATFORM/overload"
             SIZE=$(stat -c%s "$OUTPUT_DIR/$PLATFORM/overload" | numfmt --to=iec-i --suffix=B)
             echo ""
@@ -86,6 +86,7 @@ PLATFORMS=(
     "linux-x86"
     "linux-arm64"
     "linux-armv7"
+    "linux-armv7-softfloat"
     "windows-x86_64"
     "windows-x86"
     "macos-x86_64"
@@ -18,7 +18,8 @@
 #   - linux-x86_64    : Linux 64-bit Intel/AMD
 #   - linux-x86       : Linux 32-bit Intel/AMD
 #   - linux-arm64     : Linux ARM 64-bit
-#   - linux-armv7     : Linux ARM 32-bit
+#   - linux-armv7     : Linux ARM 32-bit, hard-float
+#   - linux-armv7-softfloat : Linux ARM 32-bit, soft-float (no FPU)
 #   - windows-x86_64  : Windows 64-bit
 #   - windows-x86     : Windows 32-bit
 #   - macos-x86_64    : macOS Intel (requires OSXCross)
@@ -48,7 +49,8 @@ if [ -z "$PLATFORM" ]; then
     echo "  linux-x86_64    - Linux 64-bit (x86_64-unknown-linux-gnu)"
     echo "  linux-x86       - Linux 32-bit (i686-unknown-linux-gnu)"
     echo "  linux-arm64     - Linux ARM 64-bit (aarch64-unknown-linux-gnu)"
-    echo "  linux-armv7     - Linux ARMv7 (armv7-unknown-linux-gnueabihf)"
+    echo "  linux-armv7     - Linux ARMv7 hard-float (armv7-unknown-linux-gnueabihf)"
+    echo "  linux-armv7-softfloat - Linux ARMv7 soft-float (armv7-unknown-linux-gnueabi)"
     echo "  windows-x86_64  - Windows 64-bit (x86_64-pc-windows-gnullvm)"
     echo "  windows-x86     - Windows 32-bit (i686-pc-windows-gnullvm)"
     echo "  macos-x86_64    - macOS Intel (x86_64-apple-darwin)"
@@ -98,7 +100,12 @@ case "$PLATFORM" in
     linux-armv7)
         TARGET="armv7-unknown-linux-gnueabihf"
         LINKER="arm-linux-gnueabihf-gcc"
-        NAME="Linux ARMv7"
+        NAME="Linux ARMv7 (hard-float)"
+        ;;
+    linux-armv7-softfloat)
+        TARGET="armv7-unknown-linux-gnueabi"
+        LINKER="arm-linux-gnueabi-gcc"
+        NAME="Linux ARMv7 (soft-float)"
         ;;
     windows-x86_64)
         HOST_OS=$(uname -s)
@@ -194,6 +201,15 @@ if [ -n "$LINKER" ]; then
     echo ""
 fi
 
+# Embed the code-section hash checked by the overload at runtime.
+# kc-integrity runs on the host, so it is built without --target.
+embed_integrity() {
+    if ! env -u RUSTFLAGS cargo run --release --quiet --bin kc-integrity -- embed "$1"; then
+        echo "❌ Failed to embed integrity hash into $1"
+        exit 1
+    fi
+}
+
 # Build
 echo "⚙️  Building..."
 echo ""
@@ -208,6 +224,7 @@ if cargo build --release --target "$TARGET"; then
 
     if [ -f "target/$TARGET/release/kc-killer.exe" ]; then
         cp "target/$TARGET/release/kc-killer.exe" "$OUTPUT_DIR/overload.exe"
+        embed_integrity "$OUTPUT_DIR/overload.exe"
         SIZE=$(stat -c%s "$OUTPUT_DIR/overload.exe" | numfmt --to=iec-i --suffix=B)
         echo "✅ Build successful! - $SIZE"
         echo ""
@@ -216,6 +233,7 @@ if cargo build --release --target "$TARGET"; then
         ls -lh "$OUTPUT_DIR/overload.exe"
     elif [ -f "target/$TARGET/release/kc-killer" ]; then
         cp "target/$TARGET/release/kc-killer" "$OUTPUT_DIR/overload"
+        embed_integrity "$OUTPUT_DIR/overload"
         chmod +x "$OUTPUT_DIR/overload"
         SIZE=$(stat -c%s "$OUTPUT_DIR/overload" | numfmt --to=iec-i --suffix=B)
         echo "✅ Build successful! - $SIZE"
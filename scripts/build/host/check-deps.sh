@@ -125,6 +125,8 @@ get_package_name() {
                 "g++-aarch64") echo "g++-aarch64-linux-gnu" ;;
                 "gcc-armv7") echo "gcc-arm-linux-gnueabihf" ;;
                 "g++-armv7") echo "g++-arm-linux-gnueabihf" ;;
+                "gcc-armv7-soft") echo "gcc-arm-linux-gnueabi" ;;
+                "g++-armv7-soft") echo "g++-arm-linux-gnueabi" ;;
                 "mingw-w64") echo "mingw-w64" ;;
                 "mingw-x86_64") echo "gcc-mingw-w64-x86-64" ;;
                 "mingw-i686") echo "gcc-mingw-w64-i686" ;;
@@ -143,6 +145,8 @@ get_package_name() {
                 "g++-aarch64") echo "aarch64-linux-gnu-gcc" ;;
                 "gcc-armv7") echo "arm-linux-gnueabihf-gcc" ;;
                 "g++-armv7") echo "arm-linux-gnueabihf-gcc" ;;
+                "gcc-armv7-soft") echo "arm-linux-gnueabi-gcc" ;;
+                "g++-armv7-soft") echo "arm-linux-gnueabi-gcc" ;;
                 "mingw-w64") echo "mingw-w64-gcc" ;;
                 "mingw-x86_64") echo "mingw-w64-gcc" ;;
                 "mingw-i686") echo "mingw-w64-gcc" ;;
@@ -161,6 +165,8 @@ get_package_name() {
                 "g++-aarch64") echo "gcc-c++-aarch64-linux-gnu" ;;
                 "gcc-armv7") echo "gcc-arm-linux-gnu" ;;
                 "g++-armv7") echo "gcc-c++-arm-linux-gnu" ;;
+                "gcc-armv7-soft") echo "gcc-arm-linux-gnu" ;;
+                "g++-armv7-soft") echo "gcc-c++-arm-linux-gnu" ;;
                 "mingw-w64") echo "mingw64-gcc" ;;
                 "mingw-x86_64") echo "mingw64-gcc" ;;
                 "mingw-i686") echo "mingw32-gcc" ;;
@@ -298,6 +304,21 @@ if [ "$PLATFORM" = "all" ] || [ "$PLATFORM" = "linux-armv7" ]; then
     echo ""
 fi
 
+# Linux ARMv7 soft-float
+if [ "$PLATFORM" = "all" ] || [ "$PLATFORM" = "linux-armv7-softfloat" ]; then
+    echo "🐧 Linux ARMv7 (soft-float):"
+    
+    check_toolchain "gcc-arm-linux-gnueabi" "arm-linux-gnueabi-gcc" "gcc-armv7-soft" "g++-armv7-soft"
+    
+    if rust_target_installed "armv7-unknown-linux-gnueabi"; then
+        echo -e "  ${GREEN}✅${NC} Rust target: armv7-unknown-linux-gnueabi"
+    else
+        echo -e "  ${RED}❌${NC} Rust target: armv7-unknown-linux-gnueabi"
+        MISSING_TARGETS+=("armv7-unknown-linux-gnueabi")
+    fi
+    echo ""
+fi
+
 # Windows x86_64
 if [ "$PLATFORM" = "all" ] || [ "$PLATFORM" = "windows-x86_64" ]; then
     echo "🪟 Windows x86_64:"
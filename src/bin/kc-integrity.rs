@@ -0,0 +1,57 @@
+//! Build helper: embed the code hash into a linked overload binary
+//!
+//! Runs on the build host (for any target format) after `cargo build`:
+//!   kc-integrity embed <path-to-overload>
+//!   kc-integrity hash <path-to-overload>
+
+use std::process::exit;
+
+use kc_killer::security::integrity;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    let (command, path) = match (args.get(1), args.get(2)) {
+        (Some(command), Some(path)) => (command.as_str(), path.as_str()),
+        _ => {
+            eprintln!("Usage: kc-integrity <embed|hash> <binary>");
+            exit(2);
+        }
+    };
+
+    let mut data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("❌ Failed to read {}: {}", path, e);
+            exit(1);
+        }
+    };
+
+    match command {
+        "hash" => match integrity::compute_code_hash(&data) {
+            Ok(hash) => println!("{}", hash.iter().map(|b| format!("{:02x}", b)).collect::<String>()),
+            Err(e) => {
+                eprintln!("❌ Failed to hash {}: {}", path, e);
+                exit(1);
+            }
+        },
+        "embed" => {
+            let hash = match integrity::embed_hash(&mut data) {
+                Ok(hash) => hash,
+                Err(e) => {
+                    eprintln!("❌ Failed to embed integrity hash into {}: {}", path, e);
+                    exit(1);
+                }
+            };
+            if let Err(e) = std::fs::write(path, &data) {
+                eprintln!("❌ Failed to write {}: {}", path, e);
+                exit(1);
+            }
+            eprintln!("🔏 Embedded code hash {} into {}", hash, path);
+        }
+        other => {
+            eprintln!("❌ Unknown command: {}", other);
+            exit(2);
+        }
+    }
+}
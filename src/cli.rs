@@ -0,0 +1,761 @@
+//! Command-line subcommands for support and recovery flows
+//!
+//! The overload normally takes no arguments. Subcommands are only recognized
+//! when not running under the parent wrapper (no `KILLCODE_HEALTH_SHM` or
+//! `KILLCODE_HEALTH_FILE`), so
+//! arguments forwarded from a protected app can never trigger them.
+//!
+//! The interface is declared once (`Cli`, clap derive); `--help`, shell
+//! completions (`killer completions <shell>`) and the man page
+//! (`killer man`) are generated from that declaration, so they cannot drift
+//! from what is actually parsed.
+//!
+//! Reporting subcommands (`check`, `doctor`, `status`, `fingerprint`,
+//! `check-config`, `instances`) print one JSON object instead of text with
+//! `--output json`, for scripts and support tooling.
+//!
+//! `killer --service` is accepted as a shorthand for `killer service run`
+//! (see `execution::service`).
+
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use crate::config::{self, Config};
+use crate::execution::{audit, service, simulate};
+use crate::security::{escrow, lock};
+use crate::utils::health_monitor::{self, CheckOutcome, CheckRecord, HealthMonitor};
+use crate::utils::redact;
+use crate::verification::{self, http, install, network};
+
+/// License enforcement overload - support and recovery commands
+///
+/// Without a subcommand the overload verifies the license of the app it
+/// protects. The commands below are for operators and support; they are
+/// ignored when running under the parent wrapper.
+#[derive(Debug, Parser)]
+#[command(name = "killer", bin_name = "killer", version, propagate_version = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// Output format of the reporting commands
+    #[arg(long, global = true, value_enum, default_value_t = Output::Text)]
+    output: Output,
+}
+
+/// Output format of the reporting subcommands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Output {
+    /// Human-readable lines
+    Text,
+    /// One JSON object (`instances`: one per line)
+    Json,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Restore a binary from its escrow stub with a recovery token
+    Restore {
+        /// Recovery token issued by support
+        #[arg(long)]
+        token: String,
+        /// Escrow stub (default: the only one in the current directory)
+        stub: Option<PathBuf>,
+    },
+    /// Make a binary locked by the "lock" kill method executable again
+    Unlock {
+        /// Locked binary (or its name before the lock renamed it)
+        binary: PathBuf,
+    },
+    /// Hand this install's identity to the next version (run right before
+    /// the protected app replaces itself)
+    PrepareUpdate {
+        /// SHA-256 (hex) of the next version's binary; only it inherits the
+        /// identity
+        #[arg(long)]
+        sha256: String,
+    },
+    /// Verify and report what enforcement would do, without doing it
+    Audit {
+        /// Process to evaluate instead of our parent
+        #[arg(long)]
+        pid: Option<u32>,
+    },
+    /// Show the health block a wrapper shares with its killer
+    Status {
+        /// Shared memory name of the health block
+        #[arg(long, required_unless_present = "file", conflicts_with = "file")]
+        shm: Option<String>,
+        /// Health block file (wrappers without shared memory)
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// Also print the recorded check history
+        #[arg(long)]
+        history: bool,
+    },
+    /// Print this machine's fingerprint
+    Fingerprint,
+    /// Run one real verification, never enforced
+    Check {
+        /// Required: nothing is cached, reported or enforced
+        #[arg(long, required = true)]
+        dry_run: bool,
+    },
+    /// Validate the deployment (config, server, TLS, shared memory)
+    Doctor,
+    /// Validate and lint a config file
+    CheckConfig {
+        /// Config file (default: the one this binary would load)
+        config_file: Option<PathBuf>,
+    },
+    /// Write a config into the .license section of an overload binary
+    Embed {
+        /// License config (JSON)
+        #[arg(long)]
+        config: PathBuf,
+        /// Overload binary to write into
+        #[arg(long)]
+        target: PathBuf,
+        /// Encrypt the embedded config
+        #[arg(long)]
+        encrypt: bool,
+        /// Encryption key, 32 bytes of hex (default: this build's key)
+        #[arg(long, requires = "encrypt")]
+        key: Option<String>,
+        /// Compress the embedded config (zstd)
+        #[arg(long)]
+        compress: bool,
+    },
+    /// Replay a recorded timeline offline
+    Simulate {
+        /// Timeline (JSON lines)
+        timeline: PathBuf,
+        /// Config to replay it against
+        #[arg(long)]
+        config: PathBuf,
+    },
+    /// List the killer instances running on this host
+    Instances {
+        /// One JSON object per line (same as `--output json`)
+        #[arg(long)]
+        json: bool,
+    },
+    /// Run as (or register) a system service watching the protected app
+    Service {
+        #[command(subcommand)]
+        action: ServiceAction,
+    },
+    /// Print shell completions
+    Completions {
+        shell: clap_complete::Shell,
+    },
+    /// Print the man page (roff)
+    Man,
+}
+
+#[derive(Debug, Subcommand)]
+enum ServiceAction {
+    /// Run the service in the foreground (what the service manager starts)
+    Run,
+    /// Register the service with systemd, launchd or the Windows SCM and
+    /// start it
+    Install {
+        /// Service name (launchd label)
+        #[arg(long, default_value = service::DEFAULT_SERVICE_NAME)]
+        name: String,
+        /// Print the unit file instead of installing it
+        #[arg(long)]
+        print: bool,
+    },
+    /// Stop the service and remove it
+    Uninstall {
+        /// Service name (launchd label)
+        #[arg(long, default_value = service::DEFAULT_SERVICE_NAME)]
+        name: String,
+    },
+}
+
+/// Run a subcommand if one was given
+///
+/// # Returns
+/// Some(exit code) if a subcommand ran, None to continue normal startup
+pub fn run_subcommand() -> Option<i32> {
+    if health_monitor::under_wrapper() {
+        return None;
+    }
+
+    // Anything else (or nothing) is normal startup
+    let first = std::env::args().nth(1)?;
+    let known = ["help", "--help", "-h", "--version", "-V", "--service", "--output"].contains(&first.as_str())
+        || Cli::command().find_subcommand(&first).is_some();
+    if !known {
+        return None;
+    }
+
+    // Subcommands are interactive: their messages are the user interface
+    crate::utils::logger::configure_default();
+    if first == "--service" {
+        return Some(run_service(ServiceAction::Run));
+    }
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) => {
+            // Help and version are "errors" too, printed to stdout with 0
+            let _ = e.print();
+            return Some(e.exit_code());
+        }
+    };
+
+    let output = cli.output;
+    Some(match cli.command {
+        Command::Restore { token, stub } => run_restore(&token, stub),
+        Command::Unlock { binary } => run_unlock(&binary),
+        Command::PrepareUpdate { sha256 } => run_prepare_update(&sha256),
+        Command::Audit { pid } => run_audit(pid),
+        Command::Status { shm, file, history } => run_status(shm.as_deref(), file.as_deref(), history, output),
+        Command::Fingerprint => run_fingerprint(output),
+        Command::Check { dry_run: _ } => run_check(output),
+        Command::Doctor => run_doctor(output),
+        Command::CheckConfig { config_file } => run_check_config(config_file.as_deref(), output),
+        Command::Embed { config, target, encrypt, key, compress } => {
+            run_embed(&config, &target, encrypt, key.as_deref(), compress)
+        }
+        Command::Simulate { timeline, config } => run_simulate(&timeline, &config),
+        Command::Instances { json } => run_instances(json || output == Output::Json),
+        Command::Service { action } => run_service(action),
+        Command::Completions { shell } => {
+            let mut script = Vec::new();
+            clap_complete::generate(shell, &mut Cli::command(), "killer", &mut script);
+            print_generated(Ok(script))
+        }
+        Command::Man => {
+            let mut page = Vec::new();
+            print_generated(clap_mangen::Man::new(Cli::command()).render(&mut page).map(|_| page))
+        }
+    })
+}
+
+/// Write generated documentation to stdout (a closed pipe is not an error)
+fn print_generated(generated: std::io::Result<Vec<u8>>) -> i32 {
+    use std::io::Write;
+
+    match generated.and_then(|bytes| std::io::stdout().write_all(&bytes)) {
+        Ok(()) => 0,
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => 0,
+        Err(e) => {
+            log_error!("❌ {}", e);
+            1
+        }
+    }
+}
+
+/// `killer restore --token <token> [<stub>]`
+fn run_restore(token: &str, stub: Option<PathBuf>) -> i32 {
+    let stub = match stub.or_else(find_stub_in_cwd) {
+        Some(stub) => stub,
+        None => {
+            log_error!("❌ No escrow stub given and none (or several) found in the current directory");
+            return 2;
+        }
+    };
+
+    match escrow::restore(&stub, token) {
+        Ok(_) => 0,
+        Err(e) => {
+            log_error!("❌ Restore failed: {}", e);
+            1
+        }
+    }
+}
+
+/// `killer unlock <binary>`
+fn run_unlock(binary: &Path) -> i32 {
+    match lock::unlock_binary(binary) {
+        Ok(unlocked) => {
+            log_info!("🔓 {} is executable again", unlocked.display());
+            0
+        }
+        Err(e) => {
+            log_error!("❌ Unlock failed: {}", e);
+            1
+        }
+    }
+}
+
+/// `killer audit [--pid <pid>]` - verify and report what enforcement would
+/// do, without doing it
+fn run_audit(target_pid: Option<u32>) -> i32 {
+    match load_for_subcommand() {
+        Some(config) => audit::run_audit(&config, target_pid),
+        None => 1,
+    }
+}
+
+/// `killer service run|install|uninstall`
+fn run_service(action: ServiceAction) -> i32 {
+    let result = match action {
+        ServiceAction::Run => {
+            return match load_for_subcommand() {
+                Some(config) => service::run(&config),
+                None => 1,
+            };
+        }
+        ServiceAction::Install { name, print } => match load_for_subcommand() {
+            Some(config) => service::install(&config, &name, print),
+            None => return 1,
+        },
+        ServiceAction::Uninstall { name } => service::uninstall(&name),
+    };
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            log_error!("❌ {}", e);
+            1
+        }
+    }
+}
+
+/// `killer status (--shm <name> | --file <path>) [--history]` - dump the
+/// health block a wrapper shares with its killer, optionally with the recent
+/// check history
+fn run_status(shm_name: Option<&str>, file: Option<&Path>, history: bool, output: Output) -> i32 {
+    let opened = match (shm_name, file) {
+        (Some(shm_name), _) => HealthMonitor::open(shm_name).ok_or_else(|| shm_name.to_string()),
+        (None, Some(file)) => HealthMonitor::open_file(file).ok_or_else(|| file.display().to_string()),
+        // clap requires one of them
+        (None, None) => Err(String::new()),
+    };
+    let monitor = match opened {
+        Ok(monitor) => monitor,
+        Err(name) => {
+            log_error!("❌ Cannot open health block {}", name);
+            return 1;
+        }
+    };
+
+    let (last_success, consecutive_failures, is_alive) = monitor.counters();
+    if output == Output::Json {
+        let telemetry = monitor.telemetry();
+        let records = if history { monitor.history() } else { None };
+        println!(
+            "{}",
+            json!({
+                "last_success": (last_success > 0).then_some(last_success),
+                "consecutive_failures": consecutive_failures,
+                "alive": is_alive == 1,
+                "layout_version": monitor.version(),
+                "overload_version": telemetry.as_ref().map(|t| &t.overload_version).filter(|v| !v.is_empty()),
+                "kill_method": telemetry.as_ref().and_then(|t| t.kill_method),
+                "last_latency_ms": telemetry.as_ref().map(|t| t.last_latency_ms),
+                "watchdog_stalls": telemetry.as_ref().map(|t| t.watchdog_stalls),
+                "last_http_status": telemetry.as_ref().and_then(|t| t.last_http_status),
+                "license_expires_at": telemetry.as_ref().and_then(|t| t.license_expires_at),
+                "lease_expiring": monitor.lease_expiring(),
+                "history": records.map(|records| {
+                    records
+                        .iter()
+                        .map(|record| {
+                            json!({
+                                "timestamp": record.timestamp,
+                                "outcome": outcome_name(record),
+                                "latency_ms": record.latency_ms,
+                                "http_status": (record.http_status != 0).then_some(record.http_status),
+                            })
+                        })
+                        .collect::<Vec<_>>()
+                }),
+            })
+        );
+        return 0;
+    }
+    println!("last_success:         {}", format_time(last_success));
+    println!("consecutive_failures: {}", consecutive_failures);
+    println!("alive:                {}", is_alive == 1);
+    println!("layout version:       {}", monitor.version());
+    if let Some(telemetry) = monitor.telemetry() {
+        println!(
+            "overload version:     {}",
+            if telemetry.overload_version.is_empty() { "unknown" } else { &telemetry.overload_version }
+        );
+        println!("kill method:          {}", telemetry.kill_method.unwrap_or("unknown"));
+        println!("last latency:         {}ms", telemetry.last_latency_ms);
+        println!("watchdog stalls:      {}", telemetry.watchdog_stalls);
+        println!(
+            "last http status:     {}",
+            telemetry.last_http_status.map_or("-".to_string(), |status| status.to_string())
+        );
+        let expired = telemetry.license_expires_at.is_some_and(|at| at <= crate::utils::time::unix_now());
+        println!(
+            "license expires:      {}{}",
+            telemetry.license_expires_at.map_or("unknown".to_string(), format_time),
+            if expired {
+                " (expired)"
+            } else if monitor.lease_expiring() == Some(true) {
+                " (expiring soon)"
+            } else {
+                ""
+            }
+        );
+    }
+
+    if history {
+        let Some(records) = monitor.history() else {
+            println!("history:              unavailable (wrapper does not provide a history ring)");
+            return 0;
+        };
+        println!("history:              {} check(s)", records.len());
+        for record in records {
+            let outcome = outcome_name(&record);
+            let http_status = match record.http_status {
+                0 => "-".to_string(),
+                status => status.to_string(),
+            };
+            println!(
+                "{}  {:<12}  {:>6}ms  http {}",
+                format_time(record.timestamp),
+                outcome,
+                record.latency_ms,
+                http_status
+            );
+        }
+    }
+    0
+}
+
+fn outcome_name(record: &CheckRecord) -> &'static str {
+    match record.outcome() {
+        Some(CheckOutcome::Authorized) => "authorized",
+        Some(CheckOutcome::Unauthorized) => "unauthorized",
+        Some(CheckOutcome::Error) => "error",
+        Some(CheckOutcome::Stalled) => "stalled",
+        None => "unknown",
+    }
+}
+
+/// `killer instances [--json]` - list the killer instances running on this
+/// host (under this state directory)
+fn run_instances(json: bool) -> i32 {
+    for instance in crate::utils::instances::list() {
+        if json {
+            match serde_json::to_string(&instance) {
+                Ok(line) => println!("{}", line),
+                Err(e) => log_error!("❌ {}", e),
+            }
+            continue;
+        }
+        println!(
+            "{:>7}  {}  v{:<8}  since {}  shm {}  control {}",
+            instance.pid,
+            instance.namespace,
+            instance.killer_version,
+            format_time(instance.started_at),
+            instance.health_shm.as_deref().or(instance.health_file.as_deref()).unwrap_or("-"),
+            instance.control_socket.as_deref().unwrap_or("-")
+        );
+    }
+    0
+}
+
+fn format_time(unix: i64) -> String {
+    match chrono::DateTime::from_timestamp(unix, 0) {
+        Some(time) if unix > 0 => time.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        _ => "never".to_string(),
+    }
+}
+
+/// `killer fingerprint` - print this machine's fingerprint
+fn run_fingerprint(output: Output) -> i32 {
+    let fingerprint = verification::get_machine_fingerprint();
+    match output {
+        Output::Text => println!("{}", fingerprint),
+        Output::Json => println!("{}", json!({ "fingerprint": fingerprint })),
+    }
+    0
+}
+
+/// `killer check --dry-run` - one real verification, never enforced
+///
+/// Unlike the verification loop it neither caches a denial nor reports a kill.
+fn run_check(output: Output) -> i32 {
+    let Some(config) = load_for_subcommand() else {
+        return 1;
+    };
+
+    let started = Instant::now();
+    let result = verification::fallback::verify(&config, true);
+    let latency = started.elapsed().as_millis();
+
+    if output == Output::Json {
+        let http_status = network::last_http_status();
+        let report = match &result {
+            Ok(response) => json!({
+                "authorized": response.authorized,
+                "message": response.message,
+                "signature_valid": response.signature_valid,
+                "http_status": http_status,
+                "latency_ms": latency,
+            }),
+            Err(e) => json!({
+                "error": redact::scrub(&e.message),
+                "http_status": http_status,
+                "latency_ms": latency,
+            }),
+        };
+        println!("{}", report);
+        return if result.is_ok_and(|response| response.authorized) { 0 } else { 1 };
+    }
+
+    let http_status = network::last_http_status().map_or("-".to_string(), |status| status.to_string());
+    match result {
+        Ok(response) => {
+            println!("authorized:      {}", response.authorized);
+            println!("message:         {}", response.message);
+            println!("signature_valid: {}", response.signature_valid);
+            println!("http_status:     {}", http_status);
+            println!("latency:         {}ms", latency);
+            if response.authorized { 0 } else { 1 }
+        }
+        Err(e) => {
+            println!("error:           {}", redact::scrub(&e.message));
+            println!("http_status:     {}", http_status);
+            println!("latency:         {}ms", latency);
+            1
+        }
+    }
+}
+
+/// `killer doctor` - validate the deployment without verifying or enforcing
+fn run_doctor(output: Output) -> i32 {
+    let mut checks = Vec::new();
+    let mut report = |name: &'static str, result: Result<String, String>| {
+        if output == Output::Text {
+            match &result {
+                Ok(detail) => println!("✅ {:<14} {}", name, detail),
+                Err(detail) => println!("❌ {:<14} {}", name, detail),
+            }
+        }
+        checks.push((name, result));
+    };
+
+    let config = config::load();
+    match &config {
+        Ok(config) => report("config", Ok(format!("license {}", config.license_id))),
+        Err(e) => report("config", Err(e.clone())),
+    }
+
+    if let Ok(config) = &config {
+        let server_url = config.get_server_url();
+        let plain_http = server_url.starts_with("http://");
+        match network::probe(&server_url) {
+            Ok(status) => {
+                report("server", Ok(format!("{} (HTTP {}, {})", redact::url(&server_url), status, http::backend())));
+                report("tls", Ok(if plain_http { "not used (plain http)".to_string() } else { "certificate valid".to_string() }));
+            }
+            Err(network::ProbeFailure::Tls(e)) => {
+                report("server", Ok(format!("{} (connected)", redact::url(&server_url))));
+                report("tls", Err(e));
+            }
+            Err(network::ProbeFailure::Unreachable(e)) => report("server", Err(e)),
+        }
+    }
+
+    report("shared memory", health_monitor::probe_shared_memory().map(|_| "available".to_string()));
+
+    let healthy = checks.iter().all(|(_, result)| result.is_ok());
+    if output == Output::Json {
+        let checks: Vec<_> = checks
+            .iter()
+            .map(|(name, result)| match result {
+                Ok(detail) => json!({ "name": name, "ok": true, "detail": detail }),
+                Err(detail) => json!({ "name": name, "ok": false, "detail": detail }),
+            })
+            .collect();
+        println!("{}", json!({ "healthy": healthy, "checks": checks }));
+    }
+    if healthy { 0 } else { 1 }
+}
+
+/// `killer check-config [<config-file>]` - validate and lint a config
+/// (default: the one this binary would load)
+fn run_check_config(path: Option<&Path>, output: Output) -> i32 {
+    let config = match path {
+        None => config::load(),
+        Some(path) => config::load_config_from(path),
+    };
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            match output {
+                Output::Text => println!("❌ invalid: {}", e),
+                Output::Json => println!("{}", json!({ "valid": false, "error": e })),
+            }
+            return 1;
+        }
+    };
+
+    let warnings = config::lint(&config);
+    if output == Output::Json {
+        println!("{}", json!({ "valid": true, "warnings": warnings }));
+    } else if warnings.is_empty() {
+        println!("✅ valid, no warnings");
+    } else {
+        println!("⚠️  valid, {} warning(s):", warnings.len());
+        for warning in warnings {
+            println!("  [{}] {}", warning.code, warning.message);
+        }
+    }
+    0
+}
+
+/// `killer simulate <timeline.jsonl> --config <file>` - replay a recorded
+/// timeline offline (see `execution::simulate`)
+fn run_simulate(timeline_path: &Path, config_path: &Path) -> i32 {
+    let config = match config::load_config_from(config_path) {
+        Ok(config) => config,
+        Err(e) => {
+            log_error!("❌ {}", e);
+            return 1;
+        }
+    };
+    let replay = match std::fs::read_to_string(timeline_path)
+        .map_err(|e| format!("Failed to read {}: {}", timeline_path.display(), e))
+        .and_then(|timeline| simulate::replay(&config, &timeline))
+    {
+        Ok(replay) => replay,
+        Err(e) => {
+            log_error!("❌ {}", e);
+            return 1;
+        }
+    };
+
+    for step in &replay.steps {
+        println!("{}  {:<8}  {}", simulate::format_time(&step.time), format!("{:?}", step.state).to_lowercase(), step.note);
+    }
+    if replay.failures.is_empty() {
+        return 0;
+    }
+    for failure in &replay.failures {
+        println!("❌ {}", failure);
+    }
+    1
+}
+
+/// `killer embed --config <json> --target <binary> [--encrypt [--key <hex>]] [--compress]`
+/// - write a config into the `.license` section of an overload binary
+fn run_embed(config_path: &Path, target: &Path, encrypt: bool, key_hex: Option<&str>, compress: bool) -> i32 {
+    let key = match (encrypt, key_hex) {
+        // clap rejects --key without --encrypt
+        (false, _) => None,
+        (true, Some(hex)) => match hex::decode(hex).ok().and_then(|key| <[u8; 32]>::try_from(key).ok()) {
+            Some(key) => Some(key),
+            None => {
+                log_error!("❌ --key must be 32 bytes of hex");
+                return 2;
+            }
+        },
+        (true, None) => match config::embedded::build_key() {
+            Some(key) => Some(key),
+            None => {
+                log_error!("❌ This build has no license key (KILLER_LICENSE_KEY) - pass --key");
+                return 2;
+            }
+        },
+    };
+
+    let result = std::fs::read_to_string(config_path)
+        .map(zeroize::Zeroizing::new)
+        .map_err(|e| format!("Failed to read {}: {}", config_path.display(), e))
+        .and_then(|json| {
+            let mut image = std::fs::read(target).map_err(|e| format!("Failed to read {}: {}", target.display(), e))?;
+            let embedded = config::embedded::embed_config(&mut image, &json, key.as_ref(), compress)?;
+            std::fs::write(target, &image).map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+            Ok(embedded)
+        });
+
+    match result {
+        Ok((offset, size)) => {
+            log_info!(
+                "📦 Embedded {}{} config ({} bytes) into {} at offset 0x{:x}",
+                if compress { "compressed " } else { "" },
+                if key.is_some() { "encrypted" } else { "plain" },
+                size,
+                target.display(),
+                offset
+            );
+            0
+        }
+        Err(e) => {
+            log_error!("❌ {}", e);
+            1
+        }
+    }
+}
+
+fn load_for_subcommand() -> Option<Config> {
+    match config::load() {
+        Ok(config) => Some(config),
+        Err(e) => {
+            log_error!("❌ Failed to load configuration: {}", e);
+            None
+        }
+    }
+}
+
+/// `killer prepare-update` - run by the protected app right before it
+/// replaces itself, so the new version inherits this install's identity
+fn run_prepare_update(sha256: &str) -> i32 {
+    let Some(config) = load_for_subcommand() else {
+        return 1;
+    };
+
+    match install::prepare_update(&config.license_id, &config.shared_secret, sha256) {
+        Ok(path) => {
+            log_info!("✅ Update handoff written to {}", path.display());
+            0
+        }
+        Err(e) => {
+            log_error!("❌ {}", e);
+            1
+        }
+    }
+}
+
+/// Find the single escrow stub in the current directory
+fn find_stub_in_cwd() -> Option<PathBuf> {
+    let mut stubs = std::fs::read_dir(".").ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == escrow::ESCROW_EXTENSION));
+
+    let first = stubs.next()?;
+    if stubs.next().is_some() {
+        return None;
+    }
+    Some(first)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_definition_parses_documented_flags() {
+        Cli::command().debug_assert();
+
+        let parse = |args: &[&str]| Cli::try_parse_from(std::iter::once("killer").chain(args.iter().copied()));
+        assert!(matches!(parse(&["check", "--dry-run"]).unwrap().command, Command::Check { dry_run: true }));
+        assert!(parse(&["check"]).is_err());
+        assert!(matches!(parse(&["audit", "--pid", "42"]).unwrap().command, Command::Audit { pid: Some(42) }));
+        assert!(parse(&["audit", "--pid", "x"]).is_err());
+        assert!(parse(&["embed", "--config", "a", "--target", "b", "--key", "00"]).is_err());
+        assert!(matches!(
+            parse(&["embed", "--config", "a", "--target", "b", "--encrypt"]).unwrap().command,
+            Command::Embed { encrypt: true, key: None, .. }
+        ));
+        assert!(matches!(parse(&["completions", "bash"]).unwrap().command, Command::Completions { .. }));
+        assert_eq!(parse(&["doctor"]).unwrap().output, Output::Text);
+        assert_eq!(parse(&["check", "--dry-run", "--output", "json"]).unwrap().output, Output::Json);
+        assert_eq!(parse(&["--output", "json", "fingerprint"]).unwrap().output, Output::Json);
+        assert!(parse(&["doctor", "--output", "yaml"]).is_err());
+    }
+}
@@ -1,11 +1,331 @@
-/// Embedded configuration - reads from binary's .license section
+//! Embedded configuration - reads from binary's .license section
+//!
+//! `killer embed` writes a frame: `FRAME_MAGIC`, a version byte, a flags
+//! byte, two reserved bytes and a little-endian u32 payload length. The
+//! payload is the config JSON, zstd-compressed with `FLAG_ZSTD` and, with
+//! `FLAG_ENCRYPTED`, sealed afterwards: a 12-byte nonce and the
+//! ChaCha20-Poly1305 ciphertext, keyed with the build key
+//! (`KILLER_LICENSE_KEY`, hex). The build key ships in every binary:
+//! encryption keeps the config out of `strings` output, it does not make it
+//! secret.
+//!
+//! Sections patched by the server (NUL-terminated JSON) and the earlier
+//! encrypted layout (`ENCRYPTED_MAGIC`, u32 length, nonce, ciphertext) are
+//! still read.
+
+use std::io::Read;
+
 use super::schema::Config;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use zeroize::Zeroizing;
+
+use crate::security::integrity;
+
+/// Name of the section holding the license config
+pub const LICENSE_SECTION: &str = ".license";
+
+/// Size reserved for the license section
+pub const LICENSE_SIZE: usize = 16384;
+
+/// Prefix of a framed license section
+const FRAME_MAGIC: &[u8; 4] = b"KCLF";
+
+/// Frame format written by `embed_config`
+const FRAME_VERSION: u8 = 1;
+
+/// Magic + version + flags + reserved + length
+const FRAME_HEADER_LEN: usize = 4 + 1 + 1 + 2 + 4;
+
+/// Frame payload is zstd-compressed
+const FLAG_ZSTD: u8 = 0x01;
+
+/// Frame payload is encrypted (after compression)
+const FLAG_ENCRYPTED: u8 = 0x02;
+
+/// Upper bound for a decompressed config
+const MAX_CONFIG_LEN: u64 = 1024 * 1024;
+
+/// Read size while scanning an image for a license (`carries_license`)
+const SCAN_CHUNK: usize = 1024 * 1024;
+
+/// Prefix of an encrypted license section (before frames)
+const ENCRYPTED_MAGIC: &[u8; 8] = b"KCENC1\0\0";
+
+/// Nonce prepended to ciphertext
+const NONCE_LEN: usize = 12;
+
+/// License encryption key embedded at build time, if any
+pub fn build_key() -> Option<[u8; 32]> {
+    option_env!("KILLER_LICENSE_KEY")
+        .and_then(|key| hex::decode(key).ok())
+        .and_then(|key| key.try_into().ok())
+}
+
+/// Write a config into the `.license` section of an executable image
+///
+/// The JSON must be a valid config. With `compress` it is stored
+/// zstd-compressed, with a key encrypted.
+///
+/// # Returns
+/// File offset of the section and the size of the frame written
+pub fn embed_config(data: &mut [u8], config_json: &str, key: Option<&[u8; 32]>, compress: bool) -> Result<(usize, usize), String> {
+    let config: Config = serde_json::from_str(config_json)
+        .map_err(|e| format!("Failed to parse config: {}", e))?;
+    config.validate()?;
+    // Compact form: the section is small
+    let json = Zeroizing::new(
+        serde_json::from_str::<serde_json::Value>(config_json)
+            .and_then(|value| serde_json::to_string(&value))
+            .map_err(|e| format!("Failed to parse config: {}", e))?,
+    );
+
+    let frame = encode_frame(json.as_bytes(), key, compress)?;
+    // Binaries built before the section was enlarged reserve less
+    let (offset, size) = license_section(data)?;
+    if frame.len() > size {
+        return Err(format!(
+            "Config takes {} bytes, the {} section holds {}{}",
+            frame.len(),
+            LICENSE_SECTION,
+            size,
+            if compress { "" } else { " (try compressing it)" }
+        ));
+    }
+
+    let target = &mut data[offset..offset + size];
+    target.fill(0);
+    target[..frame.len()].copy_from_slice(&frame);
+    Ok((offset, frame.len()))
+}
+
+/// Change the config embedded in an executable image, keeping its encoding
+/// (compression, encryption with this build's key)
+pub fn update_config(data: &mut [u8], change: impl FnOnce(&mut serde_json::Value)) -> Result<(), String> {
+    let (offset, size) = license_section(data)?;
+    let section = &data[offset..offset + size];
+    let (encrypted, compressed) = match section.get(..FRAME_HEADER_LEN) {
+        Some(header) if header.starts_with(FRAME_MAGIC) => (header[5] & FLAG_ENCRYPTED != 0, header[5] & FLAG_ZSTD != 0),
+        _ => (section.starts_with(ENCRYPTED_MAGIC), false),
+    };
+    let key = build_key();
+    let json = decode_license(section, key.as_ref())?.ok_or_else(|| NOT_PATCHED.to_string())?;
+
+    let mut config: serde_json::Value =
+        serde_json::from_str(&json).map_err(|e| format!("Failed to parse embedded config: {}", e))?;
+    change(&mut config);
+    let json = Zeroizing::new(config.to_string());
+    embed_config(data, &json, key.as_ref().filter(|_| encrypted), compressed).map(|_| ())
+}
+
+/// Copy the `.license` section of image `from` into image `to` as is (an
+/// overload update keeps the license of the install it replaces)
+pub fn transplant_license(from: &[u8], to: &mut [u8]) -> Result<(), String> {
+    let (from_offset, from_size) = license_section(from)?;
+    let (to_offset, to_size) = license_section(to)?;
+    let section = &from[from_offset..from_offset + from_size];
+    let used = section.iter().rposition(|&b| b != 0).map_or(0, |last| last + 1);
+    if used > to_size {
+        return Err(format!("License takes {} bytes, the new {} section holds {}", used, LICENSE_SECTION, to_size));
+    }
+
+    let target = &mut to[to_offset..to_offset + to_size];
+    target.fill(0);
+    target[..used].copy_from_slice(&section[..used]);
+    Ok(())
+}
+
+/// Whether an executable image carries the license `license_id`, in its own
+/// `.license` section or in that of an image merged into it
+///
+/// The image is streamed in `SCAN_CHUNK` reads. License sections are decoded
+/// (decompressed, decrypted with this build's key) before their license ID
+/// is compared, so any encoding is recognized; decoding is only attempted at
+/// the frame magics and, for server-patched JSON, which has none, at the
+/// objects opening shortly before the quoted license ID.
+pub fn carries_license(mut image: impl Read, license_id: &str) -> std::io::Result<bool> {
+    let key = build_key();
+    let matches = |section: &[u8]| {
+        decode_license(&section[..section.len().min(LICENSE_SIZE)], key.as_ref())
+            .ok()
+            .flatten()
+            .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+            .is_some_and(|config| config["license_id"] == license_id)
+    };
+    let quoted_id = serde_json::to_string(license_id).unwrap_or_default();
+
+    // Bytes before `scanned` were searched and are kept as look-behind for
+    // JSON opening before the license ID
+    let mut window = Vec::with_capacity(SCAN_CHUNK + 2 * LICENSE_SIZE);
+    let mut scanned = 0;
+    let mut chunk = vec![0u8; SCAN_CHUNK];
+    loop {
+        let read = image.read(&mut chunk)?;
+        window.extend_from_slice(&chunk[..read]);
+        // Leave a section's worth of look-ahead for the next round
+        let end = if read == 0 { window.len() } else { window.len().saturating_sub(LICENSE_SIZE) };
+
+        for offset in scanned..end {
+            let slice = &window[offset..];
+            if (slice.starts_with(FRAME_MAGIC) || slice.starts_with(ENCRYPTED_MAGIC)) && matches(slice) {
+                return Ok(true);
+            }
+            if slice.starts_with(quoted_id.as_bytes())
+                && (offset.saturating_sub(LICENSE_SIZE)..offset)
+                    .rev()
+                    .any(|start| window[start..].starts_with(b"{\"") && matches(&window[start..]))
+            {
+                return Ok(true);
+            }
+        }
+        if read == 0 {
+            return Ok(false);
+        }
+
+        let keep_from = end.saturating_sub(LICENSE_SIZE);
+        window.drain(..keep_from);
+        scanned = end - keep_from;
+    }
+}
+
+/// License ID in this executable's `.license` section, even when the config
+/// around it does not load
+pub fn embedded_license_id() -> Option<String> {
+    let image = crate::security::memexec::image_path().ok()?;
+    let data = Zeroizing::new(std::fs::read(image).ok()?);
+    let (offset, size) = license_section(&data).ok()?;
+    let json = decode_license(&data[offset..offset + size.min(LICENSE_SIZE)], build_key().as_ref()).ok()??;
+    let config: serde_json::Value = serde_json::from_str(&json).ok()?;
+    Some(config.get("license_id")?.as_str()?.to_string())
+}
+
+/// File offset and size of the `.license` section, from the object-file
+/// headers
+fn license_section(data: &[u8]) -> Result<(usize, usize), String> {
+    let section = integrity::parse_sections(data)?
+        .into_iter()
+        .find(|s| s.name == LICENSE_SECTION)
+        .ok_or_else(|| format!("No {} section found", LICENSE_SECTION))?;
+    if section.size < FRAME_HEADER_LEN || section.offset + section.size > data.len() {
+        return Err(format!("{} section is too small", LICENSE_SECTION));
+    }
+    Ok((section.offset, section.size))
+}
+
+/// License frame holding `json`
+fn encode_frame(json: &[u8], key: Option<&[u8; 32]>, compress: bool) -> Result<Vec<u8>, String> {
+    let mut flags = 0;
+    let mut payload = json.to_vec();
+    if compress {
+        payload = ruzstd::encoding::compress_to_vec(payload.as_slice(), ruzstd::encoding::CompressionLevel::Fastest);
+        flags |= FLAG_ZSTD;
+    }
+    if let Some(key) = key {
+        payload = encrypt(&payload, key)?;
+        flags |= FLAG_ENCRYPTED;
+    }
+
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+    frame.extend_from_slice(FRAME_MAGIC);
+    frame.extend_from_slice(&[FRAME_VERSION, flags, 0, 0]);
+    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frame.extend_from_slice(&payload);
+    Ok(frame)
+}
+
+/// Config JSON in a frame
+fn decode_frame(frame: &[u8], key: Option<&[u8; 32]>) -> Result<Zeroizing<String>, String> {
+    let header = frame.get(..FRAME_HEADER_LEN).ok_or("Truncated license frame")?;
+    let (version, flags) = (header[4], header[5]);
+    if version != FRAME_VERSION {
+        return Err(format!("Embedded license has format version {}, this build reads {}", version, FRAME_VERSION));
+    }
+    if flags & !(FLAG_ZSTD | FLAG_ENCRYPTED) != 0 {
+        return Err(format!("Embedded license has unknown flags 0x{:02x}", flags));
+    }
+    let len = u32::from_le_bytes([header[8], header[9], header[10], header[11]]) as usize;
+    let payload = frame
+        .get(FRAME_HEADER_LEN..FRAME_HEADER_LEN + len)
+        .ok_or("Truncated license frame")?;
+
+    let mut body = Zeroizing::new(payload.to_vec());
+    if flags & FLAG_ENCRYPTED != 0 {
+        let key = key.ok_or("Embedded license is encrypted but this build has no license key")?;
+        body = decrypt(&body, key)?;
+    }
+    if flags & FLAG_ZSTD != 0 {
+        let decoder = ruzstd::decoding::StreamingDecoder::new(body.as_slice())
+            .map_err(|e| format!("Invalid compressed license: {}", e))?;
+        let mut json = Zeroizing::new(Vec::new());
+        decoder
+            .take(MAX_CONFIG_LEN + 1)
+            .read_to_end(&mut json)
+            .map_err(|e| format!("Invalid compressed license: {}", e))?;
+        if json.len() as u64 > MAX_CONFIG_LEN {
+            return Err("Compressed license expands beyond the config size limit".to_string());
+        }
+        body = json;
+    }
+    String::from_utf8(body.to_vec())
+        .map(Zeroizing::new)
+        .map_err(|e| format!("Invalid UTF-8 in embedded license data: {}", e))
+}
+
+/// Nonce followed by the ChaCha20-Poly1305 ciphertext
+fn encrypt(plaintext: &[u8], key: &[u8; 32]) -> Result<Vec<u8>, String> {
+    let nonce: [u8; NONCE_LEN] = rand::random();
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| "Failed to encrypt config".to_string())?;
+    Ok([nonce.as_slice(), &ciphertext].concat())
+}
+
+fn decrypt(sealed: &[u8], key: &[u8; 32]) -> Result<Zeroizing<Vec<u8>>, String> {
+    if sealed.len() < NONCE_LEN {
+        return Err("Truncated encrypted license".to_string());
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map(Zeroizing::new)
+        .map_err(|_| "Embedded license cannot be decrypted with this build's key".to_string())
+}
+
+/// Config JSON stored in a license section (None if the section is empty)
+fn decode_license(section: &[u8], key: Option<&[u8; 32]>) -> Result<Option<Zeroizing<String>>, String> {
+    if section.starts_with(FRAME_MAGIC) {
+        return decode_frame(section, key).map(Some);
+    }
+
+    if let Some(header) = section.strip_prefix(ENCRYPTED_MAGIC.as_slice()) {
+        let key = key.ok_or("Embedded license is encrypted but this build has no license key")?;
+        let len = header
+            .get(..4)
+            .map(|len| u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize)
+            .ok_or("Truncated encrypted license")?;
+        let sealed = header
+            .get(4..4 + NONCE_LEN + len)
+            .ok_or("Truncated encrypted license")?;
+        let plaintext = decrypt(sealed, key)?;
+        return String::from_utf8(plaintext.to_vec())
+            .map(|json| Some(Zeroizing::new(json)))
+            .map_err(|e| format!("Invalid UTF-8 in embedded license data: {}", e));
+    }
+
+    let len = section.iter().position(|&b| b == 0).unwrap_or(section.len());
+    if len == 0 {
+        return Ok(None);
+    }
+    std::str::from_utf8(&section[..len])
+        .map(|json| Some(Zeroizing::new(json.to_string())))
+        .map_err(|e| format!("Invalid UTF-8 in embedded license data: {}", e))
+}
 
 /// Read configuration from embedded .license section
 /// The license data is injected into the binary by the server
 /// at a fixed offset in the .license section
 pub fn load_embedded_config() -> Result<Config, String> {
-    eprintln!("📦 Loading embedded config...");
+    log_debug!("📦 Loading embedded config...");
     
     // The .license section is embedded in the binary at compile time
     // The server patches it with actual license data
@@ -18,32 +338,25 @@ pub fn load_embedded_config() -> Result<Config, String> {
     #[used]
     #[cfg_attr(target_os = "macos", unsafe(link_section = "__DATA,.license"))]
     #[cfg_attr(not(target_os = "macos"), unsafe(link_section = ".license"))]
-    static LICENSE_DATA: [u8; 4096] = [0; 4096];
+    static LICENSE_DATA: [u8; LICENSE_SIZE] = [0; LICENSE_SIZE];
     
-    // Try to read from the static first (works when binary runs directly)
-    let config_bytes = &LICENSE_DATA;
-    let config_len = config_bytes.iter()
-        .position(|&b| b == 0)
-        .unwrap_or(config_bytes.len());
+    // Try to read from the static first (works when binary runs directly).
+    // Volatile read: the section is patched after linking
+    let config_bytes = Zeroizing::new(unsafe { std::ptr::read_volatile(&LICENSE_DATA) });
     
-    eprintln!("📦 LICENSE_DATA static: first_byte=0x{:02x}, config_len={}", config_bytes[0], config_len);
+    log_debug!("📦 LICENSE_DATA static: first_byte=0x{:02x}", config_bytes[0]);
     
     // If static has data, use it
-    if config_len > 0 {
-        eprintln!("📦 Static LICENSE_DATA has {} bytes of data", config_len);
-        let config_str = std::str::from_utf8(&config_bytes[..config_len])
-            .map_err(|e| format!("Invalid UTF-8 in embedded license data: {}", e))?;
-        
-        eprintln!("📦 Config string preview: {}...", &config_str[..std::cmp::min(50, config_str.len())]);
-        
-        let config: Config = serde_json::from_str(config_str)
+    if let Some(config_str) = decode_license(config_bytes.as_slice(), build_key().as_ref())? {
+        log_debug!("📦 Static LICENSE_DATA has {} bytes of config", config_str.len());
+        let config: Config = serde_json::from_str(&config_str)
             .map_err(|e| format!("Failed to parse embedded config: {}", e))?;
         
         config.validate()?;
         return Ok(config);
     }
     
-    eprintln!("📦 Static LICENSE_DATA is empty, trying to read from executable file...");
+    log_debug!("📦 Static LICENSE_DATA is empty, trying to read from executable file...");
     
     // If static is empty, try reading from our own executable file.
     // This handles the case where we're running from memfd after extraction.
@@ -63,9 +376,9 @@ pub fn load_embedded_config() -> Result<Config, String> {
 
     #[cfg(target_os = "linux")]
     {
-        eprintln!("📦 Linux: Trying /proc/self/exe...");
-        if let Ok(exe_data) = std::fs::read("/proc/self/exe") {
-            eprintln!("📦 Read {} bytes from /proc/self/exe", exe_data.len());
+        log_debug!("📦 Linux: Trying /proc/self/exe...");
+        if let Ok(exe_data) = std::fs::read("/proc/self/exe").map(Zeroizing::new) {
+            log_debug!("📦 Read {} bytes from /proc/self/exe", exe_data.len());
             if let Ok(config) = find_config_in_bytes(&exe_data) {
                 return Ok(config);
             }
@@ -76,50 +389,89 @@ pub fn load_embedded_config() -> Result<Config, String> {
     let current_exe = std::env::current_exe()
         .map_err(|e| format!("Failed to get current executable path: {}", e))?;
     
-    eprintln!("📦 current_exe() = {}", current_exe.display());
+    log_debug!("📦 current_exe() = {}", current_exe.display());
         
+    // Wiped on return: the buffer contains the license JSON
     let exe_data = std::fs::read(&current_exe)
+        .map(Zeroizing::new)
         .map_err(|e| format!("Failed to read executable from {}: {}", current_exe.display(), e))?;
     
-    eprintln!("📦 Read {} bytes from executable", exe_data.len());
+    log_debug!("📦 Read {} bytes from executable", exe_data.len());
     
     find_config_in_bytes(&exe_data)
 }
 
+/// Error for a binary whose license section was never written
+const NOT_PATCHED: &str = "No license data embedded in binary. This binary has not been patched by the server.";
+
+/// Config embedded in an executable image
+///
+/// The section headers locate the `.license` section and exactly that section
+/// is read: an empty one means the binary was not patched. Only an image the
+/// parser does not understand is scanned for the config.
 fn find_config_in_bytes(data: &[u8]) -> Result<Config, String> {
-    eprintln!("📦 Searching for license JSON in {} bytes of data...", data.len());
-    // Search for .license section
-    // Simple search: find 4KB block with JSON data
-    const LICENSE_SIZE: usize = 4096;
-    
+    let key = build_key();
+
+    match license_section(data) {
+        Ok((offset, size)) => {
+            log_debug!("📦 {} section at offset 0x{:x} ({} bytes)", LICENSE_SECTION, offset, size);
+            let config_str = decode_license(&data[offset..offset + size], key.as_ref())?
+                .ok_or_else(|| NOT_PATCHED.to_string())?;
+            let config: Config = serde_json::from_str(&config_str)
+                .map_err(|e| format!("Failed to parse embedded config: {}", e))?;
+            config.validate()?;
+            Ok(config)
+        }
+        Err(e) => {
+            log_warn!("⚠️  Cannot locate the {} section ({}), scanning the executable", LICENSE_SECTION, e);
+            scan_for_config(data, key.as_ref())
+        }
+    }
+}
+
+/// Last resort: search the whole image for a block holding a valid config
+fn scan_for_config(data: &[u8], key: Option<&[u8; 32]>) -> Result<Config, String> {
+    log_debug!("📦 Searching for license JSON in {} bytes of data...", data.len());
     let mut json_starts_found = 0;
     
     // Optimization: The license is likely aligned to 4 bytes
-    for offset in (0..data.len().saturating_sub(LICENSE_SIZE)).step_by(4) {
-        let slice = &data[offset..offset + LICENSE_SIZE];
+    for offset in (0..data.len().saturating_sub(FRAME_HEADER_LEN)).step_by(4) {
+        let slice = &data[offset..(offset + LICENSE_SIZE).min(data.len())];
+        
+        // Framed or encrypted license (see `embed_config`)
+        if slice.starts_with(FRAME_MAGIC) || slice.starts_with(ENCRYPTED_MAGIC) {
+            if let Ok(Some(config_str)) = decode_license(slice, key)
+                && let Ok(config) = serde_json::from_str::<Config>(&config_str)
+                && config.validate().is_ok()
+            {
+                log_info!("✅ Found encrypted license at offset 0x{:x} in executable", offset);
+                return Ok(config);
+            }
+            continue;
+        }
         
         // Check if this looks like our license section (starts with '{')
         if slice[0] == b'{' {
             json_starts_found += 1;
-            let json_len = slice.iter().position(|&b| b == 0).unwrap_or(LICENSE_SIZE);
+            let json_len = slice.iter().position(|&b| b == 0).unwrap_or(slice.len());
             if json_len > 10 {  // Minimum viable JSON
                 if let Ok(config_str) = std::str::from_utf8(&slice[..json_len]) {
                     if config_str.contains("license_id") {
-                        eprintln!("📦 Found potential license JSON at offset 0x{:x}, len={}", offset, json_len);
+                        log_debug!("📦 Found potential license JSON at offset 0x{:x}, len={}", offset, json_len);
                     }
-                    if let Ok(config) = serde_json::from_str::<Config>(config_str) {
-                        if config.validate().is_ok() {
-                            eprintln!("✅ Found license at offset 0x{:x} in executable", offset);
-                            return Ok(config);
-                        }
+                    if let Ok(config) = serde_json::from_str::<Config>(config_str)
+                        && config.validate().is_ok()
+                    {
+                        log_info!("✅ Found license at offset 0x{:x} in executable", offset);
+                        return Ok(config);
                     }
                 }
             }
         }
     }
     
-    eprintln!("📦 Searched entire binary, found {} JSON-like starts, no valid license", json_starts_found);
-    Err("No license data embedded in binary. This binary has not been patched by the server.".to_string())
+    log_debug!("📦 Searched entire binary, found {} JSON-like starts, no valid license", json_starts_found);
+    Err(NOT_PATCHED.to_string())
 }
 
 #[cfg(test)]
@@ -142,4 +494,113 @@ mod tests {
         assert_eq!(config.license_id, "lic_test");
         assert_eq!(config.check_interval_ms, 5000);
     }
+
+    #[test]
+    fn test_encrypted_license_roundtrip() {
+        let json = r#"{"license_id":"lic_enc","server_url":"https://x.example.com","shared_secret":"s"}"#;
+        let key = [7u8; 32];
+        // Layout written before frames: magic, ciphertext length, nonce, ciphertext
+        let sealed = encrypt(json.as_bytes(), &key).unwrap();
+        let mut section = ENCRYPTED_MAGIC.to_vec();
+        section.extend_from_slice(&((sealed.len() - NONCE_LEN) as u32).to_le_bytes());
+        section.extend_from_slice(&sealed);
+        section.resize(LICENSE_SIZE, 0);
+
+        assert_eq!(decode_license(&section, Some(&key)).unwrap().as_deref().map(String::as_str), Some(json));
+        assert!(decode_license(&section, Some(&[8u8; 32])).is_err());
+        assert!(decode_license(&section, None).is_err());
+
+        // Plain JSON is NUL-terminated; an untouched section is empty
+        let mut plain = json.as_bytes().to_vec();
+        plain.resize(LICENSE_SIZE, 0);
+        assert_eq!(decode_license(&plain, None).unwrap().as_deref().map(String::as_str), Some(json));
+        assert!(decode_license(&[0u8; LICENSE_SIZE], None).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_embed_into_executable() {
+        // The test binary links LICENSE_DATA, so it has a real .license section
+        let mut image = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        let json = r#"{ "license_id": "lic_embed", "server_url": "https://x.example.com", "shared_secret": "s" }"#;
+
+        let (offset, _) = embed_config(&mut image, json, None, false).unwrap();
+        let stored = decode_license(&image[offset..offset + LICENSE_SIZE], None).unwrap().unwrap();
+        assert_eq!(stored.as_str(), r#"{"license_id":"lic_embed","server_url":"https://x.example.com","shared_secret":"s"}"#);
+        assert_eq!(find_config_in_bytes(&image).unwrap().license_id, "lic_embed");
+
+        // Invalid configs never reach the binary
+        assert!(embed_config(&mut image, r#"{"license_id":""}"#, None, false).is_err());
+    }
+
+    #[test]
+    fn test_compressed_frames() {
+        // Compression is what makes a large config fit
+        let paths: Vec<String> = (0..800).map(|i| format!("/opt/vendor/plugins/{:04}/lib/", i)).collect();
+        let json = serde_json::json!({
+            "license_id": "lic_big",
+            "server_url": "https://x.example.com",
+            "shared_secret": "s",
+            "trusted_library_paths": paths,
+        })
+        .to_string();
+        assert!(json.len() > LICENSE_SIZE);
+
+        let key = [7u8; 32];
+        let frame = encode_frame(json.as_bytes(), Some(&key), true).unwrap();
+        assert!(frame.len() < LICENSE_SIZE);
+        assert_eq!(frame[5], FLAG_ZSTD | FLAG_ENCRYPTED);
+        assert_eq!(decode_license(&frame, Some(&key)).unwrap().unwrap().as_str(), json);
+        assert!(decode_license(&frame, None).is_err());
+
+        let mut image = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        let (offset, size) = embed_config(&mut image, &json, None, true).unwrap();
+        assert!(size < LICENSE_SIZE);
+        assert_eq!(decode_license(&image[offset..offset + size], None).unwrap().unwrap().as_str(), json);
+        assert!(embed_config(&mut image, &json, None, false).unwrap_err().contains("try compressing"));
+
+        // Updates keep the encoding
+        update_config(&mut image, |config| config["shared_secret"] = "s2".into()).unwrap();
+        assert_eq!(image[offset + 5], FLAG_ZSTD);
+        assert!(decode_license(&image[offset..], None).unwrap().unwrap().contains(r#""shared_secret":"s2""#));
+
+        // Frames from a newer format are refused, not misread
+        let mut future = encode_frame(b"{}", None, false).unwrap();
+        future[4] = FRAME_VERSION + 1;
+        assert!(decode_license(&future, None).unwrap_err().contains("format version"));
+    }
+
+    #[test]
+    fn test_carries_license_in_any_encoding() {
+        // Built at runtime: a config literal in the test binary would be found
+        // by the scan in `test_section_is_authoritative`
+        let json = serde_json::json!({"license_id": "lic_merged", "server_url": "https://x.example.com", "shared_secret": "s"})
+            .to_string();
+        for frame in [encode_frame(json.as_bytes(), None, true).unwrap(), json.as_bytes().to_vec()] {
+            // Overload image merged at an unaligned offset, and across a read
+            for loader_len in [15, SCAN_CHUNK - 3] {
+                let mut merged = b"\x7fELF loader ...".repeat(loader_len.div_ceil(15));
+                merged.truncate(loader_len);
+                merged.extend_from_slice(&frame);
+                merged.extend_from_slice(&[0; 16]);
+                assert!(carries_license(merged.as_slice(), "lic_merged").unwrap());
+                assert!(!carries_license(merged.as_slice(), "lic_other").unwrap());
+            }
+        }
+        // The ID alone, outside a license section, is not a license
+        assert!(!carries_license(br#""lic_merged""#.as_slice(), "lic_merged").unwrap());
+    }
+
+    #[test]
+    fn test_section_is_authoritative() {
+        // A valid config outside the section is ignored while the headers
+        // parse, and only found by the scan once they do not
+        let mut image = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        image.resize(image.len().next_multiple_of(4), 0);
+        image.extend_from_slice(br#"{"license_id":"lic_decoy","server_url":"https://x.example.com","shared_secret":"s"}"#);
+        image.resize(image.len() + LICENSE_SIZE, 0);
+
+        assert_eq!(find_config_in_bytes(&image).unwrap_err(), NOT_PATCHED);
+        image[..4].copy_from_slice(b"\0\0\0\0");
+        assert_eq!(find_config_in_bytes(&image).unwrap().license_id, "lic_decoy");
+    }
 }
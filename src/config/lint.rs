@@ -0,0 +1,164 @@
+//! Config lint: valid but risky settings
+//!
+//! `Config::validate` rejects configs that cannot work; this pass flags ones
+//! that work but will probably hurt in production. Warnings are logged at
+//! startup and by `killer check-config`, and new ones are reported as
+//! `config_lint` security events.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use super::schema::{Config, KillMethod};
+use crate::utils::platform;
+use crate::utils::state::StateStore;
+use crate::verification::events;
+use crate::verification::ratelimit::MIN_CHECK_INTERVAL_MS;
+
+/// One lint finding
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LintWarning {
+    /// Stable identifier, e.g. "shred_on_network_fs"
+    pub code: &'static str,
+    pub message: String,
+}
+
+/// Lint a config for this build and machine
+pub fn lint(config: &Config) -> Vec<LintWarning> {
+    let target = config
+        .base_binary_path
+        .as_ref()
+        .map(PathBuf::from)
+        .or_else(|| crate::security::memexec::executable_path().ok());
+    lint_with(config, !cfg!(debug_assertions), target.as_deref(), platform::is_network_filesystem)
+}
+
+/// Log lint warnings and queue an event for each one not reported before
+pub fn report(config: &Config) {
+    let warnings = lint(config);
+    for warning in &warnings {
+        log_warn!("⚠️  Config lint [{}]: {}", warning.code, warning.message);
+    }
+
+    let store = StateStore::for_license(&config.license_id);
+    let mut state = store.load();
+    let codes: Vec<String> = warnings.iter().map(|w| w.code.to_string()).collect();
+    if state.reported_lints == codes {
+        return;
+    }
+    for warning in warnings.iter().filter(|w| !state.reported_lints.iter().any(|code| code == w.code)) {
+        events::record("config_lint", &format!("{}: {}", warning.code, warning.message));
+    }
+    state.reported_lints = codes;
+    if let Err(e) = store.save(&state) {
+        log_warn!("⚠️  {}", e);
+    }
+}
+
+fn lint_with(
+    config: &Config,
+    release_build: bool,
+    shred_target: Option<&Path>,
+    on_network_fs: impl Fn(&Path) -> bool,
+) -> Vec<LintWarning> {
+    let mut warnings = Vec::new();
+    let mut warn = |code, message: String| warnings.push(LintWarning { code, message });
+
+    let shreds = config.kill_method == KillMethod::Shred || config.corrupt_then_shred;
+    if shreds
+        && let Some(target) = shred_target.filter(|path| on_network_fs(path))
+    {
+        warn(
+            "shred_on_network_fs",
+            format!("{} is on a network filesystem: overwrites are not guaranteed to reach the disk - use \"delete\" or \"corrupt\"", target.display()),
+        );
+    }
+
+    if config.check_interval_ms > 0 && config.check_interval_ms < MIN_CHECK_INTERVAL_MS {
+        warn(
+            "check_interval_too_short",
+            format!("check_interval_ms = {} would send a license request several times a second - it is raised to {}", config.check_interval_ms, MIN_CHECK_INTERVAL_MS),
+        );
+    }
+
+    if config.check_interval_ms == 0 && !config.cli_mode && config.fallback_server_url.is_none() && config.kill_grace_ms == 0 {
+        warn(
+            "fail_closed_without_grace",
+            "startup is fail-closed (check_interval_ms = 0) with no fallback_server_url and no kill_grace_ms: any server outage blocks every launch".to_string(),
+        );
+    }
+
+    if config.daemonize && config.check_interval_ms == 0 {
+        warn(
+            "daemonize_single_check",
+            "daemonize with check_interval_ms = 0: a single check never detaches".to_string(),
+        );
+    }
+
+    if config.seat_lease && config.check_interval_ms == 0 {
+        warn(
+            "seat_lease_without_renewal",
+            "seat_lease with check_interval_ms = 0: the lease is never renewed and expires on the server while the app runs".to_string(),
+        );
+    }
+
+    // event_log_url, revocation_url and policy_bundle_url are https by validation
+    let url = config.get_server_url();
+    if release_build && url.starts_with("http://") {
+        warn(
+            "insecure_url",
+            format!("{} is plain http in a release build: traffic can be read and delayed by anyone on the path", url),
+        );
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(json: &str) -> Config {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn codes(warnings: &[LintWarning]) -> Vec<&'static str> {
+        warnings.iter().map(|w| w.code).collect()
+    }
+
+    #[test]
+    fn test_lint_flags_risky_combinations() {
+        let risky = config(r#"{
+            "license_id": "lic_lint",
+            "server_url": "http://license.example.com",
+            "shared_secret": "secret",
+            "kill_method": "shred",
+            "check_interval_ms": 100
+        }"#);
+        let warnings = lint_with(&risky, true, Some(Path::new("/mnt/share/app")), |_| true);
+        assert_eq!(codes(&warnings), ["shred_on_network_fs", "check_interval_too_short", "insecure_url"]);
+
+        // Debug builds may talk plain http to a local test server
+        let warnings = lint_with(&risky, false, Some(Path::new("/mnt/share/app")), |_| false);
+        assert_eq!(codes(&warnings), ["check_interval_too_short"]);
+    }
+
+    #[test]
+    fn test_lint_fail_closed_startup() {
+        let mut sync = config(r#"{
+            "license_id": "lic_lint",
+            "server_url": "https://license.example.com",
+            "shared_secret": "secret"
+        }"#);
+        assert_eq!(codes(&lint_with(&sync, true, None, |_| false)), ["fail_closed_without_grace"]);
+
+        sync.kill_grace_ms = 30_000;
+        assert!(lint_with(&sync, true, None, |_| false).is_empty());
+        sync.kill_grace_ms = 0;
+
+        sync.fallback_server_url = Some("https://fallback.example.com".to_string());
+        assert!(lint_with(&sync, true, None, |_| false).is_empty());
+
+        sync.seat_lease = true;
+        assert_eq!(codes(&lint_with(&sync, true, None, |_| false)), ["seat_lease_without_renewal"]);
+    }
+}
@@ -1,19 +1,175 @@
-/// Configuration loader
+//! Configuration loader
+//!
+//! The effective configuration is layered, each layer overriding the fields
+//! of the ones before it:
+//! 1. the config embedded in the binary's `.license` section
+//! 2. the external `<executable>.config` file
+//! 3. `KILLER_<FIELD>` environment variables (e.g. `KILLER_CHECK_INTERVAL_MS`,
+//!    `KILLER_LOG_LEVEL`, `KILLER_SERVER_URL`)
+//!
+//! The first layer present is the base. Layers after it are overrides, which
+//! are only read if the layers before them set `"allow_overrides": true`
+//! (default false), and which may only change the fields in `OVERRIDABLE`
+//! (logging, the check interval and the server URL): the end user controls
+//! both the file and the environment, so secret, kill method and the other
+//! enforcement settings stay as shipped. A redirected server cannot
+//! authorize without the shared secret, which it does not have.
+//! Overrides cannot set `allow_overrides` themselves. A server
+//! URL compiled in (`KILLER_SERVER_URL` at build time) still wins over all
+//! layers, and a shared secret the server has rotated away from is replaced
+//! by its successor (see `verification::rotation`). Check intervals below the
+//! floor are raised to it (see `verification::ratelimit`).
+
+use super::embedded::load_embedded_config;
 use super::schema::Config;
-use std::fs;
+use serde_json::{Map, Value};
+use std::path::Path;
+
+use crate::utils::secure_fs;
+
+/// Prefix of configuration override variables
+pub const ENV_PREFIX: &str = "KILLER_";
+
+/// Fields an override layer may change; everything else, security settings
+/// above all, comes from the base layer only
+const OVERRIDABLE: [&str; 4] = ["log_level", "log_format", "check_interval_ms", "server_url"];
+
+/// Build-time variables sharing the prefix (read by `option_env!`, never
+/// config fields)
+const BUILD_VARS: [&str; 2] = ["KILLER_LICENSE_KEY", "KILLER_TRUST_ROOT_KEY"];
+
+/// Load the effective configuration: embedded < external .config <
+/// `KILLER_*` environment variables
+pub fn load() -> Result<Config, String> {
+    let mut config = load_layers()?;
+    crate::verification::rotation::apply_persisted(&mut config);
+    config.check_interval_ms = crate::verification::ratelimit::clamp_interval(config.check_interval_ms, "the configuration");
+    Ok(config)
+}
+
+fn load_layers() -> Result<Config, String> {
+    let embedded = match load_embedded_config() {
+        Ok(config) => {
+            log_info!("✅ Using embedded license configuration");
+            Some(config)
+        }
+        Err(e) => {
+            log_info!("ℹ️  No embedded license ({}), trying .config file...", e);
+            None
+        }
+    };
+
+    let exe_path = crate::security::memexec::executable_path().map_err(|e| format!("Failed to get executable path: {}", e))?;
+    let config_path = std::path::PathBuf::from(format!("{}.config", exe_path.display()));
+    let mut layers = Map::new();
+    if let Some(embedded) = embedded {
+        layers = to_object(&embedded)?;
+        if !allows_overrides(&layers) {
+            return Ok(embedded);
+        }
+    }
+    if read_file_layer(&layers, &config_path) {
+        let contents = secure_fs::read_config(&config_path)?;
+        let file: Value = serde_json::from_str(&contents).map_err(|e| format!("Failed to parse config: {}", e))?;
+        let Value::Object(file) = file else {
+            return Err("Failed to parse config: not a JSON object".to_string());
+        };
+        if layers.is_empty() {
+            layers = file;
+        } else {
+            log_info!("📄 {} overrides the embedded configuration", config_path.display());
+            merge(&mut layers, file);
+        }
+    }
+
+    if allows_overrides(&layers) {
+        for name in apply_env(&mut layers, std::env::vars()) {
+            log_info!("🌱 {} overrides the configuration", name);
+        }
+    }
+
+    let config: Config = serde_json::from_value(Value::Object(layers)).map_err(|e| format!("Failed to parse config: {}", e))?;
+    config.validate()?;
+    Ok(config)
+}
+
+/// Whether to read the .config file: always without an embedded config (a
+/// missing file is then the error), otherwise only if there is one
+fn read_file_layer(layers: &Map<String, Value>, config_path: &Path) -> bool {
+    layers.is_empty() || config_path.exists()
+}
+
+fn to_object(config: &Config) -> Result<Map<String, Value>, String> {
+    match serde_json::to_value(config) {
+        Ok(Value::Object(object)) => Ok(object),
+        Ok(_) => Err("config does not serialize to an object".to_string()),
+        Err(e) => Err(format!("Failed to serialize config: {}", e)),
+    }
+}
+
+fn allows_overrides(layers: &Map<String, Value>) -> bool {
+    layers.get("allow_overrides").and_then(Value::as_bool).unwrap_or(false)
+}
+
+/// Override the `OVERRIDABLE` top-level fields of `base` with those of `layer`
+fn merge(base: &mut Map<String, Value>, layer: Map<String, Value>) {
+    for (key, value) in layer {
+        if !OVERRIDABLE.contains(&key.as_str()) {
+            log_warn!("⚠️  Ignoring override of {}: only {} can be overridden", key, OVERRIDABLE.join(", "));
+            continue;
+        }
+        base.insert(key, value);
+    }
+}
+
+/// Apply `KILLER_<FIELD>` variables to `layers`
+///
+/// Values are JSON (numbers, booleans, lists) unless the field currently
+/// holds a string or the value is not valid JSON, in which case they are
+/// taken as strings.
+///
+/// # Returns
+/// Names of the variables applied
+fn apply_env(layers: &mut Map<String, Value>, vars: impl Iterator<Item = (String, String)>) -> Vec<String> {
+    let mut applied = Vec::new();
+    for (name, raw) in vars {
+        let Some(field) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        let field = field.to_ascii_lowercase();
+        if BUILD_VARS.contains(&name.as_str()) {
+            continue;
+        }
+        if !OVERRIDABLE.contains(&field.as_str()) {
+            log_warn!("⚠️  Ignoring {}: only {} can be overridden", name, OVERRIDABLE.join(", "));
+            continue;
+        }
+        let value = match layers.get(&field) {
+            Some(Value::String(_)) => Value::String(raw),
+            _ => serde_json::from_str(&raw).unwrap_or(Value::String(raw)),
+        };
+        layers.insert(field, value);
+        applied.push(name);
+    }
+    applied.sort();
+    applied
+}
 
 /// Load configuration from adjacent .config file
 /// Config file should be in the same directory as the executable
 /// Named: <executable>.config (e.g., "myapp.config")
 pub fn load_config() -> Result<Config, String> {
-    let exe_path = std::env::current_exe()
+    let exe_path = crate::security::memexec::executable_path()
         .map_err(|e| format!("Failed to get executable path: {}", e))?;
 
-    let config_path = format!("{}.config", exe_path.display());
+    load_config_from(Path::new(&format!("{}.config", exe_path.display())))
+}
 
-    // Read config file
-    let config_content = fs::read_to_string(&config_path)
-        .map_err(|e| format!("Failed to read config file {}: {}", config_path, e))?;
+/// Load and validate a configuration file at an explicit path
+pub fn load_config_from(config_path: &Path) -> Result<Config, String> {
+    // Read config file (wiped after parsing: it holds the shared secret);
+    // symlinked, foreign-owned or world-writable files are refused
+    let config_content = secure_fs::read_config(config_path)?;
 
     // Parse JSON config
     let config: Config = serde_json::from_str(&config_content)
@@ -28,8 +184,6 @@ pub fn load_config() -> Result<Config, String> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::io::Write;
-    use tempfile::NamedTempFile;
     
     #[test]
     fn test_load_valid_config() {
@@ -44,6 +198,43 @@ mod tests {
         assert_eq!(config.license_id, "lic_test");
     }
     
+    #[test]
+    fn test_layers_and_env_overrides() {
+        let mut layers = to_object(&serde_json::from_str::<Config>(r#"{
+            "license_id": "lic_embedded",
+            "server_url": "https://a.example",
+            "shared_secret": "secret123",
+            "check_interval_ms": 60000
+        }"#).unwrap()).unwrap();
+        // Embedded configs are final unless they opt in
+        assert!(!allows_overrides(&layers));
+        layers.insert("allow_overrides".to_string(), Value::Bool(true));
+
+        // Security fields never come from an override layer
+        let file = serde_json::json!({"kill_method": "stop", "allow_overrides": false, "log_level": "debug"});
+        merge(&mut layers, file.as_object().unwrap().clone());
+        assert_eq!(layers["kill_method"], "shred");
+        assert_eq!(layers["log_level"], "debug");
+        assert!(allows_overrides(&layers));
+
+        let vars = [
+            ("KILLER_LOG_FORMAT", "json"),
+            ("KILLER_CHECK_INTERVAL_MS", "5000"),
+            ("KILLER_SERVER_URL", "https://b.example"),
+            ("KILLER_SHARED_SECRET", "mine"),
+            ("KILLER_KILL_METHOD", "stop"),
+            ("KILLER_ALLOW_OVERRIDES", "true"),
+            ("KILLER_LICENSE_KEY", "00"),
+            ("PATH", "/usr/bin"),
+        ];
+        let applied = apply_env(&mut layers, vars.iter().map(|(k, v)| (k.to_string(), v.to_string())));
+        assert_eq!(applied, ["KILLER_CHECK_INTERVAL_MS", "KILLER_LOG_FORMAT", "KILLER_SERVER_URL"]);
+        let config: Config = serde_json::from_value(Value::Object(layers)).unwrap();
+        assert_eq!(config.check_interval_ms, 5000);
+        assert_eq!(config.server_url, "https://b.example");
+        assert_eq!(config.shared_secret.expose(), "secret123");
+    }
+    
     #[test]
     fn test_invalid_json() {
         let json = r#"{ invalid json }"#;
@@ -2,7 +2,10 @@
 pub mod schema;
 pub mod loader;
 pub mod embedded;
+pub mod snapshot;
+pub mod lint;
 
-pub use schema::{Config, KillMethod};
-pub use loader::load_config;
+pub use schema::{ActivationMode, Config, DebuggerAction, EarlyExitPolicy, KillMethod, LicenseEntry, LogFormat, PanicAction, ResourceLimits, ServiceConfig, ShredPattern};
+pub use loader::{load, load_config, load_config_from};
 pub use embedded::load_embedded_config;
+pub use lint::{lint, LintWarning};
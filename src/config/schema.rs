@@ -1,5 +1,6 @@
 /// Configuration schema for overload binary
 use serde::{Deserialize, Serialize};
+use crate::security::secrets::SecretString;
 
 /// Main configuration structure
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -10,8 +11,19 @@ pub struct Config {
     /// Server URL for verification
     pub server_url: String,
     
-    /// HMAC shared secret
-    pub shared_secret: String,
+    /// HMAC shared secret (locked in memory, wiped on drop)
+    pub shared_secret: SecretString,
+    
+    /// Further licenses, tried in order after the one above until one
+    /// authorizes; see `verification::licenses`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub licenses: Vec<LicenseEntry>,
+    
+    /// true: an external .config (over an embedded config) and KILLER_*
+    /// environment variables may change logging, the check interval and the
+    /// server URL; false (default): this config is final. See `config::loader`
+    #[serde(default)]
+    pub allow_overrides: bool,
     
     /// Interval to re-check license (milliseconds)
     /// 0 = check once and exit
@@ -23,20 +35,405 @@ pub struct Config {
     #[serde(default = "default_true")]
     pub self_destruct: bool,
     
-    /// Kill method for unauthorized access: "stop", "delete", or "shred"
+    /// Kill method for unauthorized access: "stop", "delete", "shred", "corrupt"
+    /// or "lock"
     /// - stop: Just terminate the process (SIGTERM/SIGKILL)
     /// - delete: Terminate and delete binary (rm)
-    /// - shred: Terminate and securely delete (3-pass overwrite + rm)
+    /// - shred: Terminate and securely delete (shred_passes overwrite + rm)
+    /// - corrupt: Terminate and overwrite header + entry point (instant)
+    /// - lock: Terminate and deny execution (chmod 000 / deny-execute ACL),
+    ///   reversible with `killer unlock`
     #[serde(default = "default_kill_method")]
     pub kill_method: KillMethod,
     
-    /// Log level: "debug", "info", "error", "none"
+    /// Treat an attached debugger as unauthorized access
+    #[serde(default)]
+    pub anti_debug: bool,
+    
+    /// Treat an injected library (LD_PRELOAD, unexpected loaded modules,
+    /// AppInit DLLs, Detours) as unauthorized access; see
+    /// `security::antitamper`
+    #[serde(default)]
+    pub anti_injection: bool,
+    
+    /// Path prefixes of libraries anti_injection accepts anyway (endpoint
+    /// security agents, vendor plugins)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trusted_library_paths: Vec<String>,
+    
+    /// Before each verification, check the hosts file and compare the system
+    /// resolver with an independent one for the server host; redirection is
+    /// treated as tampering. See `security::redirection`
+    #[serde(default)]
+    pub detect_redirection: bool,
+    
+    /// DNS server (host:port) for detect_redirection's independent lookup
+    /// (default 1.1.1.1:53)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirection_resolver: Option<String>,
+    
+    /// Log level: "debug", "info", "warn", "error", "none" (no output at all)
     #[serde(default = "default_log_level")]
     pub log_level: String,
     
+    /// Log line format: "text" (default) or "json" (one object per line)
+    #[serde(default)]
+    pub log_format: LogFormat,
+    
+    /// Append log lines to this file instead of stderr
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_file: Option<String>,
+    
+    /// Mask signatures, secrets, license IDs and URL queries in logs;
+    /// turning it off only takes effect with log_level "debug"
+    #[serde(default = "default_true")]
+    pub log_redaction: bool,
+    
     /// Path to base binary (for merged binaries)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub base_binary_path: Option<String>,
+    
+    /// What to do with the in-flight verification when the base binary exits
+    /// before it completes (async mode): "complete" or "cancel"
+    #[serde(default)]
+    pub early_exit_policy: EarlyExitPolicy,
+    
+    /// Short-lived CLI mode: cached token + usage ping instead of a network
+    /// verification on every invocation
+    #[serde(default)]
+    pub cli_mode: bool,
+    
+    /// CLI mode: force a full verification every N invocations (0 = only on expiry)
+    #[serde(default = "default_cli_full_check_every")]
+    pub cli_full_check_every: u32,
+    
+    /// CLI mode: maximum lifetime of the cached token (seconds)
+    #[serde(default = "default_cli_token_ttl_secs")]
+    pub cli_token_ttl_secs: u64,
+    
+    /// Maximum tolerated difference between local and signed server time
+    /// (seconds, 0 = disabled); larger drift is treated as clock tampering
+    #[serde(default = "default_max_clock_drift_secs")]
+    pub max_clock_drift_secs: u64,
+    
+    /// Write an encrypted recovery escrow stub before delete/shred
+    #[serde(default)]
+    pub escrow_on_destroy: bool,
+    
+    /// Power-aware scheduling: lengthen check intervals on battery or while
+    /// the session is idle/locked, and re-check immediately after a suspend
+    #[serde(default)]
+    pub power_aware: bool,
+    
+    /// Power-aware scheduling: interval multiplier in power-saving conditions
+    #[serde(default = "default_power_save_multiplier")]
+    pub power_save_multiplier: u32,
+    
+    /// CPU budget for periodic security checks, in percent of wall time
+    #[serde(default = "default_security_cpu_budget_pct")]
+    pub security_cpu_budget_pct: f64,
+    
+    /// Break-glass verification endpoint, only used after the primary failed
+    /// `fallback_after_failures` consecutive times; its responses must carry a
+    /// nonce-bound signature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_server_url: Option<String>,
+    
+    /// Consecutive primary failures before the fallback endpoint is consulted
+    #[serde(default = "default_fallback_after_failures")]
+    pub fallback_after_failures: u32,
+    
+    /// Overwrite passes for shred/secure deletion (default 3)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shred_passes: Option<u32>,
+    
+    /// Overwrite pattern for shred/secure deletion: "zero", "random", "dod" or
+    /// "classic" (0x00/0xFF/0xAA); unset keeps each path's historical default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shred_pattern: Option<ShredPattern>,
+    
+    /// After a "corrupt" kill, shred the neutralized binary in a detached helper
+    #[serde(default)]
+    pub corrupt_then_shred: bool,
+    
+    /// After a "lock" kill, also rename the binary to `<name>.locked`
+    #[serde(default)]
+    pub lock_rename: bool,
+    
+    /// Shell command run before the kill method (advisory: cannot cancel the kill)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_kill_hook: Option<String>,
+    
+    /// Maximum runtime of the pre-kill hook (milliseconds)
+    #[serde(default = "default_pre_kill_hook_timeout_ms")]
+    pub pre_kill_hook_timeout_ms: u64,
+    
+    /// Warning period between an unauthorized result and the kill
+    /// (milliseconds, 0 = kill immediately); a re-check during the grace
+    /// period can still rescue the session. Overridden by the server, up to
+    /// `verification::network::MAX_KILL_GRACE_MS`.
+    #[serde(default)]
+    pub kill_grace_ms: u64,
+    
+    /// Hex SHA-256 the parent binary must have before a destructive kill
+    /// method touches it (otherwise it is only stopped); see `security::identity`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_parent_sha256: Option<String>,
+    
+    /// Path (or, without a directory, file name) the parent binary must have
+    /// before a destructive kill method touches it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_parent_path: Option<String>,
+    
+    /// Async mode: restart the base binary when it crashes (non-zero exit)
+    /// while the license is still valid
+    #[serde(default)]
+    pub restart_on_crash: bool,
+    
+    /// Async mode: consecutive crash restarts before giving up
+    #[serde(default = "default_max_restarts")]
+    pub max_restarts: u32,
+    
+    /// Async mode: delay before the first restart, doubled for each further one
+    /// (milliseconds, capped at 60s)
+    #[serde(default = "default_restart_backoff_ms")]
+    pub restart_backoff_ms: u64,
+    
+    /// Async mode: what a debugger attached to the running base binary
+    /// triggers
+    #[serde(default)]
+    pub base_debugger_action: DebuggerAction,
+    
+    /// What follows a crash of the overload (after the crash report); see
+    /// `verification::crash`
+    #[serde(default)]
+    pub panic_action: PanicAction,
+    
+    /// Interval of the liveness heartbeat to /api/v1/heartbeat, independent
+    /// of check_interval_ms (0 = disabled)
+    #[serde(default)]
+    pub heartbeat_interval_ms: u64,
+    
+    /// Send uptime, check and launch counts and the platform with every
+    /// verification (see `verification::metering`; opt-in)
+    #[serde(default)]
+    pub usage_metering: bool,
+    
+    /// Base URL of the security event collector (https only, default:
+    /// server_url)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_log_url: Option<String>,
+    
+    /// Signed revocation list of the fleet (https only); see
+    /// `verification::broadcast`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revocation_url: Option<String>,
+    
+    /// Signed policy bundle replacing `enforcement_policy` (https only); see
+    /// `verification::broadcast`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy_bundle_url: Option<String>,
+    
+    /// Customer-run endpoint (localhost/intranet) receiving every check
+    /// result and security event; see `verification::webhook`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+    
+    /// Key signing webhook deliveries (chosen by the customer, not ours)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_key: Option<SecretString>,
+    
+    /// OEM policy expression deciding whether an unauthorized result is
+    /// enforced now (see `security::policy`); cannot override server mandates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enforcement_policy: Option<String>,
+    
+    /// UTC offset `weekday` and `hour` of `enforcement_policy` are taken at
+    /// (minutes, default 0 = UTC); the machine's own timezone is not trusted
+    #[serde(default)]
+    pub enforcement_policy_utc_offset_minutes: i32,
+    
+    /// How long a denial is remembered, so restarts are blocked without a
+    /// network round trip (seconds, 0 = disabled)
+    #[serde(default = "default_deny_cache_ttl_secs")]
+    pub deny_cache_ttl_secs: u64,
+    
+    /// Floating license: hold a seat lease (acquired at startup, renewed by
+    /// every check, released on shutdown or kill); see `verification::seat`
+    #[serde(default)]
+    pub seat_lease: bool,
+    
+    /// "online" (default): verify with the license server; "offline":
+    /// validate a license file signed by the trust root instead (air-gapped
+    /// sites); see `verification::offline`
+    #[serde(default)]
+    pub activation_mode: ActivationMode,
+    
+    /// Offline activation: path of the license file
+    /// (default: `<executable>.license`, next to `<executable>.config`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license_file: Option<String>,
+    
+    /// Consecutive failed verifications (network errors, stalls) after which
+    /// the kill method runs even without a wrapper watching the health block
+    /// (0 = retry forever). Overridden by the server.
+    #[serde(default)]
+    pub max_consecutive_failures: u32,
+    
+    /// Verification watchdog: a check still running after this many check
+    /// intervals (at least 60s) is abandoned and its worker restarted
+    /// (0 = disabled)
+    #[serde(default = "default_watchdog_intervals")]
+    pub watchdog_intervals: u32,
+    
+    /// How long before the license expires (`expires_in`/`expires_at`) a
+    /// renewal is attempted (seconds, jittered, 0 = only on the regular
+    /// schedule); see `security::renewal`
+    #[serde(default = "default_renewal_lead_secs")]
+    pub renewal_lead_secs: u64,
+    
+    /// Switch to this user once initialization is done when started as root
+    /// (Unix; kills then go through a root broker). On Windows any value
+    /// strips the process token's privileges instead. See `security::privileges`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_as_user: Option<String>,
+    
+    /// Loop mode: detach from the loader (fork, setsid, Unix only) and treat
+    /// a replaced parent or unexpected reparenting as tamper; see
+    /// `security::lineage`
+    #[serde(default)]
+    pub daemonize: bool,
+    
+    /// Treat a termination signal (SIGTERM/SIGINT/SIGHUP, console control
+    /// events) as an attempt to disable the overload and enforce - unless the
+    /// app goes down with it (process group signals) or our parent sent it
+    /// (the sender is only known on Linux)
+    #[serde(default)]
+    pub termination_is_tamper: bool,
+    
+    /// Re-execute from an anonymous memory image and securely delete the
+    /// on-disk overload (Linux; elsewhere it keeps running from disk). See
+    /// `security::memexec`
+    #[serde(default)]
+    pub run_from_memory: bool,
+    
+    /// Async mode: resource limits enforced on the base binary
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_limits: Option<ResourceLimits>,
+    
+    /// Service mode (`killer service run`): the protected app to watch; see
+    /// `execution::service`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<ServiceConfig>,
+    
+    /// Install overload updates advertised by the server (signed with the
+    /// trust root key, see `verification::self_update`)
+    #[serde(default = "default_true")]
+    pub self_update: bool,
+}
+
+/// Additional license (`licenses`)
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct LicenseEntry {
+    pub license_id: String,
+    pub shared_secret: SecretString,
+    /// Server of this license (default: the top-level server_url)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_url: Option<String>,
+}
+
+/// Protected app watched by a service-mode overload (at least one field set)
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ServiceConfig {
+    /// Binary path: every process running it is a target
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_path: Option<String>,
+    /// File holding the PID of the target (re-read every check)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid_file: Option<String>,
+}
+
+/// Resource limits for the base binary (unset = unlimited)
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ResourceLimits {
+    /// Address space (Unix) / committed memory (Windows) per process, in MiB
+    #[serde(default)]
+    pub memory_mb: Option<u64>,
+    /// CPU time per process, in seconds
+    #[serde(default)]
+    pub cpu_seconds: Option<u64>,
+    /// Open file descriptors per process (Unix only)
+    #[serde(default)]
+    pub max_open_files: Option<u64>,
+}
+
+/// Where the license is verified
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivationMode {
+    #[default]
+    Online,
+    Offline,
+}
+
+/// Log line format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Policy for a base binary that exits before the first verification completes
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EarlyExitPolicy {
+    /// Wait for the verification to finish, enforce its result and report usage
+    #[default]
+    Complete,
+    /// Abandon the verification and report the run as unverified
+    Cancel,
+}
+
+/// Fail-closed action after the overload itself crashed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PanicAction {
+    /// Stop the protected app, then exit
+    #[default]
+    KillBase,
+    /// Only exit with a nonzero code
+    Exit,
+}
+
+/// Reaction to a debugger attached to the base binary
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DebuggerAction {
+    /// Do not check
+    Off,
+    /// Log it and record a security event
+    #[default]
+    Warn,
+    /// Also send a tamper report to the server
+    Report,
+    /// Report it, kill the base and exit
+    Kill,
+}
+
+/// Data destruction pattern for overwrite passes (pass N uses step N mod len)
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShredPattern {
+    /// 0x00 every pass
+    Zero,
+    /// Fresh random data every pass
+    Random,
+    /// DoD 5220.22-M: 0x00, 0xFF, random
+    Dod,
+    /// 0x00, 0xFF, 0xAA
+    Classic,
 }
 
 /// Kill method for unauthorized access
@@ -47,20 +444,44 @@ pub enum KillMethod {
     Stop,
     /// Stop and delete file (rm)
     Delete,
-    /// Stop and securely delete (3-pass overwrite + rm)
+    /// Stop and securely delete (overwrite passes + rm)
     Shred,
+    /// Stop and overwrite header + entry point with random data (milliseconds)
+    Corrupt,
+    /// Stop and deny execution, keeping the binary (reversible)
+    Lock,
 }
 
 impl KillMethod {
     /// Parse KillMethod from string (case-insensitive)
+    // Option instead of FromStr's Result: unknown methods are not an error
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
         match s.to_lowercase().as_str() {
             "stop" => Some(KillMethod::Stop),
             "delete" => Some(KillMethod::Delete),
             "shred" => Some(KillMethod::Shred),
+            "corrupt" => Some(KillMethod::Corrupt),
+            "lock" => Some(KillMethod::Lock),
             _ => None,
         }
     }
+    
+    /// Lowercase name, as used in config and reports
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KillMethod::Stop => "stop",
+            KillMethod::Delete => "delete",
+            KillMethod::Shred => "shred",
+            KillMethod::Corrupt => "corrupt",
+            KillMethod::Lock => "lock",
+        }
+    }
+    
+    /// Whether the method destroys the binary (unrecoverable without escrow)
+    pub fn destroys_binary(&self) -> bool {
+        matches!(self, KillMethod::Delete | KillMethod::Shred | KillMethod::Corrupt)
+    }
 }
 
 fn default_true() -> bool {
@@ -71,6 +492,54 @@ fn default_kill_method() -> KillMethod {
     KillMethod::Shred
 }
 
+fn default_cli_full_check_every() -> u32 {
+    20
+}
+
+fn default_cli_token_ttl_secs() -> u64 {
+    86400
+}
+
+fn default_max_clock_drift_secs() -> u64 {
+    3600
+}
+
+fn default_power_save_multiplier() -> u32 {
+    4
+}
+
+fn default_security_cpu_budget_pct() -> f64 {
+    0.1
+}
+
+fn default_fallback_after_failures() -> u32 {
+    3
+}
+
+fn default_pre_kill_hook_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_max_restarts() -> u32 {
+    5
+}
+
+fn default_restart_backoff_ms() -> u64 {
+    1000
+}
+
+fn default_deny_cache_ttl_secs() -> u64 {
+    300
+}
+
+fn default_renewal_lead_secs() -> u64 {
+    300
+}
+
+fn default_watchdog_intervals() -> u32 {
+    3
+}
+
 fn default_log_level() -> String {
     "info".to_string()
 }
@@ -79,10 +548,10 @@ impl Config {
     /// Get the effective server URL, prioritizing compile-time default
     pub fn get_server_url(&self) -> String {
         // If KILLER_SERVER_URL was set at compile time, use it (hardcoded into binary)
-        if let Some(compile_time_url) = option_env!("KILLER_SERVER_URL") {
-            if !compile_time_url.is_empty() {
-                return compile_time_url.to_string();
-            }
+        if let Some(compile_time_url) = option_env!("KILLER_SERVER_URL")
+            && !compile_time_url.is_empty()
+        {
+            return compile_time_url.to_string();
         }
         
         // Otherwise use the config value
@@ -110,6 +579,79 @@ impl Config {
             return Err("server_url must start with http:// or https://".to_string());
         }
         
+        if let Some(fallback) = &self.fallback_server_url
+            && !fallback.starts_with("https://")
+        {
+            return Err("fallback_server_url must start with https://".to_string());
+        }
+        
+        if let Some(url) = &self.event_log_url
+            && !url.starts_with("https://")
+        {
+            return Err("event_log_url must start with https://".to_string());
+        }
+        
+        for (name, url) in [("revocation_url", &self.revocation_url), ("policy_bundle_url", &self.policy_bundle_url)] {
+            if let Some(url) = url
+                && !url.starts_with("https://")
+            {
+                return Err(format!("{} must start with https://", name));
+            }
+        }
+        
+        for (index, entry) in self.licenses.iter().enumerate() {
+            if entry.license_id.is_empty() || entry.shared_secret.is_empty() {
+                return Err(format!("licenses[{}] needs a license_id and a shared_secret", index));
+            }
+            if let Some(url) = &entry.server_url
+                && !url.starts_with("http://")
+                && !url.starts_with("https://")
+            {
+                return Err(format!("licenses[{}].server_url must start with http:// or https://", index));
+            }
+        }
+        
+        if let Some(hash) = &self.expected_parent_sha256
+            && !(hash.trim().len() == 64 && hash.trim().chars().all(|c| c.is_ascii_hexdigit()))
+        {
+            return Err("expected_parent_sha256 must be 64 hex characters".to_string());
+        }
+        
+        if let Some(service) = &self.service
+            && service.target_path.is_none()
+            && service.pid_file.is_none()
+        {
+            return Err("service needs a target_path or a pid_file".to_string());
+        }
+        
+        if self.activation_mode == ActivationMode::Offline {
+            if self.seat_lease {
+                return Err("seat_lease needs the license server (activation_mode \"online\")".to_string());
+            }
+            // Without a key every license file would be rejected - and enforced
+            if crate::security::trust::root_key().is_none() {
+                return Err("activation_mode \"offline\" needs a build with KILLER_TRUST_ROOT_KEY".to_string());
+            }
+        }
+        
+        if let Some(webhook) = &self.webhook_url {
+            if !webhook.starts_with("http://") && !webhook.starts_with("https://") {
+                return Err("webhook_url must start with http:// or https://".to_string());
+            }
+            if self.webhook_key.as_ref().is_none_or(|key| key.is_empty()) {
+                return Err("webhook_url requires a webhook_key".to_string());
+            }
+        }
+        
+        if self.enforcement_policy_utc_offset_minutes.unsigned_abs() > 14 * 60 {
+            return Err("enforcement_policy_utc_offset_minutes must be within ±840 (±14h)".to_string());
+        }
+        
+        #[cfg(feature = "policy")]
+        if let Some(policy) = &self.enforcement_policy {
+            crate::security::policy::compile(policy).map_err(|e| format!("enforcement_policy: {}", e))?;
+        }
+        
         Ok(())
     }
 }
@@ -120,17 +662,19 @@ mod tests {
     
     #[test]
     fn test_config_validation() {
-        let mut config = Config {
-            license_id: "test_license".to_string(),
-            server_url: "http://localhost:8080".to_string(),
-            shared_secret: "secret123".to_string(),
-            check_interval_ms: 0,
-            self_destruct: true,
-            log_level: "info".to_string(),
-        };
+        let mut config: Config = serde_json::from_str(r#"{
+            "license_id": "test_license",
+            "server_url": "http://localhost:8080",
+            "shared_secret": "secret123"
+        }"#).unwrap();
         
         assert!(config.validate().is_ok());
         
+        config.webhook_url = Some("http://127.0.0.1:9000/killer".to_string());
+        assert!(config.validate().is_err());
+        config.webhook_key = Some(SecretString::from("customer-key"));
+        assert!(config.validate().is_ok());
+        
         config.license_id = "".to_string();
         assert!(config.validate().is_err());
     }
@@ -145,7 +689,7 @@ mod tests {
         
         let config: Config = serde_json::from_str(json).unwrap();
         assert_eq!(config.check_interval_ms, 0);
-        assert_eq!(config.self_destruct, true);
+        assert!(config.self_destruct);
         assert_eq!(config.log_level, "info");
     }
 }
@@ -0,0 +1,135 @@
+//! Process-wide configuration snapshot with change notifications
+//!
+//! Runtime patches from the server change configuration that several threads
+//! read. Instead of each holding its own mutable copy, the current config is
+//! published as an immutable, versioned `Arc` snapshot: readers take the
+//! latest one (cheap clone of the `Arc`) and always see a consistent version;
+//! writers clone, modify and swap it in, and subscribers are notified.
+
+use std::ops::Deref;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+
+use super::Config;
+
+/// Latest published snapshot
+static CURRENT: RwLock<Option<Arc<ConfigSnapshot>>> = RwLock::new(None);
+
+/// Subscribers notified of every new snapshot
+static SUBSCRIBERS: Mutex<Vec<Sender<Arc<ConfigSnapshot>>>> = Mutex::new(Vec::new());
+
+/// Immutable configuration version
+#[derive(Debug)]
+pub struct ConfigSnapshot {
+    /// Increases by one with every change (1 = as loaded)
+    pub version: u64,
+    pub config: Config,
+}
+
+impl Deref for ConfigSnapshot {
+    type Target = Config;
+
+    fn deref(&self) -> &Config {
+        &self.config
+    }
+}
+
+/// Publish the loaded configuration as version 1
+pub fn install(config: Config) -> Arc<ConfigSnapshot> {
+    publish(ConfigSnapshot { version: 1, config })
+}
+
+/// Latest snapshot
+///
+/// # Panics
+/// If called before `install`
+pub fn current() -> Arc<ConfigSnapshot> {
+    try_current().expect("config snapshot read before install")
+}
+
+/// Latest snapshot, None before `install`
+pub fn try_current() -> Option<Arc<ConfigSnapshot>> {
+    CURRENT.read().ok().and_then(|guard| guard.clone())
+}
+
+/// Apply a change and publish it as a new version
+///
+/// Changes that leave the config untouched (per serialized form) do not
+/// create a version or notify anyone.
+///
+/// # Returns
+/// The snapshot in effect afterwards
+pub fn update(change: impl FnOnce(&mut Config)) -> Arc<ConfigSnapshot> {
+    // Writers are serialized by the lock: no lost updates between read and swap
+    let mut guard = CURRENT.write().unwrap_or_else(|e| e.into_inner());
+    let Some(previous) = guard.clone() else {
+        panic!("config snapshot updated before install");
+    };
+
+    let mut config = previous.config.clone();
+    change(&mut config);
+    if serde_json::to_value(&config).ok() == serde_json::to_value(&previous.config).ok() {
+        return previous;
+    }
+
+    let snapshot = Arc::new(ConfigSnapshot { version: previous.version + 1, config });
+    *guard = Some(snapshot.clone());
+    drop(guard);
+
+    notify(&snapshot);
+    snapshot
+}
+
+/// Receive every snapshot published after this call
+pub fn subscribe() -> Receiver<Arc<ConfigSnapshot>> {
+    let (sender, receiver) = channel();
+    if let Ok(mut subscribers) = SUBSCRIBERS.lock() {
+        subscribers.push(sender);
+    }
+    receiver
+}
+
+fn publish(snapshot: ConfigSnapshot) -> Arc<ConfigSnapshot> {
+    let snapshot = Arc::new(snapshot);
+    *CURRENT.write().unwrap_or_else(|e| e.into_inner()) = Some(snapshot.clone());
+    notify(&snapshot);
+    snapshot
+}
+
+fn notify(snapshot: &Arc<ConfigSnapshot>) {
+    if let Ok(mut subscribers) = SUBSCRIBERS.lock() {
+        // Dropped receivers unsubscribe themselves
+        subscribers.retain(|sender| sender.send(snapshot.clone()).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_versions_and_notifies() {
+        let config: Config = serde_json::from_str(r#"{
+            "license_id": "lic_snapshot",
+            "server_url": "http://localhost:8080",
+            "shared_secret": "secret"
+        }"#).unwrap();
+
+        let updates = subscribe();
+        let installed = install(config);
+        let before = current();
+        assert_eq!(before.license_id, "lic_snapshot");
+
+        let after = update(|c| c.check_interval_ms = installed.check_interval_ms + 5000);
+        assert_eq!(after.version, before.version + 1);
+        // Readers holding the old snapshot keep a consistent view
+        assert_eq!(before.check_interval_ms + 5000, after.check_interval_ms);
+
+        // No-op changes do not create versions
+        let same = update(|c| c.check_interval_ms = after.check_interval_ms);
+        assert_eq!(same.version, after.version);
+
+        let notified: Vec<u64> = updates.try_iter().map(|s| s.version).collect();
+        assert!(notified.ends_with(&[1, after.version]));
+    }
+}
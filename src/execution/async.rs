@@ -1,13 +1,32 @@
-/// Asynchronous execution mode
-/// Start base binary IMMEDIATELY, verify license in parallel
-/// Kill base if verification fails
+//! Asynchronous execution mode
+//! Start base binary IMMEDIATELY, verify license in parallel
+//! Kill base if verification fails
+//! Optionally supervise the base: restart it on crash while still licensed
+//! Watch the running base for an attached debugger (`base_debugger_action`)
 
-use std::process::{Command, Child, exit};
+use std::process::{Command, Child};
+use crate::utils::exit_status::{self, ExitStatus};
 use std::thread;
-use std::time::Duration;
-use crate::verification;
-use crate::config::Config;
-use crate::security::secure_delete_self;
+use std::time::{Duration, Instant};
+use crate::verification::{self, VerifyError, VerifyResponse};
+use crate::verification::usage::{report_usage, UsageEvent};
+use crate::config::{Config, DebuggerAction, EarlyExitPolicy, ResourceLimits};
+use crate::security::{antidebug, destroy_self};
+use crate::utils::job::KillOnCloseJob;
+use crate::utils::limits;
+use crate::utils::tasks::{self, Task};
+
+/// A base run at least this long resets the restart counter
+const RESTART_RESET_AFTER: Duration = Duration::from_secs(60);
+
+/// Upper bound of the restart backoff
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(60);
+
+/// How often the base is checked for an attached debugger
+const DEBUGGER_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often a supervised base is polled for exit
+const BASE_POLL_INTERVAL: Duration = Duration::from_millis(250);
 
 /// Execute in asynchronous mode
 /// 
@@ -16,112 +35,382 @@ use crate::security::secure_delete_self;
 /// 2. Verify license in parallel
 /// 3. If authorized → let base continue
 /// 4. If unauthorized → kill base process + self-destruct
+/// 5. If base exits first → settle verification per `early_exit_policy` and report usage
+/// 6. With `restart_on_crash`, a crashed base is respawned after re-verification
 pub fn execute_async(config: &Config) -> ! {
-    eprintln!("⚡ Running in ASYNC mode: Starting base binary while verifying...");
+    log_info!("⚡ Running in ASYNC mode: Starting base binary while verifying...");
     
     let base_path = match &config.base_binary_path {
         Some(path) => path.clone(),
         None => {
-            eprintln!("❌ ASYNC mode requires base_binary_path in config");
-            exit(1);
+            log_error!("❌ ASYNC mode requires base_binary_path in config");
+            exit_status::exit(ExitStatus::ConfigMissing, "async mode requires base_binary_path");
         }
     };
     
+    // Starting first would hand a just-denied machine another run
+    if let Some(denial) = verification::denial::cached(config) {
+        log_error!("⛔ Denied recently ({}) - not starting base binary", denial.message);
+        exit_status::exit(ExitStatus::Unauthorized, &denial.message);
+    }
+    
     // Start base binary in background
-    let mut base_process = match spawn_base(&base_path) {
+    let mut base_process = match spawn_base(&base_path, config.base_limits.as_ref()) {
         Ok(child) => child,
         Err(e) => {
-            eprintln!("❌ Failed to spawn base binary: {}", e);
-            exit(1);
+            log_error!("❌ Failed to spawn base binary: {}", e);
+            exit_status::exit(ExitStatus::BaseFailed, &e.to_string());
         }
     };
     
-    eprintln!("🚀 Base binary started (PID: {})", base_process.id());
+    log_info!("🚀 Base binary started (PID: {})", base_process.id());
+    verification::crash::set_target(base_process.id());
+    
+    let mut job = bind_to_job(&base_process, config.base_limits.as_ref());
+    let mut debugger_watch = DebuggerWatch::default();
     
     // Verify license in parallel
-    let license_id = config.license_id.clone();
-    let server_url = config.get_server_url();
-    let shared_secret = config.shared_secret.clone();
-    let grace_period = 0u32; // grace_period removed from config
+    let verification_config = config.clone();
     let self_destruct = config.self_destruct;
     
-    let verification_handle = thread::spawn(move || {
-        verification::verify_license(
-            &license_id,
-            &server_url,
-            &shared_secret,
-            grace_period,
-            true, // first_check
-        )
+    let verification_handle = tasks::spawn("verification", move || {
+        verification::fallback::verify_one_shot(&verification_config, true)
     });
     
     // Wait for verification (with timeout)
     let verification_timeout = Duration::from_secs(30);
-    let start = std::time::Instant::now();
+    let start = Instant::now();
     
     loop {
         // Check if verification completed
         if verification_handle.is_finished() {
             match verification_handle.join() {
                 Ok(Ok(response)) if response.authorized => {
-                    eprintln!("✅ License verified. Base binary continues running.");
-                    // Wait for base to complete
-                    let status = base_process.wait().expect("Failed to wait for base");
-                    exit(status.code().unwrap_or(0));
+                    log_info!("✅ License verified. Base binary continues running.");
+                    verification::denial::clear(config);
+                    supervise(config, &base_path, base_process, &mut job, debugger_watch);
+                }
+                Ok(result) if verification::pause::active(config).is_some() => {
+                    let failure = result.map_or_else(String::from, |response| response.message);
+                    log_warn!("⏸️  Verification failed ({}), but enforcement is paused by the server", failure);
+                    supervise(config, &base_path, base_process, &mut job, debugger_watch);
                 }
-                Ok(Ok(_response)) | Ok(Err(_)) | Err(_) => {
-                    eprintln!("❌ License verification failed. Terminating base binary...");
+                Ok(Ok(response)) => {
+                    log_error!("❌ License verification failed. Terminating base binary...");
+                    verification::denial::record(config, &response);
                     kill_base(&mut base_process);
                     
+                    exit_status::record(ExitStatus::Unauthorized, &response.message);
                     if self_destruct {
-                        secure_delete_self();
+                        destroy_self(config);
                     } else {
-                        exit(1);
+                        exit_status::exit(ExitStatus::Unauthorized, &response.message);
+                    }
+                }
+                Ok(Err(e)) => {
+                    log_error!("❌ Verification error ({}). Terminating base binary...", e);
+                    kill_base(&mut base_process);
+                    
+                    // A server outage says nothing about the license: never destroy over it
+                    exit_status::record(ExitStatus::NetworkFailure, &e.message);
+                    if self_destruct && !e.class.is_retryable() {
+                        destroy_self(config);
+                    } else {
+                        exit_status::exit(ExitStatus::NetworkFailure, &e.message);
+                    }
+                }
+                Err(_) => {
+                    log_error!("❌ License verification failed. Terminating base binary...");
+                    kill_base(&mut base_process);
+                    
+                    exit_status::record(ExitStatus::InternalError, "verification task failed");
+                    if self_destruct {
+                        destroy_self(config);
+                    } else {
+                        exit_status::exit(ExitStatus::InternalError, "verification task failed");
                     }
                 }
             }
         }
         
         // Check if verification timed out
+        if start.elapsed() > verification_timeout && verification::pause::active(config).is_some() {
+            log_warn!("⏸️  Verification timed out, but enforcement is paused by the server");
+            supervise(config, &base_path, base_process, &mut job, debugger_watch);
+        }
         if start.elapsed() > verification_timeout {
-            eprintln!("⏱️  Verification timeout. Terminating base binary...");
+            log_warn!("⏱️  Verification timeout (still running: {:?}). Terminating base binary...", tasks::active());
             kill_base(&mut base_process);
             
-            if self_destruct {
-                secure_delete_self();
-            } else {
-                exit(1);
-            }
+            // No answer at all is retryable like an outage: never destroy over it
+            exit_status::exit(ExitStatus::NetworkFailure, "verification timed out");
+        }
+        
+        if debugger_watch.poll(config, &base_process) {
+            kill_base(&mut base_process);
+            exit_status::exit(ExitStatus::TamperDetected, "debugger attached to the base binary");
         }
         
         // Check if base process died
         match base_process.try_wait() {
             Ok(Some(status)) => {
-                eprintln!("⚠️  Base binary exited early with status: {}", status);
-                exit(status.code().unwrap_or(1));
+                log_warn!("⚠️  Base binary exited early with status: {}", status);
+                let exit_code = status.code().unwrap_or(1);
+                
+                let authorized = settle_early_exit(
+                    config,
+                    verification_handle,
+                    start + verification_timeout,
+                    start,
+                    exit_code,
+                );
+                
+                let status = if authorized { ExitStatus::Authorized } else { ExitStatus::Unauthorized };
+                exit_status::record_code(status, exit_code, "base binary exited early");
+                if !authorized && self_destruct {
+                    destroy_self(config);
+                }
+                exit_status::pass_through(status, exit_code, "base binary exited early");
             }
             Ok(None) => {
                 // Still running, continue waiting
                 thread::sleep(Duration::from_millis(100));
             }
             Err(e) => {
-                eprintln!("❌ Error waiting for base: {}", e);
-                exit(1);
+                log_error!("❌ Error waiting for base: {}", e);
+                exit_status::exit(ExitStatus::InternalError, &format!("error waiting for base: {}", e));
             }
         }
     }
 }
 
+/// Settle the in-flight verification after the base exited early and report usage
+///
+/// Short-lived invocations finish before the first verification returns; without
+/// this they would never be metered or checked.
+///
+/// # Returns
+/// false if the server definitively denied the run
+fn settle_early_exit(
+    config: &Config,
+    handle: Task<Result<VerifyResponse, VerifyError>>,
+    deadline: Instant,
+    started: Instant,
+    exit_code: i32,
+) -> bool {
+    let outcome = match config.early_exit_policy {
+        EarlyExitPolicy::Complete => {
+            log_info!("⏳ Completing in-flight verification before exiting...");
+            while !handle.is_finished() && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(50));
+            }
+            
+            if handle.is_finished() {
+                match handle.join() {
+                    Ok(Ok(response)) if response.authorized => "authorized",
+                    Ok(Ok(response)) => {
+                        verification::denial::record(config, &response);
+                        "unauthorized"
+                    }
+                    Ok(Err(_)) | Err(_) => "error",
+                }
+            } else {
+                "timeout"
+            }
+        }
+        EarlyExitPolicy::Cancel => {
+            log_warn!("🚫 Cancelling in-flight verification (early_exit_policy=cancel)");
+            "cancelled"
+        }
+    };
+    
+    let mut event = UsageEvent::new(&config.license_id, "base_exit", outcome);
+    event.base_exit_code = Some(exit_code);
+    event.runtime_ms = started.elapsed().as_millis() as u64;
+    
+    if let Err(e) = report_usage(&config.get_server_url(), &config.shared_secret, &event) {
+        log_warn!("⚠️  Failed to report usage: {}", e);
+    }
+    
+    outcome != "unauthorized"
+}
+
+/// Wait for the base to finish, restarting it on crash if configured
+///
+/// A crash (non-zero exit or signal) is only followed by a restart while the
+/// license still verifies, at most `max_restarts` times in a row, with
+/// exponential backoff. A run longer than `RESTART_RESET_AFTER` counts as
+/// healthy and resets the counter. Exits with the base's exit code.
+fn supervise(
+    config: &Config,
+    base_path: &str,
+    mut base_process: Child,
+    job: &mut Option<KillOnCloseJob>,
+    mut debugger_watch: DebuggerWatch,
+) -> ! {
+    let mut restarts = 0u32;
+    let mut started = Instant::now();
+    
+    loop {
+        let status = match wait_watched(config, &mut base_process, &mut debugger_watch) {
+            Ok(Some(status)) => status,
+            Ok(None) => {
+                kill_base(&mut base_process);
+                exit_status::exit(ExitStatus::TamperDetected, "debugger attached to the base binary");
+            }
+            Err(e) => {
+                log_error!("❌ Error waiting for base: {}", e);
+                exit_status::exit(ExitStatus::InternalError, &format!("error waiting for base: {}", e));
+            }
+        };
+        let code = status.code().unwrap_or(1);
+        if status.success() || !config.restart_on_crash {
+            exit_status::pass_through(ExitStatus::Authorized, status.code().unwrap_or(0), "base binary exited");
+        }
+        
+        if started.elapsed() >= RESTART_RESET_AFTER {
+            restarts = 0;
+        }
+        if restarts >= config.max_restarts {
+            log_warn!("🛑 Base crashed ({}) - restart limit of {} reached", status, config.max_restarts);
+            exit_status::pass_through(ExitStatus::Authorized, code, "base binary crashed, restart limit reached");
+        }
+        restarts += 1;
+        
+        let delay = restart_delay(restarts, config.restart_backoff_ms);
+        log_error!("💥 Base crashed ({}) - restart {}/{} in {}ms", status, restarts, config.max_restarts, delay.as_millis());
+        thread::sleep(delay);
+        
+        // Only a still-valid license earns a restart
+        match verification::fallback::verify(config, false) {
+            Ok(response) if response.authorized => {}
+            Ok(response) => {
+                log_error!("❌ License no longer valid - not restarting base");
+                exit_status::record(ExitStatus::Unauthorized, &response.message);
+                if config.self_destruct {
+                    destroy_self(config);
+                }
+                exit_status::exit(ExitStatus::Unauthorized, &response.message);
+            }
+            Err(e) => {
+                log_warn!("⚠️  Cannot re-verify license ({}) - not restarting base", e);
+                exit_status::pass_through(ExitStatus::NetworkFailure, code, &e.message);
+            }
+        }
+        
+        base_process = match spawn_base(base_path, config.base_limits.as_ref()) {
+            Ok(child) => child,
+            Err(e) => {
+                log_error!("❌ Failed to restart base binary: {}", e);
+                exit_status::pass_through(ExitStatus::BaseFailed, code, &e.to_string());
+            }
+        };
+        log_info!("🔁 Base binary restarted (PID: {})", base_process.id());
+        verification::crash::set_target(base_process.id());
+        *job = bind_to_job(&base_process, config.base_limits.as_ref());
+        debugger_watch = DebuggerWatch::default();
+        started = Instant::now();
+    }
+}
+
+/// Wait for the base to exit, checking it for a debugger meanwhile
+///
+/// # Returns
+/// None if an attached debugger requires killing the base
+fn wait_watched(
+    config: &Config,
+    base_process: &mut Child,
+    debugger_watch: &mut DebuggerWatch,
+) -> std::io::Result<Option<std::process::ExitStatus>> {
+    if config.base_debugger_action == DebuggerAction::Off {
+        return base_process.wait().map(Some);
+    }
+    loop {
+        if let Some(status) = base_process.try_wait()? {
+            return Ok(Some(status));
+        }
+        if debugger_watch.poll(config, base_process) {
+            return Ok(None);
+        }
+        thread::sleep(BASE_POLL_INTERVAL);
+    }
+}
+
+/// Periodic debugger check of the base (`base_debugger_action`)
+#[derive(Debug, Default)]
+struct DebuggerWatch {
+    last_check: Option<Instant>,
+    /// Attachment already acted on (reported once, not every check)
+    attached: bool,
+}
+
+impl DebuggerWatch {
+    /// Check the base if due
+    ///
+    /// # Returns
+    /// true if the base must be killed
+    fn poll(&mut self, config: &Config, base_process: &Child) -> bool {
+        let action = config.base_debugger_action;
+        if action == DebuggerAction::Off || self.last_check.is_some_and(|last| last.elapsed() < DEBUGGER_CHECK_INTERVAL) {
+            return false;
+        }
+        self.last_check = Some(Instant::now());
+        let Some(detection) = antidebug::detect_debugger_in(base_process) else {
+            self.attached = false;
+            return false;
+        };
+        if std::mem::replace(&mut self.attached, true) {
+            return false;
+        }
+        
+        log_error!("🐞 Debugger attached to the base binary (PID {}): {}", base_process.id(), detection);
+        if action == DebuggerAction::Warn {
+            verification::events::record("base_debugger", &detection);
+            return false;
+        }
+        verification::tamper::report_tamper(
+            &config.get_server_url(),
+            &config.license_id,
+            &config.shared_secret,
+            "base_debugger",
+            &detection,
+        );
+        action == DebuggerAction::Kill
+    }
+}
+
+/// Backoff before restart number `attempt` (1-based): doubles, capped
+fn restart_delay(attempt: u32, base_ms: u64) -> Duration {
+    let factor = 1u64 << attempt.saturating_sub(1).min(16);
+    Duration::from_millis(base_ms.saturating_mul(factor)).min(MAX_RESTART_BACKOFF)
+}
+
+/// Windows: tie the base to our lifetime, so an overload crash cannot leave
+/// it running unprotected (the job handle is closed by the OS on exit), and
+/// apply the configured resource limits to it
+fn bind_to_job(base_process: &Child, limits: Option<&ResourceLimits>) -> Option<KillOnCloseJob> {
+    match KillOnCloseJob::bind(base_process, limits) {
+        Ok(job) => Some(job),
+        Err(e) => {
+            log_warn!("⚠️  Failed to bind base binary to job object: {}", e);
+            None
+        }
+    }
+}
+
 /// Spawn base binary as child process
-fn spawn_base(base_path: &str) -> Result<Child, std::io::Error> {
-    Command::new(base_path)
-        .args(std::env::args().skip(1)) // Forward arguments
-        .spawn()
+fn spawn_base(base_path: &str, limits: Option<&ResourceLimits>) -> Result<Child, std::io::Error> {
+    let mut command = Command::new(base_path);
+    command.args(std::env::args().skip(1)); // Forward arguments
+    if let Some(limits) = limits {
+        limits::apply_to_command(&mut command, limits);
+    }
+    command.spawn()
 }
 
 /// Kill base process and any children
 fn kill_base(child: &mut Child) {
-    eprintln!("🔪 Killing base process (PID: {})...", child.id());
+    log_warn!("🔪 Killing base process (PID: {})...", child.id());
     
     #[cfg(unix)]
     {
@@ -153,7 +442,14 @@ mod tests {
     
     #[test]
     fn test_spawn_base_error_handling() {
-        let result = spawn_base("/nonexistent/binary");
+        let result = spawn_base("/nonexistent/binary", None);
         assert!(result.is_err());
     }
+    
+    #[test]
+    fn test_restart_delay_backoff() {
+        assert_eq!(restart_delay(1, 1000), Duration::from_secs(1));
+        assert_eq!(restart_delay(3, 1000), Duration::from_secs(4));
+        assert_eq!(restart_delay(20, 1000), MAX_RESTART_BACKOFF);
+    }
 }
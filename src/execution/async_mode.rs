@@ -1,77 +1,113 @@
-/// Asynchronous execution mode  
-/// Return immediately to loader, verify license in background thread
-/// Kill parent process tree if verification fails
+//! Asynchronous execution mode  
+//! Return immediately to loader, verify license in a detached helper process
+//! Kill parent process tree if verification fails
+//!
+//! A background thread would die with this process the moment we return to
+//! the loader, so the verification runs in a helper (a re-exec of ourselves
+//! marked with `BACKGROUND_VERIFY_ENV`) in its own process group.
 
-use std::process::exit;
+use std::process::{Command, Stdio};
+use crate::utils::exit_status::{self, ExitStatus};
 use std::thread;
 use std::time::Duration;
 use crate::verification;
 use crate::config::Config;
+use crate::security::memexec;
 use crate::utils::process::get_parent_pid;
+use crate::utils::session;
 
-pub fn execute_async(config: &Config) -> ! {
-    eprintln!("⚡ Running in ASYNC mode: Returning to loader immediately, verifying in background...");
+/// Env var marking the detached verification helper (value: loader PID)
+pub const BACKGROUND_VERIFY_ENV: &str = "KILLCODE_BACKGROUND_VERIFY";
+
+pub fn execute_async(_config: &Config) -> ! {
+    log_info!("⚡ Running in ASYNC mode: Returning to loader immediately, verifying in background...");
     
     // Get parent PID (the merged binary loader) before we exit
     let parent_pid = get_parent_pid().unwrap_or(0);
     
-    eprintln!("📍 Parent loader PID: {} (will be killed if verification fails)", parent_pid);
+    log_info!("📍 Parent loader PID: {} (will be killed if verification fails)", parent_pid);
     
-    // Clone config values for background thread
-    let license_id = config.license_id.clone();
-    let server_url = config.get_server_url();
-    let shared_secret = config.shared_secret.clone();
-    let grace_period = 0u32; // grace_period removed from config
-    let kill_method = config.kill_method.clone();
+    spawn_background_verification(parent_pid);
     
-    // Spawn daemon thread that will outlive this process
-    thread::spawn(move || {
-        thread::sleep(Duration::from_millis(100));
-        
-        eprintln!("🔍 [Background] Starting license verification...");
-        
-        let verification_result = verification::verify_license(
-            &license_id,
-            &server_url,
-            &shared_secret,
-            grace_period,
-            true, // first_check - async mode always treats as first check
-        );
+    log_info!("✅ Returning control to loader → Base binary will execute (verification in background)");
+    exit_status::exit(ExitStatus::Authorized, "verification continues in the background");
+}
+
+/// Start the detached helper that verifies and enforces after we returned
+fn spawn_background_verification(parent_pid: u32) {
+    let result = memexec::image_path().and_then(|exe| {
+        let mut command = Command::new(exe);
+        command
+            .env(BACKGROUND_VERIFY_ENV, parent_pid.to_string())
+            .stdin(Stdio::null());
         
-        match verification_result {
-            Ok(response) if response.authorized => {
-                eprintln!("✅ [Background] License verified. Parent and base continue running.");
-                return;
-            }
-            Ok(_response) => {
-                eprintln!("❌ [Background] License verification FAILED!");
-            }
-            Err(e) => {
-                eprintln!("❌ [Background] Verification error: {}", e);
-            }
+        // Own process group: killing the loader's group must not take the
+        // helper down before enforcement completes
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
         }
         
-        eprintln!("💀 [Background] Killing parent process tree (PID: {})...", parent_pid);
-        kill_process_tree(parent_pid as i32);
-        
-        use crate::config::KillMethod;
-        match kill_method {
-            KillMethod::Stop => {
-                eprintln!("🛑 [Background] Stopped unauthorized process");
-            }
-            KillMethod::Delete | KillMethod::Shred => {
-                eprintln!("🗑️  [Background] Unauthorized process killed");
-            }
-        }
+        command.spawn()
     });
     
-    eprintln!("✅ Returning control to loader → Base binary will execute (verification in background)");
-    exit(0);
+    match result {
+        Ok(child) => log_info!("🔍 Background verification started (PID {})", child.id()),
+        Err(e) => log_warn!("⚠️  Cannot start background verification: {}", e),
+    }
 }
 
-
+/// Verify the license and kill the loader's process tree if it fails
+/// (runs in the detached helper process)
+pub fn run_background_verification(config: &Config, parent_pid: u32) -> ! {
+    log_info!("🔍 [Background] Starting license verification...");
+    
+    let verification_result = verification::fallback::verify_one_shot(config, true);
+    
+    let (status, message) = match verification_result {
+        Ok(response) if response.authorized => {
+            log_info!("✅ [Background] License verified. Parent and base continue running.");
+            exit_status::exit(ExitStatus::Authorized, &response.message);
+        }
+        Ok(response) => {
+            log_error!("❌ [Background] License verification FAILED!");
+            (ExitStatus::Unauthorized, response.message)
+        }
+        Err(e) => {
+            log_error!("❌ [Background] Verification error: {}", e);
+            (ExitStatus::NetworkFailure, e.message)
+        }
+    };
+    
+    // PID 0/1 would signal our own group or init
+    if parent_pid <= 1 {
+        log_warn!("⚠️  [Background] Unknown loader PID - nothing to kill");
+        exit_status::exit(status, &message);
+    }
+    
+    log_warn!("💀 [Background] Killing parent process tree (PID: {})...", parent_pid);
+    kill_process_tree(parent_pid as i32);
+    
+    use crate::config::KillMethod;
+    match config.kill_method {
+        KillMethod::Stop => {
+            log_warn!("🛑 [Background] Stopped unauthorized process");
+        }
+        KillMethod::Delete | KillMethod::Shred | KillMethod::Corrupt | KillMethod::Lock => {
+            log_warn!("🗑️  [Background] Unauthorized process killed");
+        }
+    }
+    exit_status::exit(status, &message);
+}
 
 fn kill_process_tree(pid: i32) {
+    // Enforcement is scoped to our own login session (terminal servers)
+    if !session::is_same_session(pid as u32) {
+        log_info!("👥 [Background] PID {} belongs to another login session - not killing", pid);
+        return;
+    }
+
     #[cfg(unix)]
     {
         use nix::sys::signal::{kill, Signal};
@@ -97,5 +133,5 @@ fn kill_process_tree(pid: i32) {
             .output();
     }
     
-    eprintln!("💀 [Background] Process tree killed");
+    log_warn!("💀 [Background] Process tree killed");
 }
@@ -0,0 +1,227 @@
+//! Read-only verification mode for auditors (`killer audit`)
+//!
+//! Performs a full, real verification against the server and reports what
+//! enforcement WOULD do, including the exact kill plan with resolved paths,
+//! as a signed JSON report on stdout. Nothing is stopped, deleted or
+//! overwritten: this module only ever calls planning functions.
+
+use serde::Serialize;
+use std::path::PathBuf;
+
+use super::enforcement::{self, Decision};
+use crate::config::{Config, ShredPattern};
+use crate::security::kill_parent::{plan_kill, KillPlan};
+use crate::security::renewal::RenewalScheduler;
+use crate::security::{memexec, WipePlan};
+use crate::utils::audit;
+use crate::utils::process::get_parent_pid;
+use crate::utils::state::StateStore;
+use crate::utils::time;
+use crate::verification::{self, canonical, create_signature, get_machine_fingerprint, VerifyError, VerifyResponse};
+
+/// Audit report
+#[derive(Debug, Serialize)]
+pub struct AuditReport {
+    pub license_id: String,
+    pub machine_fingerprint: String,
+    pub timestamp: i64,
+    pub authorized: bool,
+    pub message: String,
+    /// Whether enforcement would have been triggered (deferral, pause and the
+    /// failure limit included, as in the verification loop)
+    pub would_enforce: bool,
+    /// Kill method the server asked for, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_kill_method: Option<String>,
+    /// Enforcement against the protected process (verification loop mode)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kill_plan: Option<KillPlan>,
+    /// Self-destruct of the overload itself (CLI mode)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub self_destruct: Option<SelfDestructPlan>,
+}
+
+/// Files `destroy_self` would wipe
+#[derive(Debug, Serialize)]
+pub struct SelfDestructPlan {
+    pub binary_path: PathBuf,
+    pub config_path: PathBuf,
+    pub wipe: WipePlan,
+}
+
+/// Report with its signature
+#[derive(Debug, Serialize)]
+pub struct SignedAuditReport {
+    pub report: AuditReport,
+    /// HMAC-SHA256 (shared secret) over the canonical JSON of `report`
+    pub signature: String,
+}
+
+/// Run the audit and print the signed report
+///
+/// # Arguments
+/// * `target_pid` - Process to plan the kill against (default: our parent)
+///
+/// # Returns
+/// Exit code
+pub fn run_audit(config: &Config, target_pid: Option<u32>) -> i32 {
+    log_info!("🔍 Audit mode: real verification, no enforcement");
+
+    let result = verification::fallback::verify(config, true);
+    // The loop counts this check on top of the failures of earlier runs
+    let consecutive_failures = StateStore::for_license(&config.license_id).load().consecutive_failures.saturating_add(1);
+    let would_enforce = would_enforce(config, &result, consecutive_failures);
+    let (authorized, message, server_kill_method) = match result {
+        Ok(response) => (response.authorized, response.message, response.kill_method),
+        Err(e) => (false, format!("Verification error: {}", e), None),
+    };
+
+    let report = build_report(config, authorized, would_enforce, message, server_kill_method, target_pid);
+    let signed = match sign(report, &config.shared_secret) {
+        Ok(signed) => signed,
+        Err(e) => {
+            log_error!("❌ {}", e);
+            return 1;
+        }
+    };
+
+    audit::record(
+        "audit_run",
+        &format!("authorized={} would_enforce={}", signed.report.authorized, signed.report.would_enforce),
+    );
+    match serde_json::to_string_pretty(&signed) {
+        Ok(json) => {
+            println!("{}", json);
+            0
+        }
+        Err(e) => {
+            log_error!("❌ Failed to serialize audit report: {}", e);
+            1
+        }
+    }
+}
+
+/// Whether the verification loop would enforce on `result`
+///
+/// Decided like the loop does (see `enforcement`), without its side effects.
+fn would_enforce(config: &Config, result: &Result<VerifyResponse, VerifyError>, consecutive_failures: u32) -> bool {
+    let decision = match result {
+        Ok(response) if response.authorized => return false,
+        Ok(response) => enforcement::denial_decision(config, response),
+        Err(e) => {
+            let renewal = RenewalScheduler::new(config.renewal_lead_secs);
+            enforcement::failure_decision(config, &e.message, consecutive_failures, &renewal, time::unix_now())
+        }
+    };
+    matches!(decision, Decision::Enforce { .. })
+}
+
+fn build_report(
+    config: &Config,
+    authorized: bool,
+    would_enforce: bool,
+    message: String,
+    server_kill_method: Option<String>,
+    target_pid: Option<u32>,
+) -> AuditReport {
+    let requested = server_kill_method
+        .as_deref()
+        .and_then(crate::config::KillMethod::from_str)
+        .unwrap_or_else(|| config.kill_method.clone());
+
+    let (kill_plan, self_destruct) = if config.cli_mode {
+        let plan = config.self_destruct.then(|| self_destruct_plan(config));
+        (None, plan)
+    } else {
+        let plan = target_pid.or_else(get_parent_pid).map(|pid| plan_kill(&requested, config, pid));
+        (plan, None)
+    };
+
+    AuditReport {
+        license_id: config.license_id.clone(),
+        machine_fingerprint: get_machine_fingerprint(),
+        timestamp: time::protocol_now(),
+        would_enforce,
+        authorized,
+        message,
+        server_kill_method,
+        kill_plan,
+        self_destruct,
+    }
+}
+
+fn self_destruct_plan(config: &Config) -> SelfDestructPlan {
+    let binary_path = memexec::executable_path().unwrap_or_default();
+    let config_path = PathBuf::from(format!("{}.config", binary_path.display()));
+    SelfDestructPlan {
+        binary_path,
+        config_path,
+        wipe: WipePlan::from_config(config, ShredPattern::Random),
+    }
+}
+
+fn sign(report: AuditReport, shared_secret: &str) -> Result<SignedAuditReport, String> {
+    let body = canonical::to_string(&report)?;
+    Ok(SignedAuditReport {
+        signature: create_signature(&body, shared_secret),
+        report,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verification::verify_signature;
+
+    fn test_config(cli_mode: bool) -> Config {
+        let mut config: Config = serde_json::from_str(r#"{
+            "license_id": "lic_audit",
+            "server_url": "http://localhost:8080",
+            "shared_secret": "secret",
+            "kill_method": "shred"
+        }"#).unwrap();
+        config.cli_mode = cli_mode;
+        config
+    }
+
+    #[test]
+    fn test_report_plans_kill_of_target() {
+        let config = test_config(false);
+        let pid = std::process::id();
+        let report = build_report(&config, false, true, "denied".to_string(), Some("delete".to_string()), Some(pid));
+
+        assert!(report.would_enforce);
+        let plan = report.kill_plan.unwrap();
+        assert_eq!(plan.target_pid, pid);
+        assert_eq!(plan.requested_method, crate::config::KillMethod::Delete);
+        assert!(report.self_destruct.is_none());
+        // Planning never touches the target
+        assert!(std::env::current_exe().unwrap().exists());
+    }
+
+    #[test]
+    fn test_retryable_error_enforces_only_at_the_failure_limit() {
+        let mut config = test_config(false);
+        let outage: Result<VerifyResponse, VerifyError> = Err(VerifyError::retryable("HTTP 503".to_string(), None));
+        assert!(!would_enforce(&config, &outage, 1));
+
+        config.max_consecutive_failures = 3;
+        assert!(!would_enforce(&config, &outage, 2));
+        assert!(would_enforce(&config, &outage, 3));
+
+        let denied = Ok(VerifyResponse { authorized: false, message: "revoked".to_string(), ..Default::default() });
+        assert!(would_enforce(&config, &denied, 1));
+    }
+
+    #[test]
+    fn test_report_signature_covers_report() {
+        let config = test_config(true);
+        let report = build_report(&config, true, false, "ok".to_string(), None, None);
+        assert!(report.kill_plan.is_none());
+        assert!(report.self_destruct.is_some());
+
+        let signed = sign(report, "secret").unwrap();
+        let body = canonical::to_string(&signed.report).unwrap();
+        assert!(verify_signature(&body, "secret", &signed.signature));
+    }
+}
@@ -0,0 +1,261 @@
+//! Short-lived CLI licensing mode
+//!
+//! For protected CLIs that run for a few hundred milliseconds, a network round
+//! trip per invocation doubles their runtime. Instead:
+//! 1. A full verification issues a locally cached, HMAC-protected token
+//! 2. Later invocations exit 0 immediately while the token is valid and
+//!    queue a usage ping, flushed by a detached helper process
+//! 3. A full verification is forced every Nth invocation or on token expiry
+
+use std::process::{Command, Stdio};
+use crate::config::Config;
+use crate::security::{destroy_self, memexec};
+use crate::utils::exit_status::{self, ExitStatus};
+use crate::utils::state::{self, CliToken, PersistentState, StateStore};
+use crate::utils::{helper, secure_fs};
+use crate::utils::time::{self, unix_now};
+use crate::verification::{self, create_signature, get_machine_fingerprint, verify_signature};
+use crate::verification::usage::{report_usage, UsageEvent};
+
+/// Env var marking the detached usage-flush helper process (its job token,
+/// see `utils::helper`)
+pub const USAGE_FLUSH_ENV: &str = "KILLCODE_USAGE_FLUSH";
+
+/// Helper job kind of the usage-flush helper
+const USAGE_FLUSH_JOB: &str = "usage-flush";
+
+/// Execute one CLI-mode check
+///
+/// Exit codes follow sync mode: 0 lets the loader run the base binary.
+pub fn execute_cli(config: &Config) -> ! {
+    let store = StateStore::for_license(&config.license_id);
+    let mut state = store.load();
+    state.cli.invocations += 1;
+
+    let fingerprint = get_machine_fingerprint();
+    let now = unix_now();
+
+    // A just-denied machine is refused before the token or the network
+    if let Some(denial) = verification::denial::active(config, &state) {
+        log_error!("⛔ CLI mode: denied {}s ago ({})", now - denial.denied_at, denial.message);
+        let message = denial.message.clone();
+        state.cli.token = None;
+        save_state(&store, &state);
+
+        if config.self_destruct {
+            destroy_self(config);
+        }
+        exit_status::exit(ExitStatus::Unauthorized, &message);
+    }
+
+    let token_valid = state.cli.token.as_ref()
+        .is_some_and(|token| token_is_valid(config, token, &fingerprint, now));
+    let forced = config.cli_full_check_every > 0
+        && state.cli.invocations.is_multiple_of(config.cli_full_check_every as u64);
+
+    if token_valid && !forced {
+        log_info!("⚡ CLI mode: cached token valid - skipping network verification");
+        save_queued(&store, &state, 1);
+        spawn_usage_flush(config);
+        exit_status::exit(ExitStatus::Authorized, "cached CLI token valid");
+    }
+
+    log_info!(
+        "🔄 CLI mode: full verification ({})",
+        if forced { "periodic" } else { "no valid token" }
+    );
+
+    match verification::fallback::verify_one_shot(config, true) {
+        Ok(response) if response.authorized => {
+            // Token never outlives the license itself
+            let ttl = match response.expires_in {
+                Some(expires_in) if expires_in > 0 => (expires_in as u64).min(config.cli_token_ttl_secs),
+                _ => config.cli_token_ttl_secs,
+            };
+            state.cli.token = Some(issue_token(config, &fingerprint, time::expires_at(now, ttl as i64)));
+            state.denial = None;
+            save_state(&store, &state);
+
+            if state.cli.pending_usage > 0 {
+                spawn_usage_flush(config);
+            }
+
+            log_info!("✅ License verified - token cached for {}s", ttl);
+            exit_status::exit(ExitStatus::Authorized, "license verified");
+        }
+        Ok(response) => {
+            log_error!("❌ License verification failed");
+            state.cli.token = None;
+            verification::denial::mark(config, &mut state, &response);
+            save_state(&store, &state);
+
+            if config.self_destruct {
+                destroy_self(config);
+            }
+            exit_status::exit(ExitStatus::Unauthorized, &response.message);
+        }
+        Err(e) => {
+            log_error!("❌ Verification error: {}", e);
+            save_state(&store, &state);
+            exit_status::exit(ExitStatus::NetworkFailure, &e.message);
+        }
+    }
+}
+
+/// Usage-flush helper: report queued CLI invocations and exit
+///
+/// Only runs for a token issued by `spawn_usage_flush` for this license, and
+/// never exits 0, so setting the env var cannot stand in for a verification.
+pub fn run_usage_flush(config: &Config, token: &str) -> ! {
+    match helper::redeem(USAGE_FLUSH_JOB, token) {
+        Ok(namespace) if namespace == state::namespace_of(&config.license_id) => {}
+        _ => exit_status::exit(ExitStatus::Helper, "usage flush without a valid job"),
+    }
+    // One helper at a time; the lock is held until we exit
+    match secure_fs::try_lock_file(&flush_pidfile(config)) {
+        Ok(Some(mut pidfile)) => {
+            use std::io::Write;
+            let _ = pidfile.set_len(0).and_then(|_| write!(pidfile, "{}", std::process::id()));
+            flush_pending_usage(config);
+            exit_status::exit(ExitStatus::Helper, "usage flushed");
+        }
+        Ok(None) => exit_status::exit(ExitStatus::Helper, "usage flush already running"),
+        Err(e) => exit_status::exit(ExitStatus::Helper, &e),
+    }
+}
+
+/// Report queued CLI invocations (runs in the detached helper process)
+fn flush_pending_usage(config: &Config) {
+    let store = StateStore::for_license(&config.license_id);
+    let pending = store.load().cli.pending_usage;
+    if pending == 0 {
+        return;
+    }
+
+    let mut event = UsageEvent::new(&config.license_id, "cli_invocation", "cached");
+    event.invocations = pending;
+
+    match report_usage(&config.get_server_url(), &config.shared_secret, &event) {
+        Ok(()) => {
+            // Under the lock: other invocations may have queued more in the meantime
+            let flushed = store.update(|state| {
+                state.cli.pending_usage = state.cli.pending_usage.saturating_sub(pending);
+            });
+            if let Err(e) = flushed {
+                log_warn!("⚠️  {}", e);
+            }
+        }
+        Err(e) => log_warn!("⚠️  Usage flush failed, will retry later: {}", e),
+    }
+}
+
+/// Lock file held by the running usage-flush helper (contains its PID)
+fn flush_pidfile(config: &Config) -> std::path::PathBuf {
+    state::state_dir().join(format!("{}.flush.pid", state::namespace_of(&config.license_id)))
+}
+
+/// Start a detached copy of ourselves that flushes queued usage
+///
+/// The loader only waits for our exit code, so the ping never delays the base.
+/// Nothing is started while a previous helper is still running.
+fn spawn_usage_flush(config: &Config) {
+    if let Ok(None) = secure_fs::try_lock_file(&flush_pidfile(config)) {
+        log_debug!("📮 Usage flush already running");
+        return;
+    }
+    let exe = match memexec::image_path() {
+        Ok(exe) => exe,
+        Err(e) => {
+            log_warn!("⚠️  Cannot spawn usage flush: {}", e);
+            return;
+        }
+    };
+    let token = match helper::issue(USAGE_FLUSH_JOB, &state::namespace_of(&config.license_id)) {
+        Ok(token) => token,
+        Err(e) => {
+            log_warn!("⚠️  Cannot spawn usage flush: {}", e);
+            return;
+        }
+    };
+
+    let result = Command::new(exe)
+        .env(USAGE_FLUSH_ENV, token)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+
+    if let Err(e) = result {
+        log_warn!("⚠️  Cannot spawn usage flush: {}", e);
+    }
+}
+
+fn issue_token(config: &Config, fingerprint: &str, expires_at: i64) -> CliToken {
+    CliToken {
+        expires_at,
+        machine_fingerprint: fingerprint.to_string(),
+        mac: create_signature(&token_data(&config.license_id, fingerprint, expires_at), &config.shared_secret),
+    }
+}
+
+fn token_is_valid(config: &Config, token: &CliToken, fingerprint: &str, now: i64) -> bool {
+    !time::is_expired(token.expires_at, now)
+        && token.machine_fingerprint == fingerprint
+        && verify_signature(
+            &token_data(&config.license_id, &token.machine_fingerprint, token.expires_at),
+            &config.shared_secret,
+            &token.mac,
+        )
+}
+
+fn token_data(license_id: &str, fingerprint: &str, expires_at: i64) -> String {
+    format!("cli-token|{}|{}|{}", license_id, fingerprint, expires_at)
+}
+
+fn save_state(store: &StateStore, state: &PersistentState) {
+    save_queued(store, state, 0);
+}
+
+/// Save `state` with `queued` more invocations pending, keeping the usage a
+/// concurrent flush helper already reported
+fn save_queued(store: &StateStore, state: &PersistentState, queued: u64) {
+    let saved = store.update(|current| {
+        let pending = current.cli.pending_usage + queued;
+        *current = state.clone();
+        current.cli.pending_usage = pending;
+    });
+    if let Err(e) = saved {
+        log_warn!("⚠️  {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> Config {
+        serde_json::from_str(r#"{
+            "license_id": "lic_cli",
+            "server_url": "http://localhost:8080",
+            "shared_secret": "secret123",
+            "cli_mode": true
+        }"#).unwrap()
+    }
+
+    #[test]
+    fn test_token_validation() {
+        let config = test_config();
+        let token = issue_token(&config, "fp", 2_000);
+
+        assert!(token_is_valid(&config, &token, "fp", 1_000));
+        // Expired
+        assert!(!token_is_valid(&config, &token, "fp", 2_000));
+        // Different machine
+        assert!(!token_is_valid(&config, &token, "other", 1_000));
+
+        // Locally extended expiry breaks the MAC
+        let mut forged = token.clone();
+        forged.expires_at = 9_999;
+        assert!(!token_is_valid(&config, &forged, "fp", 1_000));
+    }
+}
@@ -0,0 +1,184 @@
+//! Enforcement decisions shared by the overload loop and service mode
+//!
+//! Whether a verification result leads to a kill must not depend on how the
+//! overload runs. Both `main.rs` and `execution::service` take the decision
+//! here: deferral by the enforcement policy, a server maintenance pause, the
+//! license lease, the failure limit and the kill grace period. What happens
+//! around it (health reporting, summary lines, exit codes) stays with the
+//! caller.
+
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::security::clock::ClockGuard;
+use crate::security::policy;
+use crate::security::renewal::RenewalScheduler;
+use crate::utils::{self, redact};
+use crate::verification::{denial, events, fallback, pause, tamper, VerifyResponse};
+
+/// How often the license is re-checked during a kill grace period
+pub const KILL_GRACE_RECHECK: Duration = Duration::from_secs(10);
+
+/// What to do about a failed verification
+#[derive(Debug, PartialEq)]
+pub enum Decision {
+    /// Nothing to enforce yet: retry at the next check
+    Retry,
+    /// The enforcement policy defers enforcement
+    Deferred,
+    /// The server paused enforcement until this server time (unix seconds)
+    Paused(i64),
+    /// Enforce once `grace_ms` passed without an authorized re-check
+    Enforce { reason: String, grace_ms: u64 },
+}
+
+/// Decide on a verification the server answered with a denial
+///
+/// A denial that is enforced is recorded for the next start.
+pub fn on_denial(config: &Config, response: &VerifyResponse) -> Decision {
+    let decision = denial_decision(config, response);
+    if matches!(decision, Decision::Enforce { .. }) {
+        denial::record(config, response);
+    }
+    decision
+}
+
+/// `on_denial` without recording anything (`killer audit`)
+pub fn denial_decision(config: &Config, response: &VerifyResponse) -> Decision {
+    if policy::defers_enforcement(config, response) {
+        return Decision::Deferred;
+    }
+    if let Some(until) = pause::active(config) {
+        return Decision::Paused(until);
+    }
+    Decision::Enforce { reason: response.message.clone(), grace_ms: response.kill_grace_or(config.kill_grace_ms) }
+}
+
+/// Decide on a verification that could not complete
+///
+/// * `consecutive_failures` - Failed verifications in a row, this one included
+pub fn on_failure(
+    config: &Config,
+    error: &str,
+    consecutive_failures: u32,
+    renewal: &RenewalScheduler,
+    now: i64,
+) -> Decision {
+    let decision = failure_decision(config, error, consecutive_failures, renewal, now);
+    // Enforced without a lapsed lease: the failure limit was reached
+    if let Decision::Enforce { reason, .. } = &decision
+        && !renewal.expired(now)
+    {
+        events::record("failure_limit", reason);
+    }
+    decision
+}
+
+/// `on_failure` without recording anything (`killer audit`)
+pub fn failure_decision(
+    config: &Config,
+    error: &str,
+    consecutive_failures: u32,
+    renewal: &RenewalScheduler,
+    now: i64,
+) -> Decision {
+    if let Some(until) = pause::active(config) {
+        return Decision::Paused(until);
+    }
+    // A lapsed lease is no license: same as an unauthorized answer
+    if renewal.expired(now) {
+        return Decision::Enforce {
+            reason: "license lease expired without a successful renewal".to_string(),
+            grace_ms: config.kill_grace_ms,
+        };
+    }
+    // Without a wrapper nothing else bounds an outage - or a firewalled server
+    if fallback::failure_limit_reached(consecutive_failures, config.max_consecutive_failures) {
+        let reason = format!(
+            "{} consecutive verification failures (limit {}), last: {}",
+            consecutive_failures,
+            config.max_consecutive_failures,
+            redact::scrub(error)
+        );
+        return Decision::Enforce { reason, grace_ms: 0 };
+    }
+    Decision::Retry
+}
+
+/// Check an authorized answer against the local clock
+///
+/// An authorized answer is worthless if the local clock was rewound; that is
+/// reported as tamper.
+pub fn check_clock(config: &Config, clock_guard: &mut ClockGuard, response: &VerifyResponse) -> Result<(), String> {
+    let trusted_server_time = response.server_time.filter(|_| response.signature_valid);
+    clock_guard
+        .check(utils::time::unix_now(), Instant::now(), trusted_server_time)
+        .inspect_err(|reason| {
+            tamper::report_tamper(&config.get_server_url(), &config.license_id, &config.shared_secret, "clock", reason);
+        })
+}
+
+/// Wait out a kill grace period, re-checking the license meanwhile
+///
+/// * `keep_waiting` - Called every `KILL_GRACE_RECHECK`; false ends the wait
+/// * `recheck` - Verifies again; true if the license is authorized
+///
+/// # Returns
+/// true if a re-check authorized the license before the grace period ran out
+pub fn rescued_during_grace(
+    message: &str,
+    grace_ms: u64,
+    mut keep_waiting: impl FnMut() -> bool,
+    mut recheck: impl FnMut() -> bool,
+) -> bool {
+    let deadline = Instant::now().checked_add(Duration::from_millis(grace_ms));
+    let remaining = || deadline.map_or(Duration::MAX, |deadline| deadline.saturating_duration_since(Instant::now()));
+
+    log_warn!(
+        "⚠️  WARNING: license check failed ({}). This application will be terminated in {}s - save your work now.",
+        message,
+        grace_ms.div_ceil(1000)
+    );
+    loop {
+        if remaining().is_zero() {
+            return false;
+        }
+        std::thread::sleep(remaining().min(KILL_GRACE_RECHECK));
+        if !keep_waiting() {
+            return false;
+        }
+        if recheck() {
+            log_info!("✅ License re-check succeeded - kill cancelled");
+            return true;
+        }
+        log_info!("⏳ Still unauthorized - terminating in {}s", remaining().as_secs());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failure_decisions() {
+        let config: Config = serde_json::from_str(
+            r#"{"license_id": "lic_enforce", "server_url": "https://a.example", "shared_secret": "s",
+                "max_consecutive_failures": 3, "kill_grace_ms": 5000}"#,
+        )
+        .unwrap();
+        let renewal = RenewalScheduler::new(0);
+        assert_eq!(on_failure(&config, "timeout", 2, &renewal, 1_000), Decision::Retry);
+        assert!(matches!(on_failure(&config, "timeout", 3, &renewal, 1_000), Decision::Enforce { grace_ms: 0, .. }));
+
+        let mut lapsed = RenewalScheduler::new(0);
+        lapsed.observe(&VerifyResponse { authorized: true, expires_in: Some(10), ..Default::default() }, 1_000);
+        assert!(matches!(on_failure(&config, "timeout", 1, &lapsed, 2_000), Decision::Enforce { grace_ms: 5000, .. }));
+    }
+
+    #[test]
+    fn test_grace_ends_on_rescue_or_abort() {
+        assert!(rescued_during_grace("denied", 1, || true, || true));
+        assert!(!rescued_during_grace("denied", 1, || false, || true));
+        assert!(!rescued_during_grace("denied", 0, || true, || true));
+    }
+}
@@ -1,6 +1,12 @@
 /// Execution module - Handle sync and async execution modes
 pub mod sync;
 pub mod async_mode;
+pub mod r#async;
+pub mod cli;
+pub mod audit;
+pub mod simulate;
+pub mod service;
+pub mod enforcement;
 
 // Re-export for convenience
 pub use sync::execute_sync;
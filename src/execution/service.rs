@@ -0,0 +1,743 @@
+//! Service mode (`killer service run`, or `killer --service`)
+//!
+//! Instead of running as the child of the app it protects, the overload runs
+//! as a long-lived system service and watches the app by binary path
+//! (`service.target_path`: every process running it) and/or PID file
+//! (`service.pid_file`). Each cycle verifies the license; on a denial, or
+//! once `max_consecutive_failures` is reached, the kill method runs against
+//! every target found. The service itself keeps running, so an app started
+//! again later is caught by the next cycle.
+//!
+//! Integration with the service manager:
+//! - systemd: `Type=notify`, readiness (`READY=1`), status lines, watchdog
+//!   pings (`WATCHDOG=1`) and `STOPPING=1` over `NOTIFY_SOCKET`
+//! - launchd: a `KeepAlive` daemon; no protocol beyond staying alive
+//! - Windows: runs under the service control manager (SCM), reporting its
+//!   state and stopping on STOP/SHUTDOWN; started from a console it runs in
+//!   the foreground instead
+//!
+//! `killer service install|uninstall` registers the service with the
+//! platform's manager (`--print` shows the unit file instead).
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use super::enforcement::{self, Decision};
+use crate::config::{Config, ServiceConfig};
+use crate::security::clock::ClockGuard;
+use crate::security::renewal::RenewalScheduler;
+use crate::security::{capabilities, kill_parent, memexec};
+use crate::utils::process::process_table;
+use crate::utils::state::StateStore;
+use crate::utils::{self, shutdown};
+use crate::verification::{self, denial, fallback, pause, patch};
+
+/// Service name used when none is given
+pub const DEFAULT_SERVICE_NAME: &str = "kc-killer";
+
+/// Check interval when the config has none (`check_interval_ms` = 0)
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Longest uninterrupted sleep (stop requests and watchdog pings in between)
+const SLEEP_SLICE: Duration = Duration::from_secs(1);
+
+/// Set when the service manager asked us to stop
+static STOP: AtomicBool = AtomicBool::new(false);
+
+/// Run the service until stopped
+///
+/// # Returns
+/// Exit code
+pub fn run(config: &Config) -> i32 {
+    let Some(service) = config.service.clone() else {
+        log_error!("❌ Service mode needs a \"service\" section (target_path and/or pid_file)");
+        return 1;
+    };
+
+    utils::logger::configure(config);
+    utils::redact::configure(config);
+    utils::state::set_namespace(&config.license_id);
+    verification::webhook::configure(config);
+    crate::security::secrets::harden_process();
+
+    #[cfg(windows)]
+    if let Some(code) = scm::dispatch(config, &service) {
+        return code;
+    }
+
+    stop_on_signal();
+    run_loop(config, &service)
+}
+
+/// Check and enforce until `STOP` is set
+fn run_loop(config: &Config, service: &ServiceConfig) -> i32 {
+    let notifier = Notifier::from_env();
+    let kill_method = capabilities::resolve_kill_method(&config.kill_method, "config");
+    let mut config = Config { kill_method, ..config.clone() };
+    // Targets are found by path: that path is what confirms their identity
+    if config.expected_parent_path.is_none()
+        && let Some(target) = &service.target_path
+    {
+        config.expected_parent_path = Some(canonical(target).to_string_lossy().into_owned());
+    }
+
+    log_info!(
+        "🛡️  Service mode: watching {}",
+        [service.target_path.as_deref(), service.pid_file.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    notifier.notify("READY=1\nSTATUS=Watching the protected app");
+
+    if let Some(cached) = denial::cached(&config) {
+        log_error!("⛔ Denied {}s ago ({}) - enforcing without re-verification", utils::time::unix_now() - cached.denied_at, cached.message);
+        enforce(&config, service);
+    }
+
+    let state_store = StateStore::for_license(&config.license_id);
+    let mut clock_guard = ClockGuard::new(config.max_clock_drift_secs, state_store.load().clock.high_water);
+    let mut renewal = RenewalScheduler::new(config.renewal_lead_secs);
+    let mut first_check = true;
+    let mut consecutive_failures: u32 = 0;
+    while !STOP.load(Ordering::Relaxed) {
+        log_info!("🔍 Verifying license...");
+        let decision = match fallback::verify(&config, first_check) {
+            Ok(response) if response.authorized => {
+                consecutive_failures = 0;
+                match enforcement::check_clock(&config, &mut clock_guard, &response) {
+                    Ok(()) => {
+                        let mut state = state_store.load();
+                        if state.clock.high_water != clock_guard.high_water() {
+                            state.clock.high_water = clock_guard.high_water();
+                            if let Err(e) = state_store.save(&state) {
+                                log_warn!("⚠️  {}", e);
+                            }
+                        }
+                        renewal.observe(&response, utils::time::unix_now());
+                        denial::clear(&config);
+                        log_info!("✅ License verified successfully");
+                        notifier.notify("STATUS=License verified");
+                        // Signed patches only, validated like the overload loop's
+                        for change in patch::apply(&mut config, &response, |method| {
+                            capabilities::resolve_kill_method(method, "server")
+                        }) {
+                            log_info!("🔄 Runtime patch: {} {} → {}", change.field, change.from, change.to);
+                        }
+                        Decision::Retry
+                    }
+                    Err(reason) => {
+                        log_error!("🕰️  Clock tampering detected: {}", reason);
+                        Decision::Enforce { reason, grace_ms: 0 }
+                    }
+                }
+            }
+            Ok(response) => {
+                consecutive_failures = 0;
+                log_error!("❌ License verification failed - unauthorized access");
+                notifier.notify("STATUS=License denied");
+                enforcement::on_denial(&config, &response)
+            }
+            Err(e) => {
+                consecutive_failures += 1;
+                log_error!("❌ Verification error: {}", utils::redact::scrub(&e.message));
+                notifier.notify("STATUS=Verification failing, retrying");
+                enforcement::on_failure(&config, &e.message, consecutive_failures, &renewal, utils::time::unix_now())
+            }
+        };
+        match decision {
+            Decision::Retry => {}
+            Decision::Deferred => log_warn!("🧩 Enforcement deferred by enforcement_policy"),
+            Decision::Paused(_) => log_warn!("⏸️  Enforcement paused by the server"),
+            Decision::Enforce { reason, grace_ms } => {
+                let recheck = || matches!(fallback::verify(&config, false), Ok(response) if response.authorized);
+                let keep_waiting = || {
+                    notifier.ping_watchdog();
+                    !STOP.load(Ordering::Relaxed)
+                };
+                if grace_ms == 0 || !enforcement::rescued_during_grace(&reason, grace_ms, keep_waiting, recheck) {
+                    log_error!("🚨 {} - enforcing", reason);
+                    enforce(&config, service);
+                }
+            }
+        }
+        first_check = false;
+
+        let interval = match config.check_interval_ms {
+            0 => DEFAULT_INTERVAL,
+            ms => Duration::from_millis(ms),
+        };
+        let interval = Duration::from_millis(pause::check_interval_ms(&config, interval.as_millis() as u64));
+        wait(interval, &notifier);
+    }
+
+    log_info!("🛑 Service stopping");
+    notifier.notify("STOPPING=1");
+    0
+}
+
+/// Sleep until the next check, pinging the watchdog; returns early on stop
+fn wait(interval: Duration, notifier: &Notifier) {
+    let deadline = Instant::now() + interval;
+    loop {
+        notifier.ping_watchdog();
+        let now = Instant::now();
+        if now >= deadline || STOP.load(Ordering::Relaxed) {
+            return;
+        }
+        std::thread::sleep(SLEEP_SLICE.min(deadline - now));
+    }
+}
+
+/// Run the kill method against every running target
+fn enforce(config: &Config, service: &ServiceConfig) {
+    let targets = find_targets(service);
+    if targets.is_empty() {
+        log_info!("ℹ️  Protected app not running - nothing to enforce");
+        return;
+    }
+    for pid in targets {
+        if let Err(e) = kill_parent::kill_target(&config.kill_method, config, pid) {
+            log_error!("❌ Kill execution failed for PID {}: {}", pid, e);
+        }
+    }
+}
+
+/// Processes running the protected app
+///
+/// A PID file naming a process that does not run `target_path` (when set) is
+/// stale and ignored: the PID was reused by a stranger.
+pub fn find_targets(service: &ServiceConfig) -> Vec<u32> {
+    let own_pid = std::process::id();
+    let running: Vec<u32> = process_table().into_iter().map(|(pid, _)| pid).filter(|&pid| pid != own_pid).collect();
+    let target = service.target_path.as_deref().map(canonical);
+    let runs_target = |pid: u32| match &target {
+        Some(target) => kill_parent::get_parent_binary_path(pid).is_some_and(|path| same_binary(&path, target)),
+        None => true,
+    };
+
+    let mut targets = Vec::new();
+    if let Some(pid_file) = &service.pid_file
+        && let Ok(contents) = std::fs::read_to_string(pid_file)
+    {
+        match contents.trim().parse::<u32>() {
+            Ok(pid) if running.contains(&pid) && runs_target(pid) => targets.push(pid),
+            Ok(pid) if running.contains(&pid) => log_warn!("⚠️  {} names PID {}, which does not run the protected app", pid_file, pid),
+            Ok(_) => {}
+            Err(_) => log_warn!("⚠️  {} does not hold a PID", pid_file),
+        }
+    }
+    if target.is_some() {
+        for pid in running {
+            if !targets.contains(&pid) && runs_target(pid) {
+                targets.push(pid);
+            }
+        }
+    }
+    targets
+}
+
+fn canonical(path: &str) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path))
+}
+
+/// Whether a process image path is the target binary
+fn same_binary(image: &Path, target: &Path) -> bool {
+    // Linux marks an image replaced on disk with " (deleted)"
+    let image = image.to_string_lossy();
+    let image = image.strip_suffix(" (deleted)").unwrap_or(&image);
+    #[cfg(windows)]
+    {
+        image.eq_ignore_ascii_case(&target.to_string_lossy())
+    }
+    #[cfg(not(windows))]
+    {
+        Path::new(image) == target
+    }
+}
+
+/// Stop cleanly on SIGTERM/SIGINT/SIGHUP (systemd and launchd stop with
+/// SIGTERM): shutdown hooks run and the exit code is 0
+///
+/// Must run before other threads are started, which inherit the signal mask.
+fn stop_on_signal() {
+    // The SCM stops a Windows service itself (see `scm`)
+    #[cfg(unix)]
+    shutdown::on_termination(|termination| {
+        log_info!("🛑 Received {} - service stopping", termination.signal);
+        Notifier::from_env().notify("STOPPING=1");
+        shutdown::exit(0);
+    });
+}
+
+/// systemd notification socket (`sd_notify`); does nothing when not started
+/// by systemd
+struct Notifier {
+    #[cfg(unix)]
+    socket: Option<(std::os::unix::net::UnixDatagram, std::os::unix::net::SocketAddr)>,
+    /// Ping interval: half of `WatchdogSec`
+    watchdog: Option<Duration>,
+    last_ping: std::cell::Cell<Option<Instant>>,
+}
+
+impl Notifier {
+    fn from_env() -> Self {
+        // Only pings for us: WATCHDOG_PID names the process supervised
+        let own_watchdog = std::env::var("WATCHDOG_PID").map_or(true, |pid| pid == std::process::id().to_string());
+        let watchdog = std::env::var("WATCHDOG_USEC")
+            .ok()
+            .and_then(|usec| usec.parse::<u64>().ok())
+            .filter(|&usec| usec > 0 && own_watchdog)
+            .map(|usec| Duration::from_micros(usec / 2));
+
+        Self {
+            #[cfg(unix)]
+            socket: std::env::var_os("NOTIFY_SOCKET").and_then(|path| notify_socket(&path)),
+            watchdog,
+            last_ping: std::cell::Cell::new(None),
+        }
+    }
+
+    fn notify(&self, state: &str) {
+        #[cfg(unix)]
+        if let Some((socket, addr)) = &self.socket
+            && let Err(e) = socket.send_to_addr(state.as_bytes(), addr)
+        {
+            log_debug!("  Cannot notify the service manager: {}", e);
+        }
+        #[cfg(not(unix))]
+        let _ = state;
+    }
+
+    /// `WATCHDOG=1`, at most every half watchdog period
+    fn ping_watchdog(&self) {
+        let Some(period) = self.watchdog else {
+            return;
+        };
+        if self.last_ping.get().is_none_or(|last| last.elapsed() >= period) {
+            self.notify("WATCHDOG=1");
+            self.last_ping.set(Some(Instant::now()));
+        }
+    }
+}
+
+/// Datagram socket and address for `NOTIFY_SOCKET` (a path, or an abstract
+/// name starting with `@` on Linux)
+#[cfg(unix)]
+fn notify_socket(path: &std::ffi::OsStr) -> Option<(std::os::unix::net::UnixDatagram, std::os::unix::net::SocketAddr)> {
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+    let addr = match path.as_bytes().strip_prefix(b"@") {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            SocketAddr::from_abstract_name(name)
+        }
+        #[cfg(not(target_os = "linux"))]
+        Some(_) => return None,
+        None => SocketAddr::from_pathname(path),
+    };
+    match addr.and_then(|addr| Ok((UnixDatagram::unbound()?, addr))) {
+        Ok(socket) => Some(socket),
+        Err(e) => {
+            log_warn!("⚠️  Cannot use NOTIFY_SOCKET: {}", e);
+            None
+        }
+    }
+}
+
+/// systemd unit running `exe` as a notify service with a watchdog
+pub fn systemd_unit(exe: &Path, name: &str, watchdog_secs: u64) -> String {
+    format!(
+        "[Unit]\n\
+         Description=KillCode license enforcement ({name})\n\
+         After=network-online.target\n\
+         Wants=network-online.target\n\
+         \n\
+         [Service]\n\
+         Type=notify\n\
+         ExecStart=\"{exe}\" service run\n\
+         WatchdogSec={watchdog_secs}\n\
+         Restart=always\n\
+         RestartSec=5\n\
+         \n\
+         [Install]\n\
+         WantedBy=multi-user.target\n",
+        exe = exe.display()
+    )
+}
+
+/// launchd property list keeping `exe` running as a daemon
+pub fn launchd_plist(exe: &Path, label: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n\
+         <dict>\n\
+         \t<key>Label</key>\n\
+         \t<string>{label}</string>\n\
+         \t<key>ProgramArguments</key>\n\
+         \t<array>\n\
+         \t\t<string>{exe}</string>\n\
+         \t\t<string>service</string>\n\
+         \t\t<string>run</string>\n\
+         \t</array>\n\
+         \t<key>RunAtLoad</key>\n\
+         \t<true/>\n\
+         \t<key>KeepAlive</key>\n\
+         \t<true/>\n\
+         </dict>\n\
+         </plist>\n",
+        exe = exe.display()
+    )
+}
+
+/// Watchdog period for the unit: a check (up to its HTTP timeouts) must fit
+/// between two pings, which only happen while waiting for the next check
+pub fn watchdog_secs(config: &Config) -> u64 {
+    let interval_secs = match config.check_interval_ms {
+        0 => DEFAULT_INTERVAL.as_secs(),
+        ms => ms.div_ceil(1000),
+    };
+    (interval_secs * 2).max(120)
+}
+
+/// Reject service names that are not `[A-Za-z0-9_.-]+`: the name becomes a
+/// unit file path and a service manager argument
+pub fn check_service_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b'-'))
+        && !name.starts_with('.');
+    match valid {
+        true => Ok(()),
+        false => Err(format!("Invalid service name {:?}: use letters, digits, '_', '.' and '-'", name)),
+    }
+}
+
+/// Register the service with the platform's service manager and start it
+///
+/// With `print` the unit (or command) is only printed.
+pub fn install(config: &Config, name: &str, print: bool) -> Result<(), String> {
+    check_service_name(name)?;
+    let exe = memexec::executable_path().map_err(|e| format!("Cannot locate the overload binary: {}", e))?;
+
+    #[cfg(target_os = "linux")]
+    {
+        let unit = systemd_unit(&exe, name, watchdog_secs(config));
+        if print {
+            print!("{}", unit);
+            return Ok(());
+        }
+        let path = format!("/etc/systemd/system/{}.service", name);
+        std::fs::write(&path, unit).map_err(|e| format!("Cannot write {}: {}", path, e))?;
+        run_tool("systemctl", &["daemon-reload"])?;
+        run_tool("systemctl", &["enable", "--now", &format!("{}.service", name)])?;
+        log_info!("✅ Installed and started {}", path);
+        Ok(())
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let _ = config;
+        let plist = launchd_plist(&exe, name);
+        if print {
+            print!("{}", plist);
+            return Ok(());
+        }
+        let path = format!("/Library/LaunchDaemons/{}.plist", name);
+        std::fs::write(&path, plist).map_err(|e| format!("Cannot write {}: {}", path, e))?;
+        run_tool("launchctl", &["load", "-w", &path])?;
+        log_info!("✅ Installed and loaded {}", path);
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = config;
+        if print {
+            println!("sc.exe create {} binPath= \"\\\"{}\\\" service run\" start= auto", name, exe.display());
+            return Ok(());
+        }
+        scm::install(&exe, name)?;
+        log_info!("✅ Installed and started service {}", name);
+        Ok(())
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+    {
+        let _ = (config, exe, name, print);
+        Err("No supported service manager on this platform".to_string())
+    }
+}
+
+/// Stop the service and remove it from the platform's service manager
+pub fn uninstall(name: &str) -> Result<(), String> {
+    check_service_name(name)?;
+    #[cfg(target_os = "linux")]
+    {
+        let path = format!("/etc/systemd/system/{}.service", name);
+        run_tool("systemctl", &["disable", "--now", &format!("{}.service", name)])?;
+        std::fs::remove_file(&path).map_err(|e| format!("Cannot remove {}: {}", path, e))?;
+        run_tool("systemctl", &["daemon-reload"])?;
+        log_info!("✅ Removed {}", path);
+        Ok(())
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let path = format!("/Library/LaunchDaemons/{}.plist", name);
+        run_tool("launchctl", &["unload", "-w", &path])?;
+        std::fs::remove_file(&path).map_err(|e| format!("Cannot remove {}: {}", path, e))?;
+        log_info!("✅ Removed {}", path);
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    {
+        scm::uninstall(name)?;
+        log_info!("✅ Removed service {}", name);
+        Ok(())
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+    {
+        let _ = name;
+        Err("No supported service manager on this platform".to_string())
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn run_tool(tool: &str, args: &[&str]) -> Result<(), String> {
+    let status = std::process::Command::new(tool)
+        .args(args)
+        .status()
+        .map_err(|e| format!("Cannot run {}: {}", tool, e))?;
+    if !status.success() {
+        return Err(format!("{} {} failed ({})", tool, args.join(" "), status));
+    }
+    Ok(())
+}
+
+/// Windows service control manager
+#[cfg(windows)]
+mod scm {
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+    use std::ptr;
+    use std::sync::atomic::Ordering;
+    use std::sync::OnceLock;
+
+    use winapi::shared::minwindef::{DWORD, LPVOID};
+    use winapi::shared::winerror::{
+        ERROR_CALL_NOT_IMPLEMENTED, ERROR_FAILED_SERVICE_CONTROLLER_CONNECT, ERROR_SERVICE_SPECIFIC_ERROR, NO_ERROR,
+    };
+    use winapi::um::errhandlingapi::GetLastError;
+    use winapi::um::winnt::{DELETE, LPWSTR, SERVICE_AUTO_START, SERVICE_ERROR_NORMAL, SERVICE_WIN32_OWN_PROCESS};
+    use winapi::um::winsvc::*;
+
+    use super::STOP;
+    use crate::config::{Config, ServiceConfig};
+
+    /// What `service_main` runs: set before the dispatcher starts
+    static SERVICE: OnceLock<(Config, ServiceConfig)> = OnceLock::new();
+    static EXIT_CODE: std::sync::atomic::AtomicI32 = std::sync::atomic::AtomicI32::new(0);
+
+    struct StatusHandle(SERVICE_STATUS_HANDLE);
+    // Only passed to SetServiceStatus, which may be called from any thread
+    unsafe impl Send for StatusHandle {}
+    unsafe impl Sync for StatusHandle {}
+    static STATUS: OnceLock<StatusHandle> = OnceLock::new();
+
+    fn wide(text: &str) -> Vec<u16> {
+        std::ffi::OsStr::new(text).encode_wide().chain(Some(0)).collect()
+    }
+
+    /// Run under the SCM if it started us
+    ///
+    /// # Returns
+    /// The exit code once the service stopped, None when started from a
+    /// console (run in the foreground instead)
+    pub fn dispatch(config: &Config, service: &ServiceConfig) -> Option<i32> {
+        let _ = SERVICE.set((config.clone(), service.clone()));
+        // Own-process services may leave the name empty
+        let mut name = wide("");
+        let table = [
+            SERVICE_TABLE_ENTRYW { lpServiceName: name.as_mut_ptr(), lpServiceProc: Some(service_main) },
+            SERVICE_TABLE_ENTRYW { lpServiceName: ptr::null(), lpServiceProc: None },
+        ];
+        if unsafe { StartServiceCtrlDispatcherW(table.as_ptr()) } != 0 {
+            return Some(EXIT_CODE.load(Ordering::Relaxed));
+        }
+        match unsafe { GetLastError() } {
+            ERROR_FAILED_SERVICE_CONTROLLER_CONNECT => None,
+            code => {
+                log_error!("❌ Service dispatcher failed: {}", std::io::Error::from_raw_os_error(code as i32));
+                Some(1)
+            }
+        }
+    }
+
+    fn set_state(state: DWORD, exit_code: i32) {
+        let Some(handle) = STATUS.get() else {
+            return;
+        };
+        let mut status = SERVICE_STATUS {
+            dwServiceType: SERVICE_WIN32_OWN_PROCESS,
+            dwCurrentState: state,
+            dwControlsAccepted: if state == SERVICE_RUNNING { SERVICE_ACCEPT_STOP | SERVICE_ACCEPT_SHUTDOWN } else { 0 },
+            dwWin32ExitCode: if exit_code == 0 { NO_ERROR } else { ERROR_SERVICE_SPECIFIC_ERROR },
+            dwServiceSpecificExitCode: exit_code as DWORD,
+            dwCheckPoint: 0,
+            dwWaitHint: if state == SERVICE_STOP_PENDING { 30_000 } else { 0 },
+        };
+        unsafe { SetServiceStatus(handle.0, &mut status) };
+    }
+
+    unsafe extern "system" fn service_main(argc: DWORD, argv: *mut LPWSTR) {
+        let name = if argc > 0 { unsafe { *argv } } else { ptr::null_mut() };
+        let handle = unsafe { RegisterServiceCtrlHandlerExW(name, Some(on_control), ptr::null_mut()) };
+        if handle.is_null() {
+            log_error!("❌ Cannot register the service control handler: {}", std::io::Error::last_os_error());
+            EXIT_CODE.store(1, Ordering::Relaxed);
+            return;
+        }
+        let _ = STATUS.set(StatusHandle(handle));
+        set_state(SERVICE_RUNNING, 0);
+
+        let code = match SERVICE.get() {
+            Some((config, service)) => super::run_loop(config, service),
+            None => 1,
+        };
+        EXIT_CODE.store(code, Ordering::Relaxed);
+        set_state(SERVICE_STOPPED, code);
+    }
+
+    unsafe extern "system" fn on_control(control: DWORD, _event: DWORD, _data: LPVOID, _context: LPVOID) -> DWORD {
+        match control {
+            SERVICE_CONTROL_STOP | SERVICE_CONTROL_SHUTDOWN => {
+                log_info!("🛑 Service control manager requested stop");
+                set_state(SERVICE_STOP_PENDING, 0);
+                STOP.store(true, Ordering::Relaxed);
+                NO_ERROR
+            }
+            SERVICE_CONTROL_INTERROGATE => NO_ERROR,
+            _ => ERROR_CALL_NOT_IMPLEMENTED,
+        }
+    }
+
+    /// Open the SCM or a service, closing the handle when dropped
+    struct Handle(SC_HANDLE);
+
+    impl Drop for Handle {
+        fn drop(&mut self) {
+            unsafe { CloseServiceHandle(self.0) };
+        }
+    }
+
+    fn open_manager(access: DWORD) -> Result<Handle, String> {
+        let manager = unsafe { OpenSCManagerW(ptr::null(), ptr::null(), access) };
+        if manager.is_null() {
+            return Err(format!("Cannot open the service control manager: {}", std::io::Error::last_os_error()));
+        }
+        Ok(Handle(manager))
+    }
+
+    pub fn install(exe: &Path, name: &str) -> Result<(), String> {
+        let manager = open_manager(SC_MANAGER_CREATE_SERVICE)?;
+        let command = wide(&format!("\"{}\" service run", exe.display()));
+        let name_w = wide(name);
+        let display = wide(&format!("KillCode license enforcement ({})", name));
+        let service = unsafe {
+            CreateServiceW(
+                manager.0,
+                name_w.as_ptr(),
+                display.as_ptr(),
+                SERVICE_ALL_ACCESS,
+                SERVICE_WIN32_OWN_PROCESS,
+                SERVICE_AUTO_START,
+                SERVICE_ERROR_NORMAL,
+                command.as_ptr(),
+                ptr::null(),
+                ptr::null_mut(),
+                ptr::null(),
+                ptr::null(),
+                ptr::null(),
+            )
+        };
+        if service.is_null() {
+            return Err(format!("Cannot create service {}: {}", name, std::io::Error::last_os_error()));
+        }
+        let service = Handle(service);
+        if unsafe { StartServiceW(service.0, 0, ptr::null_mut()) } == 0 {
+            return Err(format!("Service {} created but not started: {}", name, std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    pub fn uninstall(name: &str) -> Result<(), String> {
+        let manager = open_manager(SC_MANAGER_CONNECT)?;
+        let name_w = wide(name);
+        let service = unsafe { OpenServiceW(manager.0, name_w.as_ptr(), SERVICE_STOP | DELETE) };
+        if service.is_null() {
+            return Err(format!("Cannot open service {}: {}", name, std::io::Error::last_os_error()));
+        }
+        let service = Handle(service);
+        let mut status: SERVICE_STATUS = unsafe { std::mem::zeroed() };
+        // Not running is fine
+        unsafe { ControlService(service.0, SERVICE_CONTROL_STOP, &mut status) };
+        if unsafe { DeleteService(service.0) } == 0 {
+            return Err(format!("Cannot delete service {}: {}", name, std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_files_run_the_service() {
+        let exe = Path::new("/opt/kc/killer");
+        let unit = systemd_unit(exe, "kc-killer", 120);
+        assert!(unit.contains("Type=notify"));
+        assert!(unit.contains("ExecStart=\"/opt/kc/killer\" service run"));
+        assert!(unit.contains("WatchdogSec=120"));
+
+        let plist = launchd_plist(exe, "io.killcode.killer");
+        assert!(plist.contains("<string>io.killcode.killer</string>"));
+        assert!(plist.contains("<string>/opt/kc/killer</string>\n\t\t<string>service</string>\n\t\t<string>run</string>"));
+        assert!(plist.contains("<key>KeepAlive</key>"));
+
+        assert!(check_service_name("io.killcode.killer").is_ok());
+        assert!(check_service_name("kc_killer-2").is_ok());
+        assert!(check_service_name("../../etc/evil").is_err());
+        assert!(check_service_name("a b").is_err());
+        assert!(check_service_name("").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_targets_by_path_and_pid_file() {
+        let exe = std::env::current_exe().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("app.pid");
+
+        // Our own process is never a target
+        let by_path = ServiceConfig { target_path: Some(exe.to_string_lossy().into_owned()), pid_file: None };
+        assert!(!find_targets(&by_path).contains(&std::process::id()));
+
+        // A PID file naming a process that does not run target_path is stale
+        let mut child = std::process::Command::new("sleep").arg("5").spawn().unwrap();
+        std::fs::write(&pid_file, format!("{}\n", child.id())).unwrap();
+        let by_pid = ServiceConfig { target_path: None, pid_file: Some(pid_file.to_string_lossy().into_owned()) };
+        assert_eq!(find_targets(&by_pid), [child.id()]);
+        let both = ServiceConfig { pid_file: by_pid.pid_file.clone(), ..by_path };
+        assert!(!find_targets(&both).contains(&child.id()));
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
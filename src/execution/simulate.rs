@@ -0,0 +1,460 @@
+//! Offline replay of recorded field timelines
+//!
+//! Incident reports ("it killed us at 03:12") are hard to reproduce live: the
+//! outcome depends on when checks completed, how long they took and what the
+//! server answered. A timeline records exactly that, one JSON object per line
+//! (blank lines and `#` comments are skipped):
+//!
+//! ```text
+//! {"time":"2026-03-02T03:00:00+01:00","event":"check","latency_ms":180,"response":{"authorized":true,"message":"ok","expires_in":3600}}
+//! {"time":"2026-03-02T03:10:00+01:00","event":"error","latency_ms":10000,"message":"timeout"}
+//! {"time":"2026-03-02T03:11:30+01:00","event":"check","response":{"authorized":false,"message":"revoked","kill_grace_ms":30000}}
+//! {"time":"2026-03-02T03:12:00+01:00","event":"expect","state":"killed"}
+//! ```
+//!
+//! `killer simulate` replays it against the decisions the verification loop
+//! makes - runtime patches, early renewal, fallback switching,
+//! `enforcement_policy` evaluated at the recorded time and its deferral limit, kill grace
+//! periods and rescues, maintenance pauses, wrapper kill requests - without network access or
+//! enforcement, and prints each decision. `expect` lines assert the state at
+//! their time, so a reproduced incident turns into a regression test.
+//!
+//! Checks slower than the request timeout are replayed as network errors,
+//! like the client would have seen them. Security checks, clock tampering and
+//! the denial cache are not modelled.
+
+use chrono::{DateTime, FixedOffset};
+use serde::Deserialize;
+
+use crate::config::Config;
+use crate::security::policy;
+use crate::security::renewal::RenewalScheduler;
+use crate::verification::fallback::{failure_limit_reached, fallback_due};
+use crate::verification::network::REQUEST_TIMEOUT;
+use crate::verification::patch;
+use crate::verification::pause::{paused_interval_ms, MAX_PAUSE_SECS};
+use crate::verification::VerifyResponse;
+
+/// Simulated state of the overload
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum State {
+    /// Verifying on schedule
+    Running,
+    /// Unauthorized, waiting out the kill grace period
+    Grace,
+    /// The kill method was executed
+    Killed,
+    /// Single-check mode finished
+    Exited,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event {
+    /// A verification answered
+    Check {
+        response: Box<VerifyResponse>,
+        #[serde(default)]
+        latency_ms: u64,
+        /// Whether the response carried a valid signature (recorded ones do)
+        #[serde(default = "default_signed")]
+        signed: bool,
+    },
+    /// A verification failed without an answer
+    Error {
+        #[serde(default)]
+        message: String,
+        #[serde(default)]
+        latency_ms: u64,
+    },
+    /// The wrapper asked for the kill
+    KillRequest,
+    /// Assert the state at this time
+    Expect { state: State },
+}
+
+fn default_signed() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+struct Entry {
+    time: String,
+    #[serde(flatten)]
+    event: Event,
+}
+
+/// One decision of the replay
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub time: DateTime<FixedOffset>,
+    pub state: State,
+    pub note: String,
+}
+
+/// Result of a replay
+#[derive(Debug, Default)]
+pub struct Replay {
+    pub steps: Vec<Step>,
+    /// `expect` lines that did not hold
+    pub failures: Vec<String>,
+}
+
+struct Machine {
+    config: Config,
+    state: State,
+    kill_at: Option<DateTime<FixedOffset>>,
+    failures: u32,
+    renewal: RenewalScheduler,
+    /// End of the maintenance pause (unix seconds)
+    paused_until: Option<i64>,
+    /// First denial deferred by enforcement_policy since the last authorized
+    /// result (unix seconds)
+    deferred_since: Option<i64>,
+    replay: Replay,
+}
+
+/// Replay a timeline against `config`
+pub fn replay(config: &Config, timeline: &str) -> Result<Replay, String> {
+    let mut machine = Machine {
+        config: config.clone(),
+        state: State::Running,
+        kill_at: None,
+        failures: 0,
+        renewal: RenewalScheduler::new(config.renewal_lead_secs),
+        paused_until: None,
+        deferred_since: None,
+        replay: Replay::default(),
+    };
+
+    let mut previous: Option<DateTime<FixedOffset>> = None;
+    for (index, line) in timeline.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let entry: Entry = serde_json::from_str(line).map_err(|e| format!("line {}: {}", index + 1, e))?;
+        let time = DateTime::parse_from_rfc3339(&entry.time)
+            .map_err(|e| format!("line {}: invalid time {}: {}", index + 1, entry.time, e))?;
+        if previous.is_some_and(|previous| time < previous) {
+            return Err(format!("line {}: time goes backwards", index + 1));
+        }
+        previous = Some(time);
+
+        machine.advance(time);
+        machine.handle(time, entry.event);
+    }
+
+    if let Some(kill_at) = machine.kill_at.filter(|_| machine.state == State::Grace) {
+        machine.advance(kill_at);
+    }
+    Ok(machine.replay)
+}
+
+impl Machine {
+    fn step(&mut self, time: DateTime<FixedOffset>, state: State, note: String) {
+        self.state = state;
+        self.replay.steps.push(Step { time, state, note });
+    }
+
+    /// Execute a grace-period kill that fell due before `time`
+    fn advance(&mut self, time: DateTime<FixedOffset>) {
+        if self.state == State::Grace
+            && let Some(kill_at) = self.kill_at
+            && kill_at <= time
+        {
+            let note = format!("grace period over - kill ({})", self.config.kill_method.as_str());
+            self.step(kill_at, State::Killed, note);
+        }
+    }
+
+    fn handle(&mut self, time: DateTime<FixedOffset>, event: Event) {
+        if let Event::Expect { state } = event {
+            if state != self.state {
+                self.replay.failures.push(format!(
+                    "{}: expected {:?}, state is {:?}",
+                    format_time(&time),
+                    state,
+                    self.state
+                ));
+            }
+            return;
+        }
+        if matches!(self.state, State::Killed | State::Exited) {
+            return;
+        }
+
+        match event {
+            Event::Check { latency_ms, .. } | Event::Error { latency_ms, .. }
+                if latency_ms > REQUEST_TIMEOUT.as_millis() as u64 =>
+            {
+                self.on_error(time, &format!("timed out after {}ms", REQUEST_TIMEOUT.as_millis()));
+            }
+            Event::Check { mut response, signed, .. } => {
+                response.signature_valid = signed;
+                if signed {
+                    let now = time.timestamp();
+                    self.paused_until = response.paused_until.map(|until| until.min(now + MAX_PAUSE_SECS));
+                }
+                if response.authorized {
+                    self.on_authorized(time, &response);
+                } else {
+                    self.on_unauthorized(time, &response);
+                }
+            }
+            Event::Error { message, .. } => self.on_error(time, &message),
+            Event::KillRequest => {
+                let note = format!("wrapper requested kill ({})", self.config.kill_method.as_str());
+                self.step(time, State::Killed, note);
+            }
+            Event::Expect { .. } => {}
+        }
+    }
+
+    fn on_authorized(&mut self, time: DateTime<FixedOffset>, response: &VerifyResponse) {
+        self.failures = 0;
+        self.deferred_since = None;
+        if self.state == State::Grace {
+            self.kill_at = None;
+            self.step(time, State::Running, "re-check authorized - kill cancelled".to_string());
+            return;
+        }
+
+        let mut notes = vec!["authorized".to_string()];
+        for change in patch::apply(&mut self.config, response, |method| method.clone()) {
+            notes.push(format!("{} patched to {}", change.field, change.to));
+        }
+
+        if self.config.check_interval_ms == 0 {
+            notes.push("single check - exit 0".to_string());
+            self.step(time, State::Exited, notes.join(", "));
+            return;
+        }
+
+        self.renewal.observe(response, time.timestamp());
+        notes.push(self.next_check(time));
+        self.step(time, State::Running, notes.join(", "));
+    }
+
+    fn on_unauthorized(&mut self, time: DateTime<FixedOffset>, response: &VerifyResponse) {
+        self.failures = 0;
+        if self.state == State::Grace {
+            let kill_at = self.kill_at.map(|at| format_time(&at)).unwrap_or_default();
+            self.step(time, State::Grace, format!("re-check still unauthorized - kill at {}", kill_at));
+            return;
+        }
+
+        if policy::defers_enforcement_at(&self.config, response, &time)
+            && !policy::deferral_exhausted(*self.deferred_since.get_or_insert(time.timestamp()), time.timestamp())
+        {
+            let note = format!("unauthorized ({}), deferred by enforcement_policy", response.message);
+            if self.config.check_interval_ms == 0 {
+                self.step(time, State::Exited, format!("{} - exit 1", note));
+            } else {
+                let next = self.next_check(time);
+                self.step(time, State::Running, format!("{}, {}", note, next));
+            }
+            return;
+        }
+
+        if self.paused(time) {
+            let note = format!("unauthorized ({}), enforcement paused by the server, {}", response.message, self.next_check(time));
+            self.step(time, State::Running, note);
+            return;
+        }
+
+        let grace_ms = response.kill_grace_or(self.config.kill_grace_ms);
+        self.enforce(time, &format!("unauthorized ({})", response.message), grace_ms);
+    }
+
+    /// Kill now, or after `grace_ms`
+    fn enforce(&mut self, time: DateTime<FixedOffset>, reason: &str, grace_ms: u64) {
+        if grace_ms == 0 {
+            let note = format!("{} - kill ({})", reason, self.config.kill_method.as_str());
+            self.step(time, State::Killed, note);
+            return;
+        }
+
+        // Like the overload, a grace beyond what the clock can represent never ends
+        let kill_at = chrono::Duration::try_milliseconds(i64::try_from(grace_ms).unwrap_or(i64::MAX))
+            .and_then(|grace| time.checked_add_signed(grace));
+        let Some(kill_at) = kill_at else {
+            self.kill_at = None;
+            self.step(time, State::Grace, format!("{} - grace period without end", reason));
+            return;
+        };
+        self.kill_at = Some(kill_at);
+        let note = format!("{} - grace period, kill at {}", reason, format_time(&kill_at));
+        self.step(time, State::Grace, note);
+    }
+
+    fn on_error(&mut self, time: DateTime<FixedOffset>, message: &str) {
+        self.failures += 1;
+        if self.state == State::Grace {
+            self.step(time, State::Grace, format!("re-check failed ({})", message));
+            return;
+        }
+
+        if self.paused(time) {
+            let note = format!("network error ({}), enforcement paused by the server, {}", message, self.next_check(time));
+            self.step(time, State::Running, note);
+            return;
+        }
+
+        if self.renewal.expired(time.timestamp()) {
+            let reason = format!("network error ({}), lease expired", message);
+            self.enforce(time, &reason, self.config.kill_grace_ms);
+            return;
+        }
+
+        let mut note = format!("network error ({}), {} in a row", message, self.failures);
+        if failure_limit_reached(self.failures, self.config.max_consecutive_failures) {
+            note.push_str(&format!(" - failure limit reached, kill ({})", self.config.kill_method.as_str()));
+            self.step(time, State::Killed, note);
+            return;
+        }
+        if self.config.check_interval_ms == 0 {
+            note.push_str(" - exit 1");
+            self.step(time, State::Exited, note);
+            return;
+        }
+        if self.config.fallback_server_url.is_some() && fallback_due(self.failures, self.config.fallback_after_failures) {
+            note.push_str(", next check tries the fallback endpoint");
+        }
+        note.push_str(&format!(", {}", self.next_check(time)));
+        self.step(time, State::Running, note);
+    }
+
+    fn paused(&self, time: DateTime<FixedOffset>) -> bool {
+        self.paused_until.is_some_and(|until| time.timestamp() < until)
+    }
+
+    /// When the loop would verify next (jitter-free)
+    fn next_check(&self, time: DateTime<FixedOffset>) -> String {
+        let interval = match self.paused(time) {
+            true => paused_interval_ms(self.config.check_interval_ms),
+            false => self.config.check_interval_ms,
+        };
+        let wait = self.renewal.wait_with_jitter(interval, time.timestamp(), 0.0);
+        let next = format_time(&(time + chrono::Duration::milliseconds(wait as i64)));
+        if wait < interval {
+            format!("early renewal at {}", next)
+        } else {
+            format!("next check at {}", next)
+        }
+    }
+}
+
+pub fn format_time(time: &DateTime<FixedOffset>) -> String {
+    time.format("%Y-%m-%d %H:%M:%S%:z").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(extra: &str) -> Config {
+        serde_json::from_str(&format!(
+            r#"{{"license_id":"lic_sim","server_url":"https://api.example.com","shared_secret":"s",
+                "execution_mode":"sync","check_interval_ms":600000{}}}"#,
+            extra
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_grace_kill_at_recorded_time() {
+        // The incident: a denial at 03:11:30 with 30s grace, a failed re-check
+        let timeline = r#"
+            # customer report: killed at 03:12
+            {"time":"2026-03-02T03:00:00+01:00","event":"check","latency_ms":180,"response":{"authorized":true,"message":"ok"}}
+            {"time":"2026-03-02T03:10:00+01:00","event":"error","latency_ms":12000,"message":"slow"}
+            {"time":"2026-03-02T03:11:30+01:00","event":"check","response":{"authorized":false,"message":"revoked","kill_grace_ms":30000}}
+            {"time":"2026-03-02T03:11:40+01:00","event":"error","message":"connection reset"}
+            {"time":"2026-03-02T03:11:45+01:00","event":"expect","state":"grace"}
+            {"time":"2026-03-02T03:12:05+01:00","event":"expect","state":"killed"}
+        "#;
+        let replay = replay(&config(""), timeline).unwrap();
+        assert!(replay.failures.is_empty(), "{:?}", replay.failures);
+
+        assert!(replay.steps[1].note.contains("timed out"));
+        let kill = replay.steps.last().unwrap();
+        assert_eq!(kill.state, State::Killed);
+        assert_eq!(format_time(&kill.time), "2026-03-02 03:12:00+01:00");
+
+        // A configured grace beyond the calendar never ends
+        let endless = r#"{"time":"2026-03-02T03:11:30+01:00","event":"check","response":{"authorized":false,"message":"revoked"}}"#;
+        let endless = super::replay(&config(r#","kill_grace_ms":18446744073709551615"#), endless).unwrap();
+        assert_eq!(endless.steps.last().unwrap().state, State::Grace);
+        assert!(endless.steps.last().unwrap().note.contains("without end"));
+    }
+
+    #[test]
+    fn test_rescue_patches_and_failed_expectations() {
+        let timeline = r#"
+            {"time":"2026-03-02T09:00:00Z","event":"check","response":{"authorized":false,"message":"seat limit","kill_grace_ms":60000}}
+            {"time":"2026-03-02T09:00:20Z","event":"check","response":{"authorized":true,"message":"ok"}}
+            {"time":"2026-03-02T09:00:30Z","event":"check","response":{"authorized":true,"message":"ok","check_interval_ms":60000,"expires_in":100}}
+            {"time":"2026-03-02T09:05:00Z","event":"expect","state":"killed"}
+        "#;
+        let replay = replay(&config(""), timeline).unwrap();
+
+        assert!(replay.steps[1].note.contains("kill cancelled"));
+        assert!(replay.steps[2].note.contains("check_interval_ms patched to 60000"));
+        assert!(replay.steps[2].note.contains("early renewal at 2026-03-02 09:01:20+00:00"));
+        assert_eq!(replay.failures.len(), 1);
+        assert!(replay.failures[0].contains("expected Killed, state is Running"));
+
+        assert!(super::replay(&config(""), "{\"time\":\"yesterday\",\"event\":\"kill_request\"}").is_err());
+    }
+
+    #[test]
+    fn test_failure_limit_from_server() {
+        // A firewalled server: the tolerance patched in by the last good answer runs out
+        let timeline = r#"
+            {"time":"2026-03-02T09:00:00Z","event":"check","response":{"authorized":true,"message":"ok","max_consecutive_failures":2}}
+            {"time":"2026-03-02T09:10:00Z","event":"error","message":"connection refused"}
+            {"time":"2026-03-02T09:20:00Z","event":"expect","state":"running"}
+            {"time":"2026-03-02T09:20:00Z","event":"error","message":"connection refused"}
+            {"time":"2026-03-02T09:20:01Z","event":"expect","state":"killed"}
+        "#;
+        let replay = replay(&config(""), timeline).unwrap();
+        assert!(replay.failures.is_empty(), "{:?}", replay.failures);
+        assert!(replay.steps[0].note.contains("max_consecutive_failures patched to 2"));
+        assert!(replay.steps[2].note.contains("failure limit reached, kill (shred)"));
+    }
+
+    #[test]
+    fn test_maintenance_pause() {
+        // 09:00 to 11:00 paused; an unsigned answer cannot extend it
+        let timeline = r#"
+            {"time":"2026-03-02T09:00:00Z","event":"check","response":{"authorized":true,"message":"ok","paused_until":1772449200,"max_consecutive_failures":1}}
+            {"time":"2026-03-02T09:10:00Z","event":"error","message":"connection refused"}
+            {"time":"2026-03-02T09:50:00Z","event":"check","response":{"authorized":false,"message":"maintenance","paused_until":1772449200}}
+            {"time":"2026-03-02T10:30:00Z","event":"check","signed":false,"response":{"authorized":false,"message":"x","paused_until":1772460000}}
+            {"time":"2026-03-02T10:50:00Z","event":"expect","state":"running"}
+            {"time":"2026-03-02T11:10:00Z","event":"error","message":"connection refused"}
+            {"time":"2026-03-02T11:10:01Z","event":"expect","state":"killed"}
+        "#;
+        let replay = replay(&config(""), timeline).unwrap();
+        assert!(replay.failures.is_empty(), "{:?}", replay.failures);
+        assert!(replay.steps[1].note.contains("enforcement paused by the server, next check at 2026-03-02 09:50:00+00:00"));
+        assert!(replay.steps[2].note.contains("unauthorized (maintenance), enforcement paused"));
+        assert!(replay.steps[4].note.contains("failure limit reached"));
+    }
+
+    #[test]
+    fn test_lapsed_lease_is_unauthorized() {
+        let timeline = r#"
+            {"time":"2026-03-02T09:00:00Z","event":"check","response":{"authorized":true,"message":"ok","expires_in":900}}
+            {"time":"2026-03-02T09:10:00Z","event":"error","message":"connection refused"}
+            {"time":"2026-03-02T09:15:00Z","event":"error","message":"connection refused"}
+            {"time":"2026-03-02T09:15:01Z","event":"expect","state":"killed"}
+        "#;
+        let replay = replay(&config(""), timeline).unwrap();
+        assert!(replay.failures.is_empty(), "{:?}", replay.failures);
+        assert_eq!(replay.steps[1].state, State::Running);
+        assert!(replay.steps[2].note.contains("lease expired - kill"));
+    }
+}
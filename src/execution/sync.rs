@@ -1,104 +1,70 @@
-/// Synchronous execution mode
-/// Verify license FIRST, then execute base binary only if authorized
+//! Synchronous execution mode
+//! Verify license FIRST, then execute base binary only if authorized
 
-use std::process::{Command, exit};
+use crate::utils::exit_status::{self, ExitStatus};
 use crate::verification;
 use crate::config::Config;
-use crate::security::secure_delete_self;
+use crate::security::destroy_self;
 
 /// Execute in synchronous mode
 /// 
 /// Flow:
 /// 1. Verify license with server
-/// 2. If authorized → exit(0) to signal loader to continue to base
-/// 3. If unauthorized → exit(1) to signal loader to abort
+/// 2. If authorized, or enforcement is paused by the server (see
+///    `verification::pause`) → exit(0) to signal loader to continue to base
+/// 3. Otherwise → exit with the status code of the failure (see
+///    `utils::exit_status`) to signal loader to abort
 /// 
 /// NOTE: Overload runs as FIRST binary in merged executable.
 /// The merged binary's loader will check our exit code:
-///   - exit(0) → loader continues to execute base binary
-///   - exit(1) → loader aborts, base never runs
+///   - 0 → loader continues to execute base binary
+///   - 10 (unauthorized), 20 (network failure), ... → loader aborts, base
+///     never runs
 pub fn execute_sync(config: &Config) -> ! {
-    eprintln!("🔄 Running in SYNC mode: Verifying license before execution...");
+    log_info!("🔄 Running in SYNC mode: Verifying license before execution...");
     
-    // Verify license (grace_period removed from config, pass 0)
-    match verification::verify_license(
-        &config.license_id,
-        &config.get_server_url(),
-        &config.shared_secret,
-        0, // grace_period removed from config
-        true, // first_check - sync mode always treats as first check
-    ) {
+    if let Some(denial) = verification::denial::cached(config) {
+        log_error!("⛔ Denied recently ({}) - aborting without re-verification", denial.message);
+        exit_status::exit(ExitStatus::Unauthorized, &denial.message);
+    }
+    
+    // Verify license
+    match verification::fallback::verify_one_shot(config, true) {
         Ok(response) if response.authorized => {
-            eprintln!("✅ License verified successfully");
-            eprintln!("✅ Returning control to loader → Base binary will execute");
-            exit(0); // Signal success to loader
+            log_info!("✅ License verified successfully");
+            verification::denial::clear(config);
+            log_info!("✅ Returning control to loader → Base binary will execute");
+            exit_status::exit(ExitStatus::Authorized, &response.message); // Signal success to loader
+        }
+        Ok(response) if verification::pause::active(config).is_some() => {
+            log_warn!("⏸️  License verification failed ({}), but enforcement is paused by the server", response.message);
+            exit_status::exit(ExitStatus::Authorized, "enforcement paused by the server");
         }
-        Ok(_response) => {
-            eprintln!("❌ License verification failed");
-            eprintln!("❌ Signaling loader to abort → Base binary will NOT execute");
+        Err(e) if verification::pause::active(config).is_some() => {
+            log_warn!("⏸️  Verification error ({}), but enforcement is paused by the server", e);
+            exit_status::exit(ExitStatus::Authorized, "enforcement paused by the server");
+        }
+        Ok(response) => {
+            log_error!("❌ License verification failed");
+            verification::denial::record(config, &response);
+            log_error!("❌ Signaling loader to abort → Base binary will NOT execute");
+            exit_status::record(ExitStatus::Unauthorized, &response.message);
             if config.self_destruct {
-                secure_delete_self();
+                destroy_self(config);
             } else {
-                exit(1);
+                exit_status::exit(ExitStatus::Unauthorized, &response.message);
             }
         }
         Err(e) => {
-            eprintln!("❌ Verification error: {}", e);
-            eprintln!("❌ Signaling loader to abort → Base binary will NOT execute");
-            if config.self_destruct {
-                secure_delete_self();
+            log_error!("❌ Verification error: {}", e);
+            log_error!("❌ Signaling loader to abort → Base binary will NOT execute");
+            exit_status::record(ExitStatus::NetworkFailure, &e.message);
+            // A server outage says nothing about the license: never destroy over it
+            if config.self_destruct && !e.class.is_retryable() {
+                destroy_self(config);
             } else {
-                exit(1);
+                exit_status::exit(ExitStatus::NetworkFailure, &e.message);
             }
         }
     }
 }
-
-/// Chain execution to base binary
-/// This replaces the current process with the base binary
-#[cfg(unix)]
-fn chain_to_base(base_path: &str) -> ! {
-    use std::os::unix::process::CommandExt;
-    
-    eprintln!("🚀 Executing base binary...");
-    
-    let error = Command::new(base_path)
-        .args(std::env::args().skip(1)) // Forward arguments
-        .exec(); // Replace current process
-    
-    // If exec returns, it failed
-    eprintln!("❌ Failed to exec base binary: {}", error);
-    exit(1);
-}
-
-/// Chain execution to base binary (Windows version)
-/// Windows doesn't have exec(), so we spawn and exit
-#[cfg(windows)]
-fn chain_to_base(base_path: &str) -> ! {
-    eprintln!("🚀 Executing base binary...");
-    
-    let status = Command::new(base_path)
-        .args(std::env::args().skip(1)) // Forward arguments
-        .status();
-    
-    match status {
-        Ok(exit_status) => {
-            exit(exit_status.code().unwrap_or(1));
-        }
-        Err(e) => {
-            eprintln!("❌ Failed to execute base binary: {}", e);
-            exit(1);
-        }
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    
-    #[test]
-    fn test_chain_to_base_validation() {
-        // This test just ensures the function compiles
-        // Actual execution testing requires integration tests
-    }
-}
@@ -0,0 +1,33 @@
+//! KillCode Overload - library interface
+//!
+//! The overload binary (`main.rs`) is a thin consumer of these modules. The
+//! wrapper/loader links them directly to verify licenses, fingerprint the
+//! machine and run kill primitives without shelling out to the binary:
+//!
+//! ```no_run
+//! let response = kc_killer::verify_license("lic_123", "https://api.example.com", "secret", 0, true)?;
+//! if !response.authorized {
+//!     eprintln!("unauthorized on {}", kc_killer::get_machine_fingerprint());
+//! }
+//! # Ok::<(), String>(())
+//! ```
+//!
+//! Logging goes through the `log_*!` macros exported here; call
+//! `utils::logger::configure` (or `configure_default`) once, otherwise output
+//! is buffered.
+
+// Module declarations (utils first: its logging macros are used everywhere)
+#[macro_use]
+pub mod utils;
+pub mod cli;
+pub mod config;
+pub mod verification;
+pub mod execution;
+pub mod security;
+#[cfg(feature = "testing")]
+pub mod testing;
+
+pub use config::{Config, KillMethod};
+pub use verification::{get_machine_fingerprint, verify_license, VerifyResponse};
+pub use security::kill_parent::{execute_kill, plan_kill, KillPlan};
+pub use security::{destroy_self, secure_delete_file, WipePlan};
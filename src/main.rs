@@ -1,53 +1,160 @@
-/// KillCode Overload Binary - License Verification & Self-Destruct
-/// 
-/// This binary is embedded into protected executables and performs:
-/// 1. License verification via HMAC-authenticated API calls
-/// 2. Machine fingerprinting
-/// 3. Secure self-deletion on unauthorized access
-/// 4. Sync/Async execution modes
-
-// Module declarations
-mod config;
-mod verification;
-mod execution;
-mod security;
-mod utils;
-
-use std::process::exit;
+//! KillCode Overload Binary - License Verification & Self-Destruct
+//! 
+//! This binary is embedded into protected executables and performs:
+//! 1. License verification via HMAC-authenticated API calls
+//! 2. Machine fingerprinting
+//! 3. Secure self-deletion on unauthorized access
+//! 4. Sync/Async execution modes
+//!
+//! Timing: every interval, deadline and grace period is measured on the
+//! monotonic clock (`Instant`), so NTP steps or a user changing the clock
+//! cannot stretch or compress the check cadence. Wall-clock time is only used
+//! for protocol timestamps, persisted expiries and clock-tamper/suspend
+//! detection, where comparing it with monotonic time is the point.
+
+// All functionality lives in the library (lib.rs); this is the entry point
+use kc_killer::{cli, config, execution, security, utils, verification};
+use kc_killer::{log_debug, log_error, log_info, log_warn};
+
+use utils::shutdown::exit;
+use std::sync::{mpsc, Mutex};
 use std::thread;
-use std::time::Duration;
-use config::{load_config, load_embedded_config};
+use std::time::{Duration, Instant};
+use security::clock::ClockGuard;
+use security::renewal::RenewalScheduler;
+use security::scheduler::{CheckScheduler, Violation};
 use security::secure_delete_self;
-use utils::health_monitor::HealthMonitor;
+use utils::control::{Command, ControlChannel};
+use utils::exit_status::ExitStatus;
+use utils::health_monitor::{CheckOutcome, HealthMonitor};
+use utils::summary::Outcome;
+use verification::{VerifyError, VerifyResponse};
+use execution::enforcement::{self, Decision};
+use utils::state::StateStore;
+
+/// How long a signalled overload waits to see whether its app goes down too
+const TERMINATION_SETTLE: Duration = Duration::from_secs(1);
+
+/// Shortest watchdog deadline (also used in single-check mode)
+const WATCHDOG_MIN: Duration = Duration::from_secs(60);
 
 fn main() {
-    eprintln!("🚀 Overload (killer) starting... PID={}", std::process::id());
+    // A crash fails closed instead of leaving the app running unverified
+    verification::crash::install();
+    verification::crash::guard(run);
+}
+
+fn run() {
+    // Support/recovery subcommands never enter the enforcement loop
+    if let Some(code) = cli::run_subcommand() {
+        exit(code);
+    }
     
-    // Try to load configuration from embedded section first
-    let config = match load_embedded_config() {
-        Ok(cfg) => {
-            eprintln!("✅ Using embedded license configuration");
-            cfg
-        }
+    // Detached helper wiping a self-destructed binary; before loading the
+    // config, which was deleted with it
+    #[cfg(windows)]
+    if let Ok(job) = std::env::var(security::destruct::SELF_DELETE_ENV) {
+        exit(security::destruct::run_self_delete_helper(&job));
+    }
+    
+    // Detached helper trimming the filesystem a shredded file was on; it
+    // needs no config (a self-destructed binary's was deleted)
+    if let Ok(token) = std::env::var(security::erase::BACKGROUND_TRIM_ENV) {
+        security::erase::run_background_trim(&token);
+    }
+    
+    // CI gating: one check, one JSON line on stdout, distinct exit codes
+    if utils::summary::requested() {
+        utils::summary::enable();
+    }
+    
+    log_info!("🚀 Overload (killer) starting... PID={}", std::process::id());
+    
+    // No core dumps of a process holding the shared secret
+    security::secrets::harden_process();
+    
+    // Embedded config, overridden by the .config file and KILLER_* variables
+    let config = match config::load() {
+        Ok(cfg) => cfg,
         Err(e) => {
-            eprintln!("ℹ️  No embedded license ({}), trying .config file...", e);
-            // Fall back to external .config file
-            match load_config() {
-                Ok(cfg) => cfg,
-                Err(e2) => {
-                    eprintln!("❌ Failed to load configuration: {}", e2);
-                    if std::env::var("OVERLOAD_NO_DESTRUCT").is_err() {
-                        secure_delete_self();
-                    } else {
-                        exit(1);
-                    }
-                }
+            utils::logger::configure_default();
+            log_error!("❌ Failed to load configuration: {}", e);
+            verification::events::record("config_load_failure", &e);
+            utils::summary::emit(Outcome::ConfigError, &e);
+            if std::env::var("OVERLOAD_NO_DESTRUCT").is_err() {
+                utils::exit_status::record(ExitStatus::ConfigMissing, &e);
+                // No config, so no configured wipe plan
+                secure_delete_self(&security::WipePlan::default());
+            } else {
+                utils::exit_status::exit(ExitStatus::ConfigMissing, &e);
             }
         }
     };
 
+    // Apply log_level/log_format/log_file (replays what was logged so far) and
+    // mask signatures, secrets and license IDs in everything logged from here
+    utils::logger::configure(&config);
+    utils::redact::configure(&config);
+    // Other products' killers may share this host and state directory
+    utils::state::set_namespace(&config.license_id);
+    verification::webhook::configure(&config);
+    
+    // Detached helper spawned by CLI mode to report queued usage
+    if let Ok(token) = std::env::var(execution::cli::USAGE_FLUSH_ENV) {
+        execution::cli::run_usage_flush(&config, &token);
+    }
+    
+    // Detached helper finishing the shred after a "corrupt" kill
+    if let Ok(token) = std::env::var(security::kill_parent::BACKGROUND_SHRED_ENV) {
+        security::kill_parent::run_background_shred(&config, &token);
+    }
+    
+    // Root helper holding the kill for a checker that dropped privileges
+    #[cfg(unix)]
+    if let Ok(target) = std::env::var(security::privileges::KILL_BROKER_ENV) {
+        security::privileges::run_broker(&config, &target);
+    }
+    
+    // Detached helper verifying for async_mode after it returned to the loader
+    if let Ok(pid) = std::env::var(execution::async_mode::BACKGROUND_VERIFY_ENV) {
+        let pid = pid.parse().unwrap_or(0);
+        verification::crash::arm(&config);
+        verification::crash::set_target(pid);
+        execution::async_mode::run_background_verification(&config, pid);
+    }
+    
+    // Our parent is the protected app from here on (not so for a CI gate)
+    if !utils::summary::enabled() {
+        verification::crash::arm(&config);
+    }
+    
+    // Re-executes from memory (a second start, so before anything counts it)
+    security::memexec::run_from_memory(&config);
+    verification::metering::record_launch(&config);
+    if config.cli_mode {
+        execution::cli::execute_cli(&config);
+    }
+
+    // Before any thread starts: forking keeps only the calling thread. The
+    // parent is recorded before detaching from it
+    let lineage = if config.daemonize && config.check_interval_ms > 0 && !utils::summary::enabled() {
+        detach(security::lineage::Lineage::record())
+    } else {
+        None
+    };
+    
+    // Before any thread starts: termination signals are waited for by a task.
+    // They end the process gracefully (seat release, webhook drain, then the
+    // shutdown hooks) instead of mid-check
+    let signal_config = config.clone();
+    utils::shutdown::on_termination(move |termination| on_termination(&signal_config, termination));
+    verification::seat::release_when_app_exits(&config);
+    
     // Initialize health monitor (if parent wrapper created shared memory)
     let health_monitor = HealthMonitor::new();
+    // Richer commands from the parent wrapper (pause, re-check, rotate, status)
+    let control = ControlChannel::connect();
+    utils::instances::register(&config);
     
     // Overload always runs in verification loop
     // check_interval_ms controls behavior:
@@ -55,11 +162,54 @@ fn main() {
     // - >0: Check repeatedly with interval (async mode)
     
     let mut first_check = true;
-    let mut runtime_check_interval = config.check_interval_ms;
-    let mut runtime_kill_method = config.kill_method.clone();
+    
+    // Runtime patches publish new config versions instead of mutating local
+    // copies; every reader sees one consistent snapshot
+    let kill_method = security::capabilities::resolve_kill_method(&config.kill_method, "config");
+    // Summary mode is a one-shot gate
+    let check_interval_ms = if utils::summary::enabled() { 0 } else { config.check_interval_ms };
+    let config = config::snapshot::install(config::Config { kill_method, check_interval_ms, ..config });
+    let config_updates = config::snapshot::subscribe();
+    config::lint::report(&config);
+    // Everything needing privileges is open by now
+    security::privileges::drop_privileges(&config);
+    verification::heartbeat::spawn();
+    verification::events::flush_in_background(&config);
+    verification::crash::send_pending_in_background(&config);
+    
+    // A hook injected at load time could answer the first check for us
+    if config.anti_injection
+        && let Some(detection) = security::antitamper::detect_injection(&config.trusted_library_paths)
+    {
+        log_error!("💉 Library injection detected ({})", detection);
+        enforce_violation(Violation { kind: "injection", detail: detection }, &health_monitor, &config.kill_method, &config);
+    }
+    
+    // A machine denied moments ago is blocked before any network round trip
+    if let Some(denial) = verification::denial::cached(&config) {
+        log_error!(
+            "⛔ Denied {}s ago ({}) - enforcing without re-verification",
+            utils::time::unix_now() - denial.denied_at,
+            denial.message
+        );
+        enforce_unauthorized(&health_monitor, &config.kill_method, &config);
+    }
+    
+    let state_store = StateStore::for_license(&config.license_id);
+    let mut clock_guard = ClockGuard::new(config.max_clock_drift_secs, state_store.load().clock.high_water);
+    let mut scheduler = security_checks(&config, config.check_interval_ms, lineage);
+    let mut renewal = RenewalScheduler::new(config.renewal_lead_secs);
+    let mut worker = VerificationWorker::spawn();
+    // A restart must not reset the count towards max_consecutive_failures
+    let mut consecutive_failures = state_store.load().consecutive_failures;
+    if let Some(ref hm) = health_monitor {
+        hm.set_kill_method(&config.kill_method);
+    }
     
     loop {
-        eprintln!("🔍 Verifying license...");
+        // Reports and enforcement go out under the license that authorized
+        let config = verification::licenses::active(&config::snapshot::current());
+        log_info!("🔍 Verifying license...");
         
         // Update heartbeat before verification
         if let Some(ref hm) = health_monitor {
@@ -67,99 +217,675 @@ fn main() {
             
             // Check if parent has requested us to kill ourselves
             if hm.is_kill_requested() {
-                eprintln!("🚨 Parent requested kill - executing kill method: {:?}", runtime_kill_method);
-                security::kill_parent::execute_kill(&runtime_kill_method);
+                log_error!("🚨 Parent requested kill - executing kill method: {:?}", config.kill_method);
+                security::kill_parent::execute_kill(&config.kill_method, &config);
                 // If kill fails or only stops process, we should exit
-                exit(0);
+                utils::exit_status::exit(ExitStatus::Unauthorized, "kill requested by the parent wrapper");
             }
         }
         
-        match verification::verify_license(
-            &config.license_id,
-            &config.get_server_url(),
-            &config.shared_secret,
-            0, // grace_period removed from config
-            first_check,
-        ) {
+        // Security checks are staggered across the interval; due ones run here
+        if let Some(violation) = scheduler.run_due() {
+            enforce_violation(violation, &health_monitor, &config.kill_method, &config);
+        }
+        
+        // A license domain pointed at a fake server answers whatever we want
+        if config.detect_redirection
+            && let Err(detail) = security::redirection::check(&config)
+        {
+            log_error!("🧭 License server redirected: {}", detail);
+            enforce_violation(Violation { kind: "redirection", detail }, &health_monitor, &config.kill_method, &config);
+        }
+        
+        // Primary endpoint, or the break-glass fallback after repeated failures
+        let started = Instant::now();
+        let (result, stalled) = supervised_verify(&mut worker, &config, first_check, &health_monitor);
+        if let Some(ref hm) = health_monitor {
+            let outcome = match &result {
+                _ if stalled => CheckOutcome::Stalled,
+                Ok(response) if response.authorized => CheckOutcome::Authorized,
+                Ok(_) => CheckOutcome::Unauthorized,
+                Err(_) => CheckOutcome::Error,
+            };
+            hm.record_check(outcome, started.elapsed(), verification::network::last_http_status());
+        }
+        verification::events::record_check(&result, stalled);
+        consecutive_failures = if result.is_ok() { 0 } else { consecutive_failures.saturating_add(1) };
+        persist_consecutive_failures(&state_store, consecutive_failures);
+        match result {
             Ok(response) if response.authorized => {
-                eprintln!("✅ License verified successfully");
-                
-                // Apply runtime patching if server sent updated values
-                if let Some(new_interval) = response.check_interval_ms {
-                    if new_interval != runtime_check_interval {
-                        eprintln!("🔄 Runtime patch: check_interval_ms {} → {}ms", runtime_check_interval, new_interval);
-                        runtime_check_interval = new_interval;
+                if let Err(reason) = enforcement::check_clock(&config, &mut clock_guard, &response) {
+                    log_error!("🕰️  Clock tampering detected: {}", reason);
+                    enforce_unauthorized(&health_monitor, &config.kill_method, &config);
+                }
+                let local_now = utils::time::unix_now();
+                persist_clock_high_water(&state_store, clock_guard.high_water());
+                renewal.observe(&response, local_now);
+                report_lease(&renewal, local_now, true, &health_monitor);
+                // An authorization that lapsed on arrival authorizes nothing
+                if renewal.expired(local_now) {
+                    let reason = "license lease already expired".to_string();
+                    log_error!("⌛ {} - treating as unauthorized", reason);
+                    let grace_ms = response.kill_grace_or(config.kill_grace_ms);
+                    if grace_ms == 0 || !rescued_during_grace(&config, &reason, grace_ms, &health_monitor, &mut worker) {
+                        utils::summary::emit(Outcome::Unauthorized, &reason);
+                        enforce_unauthorized(&health_monitor, &config.kill_method, &config);
                     }
+                    continue;
                 }
-                if let Some(new_method_str) = response.kill_method {
-                    if let Some(new_method) = config::KillMethod::from_str(&new_method_str) {
-                        if new_method != runtime_kill_method {
-                            eprintln!("🔄 Runtime patch: kill_method {:?} → {:?}", runtime_kill_method, new_method);
-                            runtime_kill_method = new_method;
-                        }
-                    } else {
-                        eprintln!("⚠️  Invalid kill_method from server: {}", new_method_str);
+                verification::denial::clear(&config);
+                verification::heartbeat::record_check(true);
+                
+                log_info!("✅ License verified successfully");
+                
+                // Apply runtime patching if the (signed) response carries updated values
+                let patched = config::snapshot::update(|c| {
+                    let changes = verification::patch::apply(c, &response, |method| {
+                        security::capabilities::resolve_kill_method(method, "server")
+                    });
+                    for change in changes {
+                        log_info!("🔄 Runtime patch: {} {} → {}", change.field, change.from, change.to);
                     }
+                });
+                if patched.log_level != config.log_level {
+                    utils::logger::set_level(&patched.log_level);
+                }
+                verification::rotation::handle(&response);
+                // A published policy bundle replaces the configured policy
+                if let Some(policy) = verification::broadcast::policy_bundle(&config) {
+                    config::snapshot::update(|c| c.enforcement_policy = Some(policy));
+                }
+                
+                // Continue with the patched version
+                let config = verification::licenses::active(&config::snapshot::current());
+                for update in config_updates.try_iter() {
+                    scheduler.set_period(Duration::from_millis(update.check_interval_ms));
                 }
                 
                 // Update health status: success
                 if let Some(ref hm) = health_monitor {
                     hm.update(true);
+                    hm.set_kill_method(&config.kill_method);
                 }
                 
                 // Check if we should loop or exit
-                if runtime_check_interval == 0 {
-                    eprintln!("✅ Single check mode - exiting with success");
-                    exit(0);
+                if config.check_interval_ms == 0 {
+                    log_info!("✅ Single check mode - exiting with success");
+                    utils::summary::emit(Outcome::Authorized, &response.message);
+                    utils::exit_status::exit(ExitStatus::Authorized, &response.message);
                 } else {
                     first_check = false;  // Mark subsequent checks
-                    eprintln!("🔄 Will re-check in {}ms", runtime_check_interval);
-                    thread::sleep(Duration::from_millis(runtime_check_interval));
+                    // Daemonized: the loader may run the base now
+                    security::lineage::report_status(ExitStatus::Authorized.code());
+                    // Never before the loader got its answer: a download takes a while
+                    verification::self_update::handle(&config, &response);
+                    let interval_ms = verification::pause::check_interval_ms(&config, config.check_interval_ms);
+                    log_info!("🔄 Will re-check in {}ms", interval_ms);
+                    if let Some(violation) = wait_for_next_check(&config, interval_ms, &health_monitor, &control, &mut scheduler, &renewal) {
+                        enforce_violation(violation, &health_monitor, &config.kill_method, &config);
+                    }
                 }
             }
             Ok(response) => {
-                eprintln!("❌ License verification failed - unauthorized access");
-                
-                // Update health status: failure
-                if let Some(ref hm) = health_monitor {
-                    hm.update(false);
-                    hm.request_kill_base();
-
-                    // Try to kill base directly if PID is known
-                    if let Some(base_pid) = hm.get_base_pid() {
-                        eprintln!("🎯 Found base PID: {}, killing it directly...", base_pid);
-                        if let Err(e) = security::kill_parent::stop_parent(base_pid as u32) {
-                            eprintln!("⚠️ Failed to stop base process: {}", e);
+                log_error!("❌ License verification failed - unauthorized access");
+                match enforcement::on_denial(&config, &response) {
+                    Decision::Deferred => {
+                        log_warn!("🧩 Enforcement deferred by enforcement_policy");
+                        if config.check_interval_ms == 0 {
+                            let message = format!("{} (enforcement deferred by policy)", response.message);
+                            utils::summary::emit(Outcome::Unauthorized, &message);
+                            utils::exit_status::exit(ExitStatus::Unauthorized, &message);
+                        }
+                        first_check = false;
+                        if let Some(violation) = wait_for_next_check(&config, config.check_interval_ms, &health_monitor, &control, &mut scheduler, &renewal) {
+                            enforce_violation(violation, &health_monitor, &config.kill_method, &config);
+                        }
+                    }
+                    Decision::Paused(until) => {
+                        wait_out_pause(&config, until, &response.message, &health_monitor, &control, &mut scheduler, &renewal);
+                        first_check = false;
+                    }
+                    Decision::Enforce { reason, grace_ms } => {
+                        if grace_ms == 0 || !rescued_during_grace(&config, &reason, grace_ms, &health_monitor, &mut worker) {
+                            utils::summary::emit(Outcome::Unauthorized, &reason);
+                            enforce_unauthorized(&health_monitor, &config.kill_method, &config);
                         }
+                        // Rescued: continue with a regular check right away
                     }
+                    Decision::Retry => {}
                 }
-                
-                // Execute kill method on parent binary (use runtime value)
-                eprintln!("🚨 Executing kill method: {:?}", runtime_kill_method);
-                security::kill_parent::execute_kill(&runtime_kill_method);
-                
-                // Should not reach here if kill succeeded
-                exit(1);
             }
-            Err(e) => {
-                eprintln!("❌ Verification error: {}", e);
+            Err(VerifyError { class: error_class, message: e }) => {
+                log_error!("❌ Verification error: {}", utils::redact::scrub(&e));
+                verification::heartbeat::record_check(false);
                 
                 // Update health status: failure (network error)
                 if let Some(ref hm) = health_monitor {
                     hm.update(false);
                 }
                 
+                let now = utils::time::unix_now();
+                match enforcement::on_failure(&config, &e, consecutive_failures, &renewal, now) {
+                    Decision::Paused(until) => {
+                        wait_out_pause(&config, until, &e, &health_monitor, &control, &mut scheduler, &renewal);
+                        first_check = false;
+                        continue;
+                    }
+                    Decision::Enforce { reason, grace_ms } if renewal.expired(now) => {
+                        log_error!("⌛ {} - treating as unauthorized", reason);
+                        report_lease(&renewal, now, false, &health_monitor);
+                        if grace_ms == 0 || !rescued_during_grace(&config, &reason, grace_ms, &health_monitor, &mut worker) {
+                            utils::summary::emit(Outcome::Unauthorized, &reason);
+                            enforce_unauthorized(&health_monitor, &config.kill_method, &config);
+                        }
+                        // Rescued: continue with a regular check right away
+                        continue;
+                    }
+                    Decision::Enforce { reason, .. } => {
+                        log_error!("🚨 {} - enforcing", reason);
+                        utils::exit_status::record(ExitStatus::NetworkFailure, &e);
+                        enforce_unauthorized(&health_monitor, &config.kill_method, &config);
+                    }
+                    Decision::Retry | Decision::Deferred => {}
+                }
+                report_lease(&renewal, now, false, &health_monitor);
+                
                 // For network errors, continue retrying - parent will signal us if limit reached
                 // Check if we should loop or exit (same logic as success case)
-                if runtime_check_interval == 0 {
-                    eprintln!("⚠️  Single check mode - network error - exiting with failure");
-                    exit(1);
+                if config.check_interval_ms == 0 {
+                    if let Some(delay) = verification::fallback::one_shot_retry_delay(error_class, consecutive_failures) {
+                        log_warn!("⏳ Retryable verification error - retrying in {}s", delay.as_secs());
+                        std::thread::sleep(delay);
+                        continue;
+                    }
+                    log_warn!("⚠️  Single check mode - network error - exiting with failure");
+                    utils::summary::emit(Outcome::Error, &e);
+                    utils::exit_status::exit(ExitStatus::NetworkFailure, &e);
                 } else {
                     first_check = false;  // Mark subsequent checks
-                    eprintln!("⚠️  Network error - will retry in {}ms (parent will signal if limit reached)", runtime_check_interval);
-                    thread::sleep(Duration::from_millis(runtime_check_interval));
+                    // Never sooner than the server asked for (Retry-After, capped)
+                    let retry_ms = error_class
+                        .retry_after()
+                        .map_or(config.check_interval_ms, |after| config.check_interval_ms.max(after.as_millis() as u64));
+                    log_warn!("⚠️  Network error - will retry in {}ms (parent will signal if limit reached)", retry_ms);
+                    if let Some(violation) = wait_for_next_check(&config, retry_ms, &health_monitor, &control, &mut scheduler, &renewal) {
+                        enforce_violation(violation, &health_monitor, &config.kill_method, &config);
+                    }
                 }
             }
         }
     }
 }
+
+/// Skip enforcement while the server paused it for maintenance (`until`)
+///
+/// Waits for the next (reduced-rate) check; single check mode lets the
+/// loader run the base, like sync mode (a pause neither kills nor withholds
+/// the app).
+fn wait_out_pause(
+    config: &config::Config,
+    until: i64,
+    message: &str,
+    health_monitor: &Option<HealthMonitor>,
+    control: &Option<ControlChannel>,
+    scheduler: &mut CheckScheduler,
+    renewal: &RenewalScheduler,
+) {
+    log_warn!(
+        "⏸️  Enforcement paused by the server for another {}s - not enforcing",
+        until - utils::time::protocol_now()
+    );
+    if config.check_interval_ms == 0 {
+        let message = format!("{} (enforcement paused by the server)", message);
+        utils::summary::emit(Outcome::Authorized, &message);
+        utils::exit_status::exit(ExitStatus::Authorized, &message);
+    }
+    let interval_ms = verification::pause::check_interval_ms(config, config.check_interval_ms);
+    if let Some(violation) = wait_for_next_check(config, interval_ms, health_monitor, control, scheduler, renewal) {
+        enforce_violation(violation, health_monitor, &config.kill_method, config);
+    }
+}
+
+/// Verification on a worker thread, so a hung check cannot freeze enforcement
+///
+/// A wedged DNS lookup or TLS handshake never reaches the HTTP timeout. The
+/// loop waits for the worker only up to the watchdog deadline; a stalled
+/// worker is abandoned (a thread cannot be killed - it exits once the call
+/// returns and finds nobody listening) and replaced by a fresh one. What the
+/// abandoned call returns never reaches shared state (`verification::worker`).
+struct VerificationWorker {
+    requests: mpsc::Sender<bool>,
+    results: mpsc::Receiver<Result<VerifyResponse, VerifyError>>,
+}
+
+impl VerificationWorker {
+    fn spawn() -> Self {
+        let (requests, pending) = mpsc::channel::<bool>();
+        let (finished, results) = mpsc::channel();
+        let pending = Mutex::new(pending);
+        let generation = verification::worker::current();
+
+        utils::tasks::spawn("verification_worker", move || {
+            verification::worker::enter(generation);
+            let Ok(pending) = pending.lock() else {
+                return;
+            };
+            for first_check in pending.iter() {
+                let config = config::snapshot::current();
+                if finished.send(verification::seat::verify(&config, first_check)).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Self { requests, results }
+    }
+
+    /// Run one verification, waiting at most `timeout` (None = no limit)
+    ///
+    /// # Returns
+    /// The verification result, or Err if the worker stalled or died
+    fn verify(&self, first_check: bool, timeout: Option<Duration>) -> Result<Result<VerifyResponse, VerifyError>, String> {
+        self.requests
+            .send(first_check)
+            .map_err(|_| "verification worker exited".to_string())?;
+        match timeout {
+            Some(timeout) => self.results.recv_timeout(timeout).map_err(|e| match e {
+                mpsc::RecvTimeoutError::Timeout => format!("verification stalled for {}s", timeout.as_secs()),
+                mpsc::RecvTimeoutError::Disconnected => "verification worker exited".to_string(),
+            }),
+            None => self.results.recv().map_err(|_| "verification worker exited".to_string()),
+        }
+    }
+}
+
+/// Watchdog deadline for one verification (None = watchdog disabled)
+fn watchdog_timeout(config: &config::Config) -> Option<Duration> {
+    if config.watchdog_intervals == 0 {
+        return None;
+    }
+    let interval = Duration::from_millis(config.check_interval_ms);
+    Some(interval.saturating_mul(config.watchdog_intervals).max(WATCHDOG_MIN))
+}
+
+/// Verify on the worker, restarting it when the watchdog fires
+///
+/// # Returns
+/// The verification result (a stall is a network error) and whether the
+/// watchdog fired
+fn supervised_verify(
+    worker: &mut VerificationWorker,
+    config: &config::Config,
+    first_check: bool,
+    health_monitor: &Option<HealthMonitor>,
+) -> (Result<VerifyResponse, VerifyError>, bool) {
+    match worker.verify(first_check, watchdog_timeout(config)) {
+        Ok(result) => (result, false),
+        Err(e) => {
+            log_error!("🐕 Watchdog: {} - restarting the verification worker", e);
+            verification::events::record("verification_stall", &e);
+            if let Some(hm) = health_monitor {
+                hm.record_stall();
+            }
+            verification::worker::abandon();
+            *worker = VerificationWorker::spawn();
+            // No answer: the license was not judged
+            (Err(VerifyError::retryable(e, None)), true)
+        }
+    }
+}
+
+/// Daemonize (Unix), keeping the recorded parent as the one to watch
+fn detach(lineage: Option<security::lineage::Lineage>) -> Option<security::lineage::Lineage> {
+    #[cfg(unix)]
+    match security::lineage::daemonize() {
+        Ok(()) => {
+            log_info!("👻 Daemonized (PID {})", std::process::id());
+            return lineage.map(|mut lineage| {
+                lineage.adopt_current_parent();
+                lineage
+            });
+        }
+        Err(e) => log_warn!("⚠️  Cannot daemonize: {}", e),
+    }
+    #[cfg(not(unix))]
+    log_info!("ℹ️  daemonize: not detaching on this platform, watching the parent only");
+    lineage
+}
+
+/// Periodic security checks, scheduled within the configured CPU budget
+fn security_checks(
+    config: &config::Config,
+    interval_ms: u64,
+    lineage: Option<security::lineage::Lineage>,
+) -> CheckScheduler {
+    let mut scheduler = CheckScheduler::new(Duration::from_millis(interval_ms), config.security_cpu_budget_pct);
+
+    // A patched overload must not be trusted to enforce anything
+    let mut first_run = true;
+    scheduler.register("integrity", Box::new(move || {
+        let first = std::mem::replace(&mut first_run, false);
+        match security::integrity::check_self() {
+            Ok(security::integrity::IntegrityStatus::Mismatch) => {
+                log_error!("🧬 Integrity check failed - overload code has been modified");
+                Err("code section hash mismatch".to_string())
+            }
+            Ok(security::integrity::IntegrityStatus::NotProvisioned) if first => {
+                log_info!("ℹ️  No integrity hash embedded - skipping self-integrity check");
+                Ok(())
+            }
+            Ok(_) => Ok(()),
+            Err(e) => {
+                log_warn!("⚠️  Integrity check unavailable: {}", e);
+                Ok(())
+            }
+        }
+    }));
+
+    // Daemon mode: a replaced parent or an unexpected reparenting is tamper
+    if let Some(mut lineage) = lineage {
+        let lineage_config = config.clone();
+        scheduler.register("lineage", Box::new(move || match lineage.check() {
+            Ok(security::lineage::ParentState::Present) => Ok(()),
+            Ok(security::lineage::ParentState::Exited) => {
+                log_info!("👋 Protected app exited - exiting");
+                verification::seat::release(&active_config(&lineage_config));
+                exit(0);
+            }
+            Err(e) => {
+                log_error!("🧬 Parent lineage broken: {}", e);
+                Err(e)
+            }
+        }));
+    }
+
+    // A debugger attached to us is treated like unauthorized access
+    if config.anti_debug {
+        scheduler.register("debugger", Box::new(|| match security::antidebug::detect_debugger() {
+            Some(detection) => {
+                log_error!("🐞 Debugger detected ({})", detection);
+                Err(detection)
+            }
+            None => Ok(()),
+        }));
+    }
+
+    // So is a library injected to hook verification
+    if config.anti_injection {
+        let trusted = config.trusted_library_paths.clone();
+        scheduler.register("injection", Box::new(move || match security::antitamper::detect_injection(&trusted) {
+            Some(detection) => {
+                log_error!("💉 Library injection detected ({})", detection);
+                Err(detection)
+            }
+            None => Ok(()),
+        }));
+    }
+
+    scheduler
+}
+
+/// Sleep until the next verification, running due security checks meanwhile
+///
+/// The deadline is monotonic; `thread::sleep` is monotonic as well.
+///
+/// # Returns
+/// The first security violation found while waiting
+fn wait_for_next_check(
+    config: &config::Config,
+    interval_ms: u64,
+    health_monitor: &Option<HealthMonitor>,
+    control: &Option<ControlChannel>,
+    scheduler: &mut CheckScheduler,
+    renewal: &RenewalScheduler,
+) -> Option<Violation> {
+    let mut interval = interval_ms;
+    if config.power_aware {
+        let power = utils::power::current();
+        interval = utils::power::scaled_interval(interval_ms, &power, config.power_save_multiplier);
+        if interval != interval_ms {
+            log_info!("🔋 Power saving ({:?}) - next check in {}ms", power, interval);
+        }
+    }
+
+    // A lease about to lapse is renewed early, whatever the interval
+    let now = utils::time::unix_now();
+    let renew_in = renewal.next_wait_ms(interval, now);
+    if renew_in < interval {
+        let expires_in = renewal.expires_at().map_or(0, |at| at - now);
+        log_info!("⏳ License expires in {}s - renewing in {}ms", expires_in, renew_in);
+        interval = renew_in;
+    }
+
+    let regular_deadline = Instant::now() + Duration::from_millis(interval);
+    let mut deadline = regular_deadline;
+    let mut pause_used = false;
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            utils::control::set_paused_until(None);
+            return None;
+        }
+
+        let wake = scheduler.next_due().map_or(deadline, |due| due.min(deadline));
+        let nap = wake.saturating_duration_since(now);
+
+        let command = if config.power_aware {
+            // Short slices notice a wake promptly; heartbeats keep flowing so the
+            // parent does not mistake a long interval for a hang
+            if let Some(asleep) = utils::power::sleep_detecting_suspend(nap.min(utils::power::SLEEP_SLICE)) {
+                // Time spent suspended counts toward the interval: the check is overdue
+                log_info!("💤 Resumed after ~{}s of suspend - checking now", asleep.as_secs());
+                utils::control::set_paused_until(None);
+                return None;
+            }
+            if let Some(hm) = health_monitor {
+                hm.heartbeat();
+            }
+            control.as_ref().and_then(|control| control.try_recv())
+        } else if let Some(control) = control {
+            control.recv_timeout(nap)
+        } else {
+            thread::sleep(nap);
+            None
+        };
+
+        match command {
+            Some(Command::Recheck) => {
+                log_info!("🎛️  Re-check requested by the wrapper");
+                utils::control::set_paused_until(None);
+                return None;
+            }
+            // One pause per wait: a verification runs before the next one
+            Some(Command::Pause(pause)) if !pause_used => {
+                pause_used = true;
+                deadline = deadline.max(Instant::now() + pause);
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                utils::control::set_paused_until(Some(utils::time::unix_now() + remaining.as_secs() as i64));
+                log_info!("⏸️  Verification paused by the wrapper - next check in {}ms", remaining.as_millis());
+            }
+            Some(Command::Pause(_)) => log_warn!("⚠️  Pause ignored: already paused since the last check"),
+            Some(Command::Resume) => {
+                deadline = regular_deadline;
+                utils::control::set_paused_until(None);
+                log_info!("▶️  Verification resumed by the wrapper");
+            }
+            None => {}
+        }
+
+        if let Some(violation) = scheduler.run_due() {
+            return Some(violation);
+        }
+    }
+}
+
+/// Warn the user of an imminent kill and keep re-checking until it is due
+///
+/// # Returns
+/// true if a re-check authorized the license before the grace period ran out
+fn rescued_during_grace(
+    config: &config::Config,
+    message: &str,
+    grace_ms: u64,
+    health_monitor: &Option<HealthMonitor>,
+    worker: &mut VerificationWorker,
+) -> bool {
+    let kill_at = utils::time::expires_at(utils::time::unix_now(), grace_ms.div_ceil(1000) as i64);
+    if let Some(hm) = health_monitor {
+        hm.set_kill_pending(Some(kill_at));
+    }
+
+    let keep_waiting = || match health_monitor {
+        Some(hm) => {
+            hm.heartbeat();
+            if hm.is_kill_requested() {
+                log_warn!("🛑 Parent requested kill during grace period");
+                return false;
+            }
+            true
+        }
+        None => true,
+    };
+    let recheck = || matches!(supervised_verify(worker, config, false, health_monitor), (Ok(response), _) if response.authorized);
+    let rescued = enforcement::rescued_during_grace(message, grace_ms, keep_waiting, recheck);
+    if rescued && let Some(hm) = health_monitor {
+        hm.set_kill_pending(None);
+    }
+    rescued
+}
+
+/// Report a failed security check and enforce like unauthorized access
+fn enforce_violation(
+    violation: Violation,
+    health_monitor: &Option<HealthMonitor>,
+    kill_method: &config::KillMethod,
+    config: &config::Config,
+) -> ! {
+    verification::tamper::report_tamper(
+        &config.get_server_url(),
+        &config.license_id,
+        &config.shared_secret,
+        violation.kind,
+        &violation.detail,
+    );
+    let message = format!("{}: {}", violation.kind, violation.detail);
+    utils::exit_status::record(ExitStatus::TamperDetected, &message);
+    enforce_unauthorized(health_monitor, kill_method, config);
+}
+
+/// Shut down on a termination signal - or enforce, if it counts as tamper
+fn on_termination(config: &config::Config, termination: utils::shutdown::Termination) -> ! {
+    if config.termination_is_tamper && overload_targeted(&termination) {
+        log_error!("🔪 Received {} - someone is stopping the overload", termination.signal);
+        let config = active_config(config);
+        let health_monitor = HealthMonitor::open_from_env();
+        let detail = match termination.sender {
+            Some(sender) => format!("{} from PID {}", termination.signal, sender),
+            None => termination.signal.clone(),
+        };
+        enforce_violation(Violation { kind: "termination", detail }, &health_monitor, &config.kill_method, &config);
+    }
+    log_info!("🛑 Received {} - shutting down", termination.signal);
+    verification::seat::release(&active_config(config));
+    verification::webhook::drain(verification::webhook::EXIT_DRAIN);
+    exit(termination.exit_code);
+}
+
+/// Config of the license in use, as last published (`config` before that)
+fn active_config(config: &config::Config) -> config::Config {
+    config::snapshot::try_current().map_or_else(|| config.clone(), |current| verification::licenses::active(&current))
+}
+
+/// Whether a termination signal was aimed at the overload alone: the app
+/// stays up and did not send it itself (nor did its wrapper, our parent)
+fn overload_targeted(termination: &utils::shutdown::Termination) -> bool {
+    let Some(app) = security::lineage::original_parent()
+        .or_else(utils::process::get_parent_pid)
+        .filter(|&pid| pid > 1)
+    else {
+        return false;
+    };
+    if termination.sender == Some(app) {
+        return false;
+    }
+    // Signals to the whole process group (Ctrl+C, timeout, a service stop)
+    // take the app down as well
+    thread::sleep(TERMINATION_SETTLE);
+    verification::seat::parent_alive(app)
+}
+
+/// Remember the highest trusted wall-clock time for the next run
+fn persist_clock_high_water(store: &StateStore, high_water: i64) {
+    let mut state = store.load();
+    if state.clock.high_water != high_water {
+        state.clock.high_water = high_water;
+        if let Err(e) = store.save(&state) {
+            log_warn!("⚠️  {}", e);
+        }
+    }
+}
+
+/// Publish the license lease to the control channel and the health monitor,
+/// warning when it lapses within the renewal lead
+///
+/// * `renewed` - Whether this check renewed the lease
+fn report_lease(renewal: &RenewalScheduler, now: i64, renewed: bool, health_monitor: &Option<HealthMonitor>) {
+    let expiring = renewal.expiring(now);
+    if expiring {
+        let expires_in = renewal.expires_at().unwrap_or(now) - now;
+        if renewed {
+            log_warn!("⏳ License lease expires in {}s", expires_in);
+        } else {
+            log_warn!("⏳ License lease expires in {}s and could not be renewed - retrying", expires_in);
+        }
+    }
+    utils::control::set_lease(renewal.expires_at(), expiring || renewal.expired(now));
+    if let Some(hm) = health_monitor {
+        hm.set_license_expiry(renewal.expires_at());
+        hm.set_lease_expiring(expiring);
+    }
+}
+
+/// Remember the failed verifications in a row for the next run
+fn persist_consecutive_failures(store: &StateStore, failures: u32) {
+    if store.load().consecutive_failures == failures {
+        return;
+    }
+    if let Err(e) = store.update(|state| state.consecutive_failures = failures) {
+        log_warn!("⚠️  {}", e);
+    }
+}
+
+/// Report failure to the parent wrapper, stop the base and execute the kill method
+fn enforce_unauthorized(
+    health_monitor: &Option<HealthMonitor>,
+    kill_method: &config::KillMethod,
+    config: &config::Config,
+) -> ! {
+    // Update health status: failure
+    if let Some(hm) = health_monitor {
+        hm.update(false);
+        hm.request_kill_base();
+
+        // Try to kill base directly if PID is known
+        // The PID comes from shared memory; never signal another user's session
+        if let Some(base_pid) = hm.get_base_pid()
+            && utils::session::is_same_session(base_pid as u32)
+        {
+            log_debug!("🎯 Found base PID: {}, killing it directly...", base_pid);
+            if let Err(e) = security::kill_parent::stop_parent(base_pid as u32) {
+                log_warn!("⚠️ Failed to stop base process: {}", e);
+            }
+        }
+    }
+    
+    // No-op if the caller already reported a more specific reason
+    utils::summary::emit(Outcome::Unauthorized, "enforcement triggered");
+    
+    // Execute kill method on parent binary (use runtime value)
+    log_error!("🚨 Executing kill method: {:?}", kill_method);
+    security::kill_parent::execute_kill(kill_method, config);
+    
+    // Should not reach here if kill succeeded
+    utils::exit_status::exit(ExitStatus::Unauthorized, "enforcement triggered");
+}
@@ -1,19 +1,15 @@
-/// KillCode Overload Binary - License Verification & Self-Destruct
-/// 
-/// This binary is embedded into protected executables and performs:
-/// 1. License verification via HMAC-authenticated API calls
-/// 2. Machine fingerprinting
-/// 3. Secure self-deletion on unauthorized access
-/// 4. Sync/Async execution modes
+//! KillCode Overload Binary - License Verification & Self-Destruct
+//! 
+//! This binary is embedded into protected executables and performs:
+//! 1. License verification via HMAC-authenticated API calls
+//! 2. Machine fingerprinting
+//! 3. Secure self-deletion on unauthorized access
+//! 4. Sync/Async execution modes
 
-// Module declarations
-mod config;
-mod verification;
-mod execution;
-mod security;
-mod utils;
+use kc_killer::{config, execution, security, utils};
+use kc_killer::log_error;
 
-use std::process::exit;
+use utils::exit_status::{self, ExitStatus};
 use config::{load_config, ExecutionMode};
 use security::secure_delete_self;
 
@@ -22,14 +18,17 @@ fn main() {
     let config = match load_config() {
         Ok(cfg) => cfg,
         Err(e) => {
-            eprintln!("❌ Failed to load configuration: {}", e);
+            utils::logger::configure_default();
+            log_error!("❌ Failed to load configuration: {}", e);
+            exit_status::record(ExitStatus::ConfigMissing, &e);
             if std::env::var("OVERLOAD_NO_DESTRUCT").is_err() {
-                secure_delete_self();
+                secure_delete_self(&security::WipePlan::default());
             } else {
-                exit(1);
+                exit_status::exit(ExitStatus::ConfigMissing, &e);
             }
         }
     };
+    utils::logger::configure(&config);
 
     // Execute based on mode
     match config.execution_mode {
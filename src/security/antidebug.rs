@@ -0,0 +1,247 @@
+//! Anti-debugging detection
+//!
+//! Detects an attached debugger/tracer on every supported platform:
+//! - Linux: `TracerPid` in /proc/self/status, plus a ptrace attach probe
+//! - macOS: `P_TRACED` flag from sysctl(KERN_PROC_PID)
+//! - Windows: `IsDebuggerPresent` / `CheckRemoteDebuggerPresent`
+//!
+//! A detection is treated like an unauthorized verification result by the caller.
+//!
+//! `detect_debugger_in` runs the same checks against a spawned child (the
+//! base binary in async mode), apart from the ptrace probe, which only works
+//! on ourselves.
+
+/// Check whether a debugger is attached to this process
+///
+/// # Returns
+/// Some(description of the detection) if a debugger was found, None otherwise
+pub fn detect_debugger() -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        if let Ok(status) = std::fs::read_to_string("/proc/self/status")
+            && let Some(tracer) = parse_tracer_pid(&status)
+            && tracer != 0
+        {
+            return Some(format!("TracerPid={}", tracer));
+        }
+
+        if ptrace_probe_detects_tracer() {
+            return Some("ptrace attach refused".to_string());
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        if macos_is_traced(unsafe { libc::getpid() }) {
+            return Some("P_TRACED flag set".to_string());
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        use winapi::um::debugapi::{CheckRemoteDebuggerPresent, IsDebuggerPresent};
+        use winapi::um::processthreadsapi::GetCurrentProcess;
+
+        unsafe {
+            if IsDebuggerPresent() != 0 {
+                return Some("IsDebuggerPresent".to_string());
+            }
+
+            let mut remote = 0;
+            if CheckRemoteDebuggerPresent(GetCurrentProcess(), &mut remote) != 0 && remote != 0 {
+                return Some("CheckRemoteDebuggerPresent".to_string());
+            }
+        }
+    }
+
+    None
+}
+
+/// Check whether a debugger is attached to a child process
+///
+/// # Returns
+/// Some(description of the detection) if a debugger was found, None otherwise
+pub fn detect_debugger_in(child: &std::process::Child) -> Option<String> {
+    #[cfg(target_os = "linux")]
+    if let Ok(status) = std::fs::read_to_string(format!("/proc/{}/status", child.id()))
+        && let Some(tracer) = parse_tracer_pid(&status)
+        && tracer != 0
+    {
+        return Some(format!("TracerPid={}", tracer));
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        if macos_is_traced(child.id() as i32) {
+            return Some("P_TRACED flag set".to_string());
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::io::AsRawHandle;
+        use winapi::um::debugapi::CheckRemoteDebuggerPresent;
+
+        let mut remote = 0;
+        if unsafe { CheckRemoteDebuggerPresent(child.as_raw_handle() as _, &mut remote) } != 0 && remote != 0 {
+            return Some("CheckRemoteDebuggerPresent".to_string());
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+    let _ = child;
+
+    None
+}
+
+/// Extract the TracerPid value from /proc/<pid>/status contents
+pub fn parse_tracer_pid(status: &str) -> Option<i32> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("TracerPid:"))
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// Probe for a tracer by attaching to ourselves from a forked child
+///
+/// A process can only have one tracer, so PTRACE_ATTACH fails with EPERM if
+/// one exists. Seccomp filters, LSMs and missing capabilities refuse it with
+/// EPERM as well, so a refusal only counts while TracerPid confirms a tracer
+/// (one that attached between the two reads). With Yama ptrace_scope >= 1 a
+/// child may never attach to its parent, so the probe only runs at scope 0.
+#[cfg(target_os = "linux")]
+fn ptrace_probe_detects_tracer() -> bool {
+    let scope = std::fs::read_to_string("/proc/sys/kernel/yama/ptrace_scope")
+        .ok()
+        .and_then(|s| s.trim().parse::<i32>().ok())
+        .unwrap_or(0);
+    if scope != 0 {
+        return false;
+    }
+
+    // A non-dumpable process (see `secrets::harden_process`) refuses attaches
+    // from its own children, which would read as a tracer; lift it for the probe
+    let dumpable = unsafe { libc::prctl(libc::PR_GET_DUMPABLE, 0, 0, 0, 0) };
+    if dumpable == 0 {
+        unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 1, 0, 0, 0) };
+    }
+    let refused = unsafe { ptrace_attach_refused() };
+    if dumpable == 0 {
+        unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) };
+    }
+    refused
+        && std::fs::read_to_string("/proc/self/status")
+            .ok()
+            .and_then(|status| parse_tracer_pid(&status))
+            .is_some_and(|tracer| tracer != 0)
+}
+
+/// Fork a child that tries to PTRACE_ATTACH to us; true if it was refused
+/// with EPERM
+#[cfg(target_os = "linux")]
+unsafe fn ptrace_attach_refused() -> bool {
+    unsafe {
+        let target = libc::getpid();
+        let child = libc::fork();
+
+        if child < 0 {
+            return false;
+        }
+
+        if child == 0 {
+            // Child: only async-signal-safe calls from here on
+            let code = if libc::ptrace(libc::PTRACE_ATTACH, target, 0, 0) == 0 {
+                let mut status = 0;
+                libc::waitpid(target, &mut status, 0);
+                libc::ptrace(libc::PTRACE_DETACH, target, 0, 0);
+                0
+            } else if *libc::__errno_location() == libc::EPERM {
+                1
+            } else {
+                2
+            };
+            libc::_exit(code);
+        }
+
+        let mut status = 0;
+        if libc::waitpid(child, &mut status, 0) != child {
+            return false;
+        }
+        libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 1
+    }
+}
+
+/// Read P_TRACED from the kinfo_proc of process `pid`
+#[cfg(target_os = "macos")]
+fn macos_is_traced(pid: i32) -> bool {
+    // libc does not expose kinfo_proc for Apple targets, so read p_flag by offset:
+    // kinfo_proc.kp_proc starts with p_un (2 pointers), p_vmspace and p_sigacts.
+    const P_FLAG_OFFSET: usize = 4 * std::mem::size_of::<usize>();
+    const P_TRACED: i32 = 0x0000_0800;
+
+    let mut info = [0u64; 128];
+    let mut size = std::mem::size_of_val(&info);
+    let mut mib = [libc::CTL_KERN, libc::KERN_PROC, libc::KERN_PROC_PID, pid];
+
+    let ret = unsafe {
+        libc::sysctl(
+            mib.as_mut_ptr(),
+            mib.len() as u32,
+            info.as_mut_ptr() as *mut libc::c_void,
+            &mut size,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+
+    if ret != 0 || size < P_FLAG_OFFSET + 4 {
+        return false;
+    }
+
+    let bytes = unsafe { std::slice::from_raw_parts(info.as_ptr() as *const u8, size) };
+    let flag = i32::from_ne_bytes([
+        bytes[P_FLAG_OFFSET],
+        bytes[P_FLAG_OFFSET + 1],
+        bytes[P_FLAG_OFFSET + 2],
+        bytes[P_FLAG_OFFSET + 3],
+    ]);
+    flag & P_TRACED != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tracer_pid() {
+        let status = "Name:\tkiller\nState:\tR (running)\nTracerPid:\t0\nUid:\t1000\n";
+        assert_eq!(parse_tracer_pid(status), Some(0));
+
+        let traced = "Name:\tkiller\nTracerPid:\t4242\n";
+        assert_eq!(parse_tracer_pid(traced), Some(4242));
+
+        assert_eq!(parse_tracer_pid("Name:\tkiller\n"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_debugger_in_child() {
+        let mut child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+        assert_eq!(detect_debugger_in(&child), None);
+
+        // Tracing our own child is allowed under every Yama scope but 3; the
+        // tracer is this thread
+        let pid = child.id() as libc::pid_t;
+        let traced = unsafe { libc::ptrace(libc::PTRACE_ATTACH, pid, 0, 0) } == 0;
+        let detection = traced.then(|| {
+            let mut status = 0;
+            unsafe { libc::waitpid(pid, &mut status, 0) };
+            detect_debugger_in(&child)
+        });
+        let _ = child.kill();
+        let _ = child.wait();
+        if let Some(detection) = detection {
+            assert_eq!(detection, Some(format!("TracerPid={}", unsafe { libc::gettid() })));
+        }
+    }
+}
@@ -0,0 +1,280 @@
+//! Library injection detection
+//!
+//! An injected library is the standard way to hook `verify_license` and
+//! neuter it from inside the process, so a detection is treated like an
+//! unauthorized verification result by the caller. Checked on every platform:
+//! - preload variables: `LD_PRELOAD`, `LD_AUDIT`, `DYLD_INSERT_LIBRARIES`
+//! - Linux: `/etc/ld.so.preload`, and executable mappings in
+//!   /proc/self/maps outside the system library directories (including
+//!   `memfd:` images)
+//! - macOS: dyld images outside the system library directories
+//! - Windows: `AppInit_DLLs` (when loading them is enabled), modules of known
+//!   hooking frameworks, and the `.detour` section Detours adds to the image
+//!   of a process it injects into
+//!
+//! Libraries under `trusted_library_paths` (path prefixes) are never reported.
+
+/// Environment variables that make the loader inject a library
+pub const PRELOAD_VARS: [&str; 3] = ["LD_PRELOAD", "LD_AUDIT", "DYLD_INSERT_LIBRARIES"];
+
+/// Directories system libraries are loaded from
+#[cfg(target_os = "linux")]
+const SYSTEM_LIBRARY_DIRS: &[&str] = &["/lib/", "/lib32/", "/lib64/", "/usr/lib/", "/usr/lib32/", "/usr/lib64/", "/nix/store/"];
+#[cfg(target_os = "macos")]
+const SYSTEM_LIBRARY_DIRS: &[&str] = &["/usr/lib/", "/System/Library/", "/System/iOSSupport/"];
+
+// dyld image list (libc only has deprecated bindings)
+#[cfg(target_os = "macos")]
+unsafe extern "C" {
+    fn _dyld_image_count() -> u32;
+    fn _dyld_get_image_name(image_index: u32) -> *const libc::c_char;
+}
+
+/// Check whether a library was injected into this process
+///
+/// # Returns
+/// Some(description of the detection) if one was found, None otherwise
+pub fn detect_injection(trusted: &[String]) -> Option<String> {
+    for var in PRELOAD_VARS {
+        if let Ok(value) = std::env::var(var)
+            && let Some(library) = untrusted_entry(&value, trusted)
+        {
+            return Some(format!("{}={}", var, library));
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        if let Ok(preload) = std::fs::read_to_string("/etc/ld.so.preload") {
+            let entries: String = preload.lines().filter(|line| !line.trim_start().starts_with('#')).collect::<Vec<_>>().join(" ");
+            if let Some(library) = untrusted_entry(&entries, trusted) {
+                return Some(format!("/etc/ld.so.preload lists {}", library));
+            }
+        }
+
+        let own_exe = std::fs::read_link("/proc/self/exe").ok();
+        if let Ok(maps) = std::fs::read_to_string("/proc/self/maps")
+            && let Some(module) = parse_executable_maps(&maps)
+                .into_iter()
+                .find(|path| own_exe.as_deref() != Some(std::path::Path::new(path)) && unexpected_module(path, trusted))
+        {
+            return Some(format!("unexpected module {}", module));
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let own_exe = std::env::current_exe().ok();
+        // Image 0 is the main executable
+        for index in 1..unsafe { _dyld_image_count() } {
+            let name = unsafe { _dyld_get_image_name(index) };
+            if name.is_null() {
+                continue;
+            }
+            let path = unsafe { std::ffi::CStr::from_ptr(name) }.to_string_lossy();
+            if own_exe.as_deref() != Some(std::path::Path::new(path.as_ref())) && unexpected_module(&path, trusted) {
+                return Some(format!("unexpected module {}", path));
+            }
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        if let Some(detection) = windows::app_init_dlls(trusted) {
+            return Some(detection);
+        }
+        if let Some(detection) = windows::hooking_modules(trusted) {
+            return Some(detection);
+        }
+    }
+
+    None
+}
+
+/// First library in a preload list (separated by `:` or whitespace) that is
+/// not trusted
+fn untrusted_entry<'a>(list: &'a str, trusted: &[String]) -> Option<&'a str> {
+    list.split(|c: char| c == ':' || c.is_whitespace())
+        .filter(|entry| !entry.is_empty())
+        .find(|entry| !is_trusted(entry, trusted))
+}
+
+fn is_trusted(path: &str, trusted: &[String]) -> bool {
+    trusted.iter().any(|prefix| !prefix.is_empty() && path.starts_with(prefix.as_str()))
+}
+
+/// A loaded module outside the system library directories and not trusted
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn unexpected_module(path: &str, trusted: &[String]) -> bool {
+    !SYSTEM_LIBRARY_DIRS.iter().any(|dir| path.starts_with(dir)) && !is_trusted(path, trusted)
+}
+
+/// Files mapped executable, from /proc/<pid>/maps contents (in order,
+/// without duplicates)
+pub fn parse_executable_maps(maps: &str) -> Vec<&str> {
+    let mut paths: Vec<&str> = Vec::new();
+    for line in maps.lines() {
+        // address perms offset dev inode pathname (which may contain spaces)
+        let mut rest = line;
+        let mut fields = [""; 5];
+        for field in &mut fields {
+            rest = rest.trim_start();
+            let end = rest.find(' ').unwrap_or(rest.len());
+            *field = &rest[..end];
+            rest = &rest[end..];
+        }
+        let path = rest.trim();
+        // Pseudo mappings ([vdso], [stack], ...) have no leading slash
+        if fields[1].contains('x') && path.starts_with('/') && !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::os::windows::ffi::{OsStrExt, OsStringExt};
+    use std::ptr;
+
+    use winapi::shared::minwindef::{DWORD, HMODULE};
+    use winapi::shared::winerror::ERROR_SUCCESS;
+    use winapi::um::processthreadsapi::GetCurrentProcess;
+    use winapi::um::psapi::{EnumProcessModules, GetModuleFileNameExW};
+    use winapi::um::winnt::{IMAGE_DOS_HEADER, IMAGE_NT_HEADERS, IMAGE_SECTION_HEADER};
+    use winapi::um::winreg::{RegGetValueW, HKEY_LOCAL_MACHINE, RRF_RT_REG_DWORD, RRF_RT_REG_SZ};
+
+    use super::is_trusted;
+
+    /// File names of hooking framework DLLs (lowercase prefixes)
+    const HOOKING_MODULES: [&str; 4] = ["detoured", "easyhook", "minhook", "frida-agent"];
+
+    const WINDOWS_KEY: &str = "SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\\Windows";
+
+    fn wide(text: &str) -> Vec<u16> {
+        std::ffi::OsStr::new(text).encode_wide().chain(Some(0)).collect()
+    }
+
+    /// DLLs user32 loads into every GUI process when `LoadAppInit_DLLs` is set
+    pub fn app_init_dlls(trusted: &[String]) -> Option<String> {
+        let key = wide(WINDOWS_KEY);
+        let mut enabled: DWORD = 0;
+        let mut size = std::mem::size_of::<DWORD>() as DWORD;
+        let status = unsafe {
+            RegGetValueW(
+                HKEY_LOCAL_MACHINE,
+                key.as_ptr(),
+                wide("LoadAppInit_DLLs").as_ptr(),
+                RRF_RT_REG_DWORD,
+                ptr::null_mut(),
+                &mut enabled as *mut DWORD as *mut _,
+                &mut size,
+            )
+        };
+        if status != ERROR_SUCCESS as i32 || enabled == 0 {
+            return None;
+        }
+
+        let mut buffer = [0u16; 2048];
+        let mut size = std::mem::size_of_val(&buffer) as DWORD;
+        let status = unsafe {
+            RegGetValueW(
+                HKEY_LOCAL_MACHINE,
+                key.as_ptr(),
+                wide("AppInit_DLLs").as_ptr(),
+                RRF_RT_REG_SZ,
+                ptr::null_mut(),
+                buffer.as_mut_ptr() as *mut _,
+                &mut size,
+            )
+        };
+        if status != ERROR_SUCCESS as i32 {
+            return None;
+        }
+        let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+        let dlls = String::from_utf16_lossy(&buffer[..len]);
+        // Entries are separated by spaces or commas (paths contain colons)
+        dlls.split([' ', ','])
+            .find(|dll| !dll.is_empty() && !is_trusted(dll, trusted))
+            .map(|dll| format!("AppInit_DLLs loads {}", dll))
+    }
+
+    /// Loaded hooking framework DLLs, or the Detours marker section
+    pub fn hooking_modules(trusted: &[String]) -> Option<String> {
+        let process = unsafe { GetCurrentProcess() };
+        let mut modules: [HMODULE; 1024] = [ptr::null_mut(); 1024];
+        let mut needed: DWORD = 0;
+        if unsafe { EnumProcessModules(process, modules.as_mut_ptr(), std::mem::size_of_val(&modules) as DWORD, &mut needed) } == 0 {
+            return None;
+        }
+        let count = (needed as usize / std::mem::size_of::<HMODULE>()).min(modules.len());
+
+        // Module 0 is the executable
+        if count > 0 && unsafe { has_section(modules[0], b".detour") } {
+            return Some("Detours section in the process image".to_string());
+        }
+
+        for &module in &modules[..count] {
+            let mut name = [0u16; 1024];
+            let len = unsafe { GetModuleFileNameExW(process, module, name.as_mut_ptr(), name.len() as DWORD) } as usize;
+            let path = std::ffi::OsString::from_wide(&name[..len]).to_string_lossy().into_owned();
+            let file = path.rsplit('\\').next().unwrap_or(&path).to_ascii_lowercase();
+            if HOOKING_MODULES.iter().any(|hook| file.starts_with(hook)) && !is_trusted(&path, trusted) {
+                return Some(format!("hooking module {}", path));
+            }
+        }
+        None
+    }
+
+    /// Whether the PE image loaded at `module` has a section named `name`
+    unsafe fn has_section(module: HMODULE, name: &[u8]) -> bool {
+        unsafe {
+            let base = module as *const u8;
+            let dos = &*(base as *const IMAGE_DOS_HEADER);
+            let nt = &*(base.offset(dos.e_lfanew as isize) as *const IMAGE_NT_HEADERS);
+            let first = (&nt.OptionalHeader as *const _ as *const u8).add(nt.FileHeader.SizeOfOptionalHeader as usize)
+                as *const IMAGE_SECTION_HEADER;
+            (0..nt.FileHeader.NumberOfSections as usize).any(|i| {
+                let section = &*first.add(i);
+                section.Name.starts_with(name) && section.Name[name.len()..].iter().all(|&b| b == 0)
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_executable_maps() {
+        let maps = "\
+55d0c0a00000-55d0c0a21000 r--p 00000000 fd:01 1835  /opt/app/killer
+55d0c0a21000-55d0c0b00000 r-xp 00021000 fd:01 1835  /opt/app/killer
+7f1e2c000000-7f1e2c1c0000 r-xp 00028000 fd:01 4212  /usr/lib/x86_64-linux-gnu/libc.so.6
+7f1e2d000000-7f1e2d010000 r-xp 00000000 fd:01 9931  /tmp/hook dir/libhook.so
+7f1e2e000000-7f1e2e001000 r-xp 00000000 00:01 7     /memfd:payload (deleted)
+7ffd3b9f0000-7ffd3b9f2000 r-xp 00000000 00:00 0     [vdso]
+7ffd3ba00000-7ffd3ba21000 rw-p 00000000 00:00 0     [stack]
+";
+        assert_eq!(
+            parse_executable_maps(maps),
+            ["/opt/app/killer", "/usr/lib/x86_64-linux-gnu/libc.so.6", "/tmp/hook dir/libhook.so", "/memfd:payload (deleted)"]
+        );
+    }
+
+    #[test]
+    fn test_preload_entries_and_trust() {
+        let trusted = vec!["/opt/vendor/".to_string()];
+        assert_eq!(untrusted_entry("/opt/vendor/libsafe.so:/tmp/libhook.so", &trusted), Some("/tmp/libhook.so"));
+        assert_eq!(untrusted_entry("/opt/vendor/libsafe.so  ", &trusted), None);
+        assert_eq!(untrusted_entry("", &trusted), None);
+
+        #[cfg(target_os = "linux")]
+        {
+            assert!(!unexpected_module("/usr/lib/x86_64-linux-gnu/libc.so.6", &trusted));
+            assert!(!unexpected_module("/opt/vendor/libsafe.so", &trusted));
+            assert!(unexpected_module("/memfd:payload (deleted)", &trusted));
+        }
+    }
+}
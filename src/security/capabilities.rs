@@ -0,0 +1,110 @@
+//! Per-platform enforcement capabilities
+//!
+//! Not every kill method works everywhere: on APFS (macOS) writes go to new
+//! blocks, so an in-place overwrite leaves the original data intact and
+//! "shred" is no better than "delete". Killer reports what it can actually do
+//! with each verification, and downgrades server or config instructions it
+//! cannot honour to the strongest supported method, with an audit entry,
+//! instead of attempting them and half-failing.
+
+use serde::Serialize;
+
+use crate::config::KillMethod;
+use crate::utils::audit;
+use crate::utils::platform::{self, CpuFeatures};
+
+/// Enforcement capability set of this build/platform
+#[derive(Debug, Clone, Serialize)]
+pub struct Capabilities {
+    pub platform: &'static str,
+    /// Kill methods that are fully effective here
+    pub kill_methods: Vec<KillMethod>,
+    /// Whether overwriting a file in place destroys its previous contents
+    pub in_place_overwrite: bool,
+    /// Runtime-detected CPU features (crypto backend selection)
+    pub cpu: CpuFeatures,
+}
+
+/// Capabilities of the current platform
+pub fn current() -> Capabilities {
+    let in_place_overwrite = !cfg!(target_os = "macos");
+
+    // Corrupting the header works even on copy-on-write filesystems: the file
+    // as seen by the loader changes, which is all neutralization needs
+    let mut kill_methods = vec![KillMethod::Stop, KillMethod::Delete, KillMethod::Corrupt, KillMethod::Lock];
+    if in_place_overwrite {
+        kill_methods.push(KillMethod::Shred);
+    }
+
+    Capabilities {
+        platform: std::env::consts::OS,
+        kill_methods,
+        in_place_overwrite,
+        cpu: platform::cpu_features(),
+    }
+}
+
+/// Resolve a requested kill method to one this platform supports
+///
+/// # Arguments
+/// * `requested` - Kill method from config or server
+/// * `source` - Where the instruction came from (for the audit entry)
+pub fn resolve_kill_method(requested: &KillMethod, source: &str) -> KillMethod {
+    let effective = supported_kill_method(requested);
+    if effective != *requested {
+        audit::record(
+            "kill_method_downgrade",
+            &format!(
+                "{} requested {:?}, unsupported on {}; using {:?}",
+                source, requested, std::env::consts::OS, effective
+            ),
+        );
+    }
+    effective
+}
+
+/// Kill method `requested` resolves to here, without recording anything
+pub fn supported_kill_method(requested: &KillMethod) -> KillMethod {
+    downgrade(requested, &current())
+}
+
+/// Strongest supported method not stronger than the requested one
+fn downgrade(requested: &KillMethod, capabilities: &Capabilities) -> KillMethod {
+    let fallbacks: &[KillMethod] = match requested {
+        KillMethod::Shred => &[KillMethod::Shred, KillMethod::Delete, KillMethod::Stop],
+        KillMethod::Corrupt => &[KillMethod::Corrupt, KillMethod::Delete, KillMethod::Stop],
+        KillMethod::Delete => &[KillMethod::Delete, KillMethod::Stop],
+        KillMethod::Lock => &[KillMethod::Lock, KillMethod::Stop],
+        KillMethod::Stop => &[KillMethod::Stop],
+    };
+
+    fallbacks
+        .iter()
+        .find(|method| capabilities.kill_methods.contains(method))
+        .cloned()
+        .unwrap_or(KillMethod::Stop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_downgrade_without_in_place_overwrite() {
+        let apfs = Capabilities {
+            platform: "macos",
+            kill_methods: vec![KillMethod::Stop, KillMethod::Delete],
+            in_place_overwrite: false,
+            cpu: CpuFeatures::default(),
+        };
+
+        assert_eq!(downgrade(&KillMethod::Shred, &apfs), KillMethod::Delete);
+        assert_eq!(downgrade(&KillMethod::Delete, &apfs), KillMethod::Delete);
+        assert_eq!(downgrade(&KillMethod::Stop, &apfs), KillMethod::Stop);
+    }
+
+    #[test]
+    fn test_current_always_supports_stop() {
+        assert!(current().kill_methods.contains(&KillMethod::Stop));
+    }
+}
@@ -0,0 +1,133 @@
+//! Clock tampering detection
+//!
+//! Winding the local clock back is the classic way to stretch expiry and grace
+//! windows. Each authorized check is validated against:
+//! - the signed server time (absolute drift beyond `max_clock_drift_secs`)
+//! - a monotonic baseline from the previous check (wall clock went backwards)
+//! - the highest wall-clock time ever seen on this machine (persisted)
+
+use std::time::Instant;
+
+use crate::utils::time;
+
+/// Backwards movement tolerated without a server anchor (NTP slews, rounding)
+const JUMP_TOLERANCE_SECS: i64 = 60;
+
+/// Tracks wall-clock behaviour across verification checks
+pub struct ClockGuard {
+    max_drift_secs: u64,
+    /// Monotonic instant and wall-clock time of the previous check
+    baseline: Option<(Instant, i64)>,
+    /// Highest wall-clock time seen (persisted across runs)
+    high_water: i64,
+}
+
+impl ClockGuard {
+    /// Create a guard; `high_water` is the last persisted wall-clock time
+    pub fn new(max_drift_secs: u64, high_water: i64) -> Self {
+        Self {
+            max_drift_secs,
+            baseline: None,
+            high_water,
+        }
+    }
+
+    /// Highest wall-clock time seen so far (to persist)
+    pub fn high_water(&self) -> i64 {
+        self.high_water
+    }
+
+    /// Validate the local clock at an authorized check
+    ///
+    /// # Arguments
+    /// * `local_now` - Local wall-clock time (unix seconds)
+    /// * `mono_now` - Monotonic instant taken together with `local_now`
+    /// * `server_time` - Server time from a signed response, if any
+    ///
+    /// # Returns
+    /// Err(description) if the clock looks tampered
+    pub fn check(&mut self, local_now: i64, mono_now: Instant, server_time: Option<i64>) -> Result<(), String> {
+        let verdict = match server_time {
+            // A trusted server anchor settles the question on its own: a clock
+            // that agrees with the server is correct, even if it just moved back
+            Some(server_time) => {
+                let drift = time::drift(local_now, server_time);
+                if self.max_drift_secs > 0 && drift.unsigned_abs() > self.max_drift_secs {
+                    Err(format!("local clock differs from server by {}s", drift))
+                } else {
+                    Ok(())
+                }
+            }
+            None => self.check_without_anchor(local_now, mono_now),
+        };
+
+        if verdict.is_ok() {
+            self.baseline = Some((mono_now, local_now));
+            self.high_water = if server_time.is_some() {
+                // Server-confirmed time replaces a mark left by a fast clock
+                local_now
+            } else {
+                self.high_water.max(local_now)
+            };
+        }
+
+        verdict
+    }
+
+    fn check_without_anchor(&self, local_now: i64, mono_now: Instant) -> Result<(), String> {
+        // The monotonic clock may pause during suspend, so only a wall clock
+        // that falls behind it is suspicious
+        if let Some((base_mono, base_wall)) = self.baseline {
+            let expected = base_wall + mono_now.saturating_duration_since(base_mono).as_secs() as i64;
+            if local_now < expected - JUMP_TOLERANCE_SECS {
+                return Err(format!("wall clock jumped backwards by {}s", expected - local_now));
+            }
+        }
+
+        if local_now < self.high_water - JUMP_TOLERANCE_SECS {
+            return Err(format!(
+                "wall clock is {}s behind the last time seen on this machine",
+                self.high_water - local_now
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_drift_detection() {
+        let mut guard = ClockGuard::new(300, 0);
+        let now = Instant::now();
+
+        assert!(guard.check(10_000, now, Some(10_100)).is_ok());
+        assert!(guard.check(10_000, now, Some(20_000)).is_err());
+        assert!(guard.check(20_000, now, Some(10_000)).is_err());
+    }
+
+    #[test]
+    fn test_backwards_jump_without_server_time() {
+        let mut guard = ClockGuard::new(300, 0);
+        let start = Instant::now();
+
+        assert!(guard.check(50_000, start, None).is_ok());
+        // Same monotonic instant, wall clock wound back a day
+        assert!(guard.check(50_000 - 86_400, start, None).is_err());
+        // Small corrections are tolerated
+        assert!(guard.check(49_990, start, None).is_ok());
+    }
+
+    #[test]
+    fn test_persisted_high_water_mark() {
+        let mut guard = ClockGuard::new(300, 1_000_000);
+        assert!(guard.check(900_000, Instant::now(), None).is_err());
+
+        // A signed server time vouches for the rewound clock
+        assert!(guard.check(900_000, Instant::now(), Some(900_010)).is_ok());
+        assert_eq!(guard.high_water(), 900_000);
+    }
+}
@@ -0,0 +1,202 @@
+//! Instant neutralization of a binary by corrupting its header and entry point
+//!
+//! Shredding a multi-gigabyte binary takes long enough for the user to power
+//! off midway. Overwriting just the executable header and the code at the
+//! entry point with random data makes the file unexecutable within
+//! milliseconds; a full shred can follow at leisure.
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Bytes overwritten at the header and at the entry point
+const CORRUPT_REGION: usize = 4096;
+
+/// Leading bytes read to locate the entry point (headers and load commands)
+const HEADER_SCAN: usize = 64 * 1024;
+
+/// Overwrite header and entry point with random data and sync
+pub fn corrupt_binary(path: &Path) -> Result<(), String> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open {} for corruption: {}", path.display(), e))?;
+    let size = file.metadata()
+        .map_err(|e| format!("Failed to get file metadata: {}", e))?
+        .len();
+
+    let header = read_header(&mut file)?;
+    let entry = entry_point_offset(&header).filter(|&offset| offset < size);
+
+    overwrite_random(&mut file, 0, CORRUPT_REGION.min(size as usize))?;
+    match entry {
+        Some(offset) => {
+            log_debug!("🎯 Entry point at file offset 0x{:x}", offset);
+            overwrite_random(&mut file, offset, CORRUPT_REGION.min((size - offset) as usize))?;
+        }
+        None => log_warn!("⚠️  Entry point not found - header corrupted only"),
+    }
+
+    file.sync_all().map_err(|e| format!("Failed to sync: {}", e))
+}
+
+fn read_header(file: &mut File) -> Result<Vec<u8>, String> {
+    let mut header = Vec::with_capacity(HEADER_SCAN);
+    Read::by_ref(file)
+        .take(HEADER_SCAN as u64)
+        .read_to_end(&mut header)
+        .map_err(|e| format!("Failed to read header: {}", e))?;
+    Ok(header)
+}
+
+fn overwrite_random(file: &mut File, offset: u64, len: usize) -> Result<(), String> {
+    let noise: Vec<u8> = (0..len).map(|_| rand::random::<u8>()).collect();
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.write_all(&noise))
+        .map_err(|e| format!("Failed to overwrite at 0x{:x}: {}", offset, e))
+}
+
+/// File offset of the entry point of an ELF, PE or Mach-O 64 image
+pub fn entry_point_offset(data: &[u8]) -> Option<u64> {
+    if data.starts_with(b"\x7fELF") {
+        elf_entry(data)
+    } else if data.starts_with(b"MZ") {
+        pe_entry(data)
+    } else if data.starts_with(&[0xCF, 0xFA, 0xED, 0xFE]) {
+        macho_entry(data)
+    } else {
+        None
+    }
+}
+
+fn elf_entry(data: &[u8]) -> Option<u64> {
+    let is_64 = *data.get(4)? == 2;
+    let (entry, phoff, phentsize, phnum) = if is_64 {
+        (read_u64(data, 0x18)?, read_u64(data, 0x20)?, read_u16(data, 0x36)?, read_u16(data, 0x38)?)
+    } else {
+        (read_u32(data, 0x18)? as u64, read_u32(data, 0x1C)? as u64, read_u16(data, 0x2A)?, read_u16(data, 0x2C)?)
+    };
+
+    const PT_LOAD: u32 = 1;
+    (0..phnum as usize).find_map(|i| {
+        let ph = (phoff as usize).checked_add(i.checked_mul(phentsize as usize)?)?;
+        if read_u32(data, ph)? != PT_LOAD {
+            return None;
+        }
+        let (offset, vaddr, filesz) = if is_64 {
+            (read_u64(data, ph + 8)?, read_u64(data, ph + 16)?, read_u64(data, ph + 32)?)
+        } else {
+            (read_u32(data, ph + 4)? as u64, read_u32(data, ph + 8)? as u64, read_u32(data, ph + 16)? as u64)
+        };
+        (entry >= vaddr && entry < vaddr.checked_add(filesz)?).then(|| offset.checked_add(entry - vaddr))?
+    })
+}
+
+fn pe_entry(data: &[u8]) -> Option<u64> {
+    // Every offset comes from the file: a crafted header must not overflow
+    let pe = read_u32(data, 0x3C)? as usize;
+    if data.get(pe..pe.checked_add(4)?)? != b"PE\0\0" {
+        return None;
+    }
+    let sections = read_u16(data, pe + 6)? as usize;
+    let optional_size = read_u16(data, pe + 20)? as usize;
+    let optional = pe + 24;
+    let entry_rva = read_u32(data, optional + 16)?;
+
+    let table = optional + optional_size;
+    (0..sections).find_map(|i| {
+        let section = table + i * 40;
+        let virtual_size = read_u32(data, section + 8)?;
+        let virtual_address = read_u32(data, section + 12)?;
+        let raw_pointer = read_u32(data, section + 20)?;
+        (entry_rva >= virtual_address && entry_rva < virtual_address.checked_add(virtual_size)?)
+            .then(|| raw_pointer.checked_add(entry_rva - virtual_address).map(u64::from))?
+    })
+}
+
+fn macho_entry(data: &[u8]) -> Option<u64> {
+    const LC_MAIN: u32 = 0x8000_0028;
+
+    let ncmds = read_u32(data, 16)?;
+    let mut cmd = 32;
+    for _ in 0..ncmds {
+        let kind = read_u32(data, cmd)?;
+        let size = read_u32(data, cmd + 4)? as usize;
+        if kind == LC_MAIN {
+            return read_u64(data, cmd + 8);
+        }
+        if size == 0 {
+            return None;
+        }
+        cmd = cmd.checked_add(size)?;
+    }
+    None
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset.checked_add(2)?)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset.checked_add(4)?)?.try_into().ok()?))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(offset..offset.checked_add(8)?)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_entry_point_of_own_binary() {
+        let exe = std::env::current_exe().unwrap();
+        let data = fs::read(&exe).unwrap();
+
+        // ELF on the BSDs too
+        #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "openbsd"))]
+        {
+            let offset = entry_point_offset(&data).unwrap();
+            assert!(offset > 0 && offset < data.len() as u64);
+        }
+
+        assert_eq!(entry_point_offset(b"not a binary"), None);
+    }
+
+    #[test]
+    fn test_crafted_pe_header_does_not_overflow() {
+        let mut data = vec![0u8; 0x200];
+        data[..2].copy_from_slice(b"MZ");
+        // e_lfanew right at the top of the address space
+        data[0x3C..0x40].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(entry_point_offset(&data), None);
+
+        // A section whose end wraps around
+        data[0x3C..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+        data[0x40..0x44].copy_from_slice(b"PE\0\0");
+        data[0x46..0x48].copy_from_slice(&1u16.to_le_bytes());
+        data[0x68..0x6C].copy_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+        let section = 0x40 + 24;
+        data[section + 8..section + 12].copy_from_slice(&0x100u32.to_le_bytes());
+        data[section + 12..section + 16].copy_from_slice(&0xFFFF_FF00u32.to_le_bytes());
+        data[section + 20..section + 24].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(entry_point_offset(&data), None);
+    }
+
+    #[test]
+    fn test_corrupt_binary_changes_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("victim");
+        let original = fs::read(std::env::current_exe().unwrap()).unwrap();
+        fs::write(&path, &original).unwrap();
+
+        corrupt_binary(&path).unwrap();
+
+        let corrupted = fs::read(&path).unwrap();
+        assert_eq!(corrupted.len(), original.len());
+        assert_ne!(corrupted[..CORRUPT_REGION], original[..CORRUPT_REGION]);
+    }
+}
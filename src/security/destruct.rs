@@ -1,168 +1,400 @@
 /// Secure binary deletion on unauthorized access
 use std::fs;
 use std::io::{Seek, SeekFrom, Write};
-use std::process::exit;
+use std::path::Path;
+#[cfg(windows)]
+use std::time::{Duration, Instant};
+use super::erase;
+use crate::utils::exit_status::{self, ExitStatus};
+use serde::{Deserialize, Serialize};
+use crate::config::Config;
+use crate::config::schema::ShredPattern;
+#[cfg(windows)]
+use crate::utils::process::process_table;
+use crate::verification::kill_report;
+
+/// Env var marking the detached helper that wipes an executable once the
+/// process running it has exited (token of a `SelfDeleteJob`, see
+/// `utils::helper`)
+#[cfg(windows)]
+pub const SELF_DELETE_ENV: &str = "KILLCODE_SELF_DELETE";
+
+/// Helper job kind of the self-deletion helper
+#[cfg(windows)]
+const SELF_DELETE_JOB: &str = "self-delete";
+
+/// How long the helper waits for the process to exit and its image to unlock
+#[cfg(windows)]
+const SELF_DELETE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Overwrite passes and data pattern, shared by secure deletion and shredding
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WipePlan {
+    pub passes: u32,
+    pub pattern: ShredPattern,
+}
+
+impl WipePlan {
+    pub const DEFAULT_PASSES: u32 = 3;
+
+    /// Overwrite buffer: memory use stays constant on multi-GB binaries
+    pub const CHUNK_SIZE: usize = 1024 * 1024;
+
+    /// Plan from `shred_passes`/`shred_pattern`, with a per-caller default pattern
+    pub fn from_config(config: &Config, default_pattern: ShredPattern) -> Self {
+        Self {
+            passes: config.shred_passes.unwrap_or(Self::DEFAULT_PASSES).max(1),
+            pattern: config.shred_pattern.unwrap_or(default_pattern),
+        }
+    }
+
+    /// Fill `buf` with the data of a pass (0-based)
+    pub fn fill(&self, pass: u32, buf: &mut [u8]) {
+        match self.pass_byte(pass) {
+            Some(byte) => buf.fill(byte),
+            None => buf.iter_mut().for_each(|b| *b = rand::random()),
+        }
+    }
+
+    /// Overwrite the first `len` bytes of `file` with the data of a pass
+    /// (0-based), streaming one `CHUNK_SIZE` buffer; random passes draw fresh
+    /// data per chunk
+    pub fn overwrite_pass(&self, pass: u32, file: &mut fs::File, len: u64) -> std::io::Result<()> {
+        file.seek(SeekFrom::Start(0))?;
+        let mut buf = vec![0u8; (Self::CHUNK_SIZE as u64).min(len) as usize];
+        let random = self.pass_byte(pass).is_none();
+        self.fill(pass, &mut buf);
+
+        let mut remaining = len;
+        while remaining > 0 {
+            let chunk = (buf.len() as u64).min(remaining) as usize;
+            if random {
+                self.fill(pass, &mut buf[..chunk]);
+            }
+            file.write_all(&buf[..chunk])?;
+            remaining -= chunk as u64;
+        }
+        file.flush()
+    }
+
+    /// Human-readable description of a pass
+    pub fn describe(&self, pass: u32) -> String {
+        match self.pass_byte(pass) {
+            Some(byte) => format!("0x{:02X}", byte),
+            None => "random data".to_string(),
+        }
+    }
+
+    /// Fixed byte of a pass, or None for random data
+    fn pass_byte(&self, pass: u32) -> Option<u8> {
+        let steps: &[Option<u8>] = match self.pattern {
+            ShredPattern::Zero => &[Some(0x00)],
+            ShredPattern::Random => &[None],
+            ShredPattern::Dod => &[Some(0x00), Some(0xFF), None],
+            ShredPattern::Classic => &[Some(0x00), Some(0xFF), Some(0xAA)],
+        };
+        steps[pass as usize % steps.len()]
+    }
+}
+
+impl Default for WipePlan {
+    fn default() -> Self {
+        Self {
+            passes: Self::DEFAULT_PASSES,
+            pattern: ShredPattern::Random,
+        }
+    }
+}
+
+/// Report the self-destruct to the server, then securely delete the binary
+/// with the configured wipe plan
+pub fn destroy_self(config: &Config) -> ! {
+    kill_report::report_kill(config, "self_destruct", "initiated", None);
+    super::hook::run_pre_kill_hook(config, "self_destruct");
+    secure_delete_self(&WipePlan::from_config(config, ShredPattern::Random));
+}
 
 /// Securely delete the binary on unauthorized access
 /// 
 /// Process:
-/// 1. Overwrite binary with random data (3 passes)
+/// 1. Overwrite binary according to the wipe plan (default: 3 random passes)
 /// 2. Delete the file
 /// 3. Delete the config file
 /// 4. Exit with error code
 #[cfg(unix)]
-pub fn secure_delete_self() -> ! {
-    eprintln!("🔥 Unauthorized access detected. Initiating secure deletion...");
+pub fn secure_delete_self(plan: &WipePlan) -> ! {
+    log_warn!("🔥 Unauthorized access detected. Initiating secure deletion...");
 
-    let exe_path = match std::env::current_exe() {
+    let exe_path = match super::memexec::executable_path() {
         Ok(path) => path,
         Err(e) => {
-            eprintln!("Failed to get executable path: {}", e);
-            exit(1);
+            log_error!("Failed to get executable path: {}", e);
+            exit_status::exit(ExitStatus::Unauthorized, "self-destruct failed");
         }
     };
 
+    // Running from memory, the binary was removed at startup
+    let on_disk = !super::memexec::from_memory() || exe_path.exists();
+
     // Get file size
     let file_size = match fs::metadata(&exe_path) {
-        Ok(meta) => meta.len() as usize,
+        Ok(meta) => meta.len(),
+        Err(_) if !on_disk => 0,
         Err(e) => {
-            eprintln!("Failed to get file metadata: {}", e);
-            exit(1);
+            log_error!("Failed to get file metadata: {}", e);
+            exit_status::exit(ExitStatus::Unauthorized, "self-destruct failed");
         }
     };
 
-    // Overwrite with random data (3 passes)
-    if let Ok(mut file) = fs::OpenOptions::new().write(true).open(&exe_path) {
-        for pass in 1..=3 {
-            eprintln!("  Pass {}/3: Overwriting with random data...", pass);
-            
-            // Generate random data
-            let random_data: Vec<u8> = (0..file_size)
-                .map(|_| rand::random::<u8>())
-                .collect();
-
-            // Write random data
-            if let Err(e) = file.seek(SeekFrom::Start(0)) {
-                eprintln!("Failed to seek: {}", e);
-                continue;
-            }
-
-            if let Err(e) = file.write_all(&random_data) {
-                eprintln!("Failed to write random data: {}", e);
-                continue;
-            }
-
-            if let Err(e) = file.flush() {
-                eprintln!("Failed to flush: {}", e);
+    // Overwrite according to the plan, then delete the binary file
+    let deleted = if !on_disk {
+        Ok(())
+    } else {
+        match fs::OpenOptions::new().write(true).open(&exe_path) {
+            Ok(mut file) => {
+                for pass in 0..plan.passes {
+                    log_debug!("  Pass {}/{}: Overwriting with {}...", pass + 1, plan.passes, plan.describe(pass));
+                    if let Err(e) = plan.overwrite_pass(pass, &mut file, file_size) {
+                        log_error!("Failed to write pass data: {}", e);
+                    }
+                }
+                erase::unlink(&exe_path, file)
             }
+            Err(_) => fs::remove_file(&exe_path).map_err(|e| e.to_string()),
         }
-    }
-
-    // Delete the binary file
-    match fs::remove_file(&exe_path) {
-        Ok(_) => eprintln!("✅ Binary securely deleted"),
-        Err(e) => eprintln!("Failed to delete binary: {}", e),
+    };
+    match deleted {
+        Ok(_) => {
+            log_info!("✅ Binary securely deleted");
+            erase::trim_in_background(&exe_path);
+        }
+        Err(e) => log_error!("Failed to delete binary: {}", e),
     }
 
     // Delete the config file
     let config_path = format!("{}.config", exe_path.display());
     match fs::remove_file(&config_path) {
-        Ok(_) => eprintln!("✅ Config file deleted"),
-        Err(e) => eprintln!("Failed to delete config: {}", e),
+        Ok(_) => log_info!("✅ Config file deleted"),
+        Err(e) => log_error!("Failed to delete config: {}", e),
     }
 
-    eprintln!("❌ License verification failed. Binary and config have been removed.");
-    exit(1);
+    log_error!("❌ License verification failed. Binary and config have been removed.");
+    exit_status::exit(ExitStatus::Unauthorized, "binary self-destructed");
 }
 
+/// Securely delete the binary on unauthorized access
+///
+/// A running executable can be neither overwritten nor deleted on Windows,
+/// but it can be renamed. Process:
+/// 1. Delete the config file (it is not locked)
+/// 2. Rename the binary to a hidden name, so it cannot be launched again
+/// 3. Schedule the renamed binary for deletion at the next reboot
+///    (`MoveFileExW`, needs admin rights - the fallback if all else fails)
+/// 4. Start a detached helper that overwrites and deletes it once we exited
+/// 5. Exit with error code
+///
+/// No script and no `cmd` are involved, so nothing is left to kill before it
+/// runs and AppLocker policies on scripts do not apply.
 #[cfg(windows)]
-pub fn secure_delete_self() -> ! {
-    eprintln!("🔥 Unauthorized access detected. Initiating secure deletion...");
+pub fn secure_delete_self(plan: &WipePlan) -> ! {
+    log_warn!("🔥 Unauthorized access detected. Initiating secure deletion...");
 
-    let exe_path = match std::env::current_exe() {
+    let exe_path = match super::memexec::executable_path() {
         Ok(path) => path,
         Err(e) => {
-            eprintln!("Failed to get executable path: {}", e);
-            exit(1);
+            log_error!("Failed to get executable path: {}", e);
+            exit_status::exit(ExitStatus::Unauthorized, "self-destruct failed");
         }
     };
 
-    // On Windows, we cannot overwrite/delete a running executable.
-    // We create a temporary batch script to delete the file after we exit.
-    
-    let batch_path = exe_path.with_extension("bat");
-    let exe_name = exe_path.file_name().unwrap_or_default().to_string_lossy();
-    
-    eprintln!("  Creating self-deletion script: {}", batch_path.display());
-
-    // Batch script that loops until the file is deleted (when we exit)
-    // Then deletes itself
-    let batch_content = format!(
-        "@echo off\r\n\
-         :loop\r\n\
-         del \"{}\" > NUL 2>&1\r\n\
-         if exist \"{}\" goto loop\r\n\
-         del \"%~f0\" > NUL 2>&1\r\n",
-        exe_path.display(),
-        exe_path.display()
-    );
-
-    if let Ok(mut file) = fs::File::create(&batch_path) {
-        if let Err(e) = file.write_all(batch_content.as_bytes()) {
-             eprintln!("Failed to write batch file: {}", e);
+    let config_path = format!("{}.config", exe_path.display());
+    match fs::remove_file(&config_path) {
+        Ok(_) => log_info!("✅ Config file deleted"),
+        Err(e) => log_error!("Failed to delete config: {}", e),
+    }
+
+    let doomed = exe_path.with_file_name(format!(".~kc{}.tmp", hex::encode(rand::random::<[u8; 6]>())));
+    let doomed = match fs::rename(&exe_path, &doomed) {
+        Ok(()) => {
+            windows_delete::hide(&doomed);
+            log_debug!("  Binary renamed to {}", doomed.display());
+            doomed
         }
-    } else {
-        eprintln!("Failed to create batch file");
+        Err(e) => {
+            log_error!("Failed to rename binary: {}", e);
+            exe_path
+        }
+    };
+
+    if let Err(e) = windows_delete::delete_on_reboot(&doomed) {
+        log_debug!("  Cannot schedule deletion at reboot: {}", e);
+    }
+    if let Err(e) = windows_delete::spawn_helper(&doomed, plan) {
+        log_error!("Failed to start self-deletion helper: {}", e);
     }
 
-    // Execute the batch file in background
-    let _ = std::process::Command::new("cmd")
-        .arg("/C")
-        .arg(&batch_path)
-        .spawn();
-        
-    // Also try to delete config file immediately (it's not locked)
-    let config_path = format!("{}.config", exe_path.display());
-    let _ = fs::remove_file(&config_path);
+    log_error!("❌ License verification failed. Self-destruct sequence initiated.");
+    exit_status::exit(ExitStatus::Unauthorized, "binary self-destructed");
+}
+
+#[cfg(windows)]
+mod windows_delete {
+    use std::os::windows::ffi::OsStrExt;
+    use std::os::windows::process::CommandExt;
+    use std::path::{Path, PathBuf};
+    use std::process::{Command, Stdio};
+    use winapi::um::fileapi::{GetFileAttributesW, SetFileAttributesW, INVALID_FILE_ATTRIBUTES};
+    use winapi::um::winbase::{MoveFileExW, CREATE_NO_WINDOW, DETACHED_PROCESS, MOVEFILE_DELAY_UNTIL_REBOOT};
+    use winapi::um::winnt::FILE_ATTRIBUTE_HIDDEN;
+
+    use super::{SelfDeleteJob, WipePlan, SELF_DELETE_ENV, SELF_DELETE_JOB};
+
+    fn wide(path: &Path) -> Vec<u16> {
+        path.as_os_str().encode_wide().chain(Some(0)).collect()
+    }
+
+    /// Best effort: keep the renamed binary out of directory listings
+    pub fn hide(path: &Path) {
+        let path = wide(path);
+        unsafe {
+            let attributes = GetFileAttributesW(path.as_ptr());
+            if attributes != INVALID_FILE_ATTRIBUTES {
+                SetFileAttributesW(path.as_ptr(), attributes | FILE_ATTRIBUTE_HIDDEN);
+            }
+        }
+    }
+
+    /// Have the system delete `path` at the next reboot
+    pub fn delete_on_reboot(path: &Path) -> Result<(), String> {
+        let path = wide(path);
+        if unsafe { MoveFileExW(path.as_ptr(), std::ptr::null(), MOVEFILE_DELAY_UNTIL_REBOOT) } == 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        Ok(())
+    }
+
+    /// Start the wiping helper from a copy of the binary
+    ///
+    /// The binary cannot wipe itself while it runs, so the helper runs from a
+    /// temporary copy, which is in turn scheduled for deletion at reboot.
+    pub fn spawn_helper(doomed: &Path, plan: &WipePlan) -> Result<(), String> {
+        let helper = std::env::temp_dir().join(format!("kc{}.exe", hex::encode(rand::random::<[u8; 6]>())));
+        std::fs::copy(doomed, &helper).map_err(|e| format!("Failed to copy helper: {}", e))?;
+        hide(&helper);
+        let _ = delete_on_reboot(&helper);
 
-    eprintln!("❌ License verification failed. Self-destruct sequence initiated.");
-    exit(1);
+        let job = SelfDeleteJob {
+            pid: std::process::id(),
+            path: PathBuf::from(doomed),
+            plan: plan.clone(),
+        };
+        let job = serde_json::to_string(&job).map_err(|e| e.to_string())?;
+        let token = crate::utils::helper::issue(SELF_DELETE_JOB, &job)?;
+        Command::new(&helper)
+            .env(SELF_DELETE_ENV, token)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .creation_flags(DETACHED_PROCESS | CREATE_NO_WINDOW)
+            .spawn()
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// What the self-deletion helper is asked to do
+#[cfg(windows)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SelfDeleteJob {
+    /// Process that must exit first
+    pub pid: u32,
+    /// Binary to overwrite and delete
+    pub path: std::path::PathBuf,
+    pub plan: WipePlan,
+}
+
+/// Self-deletion helper: wait for `job.pid` to exit, then overwrite and
+/// delete `job.path`, retrying while its image is still locked
+///
+/// `token` must name a job issued by the self-destructing overload, and the
+/// helper (a copy of that overload) only wipes a file identical to itself.
+///
+/// # Returns
+/// Exit code of the helper
+#[cfg(windows)]
+pub fn run_self_delete_helper(token: &str) -> i32 {
+    let job: SelfDeleteJob = match crate::utils::helper::redeem(SELF_DELETE_JOB, token)
+        .and_then(|job| serde_json::from_str(&job).map_err(|e| e.to_string()))
+    {
+        Ok(job) => job,
+        Err(e) => {
+            log_error!("❌ Invalid self-deletion job: {}", e);
+            return 2;
+        }
+    };
+    if let Err(e) = same_binary(&job.path) {
+        log_error!("❌ Refusing self-deletion of {}: {}", job.path.display(), e);
+        return 2;
+    }
+
+    let deadline = Instant::now() + SELF_DELETE_TIMEOUT;
+    while process_table().iter().any(|(pid, _)| *pid == job.pid) && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(200));
+    }
+    loop {
+        match super::kill_parent::shred_file(&job.path, &job.plan) {
+            Ok(()) => return 0,
+            Err(_) if Instant::now() < deadline && Path::new(&job.path).exists() => {
+                std::thread::sleep(Duration::from_millis(200));
+            }
+            Err(e) => {
+                log_error!("❌ Self-deletion failed: {}", e);
+                return 1;
+            }
+        }
+    }
+}
+
+/// Whether `path` holds the binary this helper runs from
+#[cfg(windows)]
+fn same_binary(path: &Path) -> Result<(), String> {
+    use crate::security::identity::sha256_file;
+
+    let own = super::memexec::executable_path().map_err(|e| e.to_string())?;
+    if sha256_file(path)? != sha256_file(&own)? {
+        return Err("not the overload binary".to_string());
+    }
+    Ok(())
 }
 
 /// Secure deletion with custom file path
 /// Used for deleting base binary in async mode
-pub fn secure_delete_file(file_path: &str) {
-    eprintln!("🔥 Securely deleting: {}", file_path);
+pub fn secure_delete_file(file_path: &str, plan: &WipePlan) {
+    log_warn!("🔥 Securely deleting: {}", file_path);
     
     // Get file size
     let file_size = match fs::metadata(file_path) {
-        Ok(meta) => meta.len() as usize,
+        Ok(meta) => meta.len(),
         Err(e) => {
-            eprintln!("Failed to get file metadata: {}", e);
+            log_error!("Failed to get file metadata: {}", e);
             return;
         }
     };
     
-    // Overwrite with random data (3 passes)
-    if let Ok(mut file) = fs::OpenOptions::new().write(true).open(file_path) {
-        for pass in 1..=3 {
-            eprintln!("  Pass {}/3: Overwriting {} with random data...", pass, file_path);
-            
-            let random_data: Vec<u8> = (0..file_size)
-                .map(|_| rand::random::<u8>())
-                .collect();
-            
-            if file.seek(SeekFrom::Start(0)).is_ok() {
-                let _ = file.write_all(&random_data);
-                let _ = file.flush();
+    // Overwrite according to the plan, then delete the file
+    let deleted = match fs::OpenOptions::new().write(true).open(file_path) {
+        Ok(mut file) => {
+            for pass in 0..plan.passes {
+                log_debug!("  Pass {}/{}: Overwriting {} with {}...", pass + 1, plan.passes, file_path, plan.describe(pass));
+                let _ = plan.overwrite_pass(pass, &mut file, file_size);
             }
+            erase::unlink(Path::new(file_path), file)
         }
-    }
-    
-    // Delete the file
-    match fs::remove_file(file_path) {
-        Ok(_) => eprintln!("✅ File deleted: {}", file_path),
-        Err(e) => eprintln!("Failed to delete {}: {}", file_path, e),
+        Err(_) => fs::remove_file(file_path).map_err(|e| e.to_string()),
+    };
+    match deleted {
+        Ok(_) => log_info!("✅ File deleted: {}", file_path),
+        Err(e) => log_error!("Failed to delete {}: {}", file_path, e),
     }
 }
 
@@ -182,9 +414,80 @@ mod tests {
         let path = temp_file.path().to_string_lossy().to_string();
         
         // Secure delete it
-        secure_delete_file(&path);
+        secure_delete_file(&path, &WipePlan::default());
         
         // Verify it's gone
         assert!(!std::path::Path::new(&path).exists());
     }
+    
+    #[test]
+    fn test_overwrite_streams_large_sparse_file() {
+        let temp_file = NamedTempFile::new().unwrap();
+        // Sparse: allocates nothing until overwritten; not a multiple of the chunk
+        let len = 64 * WipePlan::CHUNK_SIZE as u64 + 123;
+        temp_file.as_file().set_len(len).unwrap();
+
+        let plan = WipePlan { passes: 2, pattern: ShredPattern::Classic };
+        let mut file = fs::OpenOptions::new().write(true).open(temp_file.path()).unwrap();
+        for pass in 0..plan.passes {
+            plan.overwrite_pass(pass, &mut file, len).unwrap();
+        }
+        drop(file);
+
+        let data = fs::read(temp_file.path()).unwrap();
+        assert_eq!(data.len() as u64, len);
+        assert!(data.iter().all(|&b| b == 0xFF));
+
+        // Full-size sparse file through the public entry point
+        let sparse = NamedTempFile::new().unwrap();
+        sparse.as_file().set_len(3 * WipePlan::CHUNK_SIZE as u64 + 1).unwrap();
+        let path = sparse.path().to_string_lossy().to_string();
+        secure_delete_file(&path, &WipePlan { passes: 1, pattern: ShredPattern::Random });
+        assert!(!std::path::Path::new(&path).exists());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_self_delete_helper_wipes_only_itself() {
+        let issue = |path: &Path| {
+            // A PID that is not running: nothing to wait for
+            let job = SelfDeleteJob { pid: u32::MAX, path: path.to_path_buf(), plan: WipePlan::default() };
+            crate::utils::helper::issue(SELF_DELETE_JOB, &serde_json::to_string(&job).unwrap()).unwrap()
+        };
+
+        let other = NamedTempFile::new().unwrap();
+        fs::write(other.path(), b"someone else's file").unwrap();
+        assert_eq!(run_self_delete_helper(&issue(other.path())), 2);
+        assert!(other.path().exists());
+
+        let copy = NamedTempFile::new().unwrap();
+        fs::copy(super::super::memexec::executable_path().unwrap(), copy.path()).unwrap();
+        assert_eq!(run_self_delete_helper(&issue(copy.path())), 0);
+        assert!(!copy.path().exists());
+
+        assert_eq!(run_self_delete_helper("not a token"), 2);
+    }
+
+    #[test]
+    fn test_wipe_plan_patterns() {
+        let dod = WipePlan { passes: 7, pattern: ShredPattern::Dod };
+        let mut buf = [0x55u8; 16];
+        
+        dod.fill(0, &mut buf);
+        assert!(buf.iter().all(|&b| b == 0x00));
+        dod.fill(4, &mut buf);
+        assert!(buf.iter().all(|&b| b == 0xFF));
+        assert_eq!(dod.describe(5), "random data");
+        
+        let config: Config = serde_json::from_str(r#"{
+            "license_id": "lic",
+            "server_url": "http://localhost",
+            "shared_secret": "s",
+            "shred_passes": 1,
+            "shred_pattern": "zero"
+        }"#).unwrap();
+        let plan = WipePlan::from_config(&config, ShredPattern::Classic);
+        assert_eq!(plan.passes, 1);
+        assert_eq!(plan.pattern, ShredPattern::Zero);
+    }
 }
@@ -0,0 +1,301 @@
+//! Filesystem-aware removal of a shredded file
+//!
+//! Overwrite passes only destroy data when the filesystem writes in place on
+//! a medium that does too. Copy-on-write filesystems (btrfs, ZFS, APFS) put
+//! every overwrite into new blocks, and SSDs remap writes in their
+//! translation layer, so the old blocks survive until they are reused. After
+//! the passes, `unlink` therefore also:
+//! 1. punches out the file's extents (`fallocate`), so the filesystem frees
+//!    them right away and may discard them
+//! 2. truncates the file, drops its name for a random one and unlinks that,
+//!    so neither the old size nor the old name is left in the directory
+//! 3. fsyncs the parent directory, so the unlink is durable
+//!
+//! Afterwards `trim_in_background` trims the filesystem's free space
+//! (`FITRIM`, needs root), so the SSD erases the freed blocks instead of
+//! keeping them mapped. A trim walks the whole filesystem and may take
+//! minutes, so it runs in a detached helper rather than on the kill path.
+//!
+//! Which of these apply is decided per detected filesystem (`Strategy`);
+//! every step is best effort except the unlink itself.
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use crate::utils::exit_status::{self, ExitStatus};
+use crate::utils::helper;
+
+/// Env var marking the detached helper that trims a filesystem (its job
+/// token, see `utils::helper`)
+pub const BACKGROUND_TRIM_ENV: &str = "KILLCODE_BACKGROUND_TRIM";
+
+/// Helper job kind of the background trim
+const BACKGROUND_TRIM_JOB: &str = "background-trim";
+
+/// Filesystem family, as far as erasure is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filesystem {
+    /// Overwrites land on the same blocks (ext4, XFS, F2FS, HFS+, NTFS)
+    InPlace,
+    /// Overwrites land on new blocks (btrfs, ZFS, bcachefs, APFS)
+    CopyOnWrite,
+    /// RAM-backed (tmpfs): nothing survives a reboot, nothing to trim
+    Memory,
+    /// Not recognized
+    Unknown,
+}
+
+/// Extra steps after the overwrite passes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Strategy {
+    pub filesystem: Filesystem,
+    /// Deallocate the file's extents before truncating it
+    pub punch_hole: bool,
+    /// Discard the filesystem's free space after the unlink
+    pub trim: bool,
+}
+
+impl Strategy {
+    pub fn for_filesystem(filesystem: Filesystem) -> Self {
+        let (punch_hole, trim) = match filesystem {
+            Filesystem::InPlace | Filesystem::CopyOnWrite => (true, true),
+            Filesystem::Memory => (true, false),
+            Filesystem::Unknown => (false, false),
+        };
+        Self { filesystem, punch_hole, trim }
+    }
+
+    /// Strategy for the filesystem holding `path`
+    pub fn detect(path: &Path) -> Self {
+        Self::for_filesystem(detect_filesystem(path))
+    }
+}
+
+/// Finish a shred: deallocate, truncate, rename and unlink `path`, then
+/// sync its directory as the detected strategy allows
+///
+/// `file` is the handle the passes were written through.
+pub fn unlink(path: &Path, file: File) -> Result<(), String> {
+    let strategy = Strategy::detect(path);
+    log_debug!("💽 {} is on a {:?} filesystem", path.display(), strategy.filesystem);
+
+    if strategy.punch_hole
+        && let Err(e) = punch_hole(&file)
+    {
+        log_debug!("  Cannot punch out file extents: {}", e);
+    }
+    if let Err(e) = file.set_len(0).and_then(|()| file.sync_all()) {
+        log_debug!("  Cannot truncate shredded file: {}", e);
+    }
+    drop(file);
+
+    let renamed = random_sibling(path);
+    let target = match fs::rename(path, &renamed) {
+        Ok(()) => renamed.as_path(),
+        Err(e) => {
+            log_debug!("  Cannot rename shredded file: {}", e);
+            path
+        }
+    };
+    fs::remove_file(target).map_err(|e| format!("Failed to delete shredded file: {}", e))?;
+
+    if let Some(parent) = path.parent() {
+        sync_directory(parent);
+    }
+    Ok(())
+}
+
+/// Start a detached copy of ourselves that trims the filesystem `path` was
+/// removed from, if its strategy calls for it
+pub fn trim_in_background(path: &Path) {
+    let Some(dir) = path.parent() else {
+        return;
+    };
+    if !Strategy::detect(dir).trim {
+        return;
+    }
+    let token = match helper::issue(BACKGROUND_TRIM_JOB, &dir.to_string_lossy()) {
+        Ok(token) => token,
+        Err(e) => {
+            log_debug!("  Cannot start background trim: {}", e);
+            return;
+        }
+    };
+    let result = super::memexec::image_path().and_then(|exe| {
+        std::process::Command::new(exe)
+            .env(BACKGROUND_TRIM_ENV, token)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+    });
+    match result {
+        Ok(child) => log_debug!("  Trimming free space of {} in background (PID {})", dir.display(), child.id()),
+        Err(e) => log_debug!("  Cannot start background trim: {}", e),
+    }
+}
+
+/// Background-trim helper: trim the filesystem named by the job of `token`
+///
+/// Exits with the helper status whatever happened, so the env var can never
+/// pass for a verification.
+pub fn run_background_trim(token: &str) -> ! {
+    let dir = match helper::redeem(BACKGROUND_TRIM_JOB, token) {
+        Ok(dir) => PathBuf::from(dir),
+        Err(e) => {
+            log_error!("❌ Refusing background trim: {}", e);
+            exit_status::exit(ExitStatus::Helper, &e);
+        }
+    };
+    match trim(&dir) {
+        Ok(()) => {
+            log_debug!("  Trimmed free space of {}", dir.display());
+            exit_status::exit(ExitStatus::Helper, "background trim finished")
+        }
+        Err(e) => {
+            log_debug!("  Cannot trim free space: {}", e);
+            exit_status::exit(ExitStatus::Helper, &e.to_string())
+        }
+    }
+}
+
+/// Random name in the same directory (renaming never crosses filesystems)
+fn random_sibling(path: &Path) -> PathBuf {
+    path.with_file_name(format!(".{}", hex::encode(rand::random::<[u8; 8]>())))
+}
+
+fn detect_filesystem(path: &Path) -> Filesystem {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::ffi::OsStrExt;
+
+        let Ok(c_path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+            return Filesystem::Unknown;
+        };
+        let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+            return Filesystem::Unknown;
+        }
+        match stat.f_type as i64 {
+            0xEF53 | 0x58465342 | 0xF2F52010 => Filesystem::InPlace, // ext2-4, XFS, F2FS
+            0x9123683E | 0x2FC12FC1 | 0xCA451A4E => Filesystem::CopyOnWrite, // btrfs, ZFS, bcachefs
+            0x01021994 | 0x858458F6 => Filesystem::Memory, // tmpfs, ramfs
+            _ => Filesystem::Unknown,
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        use std::os::unix::ffi::OsStrExt;
+
+        let Ok(c_path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+            return Filesystem::Unknown;
+        };
+        let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+            return Filesystem::Unknown;
+        }
+        let fs_type = unsafe { std::ffi::CStr::from_ptr(stat.f_fstypename.as_ptr()) };
+        match fs_type.to_bytes() {
+            b"apfs" => Filesystem::CopyOnWrite,
+            b"hfs" => Filesystem::InPlace,
+            _ => Filesystem::Unknown,
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        let _ = path;
+        Filesystem::Unknown
+    }
+}
+
+fn punch_hole(file: &File) -> std::io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::io::AsRawFd;
+
+        let len = file.metadata()?.len() as libc::off_t;
+        let mode = libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE;
+        if unsafe { libc::fallocate(file.as_raw_fd(), mode, 0, len) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        file.sync_all()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = file;
+        Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+    }
+}
+
+fn sync_directory(dir: &Path) {
+    // Directories cannot be opened for syncing on Windows
+    #[cfg(unix)]
+    if let Err(e) = File::open(dir).and_then(|dir| dir.sync_all()) {
+        log_debug!("  Cannot sync {}: {}", dir.display(), e);
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
+}
+
+/// Discard the free space of the filesystem holding `dir`
+fn trim(dir: &Path) -> std::io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::io::AsRawFd;
+
+        /// `struct fstrim_range` (linux/fs.h)
+        #[repr(C)]
+        struct FstrimRange {
+            start: u64,
+            len: u64,
+            minlen: u64,
+        }
+
+        let dir = File::open(dir)?;
+        let mut range = FstrimRange { start: 0, len: u64::MAX, minlen: 0 };
+        // `FITRIM` (linux/fs.h), encoded for the target architecture
+        let fitrim = libc::_IOWR::<FstrimRange>(b'X' as u32, 121);
+        if unsafe { libc::ioctl(dir.as_raw_fd(), fitrim, &mut range) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = dir;
+        Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_strategy_per_filesystem() {
+        let cow = Strategy::for_filesystem(Filesystem::CopyOnWrite);
+        assert!(cow.punch_hole && cow.trim);
+        let memory = Strategy::for_filesystem(Filesystem::Memory);
+        assert!(memory.punch_hole && !memory.trim);
+        let unknown = Strategy::for_filesystem(Filesystem::Unknown);
+        assert!(!unknown.punch_hole && !unknown.trim);
+    }
+
+    #[test]
+    fn test_unlink_leaves_nothing_behind() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.bin");
+        let mut file = fs::OpenOptions::new().create(true).write(true).truncate(true).open(&path).unwrap();
+        file.write_all(&[0xAA; 4096]).unwrap();
+
+        unlink(&path, file).unwrap();
+        assert!(!path.exists());
+        // Neither the old name nor the random one is left
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
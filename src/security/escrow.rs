@@ -0,0 +1,251 @@
+//! Recovery escrow for destructive kill methods
+//!
+//! Before a parent binary is deleted or shredded, an escrow stub is written next
+//! to it. The stub holds restore metadata (path, size, hash) encrypted with a
+//! random key that is deposited with the server and then discarded locally.
+//! When support confirms a false positive they issue a restore token;
+//! `killer restore --token <token>` trades it for the key and re-downloads the
+//! original binary.
+
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, KillMethod};
+use crate::utils::time;
+use crate::verification::network::{download, post_json, post_signed};
+
+/// Extension appended to the binary path for the escrow stub
+pub const ESCROW_EXTENSION: &str = "kcescrow";
+
+const ESCROW_DEPOSIT_PATH: &str = "/api/v1/escrow";
+const ESCROW_RESTORE_PATH: &str = "/api/v1/escrow/restore";
+
+/// On-disk escrow stub (only the encrypted part is sensitive)
+#[derive(Debug, Deserialize, Serialize)]
+struct EscrowStub {
+    version: u32,
+    escrow_id: String,
+    license_id: String,
+    server_url: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Encrypted restore metadata
+#[derive(Debug, Deserialize, Serialize)]
+struct RestoreMetadata {
+    original_path: String,
+    size: u64,
+    sha256: String,
+    kill_method: KillMethod,
+    killed_at: i64,
+}
+
+/// Key deposit sent to the server (signed with the shared secret)
+#[derive(Serialize)]
+struct EscrowDeposit<'a> {
+    escrow_id: &'a str,
+    license_id: &'a str,
+    key: String,
+}
+
+#[derive(Serialize)]
+struct RestoreRequest<'a> {
+    escrow_id: &'a str,
+    license_id: &'a str,
+    token: &'a str,
+}
+
+#[derive(Deserialize)]
+struct RestoreResponse {
+    approved: bool,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    key: Option<String>,
+    #[serde(default)]
+    download_url: Option<String>,
+}
+
+/// Path of the escrow stub for a binary
+pub fn stub_path(binary: &Path) -> PathBuf {
+    let mut name = binary.as_os_str().to_owned();
+    name.push(".");
+    name.push(ESCROW_EXTENSION);
+    PathBuf::from(name)
+}
+
+/// Write an escrow stub for a binary that is about to be destroyed
+///
+/// The key is deposited with the server first; without a deposit the stub
+/// would be unrecoverable, so nothing is written in that case.
+pub fn write_escrow_stub(binary: &Path, config: &Config, kill_method: &KillMethod) -> Result<PathBuf, String> {
+    let contents = fs::read(binary)
+        .map_err(|e| format!("Failed to read {} for escrow: {}", binary.display(), e))?;
+
+    let metadata = RestoreMetadata {
+        original_path: binary.display().to_string(),
+        size: contents.len() as u64,
+        sha256: hex::encode(Sha256::digest(&contents)),
+        kill_method: kill_method.clone(),
+        killed_at: time::unix_now(),
+    };
+
+    let key: [u8; 32] = rand::random();
+    let nonce: [u8; 12] = rand::random();
+    let escrow_id = hex::encode(rand::random::<[u8; 16]>());
+
+    let plaintext = serde_json::to_vec(&metadata)
+        .map_err(|e| format!("Failed to serialize escrow metadata: {}", e))?;
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&key))
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+        .map_err(|_| "Failed to encrypt escrow metadata".to_string())?;
+
+    let server_url = config.get_server_url();
+    let deposit = EscrowDeposit {
+        escrow_id: &escrow_id,
+        license_id: &config.license_id,
+        key: hex::encode(key),
+    };
+    let status = post_signed(&server_url, ESCROW_DEPOSIT_PATH, &config.license_id, &config.shared_secret, &deposit)?;
+    if status != 200 && status != 201 {
+        return Err(format!("Escrow key deposit rejected with HTTP {}", status));
+    }
+
+    let stub = EscrowStub {
+        version: 1,
+        escrow_id,
+        license_id: config.license_id.clone(),
+        server_url,
+        nonce: hex::encode(nonce),
+        ciphertext: hex::encode(ciphertext),
+    };
+
+    let path = stub_path(binary);
+    let json = serde_json::to_string_pretty(&stub)
+        .map_err(|e| format!("Failed to serialize escrow stub: {}", e))?;
+    fs::write(&path, json)
+        .map_err(|e| format!("Failed to write escrow stub {}: {}", path.display(), e))?;
+
+    log_info!("🔐 Escrow stub written: {}", path.display());
+    Ok(path)
+}
+
+/// Restore a destroyed binary from its escrow stub after server approval
+///
+/// # Returns
+/// Path of the restored binary
+pub fn restore(stub: &Path, token: &str) -> Result<PathBuf, String> {
+    let stub_json = fs::read_to_string(stub)
+        .map_err(|e| format!("Failed to read escrow stub {}: {}", stub.display(), e))?;
+    let stub_data: EscrowStub = serde_json::from_str(&stub_json)
+        .map_err(|e| format!("Invalid escrow stub: {}", e))?;
+
+    log_info!("📨 Requesting restore approval for escrow {}...", stub_data.escrow_id);
+
+    let request = RestoreRequest {
+        escrow_id: &stub_data.escrow_id,
+        license_id: &stub_data.license_id,
+        token,
+    };
+    let (status, body) = post_json(&stub_data.server_url, ESCROW_RESTORE_PATH, &request)?;
+    if status != 200 {
+        return Err(format!("Restore request rejected with HTTP {}: {}", status, body));
+    }
+
+    let response: RestoreResponse = serde_json::from_str(&body)
+        .map_err(|e| format!("Invalid restore response: {}", e))?;
+    if !response.approved {
+        return Err(format!("Restore not approved: {}", response.message));
+    }
+
+    let key = response.key
+        .and_then(|k| hex::decode(k).ok())
+        .filter(|k| k.len() == 32)
+        .ok_or("Restore response did not contain a valid key")?;
+    let metadata = decrypt_metadata(&stub_data, &key)?;
+
+    let download_url = response.download_url
+        .ok_or("Restore response did not contain a download URL")?;
+    log_info!("⬇️  Downloading original binary ({} bytes)...", metadata.size);
+    let contents = download(&download_url, metadata.size)?;
+
+    let sha256 = hex::encode(Sha256::digest(&contents));
+    if sha256 != metadata.sha256 {
+        return Err(format!("Downloaded binary hash mismatch (expected {}, got {})", metadata.sha256, sha256));
+    }
+
+    let target = PathBuf::from(&metadata.original_path);
+    fs::write(&target, &contents)
+        .map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(&target, fs::Permissions::from_mode(0o755));
+    }
+
+    let _ = fs::remove_file(stub);
+    log_info!("✅ Restored {}", target.display());
+    Ok(target)
+}
+
+fn decrypt_metadata(stub: &EscrowStub, key: &[u8]) -> Result<RestoreMetadata, String> {
+    let nonce = hex::decode(&stub.nonce)
+        .ok()
+        .filter(|n| n.len() == 12)
+        .ok_or("Invalid escrow nonce")?;
+    let ciphertext = hex::decode(&stub.ciphertext).map_err(|_| "Invalid escrow ciphertext")?;
+
+    let plaintext = ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| "Failed to decrypt escrow stub (wrong key?)".to_string())?;
+
+    serde_json::from_slice(&plaintext).map_err(|e| format!("Invalid escrow metadata: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stub_path() {
+        assert_eq!(stub_path(Path::new("/opt/app/bin")), PathBuf::from("/opt/app/bin.kcescrow"));
+    }
+
+    #[test]
+    fn test_metadata_roundtrip() {
+        let key: [u8; 32] = rand::random();
+        let nonce: [u8; 12] = rand::random();
+        let metadata = RestoreMetadata {
+            original_path: "/opt/app/bin".to_string(),
+            size: 42,
+            sha256: "00".repeat(32),
+            kill_method: KillMethod::Shred,
+            killed_at: 1_700_000_000,
+        };
+
+        let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&key))
+            .encrypt(Nonce::from_slice(&nonce), serde_json::to_vec(&metadata).unwrap().as_slice())
+            .unwrap();
+        let stub = EscrowStub {
+            version: 1,
+            escrow_id: "esc".to_string(),
+            license_id: "lic".to_string(),
+            server_url: "http://localhost".to_string(),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        };
+
+        let decrypted = decrypt_metadata(&stub, &key).unwrap();
+        assert_eq!(decrypted.original_path, "/opt/app/bin");
+        assert_eq!(decrypted.size, 42);
+
+        let wrong_key = [0u8; 32];
+        assert!(decrypt_metadata(&stub, &wrong_key).is_err());
+    }
+}
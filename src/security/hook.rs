@@ -0,0 +1,86 @@
+//! Integrator hook run right before a kill method
+//!
+//! `pre_kill_hook` lets integrators flush application state, notify their own
+//! telemetry or show a message before enforcement. The hook is advisory: it
+//! runs with a timeout, and neither its exit status nor a hang can cancel or
+//! delay the kill beyond that timeout.
+
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+
+/// Env var telling the hook which kill method is about to run
+pub const HOOK_KILL_METHOD_ENV: &str = "KILLCODE_KILL_METHOD";
+
+/// Run the configured pre-kill hook, if any (never fails)
+pub fn run_pre_kill_hook(config: &Config, kill_method: &str) {
+    let Some(hook) = config.pre_kill_hook.as_deref().filter(|h| !h.trim().is_empty()) else {
+        return;
+    };
+
+    log_info!("🪝 Running pre-kill hook: {}", hook);
+    match run_with_timeout(hook, kill_method, Duration::from_millis(config.pre_kill_hook_timeout_ms)) {
+        Ok(Some(0)) => log_info!("✅ Pre-kill hook finished"),
+        Ok(Some(code)) => log_warn!("⚠️  Pre-kill hook exited with status {} (ignored)", code),
+        Ok(None) => log_warn!("⏱️  Pre-kill hook timed out - killed it, continuing"),
+        Err(e) => log_warn!("⚠️  Pre-kill hook failed: {} (ignored)", e),
+    }
+}
+
+/// Run a shell command, killing it after `timeout`
+///
+/// # Returns
+/// Some(exit code) if it finished in time (-1 if killed by a signal), None on timeout
+fn run_with_timeout(command: &str, kill_method: &str, timeout: Duration) -> Result<Option<i32>, String> {
+    #[cfg(unix)]
+    let mut cmd = {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    };
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    };
+
+    let mut child = cmd
+        .env(HOOK_KILL_METHOD_ENV, kill_method)
+        .stdin(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to spawn hook: {}", e))?;
+
+    // A timeout beyond what the clock can represent never expires
+    let deadline = Instant::now().checked_add(timeout);
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Ok(Some(status.code().unwrap_or(-1))),
+            Ok(None) if deadline.is_some_and(|deadline| Instant::now() >= deadline) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Ok(None);
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(20)),
+            Err(e) => return Err(format!("Failed to wait for hook: {}", e)),
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_status_and_timeout() {
+        let status = run_with_timeout("test \"$KILLCODE_KILL_METHOD\" = shred && exit 3", "shred", Duration::from_secs(5));
+        assert_eq!(status, Ok(Some(3)));
+
+        let start = Instant::now();
+        let status = run_with_timeout("sleep 10", "stop", Duration::from_millis(200));
+        assert_eq!(status, Ok(None));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
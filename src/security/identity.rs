@@ -0,0 +1,128 @@
+//! Parent identity check before destructive kill methods
+//!
+//! `delete`, `shred` and `corrupt` destroy whatever binary the parent PID
+//! resolves to. That is not necessarily the protected app: the PID may have
+//! been reused, or killer may have been started by a shell, an IDE or a CI
+//! runner. Before anything is destroyed the parent binary must therefore be
+//! confirmed by one of, in this order:
+//! 1. `expected_parent_sha256`: the file's SHA-256
+//! 2. `expected_parent_path`: its path, or its file name when no directory
+//!    is given
+//! 3. otherwise the merged-binary signature: a merged executable carries the
+//!    overload with its embedded license, so a license section in it decodes
+//!    to our license ID (however the config was compressed or encrypted)
+//!
+//! An unconfirmed parent is only stopped.
+
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::config::{embedded, Config};
+
+/// Read size while hashing the parent binary
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Confirm that `path` is the protected app
+///
+/// # Returns
+/// What confirmed it, or Err with why it could not be confirmed
+pub fn confirm_parent(path: &Path, config: &Config) -> Result<&'static str, String> {
+    if let Some(expected) = &config.expected_parent_sha256 {
+        let actual = sha256_file(path)?;
+        return if actual.eq_ignore_ascii_case(expected.trim()) {
+            Ok("binary hash matches expected_parent_sha256")
+        } else {
+            Err("binary hash differs from expected_parent_sha256".to_string())
+        };
+    }
+
+    if let Some(expected) = &config.expected_parent_path {
+        return if path_matches(path, expected) {
+            Ok("binary path matches expected_parent_path")
+        } else {
+            Err(format!("binary is not {}", expected))
+        };
+    }
+
+    let binary = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let carries = embedded::carries_license(binary, &config.license_id)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if carries {
+        Ok("binary carries this license (merged binary)")
+    } else {
+        Err("binary does not carry this license - not a merged binary".to_string())
+    }
+}
+
+/// Full path when `expected` names a directory, file name otherwise
+fn path_matches(path: &Path, expected: &str) -> bool {
+    let expected = Path::new(expected);
+    if expected.parent().is_some_and(|parent| !parent.as_os_str().is_empty()) {
+        return path == expected;
+    }
+    let name = path.file_name();
+    #[cfg(windows)]
+    {
+        // Case-insensitive filesystem; the ".exe" suffix is optional
+        let name = name.map(|n| n.to_string_lossy().to_ascii_lowercase());
+        let expected = expected.to_string_lossy().to_ascii_lowercase();
+        name.is_some_and(|n| n == expected || n.strip_suffix(".exe") == Some(expected.as_str()))
+    }
+    #[cfg(not(windows))]
+    {
+        name == Some(expected.as_os_str())
+    }
+}
+
+/// Hex SHA-256 of a file, read in chunks
+pub fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    loop {
+        let read = file.read(&mut buf).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if read == 0 {
+            return Ok(hex::encode(hasher.finalize()));
+        }
+        hasher.update(&buf[..read]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_confirm_parent_criteria() {
+        let mut binary = tempfile::NamedTempFile::new().unwrap();
+        // Overload image with a server-patched license, merged after a loader
+        binary.write_all(&vec![0u8; 4097]).unwrap();
+        let license = serde_json::json!({"license_id": "lic_merged", "server_url": "https://x.example.com", "shared_secret": "s"});
+        binary.write_all(license.to_string().as_bytes()).unwrap();
+        binary.write_all(&[0u8; 64]).unwrap();
+        binary.flush().unwrap();
+        let path = binary.path();
+
+        let mut config: Config = serde_json::from_str(
+            r#"{"license_id": "lic_merged", "server_url": "http://localhost", "shared_secret": "s"}"#,
+        )
+        .unwrap();
+        assert!(confirm_parent(path, &config).is_ok());
+        config.license_id = "lic_other".to_string();
+        assert!(confirm_parent(path, &config).is_err());
+
+        // Explicit criteria take precedence over the signature
+        config.expected_parent_path = path.file_name().map(|n| n.to_string_lossy().to_string());
+        assert!(confirm_parent(path, &config).is_ok());
+        config.expected_parent_path = Some("/usr/bin/bash".to_string());
+        assert!(confirm_parent(path, &config).is_err());
+
+        config.expected_parent_sha256 = Some(sha256_file(path).unwrap().to_uppercase());
+        assert!(confirm_parent(path, &config).is_ok());
+        config.expected_parent_sha256 = Some("00".repeat(32));
+        assert!(confirm_parent(path, &config).is_err());
+    }
+}
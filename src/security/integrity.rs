@@ -0,0 +1,332 @@
+//! Binary self-integrity verification
+//!
+//! The expected SHA-256 of the overload's executable sections is stored in the
+//! patchable `.integ` section. It is written after linking by the `kc-integrity`
+//! build helper, since a binary cannot contain the hash of itself at compile time.
+//! Data sections (including the server-patched `.license`) are not covered.
+//!
+//! This file is also compiled into the `kc-integrity` helper, so it must not
+//! depend on anything else in the crate.
+
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+/// Name of the section holding the expected code hash (PE names max 8 bytes)
+pub const INTEGRITY_SECTION: &str = ".integ";
+
+/// Expected code hash, all zeros until provisioned by `kc-integrity`
+#[used]
+#[cfg_attr(target_os = "macos", unsafe(link_section = "__DATA,.integ"))]
+#[cfg_attr(not(target_os = "macos"), unsafe(link_section = ".integ"))]
+static INTEGRITY_DATA: [u8; 32] = [0; 32];
+
+/// A section of an executable file
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub name: String,
+    /// File offset of the section data
+    pub offset: usize,
+    /// Size of the section data in the file
+    pub size: usize,
+    /// Whether the section contains executable code
+    pub executable: bool,
+}
+
+/// Result of a self-integrity check
+#[derive(Debug, PartialEq)]
+pub enum IntegrityStatus {
+    /// Code sections match the embedded hash
+    Verified,
+    /// No hash was embedded at build time (development build)
+    NotProvisioned,
+    /// Code sections differ from the embedded hash
+    Mismatch,
+}
+
+/// Verify the running executable against the embedded code hash
+///
+/// # Returns
+/// Err if the executable could not be read or parsed (not treated as tamper)
+pub fn check_self() -> Result<IntegrityStatus, String> {
+    let Some(expected) = embedded_hash() else {
+        return Ok(IntegrityStatus::NotProvisioned);
+    };
+
+    let exe_data = read_own_executable()?;
+    let actual = compute_code_hash(&exe_data)?;
+
+    if bool::from(actual.ct_eq(&expected)) {
+        Ok(IntegrityStatus::Verified)
+    } else {
+        Ok(IntegrityStatus::Mismatch)
+    }
+}
+
+/// Get the embedded code hash (None if not provisioned)
+pub fn embedded_hash() -> Option<[u8; 32]> {
+    // Volatile read: the compiler must not fold the zero initializer, since the
+    // section is patched after linking
+    let hash = unsafe { std::ptr::read_volatile(&INTEGRITY_DATA) };
+    if hash.iter().all(|&b| b == 0) {
+        None
+    } else {
+        Some(hash)
+    }
+}
+
+/// Compute SHA-256 over all executable sections of an executable image
+pub fn compute_code_hash(data: &[u8]) -> Result<[u8; 32], String> {
+    let sections = parse_sections(data)?;
+    let mut hasher = Sha256::new();
+    let mut code_sections = 0;
+
+    for section in sections.iter().filter(|s| s.executable && s.size > 0) {
+        let end = section
+            .offset
+            .checked_add(section.size)
+            .filter(|&end| end <= data.len())
+            .ok_or_else(|| format!("Section {} exceeds file bounds", section.name))?;
+        hasher.update(section.name.as_bytes());
+        hasher.update(&data[section.offset..end]);
+        code_sections += 1;
+    }
+
+    if code_sections == 0 {
+        return Err("No executable sections found".to_string());
+    }
+
+    Ok(hasher.finalize().into())
+}
+
+/// Write the code hash of `data` into its integrity section
+///
+/// # Returns
+/// Hex-encoded hash that was embedded
+pub fn embed_hash(data: &mut [u8]) -> Result<String, String> {
+    let section = parse_sections(data)?
+        .into_iter()
+        .find(|s| s.name == INTEGRITY_SECTION)
+        .ok_or_else(|| format!("No {} section found", INTEGRITY_SECTION))?;
+
+    if section.size < 32 || section.offset + 32 > data.len() {
+        return Err(format!("{} section is too small", INTEGRITY_SECTION));
+    }
+
+    let hash = compute_code_hash(data)?;
+    data[section.offset..section.offset + 32].copy_from_slice(&hash);
+    Ok(hash.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Read the bytes of the running executable
+fn read_own_executable() -> Result<Vec<u8>, String> {
+    // On Linux we may run from memfd, where current_exe() is not a real path
+    #[cfg(target_os = "linux")]
+    if let Ok(data) = std::fs::read("/proc/self/exe") {
+        return Ok(data);
+    }
+
+    let exe_path = std::env::current_exe()
+        .map_err(|e| format!("Failed to get current executable path: {}", e))?;
+    std::fs::read(&exe_path)
+        .map_err(|e| format!("Failed to read executable {}: {}", exe_path.display(), e))
+}
+
+/// Parse the section table of an ELF, PE or Mach-O (64-bit) image
+pub fn parse_sections(data: &[u8]) -> Result<Vec<Section>, String> {
+    if data.starts_with(b"\x7fELF") {
+        parse_elf(data)
+    } else if data.starts_with(b"MZ") {
+        parse_pe(data)
+    } else if data.len() >= 4 && read_u32(data, 0)? == 0xfeed_facf {
+        parse_macho64(data)
+    } else {
+        Err("Unsupported executable format".to_string())
+    }
+}
+
+fn parse_elf(data: &[u8]) -> Result<Vec<Section>, String> {
+    const SHT_NOBITS: u32 = 8;
+    const SHF_EXECINSTR: u64 = 0x4;
+
+    let is_64 = match data.get(4) {
+        Some(1) => false,
+        Some(2) => true,
+        _ => return Err("Invalid ELF class".to_string()),
+    };
+    if data.get(5) != Some(&1) {
+        return Err("Only little-endian ELF is supported".to_string());
+    }
+
+    let (shoff, shentsize, shnum, shstrndx) = if is_64 {
+        (read_u64(data, 0x28)? as usize, read_u16(data, 0x3a)? as usize, read_u16(data, 0x3c)? as usize, read_u16(data, 0x3e)? as usize)
+    } else {
+        (read_u32(data, 0x20)? as usize, read_u16(data, 0x2e)? as usize, read_u16(data, 0x30)? as usize, read_u16(data, 0x32)? as usize)
+    };
+
+    // (name offset, type, flags, offset, size)
+    let header = |index: usize| -> Result<(usize, u32, u64, usize, usize), String> {
+        let base = shoff + index * shentsize;
+        if is_64 {
+            Ok((
+                read_u32(data, base)? as usize,
+                read_u32(data, base + 4)?,
+                read_u64(data, base + 8)?,
+                read_u64(data, base + 0x18)? as usize,
+                read_u64(data, base + 0x20)? as usize,
+            ))
+        } else {
+            Ok((
+                read_u32(data, base)? as usize,
+                read_u32(data, base + 4)?,
+                read_u32(data, base + 8)? as u64,
+                read_u32(data, base + 0x10)? as usize,
+                read_u32(data, base + 0x14)? as usize,
+            ))
+        }
+    };
+
+    let strtab_offset = if shstrndx < shnum { header(shstrndx)?.3 } else { 0 };
+
+    let mut sections = Vec::with_capacity(shnum);
+    for index in 0..shnum {
+        let (name_offset, sh_type, flags, offset, size) = header(index)?;
+        if sh_type == SHT_NOBITS {
+            continue;
+        }
+        sections.push(Section {
+            name: read_cstr(data, strtab_offset + name_offset, 64),
+            offset,
+            size,
+            executable: flags & SHF_EXECINSTR != 0,
+        });
+    }
+
+    Ok(sections)
+}
+
+fn parse_pe(data: &[u8]) -> Result<Vec<Section>, String> {
+    const IMAGE_SCN_CNT_CODE: u32 = 0x0000_0020;
+    const IMAGE_SCN_MEM_EXECUTE: u32 = 0x2000_0000;
+
+    let pe_offset = read_u32(data, 0x3c)? as usize;
+    if data.get(pe_offset..pe_offset + 4) != Some(b"PE\0\0") {
+        return Err("Invalid PE signature".to_string());
+    }
+
+    let coff = pe_offset + 4;
+    let section_count = read_u16(data, coff + 2)? as usize;
+    let optional_header_size = read_u16(data, coff + 16)? as usize;
+    let table = coff + 20 + optional_header_size;
+
+    let mut sections = Vec::with_capacity(section_count);
+    for index in 0..section_count {
+        let base = table + index * 40;
+        let characteristics = read_u32(data, base + 36)?;
+        sections.push(Section {
+            name: read_cstr(data, base, 8),
+            offset: read_u32(data, base + 20)? as usize,
+            size: read_u32(data, base + 16)? as usize,
+            executable: characteristics & (IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE) != 0,
+        });
+    }
+
+    Ok(sections)
+}
+
+fn parse_macho64(data: &[u8]) -> Result<Vec<Section>, String> {
+    const LC_SEGMENT_64: u32 = 0x19;
+    const S_ATTR_PURE_INSTRUCTIONS: u32 = 0x8000_0000;
+    const S_ATTR_SOME_INSTRUCTIONS: u32 = 0x0000_0400;
+
+    let command_count = read_u32(data, 16)? as usize;
+    let mut command = 32;
+    let mut sections = Vec::new();
+
+    for _ in 0..command_count {
+        let cmd = read_u32(data, command)?;
+        let cmd_size = read_u32(data, command + 4)? as usize;
+
+        if cmd == LC_SEGMENT_64 {
+            let section_count = read_u32(data, command + 64)? as usize;
+            for index in 0..section_count {
+                let base = command + 72 + index * 80;
+                let flags = read_u32(data, base + 64)?;
+                sections.push(Section {
+                    name: read_cstr(data, base, 16),
+                    offset: read_u32(data, base + 48)? as usize,
+                    size: read_u64(data, base + 40)? as usize,
+                    executable: flags & (S_ATTR_PURE_INSTRUCTIONS | S_ATTR_SOME_INSTRUCTIONS) != 0,
+                });
+            }
+        }
+
+        if cmd_size == 0 {
+            return Err("Invalid Mach-O load command size".to_string());
+        }
+        command += cmd_size;
+    }
+
+    Ok(sections)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, String> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| format!("Truncated image at offset 0x{:x}", offset))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, String> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| format!("Truncated image at offset 0x{:x}", offset))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, String> {
+    data.get(offset..offset + 8)
+        .map(|b| u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+        .ok_or_else(|| format!("Truncated image at offset 0x{:x}", offset))
+}
+
+fn read_cstr(data: &[u8], offset: usize, max_len: usize) -> String {
+    let bytes = data.get(offset..).unwrap_or(&[]);
+    let bytes = &bytes[..bytes.len().min(max_len)];
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..len]).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_own_executable() {
+        let data = read_own_executable().unwrap();
+        let sections = parse_sections(&data).unwrap();
+
+        assert!(sections.iter().any(|s| s.executable));
+        assert!(sections.iter().any(|s| s.name == INTEGRITY_SECTION));
+        assert!(!sections.iter().any(|s| s.name == ".license" && s.executable));
+    }
+
+    #[test]
+    fn test_embed_hash_ignores_data_sections() {
+        let mut data = read_own_executable().unwrap();
+        let original = compute_code_hash(&data).unwrap();
+
+        let embedded = embed_hash(&mut data).unwrap();
+        assert_eq!(embedded.len(), 64);
+
+        // Patching .integ (and .license) must not change the code hash
+        assert_eq!(compute_code_hash(&data).unwrap(), original);
+
+        // Patching a code byte must
+        let text = parse_sections(&data).unwrap().into_iter().find(|s| s.executable && s.size > 0).unwrap();
+        data[text.offset] ^= 0xff;
+        assert_ne!(compute_code_hash(&data).unwrap(), original);
+    }
+
+    #[test]
+    fn test_rejects_unknown_format() {
+        assert!(parse_sections(b"not an executable").is_err());
+    }
+}
@@ -1,21 +1,28 @@
 /// Kill parent binary according to configured method
 use std::fs;
-use std::io::{Write, Seek, SeekFrom};
-use std::path::PathBuf;
-use std::process::exit;
-use crate::config::KillMethod;
-use crate::utils::process::get_parent_pid;
+use std::path::{Path, PathBuf};
+use crate::utils::exit_status::{self, ExitStatus};
+use serde::Serialize;
+use crate::config::{Config, KillMethod, ShredPattern};
+use crate::security::{capabilities, corrupt, erase, escrow, hook, identity, lineage, lock, privileges, WipePlan};
+use crate::utils::process::{self, get_parent_pid};
+use crate::utils::session;
+use crate::verification::kill_report;
 
-// Platform-specific imports
-#[cfg(unix)]
-use std::os::unix::process::parent_id;
+/// Env var marking the detached helper that shreds a corrupted binary (its
+/// job token, see `utils::helper`)
+pub const BACKGROUND_SHRED_ENV: &str = "KILLCODE_BACKGROUND_SHRED";
+
+/// Helper job kind of the background shred
+const BACKGROUND_SHRED_JOB: &str = "background-shred";
 
+// Platform-specific imports
 #[cfg(windows)]
 use std::os::windows::process::CommandExt;
 
 
 /// Get parent binary path from PID (cross-platform)
-fn get_parent_binary_path(ppid: u32) -> Option<PathBuf> {
+pub fn get_parent_binary_path(ppid: u32) -> Option<PathBuf> {
     #[cfg(target_os = "linux")]
     {
         fs::read_link(format!("/proc/{}/exe", ppid)).ok()
@@ -42,6 +49,20 @@ fn get_parent_binary_path(ppid: u32) -> Option<PathBuf> {
         }
     }
     
+    #[cfg(target_os = "freebsd")]
+    {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = process::sysctl(&[libc::CTL_KERN, libc::KERN_PROC, libc::KERN_PROC_PATHNAME, ppid as i32])?;
+        let path = path.split(|&byte| byte == 0).next().filter(|path| !path.is_empty())?;
+        Some(PathBuf::from(std::ffi::OsStr::from_bytes(path)))
+    }
+
+    #[cfg(target_os = "openbsd")]
+    {
+        openbsd_binary_path(ppid)
+    }
+
     #[cfg(windows)]
     {
         use std::mem;
@@ -80,24 +101,101 @@ fn get_parent_binary_path(ppid: u32) -> Option<PathBuf> {
     }
 }
 
-/// Stop parent process (cross-platform)
+/// Binary of process `pid` on OpenBSD, which has no pathname sysctl
+///
+/// argv[0] is resolved against the process's working directory. A bare
+/// command name was looked up in a `PATH` we cannot see, and guessing could
+/// point the kill method at an unrelated binary, so it yields None.
+#[cfg(target_os = "openbsd")]
+fn openbsd_binary_path(pid: u32) -> Option<PathBuf> {
+    use std::os::unix::ffi::OsStrExt;
+
+    // An array of argv pointers into the same buffer, then the strings
+    let args = process::sysctl(&[libc::CTL_KERN, libc::KERN_PROC_ARGS, pid as i32, libc::KERN_PROC_ARGV])?;
+    if args.len() < std::mem::size_of::<usize>() {
+        return None;
+    }
+    let argv0 = usize::from_ne_bytes(args[..std::mem::size_of::<usize>()].try_into().ok()?);
+    let offset = argv0.checked_sub(args.as_ptr() as usize).filter(|&offset| offset < args.len())?;
+    let argv0 = args[offset..].split(|&byte| byte == 0).next()?;
+    let argv0 = Path::new(std::ffi::OsStr::from_bytes(argv0));
+
+    let path = if argv0.is_absolute() {
+        argv0.to_path_buf()
+    } else if argv0.components().count() > 1 {
+        let cwd = process::sysctl(&[libc::CTL_KERN, libc::KERN_PROC_CWD, pid as i32])?;
+        let cwd = cwd.split(|&byte| byte == 0).next().filter(|cwd| !cwd.is_empty())?;
+        Path::new(std::ffi::OsStr::from_bytes(cwd)).join(argv0)
+    } else {
+        return None;
+    };
+    path.is_file().then_some(path)
+}
+
+/// Stop parent process and all of its descendants (cross-platform)
+///
+/// Workers spawned by the protected app would otherwise survive enforcement.
+/// On Unix the tree is frozen first: the parent and then every descendant
+/// found get SIGSTOP, until a walk finds no new one, so nothing in it can
+/// fork, exit and reparent its children while it is being killed. The frozen
+/// processes are then killed deepest first. Descendants are found by parent
+/// PID in any session: a worker that called `setsid` is still the app's. We
+/// are a child of the parent ourselves, so our own PID is skipped.
 pub fn stop_parent(ppid: u32) -> Result<(), String> {
-    eprintln!("🛑 Stopping parent process PID {}...", ppid);
+    /// Walks of the process table while freezing; a tree still growing
+    /// after that many is killed as found
+    const FREEZE_ROUNDS: usize = 16;
+
+    let own_pid = std::process::id();
+    #[cfg(unix)]
+    freeze(ppid);
+    let mut descendants: Vec<u32> = Vec::new();
+    for _ in 0..FREEZE_ROUNDS {
+        let found: Vec<u32> = process::descendants(ppid, &process::process_table())
+            .into_iter()
+            .filter(|&pid| pid != own_pid)
+            .collect();
+        let new: Vec<u32> = found.iter().copied().filter(|pid| !descendants.contains(pid)).collect();
+        if new.is_empty() {
+            break;
+        }
+        #[cfg(unix)]
+        new.iter().for_each(|&pid| freeze(pid));
+        descendants = found;
+    }
     
+    if !descendants.is_empty() {
+        log_info!("🌳 Stopping {} descendant process(es) of PID {}...", descendants.len(), ppid);
+        for &pid in &descendants {
+            if let Err(e) = stop_process(pid) {
+                log_warn!("⚠️  Failed to stop descendant {}: {}", pid, e);
+            }
+        }
+    }
+    
+    log_warn!("🛑 Stopping parent process PID {}...", ppid);
+    stop_process(ppid)?;
+    log_info!("✅ Parent process stopped");
+    Ok(())
+}
+
+#[cfg(unix)]
+fn freeze(pid: u32) {
+    unsafe {
+        libc::kill(pid as i32, libc::SIGSTOP);
+    }
+}
+
+/// Stop one process: SIGKILL (a frozen process never handles SIGTERM),
+/// TerminateProcess on Windows
+fn stop_process(pid: u32) -> Result<(), String> {
     #[cfg(unix)]
     {
-        // Unix: Use signals
-        unsafe {
-            libc::kill(ppid as i32, libc::SIGTERM);
-        }
-        
-        std::thread::sleep(std::time::Duration::from_millis(100));
-        
-        // Check if still alive
-        if std::path::Path::new(&format!("/proc/{}", ppid)).exists() {
-            eprintln!("⚠️  Process still alive, sending SIGKILL...");
-            unsafe {
-                libc::kill(ppid as i32, libc::SIGKILL);
+        if unsafe { libc::kill(pid as i32, libc::SIGKILL) } != 0 {
+            let e = std::io::Error::last_os_error();
+            // Already gone is stopped
+            if e.raw_os_error() != Some(libc::ESRCH) {
+                return Err(format!("Failed to kill process {}: {}", pid, e));
             }
         }
     }
@@ -109,11 +207,11 @@ pub fn stop_parent(ppid: u32) -> Result<(), String> {
             let handle = winapi::um::processthreadsapi::OpenProcess(
                 winapi::um::winnt::PROCESS_TERMINATE,
                 0,
-                ppid,
+                pid,
             );
             
             if handle.is_null() {
-                return Err(format!("Failed to open process {}", ppid));
+                return Err(format!("Failed to open process {}", pid));
             }
             
             let result = winapi::um::processthreadsapi::TerminateProcess(handle, 1);
@@ -125,7 +223,6 @@ pub fn stop_parent(ppid: u32) -> Result<(), String> {
         }
     }
     
-    eprintln!("✅ Parent process stopped");
     Ok(())
 }
 
@@ -138,23 +235,113 @@ fn delete_parent(ppid: u32, path: &PathBuf) -> Result<(), String> {
     std::thread::sleep(std::time::Duration::from_millis(200));
     
     // Delete the file
-    eprintln!("🗑️  Deleting parent binary: {}", path.display());
+    log_warn!("🗑️  Deleting parent binary: {}", path.display());
     fs::remove_file(path)
         .map_err(|e| format!("Failed to delete parent binary: {}", e))?;
     
-    eprintln!("✅ Parent binary deleted");
+    log_info!("✅ Parent binary deleted");
+    Ok(())
+}
+
+/// Shred parent binary (overwrite passes + delete, cross-platform)
+fn shred_parent(ppid: u32, path: &Path, plan: &WipePlan) -> Result<(), String> {
+    // First stop the process
+    stop_parent(ppid)?;
+    
+    // Wait for process to fully terminate
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    
+    shred_file(path, plan)?;
+    
+    log_info!("✅ Parent binary securely shredded and deleted");
+    Ok(())
+}
+
+/// Corrupt parent binary (header + entry point), optionally shred it afterwards
+fn corrupt_parent(ppid: u32, path: &Path, config: &Config) -> Result<(), String> {
+    // First stop the process (a running executable cannot be written)
+    stop_parent(ppid)?;
+    
+    // Wait for process to fully terminate
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    
+    log_error!("💥 Corrupting parent binary: {}", path.display());
+    corrupt::corrupt_binary(path)?;
+    log_info!("✅ Parent binary neutralized");
+    
+    // The binary is already unusable; the slow full wipe must not hold us up
+    if config.corrupt_then_shred {
+        spawn_background_shred(path);
+    }
     Ok(())
 }
 
-/// Shred parent binary (3-pass overwrite + delete, cross-platform)
-fn shred_parent(ppid: u32, path: &PathBuf) -> Result<(), String> {
+/// Lock parent binary (deny execution, optionally rename); reversible
+fn lock_parent(ppid: u32, path: &Path, config: &Config) -> Result<(), String> {
     // First stop the process
     stop_parent(ppid)?;
     
     // Wait for process to fully terminate
     std::thread::sleep(std::time::Duration::from_millis(200));
     
-    eprintln!("🔥 Shredding parent binary: {}", path.display());
+    log_warn!("🔒 Locking parent binary: {}", path.display());
+    let locked = lock::lock_binary(path, config.lock_rename)?;
+    log_info!("✅ Parent binary locked: {}", locked.display());
+    Ok(())
+}
+
+/// Start a detached copy of ourselves that shreds `path`
+///
+/// The path goes through a job file only this user can write; the env var
+/// carries nothing but its token.
+fn spawn_background_shred(path: &Path) {
+    let token = match crate::utils::helper::issue(BACKGROUND_SHRED_JOB, &path.to_string_lossy()) {
+        Ok(token) => token,
+        Err(e) => {
+            log_warn!("⚠️  Cannot start background shred: {}", e);
+            return;
+        }
+    };
+    let result = super::memexec::image_path().and_then(|exe| {
+        std::process::Command::new(exe)
+            .env(BACKGROUND_SHRED_ENV, token)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+    });
+    
+    match result {
+        Ok(child) => log_warn!("🔥 Full shred continues in background (PID {})", child.id()),
+        Err(e) => log_warn!("⚠️  Cannot start background shred: {}", e),
+    }
+}
+
+/// Background-shred helper: shred the binary named by the job of `token`
+///
+/// Exits with the helper status whatever happened, so the env var can never
+/// pass for a verification.
+pub fn run_background_shred(config: &Config, token: &str) -> ! {
+    let path = match crate::utils::helper::redeem(BACKGROUND_SHRED_JOB, token) {
+        Ok(path) => PathBuf::from(path),
+        Err(e) => {
+            log_error!("❌ Refusing background shred: {}", e);
+            exit_status::exit(ExitStatus::Helper, &e);
+        }
+    };
+    let plan = WipePlan::from_config(config, ShredPattern::Classic);
+    match shred_file(&path, &plan) {
+        Ok(()) => exit_status::exit(ExitStatus::Helper, "background shred finished"),
+        Err(e) => {
+            log_error!("❌ Background shred failed: {}", e);
+            exit_status::exit(ExitStatus::Helper, &e);
+        }
+    }
+}
+
+/// Overwrite a file according to the wipe plan and delete it
+pub fn shred_file(path: &Path, plan: &WipePlan) -> Result<(), String> {
+    log_warn!("🔥 Shredding parent binary: {}", path.display());
     
     // Open file for overwriting
     let mut file = fs::OpenOptions::new()
@@ -165,85 +352,269 @@ fn shred_parent(ppid: u32, path: &PathBuf) -> Result<(), String> {
     // Get file size
     let metadata = file.metadata()
         .map_err(|e| format!("Failed to get file metadata: {}", e))?;
-    let file_size = metadata.len() as usize;
-    
-    eprintln!("📏 File size: {} bytes, starting 3-pass overwrite...", file_size);
+    let file_size = metadata.len();
     
-    // 3-pass overwrite
-    let patterns: [u8; 3] = [0x00, 0xFF, 0xAA];
+    log_debug!("📏 File size: {} bytes, starting {}-pass overwrite...", file_size, plan.passes);
     
-    for (pass, pattern) in patterns.iter().enumerate() {
-        eprintln!("🔄 Pass {}/3: Writing 0x{:02X}...", pass + 1, pattern);
+    for pass in 0..plan.passes {
+        log_info!("🔄 Pass {}/{}: Writing {}...", pass + 1, plan.passes, plan.describe(pass));
         
-        file.seek(SeekFrom::Start(0))
-            .map_err(|e| format!("Failed to seek: {}", e))?;
-        
-        let buffer = vec![*pattern; 8192];
-        let mut remaining = file_size;
-        
-        while remaining > 0 {
-            let write_size = remaining.min(buffer.len());
-            file.write_all(&buffer[..write_size])
-                .map_err(|e| format!("Failed to write during shred: {}", e))?;
-            remaining -= write_size;
-        }
+        plan.overwrite_pass(pass, &mut file, file_size)
+            .map_err(|e| format!("Failed to write during shred: {}", e))?;
         
         file.sync_all()
             .map_err(|e| format!("Failed to sync: {}", e))?;
     }
     
-    drop(file);
-    
-    // Finally delete the file
-    eprintln!("🗑️  Deleting shredded file...");
-    fs::remove_file(path)
-        .map_err(|e| format!("Failed to delete shredded file: {}", e))?;
-    
-    eprintln!("✅ Parent binary securely shredded and deleted");
+    // Finally delete the file (and what the filesystem kept of it)
+    log_warn!("🗑️  Deleting shredded file...");
+    erase::unlink(path, file)?;
+    erase::trim_in_background(path);
     Ok(())
 }
 
+/// What `execute_kill` would do against a process, without doing it
+#[derive(Debug, Clone, Serialize)]
+pub struct KillPlan {
+    pub requested_method: KillMethod,
+    pub effective_method: KillMethod,
+    /// Why the effective method differs from the requested one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub downgrade_reason: Option<String>,
+    pub target_pid: u32,
+    /// Binary that would be deleted/shredded/corrupted/locked (None: unresolvable)
+    pub target_path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub escrow_stub: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wipe: Option<WipePlan>,
+    /// Corrupt kill followed by a detached full shred
+    pub background_shred: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_kill_hook: Option<String>,
+}
+
+/// Plan the kill of `pid` (read-only, see `killer audit`)
+pub fn plan_kill(kill_method: &KillMethod, config: &Config, pid: u32) -> KillPlan {
+    let requested_method = kill_method.clone();
+    let supported = capabilities::supported_kill_method(kill_method);
+    let mut downgrade_reason = (supported != requested_method)
+        .then(|| format!("{:?} unsupported on {}", requested_method, std::env::consts::OS));
+
+    let target_path = get_parent_binary_path(pid);
+    let effective_method = match &target_path {
+        Some(path) => {
+            let mut method = session_safe_method(&supported, path);
+            if method != supported {
+                downgrade_reason = Some("binary is in use by other login sessions".to_string());
+            } else if let Err(reason) = identity_safe_method(&mut method, path, config) {
+                downgrade_reason = Some(format!("parent identity not confirmed: {}", reason));
+            }
+            method
+        }
+        // Without a path only the process can be stopped
+        None => KillMethod::Stop,
+    };
+
+    let destructive = effective_method.destroys_binary();
+    let wipe = match effective_method {
+        KillMethod::Shred => Some(WipePlan::from_config(config, ShredPattern::Classic)),
+        KillMethod::Corrupt if config.corrupt_then_shred => Some(WipePlan::from_config(config, ShredPattern::Classic)),
+        _ => None,
+    };
+
+    KillPlan {
+        escrow_stub: target_path.as_deref().filter(|_| destructive && config.escrow_on_destroy).map(escrow::stub_path),
+        background_shred: effective_method == KillMethod::Corrupt && config.corrupt_then_shred,
+        pre_kill_hook: config.pre_kill_hook.clone(),
+        requested_method,
+        effective_method,
+        downgrade_reason,
+        target_pid: pid,
+        target_path,
+        wipe,
+    }
+}
+
+/// Downgrade destructive methods to Stop when other sessions use the binary
+fn session_safe_method(kill_method: &KillMethod, path: &Path) -> KillMethod {
+    if *kill_method != KillMethod::Stop && session::binary_in_use_by_other_sessions(path) {
+        KillMethod::Stop
+    } else {
+        kill_method.clone()
+    }
+}
+
+/// Downgrade destructive methods to Stop unless the binary is confirmed to
+/// be the protected app (see `identity`)
+fn identity_safe_method(kill_method: &mut KillMethod, path: &Path, config: &Config) -> Result<(), String> {
+    if *kill_method == KillMethod::Stop {
+        return Ok(());
+    }
+    match identity::confirm_parent(path, config) {
+        Ok(confirmation) => {
+            log_debug!("🪪 Parent identity confirmed: {}", confirmation);
+            Ok(())
+        }
+        Err(reason) => {
+            *kill_method = KillMethod::Stop;
+            Err(reason)
+        }
+    }
+}
+
 /// Execute kill method based on config
-pub fn execute_kill(kill_method: &KillMethod) {
-    eprintln!("🚨 Executing kill method: {:?}", kill_method);
+pub fn execute_kill(kill_method: &KillMethod, config: &Config) {
+    log_error!("🚨 Executing kill method: {:?}", kill_method);
     
-    // Get parent PID
-    let ppid = match get_parent_pid() {
-        Some(pid) => pid,
-        None => {
-            eprintln!("❌ Failed to get parent PID");
-            exit(1);
+    // After a privilege drop only the root broker can still reach the parent
+    if let Some(result) = privileges::delegate_kill(kill_method) {
+        if let Err(e) = result {
+            log_error!("❌ Kill execution failed: {}", e);
+            exit_status::exit(ExitStatus::Unauthorized, &format!("kill failed: {}", e));
         }
-    };
+        log_info!("✅ Kill method executed by the privileged broker");
+        return;
+    }
     
-    eprintln!("📍 Parent PID: {}", ppid);
+    // Get parent PID (a daemon was reparented: its app is the recorded one).
+    // PID 1 adopted us after the app exited: there is nothing to kill
+    let ppid = match lineage::original_parent().or_else(get_parent_pid) {
+        Some(pid) if pid > 1 => pid,
+        _ => {
+            log_error!("❌ Failed to get parent PID");
+            exit_status::exit(ExitStatus::Unauthorized, "kill failed: no parent process");
+        }
+    };
+    execute_kill_target(kill_method, config, ppid);
+}
+
+/// Execute kill method against process `ppid` (the protected app), exiting
+/// if it fails
+pub fn execute_kill_target(kill_method: &KillMethod, config: &Config, ppid: u32) {
+    if let Err(e) = kill_target(kill_method, config, ppid) {
+        log_error!("❌ Kill execution failed: {}", e);
+        exit_status::exit(ExitStatus::Unauthorized, &format!("kill failed: {}", e));
+    }
+}
+
+/// Execute kill method against process `ppid` (the protected app)
+pub fn kill_target(kill_method: &KillMethod, config: &Config, ppid: u32) -> Result<(), String> {
+    log_info!("📍 Parent PID: {}", ppid);
     
     // Get parent binary path
     let path = match get_parent_binary_path(ppid) {
         Some(p) => p,
         None => {
-            eprintln!("❌ Failed to get parent binary path");
             // Still try to stop the process
             if let Err(e) = stop_parent(ppid) {
-                eprintln!("❌ Failed to stop parent: {}", e);
+                log_error!("❌ Failed to stop parent: {}", e);
             }
-            exit(1);
+            return Err("Failed to get parent binary path".to_string());
         }
     };
     
-    eprintln!("📂 Parent binary: {}", path.display());
+    log_debug!("📂 Parent binary: {}", path.display());
+    
+    // On multi-user machines the binary may be shared: destroying it would
+    // take down other sessions, so only this session's process is stopped
+    let mut safe_method = session_safe_method(kill_method, &path);
+    if safe_method != *kill_method {
+        log_info!("👥 Binary is in use by other login sessions - downgrading {:?} to Stop", kill_method);
+    }
+    // PID reuse or an unexpected launcher: never destroy a stranger's binary
+    if let Err(reason) = identity_safe_method(&mut safe_method, &path, config) {
+        log_warn!("🪪 Parent identity not confirmed ({}) - downgrading {:?} to Stop", reason, kill_method);
+    }
+    let kill_method = &safe_method;
+    
+    // Tell the server before anything is destroyed: afterwards there may be
+    // nothing left to report from
+    kill_report::report_kill(config, kill_method.as_str(), "initiated", None);
+    hook::run_pre_kill_hook(config, kill_method.as_str());
+    
+    // Deposit a recovery escrow before anything is destroyed
+    if config.escrow_on_destroy
+        && kill_method.destroys_binary()
+        && let Err(e) = escrow::write_escrow_stub(&path, config, kill_method)
+    {
+        log_warn!("⚠️  Escrow failed, continuing with kill: {}", e);
+    }
     
     // Execute kill method
     let result = match kill_method {
         KillMethod::Stop => stop_parent(ppid),
         KillMethod::Delete => delete_parent(ppid, &path),
-        KillMethod::Shred => shred_parent(ppid, &path),
+        KillMethod::Shred => shred_parent(ppid, &path, &WipePlan::from_config(config, ShredPattern::Classic)),
+        KillMethod::Corrupt => corrupt_parent(ppid, &path, config),
+        KillMethod::Lock => lock_parent(ppid, &path, config),
     };
     
     if let Err(e) = result {
-        eprintln!("❌ Kill execution failed: {}", e);
-        exit(1);
+        kill_report::report_kill(config, kill_method.as_str(), "failed", Some(&e));
+        return Err(e);
+    }
+    
+    log_info!("✅ Kill method executed successfully");
+    kill_report::report_kill(config, kill_method.as_str(), "succeeded", None);
+    Ok(())
+}
+
+/// Execute a kill method against the calling process itself, then exit
+///
+/// For hosts embedding the library in-process (FFI) there is no separate
+/// overload whose parent is the protected app: the caller is the app. A
+/// running executable cannot be overwritten on every platform, so a failed
+/// shred or corrupt falls back to deleting the binary (a failed lock does not).
+pub fn execute_kill_self(kill_method: &KillMethod, config: &Config) -> ! {
+    log_error!("🚨 Executing kill method on this process: {:?}", kill_method);
+    
+    let path = match super::memexec::executable_path() {
+        Ok(path) => path,
+        Err(e) => {
+            log_error!("❌ Failed to get executable path: {}", e);
+            exit_status::exit(ExitStatus::Unauthorized, "kill failed: no executable path");
+        }
+    };
+    
+    let kill_method = &session_safe_method(kill_method, &path);
+    kill_report::report_kill(config, kill_method.as_str(), "initiated", None);
+    hook::run_pre_kill_hook(config, kill_method.as_str());
+    
+    if config.escrow_on_destroy
+        && kill_method.destroys_binary()
+        && let Err(e) = escrow::write_escrow_stub(&path, config, kill_method)
+    {
+        log_warn!("⚠️  Escrow failed, continuing with kill: {}", e);
+    }
+    
+    let result = match kill_method {
+        KillMethod::Stop => Ok(()),
+        KillMethod::Delete => fs::remove_file(&path).map_err(|e| format!("Failed to delete binary: {}", e)),
+        KillMethod::Shred => shred_file(&path, &WipePlan::from_config(config, ShredPattern::Classic)),
+        KillMethod::Corrupt => corrupt::corrupt_binary(&path),
+        KillMethod::Lock => lock::lock_binary(&path, config.lock_rename).map(|_| ()),
+    };
+    
+    match result {
+        Ok(()) => kill_report::report_kill(config, kill_method.as_str(), "succeeded", None),
+        // A lock is meant to be reversible: never fall back to deleting
+        Err(e) if *kill_method == KillMethod::Lock => {
+            log_error!("❌ Kill execution failed: {}", e);
+            kill_report::report_kill(config, kill_method.as_str(), "failed", Some(&e));
+        }
+        Err(e) => {
+            log_warn!("⚠️  {} - deleting the binary instead", e);
+            match fs::remove_file(&path) {
+                Ok(()) => kill_report::report_kill(config, kill_method.as_str(), "succeeded", Some("binary deleted instead")),
+                Err(e) => {
+                    log_error!("❌ Kill execution failed: {}", e);
+                    kill_report::report_kill(config, kill_method.as_str(), "failed", Some(&e.to_string()));
+                }
+            }
+        }
     }
     
-    eprintln!("✅ Kill method executed successfully");
+    log_error!("🛑 Terminating unauthorized process");
+    exit_status::exit(ExitStatus::Unauthorized, "unauthorized process terminated");
 }
@@ -0,0 +1,320 @@
+//! Daemon mode and parent lineage
+//!
+//! With `daemonize` set (Unix, loop mode) the overload detaches from the
+//! loader the classic way: fork, `setsid`, fork again, standard streams on
+//! `/dev/null` (log through `log_file`). Closing the loader's terminal or
+//! process group no longer takes the overload down. The loader's own child
+//! waits until the daemon reports the outcome of its first check (or exits)
+//! and exits with that status, so the loader never mistakes the detach for
+//! an authorization.
+//!
+//! Before detaching, the parent is recorded: PID, start time, binary path
+//! and the SHA-256 of its running image. Every check cycle compares that
+//! record with the live process:
+//! - the recorded PID now belongs to a process started later: the parent
+//!   exited and its PID was reused
+//! - the recorded PID runs another binary, or its image changed: the parent
+//!   was replaced (exec) - a tamper event
+//! - our own parent changed while the recorded one still runs: unexpected
+//!   reparenting - a tamper event
+//! - the recorded parent is gone: the app exited and we exit with it. A
+//!   killed and respawned parent starts an overload of its own, which
+//!   verifies again.
+//!
+//! Kills target the recorded parent (`original_parent`), never whatever we
+//! were reparented to.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+use super::identity;
+use super::kill_parent::get_parent_binary_path;
+use crate::utils::process::{get_parent_pid, process_table, start_time};
+
+/// Parent recorded before daemonizing (PID, start time)
+static ORIGINAL_PARENT: OnceLock<(u32, Option<u64>)> = OnceLock::new();
+
+/// Write end of the pipe the loader's child waits on (-1: none)
+#[cfg(unix)]
+static STATUS_PIPE: std::sync::atomic::AtomicI32 = std::sync::atomic::AtomicI32::new(-1);
+
+/// The parent as recorded at startup, if `record` ran and it still runs
+///
+/// None once its PID belongs to another process: kills must never hit that.
+pub fn original_parent() -> Option<u32> {
+    let &(pid, started) = ORIGINAL_PARENT.get()?;
+    same_process(pid, started).then_some(pid)
+}
+
+/// Whether `pid` is still the process that had start time `started`
+fn same_process(pid: u32, started: Option<u64>) -> bool {
+    started.is_none_or(|started| start_time(pid) == Some(started))
+}
+
+/// What a lineage check found
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParentState {
+    /// The recorded parent still runs the recorded binary
+    Present,
+    /// The recorded parent has exited
+    Exited,
+}
+
+/// Recorded parent identity
+#[derive(Debug)]
+pub struct Lineage {
+    parent: u32,
+    /// Start time of the parent (tells it from a process reusing its PID)
+    started: Option<u64>,
+    /// Parent we expect to have now (changes once when daemonizing)
+    expected_ppid: u32,
+    binary: Option<PathBuf>,
+    /// Size and mtime of the image when it was hashed
+    stamp: Option<(u64, SystemTime)>,
+    hash: Option<String>,
+}
+
+impl Lineage {
+    /// Record the current parent
+    pub fn record() -> Option<Self> {
+        let parent = get_parent_pid().filter(|&pid| pid > 1)?;
+        let image = image_path(parent);
+        let lineage = Self {
+            parent,
+            started: start_time(parent),
+            expected_ppid: parent,
+            binary: get_parent_binary_path(parent).map(strip_deleted),
+            stamp: image.as_deref().and_then(stamp),
+            hash: image.as_deref().and_then(|image| identity::sha256_file(image).ok()),
+        };
+        let _ = ORIGINAL_PARENT.set((parent, lineage.started));
+        log_debug!("🧬 Parent recorded: PID {} ({:?})", parent, lineage.binary);
+        Some(lineage)
+    }
+
+    /// Accept our current parent as expected (after daemonizing, which
+    /// returns once we were reparented)
+    pub fn adopt_current_parent(&mut self) {
+        if let Some(ppid) = get_parent_pid() {
+            self.expected_ppid = ppid;
+        }
+    }
+
+    /// Compare the record with the live process
+    ///
+    /// # Returns
+    /// Err describing the tamper if the parent was replaced or we were
+    /// reparented unexpectedly
+    pub fn check(&mut self) -> Result<ParentState, String> {
+        if !process_table().iter().any(|(pid, _)| *pid == self.parent) || !same_process(self.parent, self.started) {
+            return Ok(ParentState::Exited);
+        }
+
+        if let Some(ppid) = get_parent_pid()
+            && ppid != self.expected_ppid
+        {
+            return Err(format!(
+                "reparented from PID {} to PID {} while the parent still runs",
+                self.expected_ppid, ppid
+            ));
+        }
+
+        if let (Some(recorded), Some(current)) = (&self.binary, get_parent_binary_path(self.parent).map(strip_deleted))
+            && *recorded != current
+        {
+            return Err(format!(
+                "parent PID {} now runs {} instead of {}",
+                self.parent,
+                current.display(),
+                recorded.display()
+            ));
+        }
+
+        // Re-hashing is only needed when the image looks different
+        let Some(image) = image_path(self.parent) else {
+            return Ok(ParentState::Present);
+        };
+        let current_stamp = stamp(&image);
+        if current_stamp.is_none() || current_stamp == self.stamp {
+            return Ok(ParentState::Present);
+        }
+        let hash = identity::sha256_file(&image).ok();
+        if self.hash.is_some() && hash.is_some() && hash != self.hash {
+            return Err(format!("parent PID {} runs a different binary image", self.parent));
+        }
+        self.stamp = current_stamp;
+        Ok(ParentState::Present)
+    }
+}
+
+/// File backing the running image of `pid`: on Linux the image itself, even
+/// after the path was replaced on disk (an update is not a replacement)
+fn image_path(pid: u32) -> Option<PathBuf> {
+    #[cfg(target_os = "linux")]
+    {
+        Some(PathBuf::from(format!("/proc/{}/exe", pid)))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        get_parent_binary_path(pid)
+    }
+}
+
+fn stamp(path: &Path) -> Option<(u64, SystemTime)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+/// Linux marks an image whose path was replaced with " (deleted)"
+fn strip_deleted(path: PathBuf) -> PathBuf {
+    match path.to_str().and_then(|p| p.strip_suffix(" (deleted)")) {
+        Some(stripped) => PathBuf::from(stripped),
+        None => path,
+    }
+}
+
+/// Detach from the loader: fork, new session, fork, standard streams on
+/// `/dev/null`
+///
+/// Returns in the daemon only, once the intermediate process has exited and
+/// we were reparented. The loader's child exits with the status the daemon
+/// reports (`report_status`), or `internal_error` if the daemon dies first;
+/// neither it nor the intermediate process runs shutdown hooks (they belong
+/// to the daemon). Must run before any thread is started: only the forking
+/// thread survives a fork.
+#[cfg(unix)]
+pub fn daemonize() -> Result<(), String> {
+    use std::sync::atomic::Ordering;
+
+    let mut fds = [-1; 2];
+    // Not chdir("/"): relative paths in the config stay valid
+    unsafe {
+        if libc::pipe(fds.as_mut_ptr()) == -1 {
+            return Err(format!("pipe failed: {}", std::io::Error::last_os_error()));
+        }
+        // Children we exec must not hold the loader's child up
+        for fd in fds {
+            libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+        }
+        let [read_end, write_end] = fds;
+        match libc::fork() {
+            -1 => {
+                let e = std::io::Error::last_os_error();
+                libc::close(read_end);
+                libc::close(write_end);
+                return Err(format!("fork failed: {}", e));
+            }
+            0 => {
+                libc::close(read_end);
+                STATUS_PIPE.store(write_end, Ordering::SeqCst);
+            }
+            intermediate => {
+                libc::close(write_end);
+                libc::_exit(await_status(read_end, intermediate));
+            }
+        }
+        if libc::setsid() == -1 {
+            return Err(format!("setsid failed: {}", std::io::Error::last_os_error()));
+        }
+        // A session leader could acquire a controlling terminal again
+        match libc::fork() {
+            -1 => return Err(format!("fork failed: {}", std::io::Error::last_os_error())),
+            0 => {}
+            _ => libc::_exit(0),
+        }
+        // Adopting our parent before the intermediate is gone would expect it
+        let intermediate = libc::getppid();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while libc::getppid() == intermediate && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+
+        let null = libc::open(c"/dev/null".as_ptr(), libc::O_RDWR);
+        if null == -1 {
+            return Err(format!("cannot open /dev/null: {}", std::io::Error::last_os_error()));
+        }
+        for fd in [libc::STDIN_FILENO, libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+            libc::dup2(null, fd);
+        }
+        if null > libc::STDERR_FILENO {
+            libc::close(null);
+        }
+    }
+    Ok(())
+}
+
+/// Loader's child after forking: reap the intermediate process, then wait
+/// for the daemon's status
+///
+/// # Returns
+/// The exit code the daemon reported, `internal_error` if it never did
+#[cfg(unix)]
+fn await_status(read_end: libc::c_int, intermediate: libc::pid_t) -> i32 {
+    use crate::utils::exit_status::ExitStatus;
+
+    let mut code = [0u8; 4];
+    let mut filled = 0;
+    unsafe {
+        libc::waitpid(intermediate, std::ptr::null_mut(), 0);
+        while filled < code.len() {
+            let read = libc::read(read_end, code[filled..].as_mut_ptr().cast(), code.len() - filled);
+            match read {
+                n if n > 0 => filled += n as usize,
+                -1 if std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted => {}
+                _ => return ExitStatus::InternalError.code(),
+            }
+        }
+    }
+    i32::from_ne_bytes(code)
+}
+
+/// Hand the exit code of the first check (or of an early exit) to the
+/// loader's child waiting since `daemonize`; later calls do nothing
+pub fn report_status(code: i32) {
+    #[cfg(unix)]
+    {
+        let fd = STATUS_PIPE.swap(-1, std::sync::atomic::Ordering::SeqCst);
+        if fd >= 0 {
+            unsafe {
+                libc::write(fd, code.to_ne_bytes().as_ptr().cast(), 4);
+                libc::close(fd);
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = code;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lineage_tracks_parent() {
+        let Some(mut lineage) = Lineage::record() else {
+            return;
+        };
+        assert_eq!(lineage.check(), Ok(ParentState::Present));
+        assert_eq!(original_parent(), Some(lineage.parent));
+
+        // Reparenting while the recorded parent runs is tamper
+        lineage.expected_ppid = u32::MAX;
+        assert!(lineage.check().unwrap_err().contains("reparented"));
+        lineage.adopt_current_parent();
+
+        lineage.binary = Some(PathBuf::from("/nonexistent/launcher"));
+        assert!(lineage.check().unwrap_err().contains("instead of"));
+
+        // Same PID, started at another time: the parent exited, the PID was reused
+        lineage.binary = None;
+        lineage.started = lineage.started.map(|started| started + 1);
+        assert_eq!(lineage.check(), Ok(ParentState::Exited));
+
+        lineage.parent = u32::MAX;
+        assert_eq!(lineage.check(), Ok(ParentState::Exited));
+
+        assert_eq!(strip_deleted(PathBuf::from("/opt/app (deleted)")), PathBuf::from("/opt/app"));
+    }
+}
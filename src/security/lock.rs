@@ -0,0 +1,242 @@
+//! Reversible neutralization of a binary ("lock" kill method)
+//!
+//! Deleting or shredding a customer's binary is too much for a lapsed
+//! payment. Locking only takes away the right to execute it: `chmod 000` on
+//! Unix, a deny-execute ACE for Everyone on Windows. Optionally the binary is
+//! also renamed to `<name>.locked`, so shortcuts and scripts fail visibly.
+//!
+//! A small record next to the locked binary keeps the original permissions
+//! and name; `killer unlock <binary>` puts both back once the license is in
+//! order (without the record the usual 0755 is restored). The record lies
+//! next to the binary and may have been edited: only permission bits are
+//! restored (never setuid, setgid or sticky), and the binary is only renamed
+//! back within its own directory.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Extension appended by `lock_rename`
+pub const LOCKED_EXTENSION: &str = "locked";
+
+/// Extension of the lock record, appended to the locked binary's path
+pub const LOCK_RECORD_EXTENSION: &str = "kclock";
+
+/// Mode restored when the lock record is missing
+#[cfg(unix)]
+const DEFAULT_UNLOCKED_MODE: u32 = 0o755;
+
+/// What `unlock_binary` needs to undo a lock
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct LockRecord {
+    original_path: PathBuf,
+    /// Unix permission bits before the lock
+    #[serde(default)]
+    mode: Option<u32>,
+}
+
+/// Path of the lock record for locked binary `binary`
+pub fn record_path(binary: &Path) -> PathBuf {
+    with_extension(binary, LOCK_RECORD_EXTENSION)
+}
+
+/// Deny execution of `path`, renaming it to `<name>.locked` if `rename`
+///
+/// # Returns
+/// Path of the locked binary
+pub fn lock_binary(path: &Path, rename: bool) -> Result<PathBuf, String> {
+    let metadata = fs::metadata(path).map_err(|e| format!("Failed to stat {}: {}", path.display(), e))?;
+    #[cfg(unix)]
+    let mode = {
+        use std::os::unix::fs::PermissionsExt;
+        Some(metadata.permissions().mode() & 0o777)
+    };
+    #[cfg(not(unix))]
+    let mode = {
+        let _ = metadata;
+        None
+    };
+
+    let locked = if rename {
+        let locked = with_extension(path, LOCKED_EXTENSION);
+        fs::rename(path, &locked).map_err(|e| format!("Failed to rename {}: {}", path.display(), e))?;
+        locked
+    } else {
+        path.to_path_buf()
+    };
+
+    // Without the record the lock still holds, it is just undone with defaults
+    let record = LockRecord { original_path: path.to_path_buf(), mode };
+    if let Err(e) = serde_json::to_vec_pretty(&record)
+        .map_err(|e| e.to_string())
+        .and_then(|json| fs::write(record_path(&locked), json).map_err(|e| e.to_string()))
+    {
+        log_warn!("⚠️  Failed to write lock record: {}", e);
+    }
+
+    deny_execute(&locked)?;
+    Ok(locked)
+}
+
+/// Undo `lock_binary`
+///
+/// # Arguments
+/// * `path` - The locked binary, or its original path if it was renamed
+///
+/// # Returns
+/// Path of the executable binary
+pub fn unlock_binary(path: &Path) -> Result<PathBuf, String> {
+    let locked = [path.to_path_buf(), with_extension(path, LOCKED_EXTENSION)]
+        .into_iter()
+        .find(|candidate| record_path(candidate).exists())
+        .unwrap_or_else(|| path.to_path_buf());
+    let record_file = record_path(&locked);
+    let record = match fs::read(&record_file) {
+        Ok(json) => Some(
+            serde_json::from_slice::<LockRecord>(&json)
+                .map_err(|e| format!("Invalid lock record {}: {}", record_file.display(), e))?,
+        ),
+        Err(_) => None,
+    };
+    if !locked.exists() {
+        return Err(format!("{} not found", locked.display()));
+    }
+    if let Some(record) = &record
+        && (record.original_path.parent() != locked.parent() || record.original_path.file_name().is_none())
+    {
+        return Err(format!(
+            "Lock record {} names {}, outside the locked binary's directory",
+            record_file.display(),
+            record.original_path.display()
+        ));
+    }
+
+    allow_execute(&locked, record.as_ref().and_then(|record| record.mode))?;
+
+    let original = match &record {
+        Some(record) => record.original_path.clone(),
+        None if locked.extension().is_some_and(|ext| ext == LOCKED_EXTENSION) => locked.with_extension(""),
+        None => locked.clone(),
+    };
+    if original != locked {
+        if original.exists() {
+            return Err(format!("{} already exists - not renaming {} back", original.display(), locked.display()));
+        }
+        fs::rename(&locked, &original).map_err(|e| format!("Failed to rename {}: {}", locked.display(), e))?;
+    }
+    if record.is_some() {
+        let _ = fs::remove_file(&record_file);
+    }
+    Ok(original)
+}
+
+#[cfg(unix)]
+fn deny_execute(path: &Path) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(0o000))
+        .map_err(|e| format!("Failed to remove permissions of {}: {}", path.display(), e))
+}
+
+#[cfg(unix)]
+fn allow_execute(path: &Path, mode: Option<u32>) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = mode.unwrap_or(DEFAULT_UNLOCKED_MODE) & 0o777;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+        .map_err(|e| format!("Failed to restore permissions of {}: {}", path.display(), e))
+}
+
+// Everyone by SID: the group's name is localized
+#[cfg(windows)]
+fn deny_execute(path: &Path) -> Result<(), String> {
+    icacls(path, &["/deny", "*S-1-1-0:(X)"])
+}
+
+#[cfg(windows)]
+fn allow_execute(path: &Path, _mode: Option<u32>) -> Result<(), String> {
+    icacls(path, &["/remove:d", "*S-1-1-0"])
+}
+
+#[cfg(windows)]
+fn icacls(path: &Path, args: &[&str]) -> Result<(), String> {
+    let output = std::process::Command::new("icacls")
+        .arg(path)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run icacls: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "icacls failed on {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stdout).trim()
+        ));
+    }
+    Ok(())
+}
+
+fn with_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_lock_and_unlock() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("app");
+        fs::write(&binary, b"\x7fELF").unwrap();
+        fs::set_permissions(&binary, fs::Permissions::from_mode(0o750)).unwrap();
+
+        let locked = lock_binary(&binary, true).unwrap();
+        assert_eq!(locked, dir.path().join("app.locked"));
+        assert!(!binary.exists());
+        assert_eq!(fs::metadata(&locked).unwrap().permissions().mode() & 0o7777, 0);
+
+        // Unlocking by the original name finds the renamed binary
+        assert_eq!(unlock_binary(&binary).unwrap(), binary);
+        assert_eq!(fs::metadata(&binary).unwrap().permissions().mode() & 0o7777, 0o750);
+        assert!(!record_path(&locked).exists());
+    }
+
+    #[test]
+    fn test_unlock_without_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let locked = dir.path().join("app.locked");
+        fs::write(&locked, b"\x7fELF").unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+
+        let unlocked = unlock_binary(&locked).unwrap();
+        assert_eq!(unlocked, dir.path().join("app"));
+        assert_eq!(fs::metadata(&unlocked).unwrap().permissions().mode() & 0o7777, DEFAULT_UNLOCKED_MODE);
+    }
+
+    #[test]
+    fn test_unlock_distrusts_edited_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("app");
+        fs::write(&binary, b"\x7fELF").unwrap();
+        let locked = lock_binary(&binary, true).unwrap();
+
+        let record = |original_path: PathBuf, mode: u32| {
+            let json = serde_json::to_vec(&LockRecord { original_path, mode: Some(mode) }).unwrap();
+            fs::write(record_path(&locked), json).unwrap();
+        };
+
+        // Renaming out of the directory is refused
+        record(dir.path().join("bin").join("app"), 0o755);
+        assert!(unlock_binary(&locked).unwrap_err().contains("outside"));
+        assert!(locked.exists());
+
+        // Only permission bits come back
+        record(binary.clone(), 0o4755);
+        assert_eq!(unlock_binary(&locked).unwrap(), binary);
+        assert_eq!(fs::metadata(&binary).unwrap().permissions().mode() & 0o7777, 0o755);
+    }
+}
@@ -0,0 +1,189 @@
+//! Running from memory
+//!
+//! With `run_from_memory` set, the overload copies its image into an
+//! anonymous, sealed `memfd` and re-executes itself from there with
+//! `fexecve` (same PID, arguments and environment). The re-executed process
+//! then securely deletes the executable it was started from: enforcement
+//! continues from memory, with no file left on disk to patch, replace or
+//! analyze.
+//!
+//! The on-disk path travels in `FROM_MEMORY_ENV`; the `.config` file next to
+//! it is still read from there, and helpers we spawn run our memory image
+//! through `/proc/self/exe`. The file is only deleted when it still holds
+//! exactly the image we run, so a stray variable cannot make us shred
+//! anything else.
+//!
+//! Linux only. Elsewhere, and where the kernel refuses (no `memfd_create`,
+//! `vm.memfd_noexec`), the overload keeps running from disk.
+
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::config::schema::ShredPattern;
+use crate::config::Config;
+use super::destruct::{secure_delete_file, WipePlan};
+
+/// Env var naming the on-disk executable of an overload re-executed from
+/// memory
+pub const FROM_MEMORY_ENV: &str = "KILLCODE_FROM_MEMORY";
+
+/// On-disk executable, if we run from memory
+static DISK_PATH: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// Executable the overload was started from (also after it was deleted)
+pub fn executable_path() -> std::io::Result<PathBuf> {
+    match disk_path() {
+        Some(path) => Ok(path),
+        None => std::env::current_exe(),
+    }
+}
+
+/// Path of our running image, to read it or spawn copies of ourselves
+pub fn image_path() -> std::io::Result<PathBuf> {
+    if disk_path().is_some() {
+        return Ok(PathBuf::from("/proc/self/exe"));
+    }
+    std::env::current_exe()
+}
+
+/// Whether this process runs from a memfd image
+pub fn from_memory() -> bool {
+    disk_path().is_some()
+}
+
+fn disk_path() -> Option<PathBuf> {
+    DISK_PATH
+        .get_or_init(|| {
+            let path = std::env::var_os(FROM_MEMORY_ENV)?;
+            let image = std::fs::read_link("/proc/self/exe").ok()?;
+            image.to_string_lossy().starts_with("/memfd:").then(|| PathBuf::from(path))
+        })
+        .clone()
+}
+
+/// Move to memory if configured: re-execute from a memfd (does not return
+/// on success), or, once re-executed, delete the on-disk executable
+pub fn run_from_memory(config: &Config) {
+    if from_memory() {
+        remove_disk_copy(config);
+        return;
+    }
+    if !config.run_from_memory {
+        return;
+    }
+    if config.cli_mode {
+        log_info!("ℹ️  run_from_memory: not used in CLI mode");
+        return;
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let error = relaunch();
+        log_warn!("⚠️  Cannot run from memory, continuing from disk: {}", error);
+    }
+    #[cfg(not(target_os = "linux"))]
+    log_info!("ℹ️  run_from_memory: not supported on this platform, running from disk");
+}
+
+/// Securely delete the executable we were started from
+fn remove_disk_copy(config: &Config) {
+    // Gone already: a self-update re-executed us from memory
+    let Some(path) = disk_path().filter(|path| path.exists()) else {
+        return;
+    };
+    let same_image = match (std::fs::read(&path), std::fs::read("/proc/self/exe")) {
+        (Ok(disk), Ok(memory)) => disk == memory,
+        _ => false,
+    };
+    if !same_image {
+        log_warn!("⚠️  {} no longer holds this overload - leaving it alone", path.display());
+        return;
+    }
+    log_info!("🧠 Running from memory - removing {}", path.display());
+    secure_delete_file(&path.to_string_lossy(), &WipePlan::from_config(config, ShredPattern::Random));
+    super::erase::trim_in_background(&path);
+}
+
+/// Re-execute our image from a sealed memfd
+///
+/// # Returns
+/// Why that failed (it does not return otherwise)
+#[cfg(target_os = "linux")]
+fn relaunch() -> String {
+    let disk = match std::env::current_exe() {
+        Ok(path) => path,
+        Err(e) => return format!("Failed to get executable path: {}", e),
+    };
+    let image = match std::fs::read("/proc/self/exe") {
+        Ok(image) => image,
+        Err(e) => return format!("Failed to read own image: {}", e),
+    };
+    log_info!("🧠 Re-executing from memory");
+    exec_image(&image, Some(disk.as_os_str()))
+}
+
+/// Execute `image` from a sealed memfd, replacing this process (same PID,
+/// arguments and environment)
+///
+/// # Arguments
+/// * `image` - Executable image
+/// * `disk_path` - On-disk executable to pass in `FROM_MEMORY_ENV` (None:
+///   the variable is removed)
+///
+/// # Returns
+/// Why that failed (it does not return otherwise)
+#[cfg(target_os = "linux")]
+pub fn exec_image(image: &[u8], disk_path: Option<&std::ffi::OsStr>) -> String {
+    use std::ffi::CString;
+    use std::io::Write;
+    use std::os::fd::{AsRawFd, FromRawFd};
+    use std::os::unix::ffi::OsStrExt;
+
+    let fd = unsafe { libc::memfd_create(c"overload".as_ptr(), libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING) };
+    if fd < 0 {
+        return format!("memfd_create failed: {}", std::io::Error::last_os_error());
+    }
+    let mut memfd = unsafe { std::fs::File::from_raw_fd(fd) };
+    if let Err(e) = memfd.write_all(image) {
+        return format!("Failed to write memfd: {}", e);
+    }
+    // Nothing may patch the image once it runs
+    let seals = libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_WRITE | libc::F_SEAL_SEAL;
+    if unsafe { libc::fcntl(memfd.as_raw_fd(), libc::F_ADD_SEALS, seals) } != 0 {
+        log_debug!("  memfd not sealed: {}", std::io::Error::last_os_error());
+    }
+
+    let to_cstring = |bytes: &[u8]| CString::new(bytes).ok();
+    let args: Option<Vec<CString>> = std::env::args_os().map(|arg| to_cstring(arg.as_bytes())).collect();
+    let env: Option<Vec<CString>> = std::env::vars_os()
+        .filter(|(name, _)| name != FROM_MEMORY_ENV)
+        .chain(disk_path.map(|path| (FROM_MEMORY_ENV.into(), path.to_os_string())))
+        .map(|(name, value)| {
+            let mut entry = name.as_bytes().to_vec();
+            entry.push(b'=');
+            entry.extend_from_slice(value.as_bytes());
+            to_cstring(&entry)
+        })
+        .collect();
+    let (Some(args), Some(env)) = (args, env) else {
+        return "argument or environment contains a NUL byte".to_string();
+    };
+    let argv: Vec<*const libc::c_char> = args.iter().map(|arg| arg.as_ptr()).chain(std::iter::once(std::ptr::null())).collect();
+    let envp: Vec<*const libc::c_char> = env.iter().map(|entry| entry.as_ptr()).chain(std::iter::once(std::ptr::null())).collect();
+
+    unsafe { libc::fexecve(memfd.as_raw_fd(), argv.as_ptr(), envp.as_ptr()) };
+    format!("fexecve failed: {}", std::io::Error::last_os_error())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_disk_without_memfd_image() {
+        // The test binary runs from a regular file: the variable is ignored
+        assert!(!from_memory());
+        assert_eq!(executable_path().unwrap(), std::env::current_exe().unwrap());
+        assert_eq!(image_path().unwrap(), std::env::current_exe().unwrap());
+    }
+}
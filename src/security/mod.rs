@@ -1,5 +1,25 @@
 /// Security module - Secure deletion and anti-tampering
 pub mod destruct;
+pub mod erase;
 pub mod kill_parent;
+pub mod antidebug;
+pub mod antitamper;
+pub mod integrity;
+pub mod clock;
+pub mod escrow;
+pub mod secrets;
+pub mod scheduler;
+pub mod capabilities;
+pub mod corrupt;
+pub mod lock;
+pub mod hook;
+pub mod identity;
+pub mod lineage;
+pub mod trust;
+pub mod policy;
+pub mod renewal;
+pub mod privileges;
+pub mod redirection;
+pub mod memexec;
 
-pub use destruct::{secure_delete_self, secure_delete_file};
+pub use destruct::{destroy_self, secure_delete_self, secure_delete_file, WipePlan};
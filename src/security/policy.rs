@@ -0,0 +1,553 @@
+//! OEM enforcement policy (`enforcement_policy`)
+//!
+//! A small, sandboxed expression language deciding whether an unauthorized
+//! result is enforced now, e.g.
+//!
+//! ```text
+//! weekday in ['mon', 'tue', 'wed', 'thu', 'fri'] && !(hostname ~ 'build-*')
+//! ```
+//!
+//! Variables: `weekday` ("mon".."sun"), `hour` (0-23), `hostname`,
+//! `platform`, `kill_method`, `reason` (server message). Time is UTC, or the
+//! fixed `enforcement_policy_utc_offset_minutes` - never the machine's
+//! timezone, which the user controls.
+//! Operators: `||`, `&&`, `!`, `==`, `!=`, `<`, `<=`, `>`, `>=`, `~` (glob
+//! with `*`/`?`), `in` (list membership); literals are strings, integers,
+//! `true`/`false` and `[...]` lists.
+//!
+//! Hard limits:
+//! - no loops, calls or assignments; size and nesting are capped, so
+//!   evaluation is bounded by the length of the expression
+//! - the policy can only defer enforcement of a signed denial the server did
+//!   not mark `enforcement_required`; it can never authorize or pick a kill
+//!   method
+//! - parse or evaluation errors fail closed (enforce)
+//! - denials are deferred for at most `MAX_DEFERRAL_SECS` in a row: the
+//!   start is persisted (sealed with the shared secret) and only an
+//!   authorized result resets it
+//!
+//! The evaluator is behind the `policy` cargo feature (on by default).
+
+use chrono::{DateTime, FixedOffset, Utc};
+
+use crate::config::Config;
+use crate::utils::state::{DeferralState, StateStore};
+use crate::utils::time;
+use crate::verification::{create_signature, verify_signature, VerifyResponse};
+
+/// Longest the policy can defer enforcement without an authorized result
+pub const MAX_DEFERRAL_SECS: i64 = 24 * 60 * 60;
+
+/// Whether the policy defers enforcement of this unauthorized response
+pub fn defers_enforcement(config: &Config, response: &VerifyResponse) -> bool {
+    if !defers_enforcement_at(config, response, &Utc::now().fixed_offset()) {
+        return false;
+    }
+
+    let now = time::unix_now();
+    let store = StateStore::for_license(&config.license_id);
+    let started = store.update(|state| {
+        let since = state
+            .deferral
+            .as_ref()
+            .filter(|deferral| verify_signature(&deferral_message(&config.license_id, deferral.since), &config.shared_secret, &deferral.signature))
+            .map_or(now, |deferral| deferral.since);
+        let signature = create_signature(&deferral_message(&config.license_id, since), &config.shared_secret);
+        state.deferral = Some(DeferralState { since, signature });
+        since
+    });
+    match started {
+        Ok(since) if deferral_exhausted(since, now) => {
+            log_warn!("⚠️  enforcement_policy deferred enforcement for {}h already - enforcing", MAX_DEFERRAL_SECS / 3600);
+            false
+        }
+        Ok(_) => true,
+        // An unrecorded deferral could never run out
+        Err(e) => {
+            log_warn!("⚠️  enforcement_policy deferral not recorded ({}) - enforcing", e);
+            false
+        }
+    }
+}
+
+/// Whether a deferral that started at `since` has run out at `now` (also
+/// when the clock was set back before its start)
+pub fn deferral_exhausted(since: i64, now: i64) -> bool {
+    !(since..since.saturating_add(MAX_DEFERRAL_SECS)).contains(&now)
+}
+
+fn deferral_message(license_id: &str, since: i64) -> String {
+    format!("deferral:{}:{}", license_id, since)
+}
+
+/// Same decision with `weekday`/`hour` taken from `now` (replays), without
+/// the deferral limit
+pub fn defers_enforcement_at(config: &Config, response: &VerifyResponse, now: &DateTime<FixedOffset>) -> bool {
+    let Some(source) = config.enforcement_policy.as_deref() else {
+        return false;
+    };
+    if !response.signature_valid || response.enforcement_required {
+        log_info!("🧩 enforcement_policy not applicable (server-mandated enforcement)");
+        return false;
+    }
+
+    #[cfg(feature = "policy")]
+    {
+        let context = PolicyContext::at(config, response, &policy_time(config, now));
+        match compile(source).and_then(|policy| policy.evaluate(&context)) {
+            Ok(enforce) => !enforce,
+            Err(e) => {
+                log_warn!("⚠️  enforcement_policy failed ({}) - enforcing", e);
+                false
+            }
+        }
+    }
+
+    #[cfg(not(feature = "policy"))]
+    {
+        let _ = (source, now, policy_time);
+        log_warn!("⚠️  enforcement_policy set but this build has no policy support - enforcing");
+        false
+    }
+}
+
+/// `now` at the configured policy offset
+fn policy_time(config: &Config, now: &DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+    let offset = FixedOffset::east_opt(config.enforcement_policy_utc_offset_minutes * 60)
+        .unwrap_or_else(|| FixedOffset::east_opt(0).expect("UTC is a valid offset"));
+    now.with_timezone(&offset)
+}
+
+#[cfg(feature = "policy")]
+pub use lang::{compile, Policy, PolicyContext};
+
+#[cfg(feature = "policy")]
+mod lang {
+    use chrono::{DateTime, Datelike, FixedOffset, Timelike};
+
+    use crate::config::Config;
+    use crate::verification::VerifyResponse;
+
+    /// Longest accepted policy source
+    const MAX_SOURCE_LEN: usize = 1024;
+    /// Deepest accepted nesting of sub-expressions
+    const MAX_DEPTH: usize = 16;
+    /// Largest accepted number of expression nodes
+    const MAX_NODES: usize = 128;
+
+    const VARIABLES: &[&str] = &["weekday", "hour", "hostname", "platform", "kill_method", "reason"];
+
+    /// Values the policy is evaluated against
+    #[derive(Debug, Clone)]
+    pub struct PolicyContext {
+        pub weekday: String,
+        pub hour: i64,
+        pub hostname: String,
+        pub platform: String,
+        pub kill_method: String,
+        pub reason: String,
+    }
+
+    impl PolicyContext {
+        /// Context at a given time (in the policy's offset)
+        pub fn at(config: &Config, response: &VerifyResponse, now: &DateTime<FixedOffset>) -> Self {
+            Self {
+                weekday: now.weekday().to_string().to_ascii_lowercase(),
+                hour: now.hour() as i64,
+                hostname: hostname::get().map(|h| h.to_string_lossy().into_owned()).unwrap_or_default(),
+                platform: std::env::consts::OS.to_string(),
+                kill_method: config.kill_method.as_str().to_string(),
+                reason: response.message.clone(),
+            }
+        }
+
+        fn get(&self, name: &str) -> Value {
+            match name {
+                "weekday" => Value::Str(self.weekday.clone()),
+                "hour" => Value::Int(self.hour),
+                "hostname" => Value::Str(self.hostname.clone()),
+                "platform" => Value::Str(self.platform.clone()),
+                "kill_method" => Value::Str(self.kill_method.clone()),
+                _ => Value::Str(self.reason.clone()),
+            }
+        }
+    }
+
+    /// Compiled policy expression
+    #[derive(Debug)]
+    pub struct Policy {
+        root: Expr,
+    }
+
+    impl Policy {
+        /// true = enforce now, false = defer
+        pub fn evaluate(&self, context: &PolicyContext) -> Result<bool, String> {
+            match eval(&self.root, context)? {
+                Value::Bool(enforce) => Ok(enforce),
+                other => Err(format!("policy must be a boolean, got {}", other.kind())),
+            }
+        }
+    }
+
+    /// Parse and check a policy expression
+    pub fn compile(source: &str) -> Result<Policy, String> {
+        if source.len() > MAX_SOURCE_LEN {
+            return Err(format!("policy longer than {} bytes", MAX_SOURCE_LEN));
+        }
+        let mut parser = Parser { tokens: tokenize(source)?, pos: 0, depth: 0, nodes: 0 };
+        let root = parser.expr()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(format!("unexpected {:?}", token));
+        }
+        Ok(Policy { root })
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Value {
+        Bool(bool),
+        Int(i64),
+        Str(String),
+        List(Vec<Value>),
+    }
+
+    impl Value {
+        fn kind(&self) -> &'static str {
+            match self {
+                Value::Bool(_) => "boolean",
+                Value::Int(_) => "integer",
+                Value::Str(_) => "string",
+                Value::List(_) => "list",
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Op {
+        Eq,
+        Ne,
+        Lt,
+        Le,
+        Gt,
+        Ge,
+        Glob,
+        In,
+    }
+
+    #[derive(Debug)]
+    enum Expr {
+        Lit(Value),
+        Var(String),
+        List(Vec<Expr>),
+        Not(Box<Expr>),
+        And(Box<Expr>, Box<Expr>),
+        Or(Box<Expr>, Box<Expr>),
+        Cmp(Op, Box<Expr>, Box<Expr>),
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Token {
+        Ident(String),
+        Str(String),
+        Int(i64),
+        Op(Op),
+        And,
+        Or,
+        Not,
+        Open,
+        Close,
+        OpenList,
+        CloseList,
+        Comma,
+    }
+
+    fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+        let chars: Vec<char> = source.chars().collect();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            let next = chars.get(i + 1).copied();
+            let (token, len) = match c {
+                c if c.is_whitespace() => {
+                    i += 1;
+                    continue;
+                }
+                '(' => (Token::Open, 1),
+                ')' => (Token::Close, 1),
+                '[' => (Token::OpenList, 1),
+                ']' => (Token::CloseList, 1),
+                ',' => (Token::Comma, 1),
+                '~' => (Token::Op(Op::Glob), 1),
+                '&' if next == Some('&') => (Token::And, 2),
+                '|' if next == Some('|') => (Token::Or, 2),
+                '=' if next == Some('=') => (Token::Op(Op::Eq), 2),
+                '!' if next == Some('=') => (Token::Op(Op::Ne), 2),
+                '!' => (Token::Not, 1),
+                '<' if next == Some('=') => (Token::Op(Op::Le), 2),
+                '<' => (Token::Op(Op::Lt), 1),
+                '>' if next == Some('=') => (Token::Op(Op::Ge), 2),
+                '>' => (Token::Op(Op::Gt), 1),
+                '\'' | '"' => {
+                    let end = chars[i + 1..]
+                        .iter()
+                        .position(|&ch| ch == c)
+                        .ok_or("unterminated string")?;
+                    let text: String = chars[i + 1..i + 1 + end].iter().collect();
+                    (Token::Str(text), end + 2)
+                }
+                c if c.is_ascii_digit() => {
+                    let len = chars[i..].iter().take_while(|ch| ch.is_ascii_digit()).count();
+                    let text: String = chars[i..i + len].iter().collect();
+                    (Token::Int(text.parse().map_err(|_| "integer out of range")?), len)
+                }
+                c if c.is_ascii_alphabetic() || c == '_' => {
+                    let len = chars[i..]
+                        .iter()
+                        .take_while(|ch| ch.is_ascii_alphanumeric() || **ch == '_')
+                        .count();
+                    let word: String = chars[i..i + len].iter().collect();
+                    let token = match word.as_str() {
+                        "in" => Token::Op(Op::In),
+                        _ => Token::Ident(word),
+                    };
+                    (token, len)
+                }
+                other => return Err(format!("unexpected character '{}'", other)),
+            };
+            tokens.push(token);
+            i += len;
+        }
+        Ok(tokens)
+    }
+
+    struct Parser {
+        tokens: Vec<Token>,
+        pos: usize,
+        depth: usize,
+        nodes: usize,
+    }
+
+    impl Parser {
+        fn peek(&self) -> Option<&Token> {
+            self.tokens.get(self.pos)
+        }
+
+        fn eat(&mut self, token: &Token) -> bool {
+            if self.peek() == Some(token) {
+                self.pos += 1;
+                true
+            } else {
+                false
+            }
+        }
+
+        fn node(&mut self, expr: Expr) -> Result<Expr, String> {
+            self.nodes += 1;
+            if self.nodes > MAX_NODES {
+                return Err(format!("policy has more than {} terms", MAX_NODES));
+            }
+            Ok(expr)
+        }
+
+        fn expr(&mut self) -> Result<Expr, String> {
+            self.depth += 1;
+            if self.depth > MAX_DEPTH {
+                return Err(format!("policy nested deeper than {}", MAX_DEPTH));
+            }
+            let mut left = self.and()?;
+            while self.eat(&Token::Or) {
+                let right = self.and()?;
+                left = self.node(Expr::Or(Box::new(left), Box::new(right)))?;
+            }
+            self.depth -= 1;
+            Ok(left)
+        }
+
+        fn and(&mut self) -> Result<Expr, String> {
+            let mut left = self.not()?;
+            while self.eat(&Token::And) {
+                let right = self.not()?;
+                left = self.node(Expr::And(Box::new(left), Box::new(right)))?;
+            }
+            Ok(left)
+        }
+
+        fn not(&mut self) -> Result<Expr, String> {
+            if self.eat(&Token::Not) {
+                let inner = self.not()?;
+                return self.node(Expr::Not(Box::new(inner)));
+            }
+            self.comparison()
+        }
+
+        fn comparison(&mut self) -> Result<Expr, String> {
+            let left = self.primary()?;
+            if let Some(Token::Op(op)) = self.peek().cloned() {
+                self.pos += 1;
+                let right = self.primary()?;
+                return self.node(Expr::Cmp(op, Box::new(left), Box::new(right)));
+            }
+            Ok(left)
+        }
+
+        fn primary(&mut self) -> Result<Expr, String> {
+            let token = self.peek().cloned().ok_or("unexpected end of policy")?;
+            self.pos += 1;
+            let expr = match token {
+                Token::Str(s) => Expr::Lit(Value::Str(s)),
+                Token::Int(n) => Expr::Lit(Value::Int(n)),
+                Token::Ident(name) => match name.as_str() {
+                    "true" => Expr::Lit(Value::Bool(true)),
+                    "false" => Expr::Lit(Value::Bool(false)),
+                    _ if VARIABLES.contains(&name.as_str()) => Expr::Var(name),
+                    _ => return Err(format!("unknown variable '{}'", name)),
+                },
+                Token::Open => {
+                    let inner = self.expr()?;
+                    if !self.eat(&Token::Close) {
+                        return Err("missing ')'".to_string());
+                    }
+                    return Ok(inner);
+                }
+                Token::OpenList => {
+                    let mut items = Vec::new();
+                    if !self.eat(&Token::CloseList) {
+                        loop {
+                            items.push(self.primary()?);
+                            if self.eat(&Token::CloseList) {
+                                break;
+                            }
+                            if !self.eat(&Token::Comma) {
+                                return Err("expected ',' or ']'".to_string());
+                            }
+                        }
+                    }
+                    Expr::List(items)
+                }
+                other => return Err(format!("unexpected {:?}", other)),
+            };
+            self.node(expr)
+        }
+    }
+
+    fn eval(expr: &Expr, context: &PolicyContext) -> Result<Value, String> {
+        Ok(match expr {
+            Expr::Lit(value) => value.clone(),
+            Expr::Var(name) => context.get(name),
+            Expr::List(items) => Value::List(items.iter().map(|item| eval(item, context)).collect::<Result<_, _>>()?),
+            Expr::Not(inner) => Value::Bool(!boolean(eval(inner, context)?)?),
+            Expr::And(left, right) => Value::Bool(boolean(eval(left, context)?)? && boolean(eval(right, context)?)?),
+            Expr::Or(left, right) => Value::Bool(boolean(eval(left, context)?)? || boolean(eval(right, context)?)?),
+            Expr::Cmp(op, left, right) => Value::Bool(compare(*op, eval(left, context)?, eval(right, context)?)?),
+        })
+    }
+
+    fn boolean(value: Value) -> Result<bool, String> {
+        match value {
+            Value::Bool(b) => Ok(b),
+            other => Err(format!("expected a boolean, got {}", other.kind())),
+        }
+    }
+
+    fn compare(op: Op, left: Value, right: Value) -> Result<bool, String> {
+        match (op, left, right) {
+            (Op::Eq, l, r) => Ok(l == r),
+            (Op::Ne, l, r) => Ok(l != r),
+            (Op::In, l, Value::List(items)) => Ok(items.contains(&l)),
+            (Op::Glob, Value::Str(s), Value::Str(pattern)) => Ok(glob_match(&pattern, &s)),
+            (op, Value::Int(l), Value::Int(r)) => Ok(match op {
+                Op::Lt => l < r,
+                Op::Le => l <= r,
+                Op::Gt => l > r,
+                _ => l >= r,
+            }),
+            (op, l, r) => Err(format!("cannot apply {:?} to {} and {}", op, l.kind(), r.kind())),
+        }
+    }
+
+    /// Case-insensitive glob match (`*` any run, `?` any character)
+    fn glob_match(pattern: &str, text: &str) -> bool {
+        let p: Vec<char> = pattern.to_lowercase().chars().collect();
+        let t: Vec<char> = text.to_lowercase().chars().collect();
+        let (mut pi, mut ti) = (0, 0);
+        let mut backtrack: Option<(usize, usize)> = None;
+
+        while ti < t.len() {
+            if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+                pi += 1;
+                ti += 1;
+            } else if pi < p.len() && p[pi] == '*' {
+                backtrack = Some((pi, ti));
+                pi += 1;
+            } else if let Some((star, matched)) = backtrack {
+                pi = star + 1;
+                ti = matched + 1;
+                backtrack = Some((star, matched + 1));
+            } else {
+                return false;
+            }
+        }
+        p[pi..].iter().all(|&c| c == '*')
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn context() -> PolicyContext {
+            PolicyContext {
+                weekday: "sat".to_string(),
+                hour: 14,
+                hostname: "BUILD-agent-07".to_string(),
+                platform: "linux".to_string(),
+                kill_method: "stop".to_string(),
+                reason: "License expired".to_string(),
+            }
+        }
+
+        fn run(source: &str) -> Result<bool, String> {
+            compile(source)?.evaluate(&context())
+        }
+
+        #[test]
+        fn test_policy_evaluation() {
+            assert_eq!(run("weekday in ['mon', 'tue', 'wed', 'thu', 'fri']"), Ok(false));
+            assert_eq!(run("!(hostname ~ 'build-*') || hour >= 9 && hour < 17"), Ok(true));
+            assert_eq!(run("hostname ~ 'build-agent-??' && platform == \"linux\""), Ok(true));
+            assert_eq!(run("kill_method != 'stop'"), Ok(false));
+        }
+
+        #[test]
+        fn test_policy_time_and_deferral_limit() {
+            use crate::security::policy::{defers_enforcement_at, deferral_exhausted, MAX_DEFERRAL_SECS};
+
+            let mut config: Config = serde_json::from_str(
+                r#"{"license_id": "lic", "server_url": "https://a.example", "shared_secret": "s", "enforcement_policy": "hour < 12"}"#,
+            )
+            .unwrap();
+            let response = VerifyResponse { signature_valid: true, ..Default::default() };
+            // The machine says 13:00 (+02:00): 11:00 UTC, enforced
+            let now = DateTime::parse_from_rfc3339("2026-03-02T13:00:00+02:00").unwrap();
+            assert!(!defers_enforcement_at(&config, &response, &now));
+            config.enforcement_policy_utc_offset_minutes = 120;
+            assert!(defers_enforcement_at(&config, &response, &now));
+
+            assert!(!deferral_exhausted(1_000, 1_000 + MAX_DEFERRAL_SECS - 1));
+            assert!(deferral_exhausted(1_000, 1_000 + MAX_DEFERRAL_SECS));
+            assert!(deferral_exhausted(1_000, 999));
+        }
+
+        #[test]
+        fn test_policy_limits_and_errors() {
+            assert!(compile("secret == 'x'").unwrap_err().contains("unknown variable"));
+            assert!(compile("hour >").is_err());
+            assert!(compile(&"(".repeat(40)).unwrap_err().contains("nested"));
+            assert!(compile(&vec!["true"; 100].join("&&")).unwrap_err().contains("terms"));
+            assert!(compile(&"x".repeat(2000)).is_err());
+            // Type errors surface at evaluation and fail closed upstream
+            assert!(run("hour").is_err());
+            assert!(run("hostname < 3").is_err());
+        }
+    }
+}
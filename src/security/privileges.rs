@@ -0,0 +1,273 @@
+//! Dropping root privileges after initialization
+//!
+//! When the protected service runs as root, killer does too - and spends its
+//! life parsing server-controlled JSON. With `run_as_user` set, everything
+//! that needs privileges happens first (config, log file, health block,
+//! control channel, capability probe); then the process switches to that
+//! user for good.
+//!
+//! On Unix the kill itself still needs root: signal and unlink permissions
+//! are checked at the time of the call, so no file handle opened early can
+//! carry them. Before dropping, killer therefore starts a copy of itself as a
+//! kill broker that stays root and keeps the other end of a pipe. The broker
+//! only ever acts on the parent fixed at spawn time, identified by PID and
+//! start time so a reused PID is never killed, and accepts a single kill
+//! method name; if the pipe closes without one it exits quietly.
+//!
+//! On Windows the user does not change: every privilege of the process token
+//! except traversal (SeChangeNotifyPrivilege) is removed for good, so an
+//! elevated install cannot debug, back up or take ownership on behalf of a
+//! compromised check. Handles opened earlier stay valid.
+//!
+//! The state directory and a `.config` read by `rotate_config` must be
+//! accessible to the target user afterwards.
+
+use crate::config::{Config, KillMethod};
+
+/// Env var marking the kill broker (value: `<pid>:<start time>` of the
+/// protected parent)
+pub const KILL_BROKER_ENV: &str = "KILLCODE_KILL_BROKER";
+
+#[cfg(unix)]
+static BROKER: std::sync::Mutex<Option<std::process::Child>> = std::sync::Mutex::new(None);
+
+/// Drop privileges as configured by `run_as_user` (no-op when unset)
+pub fn drop_privileges(config: &Config) {
+    let Some(user) = config.run_as_user.as_deref() else {
+        return;
+    };
+    match drop_to(user) {
+        Ok(true) => log_info!("🔻 Privileges dropped ({})", user),
+        Ok(false) => log_debug!("🔻 Not running privileged - nothing to drop"),
+        Err(e) => {
+            // Still enforcing beats a hardened process that can no longer kill
+            log_error!("❌ Failed to drop privileges, continuing privileged: {}", e);
+            crate::utils::audit::record("privilege_drop_failed", &e);
+        }
+    }
+}
+
+/// Hand the kill to the broker, if privileges were dropped
+///
+/// None when there is no broker (the caller kills directly).
+pub fn delegate_kill(kill_method: &KillMethod) -> Option<Result<(), String>> {
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+
+        let mut broker = BROKER.lock().unwrap_or_else(|e| e.into_inner()).take()?;
+        log_info!("📨 Handing {} kill to the privileged broker (PID {})", kill_method.as_str(), broker.id());
+        let result = broker
+            .stdin
+            .take()
+            .ok_or_else(|| "broker pipe already closed".to_string())
+            .and_then(|mut pipe| {
+                writeln!(pipe, "{}", kill_method.as_str()).map_err(|e| format!("broker pipe: {}", e))
+            })
+            .and_then(|_| broker.wait().map_err(|e| format!("broker: {}", e)))
+            .and_then(|status| match status.success() {
+                true => Ok(()),
+                false => Err(format!("broker failed ({})", status)),
+            });
+        Some(result)
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = kill_method;
+        None
+    }
+}
+
+/// Kill broker main loop: wait for the kill method, kill `target`, exit
+#[cfg(unix)]
+pub fn run_broker(config: &Config, target: &str) -> ! {
+    use std::io::{BufRead, Read};
+
+    let (pid, started) = match parse_target(target) {
+        Ok(target) => target,
+        Err(e) => {
+            log_error!("❌ Kill broker: {}", e);
+            crate::utils::shutdown::exit(2);
+        }
+    };
+
+    let mut line = String::new();
+    let read = std::io::stdin().lock().take(64).read_line(&mut line);
+    if matches!(read, Ok(0) | Err(_)) {
+        // The checker exited without enforcing
+        crate::utils::shutdown::exit(0);
+    }
+    let kill_method = match parse_request(&line) {
+        Ok(kill_method) => kill_method,
+        Err(e) => {
+            log_error!("❌ Kill broker: {}", e);
+            crate::utils::shutdown::exit(2);
+        }
+    };
+    // The parent may have exited while we waited, and its PID been reused
+    if crate::utils::process::start_time(pid) != Some(started) {
+        log_error!("❌ Kill broker: PID {} is no longer the protected process", pid);
+        crate::utils::shutdown::exit(2);
+    }
+    super::kill_parent::execute_kill_target(&kill_method, config, pid);
+    crate::utils::shutdown::exit(0);
+}
+
+/// PID and start time of the broker's target
+///
+/// PIDs 0 and 1 are refused: as root, a kill on 0 hits our whole process
+/// group, and 1 is init.
+#[cfg(unix)]
+fn parse_target(target: &str) -> Result<(u32, u64), String> {
+    let (pid, started) = target.split_once(':').ok_or_else(|| format!("invalid target {:?}", target))?;
+    let pid: u32 = pid.parse().map_err(|_| format!("invalid target PID {:?}", pid))?;
+    let started: u64 = started.parse().map_err(|_| format!("invalid target start time {:?}", started))?;
+    if pid <= 1 {
+        return Err(format!("refusing target PID {}", pid));
+    }
+    Ok((pid, started))
+}
+
+/// Kill method named in a broker request, downgraded to what works here
+fn parse_request(line: &str) -> Result<KillMethod, String> {
+    let requested = KillMethod::from_str(line.trim()).ok_or_else(|| format!("invalid request {:?}", line.trim()))?;
+    Ok(super::capabilities::supported_kill_method(&requested))
+}
+
+/// Ok(false) when there were no privileges to drop
+#[cfg(unix)]
+fn drop_to(user: &str) -> Result<bool, String> {
+    if unsafe { libc::geteuid() } != 0 {
+        return Ok(false);
+    }
+    let (uid, gid) = lookup_user(user)?;
+    if uid == 0 {
+        return Err(format!("{} is root", user));
+    }
+
+    spawn_broker()?;
+    unsafe {
+        if libc::setgroups(1, &gid) != 0 || libc::setgid(gid) != 0 || libc::setuid(uid) != 0 {
+            return Err(format!("switching to uid {}: {}", uid, std::io::Error::last_os_error()));
+        }
+        // Only a real drop counts: regaining root must fail
+        if libc::setuid(0) == 0 {
+            std::process::abort();
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(windows)]
+fn drop_to(_user: &str) -> Result<bool, String> {
+    use std::ptr;
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::{GetCurrentProcess, OpenProcessToken};
+    use winapi::um::securitybaseapi::{AdjustTokenPrivileges, GetTokenInformation};
+    use winapi::um::winbase::LookupPrivilegeValueW;
+    use winapi::um::winnt::{
+        TokenPrivileges, HANDLE, LUID, LUID_AND_ATTRIBUTES, SE_PRIVILEGE_REMOVED, TOKEN_ADJUST_PRIVILEGES,
+        TOKEN_PRIVILEGES, TOKEN_QUERY,
+    };
+
+    let os_error = |what: &str| format!("{}: {}", what, std::io::Error::last_os_error());
+    unsafe {
+        let mut token: HANDLE = ptr::null_mut();
+        if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY | TOKEN_ADJUST_PRIVILEGES, &mut token) == 0 {
+            return Err(os_error("OpenProcessToken"));
+        }
+
+        let mut len = 0;
+        GetTokenInformation(token, TokenPrivileges, ptr::null_mut(), 0, &mut len);
+        // u32 buffer: TOKEN_PRIVILEGES is 4-byte aligned
+        let mut buffer = vec![0u32; (len as usize).div_ceil(4)];
+        if GetTokenInformation(token, TokenPrivileges, buffer.as_mut_ptr().cast(), len, &mut len) == 0 {
+            let e = os_error("GetTokenInformation");
+            CloseHandle(token);
+            return Err(e);
+        }
+
+        let mut keep = LUID { LowPart: 0, HighPart: 0 };
+        let name: Vec<u16> = "SeChangeNotifyPrivilege\0".encode_utf16().collect();
+        LookupPrivilegeValueW(ptr::null(), name.as_ptr(), &mut keep);
+
+        let privileges = buffer.as_mut_ptr() as *mut TOKEN_PRIVILEGES;
+        let entries = std::slice::from_raw_parts_mut(
+            (*privileges).Privileges.as_mut_ptr() as *mut LUID_AND_ATTRIBUTES,
+            (*privileges).PrivilegeCount as usize,
+        );
+        for entry in entries.iter_mut() {
+            if entry.Luid.LowPart != keep.LowPart || entry.Luid.HighPart != keep.HighPart {
+                entry.Attributes = SE_PRIVILEGE_REMOVED;
+            }
+        }
+
+        let adjusted = AdjustTokenPrivileges(token, 0, privileges, 0, ptr::null_mut(), ptr::null_mut());
+        let result = if adjusted == 0 { Err(os_error("AdjustTokenPrivileges")) } else { Ok(true) };
+        CloseHandle(token);
+        result
+    }
+}
+
+/// uid and primary gid of `user`
+#[cfg(unix)]
+fn lookup_user(user: &str) -> Result<(libc::uid_t, libc::gid_t), String> {
+    let name = std::ffi::CString::new(user).map_err(|_| format!("Invalid user name: {:?}", user))?;
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut buffer = vec![0 as libc::c_char; 16 * 1024];
+    let mut found = std::ptr::null_mut();
+    let rc = unsafe { libc::getpwnam_r(name.as_ptr(), &mut passwd, buffer.as_mut_ptr(), buffer.len(), &mut found) };
+    if rc != 0 || found.is_null() {
+        return Err(format!("Unknown user: {}", user));
+    }
+    Ok((passwd.pw_uid, passwd.pw_gid))
+}
+
+/// Start the root kill broker for our parent
+#[cfg(unix)]
+fn spawn_broker() -> Result<(), String> {
+    use std::process::{Command, Stdio};
+
+    let parent = crate::utils::process::get_parent_pid()
+        .filter(|&pid| pid > 1)
+        .ok_or("Failed to get parent PID")?;
+    let started = crate::utils::process::start_time(parent).ok_or("Failed to get parent start time")?;
+    let child = super::memexec::image_path()
+        .and_then(|exe| {
+            Command::new(exe)
+                .env(KILL_BROKER_ENV, format!("{}:{}", parent, started))
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .spawn()
+        })
+        .map_err(|e| format!("Failed to start kill broker: {}", e))?;
+    log_debug!("📨 Kill broker started (PID {}) for parent {}", child.id(), parent);
+    *BROKER.lock().unwrap_or_else(|e| e.into_inner()) = Some(child);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_broker_request_and_user_lookup() {
+        assert_eq!(parse_request("stop\n"), Ok(KillMethod::Stop));
+        assert_eq!(parse_request("delete"), Ok(KillMethod::Delete));
+        assert!(parse_request("stop; rm -rf /").is_err());
+        assert!(parse_request("").is_err());
+
+        #[cfg(unix)]
+        {
+            assert_eq!(parse_target("1234:5678"), Ok((1234, 5678)));
+            assert!(parse_target("0:5678").is_err());
+            assert!(parse_target("1:5678").is_err());
+            assert!(parse_target("1234").is_err());
+            assert!(parse_target("x:1").is_err());
+
+            assert_eq!(lookup_user("root"), Ok((0, 0)));
+            assert!(lookup_user("no-such-user-kc").is_err());
+        }
+    }
+}
@@ -0,0 +1,264 @@
+//! License server redirection detection
+//!
+//! Pointing the license domain at a local fake server that answers
+//! `authorized: true` takes one line in the hosts file. With
+//! `detect_redirection` set, every verification is preceded by:
+//! 1. a hosts file scan (/etc/hosts, or `drivers\etc\hosts` on Windows) for
+//!    entries naming the server host
+//! 2. a comparison of the system resolver's answer with one obtained
+//!    independently, by a plain DNS query to `redirection_resolver`
+//!
+//! A resolver answer is only treated as tamper when the system resolves the
+//! server to a loopback, private or link-local address the independent
+//! resolver does not return; disjoint public answers are normal for CDNs and
+//! geo-DNS. No answer from the independent resolver (UDP 53 blocked) proves
+//! nothing and passes. Servers configured as an IP address or as a local name
+//! are not checked.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, ToSocketAddrs, UdpSocket};
+use std::time::Duration;
+
+use crate::config::Config;
+
+/// Resolver queried when `redirection_resolver` is unset
+pub const DEFAULT_RESOLVER: &str = "1.1.1.1:53";
+
+/// How long the independent resolver gets to answer
+const QUERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+
+/// Check the license server host for redirection
+///
+/// # Returns
+/// Err describing the redirection if one was found
+pub fn check(config: &Config) -> Result<(), String> {
+    let Some(host) = server_host(&config.get_server_url()) else {
+        return Ok(());
+    };
+
+    if let Some(address) = read_hosts_file().and_then(|hosts| hosts_override(&hosts, &host)) {
+        return Err(format!("hosts file maps {} to {}", host, address));
+    }
+
+    let system: Vec<IpAddr> = match (host.as_str(), 443).to_socket_addrs() {
+        Ok(addresses) => addresses.map(|address| address.ip()).collect(),
+        // Unresolvable is a network error for the verification to report
+        Err(_) => return Ok(()),
+    };
+    let resolver = config.redirection_resolver.as_deref().unwrap_or(DEFAULT_RESOLVER);
+    let independent = match resolve_independently(&host, resolver) {
+        Ok(addresses) => addresses,
+        Err(e) => {
+            log_debug!("  Independent resolution of {} unavailable: {}", host, e);
+            return Ok(());
+        }
+    };
+    match redirected_address(&system, &independent) {
+        Some(address) => Err(format!("system resolver maps {} to {}, {} does not", host, address, resolver)),
+        None => Ok(()),
+    }
+}
+
+/// Domain name of the server, unless it is an IP address or a local name
+fn server_host(server_url: &str) -> Option<String> {
+    let (_, rest) = server_url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host_port = authority.rsplit_once('@').map_or(authority, |(_, host_port)| host_port);
+    // Bracketed IPv6 literal
+    if host_port.starts_with('[') {
+        return None;
+    }
+    let host = host_port.split(':').next()?.trim_end_matches('.').to_ascii_lowercase();
+    if host.parse::<std::net::Ipv4Addr>().is_ok() {
+        return None;
+    }
+    (host.contains('.') && !host.ends_with(".localhost") && !host.ends_with(".local")).then_some(host)
+}
+
+fn read_hosts_file() -> Option<String> {
+    #[cfg(windows)]
+    let path = std::path::Path::new(&std::env::var("SystemRoot").unwrap_or_else(|_| "C:\\Windows".to_string()))
+        .join("System32\\drivers\\etc\\hosts");
+    #[cfg(not(windows))]
+    let path = std::path::Path::new("/etc/hosts");
+
+    std::fs::read_to_string(path).ok()
+}
+
+/// Address a hosts file assigns to `host`, if any
+pub fn hosts_override(hosts: &str, host: &str) -> Option<String> {
+    hosts.lines().find_map(|line| {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        let address = fields.next()?;
+        fields.any(|name| name.trim_end_matches('.').eq_ignore_ascii_case(host)).then(|| address.to_string())
+    })
+}
+
+/// A system answer pointing somewhere local that the independent resolver
+/// does not confirm
+fn redirected_address(system: &[IpAddr], independent: &[IpAddr]) -> Option<IpAddr> {
+    if independent.is_empty() {
+        return None;
+    }
+    system.iter().copied().find(|address| is_local(address) && !independent.contains(address))
+}
+
+fn is_local(address: &IpAddr) -> bool {
+    match address {
+        IpAddr::V4(v4) => v4.is_loopback() || v4.is_private() || v4.is_link_local() || v4.is_unspecified(),
+        IpAddr::V6(v6) => {
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_unique_local()
+                || v6.is_unicast_link_local()
+                || v6.to_ipv4_mapped().is_some_and(|v4| is_local(&IpAddr::V4(v4)))
+        }
+    }
+}
+
+/// A and AAAA records of `host` straight from `resolver`, bypassing the
+/// system resolver (and with it the hosts file and local DNS settings)
+fn resolve_independently(host: &str, resolver: &str) -> Result<Vec<IpAddr>, String> {
+    let socket = UdpSocket::bind(if resolver.starts_with('[') { "[::]:0" } else { "0.0.0.0:0" })
+        .map_err(|e| format!("cannot open a UDP socket: {}", e))?;
+    socket.set_read_timeout(Some(QUERY_TIMEOUT)).map_err(|e| e.to_string())?;
+    socket.connect(resolver).map_err(|e| format!("cannot reach {}: {}", resolver, e))?;
+
+    let mut addresses = Vec::new();
+    for qtype in [TYPE_A, TYPE_AAAA] {
+        let id = rand::random::<u16>();
+        socket.send(&build_query(id, host, qtype)?).map_err(|e| format!("query failed: {}", e))?;
+        let mut response = [0u8; 1500];
+        // Stray or spoofed datagrams carry another ID: keep reading
+        loop {
+            let len = socket.recv(&mut response).map_err(|e| format!("no answer: {}", e))?;
+            if let Some(answers) = parse_answers(&response[..len], id)? {
+                addresses.extend(answers);
+                break;
+            }
+        }
+    }
+    Ok(addresses)
+}
+
+/// Recursive query for one record type
+pub fn build_query(id: u16, host: &str, qtype: u16) -> Result<Vec<u8>, String> {
+    let mut query = Vec::with_capacity(18 + host.len());
+    query.extend_from_slice(&id.to_be_bytes());
+    // Flags: recursion desired; one question
+    query.extend_from_slice(&[0x01, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0]);
+    for label in host.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(format!("invalid host name {}", host));
+        }
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&qtype.to_be_bytes());
+    query.extend_from_slice(&1u16.to_be_bytes()); // class IN
+    Ok(query)
+}
+
+/// Addresses in the answer section of a response
+///
+/// # Returns
+/// None if the response belongs to another query, Err if it is malformed or
+/// reports an error
+pub fn parse_answers(response: &[u8], id: u16) -> Result<Option<Vec<IpAddr>>, String> {
+    let malformed = || "malformed DNS response".to_string();
+    if response.len() < 12 {
+        return Err(malformed());
+    }
+    if u16::from_be_bytes([response[0], response[1]]) != id || response[2] & 0x80 == 0 {
+        return Ok(None);
+    }
+    match response[3] & 0x0F {
+        0 => {}
+        // NXDOMAIN: nothing to compare with
+        3 => return Ok(Some(Vec::new())),
+        rcode => return Err(format!("resolver error (rcode {})", rcode)),
+    }
+    let questions = u16::from_be_bytes([response[4], response[5]]);
+    let answers = u16::from_be_bytes([response[6], response[7]]);
+
+    let mut offset = 12;
+    for _ in 0..questions {
+        offset = skip_name(response, offset).ok_or_else(malformed)? + 4;
+    }
+    let mut addresses = Vec::new();
+    for _ in 0..answers {
+        offset = skip_name(response, offset).ok_or_else(malformed)?;
+        let header = response.get(offset..offset + 10).ok_or_else(malformed)?;
+        let rtype = u16::from_be_bytes([header[0], header[1]]);
+        let len = u16::from_be_bytes([header[8], header[9]]) as usize;
+        let data = response.get(offset + 10..offset + 10 + len).ok_or_else(malformed)?;
+        match (rtype, len) {
+            (TYPE_A, 4) => addresses.push(IpAddr::V4(Ipv4Addr::new(data[0], data[1], data[2], data[3]))),
+            (TYPE_AAAA, 16) => {
+                let octets: [u8; 16] = data.try_into().map_err(|_| malformed())?;
+                addresses.push(IpAddr::V6(Ipv6Addr::from(octets)));
+            }
+            // CNAMEs and the like
+            _ => {}
+        }
+        offset += 10 + len;
+    }
+    Ok(Some(addresses))
+}
+
+/// Offset just past the (possibly compressed) name at `offset`
+fn skip_name(message: &[u8], mut offset: usize) -> Option<usize> {
+    loop {
+        let len = *message.get(offset)?;
+        match len {
+            0 => return Some(offset + 1),
+            // Compression pointer: the name ends here
+            len if len & 0xC0 == 0xC0 => return Some(offset + 2),
+            len => offset += 1 + len as usize,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hosts_override() {
+        let hosts = "127.0.0.1 localhost\n# 10.0.0.1 api.killcode.io\n::1 ip6-localhost\n127.0.0.1\tfoo API.killcode.io. # fake\n";
+        assert_eq!(hosts_override(hosts, "api.killcode.io"), Some("127.0.0.1".to_string()));
+        assert_eq!(hosts_override(hosts, "killcode.io"), None);
+
+        assert_eq!(server_host("https://API.killcode.io/v1"), Some("api.killcode.io".to_string()));
+        assert_eq!(server_host("http://127.0.0.1:18600"), None);
+        assert_eq!(server_host("http://localhost:8080"), None);
+        assert_eq!(server_host("https://user:pw@api.killcode.io.:8443?x=1"), Some("api.killcode.io".to_string()));
+        assert_eq!(server_host("https://[::1]:8443/"), None);
+    }
+
+    #[test]
+    fn test_dns_answers_and_redirection() {
+        let mut response = build_query(0x1234, "api.killcode.io", TYPE_A).unwrap();
+        response[2] = 0x81; // response, recursion desired
+        response[3] = 0x80; // recursion available, no error
+        response[7] = 2; // two answers
+        // CNAME to a name elsewhere in the message, then the A record
+        response.extend_from_slice(&[0xC0, 0x0C, 0, 5, 0, 1, 0, 0, 0, 60, 0, 2, 0xC0, 0x10]);
+        response.extend_from_slice(&[0xC0, 0x0C, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 203, 0, 113, 7]);
+
+        let answers = parse_answers(&response, 0x1234).unwrap().unwrap();
+        assert_eq!(answers, [IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7))]);
+        assert_eq!(parse_answers(&response, 0x4321).unwrap(), None);
+        assert!(parse_answers(&response[..response.len() - 2], 0x1234).is_err());
+
+        let loopback = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        assert_eq!(redirected_address(&[loopback], &answers), Some(loopback));
+        // CDNs answer differently per resolver; only local answers are suspect
+        assert_eq!(redirected_address(&[IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1))], &answers), None);
+        assert_eq!(redirected_address(&[loopback], &[]), None);
+    }
+}
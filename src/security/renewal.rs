@@ -0,0 +1,157 @@
+//! Expiry-aware renewal of the license lease
+//!
+//! An authorized response may say when the license lapses (`expires_at`, or
+//! `expires_in` relative to the response). Waiting a full check interval can
+//! run past that point, and a transient outage right then turns into a
+//! denial. The renewal scheduler moves the next verification ahead of the
+//! expiry by `renewal_lead_secs`, minus random jitter so a fleet issued
+//! leases together does not renew in lockstep. Leases shorter than twice the
+//! lead renew at half their remaining lifetime.
+//!
+//! Renewal only ever shortens the wait; it never delays a check. A lease that
+//! runs out without a successful renewal counts as an unauthorized result.
+
+use crate::utils::time;
+use crate::verification::VerifyResponse;
+
+/// Jitter is drawn from up to this fraction of the lead time
+const JITTER_FRACTION: f64 = 0.25;
+
+/// Shortest early renewal wait, so retries close to the expiry do not spin
+const MIN_WAIT_MS: u64 = 5_000;
+
+/// Tracks the current lease expiry and shortens waits ahead of it
+#[derive(Debug, Default)]
+pub struct RenewalScheduler {
+    lead_secs: u64,
+    /// Local unix time the current lease lapses (None = no expiry known)
+    expires_at: Option<i64>,
+}
+
+impl RenewalScheduler {
+    /// `lead_secs` = 0 disables early renewal
+    pub fn new(lead_secs: u64) -> Self {
+        Self { lead_secs, expires_at: None }
+    }
+
+    /// Take the lease expiry from an authorized response received at `now`
+    pub fn observe(&mut self, response: &VerifyResponse, now: i64) {
+        self.expires_at = lease_expiry(response, now);
+    }
+
+    /// Local unix time the current lease lapses
+    pub fn expires_at(&self) -> Option<i64> {
+        self.expires_at
+    }
+
+    /// Whether the lease ran out at `now` (never, if no expiry is known)
+    pub fn expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+
+    /// Whether the lease runs out within the lead time of `now`
+    pub fn expiring(&self, now: i64) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| now < expires_at && expires_at - now <= self.lead_secs as i64)
+    }
+
+    /// Wait before the next verification: `interval_ms`, shortened so the
+    /// renewal starts ahead of the lease expiry
+    pub fn next_wait_ms(&self, interval_ms: u64, now: i64) -> u64 {
+        self.wait_with_jitter(interval_ms, now, rand::random::<f64>())
+    }
+
+    /// `jitter` in [0, 1) scales the random share of the lead time (0 for
+    /// deterministic replays)
+    pub fn wait_with_jitter(&self, interval_ms: u64, now: i64, jitter: f64) -> u64 {
+        let Some(expires_at) = self.expires_at else {
+            return interval_ms;
+        };
+        let remaining_ms = (expires_at.saturating_sub(now).max(0) as u64).saturating_mul(1000);
+        // Nothing left to prefetch: the regular schedule applies
+        if self.lead_secs == 0 || remaining_ms == 0 {
+            return interval_ms;
+        }
+
+        let lead_ms = self.lead_secs.saturating_mul(1000).min(remaining_ms / 2);
+        let jitter_ms = (lead_ms as f64 * JITTER_FRACTION * jitter) as u64;
+        let renew_in = (remaining_ms - lead_ms).saturating_sub(jitter_ms);
+        interval_ms.min(renew_in.max(MIN_WAIT_MS))
+    }
+}
+
+/// Local unix time the response's lease lapses
+///
+/// `expires_at` is in server time, so it is shifted by the server/local clock
+/// offset when the response carries a signed `server_time`.
+fn lease_expiry(response: &VerifyResponse, now: i64) -> Option<i64> {
+    if let Some(expires_at) = response.expires_at {
+        let offset = response
+            .server_time
+            .filter(|_| response.signature_valid)
+            .map_or(0, |server_time| now.saturating_sub(server_time));
+        return Some(expires_at.saturating_add(offset));
+    }
+    response.expires_in.map(|expires_in| time::expires_at(now, expires_in))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scheduler(lead_secs: u64, response: VerifyResponse) -> RenewalScheduler {
+        let mut scheduler = RenewalScheduler::new(lead_secs);
+        scheduler.observe(&response, 1_000);
+        scheduler
+    }
+
+    #[test]
+    fn test_renews_ahead_of_expiry() {
+        let lease = VerifyResponse { expires_in: Some(3_600), ..Default::default() };
+
+        // Expiry far beyond the interval: regular schedule
+        assert_eq!(scheduler(300, lease.clone()).wait_with_jitter(60_000, 1_000, 0.0), 60_000);
+
+        // Interval would run past the expiry: renew `lead` (plus jitter) early
+        let renewal = scheduler(300, lease.clone());
+        assert_eq!(renewal.wait_with_jitter(7_200_000, 1_000, 0.0), 3_300_000);
+        assert_eq!(renewal.wait_with_jitter(7_200_000, 1_000, 1.0), 3_225_000);
+
+        // Short lease: half the remaining lifetime, never below the floor
+        let short = scheduler(300, VerifyResponse { expires_in: Some(120), ..Default::default() });
+        assert_eq!(short.wait_with_jitter(600_000, 1_000, 0.0), 60_000);
+        assert_eq!(short.wait_with_jitter(600_000, 1_116, 0.0), MIN_WAIT_MS);
+
+        // Expired, disabled or no expiry: regular schedule
+        assert_eq!(short.wait_with_jitter(600_000, 2_000, 0.0), 600_000);
+        assert_eq!(scheduler(0, lease).wait_with_jitter(7_200_000, 1_000, 0.0), 7_200_000);
+        assert_eq!(scheduler(300, VerifyResponse::default()).wait_with_jitter(60_000, 1_000, 0.0), 60_000);
+    }
+
+    #[test]
+    fn test_expiring_and_expired() {
+        let renewal = scheduler(300, VerifyResponse { expires_in: Some(3_600), ..Default::default() });
+        assert!(!renewal.expiring(1_000) && !renewal.expired(1_000));
+        assert!(renewal.expiring(4_300) && !renewal.expired(4_300));
+        assert!(!renewal.expiring(4_600) && renewal.expired(4_600));
+
+        // No known expiry: never runs out
+        assert!(!scheduler(300, VerifyResponse::default()).expired(i64::MAX));
+    }
+
+    #[test]
+    fn test_expires_at_uses_signed_server_offset() {
+        // Server clock 100s ahead of ours
+        let response = VerifyResponse {
+            expires_at: Some(1_700),
+            server_time: Some(1_100),
+            expires_in: Some(9_999),
+            ..Default::default()
+        };
+        assert_eq!(lease_expiry(&response, 1_000), Some(1_700));
+        assert_eq!(
+            lease_expiry(&VerifyResponse { signature_valid: true, ..response }, 1_000),
+            Some(1_600)
+        );
+    }
+}
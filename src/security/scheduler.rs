@@ -0,0 +1,303 @@
+//! CPU-budgeted scheduling of periodic security checks
+//!
+//! Security checks (integrity, anti-debug, ...) run on customer machines and
+//! must stay cheap. Instead of running all of them back to back at every
+//! verification, the scheduler staggers them across the check interval and
+//! measures the CPU time each run costs. A check is deferred while running it
+//! would push total overhead above the configured budget, but never beyond
+//! `MAX_DEFER_PERIODS` periods, so the budget cannot starve enforcement.
+//!
+//! The measured overhead is published for telemetry (sent with verification).
+
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A check overdue by this many periods runs regardless of the budget
+const MAX_DEFER_PERIODS: u32 = 4;
+
+/// Latest published overhead report
+static OVERHEAD: Mutex<Option<OverheadReport>> = Mutex::new(None);
+
+/// A failed security check
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// Check kind, e.g. "integrity" (also the tamper report kind)
+    pub kind: &'static str,
+    pub detail: String,
+}
+
+/// Security check callback: Err(detail) on violation
+pub type CheckFn = Box<dyn FnMut() -> Result<(), String> + Send>;
+
+struct ScheduledCheck {
+    name: &'static str,
+    check: CheckFn,
+    /// Position in the interval (fraction of the period), for staggering
+    phase: f64,
+    next_due: Instant,
+    /// When the check first became due while deferred (deferrals move `next_due`)
+    due_since: Option<Instant>,
+    runs: u64,
+    deferrals: u64,
+    cpu: Duration,
+    last_cost: Duration,
+}
+
+/// Measured overhead of security checks
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OverheadReport {
+    pub cpu_ms: f64,
+    pub wall_ms: u64,
+    /// CPU time as a percentage of wall time
+    pub cpu_percent: f64,
+    pub budget_percent: f64,
+    pub checks: Vec<CheckOverhead>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CheckOverhead {
+    pub name: String,
+    pub runs: u64,
+    pub deferrals: u64,
+    pub cpu_ms: f64,
+}
+
+/// Staggers periodic checks within a CPU budget
+pub struct CheckScheduler {
+    checks: Vec<ScheduledCheck>,
+    period: Duration,
+    /// Allowed CPU share in percent (e.g. 0.1 = 0.1%)
+    budget_percent: f64,
+    started: Instant,
+    cpu_used: Duration,
+}
+
+impl CheckScheduler {
+    pub fn new(period: Duration, budget_percent: f64) -> Self {
+        Self {
+            checks: Vec::new(),
+            period,
+            budget_percent,
+            started: Instant::now(),
+            cpu_used: Duration::ZERO,
+        }
+    }
+
+    /// Register a check; every check runs once immediately on the first `run_due`
+    pub fn register(&mut self, name: &'static str, check: CheckFn) {
+        self.checks.push(ScheduledCheck {
+            name,
+            check,
+            phase: 0.0,
+            next_due: Instant::now(),
+            due_since: None,
+            runs: 0,
+            deferrals: 0,
+            cpu: Duration::ZERO,
+            last_cost: Duration::ZERO,
+        });
+
+        // Spread checks evenly over the period
+        let count = self.checks.len() as f64;
+        for (i, check) in self.checks.iter_mut().enumerate() {
+            check.phase = (i + 1) as f64 / (count + 1.0);
+        }
+    }
+
+    /// Update the period (runtime-patched check interval)
+    pub fn set_period(&mut self, period: Duration) {
+        self.period = period;
+    }
+
+    /// Earliest time any check becomes due
+    pub fn next_due(&self) -> Option<Instant> {
+        self.checks.iter().map(|c| c.next_due).min()
+    }
+
+    /// Run all checks that are due and fit the budget
+    ///
+    /// # Returns
+    /// The first violation found, if any
+    pub fn run_due(&mut self) -> Option<Violation> {
+        let now = Instant::now();
+        let mut violation = None;
+
+        for index in 0..self.checks.len() {
+            if self.checks[index].next_due > now {
+                continue;
+            }
+
+            let check = &self.checks[index];
+            let due_since = check.due_since.unwrap_or(check.next_due);
+            let starving = now.saturating_duration_since(due_since) >= self.period * MAX_DEFER_PERIODS;
+            // First runs always happen: they establish the cost estimate
+            if check.runs > 0 && !starving && !self.within_budget(check.last_cost) {
+                let check = &mut self.checks[index];
+                check.deferrals += 1;
+                check.due_since = Some(due_since);
+                check.next_due = now + self.period / 10;
+                continue;
+            }
+
+            let period = self.period;
+            let check = &mut self.checks[index];
+            let cpu_before = thread_cpu_time();
+            let result = (check.check)();
+            let cost = thread_cpu_time().saturating_sub(cpu_before);
+
+            check.runs += 1;
+            check.due_since = None;
+            check.cpu += cost;
+            check.last_cost = cost;
+            check.next_due = if check.runs == 1 {
+                now + period.mul_f64(check.phase)
+            } else {
+                now + period
+            };
+            self.cpu_used += cost;
+
+            if let Err(detail) = result
+                && violation.is_none()
+            {
+                violation = Some(Violation { kind: check.name, detail });
+            }
+        }
+
+        publish(self.overhead());
+        violation
+    }
+
+    /// Whether spending `cost` more CPU keeps overhead within budget
+    fn within_budget(&self, cost: Duration) -> bool {
+        let allowed = self.started.elapsed().mul_f64(self.budget_percent / 100.0);
+        self.cpu_used + cost <= allowed
+    }
+
+    /// Overhead measured so far
+    pub fn overhead(&self) -> OverheadReport {
+        let wall = self.started.elapsed();
+        let cpu_ms = self.cpu_used.as_secs_f64() * 1000.0;
+        OverheadReport {
+            cpu_ms,
+            wall_ms: wall.as_millis() as u64,
+            cpu_percent: if wall.is_zero() { 0.0 } else { cpu_ms / (wall.as_secs_f64() * 1000.0) * 100.0 },
+            budget_percent: self.budget_percent,
+            checks: self.checks.iter().map(|c| CheckOverhead {
+                name: c.name.to_string(),
+                runs: c.runs,
+                deferrals: c.deferrals,
+                cpu_ms: c.cpu.as_secs_f64() * 1000.0,
+            }).collect(),
+        }
+    }
+}
+
+/// Latest overhead report for telemetry
+pub fn latest_overhead() -> Option<OverheadReport> {
+    OVERHEAD.lock().ok().and_then(|guard| guard.clone())
+}
+
+fn publish(report: OverheadReport) {
+    if let Ok(mut guard) = OVERHEAD.lock() {
+        *guard = Some(report);
+    }
+}
+
+/// CPU time consumed by the calling thread
+fn thread_cpu_time() -> Duration {
+    #[cfg(unix)]
+    {
+        let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+        if unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut ts) } != 0 {
+            return Duration::ZERO;
+        }
+        Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+    }
+
+    #[cfg(windows)]
+    {
+        use winapi::shared::minwindef::FILETIME;
+        use winapi::um::processthreadsapi::{GetCurrentThread, GetThreadTimes};
+
+        let zero = || FILETIME { dwLowDateTime: 0, dwHighDateTime: 0 };
+        let (mut creation, mut exit, mut kernel, mut user) = (zero(), zero(), zero(), zero());
+        let ok = unsafe { GetThreadTimes(GetCurrentThread(), &mut creation, &mut exit, &mut kernel, &mut user) };
+        if ok == 0 {
+            return Duration::ZERO;
+        }
+
+        // FILETIME counts 100ns units
+        let ticks = |t: FILETIME| ((t.dwHighDateTime as u64) << 32) | t.dwLowDateTime as u64;
+        Duration::from_nanos((ticks(kernel) + ticks(user)) * 100)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_first_run_and_violation() {
+        let mut scheduler = CheckScheduler::new(Duration::from_secs(60), 0.1);
+        scheduler.register("ok", Box::new(|| Ok(())));
+        scheduler.register("bad", Box::new(|| Err("tampered".to_string())));
+
+        let violation = scheduler.run_due().unwrap();
+        assert_eq!(violation.kind, "bad");
+        assert_eq!(violation.detail, "tampered");
+
+        // Staggered: nothing is due right after the first run
+        assert!(scheduler.run_due().is_none());
+        assert!(scheduler.next_due().unwrap() > Instant::now());
+    }
+
+    #[test]
+    fn test_budget_defers_expensive_checks() {
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = runs.clone();
+
+        let mut scheduler = CheckScheduler::new(Duration::from_millis(100), 0.0001);
+        scheduler.register("busy", Box::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let start = Instant::now();
+            while start.elapsed() < Duration::from_millis(5) {}
+            Ok(())
+        }));
+
+        scheduler.run_due();
+        std::thread::sleep(Duration::from_millis(60));
+        scheduler.run_due();
+
+        // Second run is over budget and not yet starving
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!(scheduler.overhead().checks[0].deferrals, 1);
+    }
+
+    #[test]
+    fn test_deferred_check_runs_once_starving() {
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = runs.clone();
+
+        let period = Duration::from_millis(20);
+        let mut scheduler = CheckScheduler::new(period, 0.0001);
+        scheduler.register("busy", Box::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let start = Instant::now();
+            while start.elapsed() < Duration::from_millis(5) {}
+            Ok(())
+        }));
+        scheduler.run_due();
+
+        // Always over budget: each deferral pushes the check back, yet it must run
+        let deadline = Instant::now() + period * (MAX_DEFER_PERIODS + 4);
+        while runs.load(Ordering::SeqCst) < 2 && Instant::now() < deadline {
+            std::thread::sleep(period / 10);
+            scheduler.run_due();
+        }
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        assert!(scheduler.overhead().checks[0].deferrals > 0);
+    }
+}
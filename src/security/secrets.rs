@@ -0,0 +1,254 @@
+//! In-memory protection of secrets
+//!
+//! The shared secret must not be recoverable from core dumps, swap or
+//! /proc/pid/mem. `SecretString` keeps it in a single locked allocation that
+//! is wiped on drop, and `harden_process` disables core dumps at startup.
+//! Exits skip destructors, so the secrets still alive are wiped by a
+//! shutdown hook instead.
+//!
+//! Memory is locked per page and locks do not nest: small secrets share
+//! pages (with each other and with unrelated data), and unlocking one would
+//! unlock its neighbours. Pages are therefore counted and only unlocked
+//! when the last secret on them is dropped.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Mutex;
+use zeroize::Zeroize;
+
+/// Number of live locked secrets per page (by page address)
+static LOCKED_PAGES: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+
+/// Allocations of live secrets (address, capacity), wiped at exit
+static LIVE: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+
+/// String secret that is memory-locked while alive and zeroized on drop
+///
+/// Derefs to `&str` so it can be passed wherever a secret is consumed.
+/// `Debug` never prints the value.
+pub struct SecretString {
+    value: String,
+    locked: bool,
+}
+
+impl SecretString {
+    pub fn new(mut value: String) -> Self {
+        // Lock the final allocation: a later reallocation would leave an
+        // unlocked, unwiped copy behind
+        value.shrink_to_fit();
+        let locked = lock_memory(value.as_ptr(), value.capacity());
+        if value.capacity() > 0 {
+            LIVE.lock().unwrap_or_else(|e| e.into_inner()).insert(value.as_ptr() as usize, value.capacity());
+        }
+        Self { value, locked }
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.value
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        let (ptr, len) = (self.value.as_ptr(), self.value.capacity());
+        LIVE.lock().unwrap_or_else(|e| e.into_inner()).remove(&(ptr as usize));
+        self.value.zeroize();
+        if self.locked {
+            unlock_memory(ptr, len);
+        }
+    }
+}
+
+impl Deref for SecretString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.value
+    }
+}
+
+impl Clone for SecretString {
+    fn clone(&self) -> Self {
+        Self::new(self.value.clone())
+    }
+}
+
+impl PartialEq for SecretString {
+    fn eq(&self, other: &Self) -> bool {
+        use subtle::ConstantTimeEq;
+        self.value.as_bytes().ct_eq(other.value.as_bytes()).into()
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretString(<redacted>)")
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(value: &str) -> Self {
+        Self::new(value.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+impl Serialize for SecretString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.value)
+    }
+}
+
+/// Disable core dumps and crash dumps for this process, and wipe the secrets
+/// still alive on exit
+///
+/// Call once at startup, before any secret is loaded.
+pub fn harden_process() {
+    // Registered first, so it runs after every hook that may still sign
+    crate::utils::shutdown::register("secret_wipe", wipe_live);
+
+    #[cfg(unix)]
+    unsafe {
+        let no_core = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+        if libc::setrlimit(libc::RLIMIT_CORE, &no_core) != 0 {
+            log_warn!("⚠️  Failed to disable core dumps");
+        }
+    }
+
+    // Also makes /proc/self/mem and friends root-only and blocks ptrace
+    // attaches from other processes of the same user
+    #[cfg(target_os = "linux")]
+    unsafe {
+        if libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) != 0 {
+            log_warn!("⚠️  Failed to mark process non-dumpable");
+        }
+    }
+
+    // Suppress Windows Error Reporting dialogs and the crash dumps they collect
+    #[cfg(windows)]
+    unsafe {
+        use winapi::um::errhandlingapi::SetErrorMode;
+        use winapi::um::winbase::{SEM_FAILCRITICALERRORS, SEM_NOGPFAULTERRORBOX};
+        SetErrorMode(SEM_FAILCRITICALERRORS | SEM_NOGPFAULTERRORBOX);
+    }
+}
+
+/// Zero every live secret in place (exit path: never blocks on the registry)
+fn wipe_live() {
+    let Ok(live) = LIVE.try_lock() else {
+        return;
+    };
+    for (&ptr, &len) in live.iter() {
+        // The owners are never used again: the process exits next
+        unsafe { std::slice::from_raw_parts_mut(ptr as *mut u8, len) }.zeroize();
+    }
+}
+
+fn lock_memory(ptr: *const u8, len: usize) -> bool {
+    if len == 0 {
+        return false;
+    }
+    let Ok(mut locked) = LOCKED_PAGES.lock() else {
+        return false;
+    };
+
+    // Locking an already locked page again is harmless
+    #[cfg(unix)]
+    // Fails under a tight RLIMIT_MEMLOCK; the value is still wiped on drop
+    let ok = unsafe { libc::mlock(ptr as *const libc::c_void, len) == 0 };
+
+    #[cfg(windows)]
+    let ok = unsafe { winapi::um::memoryapi::VirtualLock(ptr as *mut _, len) != 0 };
+
+    if ok {
+        for page in pages(ptr, len) {
+            *locked.entry(page).or_insert(0) += 1;
+        }
+    }
+    ok
+}
+
+fn unlock_memory(ptr: *const u8, len: usize) {
+    let Ok(mut locked) = LOCKED_PAGES.lock() else {
+        return;
+    };
+    for page in pages(ptr, len) {
+        let Some(count) = locked.get_mut(&page) else {
+            continue;
+        };
+        *count -= 1;
+        if *count > 0 {
+            continue;
+        }
+        locked.remove(&page);
+
+        #[cfg(unix)]
+        unsafe {
+            libc::munlock(page as *const libc::c_void, page_size());
+        }
+
+        #[cfg(windows)]
+        unsafe {
+            winapi::um::memoryapi::VirtualUnlock(page as *mut _, page_size());
+        }
+    }
+}
+
+/// Addresses of the pages `len` bytes at `ptr` touch
+fn pages(ptr: *const u8, len: usize) -> impl Iterator<Item = usize> {
+    let size = page_size();
+    let start = ptr as usize / size * size;
+    (start..ptr as usize + len).step_by(size)
+}
+
+fn page_size() -> usize {
+    #[cfg(unix)]
+    let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+
+    #[cfg(windows)]
+    let size = unsafe {
+        let mut info: winapi::um::sysinfoapi::SYSTEM_INFO = std::mem::zeroed();
+        winapi::um::sysinfoapi::GetSystemInfo(&mut info);
+        info.dwPageSize
+    };
+
+    if size > 0 { size as usize } else { 4096 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_is_redacted_and_usable() {
+        let secret = SecretString::from("hunter2");
+        assert_eq!(secret.expose(), "hunter2");
+        assert_eq!(&*secret, "hunter2");
+        assert!(!format!("{:?}", secret).contains("hunter2"));
+
+        let parsed: SecretString = serde_json::from_str("\"hunter2\"").unwrap();
+        assert!(parsed == secret);
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), "\"hunter2\"");
+    }
+
+    #[test]
+    fn test_shared_page_stays_locked() {
+        let first = SecretString::from("first");
+        let second = SecretString::from("second");
+        if !first.locked || !second.locked {
+            return; // RLIMIT_MEMLOCK too tight here
+        }
+        let page = pages(second.value.as_ptr(), second.value.capacity()).next().unwrap();
+
+        // Dropping one secret keeps the pages of the others counted
+        drop(first);
+        assert!(LOCKED_PAGES.lock().unwrap().get(&page).is_some_and(|&count| count > 0));
+    }
+}
@@ -0,0 +1,246 @@
+//! Root of trust for server-signed artifacts
+//!
+//! The HMAC shared secret authenticates the server's answers, but it ships in
+//! every binary and cannot prove authorship of artifacts that must also hold
+//! offline (licenses, policy bundles, revocations, updates). Those are signed
+//! with an Ed25519 key whose public half is embedded at build time
+//! (`KILLER_TRUST_ROOT_KEY`, hex).
+//!
+//! Rotation: the server publishes a successor key signed by the current one.
+//! Accepted successors are persisted and every link of the chain is
+//! re-verified from the embedded root on every load, so editing the state
+//! file cannot inject a key. A stored chain with a link that fails is refused
+//! as a whole - nothing verifies against it - rather than cut back to an
+//! older key; the server rebuilds it from the root. Only the newest key in
+//! the chain is trusted.
+//!
+//! Every signature covers `context || 0x00 || payload`, so an artifact signed
+//! for one purpose can never be replayed as another.
+
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::state::StateStore;
+
+/// Signature context of successor-key certificates
+pub const SUCCESSOR_CONTEXT: &str = "killcode-successor-key";
+
+/// Successor-key certificate, signed by the key it replaces
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SuccessorKey {
+    /// Position in the chain (root = 0, first successor = 1, ...)
+    pub sequence: u64,
+    /// Hex Ed25519 public key
+    pub public_key: String,
+    /// Hex signature by the previous key
+    pub signature: String,
+}
+
+impl SuccessorKey {
+    fn signed_payload(&self) -> String {
+        format!("{}:{}", self.sequence, self.public_key.to_ascii_lowercase())
+    }
+}
+
+/// Verified key chain starting at the embedded root
+#[derive(Debug, Clone)]
+pub struct TrustChain {
+    active: [u8; 32],
+    sequence: u64,
+    links: Vec<SuccessorKey>,
+}
+
+impl TrustChain {
+    /// Chain of the root alone
+    pub fn new(root: [u8; 32]) -> Self {
+        Self { active: root, sequence: 0, links: Vec::new() }
+    }
+
+    /// Rebuild the chain from the root, verifying every successor
+    ///
+    /// # Returns
+    /// Err if any successor fails to verify
+    pub fn rebuild(root: [u8; 32], successors: &[SuccessorKey]) -> Result<Self, String> {
+        let mut chain = Self::new(root);
+        for successor in successors {
+            chain
+                .accept(successor.clone())
+                .map_err(|e| format!("stored successor key {} is invalid: {}", successor.sequence, e))?;
+        }
+        Ok(chain)
+    }
+
+    /// Sequence number of the active key
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Accepted successors, in order
+    pub fn links(&self) -> &[SuccessorKey] {
+        &self.links
+    }
+
+    /// Verify an artifact against the active key
+    pub fn verify(&self, context: &str, payload: &[u8], signature_hex: &str) -> Result<(), String> {
+        verify_with(&self.active, context, payload, signature_hex)
+    }
+
+    /// Rotate to a successor signed by the active key
+    pub fn accept(&mut self, successor: SuccessorKey) -> Result<(), String> {
+        if successor.sequence != self.sequence + 1 {
+            return Err(format!(
+                "successor key out of order (expected {}, got {})",
+                self.sequence + 1,
+                successor.sequence
+            ));
+        }
+        let key = parse_key(&successor.public_key)?;
+        self.verify(SUCCESSOR_CONTEXT, successor.signed_payload().as_bytes(), &successor.signature)
+            .map_err(|e| format!("successor key not signed by the active key: {}", e))?;
+
+        self.active = key;
+        self.sequence = successor.sequence;
+        self.links.push(successor);
+        Ok(())
+    }
+}
+
+/// Public key embedded at build time, if any
+pub fn root_key() -> Option<[u8; 32]> {
+    option_env!("KILLER_TRUST_ROOT_KEY")
+        .filter(|key| !key.is_empty())
+        .and_then(|key| parse_key(key).ok())
+}
+
+/// Verify a server-signed artifact against the current trust chain
+///
+/// # Arguments
+/// * `license_id` - License whose stored trust chain is used
+/// * `context` - What the artifact is (e.g. "offline-license"); part of the signed data
+/// * `payload` - Exact bytes that were signed
+/// * `signature_hex` - Hex Ed25519 signature
+pub fn verify_signed_blob(license_id: &str, context: &str, payload: &[u8], signature_hex: &str) -> Result<(), String> {
+    load_chain(license_id)?.verify(context, payload, signature_hex)
+}
+
+/// Persist a successor key delivered by the server
+///
+/// Already known successors are ignored. A broken stored chain is
+/// discarded, so the server can deliver it again starting from the root.
+pub fn accept_successor(license_id: &str, successor: &SuccessorKey) -> Result<(), String> {
+    if load_chain(license_id).is_ok_and(|chain| successor.sequence <= chain.sequence()) {
+        return Ok(());
+    }
+    let root = root_key().ok_or("no trust root key embedded in this build")?;
+    let store = StateStore::for_license(license_id);
+    let rotated = store.update(|state| {
+        let mut chain = TrustChain::rebuild(root, &state.trust_chain).unwrap_or_else(|e| {
+            log_warn!("⚠️  Discarding the stored trust chain: {}", e);
+            TrustChain::new(root)
+        });
+        if successor.sequence <= chain.sequence() {
+            state.trust_chain = chain.links().to_vec();
+            return Ok(None);
+        }
+        chain.accept(successor.clone())?;
+        state.trust_chain = chain.links().to_vec();
+        Ok::<_, String>(Some(chain.sequence()))
+    })??;
+
+    if let Some(sequence) = rotated {
+        log_info!("🔑 Trust root rotated to key #{}", sequence);
+    }
+    Ok(())
+}
+
+fn load_chain(license_id: &str) -> Result<TrustChain, String> {
+    let root = root_key().ok_or("no trust root key embedded in this build")?;
+    let state = StateStore::for_license(license_id).load();
+    TrustChain::rebuild(root, &state.trust_chain)
+}
+
+fn verify_with(key: &[u8; 32], context: &str, payload: &[u8], signature_hex: &str) -> Result<(), String> {
+    let signature = hex::decode(signature_hex).map_err(|_| "signature is not valid hex")?;
+    UnparsedPublicKey::new(&ED25519, key)
+        .verify(&signed_message(context, payload), &signature)
+        .map_err(|_| "invalid signature".to_string())
+}
+
+fn signed_message(context: &str, payload: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(context.len() + 1 + payload.len());
+    message.extend_from_slice(context.as_bytes());
+    message.push(0);
+    message.extend_from_slice(payload);
+    message
+}
+
+fn parse_key(hex_key: &str) -> Result<[u8; 32], String> {
+    hex::decode(hex_key.trim())
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| "public key must be 32 bytes of hex".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    fn keypair(seed: u8) -> Ed25519KeyPair {
+        Ed25519KeyPair::from_seed_unchecked(&[seed; 32]).unwrap()
+    }
+
+    fn public(key: &Ed25519KeyPair) -> [u8; 32] {
+        key.public_key().as_ref().try_into().unwrap()
+    }
+
+    fn sign(key: &Ed25519KeyPair, context: &str, payload: &[u8]) -> String {
+        hex::encode(key.sign(&signed_message(context, payload)))
+    }
+
+    fn successor(signer: &Ed25519KeyPair, next: &Ed25519KeyPair, sequence: u64) -> SuccessorKey {
+        let mut successor = SuccessorKey {
+            sequence,
+            public_key: hex::encode(public(next)),
+            signature: String::new(),
+        };
+        successor.signature = sign(signer, SUCCESSOR_CONTEXT, successor.signed_payload().as_bytes());
+        successor
+    }
+
+    #[test]
+    fn test_blob_signature_bound_to_context() {
+        let root = keypair(1);
+        let chain = TrustChain::new(public(&root));
+        let signature = sign(&root, "policy", b"{}");
+
+        assert!(chain.verify("policy", b"{}", &signature).is_ok());
+        assert!(chain.verify("revocation", b"{}", &signature).is_err());
+        assert!(chain.verify("policy", b"{ }", &signature).is_err());
+    }
+
+    #[test]
+    fn test_rotation_retires_previous_key() {
+        let (root, next) = (keypair(1), keypair(2));
+        let chain = TrustChain::rebuild(public(&root), &[successor(&root, &next, 1)]).unwrap();
+
+        assert_eq!(chain.sequence(), 1);
+        assert!(chain.verify("policy", b"x", &sign(&next, "policy", b"x")).is_ok());
+        assert!(chain.verify("policy", b"x", &sign(&root, "policy", b"x")).is_err());
+    }
+
+    #[test]
+    fn test_forged_or_out_of_order_successor_rejected() {
+        let (root, attacker, next) = (keypair(1), keypair(9), keypair(2));
+        let mut chain = TrustChain::new(public(&root));
+
+        assert!(chain.accept(successor(&attacker, &next, 1)).is_err());
+        assert!(chain.accept(successor(&root, &next, 2)).is_err());
+        assert_eq!(chain.sequence(), 0);
+
+        // A tampered stored chain is refused as a whole, not cut back
+        let stored = [successor(&root, &next, 1), successor(&attacker, &attacker, 2)];
+        assert!(TrustChain::rebuild(public(&root), &stored).is_err());
+        assert!(TrustChain::rebuild(public(&root), &stored[1..]).is_err());
+    }
+}
@@ -0,0 +1,232 @@
+//! In-process mock license server
+//!
+//! Plain HTTP on 127.0.0.1 (an ephemeral port), implementing
+//! `/api/v1/verify` with scripted replies. Replies carry an
+//! `X-Response-Signature` made with the shared secret, like the real server's,
+//! unless scripted otherwise; once the script is used up every check is
+//! answered `Authorized`. Other API paths (usage, tamper and kill reports...)
+//! get a signed `{"ok":true}`. Every request is recorded for assertions.
+//!
+//! ```no_run
+//! use kc_killer::testing::{MockServer, Reply};
+//!
+//! let server = MockServer::start("secret").unwrap();
+//! server.script([Reply::Authorized, Reply::Revoked]);
+//! let response = kc_killer::verify_license("lic", &server.url(), "secret", 0, true)?;
+//! assert!(response.authorized);
+//! # Ok::<(), String>(())
+//! ```
+
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::verification::hmac::create_signature;
+use crate::verification::network::VERIFY_PATH;
+
+/// Scripted answer to one verification request
+#[derive(Debug, Clone)]
+pub enum Reply {
+    /// Signed 200 `authorized: true`
+    Authorized,
+    /// Signed 403 denial (`authorized: false`)
+    Revoked,
+    /// 429 with `Retry-After`
+    RateLimited { retry_after_secs: u64 },
+    /// 200 with a body that is not JSON
+    Malformed,
+    /// `reply`, after `delay`
+    Slow { delay: Duration, reply: Box<Reply> },
+    /// Any status and JSON body, signed or not (runtime patches, rotations...)
+    Json { status: u16, body: Value, signed: bool },
+}
+
+/// Request as received by the mock server
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    /// Header names in lower case
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    /// Value of header `name` (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        let name = name.to_ascii_lowercase();
+        self.headers.iter().find(|(header, _)| *header == name).map(|(_, value)| value.as_str())
+    }
+
+    /// Body parsed as JSON
+    pub fn json(&self) -> Option<Value> {
+        serde_json::from_str(&self.body).ok()
+    }
+}
+
+struct State {
+    shared_secret: String,
+    script: Mutex<VecDeque<Reply>>,
+    requests: Mutex<Vec<RecordedRequest>>,
+}
+
+/// Running mock license server (stops when dropped)
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<State>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    /// Start a server signing its replies with `shared_secret`
+    pub fn start(shared_secret: &str) -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(State {
+            shared_secret: shared_secret.to_string(),
+            script: Mutex::new(VecDeque::new()),
+            requests: Mutex::new(Vec::new()),
+        });
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let state = Arc::clone(&state);
+            let stop = Arc::clone(&stop);
+            thread::Builder::new().name("mock_license_server".to_string()).spawn(move || {
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(stream) = stream else {
+                        continue;
+                    };
+                    // One thread per connection: a slow reply must not hold up others
+                    let state = Arc::clone(&state);
+                    thread::spawn(move || serve(stream, &state));
+                }
+            })?
+        };
+        Ok(Self { addr, state, stop, thread: Some(thread) })
+    }
+
+    /// Base URL to use as `server_url`
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Queue replies for the next verification requests
+    pub fn script(&self, replies: impl IntoIterator<Item = Reply>) {
+        self.state.script.lock().unwrap_or_else(|e| e.into_inner()).extend(replies);
+    }
+
+    /// Every request received so far
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.requests.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Verification requests received so far
+    pub fn verify_requests(&self) -> Vec<RecordedRequest> {
+        self.requests().into_iter().filter(|request| request.path == VERIFY_PATH).collect()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake the accept loop
+        let _ = TcpStream::connect(self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn serve(stream: TcpStream, state: &State) {
+    let Some(request) = read_request(&stream) else {
+        return;
+    };
+    let reply = if request.path == VERIFY_PATH {
+        state.script.lock().unwrap_or_else(|e| e.into_inner()).pop_front().unwrap_or(Reply::Authorized)
+    } else {
+        Reply::Json { status: 200, body: json!({ "ok": true }), signed: true }
+    };
+    state.requests.lock().unwrap_or_else(|e| e.into_inner()).push(request);
+    write_reply(stream, &reply, &state.shared_secret);
+}
+
+fn read_request(stream: &TcpStream) -> Option<RecordedRequest> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line).ok()?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+
+    let length = headers
+        .iter()
+        .find(|(name, _)| name == "content-length")
+        .and_then(|(_, value)| value.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+    Some(RecordedRequest { method, path, headers, body: String::from_utf8_lossy(&body).into_owned() })
+}
+
+fn write_reply(mut stream: TcpStream, reply: &Reply, shared_secret: &str) {
+    let mut extra_headers = Vec::new();
+    let (status, body, signed) = match reply {
+        Reply::Authorized => (200, json!({ "authorized": true, "message": "ok", "expires_in": null }).to_string(), true),
+        Reply::Revoked => (
+            403,
+            json!({ "authorized": false, "message": "License revoked", "expires_in": null }).to_string(),
+            true,
+        ),
+        Reply::RateLimited { retry_after_secs } => {
+            extra_headers.push(format!("Retry-After: {}", retry_after_secs));
+            (429, json!({ "error": "rate limited" }).to_string(), false)
+        }
+        Reply::Malformed => (200, "<<not json>>".to_string(), false),
+        Reply::Slow { delay, reply } => {
+            thread::sleep(*delay);
+            return write_reply(stream, reply, shared_secret);
+        }
+        Reply::Json { status, body, signed } => (*status, body.to_string(), *signed),
+    };
+    if signed {
+        extra_headers.push(format!("X-Response-Signature: {}", create_signature(&body, shared_secret)));
+    }
+
+    let mut response = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        body.len()
+    );
+    for header in extra_headers {
+        response.push_str(&header);
+        response.push_str("\r\n");
+    }
+    response.push_str("\r\n");
+    response.push_str(&body);
+    let _ = stream.write_all(response.as_bytes());
+    let _ = stream.flush();
+}
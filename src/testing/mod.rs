@@ -0,0 +1,9 @@
+//! Test support (`testing` feature)
+//!
+//! Not part of the overload: end-to-end tests of this crate and of loaders
+//! linking it use these to run the real verification code against a local
+//! license server.
+
+pub mod mock_server;
+
+pub use mock_server::{MockServer, RecordedRequest, Reply};
@@ -0,0 +1,108 @@
+//! Local audit trail of enforcement decisions
+//!
+//! Decisions that deviate from what the server or config asked for (e.g. a
+//! downgraded kill method) are appended as JSON lines to `audit.log` in the
+//! license's state directory, so support can reconstruct them after the fact.
+//! The log is synced to disk on exit (a shutdown hook), so a decision right
+//! before a kill is not lost with the page cache. Once it reaches
+//! `MAX_AUDIT_BYTES` it is rotated to `audit.log.1`, replacing the previous
+//! generation, so a seat that keeps downgrading cannot fill the disk.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+
+use super::{secure_fs, shutdown};
+use super::state::namespace_dir;
+use super::time;
+
+/// File name of the audit log inside the state directory
+pub const AUDIT_FILE: &str = "audit.log";
+
+/// Size at which the audit log is rotated
+pub const MAX_AUDIT_BYTES: u64 = 1024 * 1024;
+
+/// One audit log entry
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AuditEntry {
+    pub timestamp: i64,
+    /// Decision kind, e.g. "kill_method_downgrade"
+    pub kind: String,
+    pub detail: String,
+}
+
+/// Append an entry to the audit log (best effort, also logged to stderr)
+pub fn record(kind: &str, detail: &str) {
+    log_info!("📝 Audit: {} - {}", kind, detail);
+
+    let entry = AuditEntry {
+        timestamp: time::unix_now(),
+        kind: kind.to_string(),
+        detail: detail.to_string(),
+    };
+
+    if let Err(e) = append(&audit_path(), &entry) {
+        log_warn!("⚠️  Failed to write audit log: {}", e);
+    }
+    static SYNC_ON_EXIT: Once = Once::new();
+    SYNC_ON_EXIT.call_once(|| shutdown::register("audit_sync", sync));
+}
+
+/// Flush the audit log to disk
+fn sync() {
+    if let Ok(file) = std::fs::File::open(audit_path()) {
+        let _ = file.sync_data();
+    }
+}
+
+/// Path of the audit log
+pub fn audit_path() -> PathBuf {
+    namespace_dir().join(AUDIT_FILE)
+}
+
+fn append(path: &Path, entry: &AuditEntry) -> Result<(), String> {
+    let line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+    rotate_if_larger(path, MAX_AUDIT_BYTES);
+    secure_fs::append_private(path, &line)
+}
+
+/// Move the log to `<name>.1` once it holds `max_bytes` or more
+fn rotate_if_larger(path: &Path, max_bytes: u64) {
+    if !fs::symlink_metadata(path).is_ok_and(|metadata| metadata.len() >= max_bytes) {
+        return;
+    }
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(".1");
+    if let Err(e) = fs::rename(path, &rotated) {
+        log_warn!("⚠️  Failed to rotate audit log: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_writes_json_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(AUDIT_FILE);
+
+        for kind in ["first", "second"] {
+            let entry = AuditEntry { timestamp: 1, kind: kind.to_string(), detail: "d".to_string() };
+            append(&path, &entry).unwrap();
+        }
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let entries: Vec<AuditEntry> = contents.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].kind, "second");
+
+        // Rotation keeps one previous generation
+        rotate_if_larger(&path, u64::MAX);
+        assert!(path.exists());
+        rotate_if_larger(&path, 1);
+        assert!(!path.exists());
+        assert_eq!(std::fs::read_to_string(dir.path().join("audit.log.1")).unwrap(), contents);
+    }
+}
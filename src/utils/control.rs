@@ -0,0 +1,351 @@
+//! Control channel from the parent wrapper
+//!
+//! The shared-memory block (`health_monitor`) carries a single kill bit from
+//! the parent. Wrappers that need more listen on a Unix domain socket
+//! (Linux/macOS) or named pipe (Windows) and name it in
+//! `KILLCODE_CONTROL_SOCKET`; killer connects and serves one JSON request per
+//! line, answering each with one JSON line (on Unix only if the listening
+//! end runs as our user or root):
+//!
+//! ```text
+//! {"command":"status"}                     -> {"ok":true,"status":{...}}
+//! {"command":"pause","duration_ms":60000}  -> {"ok":true}
+//! {"command":"resume"}                     -> {"ok":true}
+//! {"command":"recheck"}                    -> {"ok":true}
+//! {"command":"rotate_config"}              -> {"ok":true,"config_version":2}
+//! ```
+//!
+//! A pause postpones the next verification by at most `MAX_PAUSE`, and a
+//! verification always runs between two pauses, so the channel can slow
+//! checks down but never stop them. Security checks and kill requests keep
+//! running while paused.
+
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+use crate::config::{self, snapshot, Config};
+use crate::utils::tasks;
+use crate::verification::cache::{self, CacheSnapshot};
+use crate::verification::patch;
+
+/// Env var naming the socket (Unix) or pipe (Windows) the wrapper listens on
+pub const CONTROL_SOCKET_ENV: &str = "KILLCODE_CONTROL_SOCKET";
+
+/// Longest honored pause
+pub const MAX_PAUSE: Duration = Duration::from_secs(600);
+
+/// Longest accepted request line
+const MAX_LINE: usize = 4096;
+
+/// Unix time the current pause ends (0 = not paused), for `status`
+static PAUSED_UNTIL: AtomicI64 = AtomicI64::new(0);
+
+/// Unix time the license lease lapses (0 = unknown), for `status`
+static LEASE_EXPIRES_AT: AtomicI64 = AtomicI64::new(0);
+
+/// Whether the lease lapses within the renewal lead (or lapsed)
+static LEASE_EXPIRING: AtomicBool = AtomicBool::new(false);
+
+/// Commands for the verification loop
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    /// Postpone the next verification (capped at `MAX_PAUSE`)
+    Pause(Duration),
+    /// End a pause
+    Resume,
+    /// Verify now
+    Recheck,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "command", rename_all = "snake_case")]
+enum Request {
+    Status,
+    Pause { duration_ms: u64 },
+    Resume,
+    Recheck,
+    RotateConfig,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Reply {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<Status>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    config_version: Option<u64>,
+}
+
+/// Answer to `status`
+#[derive(Debug, Serialize)]
+struct Status {
+    /// Latest verification result (None before the first one)
+    last_check: Option<CacheSnapshot>,
+    config_version: u64,
+    /// Unix time the current pause ends
+    paused_until: Option<i64>,
+    /// Unix time the license lease lapses
+    lease_expires_at: Option<i64>,
+    /// The lease lapses within the renewal lead, or lapsed
+    lease_expiring: bool,
+}
+
+/// Receiving end of the wrapper's commands
+pub struct ControlChannel {
+    commands: Receiver<Command>,
+}
+
+impl ControlChannel {
+    /// Connect to the wrapper's channel if KILLCODE_CONTROL_SOCKET is set
+    pub fn connect() -> Option<Self> {
+        let path = env::var(CONTROL_SOCKET_ENV).ok()?;
+        let (sender, commands) = channel();
+
+        tasks::spawn("control", move || {
+            match open(&path) {
+                Ok((reader, writer)) => {
+                    log_info!("🎛️  Control channel connected: {}", path);
+                    serve(reader, writer, &sender);
+                    log_warn!("⚠️  Control channel closed by the wrapper");
+                }
+                Err(e) => log_warn!("⚠️  Failed to connect control channel {}: {}", path, e),
+            }
+        });
+
+        Some(Self { commands })
+    }
+
+    /// Wait up to `timeout` for a command
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Command> {
+        match self.commands.recv_timeout(timeout) {
+            Ok(command) => Some(command),
+            Err(RecvTimeoutError::Timeout) => None,
+            // Channel gone: behave like a plain sleep
+            Err(RecvTimeoutError::Disconnected) => {
+                thread::sleep(timeout);
+                None
+            }
+        }
+    }
+
+    /// A pending command, without waiting
+    pub fn try_recv(&self) -> Option<Command> {
+        self.commands.try_recv().ok()
+    }
+}
+
+/// Publish the end of the current pause (None = not paused)
+pub fn set_paused_until(until: Option<i64>) {
+    PAUSED_UNTIL.store(until.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// Publish the lease expiry and whether it is about to lapse
+pub fn set_lease(expires_at: Option<i64>, expiring: bool) {
+    LEASE_EXPIRES_AT.store(expires_at.unwrap_or(0), Ordering::Relaxed);
+    LEASE_EXPIRING.store(expiring, Ordering::Relaxed);
+}
+
+#[cfg(unix)]
+fn open(path: &str) -> std::io::Result<(impl Read, impl Write)> {
+    let stream = std::os::unix::net::UnixStream::connect(path)?;
+    check_peer(&stream)?;
+    Ok((stream.try_clone()?, stream))
+}
+
+/// Only a listener of our own user (or root) may steer us: anyone able to
+/// bind the socket path could otherwise pause checks or swap the config
+#[cfg(unix)]
+fn check_peer(stream: &std::os::unix::net::UnixStream) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let uid = peer_uid(stream.as_raw_fd())?;
+    let own = unsafe { libc::geteuid() };
+    if uid == own || uid == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!("socket is owned by UID {}, not {}", uid, own),
+        ))
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_uid(fd: std::os::fd::RawFd) -> std::io::Result<libc::uid_t> {
+    let mut cred: libc::ucred = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let result = unsafe {
+        libc::getsockopt(fd, libc::SOL_SOCKET, libc::SO_PEERCRED, (&mut cred as *mut libc::ucred).cast(), &mut len)
+    };
+    if result == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(cred.uid)
+}
+
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+fn peer_uid(fd: std::os::fd::RawFd) -> std::io::Result<libc::uid_t> {
+    let (mut uid, mut gid) = (0, 0);
+    if unsafe { libc::getpeereid(fd, &mut uid, &mut gid) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(uid)
+}
+
+#[cfg(windows)]
+fn open(path: &str) -> std::io::Result<(impl Read, impl Write)> {
+    // Client end of the wrapper's named pipe (\\.\pipe\...)
+    let pipe = std::fs::OpenOptions::new().read(true).write(true).open(path)?;
+    Ok((pipe.try_clone()?, pipe))
+}
+
+/// Answer requests until the wrapper closes the channel
+fn serve(reader: impl Read, mut writer: impl Write, commands: &Sender<Command>) {
+    let mut reader = BufReader::new(reader);
+    loop {
+        let mut line = String::new();
+        match (&mut reader).take(MAX_LINE as u64 + 1).read_line(&mut line) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+
+        let reply = if line.len() > MAX_LINE {
+            // Resynchronize on the next line
+            if !line.ends_with('\n') {
+                let _ = reader.read_until(b'\n', &mut Vec::new());
+            }
+            error(format!("request longer than {} bytes", MAX_LINE))
+        } else {
+            match serde_json::from_str::<Request>(line.trim()) {
+                Ok(request) => handle(request, commands),
+                Err(e) => error(format!("invalid request: {}", e)),
+            }
+        };
+
+        let Ok(mut json) = serde_json::to_string(&reply) else {
+            return;
+        };
+        json.push('\n');
+        if writer.write_all(json.as_bytes()).and_then(|_| writer.flush()).is_err() {
+            return;
+        }
+    }
+}
+
+fn handle(request: Request, commands: &Sender<Command>) -> Reply {
+    log_debug!("🎛️  Control request: {:?}", request);
+    let command = match request {
+        Request::Status => {
+            let paused_until = PAUSED_UNTIL.load(Ordering::Relaxed);
+            let lease_expires_at = LEASE_EXPIRES_AT.load(Ordering::Relaxed);
+            return Reply {
+                ok: true,
+                status: Some(Status {
+                    last_check: cache::snapshot(),
+                    config_version: snapshot::try_current().map_or(0, |config| config.version),
+                    paused_until: (paused_until > 0).then_some(paused_until),
+                    lease_expires_at: (lease_expires_at > 0).then_some(lease_expires_at),
+                    lease_expiring: LEASE_EXPIRING.load(Ordering::Relaxed),
+                }),
+                ..Default::default()
+            };
+        }
+        Request::RotateConfig => {
+            return match rotate_config() {
+                Ok(version) => Reply { ok: true, config_version: Some(version), ..Default::default() },
+                Err(e) => error(e),
+            };
+        }
+        Request::Pause { duration_ms } => Command::Pause(Duration::from_millis(duration_ms).min(MAX_PAUSE)),
+        Request::Resume => Command::Resume,
+        Request::Recheck => Command::Recheck,
+    };
+
+    match commands.send(command) {
+        Ok(()) => Reply { ok: true, ..Default::default() },
+        Err(_) => error("verification loop is not running".to_string()),
+    }
+}
+
+/// Reload the configuration (embedded section, .config file, environment),
+/// keep the server's runtime patches and publish it; the license itself
+/// cannot change in flight
+fn rotate_config() -> Result<u64, String> {
+    let reloaded = config::load()?;
+    let Some(current) = snapshot::try_current() else {
+        return Err("no configuration installed yet".to_string());
+    };
+    if reloaded.license_id != current.license_id {
+        return Err("rotated configuration is for another license".to_string());
+    }
+
+    let kill_method = crate::security::capabilities::resolve_kill_method(&reloaded.kill_method, "config");
+    let mut reloaded = Config { kill_method, ..reloaded };
+    // Values the server patched at runtime outrank the wrapper's files
+    for change in patch::reapply(&mut reloaded, |method| {
+        crate::security::capabilities::resolve_kill_method(method, "server")
+    }) {
+        log_debug!("🔄 Keeping runtime patch: {} {} → {}", change.field, change.from, change.to);
+    }
+    super::redact::configure(&reloaded);
+    config::lint::report(&reloaded);
+    let version = snapshot::update(|config| *config = reloaded).version;
+    if version == current.version {
+        log_info!("🔄 Configuration rotation requested - unchanged");
+    } else {
+        log_info!("🔄 Configuration rotated by the wrapper (version {})", version);
+    }
+    Ok(version)
+}
+
+fn error(message: String) -> Reply {
+    Reply { ok: false, error: Some(message), ..Default::default() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serve_answers_each_line() {
+        let input = concat!(
+            "{\"command\":\"pause\",\"duration_ms\":3600000}\n",
+            "{\"command\":\"recheck\"}\n",
+            "{\"command\":\"reboot\"}\n",
+            "{\"command\":\"status\"}\n",
+        );
+        let (sender, commands) = channel();
+        let mut output = Vec::new();
+        serve(input.as_bytes(), &mut output, &sender);
+
+        // Pauses are capped
+        assert_eq!(commands.try_recv(), Ok(Command::Pause(MAX_PAUSE)));
+        assert_eq!(commands.try_recv(), Ok(Command::Recheck));
+        assert!(commands.try_recv().is_err());
+
+        let replies: Vec<serde_json::Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(replies.len(), 4);
+        assert_eq!(replies[0]["ok"], true);
+        assert_eq!(replies[2]["ok"], false);
+        assert!(replies[2]["error"].as_str().unwrap().contains("invalid request"));
+        assert!(replies[3]["status"].is_object());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_peer_accepts_own_user() {
+        let (ours, _theirs) = std::os::unix::net::UnixStream::pair().unwrap();
+        assert!(check_peer(&ours).is_ok());
+    }
+}
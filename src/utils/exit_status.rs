@@ -0,0 +1,199 @@
+//! Exit-code protocol between the overload and its loader
+//!
+//! The loader of a merged binary decides from our exit code whether the base
+//! runs, and a bare `exit(1)` cannot tell "server down" from "license
+//! revoked". Every outcome has its own code:
+//!
+//! | Code | Status            | Meaning                                          |
+//! |------|-------------------|--------------------------------------------------|
+//! | 0    | `authorized`      | License verified, the base may run               |
+//! | 10   | `unauthorized`    | The server denied the license (or it lapsed)     |
+//! | 20   | `network_failure` | Verification could not complete (network, server)|
+//! | 30   | `config_missing`  | No usable configuration                          |
+//! | 40   | `tamper_detected` | A security check failed (debugger, integrity...) |
+//! | 50   | `base_failed`     | The base binary could not be started             |
+//! | 60   | `internal_error`  | The overload failed internally                   |
+//! | 70   | `crashed`         | The overload panicked (EX_SOFTWARE)              |
+//! | 80   | `helper`          | A detached helper finished (never authorizes)    |
+//!
+//! Once a base we spawned has run, its own exit code is passed through,
+//! unless a restart is refused because the license no longer authorizes.
+//! Termination signals exit with 128 + signal number, and summary mode
+//! (`--quiet-summary`) keeps its own 0-3 codes for CI pipelines.
+//!
+//! A loader that wants more than a number sets `STATUS_FD_ENV` to a file
+//! descriptor (Windows: an inheritable handle) open for writing; one JSON
+//! line is written to it before exiting. Standard streams and descriptors
+//! that are not open are refused:
+//!
+//! ```json
+//! {"status":"unauthorized","exit_code":10,"message":"License revoked"}
+//! ```
+//!
+//! The first status recorded wins: enforcement that started over tamper keeps
+//! reporting tamper while the kill path exits.
+
+use serde::Serialize;
+use std::sync::OnceLock;
+
+use super::{redact, shutdown, summary};
+use super::summary::Outcome;
+
+/// Env var naming the descriptor the JSON status line is written to
+pub const STATUS_FD_ENV: &str = "KILLCODE_STATUS_FD";
+
+/// Exit code of the status recorded for this process
+static RECORDED: OnceLock<i32> = OnceLock::new();
+
+/// Outcome reported to the loader
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitStatus {
+    Authorized = 0,
+    Unauthorized = 10,
+    NetworkFailure = 20,
+    ConfigMissing = 30,
+    TamperDetected = 40,
+    BaseFailed = 50,
+    InternalError = 60,
+    Crashed = 70,
+    Helper = 80,
+}
+
+impl ExitStatus {
+    /// Exit code of this status
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+
+    /// Summary-mode outcome of this status
+    fn outcome(self) -> Outcome {
+        match self {
+            ExitStatus::Authorized => Outcome::Authorized,
+            ExitStatus::Unauthorized | ExitStatus::TamperDetected => Outcome::Unauthorized,
+            ExitStatus::ConfigMissing => Outcome::ConfigError,
+            ExitStatus::NetworkFailure
+            | ExitStatus::BaseFailed
+            | ExitStatus::InternalError
+            | ExitStatus::Crashed
+            | ExitStatus::Helper => Outcome::Error,
+        }
+    }
+}
+
+/// Status line written to `STATUS_FD_ENV`
+#[derive(Serialize)]
+struct StatusLine<'a> {
+    status: ExitStatus,
+    exit_code: i32,
+    message: &'a str,
+}
+
+/// Record `status` (unless one was recorded already) and exit with its code
+pub fn exit(status: ExitStatus, message: &str) -> ! {
+    shutdown::exit(record(status, message))
+}
+
+/// Record `status` and exit with `code`, the exit code of the base we ran
+pub fn pass_through(status: ExitStatus, code: i32, message: &str) -> ! {
+    shutdown::exit(record_code(status, code, message))
+}
+
+/// Record `status` for the exit that follows (first one wins)
+///
+/// # Returns
+/// The exit code of the status that was recorded first
+pub fn record(status: ExitStatus, message: &str) -> i32 {
+    let code = if summary::enabled() { status.outcome().exit_code() } else { status.code() };
+    record_code(status, code, message)
+}
+
+/// Record `status` with `code`, the exit code of the base we ran
+pub fn record_code(status: ExitStatus, code: i32, message: &str) -> i32 {
+    let mut first = false;
+    let &code = RECORDED.get_or_init(|| {
+        first = true;
+        code
+    });
+    if first {
+        write_status(&StatusLine { status, exit_code: code, message: &redact::scrub(message) });
+        // A daemonized overload: the loader waits on its original child
+        crate::security::lineage::report_status(code);
+    }
+    code
+}
+
+/// Write the status line to the loader's descriptor, if it gave one
+fn write_status(line: &StatusLine) {
+    let Some(fd) = std::env::var(STATUS_FD_ENV).ok().and_then(|fd| fd.trim().parse::<usize>().ok()) else {
+        return;
+    };
+    let Ok(mut json) = serde_json::to_string(line) else {
+        return;
+    };
+    json.push('\n');
+
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::fd::FromRawFd;
+        let Some(fd) = i32::try_from(fd).ok().filter(|&fd| fd > libc::STDERR_FILENO) else {
+            log_warn!("⚠️  {} must name a descriptor above 2, got {}", STATUS_FD_ENV, fd);
+            return;
+        };
+        if unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+            log_warn!("⚠️  {} names fd {}, which is not open", STATUS_FD_ENV, fd);
+            return;
+        }
+        // Borrowed: the loader owns the descriptor
+        let mut file = std::mem::ManuallyDrop::new(unsafe { std::fs::File::from_raw_fd(fd) });
+        if let Err(e) = file.write_all(json.as_bytes()) {
+            log_warn!("⚠️  Failed to write exit status to fd {}: {}", fd, e);
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        use winapi::um::fileapi::WriteFile;
+        let mut written = 0;
+        let ok = unsafe {
+            WriteFile(
+                fd as winapi::um::winnt::HANDLE,
+                json.as_ptr() as *const _,
+                json.len() as u32,
+                &mut written,
+                std::ptr::null_mut(),
+            )
+        };
+        if ok == 0 {
+            log_warn!("⚠️  Failed to write exit status to handle {}: {}", fd, std::io::Error::last_os_error());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_codes_and_line() {
+        let codes: Vec<_> = [
+            ExitStatus::Authorized,
+            ExitStatus::Unauthorized,
+            ExitStatus::NetworkFailure,
+            ExitStatus::ConfigMissing,
+            ExitStatus::TamperDetected,
+        ]
+        .iter()
+        .map(|status| status.code())
+        .collect();
+        assert_eq!(codes, [0, 10, 20, 30, 40]);
+        assert_eq!(ExitStatus::TamperDetected.outcome(), Outcome::Unauthorized);
+
+        let line = StatusLine { status: ExitStatus::NetworkFailure, exit_code: 20, message: "timed out" };
+        assert_eq!(
+            serde_json::to_string(&line).unwrap(),
+            r#"{"status":"network_failure","exit_code":20,"message":"timed out"}"#
+        );
+    }
+}
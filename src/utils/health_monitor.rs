@@ -1,29 +1,407 @@
-/// Shared memory health status communication with parent wrapper
+//! Shared memory health status communication with parent wrapper
+//!
+//! Current wrappers create a versioned `HealthBlock`: a magic number and
+//! layout version, the status fields, then a ring buffer of the last
+//! `HISTORY_LEN` check results so what killer observed can be reconstructed
+//! after an incident. Blocks without the magic use the pre-versioning
+//! `LegacyStatus` layout and are still accepted; a block carrying the magic
+//! with another version comes from a mismatched wrapper build and is refused
+//! rather than misread. Versions only ever append fields, and killer writes
+//! the layout the wrapper asked for, so wrappers built for an older version
+//! keep working; version 3 adds operator telemetry (`Telemetry`).
+//!
+//! Both processes touch the block concurrently, so every field is accessed
+//! atomically (Acquire loads, Release stores).
+//!
+//! Field ownership: the wrapper owns the header (`magic`, `version`),
+//! `parent_requests_kill` and `base_pid`. Killer owns the health data
+//! (`last_success`, `consecutive_failures`, `lease_expiring`, the history
+//! ring, telemetry) and
+//! zeroes it when it detaches - on drop, and in a shutdown hook on exit - so the last
+//! check results do not outlive it for other processes to read. The signals
+//! killer sends (`should_kill_base`, `kill_pending_until`) are left as they
+//! are: they are its last word to the wrapper. An exit clears `is_alive`. Only the process
+//! that attached with `new` scrubs; inspectors (`open`) leave the block alone.
+//!
+//! Where shared memory is unavailable (SELinux denials, sandboxes without
+//! `/dev/shm`) the wrapper can also name a file in `KILLCODE_HEALTH_FILE`,
+//! sized and laid out like the block. Killer memory-maps it if the shared
+//! memory object cannot be opened. Both processes then see the same mapping,
+//! so the block semantics above hold unchanged.
+
 use std::env;
 use std::ffi::CString;
+use std::cell::UnsafeCell;
+use std::path::Path;
 use std::ptr;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{fence, AtomicI32, AtomicI64, AtomicU32, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::config::KillMethod;
+use super::shutdown;
+
+/// Env var naming the wrapper's shared memory block
+pub const HEALTH_SHM_ENV: &str = "KILLCODE_HEALTH_SHM";
+
+/// Env var naming the file to map when shared memory is unavailable
+pub const HEALTH_FILE_ENV: &str = "KILLCODE_HEALTH_FILE";
+
+/// Check results kept in the shared history ring
+pub const HISTORY_LEN: usize = 32;
+
+/// First word of a versioned health block ("KCHM")
+pub const HEALTH_MAGIC: u32 = 0x4B43_484D;
+
+/// Newest layout version (the legacy layout counts as 1)
+pub const HEALTH_VERSION: u32 = 3;
+
+/// Oldest versioned layout still served
+pub const MIN_HEALTH_VERSION: u32 = 2;
 
+/// Bytes reserved for the overload version string (NUL-padded)
+const VERSION_LEN: usize = 32;
+
+/// Pre-versioning block, still created by older wrappers
 #[repr(C)]
-struct HealthStatus {
+struct LegacyStatus {
     last_success: i64,          // Timestamp of last successful check
     consecutive_failures: i32,   // Counter of network failures
     is_alive: i32,               // Heartbeat flag (1=alive, 0=dead)
     should_kill_base: i32,       // Signal to kill base (1=kill, 0=continue)
     parent_requests_kill: i32,   // Signal from parent: kill yourself now (1=kill, 0=continue)
     base_pid: i32,               // PID of the base process
+    kill_pending_until: i64,     // Unix time of a pending kill (0=none); later legacy wrappers only
+}
+
+/// Versioned block. 64-bit fields sit at 8-byte offsets so the layout is the
+/// same on 32-bit targets, where a plain `i64` may only be 4-aligned
+#[repr(C)]
+struct HealthBlock {
+    magic: AtomicU32,                // HEALTH_MAGIC, written by the wrapper
+    version: AtomicU32,              // HEALTH_VERSION, written by the wrapper
+    last_success: AtomicI64,
+    kill_pending_until: AtomicI64,
+    consecutive_failures: AtomicI32,
+    is_alive: AtomicI32,
+    should_kill_base: AtomicI32,
+    parent_requests_kill: AtomicI32,
+    base_pid: AtomicI32,
+    lease_expiring: AtomicI32,       // 1 = the license lease lapses within renewal_lead_secs (reserved, 0, before)
+    history: HistoryRing,
+}
+
+/// Version 3: the version 2 block followed by telemetry for the operator
+#[repr(C)]
+struct HealthBlockV3 {
+    block: HealthBlock,
+    telemetry: Telemetry,
+}
+
+/// Details of the latest check beyond pass/fail (version 3 blocks)
+#[repr(C)]
+struct Telemetry {
+    last_latency_ms: AtomicU32,      // Duration of the latest verification
+    last_http_status: AtomicU32,     // HTTP status of its response (0 = none)
+    license_expires_at: AtomicI64,   // Unix time the license lapses (0 = unknown)
+    kill_method: AtomicU32,          // Active kill method (0 = unknown, 1 = stop, 2 = delete, 3 = shred, 4 = corrupt, 5 = lock)
+    watchdog_stalls: AtomicU32,      // Verifications abandoned by the watchdog
+    overload_version: UnsafeCell<[u8; VERSION_LEN]>, // Killer's version, written once on attach
+}
+
+/// Telemetry as read back from a version 3 block
+#[derive(Debug, Clone, PartialEq)]
+pub struct TelemetrySnapshot {
+    pub last_latency_ms: u32,
+    pub last_http_status: Option<u16>,
+    pub license_expires_at: Option<i64>,
+    pub kill_method: Option<&'static str>,
+    pub overload_version: String,
+    pub watchdog_stalls: u32,
+}
+
+const KILL_METHODS: [KillMethod; 5] = [KillMethod::Stop, KillMethod::Delete, KillMethod::Shred, KillMethod::Corrupt, KillMethod::Lock];
+
+/// Outcome of one verification, as stored in the history ring
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum CheckOutcome {
+    Authorized = 1,
+    Unauthorized = 2,
+    Error = 3,
+    /// Abandoned by the verification watchdog
+    Stalled = 4,
+}
+
+/// One history entry (`outcome` 0 = unused slot)
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CheckRecord {
+    pub timestamp: i64,
+    pub latency_ms: u32,
+    /// HTTP status of the verification response (0 = no response)
+    pub http_status: u16,
+    pub outcome: u8,
+    _reserved: u8,
+}
+
+impl CheckRecord {
+    pub fn outcome(&self) -> Option<CheckOutcome> {
+        match self.outcome {
+            1 => Some(CheckOutcome::Authorized),
+            2 => Some(CheckOutcome::Unauthorized),
+            3 => Some(CheckOutcome::Error),
+            4 => Some(CheckOutcome::Stalled),
+            _ => None,
+        }
+    }
+}
+
+#[repr(C)]
+struct HistoryRing {
+    capacity: AtomicU32,         // Number of slots (set by killer)
+    _reserved: u32,
+    written: AtomicU64,          // Total records written; next slot is written % capacity
+    records: UnsafeCell<[CheckRecord; HISTORY_LEN]>,
+}
+
+impl HistoryRing {
+    /// Killer is the only writer; the wrapper reads `written` (Acquire) and
+    /// then the slots it covers
+    fn push(&self, record: CheckRecord) {
+        self.capacity.store(HISTORY_LEN as u32, Ordering::Relaxed);
+        let written = self.written.load(Ordering::Relaxed);
+        unsafe {
+            let slot = self.records.get().cast::<CheckRecord>().add((written % HISTORY_LEN as u64) as usize);
+            ptr::write_volatile(slot, record);
+        }
+        // Readers see the record before the advanced counter
+        self.written.store(written + 1, Ordering::Release);
+    }
+
+    /// Records oldest first
+    fn records(&self) -> Vec<CheckRecord> {
+        let written = self.written.load(Ordering::Acquire);
+        let start = written.saturating_sub(HISTORY_LEN as u64);
+        (start..written)
+            .map(|n| unsafe {
+                ptr::read_volatile(self.records.get().cast::<CheckRecord>().add((n % HISTORY_LEN as u64) as usize))
+            })
+            .collect()
+    }
+}
+
+/// Block attached by this process as its owner: (start address, layout),
+/// scrubbed at exit unless the monitor was dropped first
+static OWNED: Mutex<Option<(usize, Layout)>> = Mutex::new(None);
+
+/// How a mapped block is laid out
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Layout {
+    /// No header; `extended` when the block reaches `kill_pending_until`
+    Legacy { extended: bool },
+    /// Header version, between MIN_HEALTH_VERSION and HEALTH_VERSION
+    Versioned(u32),
+}
+
+/// Bytes a versioned block of `version` spans
+fn versioned_size(version: u32) -> usize {
+    if version >= 3 {
+        std::mem::size_of::<HealthBlockV3>()
+    } else {
+        std::mem::size_of::<HealthBlock>()
+    }
+}
+
+/// Decide how to read a block of `size` bytes (None = unknown) whose first
+/// two words are `magic` and `version`. A legacy block starts with
+/// `last_success`, which never reads as the magic for a real timestamp
+fn classify(size: Option<usize>, magic: u32, version: u32) -> Result<Layout, String> {
+    if magic != HEALTH_MAGIC {
+        let extended = size.is_none_or(|size| size >= std::mem::size_of::<LegacyStatus>());
+        return Ok(Layout::Legacy { extended });
+    }
+    if !(MIN_HEALTH_VERSION..=HEALTH_VERSION).contains(&version) {
+        return Err(format!(
+            "Health block version {} is not supported (expected {}-{}): wrapper and killer builds do not match",
+            version, MIN_HEALTH_VERSION, HEALTH_VERSION
+        ));
+    }
+    if let Some(size) = size
+        && size < versioned_size(version)
+    {
+        return Err(format!(
+            "Health block is {} bytes, version {} needs {}",
+            size,
+            version,
+            versioned_size(version)
+        ));
+    }
+    Ok(Layout::Versioned(version))
+}
+
+/// A 64-bit field: atomic where aligned for it, volatile plus fences
+/// otherwise (`kill_pending_until` in the legacy layout on 32-bit x86)
+#[derive(Clone, Copy)]
+struct Field64(*mut i64);
+
+impl Field64 {
+    unsafe fn load(self) -> i64 {
+        unsafe {
+            if self.0.align_offset(std::mem::align_of::<AtomicI64>()) == 0 {
+                AtomicI64::from_ptr(self.0).load(Ordering::Acquire)
+            } else {
+                let value = ptr::read_volatile(self.0);
+                fence(Ordering::Acquire);
+                value
+            }
+        }
+    }
+
+    unsafe fn store(self, value: i64) {
+        unsafe {
+            if self.0.align_offset(std::mem::align_of::<AtomicI64>()) == 0 {
+                AtomicI64::from_ptr(self.0).store(value, Ordering::Release);
+            } else {
+                fence(Ordering::Release);
+                ptr::write_volatile(self.0, value);
+            }
+        }
+    }
+}
+
+/// Where each status field lives in the mapped block
+struct Fields {
+    last_success: Field64,
+    consecutive_failures: *const AtomicI32,
+    is_alive: *const AtomicI32,
+    should_kill_base: *const AtomicI32,
+    parent_requests_kill: *const AtomicI32,
+    base_pid: *const AtomicI32,
+    /// None for legacy blocks that end at `base_pid`
+    kill_pending_until: Option<Field64>,
+    /// Versioned blocks only
+    history: Option<*const HistoryRing>,
+    lease_expiring: Option<*const AtomicI32>,
+    /// Version 3 and later
+    telemetry: Option<*const Telemetry>,
+}
+
+impl Fields {
+    unsafe fn new(base: *mut u8, layout: &Layout) -> Self {
+        unsafe {
+            match *layout {
+                Layout::Legacy { extended } => {
+                    let status = base.cast::<LegacyStatus>();
+                    Self {
+                        last_success: Field64(ptr::addr_of_mut!((*status).last_success)),
+                        consecutive_failures: ptr::addr_of_mut!((*status).consecutive_failures).cast(),
+                        is_alive: ptr::addr_of_mut!((*status).is_alive).cast(),
+                        should_kill_base: ptr::addr_of_mut!((*status).should_kill_base).cast(),
+                        parent_requests_kill: ptr::addr_of_mut!((*status).parent_requests_kill).cast(),
+                        base_pid: ptr::addr_of_mut!((*status).base_pid).cast(),
+                        kill_pending_until: extended
+                            .then(|| Field64(ptr::addr_of_mut!((*status).kill_pending_until))),
+                        history: None,
+                        lease_expiring: None,
+                        telemetry: None,
+                    }
+                }
+                Layout::Versioned(version) => {
+                    let block = base.cast::<HealthBlock>();
+                    Self {
+                        last_success: Field64(ptr::addr_of_mut!((*block).last_success).cast()),
+                        consecutive_failures: ptr::addr_of!((*block).consecutive_failures),
+                        is_alive: ptr::addr_of!((*block).is_alive),
+                        should_kill_base: ptr::addr_of!((*block).should_kill_base),
+                        parent_requests_kill: ptr::addr_of!((*block).parent_requests_kill),
+                        base_pid: ptr::addr_of!((*block).base_pid),
+                        kill_pending_until: Some(Field64(ptr::addr_of_mut!((*block).kill_pending_until).cast())),
+                        history: Some(ptr::addr_of!((*block).history)),
+                        lease_expiring: Some(ptr::addr_of!((*block).lease_expiring)),
+                        telemetry: (version >= 3)
+                            .then(|| ptr::addr_of!((*base.cast::<HealthBlockV3>()).telemetry)),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Zero the health data killer owns (see the module docs)
+    unsafe fn scrub(&self) {
+        unsafe {
+            self.last_success.store(0);
+            (*self.consecutive_failures).store(0, Ordering::Release);
+            if let Some(lease_expiring) = self.lease_expiring {
+                (*lease_expiring).store(0, Ordering::Release);
+            }
+            if let Some(history) = self.history {
+                (*history).written.store(0, Ordering::Release);
+                (*history).capacity.store(0, Ordering::Release);
+                ptr::write_volatile((*history).records.get(), [CheckRecord::default(); HISTORY_LEN]);
+            }
+            if let Some(telemetry) = self.telemetry {
+                let telemetry = &*telemetry;
+                telemetry.last_latency_ms.store(0, Ordering::Release);
+                telemetry.last_http_status.store(0, Ordering::Release);
+                telemetry.license_expires_at.store(0, Ordering::Release);
+                telemetry.kill_method.store(0, Ordering::Release);
+                telemetry.watchdog_stalls.store(0, Ordering::Release);
+                ptr::write_volatile(telemetry.overload_version.get(), [0u8; VERSION_LEN]);
+            }
+        }
+    }
 }
 
 pub struct HealthMonitor {
-    shm_ptr: *mut HealthStatus,
+    /// Start of the mapping (unmapped on drop)
+    base: *mut u8,
+    fields: Fields,
+    /// 1 for legacy blocks, otherwise the header version
+    version: u32,
+    /// How the fields were located
+    layout: Layout,
+    /// Attached by `new`: killer's own block, scrubbed on detach
+    owner: bool,
 }
 
 impl HealthMonitor {
-    /// Open shared memory if KILLCODE_HEALTH_SHM env var is set
+    /// Open the wrapper's block (`KILLCODE_HEALTH_SHM`, else
+    /// `KILLCODE_HEALTH_FILE`), announcing this overload's version to the
+    /// wrapper
     pub fn new() -> Option<Self> {
-        let shm_name = env::var("KILLCODE_HEALTH_SHM").ok()?;
-        
-        eprintln!("📊 Opening health monitor: {}", shm_name);
+        let mut monitor = Self::open_from_env()?;
+        monitor.owner = true;
+        *OWNED.lock().unwrap_or_else(|e| e.into_inner()) = Some((monitor.base as usize, monitor.layout));
+        shutdown::register("health_scrub", scrub_at_exit);
+        if let Some(telemetry) = monitor.fields.telemetry {
+            let mut overload_version = [0u8; VERSION_LEN];
+            let own = env!("CARGO_PKG_VERSION").as_bytes();
+            let len = own.len().min(VERSION_LEN - 1);
+            overload_version[..len].copy_from_slice(&own[..len]);
+            unsafe {
+                ptr::write_volatile((*telemetry).overload_version.get(), overload_version);
+            }
+        }
+        Some(monitor)
+    }
+
+    /// Open the wrapper's block named in the environment, without owning it
+    pub fn open_from_env() -> Option<Self> {
+        let shm_name = env::var(HEALTH_SHM_ENV).ok();
+        if let Some(monitor) = shm_name.as_deref().and_then(Self::open) {
+            return Some(monitor);
+        }
+        let path = env::var_os(HEALTH_FILE_ENV)?;
+        if shm_name.is_some() {
+            log_warn!("⚠️  Shared memory unavailable - falling back to the health file");
+        }
+        Self::open_file(Path::new(&path))
+    }
+
+    /// Open the named shared memory block created by a wrapper
+    pub fn open(shm_name: &str) -> Option<Self> {
+        log_info!("📊 Opening health monitor: {}", shm_name);
         
         #[cfg(unix)]
         unsafe {
@@ -37,14 +415,19 @@ impl HealthMonitor {
             );
             
             if shm_fd < 0 {
-                eprintln!("⚠️  Failed to open shared memory: {}", std::io::Error::last_os_error());
+                log_warn!("⚠️  Failed to open shared memory: {}", std::io::Error::last_os_error());
                 return None;
             }
             
+            // Older wrappers create smaller blocks; the mapping below is still
+            // within their first page, but only the fields they created are used
+            let mut stat: libc::stat = std::mem::zeroed();
+            let size = if libc::fstat(shm_fd, &mut stat) == 0 { stat.st_size as usize } else { 0 };
+            
             // Map shared memory
             let shm_ptr = libc::mmap(
                 ptr::null_mut(),
-                std::mem::size_of::<HealthStatus>(),
+                std::mem::size_of::<HealthBlockV3>(),
                 libc::PROT_READ | libc::PROT_WRITE,
                 libc::MAP_SHARED,
                 shm_fd,
@@ -54,20 +437,20 @@ impl HealthMonitor {
             libc::close(shm_fd);
             
             if shm_ptr == libc::MAP_FAILED {
-                eprintln!("⚠️  Failed to map shared memory: {}", std::io::Error::last_os_error());
+                log_warn!("⚠️  Failed to map shared memory: {}", std::io::Error::last_os_error());
                 return None;
             }
             
-            eprintln!("✅ Health monitor initialized");
-            
-            Some(Self {
-                shm_ptr: shm_ptr as *mut HealthStatus,
-            })
+            let monitor = Self::attach(shm_ptr.cast(), Some(size));
+            if monitor.is_none() {
+                libc::munmap(shm_ptr, std::mem::size_of::<HealthBlockV3>());
+            }
+            monitor
         }
 
         #[cfg(windows)]
         unsafe {
-            use winapi::um::memoryapi::{MapViewOfFile, FILE_MAP_ALL_ACCESS};
+            use winapi::um::memoryapi::{MapViewOfFile, UnmapViewOfFile, FILE_MAP_ALL_ACCESS};
             use winapi::um::handleapi::CloseHandle;
             use winapi::um::winbase::OpenFileMappingA;
 
@@ -80,115 +463,534 @@ impl HealthMonitor {
             );
 
             if handle.is_null() {
-                 eprintln!("⚠️  Failed to open shared memory: {}", std::io::Error::last_os_error());
+                 log_warn!("⚠️  Failed to open shared memory: {}", std::io::Error::last_os_error());
                  return None;
             }
 
+            // Map the whole object: older wrappers create only the legacy block
+            // size, and a view larger than the mapping object would fail. Views
+            // are page-granular, so every field of either layout is addressable
             let shm_ptr = MapViewOfFile(
                 handle,
                 FILE_MAP_ALL_ACCESS,
                 0,
                 0,
-                std::mem::size_of::<HealthStatus>(),
+                0,
             );
 
             CloseHandle(handle); // We can close the handle after mapping
 
             if shm_ptr.is_null() {
-                 eprintln!("⚠️  Failed to map shared memory: {}", std::io::Error::last_os_error());
+                 log_warn!("⚠️  Failed to map shared memory: {}", std::io::Error::last_os_error());
                  return None;
             }
 
-            eprintln!("✅ Health monitor initialized");
+            let monitor = Self::attach(shm_ptr.cast(), None);
+            if monitor.is_none() {
+                UnmapViewOfFile(shm_ptr);
+            }
+            monitor
+        }
+    }
+
+    /// Open a health block file created by a wrapper (memory-mapped, shared
+    /// with the wrapper like the shared memory block)
+    pub fn open_file(path: &Path) -> Option<Self> {
+        log_info!("📊 Opening health file: {}", path.display());
+
+        let file = match std::fs::OpenOptions::new().read(true).write(true).open(path) {
+            Ok(file) => file,
+            Err(e) => {
+                log_warn!("⚠️  Failed to open health file: {}", e);
+                return None;
+            }
+        };
+        let size = file.metadata().map(|metadata| metadata.len() as usize).unwrap_or(0);
+        // An empty file cannot be mapped, and holds no header to check
+        if size == 0 {
+            log_warn!("⚠️  Health file is empty - the wrapper has not initialized it");
+            return None;
+        }
+
+        #[cfg(unix)]
+        unsafe {
+            use std::os::fd::AsRawFd;
+
+            // Like a small shared memory block: the mapping stays within the
+            // file's first page
+            let file_ptr = libc::mmap(
+                ptr::null_mut(),
+                std::mem::size_of::<HealthBlockV3>(),
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            );
+            if file_ptr == libc::MAP_FAILED {
+                log_warn!("⚠️  Failed to map health file: {}", std::io::Error::last_os_error());
+                return None;
+            }
+
+            let monitor = Self::attach(file_ptr.cast(), Some(size));
+            if monitor.is_none() {
+                libc::munmap(file_ptr, std::mem::size_of::<HealthBlockV3>());
+            }
+            monitor
+        }
+
+        #[cfg(windows)]
+        unsafe {
+            use std::os::windows::io::AsRawHandle;
+            use winapi::um::handleapi::CloseHandle;
+            use winapi::um::memoryapi::{CreateFileMappingW, MapViewOfFile, UnmapViewOfFile, FILE_MAP_ALL_ACCESS};
+            use winapi::um::winnt::PAGE_READWRITE;
+
+            // Size 0: the mapping object spans the file as it is
+            let handle = CreateFileMappingW(file.as_raw_handle().cast(), ptr::null_mut(), PAGE_READWRITE, 0, 0, ptr::null());
+            if handle.is_null() {
+                log_warn!("⚠️  Failed to map health file: {}", std::io::Error::last_os_error());
+                return None;
+            }
+            let file_ptr = MapViewOfFile(handle, FILE_MAP_ALL_ACCESS, 0, 0, 0);
+            CloseHandle(handle);
+            if file_ptr.is_null() {
+                log_warn!("⚠️  Failed to map health file: {}", std::io::Error::last_os_error());
+                return None;
+            }
+
+            let monitor = Self::attach(file_ptr.cast(), Some(size));
+            if monitor.is_none() {
+                UnmapViewOfFile(file_ptr);
+            }
+            monitor
+        }
+    }
+
+    /// Check the header of a freshly mapped block and locate its fields
+    unsafe fn attach(base: *mut u8, size: Option<usize>) -> Option<Self> {
+        unsafe {
+            let magic = (*base.cast::<AtomicU32>()).load(Ordering::Acquire);
+            let version = (*base.cast::<AtomicU32>().add(1)).load(Ordering::Acquire);
+            let layout = match classify(size, magic, version) {
+                Ok(layout) => layout,
+                Err(e) => {
+                    log_error!("❌ {}", e);
+                    return None;
+                }
+            };
+
+            let version = match layout {
+                Layout::Legacy { .. } => {
+                    log_debug!("🔍 Legacy health block (no version header)");
+                    1
+                }
+                Layout::Versioned(version) => version,
+            };
+            log_info!("✅ Health monitor initialized");
 
             Some(Self {
-                shm_ptr: shm_ptr as *mut HealthStatus,
+                base,
+                fields: Fields::new(base, &layout),
+                version,
+                layout,
+                owner: false,
             })
         }
     }
-    
+
+    /// Layout version of the wrapper's block (1 = legacy, no header)
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
     /// Update health status after verification attempt
     pub fn update(&self, success: bool) {
         unsafe {
-            if self.shm_ptr.is_null() {
-                return;
-            }
-            
-            let now = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs() as i64;
+            let now = super::time::unix_now();
             
             if success {
-                (*self.shm_ptr).consecutive_failures = 0;
-                (*self.shm_ptr).last_success = now;
-                eprintln!("✅ Health update: verification successful");
+                (*self.fields.consecutive_failures).store(0, Ordering::Release);
+                self.fields.last_success.store(now);
+                log_info!("✅ Health update: verification successful");
             } else {
-                (*self.shm_ptr).consecutive_failures += 1;
-                eprintln!("⚠️  Health update: verification failed (consecutive: {})", 
-                         (*self.shm_ptr).consecutive_failures);
+                let failures = (*self.fields.consecutive_failures).fetch_add(1, Ordering::AcqRel) + 1;
+                log_warn!("⚠️  Health update: verification failed (consecutive: {})", failures);
             }
             
             // Update heartbeat
-            (*self.shm_ptr).is_alive = 1;
+            (*self.fields.is_alive).store(1, Ordering::Release);
         }
     }
     
+    /// Append a verification result to the shared history ring and telemetry
+    pub fn record_check(&self, outcome: CheckOutcome, latency: Duration, http_status: Option<u16>) {
+        let latency_ms = latency.as_millis().min(u32::MAX as u128) as u32;
+        if let Some(telemetry) = self.fields.telemetry {
+            unsafe {
+                (*telemetry).last_latency_ms.store(latency_ms, Ordering::Release);
+                (*telemetry).last_http_status.store(http_status.unwrap_or(0) as u32, Ordering::Release);
+            }
+        }
+
+        let Some(history) = self.fields.history else {
+            return;
+        };
+        let record = CheckRecord {
+            timestamp: super::time::unix_now(),
+            latency_ms,
+            http_status: http_status.unwrap_or(0),
+            outcome: outcome as u8,
+            _reserved: 0,
+        };
+        unsafe {
+            (*history).push(record);
+        }
+    }
+
+    /// Count a verification abandoned by the watchdog
+    pub fn record_stall(&self) {
+        if let Some(telemetry) = self.fields.telemetry {
+            unsafe {
+                (*telemetry).watchdog_stalls.fetch_add(1, Ordering::AcqRel);
+            }
+        }
+    }
+
+    /// Publish when the license lapses (None = unknown)
+    pub fn set_license_expiry(&self, expires_at: Option<i64>) {
+        if let Some(telemetry) = self.fields.telemetry {
+            unsafe {
+                (*telemetry).license_expires_at.store(expires_at.unwrap_or(0), Ordering::Release);
+            }
+        }
+    }
+
+    /// Publish whether the license lease lapses soon (versioned blocks)
+    pub fn set_lease_expiring(&self, expiring: bool) {
+        if let Some(lease_expiring) = self.fields.lease_expiring {
+            unsafe {
+                (*lease_expiring).store(expiring as i32, Ordering::Release);
+            }
+        }
+    }
+
+    /// Whether the license lease lapses soon (None for legacy blocks)
+    pub fn lease_expiring(&self) -> Option<bool> {
+        let lease_expiring = self.fields.lease_expiring?;
+        unsafe { Some((*lease_expiring).load(Ordering::Acquire) == 1) }
+    }
+
+    /// Publish the kill method currently in force
+    pub fn set_kill_method(&self, method: &KillMethod) {
+        if let Some(telemetry) = self.fields.telemetry {
+            let code = KILL_METHODS.iter().position(|m| m == method).map_or(0, |i| i as u32 + 1);
+            unsafe {
+                (*telemetry).kill_method.store(code, Ordering::Release);
+            }
+        }
+    }
+
+    /// Telemetry (None if the block predates version 3)
+    pub fn telemetry(&self) -> Option<TelemetrySnapshot> {
+        let telemetry = self.fields.telemetry?;
+        unsafe {
+            let telemetry = &*telemetry;
+            let version = ptr::read_volatile(telemetry.overload_version.get());
+            let len = version.iter().position(|&b| b == 0).unwrap_or(VERSION_LEN);
+            Some(TelemetrySnapshot {
+                last_latency_ms: telemetry.last_latency_ms.load(Ordering::Acquire),
+                last_http_status: match telemetry.last_http_status.load(Ordering::Acquire) {
+                    0 => None,
+                    status => Some(status.min(u16::MAX as u32) as u16),
+                },
+                license_expires_at: match telemetry.license_expires_at.load(Ordering::Acquire) {
+                    0 => None,
+                    at => Some(at),
+                },
+                kill_method: match telemetry.kill_method.load(Ordering::Acquire) {
+                    code @ 1..=5 => Some(KILL_METHODS[code as usize - 1].as_str()),
+                    _ => None,
+                },
+                overload_version: String::from_utf8_lossy(&version[..len]).into_owned(),
+                watchdog_stalls: telemetry.watchdog_stalls.load(Ordering::Acquire),
+            })
+        }
+    }
+
+    /// Check history, oldest first (None if the block has no history ring)
+    pub fn history(&self) -> Option<Vec<CheckRecord>> {
+        let history = self.fields.history?;
+        unsafe { Some((*history).records()) }
+    }
+
+    /// Current counters: (last_success, consecutive_failures, is_alive)
+    pub fn counters(&self) -> (i64, i32, i32) {
+        unsafe {
+            (
+                self.fields.last_success.load(),
+                (*self.fields.consecutive_failures).load(Ordering::Acquire),
+                (*self.fields.is_alive).load(Ordering::Acquire),
+            )
+        }
+    }
+
     /// Signal parent to kill base binary
     pub fn request_kill_base(&self) {
         unsafe {
-            if !self.shm_ptr.is_null() {
-                (*self.shm_ptr).should_kill_base = 1;
-                eprintln!("🚨 Signaled parent to kill base binary");
-            }
+            (*self.fields.should_kill_base).store(1, Ordering::Release);
         }
+        log_error!("🚨 Signaled parent to kill base binary");
     }
     
     /// Update heartbeat to show we're still alive
     pub fn heartbeat(&self) {
         unsafe {
-            if !self.shm_ptr.is_null() {
-                (*self.shm_ptr).is_alive = 1;
-            }
+            (*self.fields.is_alive).store(1, Ordering::Release);
         }
     }
     
     /// Check if parent has requested us to kill ourselves
     pub fn is_kill_requested(&self) -> bool {
-        unsafe {
-            if self.shm_ptr.is_null() {
-                return false;
+        unsafe { (*self.fields.parent_requests_kill).load(Ordering::Acquire) == 1 }
+    }
+
+    /// Announce (Some(unix time)) or clear (None) a pending kill to the wrapper
+    pub fn set_kill_pending(&self, until: Option<i64>) {
+        if let Some(field) = self.fields.kill_pending_until {
+            unsafe {
+                field.store(until.unwrap_or(0));
             }
-            (*self.shm_ptr).parent_requests_kill == 1
         }
     }
 
     /// Get the base PID if it's valid
     pub fn get_base_pid(&self) -> Option<i32> {
-        unsafe {
-            let pid = (*self.shm_ptr).base_pid;
-            if pid > 0 {
-                Some(pid)
+        let pid = unsafe { (*self.fields.base_pid).load(Ordering::Acquire) };
+        if pid > 0 {
+            Some(pid)
+        } else {
+            None
+        }
+    }
+}
+
+/// Create, map and remove a scratch block like the one a wrapper shares
+/// (`killer doctor`)
+pub fn probe_shared_memory() -> Result<(), String> {
+    let size = std::mem::size_of::<HealthBlockV3>();
+    let name = CString::new(format!("/kc_doctor_{}", std::process::id())).map_err(|e| e.to_string())?;
+
+    #[cfg(unix)]
+    unsafe {
+        let fd = libc::shm_open(name.as_ptr(), libc::O_CREAT | libc::O_EXCL | libc::O_RDWR, 0o600);
+        if fd < 0 {
+            return Err(format!("shm_open failed: {}", std::io::Error::last_os_error()));
+        }
+        let result = if libc::ftruncate(fd, size as libc::off_t) != 0 {
+            Err(format!("ftruncate failed: {}", std::io::Error::last_os_error()))
+        } else {
+            let ptr = libc::mmap(ptr::null_mut(), size, libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED, fd, 0);
+            if ptr == libc::MAP_FAILED {
+                Err(format!("mmap failed: {}", std::io::Error::last_os_error()))
             } else {
-                None
+                libc::munmap(ptr, size);
+                Ok(())
             }
+        };
+        libc::close(fd);
+        libc::shm_unlink(name.as_ptr());
+        result
+    }
+
+    #[cfg(windows)]
+    unsafe {
+        use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+        use winapi::um::memoryapi::{MapViewOfFile, UnmapViewOfFile, FILE_MAP_ALL_ACCESS};
+        use winapi::um::winbase::CreateFileMappingA;
+        use winapi::um::winnt::PAGE_READWRITE;
+
+        let handle = CreateFileMappingA(INVALID_HANDLE_VALUE, ptr::null_mut(), PAGE_READWRITE, 0, size as u32, name.as_ptr());
+        if handle.is_null() {
+            return Err(format!("CreateFileMapping failed: {}", std::io::Error::last_os_error()));
+        }
+        let view = MapViewOfFile(handle, FILE_MAP_ALL_ACCESS, 0, 0, size);
+        let result = if view.is_null() {
+            Err(format!("MapViewOfFile failed: {}", std::io::Error::last_os_error()))
+        } else {
+            UnmapViewOfFile(view);
+            Ok(())
+        };
+        CloseHandle(handle);
+        result
+    }
+}
+
+/// `process::exit` skips destructors; scrub the owned block on the way out
+fn scrub_at_exit() {
+    // try_lock: never block an exiting process
+    let owned = OWNED.try_lock().ok().and_then(|mut owned| owned.take());
+    if let Some((base, layout)) = owned {
+        let fields = unsafe { Fields::new(base as *mut u8, &layout) };
+        unsafe {
+            fields.scrub();
+            // Final state: the wrapper must not wait for another heartbeat
+            (*fields.is_alive).store(0, Ordering::Release);
         }
     }
 }
 
 impl Drop for HealthMonitor {
     fn drop(&mut self) {
+        if self.owner {
+            // Unmapped below: the exit hook must not touch it any more
+            OWNED.lock().unwrap_or_else(|e| e.into_inner()).take();
+            unsafe { self.fields.scrub() };
+        }
         unsafe {
-            if !self.shm_ptr.is_null() {
-                #[cfg(unix)]
-                libc::munmap(
-                    self.shm_ptr as *mut libc::c_void,
-                    std::mem::size_of::<HealthStatus>(),
-                );
+            #[cfg(unix)]
+            libc::munmap(
+                self.base as *mut libc::c_void,
+                std::mem::size_of::<HealthBlockV3>(),
+            );
 
-                #[cfg(windows)]
-                winapi::um::memoryapi::UnmapViewOfFile(self.shm_ptr as *const _);
-            }
+            #[cfg(windows)]
+            winapi::um::memoryapi::UnmapViewOfFile(self.base as *const _);
         }
     }
 }
+
+/// Whether a parent wrapper handed us a health block
+pub fn under_wrapper() -> bool {
+    env::var_os(HEALTH_SHM_ENV).is_some() || env::var_os(HEALTH_FILE_ENV).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(n: i64) -> CheckRecord {
+        CheckRecord { timestamp: n, outcome: CheckOutcome::Authorized as u8, ..Default::default() }
+    }
+
+    #[test]
+    fn test_history_ring_wraps_oldest_first() {
+        let ring = HistoryRing {
+            capacity: AtomicU32::new(0),
+            _reserved: 0,
+            written: AtomicU64::new(0),
+            records: UnsafeCell::new([CheckRecord::default(); HISTORY_LEN]),
+        };
+        assert!(ring.records().is_empty());
+
+        for n in 0..(HISTORY_LEN as i64 + 3) {
+            ring.push(record(n));
+        }
+
+        let records = ring.records();
+        assert_eq!(ring.capacity.load(Ordering::Relaxed), HISTORY_LEN as u32);
+        assert_eq!(records.len(), HISTORY_LEN);
+        assert_eq!(records[0].timestamp, 3);
+        assert_eq!(records.last().unwrap().timestamp, HISTORY_LEN as i64 + 2);
+        assert_eq!(records[0].outcome(), Some(CheckOutcome::Authorized));
+    }
+
+    #[test]
+    fn test_scrub_clears_only_killer_owned_fields() {
+        let mut block = vec![0u64; std::mem::size_of::<HealthBlockV3>().div_ceil(8)];
+        let base = block.as_mut_ptr().cast::<u8>();
+        unsafe {
+            let fields = Fields::new(base, &Layout::Versioned(3));
+            (*base.cast::<AtomicU32>()).store(HEALTH_MAGIC, Ordering::Release);
+            (*fields.base_pid).store(4242, Ordering::Release);
+            (*fields.should_kill_base).store(1, Ordering::Release);
+            fields.last_success.store(1_700_000_000);
+            (*fields.consecutive_failures).store(3, Ordering::Release);
+            (*fields.lease_expiring.unwrap()).store(1, Ordering::Release);
+            (*fields.history.unwrap()).push(record(1_700_000_000));
+            (*fields.telemetry.unwrap()).last_http_status.store(200, Ordering::Release);
+            ptr::write_volatile((*fields.telemetry.unwrap()).overload_version.get(), [b'1'; VERSION_LEN]);
+
+            fields.scrub();
+
+            assert_eq!(fields.last_success.load(), 0);
+            assert_eq!((*fields.consecutive_failures).load(Ordering::Acquire), 0);
+            assert_eq!((*fields.lease_expiring.unwrap()).load(Ordering::Acquire), 0);
+            assert!((*fields.history.unwrap()).records().is_empty());
+            assert_eq!(ptr::read_volatile((*fields.history.unwrap()).records.get())[0], CheckRecord::default());
+            assert_eq!((*fields.telemetry.unwrap()).last_http_status.load(Ordering::Acquire), 0);
+            assert_eq!(ptr::read_volatile((*fields.telemetry.unwrap()).overload_version.get()), [0; VERSION_LEN]);
+
+            // Wrapper-owned fields and killer's signals survive
+            assert_eq!((*base.cast::<AtomicU32>()).load(Ordering::Acquire), HEALTH_MAGIC);
+            assert_eq!((*fields.base_pid).load(Ordering::Acquire), 4242);
+            assert_eq!((*fields.should_kill_base).load(Ordering::Acquire), 1);
+        }
+    }
+
+    #[test]
+    fn test_versioned_layout_is_fixed() {
+        // Wrappers hard-code these offsets; they must not move on any target
+        assert_eq!(std::mem::offset_of!(HealthBlock, last_success), 8);
+        assert_eq!(std::mem::offset_of!(HealthBlock, kill_pending_until), 16);
+        assert_eq!(std::mem::offset_of!(HealthBlock, base_pid), 40);
+        assert_eq!(std::mem::offset_of!(HealthBlock, lease_expiring), 44);
+        assert_eq!(std::mem::offset_of!(HealthBlock, history), 48);
+        assert_eq!(std::mem::size_of::<HealthBlock>(), 64 + HISTORY_LEN * 16);
+
+        let telemetry = std::mem::offset_of!(HealthBlockV3, telemetry);
+        assert_eq!(telemetry, std::mem::size_of::<HealthBlock>());
+        assert_eq!(std::mem::offset_of!(Telemetry, license_expires_at), 8);
+        assert_eq!(std::mem::offset_of!(Telemetry, kill_method), 16);
+        assert_eq!(std::mem::offset_of!(Telemetry, watchdog_stalls), 20);
+        assert_eq!(std::mem::offset_of!(Telemetry, overload_version), 24);
+        assert_eq!(std::mem::size_of::<HealthBlockV3>(), telemetry + 24 + VERSION_LEN);
+    }
+
+    #[test]
+    fn test_classify_detects_mismatched_builds() {
+        let full = std::mem::size_of::<HealthBlockV3>();
+        let v2 = std::mem::size_of::<HealthBlock>();
+        assert_eq!(classify(Some(full), HEALTH_MAGIC, HEALTH_VERSION), Ok(Layout::Versioned(HEALTH_VERSION)));
+        assert_eq!(classify(None, HEALTH_MAGIC, HEALTH_VERSION), Ok(Layout::Versioned(HEALTH_VERSION)));
+        assert!(classify(Some(full), HEALTH_MAGIC, HEALTH_VERSION + 1).unwrap_err().contains("not supported"));
+        assert!(classify(Some(full), HEALTH_MAGIC, 1).unwrap_err().contains("not supported"));
+        assert!(classify(Some(32), HEALTH_MAGIC, HEALTH_VERSION).is_err());
+
+        // Older versioned wrappers keep working with their smaller block
+        assert_eq!(classify(Some(v2), HEALTH_MAGIC, 2), Ok(Layout::Versioned(2)));
+        assert!(classify(Some(v2), HEALTH_MAGIC, 3).is_err());
+
+        // No magic: a legacy block, whose first word is the low half of last_success
+        assert_eq!(classify(Some(28), 0, 0), Ok(Layout::Legacy { extended: false }));
+        assert_eq!(
+            classify(Some(std::mem::size_of::<LegacyStatus>()), 1_700_000_000, 0),
+            Ok(Layout::Legacy { extended: true })
+        );
+    }
+
+    #[test]
+    fn test_health_file_shared_like_shm() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("health");
+        let mut block = vec![0u8; std::mem::size_of::<HealthBlockV3>()];
+        block[..4].copy_from_slice(&HEALTH_MAGIC.to_ne_bytes());
+        block[4..8].copy_from_slice(&HEALTH_VERSION.to_ne_bytes());
+        std::fs::write(&path, &block).unwrap();
+
+        let killer = HealthMonitor::open_file(&path).unwrap();
+        let wrapper = HealthMonitor::open_file(&path).unwrap();
+        assert_eq!(killer.version(), HEALTH_VERSION);
+        killer.update(false);
+        assert_eq!(wrapper.counters(), (0, 1, 1));
+        killer.set_lease_expiring(true);
+        assert_eq!(wrapper.lease_expiring(), Some(true));
+
+        // The wrapper's kill request reaches killer through the file
+        unsafe { (*wrapper.fields.parent_requests_kill).store(1, Ordering::Release) };
+        assert!(killer.is_kill_requested());
+
+        // Uninitialized files are refused
+        std::fs::write(dir.path().join("empty"), b"").unwrap();
+        assert!(HealthMonitor::open_file(&dir.path().join("empty")).is_none());
+    }
+}
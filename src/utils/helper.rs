@@ -0,0 +1,73 @@
+//! Authenticated jobs for detached helper processes
+//!
+//! Some work has to outlive the process that starts it (wiping a binary after
+//! it exited, finishing a shred, reporting usage). The helper is the overload
+//! binary itself, started with an env var naming the job. Anyone can set an
+//! env var, so the env var only carries a random token: the job itself is
+//! written by the spawning process to a 0600 file in the state directory,
+//! named after the token's hash. The helper redeems the token once; without
+//! a matching file written by the same user it does nothing.
+
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+use super::{secure_fs, state};
+
+/// Write `payload` for a `kind` helper
+///
+/// # Returns
+/// The token to hand to the helper (in its env var)
+pub fn issue(kind: &str, payload: &str) -> Result<String, String> {
+    issue_in(&jobs_dir(), kind, payload)
+}
+
+/// Take the payload of a `kind` job issued with `token` (once)
+pub fn redeem(kind: &str, token: &str) -> Result<String, String> {
+    redeem_in(&jobs_dir(), kind, token)
+}
+
+/// Helper jobs live outside license namespaces: a self-deleting binary's
+/// helper runs without a config
+fn jobs_dir() -> PathBuf {
+    state::state_dir().join("helpers")
+}
+
+fn issue_in(dir: &Path, kind: &str, payload: &str) -> Result<String, String> {
+    let token = hex::encode(rand::random::<[u8; 32]>());
+    secure_fs::write_private(&job_path(dir, kind, &token), payload.as_bytes())?;
+    Ok(token)
+}
+
+fn redeem_in(dir: &Path, kind: &str, token: &str) -> Result<String, String> {
+    if token.len() != 64 || !token.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("Invalid {} helper token", kind));
+    }
+    let path = job_path(dir, kind, token);
+    let payload = secure_fs::read_config(&path).map_err(|_| format!("No {} job issued for this token", kind))?;
+    let _ = std::fs::remove_file(&path);
+    Ok(payload.to_string())
+}
+
+fn job_path(dir: &Path, kind: &str, token: &str) -> PathBuf {
+    let digest = hex::encode(Sha256::digest(token.as_bytes()));
+    dir.join(format!("{}-{}", kind, &digest[..16]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_and_redeem_once() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("helpers");
+
+        let token = issue_in(&dir, "test", "/tmp/target").unwrap();
+        assert!(redeem_in(&dir, "other", &token).is_err());
+        assert_eq!(redeem_in(&dir, "test", &token).unwrap(), "/tmp/target");
+        assert!(redeem_in(&dir, "test", &token).is_err());
+
+        assert!(redeem_in(&dir, "test", "1").is_err());
+        assert!(redeem_in(&dir, "test", &"0".repeat(64)).is_err());
+    }
+}
@@ -0,0 +1,160 @@
+//! Discovery of killer instances running on this host
+//!
+//! Every verifying process announces itself with one JSON file in
+//! `instances/` under the state directory, named `<namespace>-<pid>.json`
+//! (`namespace` = `state::namespace_of(license_id)`). The record names the
+//! wrapper-provided resources the instance uses (health block, control
+//! socket, status file), so fleet tooling can find and query every instance
+//! of every product and version without knowing their naming schemes.
+//! Wrappers are expected to put the namespace into the names they choose as
+//! well, so two products never share a health block.
+//!
+//! Records are not removed on exit (most exits are hard); `list` skips and
+//! prunes records of processes that are gone. The state directory is
+//! per-user: tooling enumerates each account's (or a shared
+//! `KILLCODE_STATE_DIR`).
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::health_monitor::{HEALTH_FILE_ENV, HEALTH_SHM_ENV};
+use super::process;
+use super::secure_fs;
+use super::state::{namespace_of, state_dir};
+use super::time;
+use crate::config::Config;
+use crate::verification::cache::STATUS_FILE_ENV;
+
+/// Subdirectory of the state directory holding instance records
+pub const INSTANCES_DIR: &str = "instances";
+
+/// One running instance
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Instance {
+    pub pid: u32,
+    /// Protected app (our parent)
+    #[serde(default)]
+    pub parent_pid: Option<u32>,
+    /// License namespace (hashed license ID)
+    pub namespace: String,
+    pub killer_version: String,
+    pub binary: Option<String>,
+    pub started_at: i64,
+    #[serde(default)]
+    pub health_shm: Option<String>,
+    /// Health block file, where the wrapper uses one (see `health_monitor`)
+    #[serde(default)]
+    pub health_file: Option<String>,
+    #[serde(default)]
+    pub control_socket: Option<String>,
+    #[serde(default)]
+    pub status_file: Option<String>,
+}
+
+/// Announce this process (best effort)
+pub fn register(config: &Config) {
+    let instance = Instance {
+        pid: std::process::id(),
+        parent_pid: process::get_parent_pid(),
+        namespace: namespace_of(&config.license_id),
+        killer_version: env!("CARGO_PKG_VERSION").to_string(),
+        binary: crate::security::memexec::executable_path().ok().map(|path| path.display().to_string()),
+        started_at: time::unix_now(),
+        health_shm: std::env::var(HEALTH_SHM_ENV).ok(),
+        health_file: std::env::var(HEALTH_FILE_ENV).ok(),
+        control_socket: std::env::var(super::control::CONTROL_SOCKET_ENV).ok(),
+        status_file: std::env::var(STATUS_FILE_ENV).ok(),
+    };
+
+    let path = registry_dir().join(record_name(&instance));
+    let result = serde_json::to_vec(&instance)
+        .map_err(|e| e.to_string())
+        .and_then(|json| secure_fs::write_private(&path, &json));
+    match result {
+        Ok(()) => log_debug!("🗂️  Registered instance: {}", path.display()),
+        Err(e) => log_warn!("⚠️  Failed to register instance: {}", e),
+    }
+}
+
+/// Instances that are still running, oldest first (stale records are removed)
+pub fn list() -> Vec<Instance> {
+    let running: Vec<u32> = process::process_table().into_iter().map(|(pid, _)| pid).collect();
+    list_in(&registry_dir(), |pid| running.contains(&pid))
+}
+
+fn list_in(dir: &Path, is_running: impl Fn(u32) -> bool) -> Vec<Instance> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut instances = Vec::new();
+    for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        if path.extension().is_none_or(|extension| extension != "json") {
+            continue;
+        }
+        let Some(instance) = fs::read_to_string(&path)
+            .ok()
+            .and_then(|json| serde_json::from_str::<Instance>(&json).ok())
+        else {
+            continue;
+        };
+        // A record whose name does not match its content was not written by us
+        if path.file_name().is_none_or(|name| *name != *record_name(&instance)) {
+            continue;
+        }
+        if is_running(instance.pid) {
+            instances.push(instance);
+        } else {
+            let _ = fs::remove_file(&path);
+        }
+    }
+    instances.sort_by_key(|instance| (instance.started_at, instance.pid));
+    instances
+}
+
+fn registry_dir() -> PathBuf {
+    state_dir().join(INSTANCES_DIR)
+}
+
+fn record_name(instance: &Instance) -> String {
+    format!("{}-{}.json", instance.namespace, instance.pid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instance(pid: u32, license_id: &str) -> Instance {
+        Instance {
+            pid,
+            parent_pid: Some(1),
+            namespace: namespace_of(license_id),
+            killer_version: "0.0.0".to_string(),
+            binary: None,
+            started_at: pid as i64,
+            health_shm: Some(format!("/kc-{}", namespace_of(license_id))),
+            health_file: None,
+            control_socket: None,
+            status_file: None,
+        }
+    }
+
+    #[test]
+    fn test_list_skips_and_prunes_dead_instances() {
+        let dir = tempfile::tempdir().unwrap();
+        for instance in [instance(30, "lic_a"), instance(20, "lic_b"), instance(10, "lic_a")] {
+            let path = dir.path().join(record_name(&instance));
+            secure_fs::write_private(&path, &serde_json::to_vec(&instance).unwrap()).unwrap();
+        }
+        fs::write(dir.path().join("forged-1.json"), serde_json::to_vec(&instance(1, "lic_c")).unwrap()).unwrap();
+
+        let listed = list_in(dir.path(), |pid| pid != 20);
+        assert_eq!(listed, vec![instance(10, "lic_a"), instance(30, "lic_a")]);
+        // Same license, different processes: distinct records
+        assert_ne!(record_name(&listed[0]), record_name(&listed[1]));
+
+        assert!(!dir.path().join(record_name(&instance(20, "lic_b"))).exists());
+        assert!(dir.path().join("forged-1.json").exists());
+    }
+}
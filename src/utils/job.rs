@@ -0,0 +1,95 @@
+//! Bind spawned base binaries to the overload's lifetime
+//!
+//! On Windows a child outlives its parent by default, so an overload crash
+//! would leave the base running unprotected. The base is assigned to a Job
+//! Object with `JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE`; the job handle is only
+//! held by the overload, so when the overload exits or dies for any reason
+//! the OS closes the handle and terminates the base and its children.
+
+use std::process::Child;
+
+use crate::config::ResourceLimits;
+
+/// Job the base process is bound to; the base dies when this is closed
+pub struct KillOnCloseJob {
+    #[cfg(windows)]
+    handle: winapi::um::winnt::HANDLE,
+}
+
+impl KillOnCloseJob {
+    /// Bind `child` (and everything it spawns later) to a kill-on-close job,
+    /// with per-process memory/CPU limits if given
+    ///
+    /// No-op on Unix, where there is no equivalent parent-owned handle (limits
+    /// are applied with setrlimit at spawn, see `utils::limits`).
+    pub fn bind(child: &Child, limits: Option<&ResourceLimits>) -> Result<Self, String> {
+        #[cfg(unix)]
+        {
+            let _ = (child, limits);
+            Ok(Self {})
+        }
+
+        #[cfg(windows)]
+        unsafe {
+            use std::os::windows::io::AsRawHandle;
+            use winapi::um::handleapi::CloseHandle;
+            use winapi::um::jobapi2::{AssignProcessToJobObject, CreateJobObjectW, SetInformationJobObject};
+            use winapi::um::winnt::{
+                JobObjectExtendedLimitInformation, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+                JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE, JOB_OBJECT_LIMIT_PROCESS_MEMORY,
+                JOB_OBJECT_LIMIT_PROCESS_TIME,
+            };
+
+            let handle = CreateJobObjectW(std::ptr::null_mut(), std::ptr::null());
+            if handle.is_null() {
+                return Err(format!("CreateJobObject failed: {}", std::io::Error::last_os_error()));
+            }
+
+            let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+            info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+            if let Some(limits) = limits {
+                if let Some(mb) = limits.memory_mb {
+                    info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_PROCESS_MEMORY;
+                    info.ProcessMemoryLimit = mb.saturating_mul(1024 * 1024) as usize;
+                }
+                if let Some(secs) = limits.cpu_seconds {
+                    // User-mode CPU time in 100ns units
+                    info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_PROCESS_TIME;
+                    *info.BasicLimitInformation.PerProcessUserTimeLimit.QuadPart_mut() = secs.saturating_mul(10_000_000) as i64;
+                }
+                if limits.max_open_files.is_some() {
+                    log_warn!("⚠️  max_open_files is not enforceable on Windows - ignored");
+                }
+            }
+            let configured = SetInformationJobObject(
+                handle,
+                JobObjectExtendedLimitInformation,
+                &mut info as *mut _ as *mut _,
+                std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+            );
+            if configured == 0 {
+                let e = std::io::Error::last_os_error();
+                CloseHandle(handle);
+                return Err(format!("SetInformationJobObject failed: {}", e));
+            }
+
+            if AssignProcessToJobObject(handle, child.as_raw_handle() as _) == 0 {
+                let e = std::io::Error::last_os_error();
+                CloseHandle(handle);
+                return Err(format!("AssignProcessToJobObject failed: {}", e));
+            }
+
+            Ok(Self { handle })
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Drop for KillOnCloseJob {
+    fn drop(&mut self) {
+        // Closing the last handle terminates every process in the job
+        unsafe {
+            winapi::um::handleapi::CloseHandle(self.handle);
+        }
+    }
+}
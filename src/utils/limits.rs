@@ -0,0 +1,104 @@
+//! Resource limits for the spawned base binary
+//!
+//! Licensing tiers cap memory, CPU time and open files. The overload enforces
+//! them on the base instead of trusting the app: on Unix through `setrlimit`
+//! in the child before `exec` (inherited by everything the base spawns), on
+//! Windows through limits on the base's Job Object (see `utils::job`).
+
+use std::process::Command;
+
+use crate::config::ResourceLimits;
+
+/// Apply `limits` to a command before it is spawned (Unix)
+///
+/// On Windows limits are applied to the Job Object after spawning instead.
+pub fn apply_to_command(command: &mut Command, limits: &ResourceLimits) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+
+        let rlimits = rlimits(limits);
+        if rlimits.is_empty() {
+            return;
+        }
+
+        // Only async-signal-safe calls between fork and exec
+        unsafe {
+            command.pre_exec(move || {
+                for &(resource, value) in &rlimits {
+                    // Raising the hard limit needs privileges: the soft limit
+                    // is capped at it and the hard limit left as it is
+                    let mut limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+                    if libc::getrlimit(resource, &mut limit) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    limit.rlim_cur = value.min(limit.rlim_max);
+                    if libc::setrlimit(resource, &limit) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            });
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = (command, limits);
+    }
+}
+
+/// (resource, value) pairs for setrlimit
+#[cfg(unix)]
+fn rlimits(limits: &ResourceLimits) -> Vec<(RlimitResource, libc::rlim_t)> {
+    let mut rlimits = Vec::new();
+    if let Some(mb) = limits.memory_mb {
+        rlimits.push((MEMORY_RLIMIT, mb.saturating_mul(1024 * 1024) as libc::rlim_t));
+    }
+    if let Some(secs) = limits.cpu_seconds {
+        rlimits.push((libc::RLIMIT_CPU, secs as libc::rlim_t));
+    }
+    if let Some(files) = limits.max_open_files {
+        rlimits.push((libc::RLIMIT_NOFILE, files as libc::rlim_t));
+    }
+    rlimits
+}
+
+/// OpenBSD has no address-space limit; its data limit also covers
+/// anonymous mappings (malloc)
+#[cfg(all(unix, not(target_os = "openbsd")))]
+const MEMORY_RLIMIT: RlimitResource = libc::RLIMIT_AS;
+#[cfg(target_os = "openbsd")]
+const MEMORY_RLIMIT: RlimitResource = libc::RLIMIT_DATA;
+
+#[cfg(all(unix, target_os = "linux", target_env = "gnu"))]
+type RlimitResource = libc::__rlimit_resource_t;
+
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+#[cfg(unix)]
+type RlimitResource = libc::c_int;
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_apply_to_child() {
+        let limits = ResourceLimits { memory_mb: None, cpu_seconds: Some(30), max_open_files: Some(64) };
+        let mut command = Command::new("sh");
+        command.args(["-c", "ulimit -n; ulimit -t"]);
+        apply_to_command(&mut command, &limits);
+
+        let output = command.output().unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let lines: Vec<&str> = stdout.lines().collect();
+        assert_eq!(lines, ["64", "30"]);
+
+        // Above the hard limit: capped, not a failed spawn
+        let limits = ResourceLimits { memory_mb: None, cpu_seconds: None, max_open_files: Some(u64::MAX - 1) };
+        let mut command = Command::new("sh");
+        command.args(["-c", "ulimit -n"]);
+        apply_to_command(&mut command, &limits);
+        assert!(command.output().unwrap().status.success());
+    }
+}
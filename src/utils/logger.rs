@@ -0,0 +1,246 @@
+//! Leveled logging honoring `log_level`, `log_format` and `log_file`
+//!
+//! All diagnostics go through the `log_error!`, `log_warn!`, `log_info!` and
+//! `log_debug!` macros (exported at the crate root). Records emitted before the config is loaded are held
+//! back and replayed by `configure`, so `log_level: "none"` really produces no
+//! output at all; `configure_default` releases them when there is no config.
+
+use serde::Serialize;
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+use crate::config::{Config, LogFormat};
+use super::{secure_fs, time};
+
+/// Log level, ordered by verbosity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    None = 0,
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+}
+
+impl Level {
+    /// Parse a `log_level` value; unknown values fall back to info
+    pub fn parse(value: &str) -> Self {
+        match value.to_ascii_lowercase().as_str() {
+            "none" | "off" | "quiet" => Level::None,
+            "error" => Level::Error,
+            "warn" | "warning" => Level::Warn,
+            "debug" | "trace" => Level::Debug,
+            _ => Level::Info,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Level::None => "none",
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+        }
+    }
+}
+
+/// Marker for "not configured yet": records are buffered
+const UNCONFIGURED: u8 = u8::MAX;
+
+/// Records kept while unconfigured (the oldest are dropped beyond this)
+const MAX_PENDING: usize = 512;
+
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(UNCONFIGURED);
+
+/// Set by `silence`: later configuration cannot re-enable output
+static SILENCED: AtomicBool = AtomicBool::new(false);
+
+/// Output settings and records buffered before configuration
+static SINK: Mutex<Sink> = Mutex::new(Sink { format: LogFormat::Text, file: None, pending: Vec::new() });
+
+struct Sink {
+    format: LogFormat,
+    file: Option<File>,
+    pending: Vec<Record>,
+}
+
+/// One log record (also the JSON line layout)
+#[derive(Debug, Serialize)]
+struct Record {
+    timestamp: i64,
+    level: &'static str,
+    target: &'static str,
+    message: String,
+    #[serde(skip)]
+    rank: Level,
+}
+
+/// Apply the config's log settings and replay buffered records
+pub fn configure(config: &Config) {
+    let level = Level::parse(&config.log_level);
+    let file = config.log_file.as_ref().and_then(|path| {
+        match secure_fs::open_append(Path::new(path)) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                if level >= Level::Warn {
+                    eprintln!("⚠️  Cannot open log file {}: {} - logging to stderr", path, e);
+                }
+                None
+            }
+        }
+    });
+    install(level, config.log_format, file);
+}
+
+/// Change the level of the configured sink (runtime patch)
+pub fn set_level(value: &str) {
+    let level = if SILENCED.load(Ordering::Relaxed) { Level::None } else { Level::parse(value) };
+    MAX_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Plain stderr logging at info level (no config available)
+pub fn configure_default() {
+    if MAX_LEVEL.load(Ordering::Relaxed) == UNCONFIGURED {
+        install(Level::Info, LogFormat::Text, None);
+    }
+}
+
+/// Drop everything, including buffered records, for the rest of the process
+/// (summary mode, see `utils::summary`)
+pub fn silence() {
+    SILENCED.store(true, Ordering::Relaxed);
+    install(Level::None, LogFormat::Text, None);
+}
+
+/// Stop emitting records without touching the sink (which the caller may
+/// hold: panic handling)
+pub fn mute() {
+    SILENCED.store(true, Ordering::Relaxed);
+    MAX_LEVEL.store(Level::None as u8, Ordering::Relaxed);
+}
+
+fn install(level: Level, format: LogFormat, file: Option<File>) {
+    let level = if SILENCED.load(Ordering::Relaxed) { Level::None } else { level };
+    let mut sink = SINK.lock().unwrap_or_else(|e| e.into_inner());
+    sink.format = format;
+    sink.file = file;
+    MAX_LEVEL.store(level as u8, Ordering::Relaxed);
+
+    for record in std::mem::take(&mut sink.pending) {
+        if record.rank <= level {
+            sink.write(&record);
+        }
+    }
+}
+
+/// Whether records of this level are currently emitted (or buffered)
+pub fn enabled(level: Level) -> bool {
+    let max = MAX_LEVEL.load(Ordering::Relaxed);
+    level != Level::None && (max == UNCONFIGURED || level as u8 <= max)
+}
+
+/// Emit one record (use the macros instead)
+pub fn log(level: Level, target: &'static str, args: fmt::Arguments) {
+    if !enabled(level) {
+        return;
+    }
+
+    let record = Record {
+        timestamp: time::unix_now(),
+        level: level.as_str(),
+        target,
+        message: args.to_string(),
+        rank: level,
+    };
+
+    let mut sink = SINK.lock().unwrap_or_else(|e| e.into_inner());
+    if MAX_LEVEL.load(Ordering::Relaxed) == UNCONFIGURED && !cfg!(test) {
+        if sink.pending.len() >= MAX_PENDING {
+            sink.pending.remove(0);
+        }
+        sink.pending.push(record);
+    } else {
+        sink.write(&record);
+    }
+}
+
+impl Sink {
+    fn write(&mut self, record: &Record) {
+        let line = format_record(self.format, record);
+        match self.file.as_mut() {
+            Some(file) => {
+                let _ = writeln!(file, "{}", line);
+            }
+            None => eprintln!("{}", line),
+        }
+    }
+}
+
+fn format_record(format: LogFormat, record: &Record) -> String {
+    match format {
+        LogFormat::Text => record.message.clone(),
+        LogFormat::Json => serde_json::to_string(record).unwrap_or_else(|_| record.message.clone()),
+    }
+}
+
+#[macro_export]
+macro_rules! log_error {
+    ($($arg:tt)*) => {
+        $crate::utils::logger::log($crate::utils::logger::Level::Error, module_path!(), format_args!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        $crate::utils::logger::log($crate::utils::logger::Level::Warn, module_path!(), format_args!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        $crate::utils::logger::log($crate::utils::logger::Level::Info, module_path!(), format_args!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! log_debug {
+    ($($arg:tt)*) => {
+        $crate::utils::logger::log($crate::utils::logger::Level::Debug, module_path!(), format_args!($($arg)*))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_parsing_and_order() {
+        assert_eq!(Level::parse("none"), Level::None);
+        assert_eq!(Level::parse("WARNING"), Level::Warn);
+        assert_eq!(Level::parse("bogus"), Level::Info);
+        assert!(Level::Error < Level::Info && Level::Info < Level::Debug);
+    }
+
+    #[test]
+    fn test_json_record_format() {
+        let record = Record {
+            timestamp: 1,
+            level: "warn",
+            target: "kc_killer::verification",
+            message: "⚠️  retrying".to_string(),
+            rank: Level::Warn,
+        };
+
+        assert_eq!(format_record(LogFormat::Text, &record), "⚠️  retrying");
+        let json = format_record(LogFormat::Json, &record);
+        assert!(json.starts_with(r#"{"timestamp":1,"level":"warn","target":"kc_killer::verification""#));
+        assert!(!json.contains("rank"));
+    }
+}
@@ -1,3 +1,21 @@
+#[macro_use]
+pub mod logger;
 pub mod platform;
 pub mod health_monitor;
+pub mod control;
 pub mod process;
+pub mod state;
+pub mod secure_fs;
+pub mod session;
+pub mod power;
+pub mod audit;
+pub mod redact;
+pub mod job;
+pub mod time;
+pub mod limits;
+pub mod tasks;
+pub mod summary;
+pub mod instances;
+pub mod shutdown;
+pub mod exit_status;
+pub mod helper;
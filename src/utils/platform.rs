@@ -1,6 +1,9 @@
 /// Platform-specific utilities
 ///  
 /// Detect OS, architecture, and provide platform-specific helpers
+use serde::Serialize;
+use std::path::Path;
+use std::sync::OnceLock;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Platform {
@@ -63,6 +66,144 @@ impl Platform {
             Platform::Unknown => "unknown",
         }
     }
+
+    /// Operating system and architecture parts of `name`
+    pub fn os_arch(&self) -> (&'static str, &'static str) {
+        self.name().split_once('-').unwrap_or((std::env::consts::OS, std::env::consts::ARCH))
+    }
+}
+
+/// CPU features relevant to the crypto backends
+///
+/// ARM32 set-top boxes ship with and without VFP/NEON; the portable builds
+/// must only take accelerated paths after checking at runtime.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct CpuFeatures {
+    /// Hardware floating point
+    pub fpu: bool,
+    /// SIMD: NEON on ARM, SSE2 on x86
+    pub simd: bool,
+    /// AES instructions (AES-GCM is slow constant-time software otherwise)
+    pub aes: bool,
+}
+
+/// Detect CPU features (once per process)
+pub fn cpu_features() -> CpuFeatures {
+    static FEATURES: OnceLock<CpuFeatures> = OnceLock::new();
+    *FEATURES.get_or_init(detect_cpu_features)
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn detect_cpu_features() -> CpuFeatures {
+    CpuFeatures {
+        fpu: true,
+        simd: std::is_x86_feature_detected!("sse2"),
+        aes: std::is_x86_feature_detected!("aes") && std::is_x86_feature_detected!("pclmulqdq"),
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+fn detect_cpu_features() -> CpuFeatures {
+    CpuFeatures {
+        fpu: true,
+        simd: true, // mandatory on AArch64
+        aes: std::arch::is_aarch64_feature_detected!("aes"),
+    }
+}
+
+#[cfg(all(target_arch = "arm", target_os = "linux"))]
+fn detect_cpu_features() -> CpuFeatures {
+    // <asm/hwcap.h>
+    const HWCAP_VFP: libc::c_ulong = 1 << 6;
+    const HWCAP_NEON: libc::c_ulong = 1 << 12;
+    const HWCAP2_AES: libc::c_ulong = 1 << 0;
+
+    let hwcap = unsafe { libc::getauxval(libc::AT_HWCAP) };
+    let hwcap2 = unsafe { libc::getauxval(libc::AT_HWCAP2) };
+    CpuFeatures {
+        fpu: hwcap & HWCAP_VFP != 0,
+        simd: hwcap & HWCAP_NEON != 0,
+        aes: hwcap2 & HWCAP2_AES != 0,
+    }
+}
+
+#[cfg(not(any(
+    target_arch = "x86",
+    target_arch = "x86_64",
+    target_arch = "aarch64",
+    all(target_arch = "arm", target_os = "linux")
+)))]
+fn detect_cpu_features() -> CpuFeatures {
+    // Only what the build itself guarantees
+    CpuFeatures {
+        fpu: !cfg!(target_feature = "soft-float"),
+        simd: cfg!(target_feature = "neon"),
+        aes: cfg!(target_feature = "aes"),
+    }
+}
+
+/// Whether a path lives on a network filesystem (NFS, SMB, ...)
+pub fn is_network_filesystem(path: &Path) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::ffi::OsStrExt;
+
+        const NETWORK_FS_MAGIC: [i64; 7] = [
+            0x6969,     // NFS
+            0x517B,     // SMB
+            0xFF534D42, // CIFS
+            0xFE534D42, // SMB2
+            0x01021997, // 9P
+            0x5346414F, // AFS
+            0x00C36400, // Ceph
+        ];
+        let Ok(c_path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+            return false;
+        };
+        let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+            return false;
+        }
+        NETWORK_FS_MAGIC.contains(&(stat.f_type as i64))
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        use std::os::unix::ffi::OsStrExt;
+
+        let Ok(c_path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+            return false;
+        };
+        let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statfs(c_path.as_ptr(), &mut stat) } != 0 {
+            return false;
+        }
+        let fs_type = unsafe { std::ffi::CStr::from_ptr(stat.f_fstypename.as_ptr()) };
+        matches!(fs_type.to_bytes(), b"nfs" | b"smbfs" | b"afpfs" | b"webdav" | b"cifs")
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        use std::path::{Component, Prefix};
+
+        let root = match path.components().next() {
+            Some(Component::Prefix(prefix)) => match prefix.kind() {
+                Prefix::UNC(..) | Prefix::VerbatimUNC(..) => return true,
+                Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => format!("{}:\\", letter as char),
+                _ => return false,
+            },
+            _ => return false,
+        };
+        let wide: Vec<u16> = std::ffi::OsStr::new(&root).encode_wide().chain(Some(0)).collect();
+        unsafe { winapi::um::fileapi::GetDriveTypeW(wide.as_ptr()) == winapi::um::winbase::DRIVE_REMOTE }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+    {
+        let _ = path;
+        false
+    }
 }
 
 #[cfg(test)]
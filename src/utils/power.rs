@@ -0,0 +1,215 @@
+//! Power-aware check scheduling
+//!
+//! Periodic verification should not keep laptops awake or drain batteries.
+//! When `power_aware` is enabled the check interval is stretched while the
+//! machine runs on battery or the user session is idle or locked (logind hints
+//! cover both X11 and Wayland sessions), and a suspend is detected on wake so
+//! the overdue check runs immediately instead of after the remaining interval.
+
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Input inactivity after which a session counts as idle (where the platform
+/// reports raw idle time rather than an idle hint)
+#[cfg(any(target_os = "macos", windows))]
+const IDLE_THRESHOLD_SECS: u64 = 300;
+
+/// Granularity of interval sleeps (suspend detection latency)
+pub const SLEEP_SLICE: Duration = Duration::from_secs(5);
+
+/// Wall-clock time gained over the monotonic clock that counts as a suspend
+const SUSPEND_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// Power and session conditions relevant to scheduling
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PowerState {
+    pub on_battery: bool,
+    pub idle: bool,
+    pub locked: bool,
+}
+
+impl PowerState {
+    /// Whether checks should be spaced out
+    pub fn power_saving(&self) -> bool {
+        self.on_battery || self.idle || self.locked
+    }
+}
+
+/// Query the current power state (conditions that cannot be determined are false)
+pub fn current() -> PowerState {
+    #[cfg(target_os = "linux")]
+    {
+        let (idle, locked) = logind_hints();
+        PowerState {
+            on_battery: linux_on_battery(),
+            idle,
+            locked,
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let on_battery = command_output("pmset", &["-g", "batt"])
+            .is_some_and(|out| out.contains("'Battery Power'"));
+        let idle = command_output("ioreg", &["-c", "IOHIDSystem", "-d", "4"])
+            .and_then(|out| parse_hid_idle_secs(&out))
+            .is_some_and(|secs| secs >= IDLE_THRESHOLD_SECS);
+        PowerState {
+            on_battery,
+            idle,
+            locked: false,
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        windows_power_state()
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+    {
+        PowerState::default()
+    }
+}
+
+/// Effective check interval for the given power state
+pub fn scaled_interval(interval_ms: u64, state: &PowerState, multiplier: u32) -> u64 {
+    if state.power_saving() {
+        interval_ms.saturating_mul(multiplier.max(1) as u64)
+    } else {
+        interval_ms
+    }
+}
+
+/// Sleep for `duration`, reporting whether the machine was suspended meanwhile
+///
+/// Callers sleep in slices of at most `SLEEP_SLICE` so a wake is noticed promptly.
+///
+/// # Returns
+/// Some(time asleep) if a suspend was detected
+pub fn sleep_detecting_suspend(duration: Duration) -> Option<Duration> {
+    let wall_before = SystemTime::now();
+    let mono_before = Instant::now();
+    thread::sleep(duration);
+    suspend_gap(wall_before, mono_before)
+}
+
+/// The monotonic clock stops during suspend while the wall clock keeps going
+fn suspend_gap(wall_before: SystemTime, mono_before: Instant) -> Option<Duration> {
+    let wall_elapsed = SystemTime::now().duration_since(wall_before).ok()?;
+    let gap = wall_elapsed.checked_sub(mono_before.elapsed())?;
+    (gap > SUSPEND_THRESHOLD).then_some(gap)
+}
+
+#[cfg(target_os = "linux")]
+fn linux_on_battery() -> bool {
+    let Ok(entries) = std::fs::read_dir("/sys/class/power_supply") else {
+        return false;
+    };
+
+    let mut discharging = false;
+    for entry in entries.flatten() {
+        let read = |name: &str| {
+            std::fs::read_to_string(entry.path().join(name))
+                .map(|s| s.trim().to_string())
+                .unwrap_or_default()
+        };
+        match read("type").as_str() {
+            "Mains" | "USB" if read("online") == "1" => return false,
+            "Battery" if read("status") == "Discharging" => discharging = true,
+            _ => {}
+        }
+    }
+    discharging
+}
+
+/// IdleHint/LockedHint of our logind session
+#[cfg(target_os = "linux")]
+fn logind_hints() -> (bool, bool) {
+    let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string());
+    command_output("loginctl", &["show-session", &session, "-p", "IdleHint", "-p", "LockedHint"])
+        .map(|out| parse_logind_hints(&out))
+        .unwrap_or((false, false))
+}
+
+/// Parse `loginctl show-session -p IdleHint -p LockedHint` output
+pub fn parse_logind_hints(output: &str) -> (bool, bool) {
+    let hint = |name: &str| {
+        output
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .any(|(key, value)| key.trim() == name && value.trim() == "yes")
+    };
+    (hint("IdleHint"), hint("LockedHint"))
+}
+
+/// Parse HIDIdleTime (nanoseconds) from `ioreg -c IOHIDSystem` output
+pub fn parse_hid_idle_secs(output: &str) -> Option<u64> {
+    output
+        .lines()
+        .find(|line| line.contains("\"HIDIdleTime\""))
+        .and_then(|line| line.rsplit('=').next())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(|nanos| nanos / 1_000_000_000)
+}
+
+#[cfg(windows)]
+fn windows_power_state() -> PowerState {
+    use std::mem;
+    use winapi::um::sysinfoapi::GetTickCount;
+    use winapi::um::winbase::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+    use winapi::um::winuser::{GetLastInputInfo, LASTINPUTINFO};
+
+    unsafe {
+        let mut power: SYSTEM_POWER_STATUS = mem::zeroed();
+        let on_battery = GetSystemPowerStatus(&mut power) != 0 && power.ACLineStatus == 0;
+
+        let mut input = LASTINPUTINFO {
+            cbSize: mem::size_of::<LASTINPUTINFO>() as u32,
+            dwTime: 0,
+        };
+        let idle = GetLastInputInfo(&mut input) != 0
+            && (GetTickCount().wrapping_sub(input.dwTime) as u64) / 1000 >= IDLE_THRESHOLD_SECS;
+
+        PowerState {
+            on_battery,
+            idle,
+            locked: false,
+        }
+    }
+}
+
+#[cfg(unix)]
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program).args(args).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_logind_hints() {
+        assert_eq!(parse_logind_hints("IdleHint=no\nLockedHint=yes\n"), (false, true));
+        assert_eq!(parse_logind_hints("IdleHint=yes\nLockedHint=no\n"), (true, false));
+        assert_eq!(parse_logind_hints(""), (false, false));
+    }
+
+    #[test]
+    fn test_parse_hid_idle_secs() {
+        let output = "    | |   \"HIDIdleTime\" = 421000000000\n";
+        assert_eq!(parse_hid_idle_secs(output), Some(421));
+        assert_eq!(parse_hid_idle_secs("nothing here"), None);
+    }
+
+    #[test]
+    fn test_scaled_interval() {
+        let active = PowerState::default();
+        let battery = PowerState { on_battery: true, ..Default::default() };
+
+        assert_eq!(scaled_interval(60_000, &active, 4), 60_000);
+        assert_eq!(scaled_interval(60_000, &battery, 4), 240_000);
+        assert_eq!(scaled_interval(60_000, &battery, 0), 60_000);
+    }
+}
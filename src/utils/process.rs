@@ -1,4 +1,4 @@
-/// Process utilities
+//! Process utilities
 
 #[cfg(unix)]
 use std::os::unix::process::parent_id;
@@ -50,3 +50,213 @@ pub fn get_parent_pid() -> Option<u32> {
         }
     }
 }
+
+/// All processes as (pid, parent pid) pairs
+pub fn process_table() -> Vec<(u32, u32)> {
+    #[cfg(target_os = "linux")]
+    {
+        let Ok(entries) = std::fs::read_dir("/proc") else {
+            return Vec::new();
+        };
+        entries
+            .flatten()
+            .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+            .filter_map(|pid| {
+                let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+                Some((pid, parse_stat_ppid(&stat)?))
+            })
+            .collect()
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "freebsd", target_os = "openbsd"))]
+    {
+        let Ok(output) = std::process::Command::new("ps").args(["-A", "-o", "pid=,ppid="]).output() else {
+            return Vec::new();
+        };
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                Some((fields.next()?.parse().ok()?, fields.next()?.parse().ok()?))
+            })
+            .collect()
+    }
+
+    #[cfg(windows)]
+    {
+        use std::mem;
+        use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+        use winapi::um::tlhelp32::{
+            CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W, TH32CS_SNAPPROCESS,
+        };
+
+        let mut table = Vec::new();
+        unsafe {
+            let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+            if snapshot == INVALID_HANDLE_VALUE {
+                return table;
+            }
+
+            let mut entry: PROCESSENTRY32W = mem::zeroed();
+            entry.dwSize = mem::size_of::<PROCESSENTRY32W>() as u32;
+            if Process32FirstW(snapshot, &mut entry) != 0 {
+                loop {
+                    table.push((entry.th32ProcessID, entry.th32ParentProcessID));
+                    if Process32NextW(snapshot, &mut entry) == 0 {
+                        break;
+                    }
+                }
+            }
+            CloseHandle(snapshot);
+        }
+        table
+    }
+}
+
+/// Start time of process `pid`, in a platform-specific unit
+///
+/// Only meaningful compared with another value for the same pid: a PID that
+/// was reused by a new process has a different start time.
+pub fn start_time(pid: u32) -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        parse_stat_start_time(&stat)
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let mut info: libc::proc_bsdinfo = unsafe { std::mem::zeroed() };
+        let size = std::mem::size_of::<libc::proc_bsdinfo>() as libc::c_int;
+        let read = unsafe {
+            libc::proc_pidinfo(pid as libc::c_int, libc::PROC_PIDTBSDINFO, 0, &mut info as *mut _ as *mut libc::c_void, size)
+        };
+        (read == size).then(|| info.pbi_start_tvsec * 1_000_000 + info.pbi_start_tvusec)
+    }
+
+    #[cfg(any(target_os = "freebsd", target_os = "openbsd"))]
+    {
+        let output = std::process::Command::new("ps").args(["-o", "lstart=", "-p", &pid.to_string()]).output().ok()?;
+        let started = String::from_utf8_lossy(&output.stdout);
+        let started = chrono::NaiveDateTime::parse_from_str(started.trim(), "%a %b %e %H:%M:%S %Y").ok()?;
+        u64::try_from(started.and_utc().timestamp()).ok()
+    }
+
+    #[cfg(windows)]
+    unsafe {
+        use winapi::shared::minwindef::FILETIME;
+        use winapi::um::handleapi::CloseHandle;
+        use winapi::um::processthreadsapi::{GetProcessTimes, OpenProcess};
+        use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle.is_null() {
+            return None;
+        }
+        let mut times: [FILETIME; 4] = std::mem::zeroed();
+        let [created, exited, kernel, user] = &mut times;
+        let ok = GetProcessTimes(handle, created, exited, kernel, user);
+        CloseHandle(handle);
+        (ok != 0).then(|| (times[0].dwHighDateTime as u64) << 32 | times[0].dwLowDateTime as u64)
+    }
+}
+
+/// Value of the sysctl `mib` (FreeBSD, OpenBSD)
+///
+/// The buffer grows until the value fits; some nodes (OpenBSD's process
+/// arguments) do not report their size up front.
+#[cfg(any(target_os = "freebsd", target_os = "openbsd"))]
+pub fn sysctl(mib: &[libc::c_int]) -> Option<Vec<u8>> {
+    const MAX_SIZE: usize = 1024 * 1024;
+
+    let mut mib = mib.to_vec();
+    let mut buffer = vec![0u8; 4096];
+    loop {
+        let mut size = buffer.len();
+        let ret = unsafe {
+            libc::sysctl(
+                mib.as_mut_ptr(),
+                mib.len() as libc::c_uint,
+                buffer.as_mut_ptr() as *mut libc::c_void,
+                &mut size,
+                std::ptr::null_mut(),
+                0,
+            )
+        };
+        if ret == 0 {
+            buffer.truncate(size);
+            return Some(buffer);
+        }
+        if std::io::Error::last_os_error().raw_os_error() != Some(libc::ENOMEM) || buffer.len() >= MAX_SIZE {
+            return None;
+        }
+        buffer.resize(buffer.len() * 2, 0);
+    }
+}
+
+/// Parent PID from the contents of /proc/<pid>/stat
+///
+/// The command name may contain spaces and parentheses, so fields are
+/// counted from the last ')'.
+#[cfg(target_os = "linux")]
+fn parse_stat_ppid(stat: &str) -> Option<u32> {
+    let rest = &stat[stat.rfind(')')? + 1..];
+    // Fields after the name: state, ppid, ...
+    rest.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Start time (clock ticks since boot) from the contents of /proc/<pid>/stat
+#[cfg(target_os = "linux")]
+fn parse_stat_start_time(stat: &str) -> Option<u64> {
+    let rest = &stat[stat.rfind(')')? + 1..];
+    // Field 22 of the file, the 20th after the name
+    rest.split_whitespace().nth(19)?.parse().ok()
+}
+
+/// All descendants of `root` in `table`, deepest first
+pub fn descendants(root: u32, table: &[(u32, u32)]) -> Vec<u32> {
+    let mut found = Vec::new();
+    let mut frontier = vec![root];
+    while let Some(parent) = frontier.pop() {
+        for &(pid, ppid) in table {
+            // pid 0 is its own parent on some platforms
+            if ppid == parent && pid != parent && pid != root && !found.contains(&pid) {
+                found.push(pid);
+                frontier.push(pid);
+            }
+        }
+    }
+    found.reverse();
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_descendants_deepest_first() {
+        // 1 ─ 10 ─ 11 ─ 12
+        //   └ 20
+        let table = [(1, 0), (10, 1), (11, 10), (12, 11), (20, 1), (30, 2)];
+        let tree = descendants(10, &table);
+        assert_eq!(tree, vec![12, 11]);
+
+        let all = descendants(1, &table);
+        assert_eq!(all.len(), 4);
+        assert!(all.iter().position(|&p| p == 12) < all.iter().position(|&p| p == 10));
+        assert!(!all.contains(&30));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parse_stat_ppid() {
+        assert_eq!(parse_stat_ppid("1234 (my (odd) app) S 42 1234 1234 0"), Some(42));
+        assert!(process_table().contains(&(std::process::id(), parent_id())));
+
+        let stat = "1234 (a b) S 42 1234 1234 0 -1 4194560 100 0 0 0 1 2 0 0 20 0 1 0 987654 1000 10";
+        assert_eq!(parse_stat_start_time(stat), Some(987654));
+        assert!(start_time(std::process::id()).is_some());
+        assert_eq!(start_time(std::process::id()), start_time(std::process::id()));
+    }
+}
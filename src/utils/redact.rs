@@ -0,0 +1,164 @@
+//! Redaction of sensitive values in log output
+//!
+//! Logs end up in world-readable places (journald, support bundles). A full
+//! request signature is replayable within the timestamp window, and license
+//! IDs and secrets identify or impersonate an install, so log lines show only
+//! masked forms. The raw values are logged only when redaction is explicitly
+//! turned off (`log_redaction: false`) together with `log_level: "debug"`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::config::Config;
+use crate::security::secrets::SecretString;
+
+/// Whether redaction is active (on until configured otherwise)
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Values scrubbed from free-form log text (license ID, shared secret)
+static SENSITIVE: Mutex<Vec<SecretString>> = Mutex::new(Vec::new());
+
+/// Shorter values are not scrubbed: they would mask ordinary words and
+/// numbers all over the log
+const MIN_SCRUBBED_LEN: usize = 8;
+
+/// Apply the config's redaction setting and register its sensitive values
+pub fn configure(config: &Config) {
+    let enabled = config.log_redaction || config.log_level != "debug";
+    ENABLED.store(enabled, Ordering::Relaxed);
+    if !enabled {
+        log_warn!("🔓 Log redaction disabled (debug) - logs contain secrets");
+    }
+
+    if let Ok(mut sensitive) = SENSITIVE.lock() {
+        sensitive.clear();
+        sensitive.push(SecretString::from(config.license_id.as_str()));
+        sensitive.push(config.shared_secret.clone());
+        sensitive.extend(config.webhook_key.clone());
+        for license in &config.licenses {
+            sensitive.push(SecretString::from(license.license_id.as_str()));
+            sensitive.push(license.shared_secret.clone());
+        }
+        sensitive.retain(|value| value.len() >= MIN_SCRUBBED_LEN);
+    }
+}
+
+/// Whether log output is being redacted
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Mask a signature, token or secret: only its length is shown
+pub fn secret(value: &str) -> String {
+    if !enabled() {
+        return value.to_string();
+    }
+    format!("<redacted:{}>", value.len())
+}
+
+/// Mask an identifier, keeping the last 4 characters for correlation
+pub fn identifier(value: &str) -> String {
+    if !enabled() {
+        return value.to_string();
+    }
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() <= 8 {
+        return "****".to_string();
+    }
+    format!("****{}", chars[chars.len() - 4..].iter().collect::<String>())
+}
+
+/// Strip credentials and the query string from a URL
+pub fn url(value: &str) -> String {
+    if !enabled() {
+        return value.to_string();
+    }
+    let (scheme, rest) = value.split_once("://").unwrap_or(("", value));
+    let rest = match rest.find('@') {
+        // Userinfo only counts before the first path separator
+        Some(at) if rest[..at].find('/').is_none() => &rest[at + 1..],
+        _ => rest,
+    };
+    let (base, query) = match rest.split_once('?') {
+        Some((base, _)) => (base, "?<redacted>"),
+        None => (rest, ""),
+    };
+    if scheme.is_empty() {
+        format!("{}{}", base, query)
+    } else {
+        format!("{}://{}{}", scheme, base, query)
+    }
+}
+
+/// Replace registered sensitive values in free-form text, where they stand
+/// as a token of their own
+pub fn scrub(text: &str) -> String {
+    if !enabled() {
+        return text.to_string();
+    }
+    let Ok(sensitive) = SENSITIVE.lock() else {
+        return text.to_string();
+    };
+    sensitive.iter().fold(text.to_string(), |text, value| replace_tokens(&text, value, &identifier(value)))
+}
+
+/// Replace the occurrences of `value` not adjacent to other token characters
+fn replace_tokens(text: &str, value: &str, mask: &str) -> String {
+    let is_token_char = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
+    let mut result = String::with_capacity(text.len());
+    let mut copied = 0;
+    for (start, _) in text.match_indices(value) {
+        let end = start + value.len();
+        if text[..start].chars().next_back().is_some_and(is_token_char) || text[end..].chars().next().is_some_and(is_token_char) {
+            continue;
+        }
+        result.push_str(&text[copied..start]);
+        result.push_str(mask);
+        copied = end;
+    }
+    result.push_str(&text[copied..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_masks() {
+        assert_eq!(secret("a1b2c3d4"), "<redacted:8>");
+        assert_eq!(identifier("lic_1234567890"), "****7890");
+        assert_eq!(identifier("short"), "****");
+        assert_eq!(
+            url("https://user:pw@api.example.com/api/v1/verify?token=abc"),
+            "https://api.example.com/api/v1/verify?<redacted>"
+        );
+        assert_eq!(url("http://localhost:8080/api/v1/verify"), "http://localhost:8080/api/v1/verify");
+    }
+
+    #[test]
+    fn test_scrub_registered_values() {
+        let config: Config = serde_json::from_str(r#"{
+            "license_id": "lic_scrub_0001",
+            "server_url": "http://localhost:8080",
+            "shared_secret": "very-secret-value"
+        }"#).unwrap();
+        configure(&config);
+
+        let scrubbed = scrub("license lic_scrub_0001 signed with very-secret-value");
+        assert!(!scrubbed.contains("lic_scrub_0001"));
+        assert!(!scrubbed.contains("very-secret-value"));
+        assert!(scrubbed.contains("****0001"));
+
+        // Only whole tokens, and only values long enough to be distinctive
+        assert_eq!(scrub("lic_scrub_00012 very-secret-values"), "lic_scrub_00012 very-secret-values");
+        assert_eq!(replace_tokens("key=abcdefgh, xabcdefgh", "abcdefgh", "****"), "key=****, xabcdefgh");
+        let short: Config = serde_json::from_str(r#"{
+            "license_id": "lic_scrub_0001",
+            "server_url": "http://localhost:8080",
+            "shared_secret": "1234"
+        }"#).unwrap();
+        configure(&short);
+        assert_eq!(scrub("retry 1234 in 5s"), "retry 1234 in 5s");
+    }
+}
@@ -0,0 +1,364 @@
+//! Symlink-safe reads of configuration and writes of state
+//!
+//! killer often runs with more privileges than the user who can write next to
+//! the binary or into a shared state directory. A `.config` symlinked to
+//! `/etc/shadow`, or a state file symlinked to `/etc/passwd`, would otherwise
+//! make it read or clobber files on the attacker's behalf.
+//!
+//! On Unix the final path component is opened with `O_NOFOLLOW`, and writes go
+//! through a descriptor of the parent directory (`openat`/`renameat`), so a
+//! directory swapped for a symlink between the check and the write is not
+//! followed either. Configuration files and state directories must be owned
+//! by us or root and must not be world-writable. State files are created 0600
+//! and replaced atomically.
+//!
+//! Windows has no equivalent of the mode bits; there only reparse points
+//! (symlinks, junctions) are refused.
+
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+use zeroize::Zeroizing;
+
+/// Read a configuration file, refusing symlinks and files others can modify
+pub fn read_config(path: &Path) -> Result<Zeroizing<String>, String> {
+    let mut file = open_config(path)?;
+    let mut contents = Zeroizing::new(String::new());
+    file.read_to_string(&mut contents)
+        .map_err(|e| format!("Failed to read config file {}: {}", path.display(), e))?;
+    Ok(contents)
+}
+
+/// Atomically replace `path` with `contents`, readable by the owner only
+pub fn write_private(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let (dir, name) = split(path)?;
+    create_private_dir(dir)?;
+    write_in(dir, name, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Append `line` and a newline to `path` (created 0600, symlinks not followed)
+pub fn append_private(path: &Path, line: &str) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        create_private_dir(dir)?;
+    }
+    let mut file = open_append(path)?;
+    writeln!(file, "{}", line).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Open `path` for appending (created 0600, symlinks not followed)
+pub fn open_append(path: &Path) -> Result<File, String> {
+    let mut options = fs::OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600).custom_flags(libc::O_NOFOLLOW | libc::O_CLOEXEC);
+    }
+    #[cfg(windows)]
+    refuse_reparse_point(path)?;
+    options.open(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Create `path` (0600, symlinks not followed), failing if it exists
+pub fn create_private(path: &Path) -> Result<File, String> {
+    if let Some(dir) = path.parent() {
+        create_private_dir(dir)?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600).custom_flags(libc::O_NOFOLLOW | libc::O_CLOEXEC);
+    }
+    options.open(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Take an exclusive lock on `path` (created 0600), waiting for it
+///
+/// The lock is held until the returned file is closed; it is advisory and
+/// only excludes other processes that lock the same file.
+pub fn lock_file(path: &Path) -> Result<File, String> {
+    lock(path, true)?.ok_or_else(|| format!("Failed to lock {}", path.display()))
+}
+
+/// Take an exclusive lock on `path` unless another process holds it
+pub fn try_lock_file(path: &Path) -> Result<Option<File>, String> {
+    lock(path, false)
+}
+
+fn lock(path: &Path, wait: bool) -> Result<Option<File>, String> {
+    if let Some(dir) = path.parent() {
+        create_private_dir(dir)?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.create(true).truncate(false).write(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600).custom_flags(libc::O_NOFOLLOW | libc::O_CLOEXEC);
+    }
+    let file = options.open(path).map_err(|e| format!("Failed to open lock {}: {}", path.display(), e))?;
+
+    #[cfg(unix)]
+    let locked = {
+        use std::os::fd::AsRawFd;
+        let operation = if wait { libc::LOCK_EX } else { libc::LOCK_EX | libc::LOCK_NB };
+        match unsafe { libc::flock(file.as_raw_fd(), operation) } {
+            0 => Ok(true),
+            _ => match std::io::Error::last_os_error() {
+                e if e.raw_os_error() == Some(libc::EWOULDBLOCK) => Ok(false),
+                e => Err(e),
+            },
+        }
+    };
+
+    #[cfg(windows)]
+    let locked = {
+        use std::os::windows::io::AsRawHandle;
+        use winapi::shared::winerror::ERROR_LOCK_VIOLATION;
+        use winapi::um::fileapi::LockFileEx;
+        use winapi::um::minwinbase::{LOCKFILE_EXCLUSIVE_LOCK, LOCKFILE_FAIL_IMMEDIATELY};
+
+        let flags = if wait { LOCKFILE_EXCLUSIVE_LOCK } else { LOCKFILE_EXCLUSIVE_LOCK | LOCKFILE_FAIL_IMMEDIATELY };
+        let mut overlapped = unsafe { std::mem::zeroed() };
+        match unsafe { LockFileEx(file.as_raw_handle() as _, flags, 0, !0, !0, &mut overlapped) } {
+            0 => match std::io::Error::last_os_error() {
+                e if e.raw_os_error() == Some(ERROR_LOCK_VIOLATION as i32) => Ok(false),
+                e => Err(e),
+            },
+            _ => Ok(true),
+        }
+    };
+
+    match locked {
+        Ok(true) => Ok(Some(file)),
+        Ok(false) => Ok(None),
+        Err(e) => Err(format!("Failed to lock {}: {}", path.display(), e)),
+    }
+}
+
+/// Create a directory (and missing parents) accessible by the owner only
+pub fn create_private_dir(dir: &Path) -> Result<(), String> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder
+        .create(dir)
+        .map_err(|e| format!("Failed to create directory {}: {}", dir.display(), e))
+}
+
+/// Reject names that are not a single plain path component
+pub fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\', '\0']) {
+        return Err(format!("Invalid file name: {:?}", name));
+    }
+    Ok(())
+}
+
+fn split(path: &Path) -> Result<(&Path, &str), String> {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| format!("Invalid path: {}", path.display()))?;
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    Ok((dir, name))
+}
+
+#[cfg(unix)]
+fn open_config(path: &Path) -> Result<File, String> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    // O_NONBLOCK: a FIFO planted as the config must not hang the open
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NOFOLLOW | libc::O_NONBLOCK)
+        .open(path)
+        .map_err(|e| match e.raw_os_error() {
+            Some(libc::ELOOP) => format!("Refusing config file {}: it is a symlink", path.display()),
+            _ => format!("Failed to read config file {}: {}", path.display(), e),
+        })?;
+
+    // Checked on the open descriptor, not the path, so the file cannot be swapped in between
+    let metadata = file
+        .metadata()
+        .map_err(|e| format!("Failed to stat config file {}: {}", path.display(), e))?;
+    if !metadata.is_file() {
+        return Err(format!("Refusing config file {}: not a regular file", path.display()));
+    }
+    check_owner(path, &metadata)?;
+    Ok(file)
+}
+
+#[cfg(windows)]
+fn open_config(path: &Path) -> Result<File, String> {
+    refuse_reparse_point(path)?;
+    File::open(path).map_err(|e| format!("Failed to read config file {}: {}", path.display(), e))
+}
+
+/// Owned by us or root, and not writable by everyone
+#[cfg(unix)]
+fn check_owner(path: &Path, metadata: &fs::Metadata) -> Result<(), String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let (uid, mode) = (metadata.uid(), metadata.mode());
+    let euid = unsafe { libc::geteuid() };
+    if uid != euid && uid != 0 {
+        return Err(format!("Refusing {}: owned by uid {} (running as uid {})", path.display(), uid, euid));
+    }
+    // World-writable directories are fine with the sticky bit (like /tmp)
+    let sticky_dir = metadata.is_dir() && mode & 0o1000 != 0;
+    if mode & 0o002 != 0 && !sticky_dir {
+        return Err(format!("Refusing {}: world-writable (mode {:o})", path.display(), mode & 0o7777));
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn write_in(dir: &Path, name: &str, contents: &[u8]) -> std::io::Result<()> {
+    use std::ffi::CString;
+    use std::io::{Error, ErrorKind};
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let dir_handle = fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_DIRECTORY | libc::O_NOFOLLOW)
+        .open(dir)?;
+    let metadata = dir_handle.metadata()?;
+    check_owner(dir, &metadata).map_err(|e| Error::new(ErrorKind::PermissionDenied, e))?;
+
+    let c_name = |name: &str| CString::new(name).map_err(|_| Error::from(ErrorKind::InvalidInput));
+    let target = c_name(name)?;
+    let tmp = c_name(&format!(".{}.tmp{}", name, std::process::id()))?;
+    let dirfd = dir_handle.as_raw_fd();
+
+    // A stale temp file from a crashed run with the same pid (or a planted link) goes first
+    unsafe { libc::unlinkat(dirfd, tmp.as_ptr(), 0) };
+    let fd = unsafe {
+        libc::openat(
+            dirfd,
+            tmp.as_ptr(),
+            libc::O_WRONLY | libc::O_CREAT | libc::O_EXCL | libc::O_NOFOLLOW | libc::O_CLOEXEC,
+            0o600 as libc::c_uint,
+        )
+    };
+    if fd < 0 {
+        return Err(Error::last_os_error());
+    }
+    let mut file = File::from(unsafe { OwnedFd::from_raw_fd(fd) });
+
+    let written = file.write_all(contents).and_then(|_| file.sync_all());
+    // renameat replaces a symlink at the target, it never writes through it
+    let renamed = written.and_then(|_| {
+        match unsafe { libc::renameat(dirfd, tmp.as_ptr(), dirfd, target.as_ptr()) } {
+            0 => Ok(()),
+            _ => Err(Error::last_os_error()),
+        }
+    });
+    if renamed.is_err() {
+        unsafe { libc::unlinkat(dirfd, tmp.as_ptr(), 0) };
+    }
+    renamed
+}
+
+#[cfg(windows)]
+fn write_in(dir: &Path, name: &str, contents: &[u8]) -> std::io::Result<()> {
+    use std::io::{Error, ErrorKind};
+
+    refuse_reparse_point(dir).map_err(|e| Error::new(ErrorKind::PermissionDenied, e))?;
+    let target = dir.join(name);
+    let tmp = dir.join(format!(".{}.tmp{}", name, std::process::id()));
+    let _ = fs::remove_file(&tmp);
+
+    let mut file = fs::OpenOptions::new().write(true).create_new(true).open(&tmp)?;
+    let written = file.write_all(contents).and_then(|_| file.sync_all());
+    drop(file);
+    let renamed = written.and_then(|_| fs::rename(&tmp, &target));
+    if renamed.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    renamed
+}
+
+#[cfg(windows)]
+fn refuse_reparse_point(path: &Path) -> Result<(), String> {
+    use std::os::windows::fs::MetadataExt;
+
+    const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT != 0 => {
+            Err(format!("Refusing {}: it is a symlink or junction", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::{symlink, PermissionsExt};
+
+    #[test]
+    fn test_write_private_replaces_symlink_without_following() {
+        let dir = tempfile::tempdir().unwrap();
+        let victim = dir.path().join("passwd");
+        fs::write(&victim, "root:x:0:0").unwrap();
+        let state = dir.path().join("state").join("license.json");
+        fs::create_dir(dir.path().join("state")).unwrap();
+        symlink(&victim, &state).unwrap();
+
+        write_private(&state, b"{}").unwrap();
+        assert_eq!(fs::read_to_string(&victim).unwrap(), "root:x:0:0");
+        assert_eq!(fs::read_to_string(&state).unwrap(), "{}");
+        let metadata = fs::symlink_metadata(&state).unwrap();
+        assert!(metadata.is_file());
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+
+        // A symlinked directory is not written through either
+        let linked_dir = dir.path().join("linked");
+        symlink(dir.path().join("state"), &linked_dir).unwrap();
+        assert!(write_private(&linked_dir.join("license.json"), b"{}").is_err());
+
+        // Nor is a symlinked log
+        let log = dir.path().join("killer.log");
+        symlink(&victim, &log).unwrap();
+        assert!(open_append(&log).is_err());
+        assert_eq!(fs::read_to_string(&victim).unwrap(), "root:x:0:0");
+    }
+
+    #[test]
+    fn test_read_config_refuses_symlinks_and_world_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("app.config");
+        fs::write(&config, "{}").unwrap();
+        fs::set_permissions(&config, fs::Permissions::from_mode(0o600)).unwrap();
+        assert_eq!(read_config(&config).unwrap().as_str(), "{}");
+
+        let link = dir.path().join("linked.config");
+        symlink(&config, &link).unwrap();
+        assert!(read_config(&link).unwrap_err().contains("symlink"));
+
+        fs::set_permissions(&config, fs::Permissions::from_mode(0o666)).unwrap();
+        assert!(read_config(&config).unwrap_err().contains("world-writable"));
+
+        assert!(check_name("../escape").is_err());
+        assert!(check_name(".hidden").is_err());
+        assert!(check_name("revocations").is_ok());
+    }
+
+    #[test]
+    fn test_lock_file_excludes_second_holder() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("locks").join("state.lock");
+
+        let held = lock_file(&path).unwrap();
+        assert!(try_lock_file(&path).unwrap().is_none());
+        drop(held);
+        assert!(try_lock_file(&path).unwrap().is_some());
+    }
+}
@@ -0,0 +1,127 @@
+//! Login session awareness for multi-user machines
+//!
+//! On terminal servers several users run the same protected binary at once.
+//! Enforcement must stay inside the offending session: processes of other
+//! sessions are never signalled, and the shared binary is not deleted while
+//! another session is running it. The session identity is also sent with each
+//! verification so the server counts seats per session.
+
+use std::path::Path;
+
+/// Identity of the login session we run in
+#[derive(Debug, Clone)]
+pub struct SessionInfo {
+    pub session_id: String,
+    pub user: String,
+}
+
+/// Session identity of the current process
+pub fn current_session() -> SessionInfo {
+    SessionInfo {
+        session_id: session_of(std::process::id()).unwrap_or_else(|| "unknown".to_string()),
+        user: current_user(),
+    }
+}
+
+/// Login session identifier of a process
+pub fn session_of(pid: u32) -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        // Audit login session (one per login, shared by its whole process tree);
+        // fall back to the POSIX session id when auditing is unavailable
+        const UNSET_SESSION: &str = "4294967295";
+        if let Ok(id) = std::fs::read_to_string(format!("/proc/{}/sessionid", pid)) {
+            let id = id.trim();
+            if !id.is_empty() && id != UNSET_SESSION {
+                return Some(id.to_string());
+            }
+        }
+        let sid = unsafe { libc::getsid(pid as libc::pid_t) };
+        (sid >= 0).then(|| format!("sid-{}", sid))
+    }
+
+    #[cfg(all(unix, not(target_os = "linux")))]
+    {
+        let sid = unsafe { libc::getsid(pid as libc::pid_t) };
+        (sid >= 0).then(|| format!("sid-{}", sid))
+    }
+
+    #[cfg(windows)]
+    {
+        let mut session = 0u32;
+        let ok = unsafe { winapi::um::processthreadsapi::ProcessIdToSessionId(pid, &mut session) };
+        (ok != 0).then(|| session.to_string())
+    }
+}
+
+/// Whether a process belongs to our login session
+pub fn is_same_session(pid: u32) -> bool {
+    match (session_of(pid), session_of(std::process::id())) {
+        (Some(theirs), Some(ours)) => theirs == ours,
+        // Unknown: assume the process is ours, matching behaviour before
+        // session awareness
+        _ => true,
+    }
+}
+
+/// Whether a process outside our session is running the given binary
+///
+/// Deleting it would break other users, so destructive kill methods are
+/// downgraded when this is true.
+pub fn binary_in_use_by_other_sessions(path: &Path) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        let Some(ours) = session_of(std::process::id()) else {
+            return false;
+        };
+
+        list_pids().into_iter().any(|pid| {
+            std::fs::read_link(format!("/proc/{}/exe", pid)).is_ok_and(|exe| exe == path)
+                && session_of(pid).is_some_and(|theirs| theirs != ours)
+        })
+    }
+
+    // Windows locks running executables against deletion anyway, and other
+    // platforms lack a cheap way to map processes to binaries
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn list_pids() -> Vec<u32> {
+    std::fs::read_dir("/proc")
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn current_user() -> String {
+    #[cfg(unix)]
+    {
+        std::env::var("USER").unwrap_or_else(|_| format!("uid-{}", unsafe { libc::getuid() }))
+    }
+
+    #[cfg(windows)]
+    {
+        std::env::var("USERNAME").unwrap_or_else(|_| "unknown".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_own_session() {
+        let session = current_session();
+        assert!(!session.session_id.is_empty());
+        assert!(is_same_session(std::process::id()));
+    }
+}
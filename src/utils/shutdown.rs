@@ -0,0 +1,188 @@
+//! Shutdown coordinator
+//!
+//! `std::process::exit` runs no destructors, so cleanup tied to `Drop` (shared
+//! memory scrubbing, audit log sync, secret zeroization) is lost on every
+//! early exit. Every module therefore leaves through `exit` here instead:
+//! modules `register` their cleanup once, and `exit` runs the hooks - newest
+//! first, each at most once - before the process really exits.
+//!
+//! Kill and crash paths exit through here too, so hooks must be local and
+//! bounded: no network, no blocking on locks other threads may hold. Work
+//! that talks to a server belongs on the graceful paths that need it.
+//!
+//! A hook that exits itself (or an exit from another thread while hooks run)
+//! does not run the hooks twice:
+//! only the first caller runs them, a nested call from that thread exits right
+//! away and other threads wait for it.
+//!
+//! Termination signals (SIGTERM, SIGINT, SIGHUP; console control events on
+//! Windows) would kill the process without any of that. `on_termination`
+//! turns them into a call of the caller's handler, which normally ends in
+//! `exit`.
+
+use std::io::Write;
+use std::sync::Mutex;
+use std::thread::{self, ThreadId};
+
+type Hook = Box<dyn FnOnce() + Send>;
+
+/// Cleanups still to run, in registration order
+static HOOKS: Mutex<Vec<(&'static str, Hook)>> = Mutex::new(Vec::new());
+
+/// Thread running the hooks, once shutdown began
+static EXITING: Mutex<Option<ThreadId>> = Mutex::new(None);
+
+/// A termination request as received
+#[derive(Debug, Clone)]
+pub struct Termination {
+    /// Signal or console event name, e.g. "SIGTERM"
+    pub signal: String,
+    /// Process that sent it, where the platform tells (Linux)
+    pub sender: Option<u32>,
+    /// Exit code of a process ended by it (128 + signal number on Unix)
+    pub exit_code: i32,
+}
+
+/// Register a cleanup to run before the process exits (local and bounded,
+/// see the module docs)
+pub fn register(name: &'static str, hook: impl FnOnce() + Send + 'static) {
+    HOOKS.lock().unwrap_or_else(|e| e.into_inner()).push((name, Box::new(hook)));
+}
+
+/// Run the registered cleanups and exit with `code`
+pub fn exit(code: i32) -> ! {
+    let current = thread::current().id();
+    let first = {
+        let mut exiting = EXITING.lock().unwrap_or_else(|e| e.into_inner());
+        match *exiting {
+            None => {
+                *exiting = Some(current);
+                true
+            }
+            Some(thread) => {
+                if thread != current {
+                    // The first caller exits the process for us
+                    drop(exiting);
+                    loop {
+                        thread::park();
+                    }
+                }
+                false
+            }
+        }
+    };
+
+    if first {
+        run_hooks();
+    }
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stderr().flush();
+    std::process::exit(code)
+}
+
+/// Hand termination signals to `handler` instead of dying on them
+///
+/// Must run before other threads are started: on Unix the signals are
+/// blocked here and waited for by a dedicated task, and threads inherit the
+/// mask of the thread that creates them.
+pub fn on_termination(handler: impl Fn(Termination) + Send + Sync + 'static) {
+    #[cfg(unix)]
+    {
+        use nix::sys::signal::{SigSet, Signal};
+
+        let mut signals = SigSet::empty();
+        for signal in [Signal::SIGTERM, Signal::SIGINT, Signal::SIGHUP] {
+            signals.add(signal);
+        }
+        if let Err(e) = signals.thread_block() {
+            log_warn!("⚠️  Cannot handle termination signals: {}", e);
+            return;
+        }
+        super::tasks::spawn("termination_signal", move || {
+            if let Some((signal, sender)) = wait_for_signal(&signals) {
+                handler(Termination { signal: signal.as_str().to_string(), sender, exit_code: 128 + signal as i32 });
+            }
+        });
+    }
+
+    #[cfg(windows)]
+    {
+        use winapi::shared::minwindef::{BOOL, DWORD, TRUE};
+        use winapi::um::consoleapi::SetConsoleCtrlHandler;
+        use winapi::um::wincon::{CTRL_BREAK_EVENT, CTRL_CLOSE_EVENT, CTRL_C_EVENT, CTRL_LOGOFF_EVENT, CTRL_SHUTDOWN_EVENT};
+
+        type Handler = Box<dyn Fn(Termination) + Send + Sync>;
+        static HANDLER: std::sync::OnceLock<Handler> = std::sync::OnceLock::new();
+        /// Exit code of a console process ended by Ctrl+C (STATUS_CONTROL_C_EXIT)
+        const CONTROL_C_EXIT: i32 = 0xC000_013A_u32 as i32;
+
+        // Runs on a thread of its own; returning TRUE keeps the process alive
+        unsafe extern "system" fn on_ctrl(event: DWORD) -> BOOL {
+            let signal = match event {
+                CTRL_C_EVENT => "CTRL_C",
+                CTRL_BREAK_EVENT => "CTRL_BREAK",
+                CTRL_CLOSE_EVENT => "CTRL_CLOSE",
+                CTRL_LOGOFF_EVENT => "CTRL_LOGOFF",
+                CTRL_SHUTDOWN_EVENT => "CTRL_SHUTDOWN",
+                _ => return 0,
+            };
+            let Some(handler) = HANDLER.get() else {
+                return 0;
+            };
+            handler(Termination { signal: signal.to_string(), sender: None, exit_code: CONTROL_C_EXIT });
+            TRUE
+        }
+
+        if HANDLER.set(Box::new(handler)).is_err() {
+            return;
+        }
+        if unsafe { SetConsoleCtrlHandler(Some(on_ctrl), TRUE) } == 0 {
+            log_warn!("⚠️  Cannot handle console control events: {}", std::io::Error::last_os_error());
+        }
+    }
+}
+
+/// Wait for one of `signals` (blocked), with the sending process
+#[cfg(target_os = "linux")]
+fn wait_for_signal(signals: &nix::sys::signal::SigSet) -> Option<(nix::sys::signal::Signal, Option<u32>)> {
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+    let number = unsafe { libc::sigwaitinfo(signals.as_ref(), &mut info) };
+    let signal = nix::sys::signal::Signal::try_from(number).ok()?;
+    let sender = unsafe { info.si_pid() };
+    Some((signal, (sender > 0).then_some(sender as u32)))
+}
+
+/// Wait for one of `signals` (blocked); the sender is not reported here
+#[cfg(all(unix, not(target_os = "linux")))]
+fn wait_for_signal(signals: &nix::sys::signal::SigSet) -> Option<(nix::sys::signal::Signal, Option<u32>)> {
+    signals.wait().ok().map(|signal| (signal, None))
+}
+
+/// Run (and forget) the registered hooks, newest first
+fn run_hooks() {
+    loop {
+        // Taken one at a time: a hook may register or exit itself
+        let Some((name, hook)) = HOOKS.lock().unwrap_or_else(|e| e.into_inner()).pop() else {
+            return;
+        };
+        log_debug!("🧹 Running shutdown hook '{}'", name);
+        hook();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hooks_run_newest_first_and_once() {
+        static ORDER: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+        register("first", || ORDER.lock().unwrap().push("first"));
+        register("second", || ORDER.lock().unwrap().push("second"));
+        run_hooks();
+        run_hooks();
+
+        assert_eq!(*ORDER.lock().unwrap(), ["second", "first"]);
+    }
+}
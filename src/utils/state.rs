@@ -0,0 +1,294 @@
+//! Persistent per-license state shared across overload invocations
+//!
+//! State lives in one JSON file per license under the state directory
+//! (`KILLCODE_STATE_DIR`, or a per-user platform default). Each feature owns a
+//! section of `PersistentState`; missing sections deserialize to defaults so
+//! older state files keep loading.
+//!
+//! Several products wrapped with different killer versions can share one host
+//! and one state directory. Everything else a process keeps there (event
+//! queue, audit log, fetched artifacts) lives in a directory named after the
+//! license namespace once the config is known (`set_namespace`), so instances
+//! never read or ship each other's files.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use super::secure_fs;
+use crate::security::trust::SuccessorKey;
+use crate::verification::rotation::RotatedSecret;
+
+/// Env var overriding the state directory
+pub const STATE_DIR_ENV: &str = "KILLCODE_STATE_DIR";
+
+/// License namespace of this process (see `set_namespace`)
+static NAMESPACE: OnceLock<String> = OnceLock::new();
+
+/// Everything persisted between runs
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct PersistentState {
+    #[serde(default)]
+    pub cli: CliState,
+    #[serde(default)]
+    pub clock: ClockState,
+    #[serde(default)]
+    pub install: InstallState,
+    /// Last denial verdict (see `verification::denial`)
+    #[serde(default)]
+    pub denial: Option<DenialState>,
+    /// Accepted successor keys of the trust root (see `security::trust`)
+    #[serde(default)]
+    pub trust_chain: Vec<SuccessorKey>,
+    /// Config lint codes already reported (see `config::lint`)
+    #[serde(default)]
+    pub reported_lints: Vec<String>,
+    /// Shared secret rotated by the server (see `verification::rotation`)
+    #[serde(default)]
+    pub rotated_secret: Option<RotatedSecret>,
+    #[serde(default)]
+    pub metering: MeteringState,
+    /// Enforcement pause granted by the server (see `verification::pause`)
+    #[serde(default)]
+    pub pause: Option<PauseState>,
+    /// Overload update version that failed verification or cannot be
+    /// installed here (see `verification::self_update`)
+    #[serde(default)]
+    pub skipped_update: Option<String>,
+    /// Since when enforcement_policy defers enforcement (see
+    /// `security::policy`)
+    #[serde(default)]
+    pub deferral: Option<DeferralState>,
+    /// Failed verifications in a row, kept across restarts (see
+    /// `max_consecutive_failures`)
+    #[serde(default)]
+    pub consecutive_failures: u32,
+}
+
+/// Start of a deferral by the enforcement policy, sealed with the shared
+/// secret
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct DeferralState {
+    /// Unix seconds of the first deferred denial
+    pub since: i64,
+    pub signature: String,
+}
+
+/// Maintenance pause, sealed with the shared secret
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PauseState {
+    /// Server time (unix seconds) enforcement resumes
+    pub until: i64,
+    /// Server time the pause was granted
+    pub granted_at: i64,
+    pub signature: String,
+}
+
+/// Usage metering state (see `verification::metering`)
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct MeteringState {
+    /// Launches of the protected binary on this machine
+    #[serde(default)]
+    pub launches: u64,
+}
+
+/// Cached denial verdict for this machine
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DenialState {
+    pub machine_fingerprint: String,
+    pub denied_at: i64,
+    pub expires_at: i64,
+    pub message: String,
+}
+
+/// Install identity continuity state (see `verification::install`)
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct InstallState {
+    /// Random identifier of this install, kept across updates
+    #[serde(default)]
+    pub install_id: Option<String>,
+    /// SHA-256 of the binary seen on the last run
+    #[serde(default)]
+    pub binary_hash: Option<String>,
+    #[serde(default)]
+    pub binary_path: Option<String>,
+    /// Machine fingerprint seen on the last run (drift is reported)
+    #[serde(default)]
+    pub machine_fingerprint: Option<String>,
+}
+
+/// Clock tampering detection state (see `security::clock`)
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct ClockState {
+    /// Highest trusted wall-clock time seen (unix seconds)
+    #[serde(default)]
+    pub high_water: i64,
+}
+
+/// Short-lived CLI mode state (see `execution::cli`)
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct CliState {
+    /// Total invocations seen on this machine
+    #[serde(default)]
+    pub invocations: u64,
+    /// Invocations served from the cached token and not yet reported
+    #[serde(default)]
+    pub pending_usage: u64,
+    /// Cached token from the last full verification
+    #[serde(default)]
+    pub token: Option<CliToken>,
+}
+
+/// Locally cached proof of a successful verification
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CliToken {
+    /// Unix timestamp after which a full verification is required
+    pub expires_at: i64,
+    /// Machine fingerprint the token was issued for
+    pub machine_fingerprint: String,
+    /// HMAC over license_id, fingerprint and expiry (prevents local edits)
+    pub mac: String,
+}
+
+/// Handle to the state file of one license
+pub struct StateStore {
+    path: PathBuf,
+}
+
+impl StateStore {
+    /// Open the store for a license (the file is created on first save)
+    pub fn for_license(license_id: &str) -> Self {
+        Self {
+            path: state_dir().join(format!("{}.json", namespace_of(license_id))),
+        }
+    }
+
+    /// Load state, falling back to defaults if missing or unreadable
+    pub fn load(&self) -> PersistentState {
+        match fs::read_to_string(&self.path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                log_warn!("⚠️  Ignoring corrupt state file {}: {}", self.path.display(), e);
+                PersistentState::default()
+            }),
+            Err(_) => PersistentState::default(),
+        }
+    }
+
+    /// Save state atomically (0600 temp file + rename, symlinks not followed)
+    pub fn save(&self, state: &PersistentState) -> Result<(), String> {
+        let json = serde_json::to_string(state)
+            .map_err(|e| format!("Failed to serialize state: {}", e))?;
+
+        secure_fs::write_private(&self.path, json.as_bytes())
+            .map_err(|e| format!("Failed to save state: {}", e))
+    }
+
+    /// Load, modify and save state under an exclusive lock, so concurrent
+    /// overloads (CLI invocations, helpers) do not lose each other's updates
+    pub fn update<T>(&self, f: impl FnOnce(&mut PersistentState) -> T) -> Result<T, String> {
+        let _lock = secure_fs::lock_file(&self.path.with_extension("lock"))?;
+        let mut state = self.load();
+        let result = f(&mut state);
+        self.save(&state)?;
+        Ok(result)
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+}
+
+/// Namespace of host-global resources of a license (16 hex digits, does not
+/// reveal the license ID)
+pub fn namespace_of(license_id: &str) -> String {
+    let digest = hex::encode(Sha256::digest(license_id.as_bytes()));
+    digest[..16].to_string()
+}
+
+/// Scope this process's files to `license_id` (first call wins)
+pub fn set_namespace(license_id: &str) {
+    let _ = NAMESPACE.set(namespace_of(license_id));
+}
+
+/// License namespace of this process, once the config is loaded
+pub fn namespace() -> Option<&'static str> {
+    NAMESPACE.get().map(String::as_str)
+}
+
+/// Directory of this license's files (the state directory itself until the
+/// namespace is known)
+pub fn namespace_dir() -> PathBuf {
+    match namespace() {
+        Some(namespace) => state_dir().join(namespace),
+        None => state_dir(),
+    }
+}
+
+/// Resolve the state directory
+pub fn state_dir() -> PathBuf {
+    if let Ok(dir) = std::env::var(STATE_DIR_ENV) {
+        return PathBuf::from(dir);
+    }
+
+    #[cfg(windows)]
+    {
+        if let Ok(dir) = std::env::var("LOCALAPPDATA") {
+            return PathBuf::from(dir).join("killcode");
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        if let Ok(home) = std::env::var("HOME") {
+            return PathBuf::from(home).join("Library/Application Support/killcode");
+        }
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        if let Ok(dir) = std::env::var("XDG_STATE_HOME") {
+            return PathBuf::from(dir).join("killcode");
+        }
+        if let Ok(home) = std::env::var("HOME") {
+            return PathBuf::from(home).join(".local/state/killcode");
+        }
+    }
+
+    std::env::temp_dir().join("killcode")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore {
+            path: dir.path().join("state.json"),
+        };
+
+        assert_eq!(store.load().cli.invocations, 0);
+
+        let mut state = PersistentState::default();
+        state.cli.invocations = 7;
+        state.cli.pending_usage = 3;
+        store.save(&state).unwrap();
+
+        let loaded = store.load();
+        assert_eq!(loaded.cli.invocations, 7);
+        assert_eq!(loaded.cli.pending_usage, 3);
+    }
+
+    #[test]
+    fn test_corrupt_state_falls_back_to_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        fs::write(&path, "{ not json").unwrap();
+
+        let store = StateStore { path };
+        assert_eq!(store.load().cli.invocations, 0);
+    }
+}
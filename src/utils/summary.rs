@@ -0,0 +1,121 @@
+//! Single-line summary mode for CI pipelines (`--quiet-summary`)
+//!
+//! Build pipelines gate artifact publishing on a one-shot verification and
+//! want exactly one machine-parseable line on stdout, nothing on stderr, and
+//! an exit code that tells the failure classes apart. Summary mode silences
+//! the logger, forces a single check and prints one JSON line at the end.
+//! It changes output only: enforcement runs exactly as without it.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use super::redact;
+
+/// Command-line flag enabling summary mode
+pub const QUIET_SUMMARY_FLAG: &str = "--quiet-summary";
+
+/// Env var enabling summary mode where a flag cannot be passed (ignored under
+/// the parent wrapper, like the flag)
+pub const QUIET_SUMMARY_ENV: &str = "KILLCODE_QUIET_SUMMARY";
+
+/// When summary mode was enabled
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// Whether the summary line has been printed
+static EMITTED: AtomicBool = AtomicBool::new(false);
+
+/// Verification outcome as reported in the summary
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Authorized,
+    Unauthorized,
+    /// Verification could not complete (network, server)
+    Error,
+    /// No usable configuration
+    ConfigError,
+}
+
+impl Outcome {
+    /// Exit code in summary mode
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Outcome::Authorized => 0,
+            Outcome::Unauthorized => 1,
+            Outcome::Error => 2,
+            Outcome::ConfigError => 3,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Summary<'a> {
+    result: Outcome,
+    exit_code: i32,
+    message: &'a str,
+    duration_ms: u128,
+}
+
+/// Whether summary mode was requested by flag or env var
+///
+/// Both are only honored outside the parent wrapper, like subcommands: under
+/// the wrapper a one-shot check would replace continuous enforcement.
+pub fn requested() -> bool {
+    if super::health_monitor::under_wrapper() {
+        return false;
+    }
+    std::env::args().skip(1).any(|arg| arg == QUIET_SUMMARY_FLAG)
+        || std::env::var(QUIET_SUMMARY_ENV).is_ok_and(|v| v == "1" || v == "true")
+}
+
+/// Enter summary mode: nothing but the summary line is printed from now on
+pub fn enable() {
+    STARTED.get_or_init(Instant::now);
+    super::logger::silence();
+}
+
+pub fn enabled() -> bool {
+    STARTED.get().is_some()
+}
+
+/// Print the summary line (summary mode only, at most once per process)
+pub fn emit(outcome: Outcome, message: &str) {
+    let Some(started) = STARTED.get() else {
+        return;
+    };
+    if EMITTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let message = redact::scrub(message);
+    let summary = Summary {
+        result: outcome,
+        exit_code: outcome.exit_code(),
+        message: &message,
+        duration_ms: started.elapsed().as_millis(),
+    };
+    if let Ok(line) = serde_json::to_string(&summary) {
+        println!("{}", line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_line_shape() {
+        let summary = Summary {
+            result: Outcome::ConfigError,
+            exit_code: Outcome::ConfigError.exit_code(),
+            message: "no config",
+            duration_ms: 12,
+        };
+        assert_eq!(
+            serde_json::to_string(&summary).unwrap(),
+            r#"{"result":"config_error","exit_code":3,"message":"no config","duration_ms":12}"#
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Supervised background threads
+//!
+//! Every background thread is started through `spawn` instead of bare
+//! `thread::spawn`, so that it is tracked by name while running (`active`).
+//!
+//! Panics are not caught here: release builds abort on panic, so a panicking
+//! task takes the process down through the crash handler, which fails closed
+//! (see `verification::crash`). Nothing is retried in-process.
+//!
+//! A thread cannot outlive the process: work that must finish after the
+//! process exits belongs in a detached helper process, not in a task.
+
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+
+/// Names of the tasks currently running
+static ACTIVE: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+/// Handle of a supervised task
+pub struct Task<T> {
+    name: &'static str,
+    handle: JoinHandle<T>,
+}
+
+impl<T> Task<T> {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Wait for the task
+    ///
+    /// # Returns
+    /// The task's result, or Err with the panic message if it panicked
+    /// (builds that unwind)
+    pub fn join(self) -> Result<T, String> {
+        self.handle
+            .join()
+            .map_err(|payload| format!("task '{}' panicked: {}", self.name, panic_message(payload.as_ref())))
+    }
+}
+
+/// Start a supervised background task
+pub fn spawn<T, F>(name: &'static str, task: F) -> Task<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    ACTIVE.lock().unwrap_or_else(|e| e.into_inner()).push(name);
+
+    let handle = thread::spawn(move || {
+        let _tracked = Tracked(name);
+        task()
+    });
+
+    Task { name, handle }
+}
+
+/// Names of the tasks currently running
+pub fn active() -> Vec<&'static str> {
+    ACTIVE.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Removes a task from `ACTIVE` when its thread ends, however it ends
+struct Tracked(&'static str);
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        let mut active = ACTIVE.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(pos) = active.iter().position(|name| *name == self.0) {
+            active.remove(pos);
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panic_reported_on_join() {
+        let task = spawn("test_panic", || -> u32 { panic!("boom") });
+
+        let err = task.join().unwrap_err();
+        assert!(err.contains("test_panic") && err.contains("boom"));
+        assert!(!active().contains(&"test_panic"));
+
+        assert_eq!(spawn("test_result", || 42).join(), Ok(42));
+    }
+}
@@ -0,0 +1,126 @@
+//! Wall-clock time for protocol timestamps and expiries
+//!
+//! All wall-clock values are UTC unix seconds, so nothing depends on the
+//! local timezone or locale. Two real-world effects are handled centrally:
+//! - Skew: a verify response carries the server's clock in `server_time`;
+//!   once its signature checked out, the offset to the local clock is
+//!   applied to timestamps sent to the server, so a wrong local clock does
+//!   not push signed requests out of the server's replay window. Unsigned
+//!   clocks (the `Date` header) are never learned: whoever sits in between
+//!   could shift our timestamps with them. Offsets beyond `MAX_SKEW_SECS`
+//!   are refused: such a clock must be fixed, not hidden.
+//! - Leap seconds and leap smearing: two clocks (local vs server) that agree
+//!   up to `LEAP_TOLERANCE_SECS` are treated as equal, so a smeared or not
+//!   yet applied leap second never counts as drift. Expiries compare one
+//!   clock with itself; a leap second only repeats a second and never skips
+//!   one, so they stay exact.
+//!
+//! Scheduling does not use wall-clock time at all (see `Instant`).
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Disagreement absorbed by leap seconds / smearing
+pub const LEAP_TOLERANCE_SECS: i64 = 1;
+
+/// Largest server offset compensated (one hour)
+pub const MAX_SKEW_SECS: i64 = 3600;
+
+/// Server minus local clock, learned from signed responses (seconds)
+static SERVER_OFFSET: AtomicI64 = AtomicI64::new(0);
+
+/// Local UTC time in unix seconds (0 if the clock is before 1970)
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Server-aligned time for timestamps sent to the server
+pub fn protocol_now() -> i64 {
+    unix_now().saturating_add(server_offset())
+}
+
+/// Remember the server's clock from a response
+///
+/// # Returns
+/// Err (keeping the previous offset) if the clocks are more than
+/// `MAX_SKEW_SECS` apart
+pub fn record_server_time(server_time: i64, local_now: i64) -> Result<(), String> {
+    SERVER_OFFSET.store(skew(server_time, local_now)?, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Offset to compensate for a server clock reading `server_time` at `local_now`
+///
+/// # Returns
+/// Err if the clocks are more than `MAX_SKEW_SECS` apart
+pub fn skew(server_time: i64, local_now: i64) -> Result<i64, String> {
+    let offset = drift(server_time, local_now);
+    if offset.unsigned_abs() > MAX_SKEW_SECS as u64 {
+        return Err(format!(
+            "local clock is {}s off the server's (more than {}s cannot be compensated)",
+            offset, MAX_SKEW_SECS
+        ));
+    }
+    Ok(offset)
+}
+
+/// Current server offset (seconds)
+pub fn server_offset() -> i64 {
+    SERVER_OFFSET.load(Ordering::Relaxed)
+}
+
+/// Difference `a - b`, with leap-second-sized differences treated as zero
+pub fn drift(a: i64, b: i64) -> i64 {
+    let diff = a.saturating_sub(b);
+    if diff.unsigned_abs() <= LEAP_TOLERANCE_SECS as u64 { 0 } else { diff }
+}
+
+/// Expiry for a relative lifetime (negative lifetimes expire immediately)
+pub fn expires_at(now: i64, expires_in: i64) -> i64 {
+    now.saturating_add(expires_in.max(0))
+}
+
+/// Whether `expires_at` has been reached at `now`
+pub fn is_expired(expires_at: i64, now: i64) -> bool {
+    now >= expires_at
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drift_tolerates_leap_second() {
+        assert_eq!(drift(1_000, 999), 0);
+        assert_eq!(drift(999, 1_000), 0);
+        assert_eq!(drift(1_010, 1_000), 10);
+        assert_eq!(drift(i64::MIN, 1), i64::MIN);
+    }
+
+    #[test]
+    fn test_expiry() {
+        let at = expires_at(1_000, 60);
+        assert_eq!(at, 1_060);
+        assert!(!is_expired(at, 1_059));
+        assert!(is_expired(at, 1_060));
+
+        assert_eq!(expires_at(1_000, -5), 1_000);
+        assert_eq!(expires_at(i64::MAX - 1, 60), i64::MAX);
+    }
+
+    // The offset itself is process-global (and used by concurrently running
+    // tests): only the pure computation is tested
+    #[test]
+    fn test_server_skew_capped() {
+        assert_eq!(skew(10_120, 10_000), Ok(120));
+        assert_eq!(skew(10_001, 10_000), Ok(0));
+        assert_eq!(skew(10_000 + MAX_SKEW_SECS, 10_000), Ok(MAX_SKEW_SECS));
+
+        // Beyond the limit: refused
+        assert!(skew(10_000 + MAX_SKEW_SECS + 1, 10_000).is_err());
+        assert!(skew(10_000 - MAX_SKEW_SECS - 1, 10_000).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! Fleet-wide signed broadcasts: revocation list and policy bundle
+//!
+//! Two artifacts are published once for the whole fleet instead of being
+//! answered per check:
+//! - the revocation list (`revocation_url`): licenses revoked before their
+//!   lease runs out. A license on it is denied even when the server (or its
+//!   fallback) still authorizes it.
+//! - the policy bundle (`policy_bundle_url`): an `enforcement_policy` the
+//!   vendor can change without shipping new configs. It replaces the
+//!   configured one.
+//!
+//! Both are downloaded through `fetch` on every check, so an unchanged
+//! artifact costs one 304. They come in the envelope of offline license
+//! files - the exact JSON string that was signed plus a hex Ed25519
+//! signature:
+//!
+//! ```json
+//! {"payload": "{\"license_ids\":[\"lic_123\"]}", "signature": "<hex>"}
+//! ```
+//!
+//! signed for `revocation-list` or `policy-bundle` (see `security::trust`).
+//! An artifact that cannot be fetched or verified changes nothing.
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use super::fetch;
+use super::network::VerifyResponse;
+use crate::config::Config;
+use crate::security::trust;
+use crate::utils::redact;
+
+/// Signature context of the revocation list
+pub const REVOCATION_CONTEXT: &str = "revocation-list";
+
+/// Signature context of the policy bundle
+pub const POLICY_BUNDLE_CONTEXT: &str = "policy-bundle";
+
+/// Artifact as distributed
+#[derive(Debug, Deserialize)]
+struct SignedArtifact {
+    /// JSON of the artifact, exactly as signed
+    payload: String,
+    /// Hex Ed25519 signature
+    signature: String,
+}
+
+/// Revoked licenses
+#[derive(Debug, Deserialize)]
+struct RevocationList {
+    #[serde(default)]
+    license_ids: Vec<String>,
+}
+
+/// Fleet-wide enforcement policy
+#[derive(Debug, Deserialize)]
+struct PolicyBundle {
+    enforcement_policy: String,
+}
+
+/// `response`, turned into a denial if the revocation list names the license
+pub fn apply_revocations(config: &Config, response: VerifyResponse) -> VerifyResponse {
+    let Some(url) = config.revocation_url.as_deref() else {
+        return response;
+    };
+    if !response.authorized {
+        return response;
+    }
+    match load::<RevocationList>(config, url, "revocations", REVOCATION_CONTEXT) {
+        Ok(list) if list.license_ids.contains(&config.license_id) => {
+            log_error!("⛔ License {} is on the revocation list", redact::identifier(&config.license_id));
+            VerifyResponse {
+                authorized: false,
+                message: "License revoked".to_string(),
+                signature_valid: true,
+                ..Default::default()
+            }
+        }
+        Ok(_) => response,
+        Err(e) => {
+            log_warn!("⚠️  Revocation list unavailable: {}", e);
+            response
+        }
+    }
+}
+
+/// Enforcement policy of the published policy bundle, if one is configured
+/// and verifies
+pub fn policy_bundle(config: &Config) -> Option<String> {
+    let url = config.policy_bundle_url.as_deref()?;
+    let bundle = match load::<PolicyBundle>(config, url, "policy-bundle", POLICY_BUNDLE_CONTEXT) {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            log_warn!("⚠️  Policy bundle unavailable: {}", e);
+            return None;
+        }
+    };
+    #[cfg(feature = "policy")]
+    if let Err(e) = crate::security::policy::compile(&bundle.enforcement_policy) {
+        log_warn!("⚠️  Policy bundle rejected: {}", e);
+        return None;
+    }
+    Some(bundle.enforcement_policy)
+}
+
+fn load<T: DeserializeOwned>(config: &Config, url: &str, name: &str, context: &str) -> Result<T, String> {
+    let fetched = fetch::fetch(url, name)?;
+    parse(&fetched.body, |payload, signature| {
+        trust::verify_signed_blob(&config.license_id, context, payload, signature)
+    })
+}
+
+/// The artifact in `body`, if `verify` accepts its signature
+fn parse<T: DeserializeOwned>(
+    body: &[u8],
+    verify: impl FnOnce(&[u8], &str) -> Result<(), String>,
+) -> Result<T, String> {
+    let artifact: SignedArtifact = serde_json::from_slice(body).map_err(|e| format!("malformed artifact: {}", e))?;
+    verify(artifact.payload.as_bytes(), &artifact.signature).map_err(|e| format!("signature rejected: {}", e))?;
+    serde_json::from_str(&artifact.payload).map_err(|e| format!("malformed payload: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_checks_the_signed_payload() {
+        let body = serde_json::json!({"payload": r#"{"license_ids":["lic_1"]}"#, "signature": "ab"}).to_string();
+
+        let list: RevocationList = parse(body.as_bytes(), |payload, signature| {
+            assert_eq!(payload, br#"{"license_ids":["lic_1"]}"#);
+            assert_eq!(signature, "ab");
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(list.license_ids, ["lic_1"]);
+
+        let forged = parse::<RevocationList>(body.as_bytes(), |_, _| Err("bad signature".to_string()));
+        assert!(forged.unwrap_err().contains("signature rejected"));
+        assert!(parse::<PolicyBundle>(b"{}", |_, _| Ok(())).is_err());
+    }
+}
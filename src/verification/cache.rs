@@ -0,0 +1,143 @@
+//! Local cache of the last verification response
+//!
+//! The protected application (through the SDK/FFI) regularly asks whether it is
+//! licensed and which entitlements it holds. Those questions are answered from
+//! this snapshot instead of triggering a network round trip.
+
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::network::VerifyResponse;
+use crate::utils::{secure_fs, time};
+
+/// Env var naming a file that receives the JSON status snapshot after each check
+pub const STATUS_FILE_ENV: &str = "KILLCODE_STATUS_FILE";
+
+struct CacheEntry {
+    response: VerifyResponse,
+    verified_at: Instant,
+    verified_at_unix: i64,
+}
+
+static LAST_RESPONSE: Mutex<Option<CacheEntry>> = Mutex::new(None);
+
+/// Snapshot of the cached response with age metadata
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheSnapshot {
+    pub authorized: bool,
+    pub message: String,
+    pub expires_in: Option<i64>,
+    pub entitlements: Vec<String>,
+    /// Unix timestamp (seconds) of the response
+    pub verified_at: i64,
+    /// Age of the response in milliseconds at snapshot time
+    pub age_ms: u64,
+}
+
+impl CacheSnapshot {
+    pub fn age(&self) -> Duration {
+        Duration::from_millis(self.age_ms)
+    }
+
+    /// Check whether an entitlement was granted by the last response
+    pub fn has_entitlement(&self, name: &str) -> bool {
+        self.entitlements.iter().any(|e| e == name)
+    }
+}
+
+/// Store a server response as the latest known verification result
+pub fn store(response: &VerifyResponse) {
+    let verified_at_unix = time::unix_now();
+
+    if let Ok(mut slot) = LAST_RESPONSE.lock() {
+        *slot = Some(CacheEntry {
+            response: response.clone(),
+            verified_at: Instant::now(),
+            verified_at_unix,
+        });
+    }
+
+    publish();
+}
+
+/// Get a snapshot of the last response (None if no check has completed yet)
+pub fn snapshot() -> Option<CacheSnapshot> {
+    let slot = LAST_RESPONSE.lock().ok()?;
+    let entry = slot.as_ref()?;
+
+    Some(CacheSnapshot {
+        authorized: entry.response.authorized,
+        message: entry.response.message.clone(),
+        expires_in: entry.response.expires_in,
+        entitlements: entry.response.entitlements.clone(),
+        verified_at: entry.verified_at_unix,
+        age_ms: entry.verified_at.elapsed().as_millis() as u64,
+    })
+}
+
+/// Whether the last response authorized us and is not older than `max_age`
+pub fn is_licensed(max_age: Duration) -> bool {
+    snapshot().is_some_and(|s| s.authorized && s.age() <= max_age)
+}
+
+/// Forget the cached response
+pub fn clear() {
+    if let Ok(mut slot) = LAST_RESPONSE.lock() {
+        *slot = None;
+    }
+}
+
+/// Write the current snapshot to the status file, if one was requested
+fn publish() {
+    let Ok(path) = std::env::var(STATUS_FILE_ENV) else {
+        return;
+    };
+    let Some(snapshot) = snapshot() else {
+        return;
+    };
+
+    let json = match serde_json::to_string(&snapshot) {
+        Ok(json) => json,
+        Err(e) => {
+            log_warn!("⚠️  Failed to serialize status snapshot: {}", e);
+            return;
+        }
+    };
+
+    // Replaced atomically, so readers never see a partial snapshot, and
+    // never written through a planted symlink
+    if let Err(e) = secure_fs::write_private(Path::new(&path), json.as_bytes()) {
+        log_warn!("⚠️  Failed to write status file: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_and_snapshot() {
+        let response = VerifyResponse {
+            authorized: true,
+            message: "ok".to_string(),
+            expires_in: Some(3600),
+            entitlements: vec!["pro".to_string()],
+            ..Default::default()
+        };
+
+        store(&response);
+
+        let snap = snapshot().unwrap();
+        assert!(snap.authorized);
+        assert!(snap.has_entitlement("pro"));
+        assert!(!snap.has_entitlement("enterprise"));
+        assert!(snap.age() < Duration::from_secs(5));
+        assert!(is_licensed(Duration::from_secs(60)));
+
+        clear();
+        assert!(snapshot().is_none());
+        assert!(!is_licensed(Duration::from_secs(60)));
+    }
+}
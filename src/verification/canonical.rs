@@ -0,0 +1,162 @@
+//! Canonical JSON (RFC 8785 / JCS) for signed payloads
+//!
+//! A signature over JSON only verifies if both sides hash the same bytes, and
+//! serializers disagree on key order, whitespace, escaping and number format.
+//! Every JSON signature computed in `verification` covers the canonical form:
+//! - object members sorted by the UTF-16 code units of their keys
+//! - no insignificant whitespace
+//! - strings escaped minimally (`"`, `\`, control characters; `\u00xx`
+//!   lowercase), everything else literal UTF-8
+//! - floats in ECMAScript `Number.prototype.toString` form (`4.5`, `1e+30`,
+//!   `1e-7`); integers as written (our integers stay below 2^53)
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Serialize a value to canonical JSON
+pub fn to_string<T: Serialize>(value: &T) -> Result<String, String> {
+    let value = serde_json::to_value(value).map_err(|e| format!("Failed to serialize payload: {}", e))?;
+    let mut out = String::new();
+    write_value(&value, &mut out);
+    Ok(out)
+}
+
+/// Canonical form of a JSON document
+pub fn canonicalize(json: &str) -> Result<String, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| format!("Invalid JSON: {}", e))?;
+    to_string(&value)
+}
+
+fn write_value(value: &Value, out: &mut String) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => match (n.as_i64(), n.as_u64(), n.as_f64()) {
+            (Some(i), _, _) => out.push_str(&i.to_string()),
+            (_, Some(u), _) => out.push_str(&u.to_string()),
+            (_, _, Some(f)) => out.push_str(&format_number(f)),
+            _ => out.push_str("null"),
+        },
+        Value::String(s) => write_string(s, out),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut members: Vec<(&String, &Value)> = map.iter().collect();
+            members.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+
+            out.push('{');
+            for (i, (key, member)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(key, out);
+                out.push(':');
+                write_value(member, out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// ECMAScript Number-to-String of a finite double
+fn format_number(f: f64) -> String {
+    if f == 0.0 || !f.is_finite() {
+        return "0".to_string();
+    }
+
+    // Shortest round-trip digits and decimal exponent, e.g. "4.5e0"
+    let sci = format!("{:e}", f.abs());
+    let (mantissa, exponent) = sci.split_once('e').unwrap_or((&sci, "0"));
+    let digits: String = mantissa.chars().filter(|c| c.is_ascii_digit()).collect();
+    let k = digits.len() as i32;
+    let n = exponent.parse::<i32>().unwrap_or(0) + 1;
+
+    let body = if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat((-n) as usize), digits)
+    } else {
+        let sign = if n - 1 < 0 { '-' } else { '+' };
+        let fraction = if k > 1 { format!(".{}", &digits[1..]) } else { String::new() };
+        format!("{}{}e{}{}", &digits[..1], fraction, sign, (n - 1).abs())
+    };
+
+    if f < 0.0 { format!("-{}", body) } else { body }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Shared vectors (RFC 8785 sections 3.2.2/3.2.3 and appendix B); the
+    /// server-side implementations are tested against the same list
+    #[test]
+    fn test_cross_language_vectors() {
+        let vectors = [
+            (
+                r#"{"numbers":[333333333.33333329,1E30,4.50,2e-3,0.000000000000000000000000001],"string":"\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/","literals":[null,true,false]}"#,
+                r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#,
+            ),
+            (
+                r#"{"\u20ac":"Euro Sign","\r":"Carriage Return","\ufb33":"Hebrew Letter Dalet With Dagesh","1":"One","\ud83d\ude00":"Emoji: Grinning Face","\u0080":"Control","\u00f6":"Latin Small Letter O With Diaeresis"}"#,
+                "{\"\\r\":\"Carriage Return\",\"1\":\"One\",\"\u{80}\":\"Control\",\"\u{f6}\":\"Latin Small Letter O With Diaeresis\",\"\u{20ac}\":\"Euro Sign\",\"\u{1f600}\":\"Emoji: Grinning Face\",\"\u{fb33}\":\"Hebrew Letter Dalet With Dagesh\"}",
+            ),
+            (
+                "{ \"b\" : [ 1 , { \"d\" : 2 , \"c\" : 3 } ] ,\n \"a\" : \"x\" }",
+                r#"{"a":"x","b":[1,{"c":3,"d":2}]}"#,
+            ),
+        ];
+
+        for (input, expected) in vectors {
+            assert_eq!(canonicalize(input).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_number_formatting() {
+        let vectors = [
+            (0.0, "0"),
+            (-0.0, "0"),
+            (4.5, "4.5"),
+            (-1.5, "-1.5"),
+            (0.000001, "0.000001"),
+            (1e-7, "1e-7"),
+            (1e21, "1e+21"),
+            (999999999999999900000.0, "999999999999999900000"),
+            (5e-324, "5e-324"),
+            (1.7976931348623157e308, "1.7976931348623157e+308"),
+        ];
+
+        for (value, expected) in vectors {
+            assert_eq!(format_number(value), expected, "{:?}", value);
+        }
+    }
+}
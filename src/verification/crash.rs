@@ -0,0 +1,290 @@
+//! Crash reports and fail-closed panics
+//!
+//! A panicking overload must not leave the protected app running unverified.
+//! `install` sets a panic hook that
+//! 1. writes a crash report, signed with the shared secret, into the crash
+//!    file `arm` opened in the license's `crash/` state directory
+//! 2. runs the fail-closed action (`panic_action`): stop the protected app
+//!    (`kill_base`) or only exit (`exit`), with `ExitStatus::Crashed` (70)
+//!
+//! The hook does nothing that could block on state the panicking thread may
+//! hold: no logging (the logger is muted first), no network, one write(2) to
+//! a file opened in advance. The next start sends pending reports
+//! (`send_pending_in_background`).
+//!
+//! Release builds abort on panic, so the hook acts itself, whichever thread
+//! panicked. With unwinding, a panic reaching `main` is caught by `guard`,
+//! which acts then.
+//!
+//! Until `arm` ran (support subcommands, detached helpers, summary mode) a
+//! panic only exits: our parent may be a shell rather than the app.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::panic::{self, PanicHookInfo, UnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::OnceLock;
+
+use serde::Serialize;
+
+use super::canonical;
+use super::hmac::{create_signature, verify_signature};
+use super::network::post_signed;
+use crate::config::{Config, PanicAction};
+use crate::security::{kill_parent, lineage};
+use crate::utils::exit_status::{self, ExitStatus};
+use crate::utils::tasks;
+use crate::utils::{logger, process, secure_fs, session, state, time};
+
+/// API path of the crash report endpoint
+const CRASH_REPORT_PATH: &str = "/api/v1/crash-report";
+
+/// Configuration of an armed overload
+static ARMED: OnceLock<Config> = OnceLock::new();
+
+/// Process the fail-closed action stops (0 = our parent)
+static TARGET: AtomicU32 = AtomicU32::new(0);
+
+/// Set while a panic is being handled
+static HANDLING: AtomicBool = AtomicBool::new(false);
+
+/// Crash file opened by `arm`, written at most once
+static CRASH_FILE: OnceLock<File> = OnceLock::new();
+static CRASH_WRITTEN: AtomicBool = AtomicBool::new(false);
+
+/// Crash report payload
+#[derive(Debug, Serialize)]
+struct CrashReport<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    license_id: Option<&'a str>,
+    version: &'static str,
+    thread: &'a str,
+    message: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<String>,
+    timestamp: i64,
+}
+
+/// Crash file contents
+#[derive(Debug, Serialize)]
+struct CrashFile<'a> {
+    #[serde(flatten)]
+    report: &'a CrashReport<'a>,
+    /// HMAC-SHA256 of the canonical report (absent before the config loaded)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+}
+
+/// Install the panic hook (first thing in `main`)
+pub fn install() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        previous(info);
+        on_panic(info);
+    }));
+}
+
+/// Enable the fail-closed action and signed reports for `config`
+pub fn arm(config: &Config) {
+    if ARMED.set(config.clone()).is_err() {
+        return;
+    }
+    let path = crash_dir(config).join(format!("{}-{}.json", time::unix_now(), std::process::id()));
+    match secure_fs::create_private(&path) {
+        Ok(file) => {
+            let _ = CRASH_FILE.set(file);
+        }
+        Err(e) => log_warn!("⚠️  Cannot open a crash file, crashes will not be reported: {}", e),
+    }
+}
+
+/// Stop `pid` instead of our parent on a crash (a base we spawned ourselves)
+pub fn set_target(pid: u32) {
+    TARGET.store(pid, Ordering::Relaxed);
+}
+
+/// Run `main`, failing closed if a panic unwinds out of it
+pub fn guard(main: impl FnOnce() + UnwindSafe) {
+    if panic::catch_unwind(main).is_err() {
+        fail_closed();
+    }
+}
+
+fn on_panic(info: &PanicHookInfo) {
+    // A panic while reporting one: the first handler finishes the job. Under
+    // abort, returning would abort the process before it failed closed.
+    if HANDLING.swap(true, Ordering::SeqCst) {
+        if cfg!(panic = "abort") {
+            loop {
+                std::thread::park();
+            }
+        }
+        return;
+    }
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string());
+    let thread = std::thread::current();
+    write_report(&CrashReport {
+        license_id: ARMED.get().map(|config| config.license_id.as_str()),
+        version: env!("CARGO_PKG_VERSION"),
+        thread: thread.name().unwrap_or("unnamed"),
+        message: &message,
+        location: info.location().map(|location| location.to_string()),
+        timestamp: time::unix_now(),
+    });
+
+    if cfg!(panic = "abort") {
+        // The panicking thread may hold the logger
+        logger::mute();
+        fail_closed();
+    }
+    HANDLING.store(false, Ordering::SeqCst);
+}
+
+/// Write the report into the crash file opened by `arm` (panic hook: one
+/// write, no locks)
+fn write_report(report: &CrashReport) {
+    let (Some(config), Some(mut file)) = (ARMED.get(), CRASH_FILE.get()) else {
+        return;
+    };
+    if CRASH_WRITTEN.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let signature = canonical::to_string(report).ok().map(|json| create_signature(&json, &config.shared_secret));
+    if let Ok(json) = serde_json::to_vec(&CrashFile { report, signature }) {
+        let _ = file.write_all(&json);
+    }
+}
+
+/// Send the crash reports earlier runs left for this license
+pub fn send_pending_in_background(config: &Config) {
+    let config = config.clone();
+    tasks::spawn("crash_reports", move || send_pending(&config));
+}
+
+fn send_pending(config: &Config) {
+    for path in pending_reports(&crash_dir(config)) {
+        let Some(report) = read_report(&path, config) else {
+            log_warn!("⚠️  Discarding invalid crash report {}", path.display());
+            let _ = fs::remove_file(&path);
+            continue;
+        };
+        match post_signed(&config.get_server_url(), CRASH_REPORT_PATH, &config.license_id, &config.shared_secret, &report) {
+            Ok(status) if status == 200 || status == 202 => {
+                log_info!("📤 Sent crash report {}", path.display());
+                let _ = fs::remove_file(&path);
+            }
+            Ok(status) => log_warn!("⚠️  Crash report rejected with HTTP {}", status),
+            Err(e) => {
+                log_warn!("⚠️  Failed to send crash report, will retry at the next start: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+/// Crash reports waiting to be sent; crash files of runs that ended without
+/// a crash are removed
+fn pending_reports(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut pending = Vec::new();
+    for path in entries.flatten().map(|entry| entry.path()) {
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if metadata.len() > 0 {
+            pending.push(path);
+            continue;
+        }
+        // An empty file may still belong to a running overload
+        let pid = path
+            .file_stem()
+            .and_then(|stem| stem.to_str()?.rsplit('-').next()?.parse::<u32>().ok());
+        if pid.is_none_or(|pid| pid != std::process::id() && process::start_time(pid).is_none()) {
+            let _ = fs::remove_file(&path);
+        }
+    }
+    pending.sort();
+    pending
+}
+
+/// The report in a crash file, if it is signed for this license
+fn read_report(path: &Path, config: &Config) -> Option<serde_json::Value> {
+    let mut report: serde_json::Value = serde_json::from_slice(&fs::read(path).ok()?).ok()?;
+    let signature = report.as_object_mut()?.remove("signature")?;
+    let valid = verify_signature(&canonical::to_string(&report).ok()?, &config.shared_secret, signature.as_str()?);
+    (valid && report["license_id"] == config.license_id.as_str()).then_some(report)
+}
+
+/// Crash files of this license
+fn crash_dir(config: &Config) -> PathBuf {
+    state::state_dir().join(state::namespace_of(&config.license_id)).join("crash")
+}
+
+/// Run the fail-closed action and exit
+fn fail_closed() -> ! {
+    if let Some(config) = ARMED.get()
+        && config.panic_action == PanicAction::KillBase
+    {
+        let target = match TARGET.load(Ordering::Relaxed) {
+            0 => lineage::original_parent().or_else(process::get_parent_pid),
+            pid => Some(pid),
+        };
+        match target.filter(|&pid| pid > 1 && session::is_same_session(pid)) {
+            Some(pid) => {
+                log_error!("🛑 Failing closed - stopping the protected app (PID {})", pid);
+                if let Err(e) = kill_parent::stop_parent(pid) {
+                    log_error!("❌ Failed to stop the protected app: {}", e);
+                }
+            }
+            None => log_warn!("⚠️  No protected app to stop"),
+        }
+    }
+    exit_status::exit(ExitStatus::Crashed, "overload crashed");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crash_file_signature() {
+        let report = CrashReport {
+            license_id: Some("lic"),
+            version: "1.0.0",
+            thread: "main",
+            message: "index out of bounds",
+            location: Some("src/main.rs:1:1".to_string()),
+            timestamp: 1,
+        };
+        let signature = create_signature(&canonical::to_string(&report).unwrap(), "secret");
+        let file = serde_json::to_value(CrashFile { report: &report, signature: Some(signature.clone()) }).unwrap();
+
+        // A verifier strips the signature and hashes the canonical rest
+        let mut unsigned = file.clone();
+        unsigned.as_object_mut().unwrap().remove("signature");
+        assert_eq!(create_signature(&canonical::to_string(&unsigned).unwrap(), "secret"), signature);
+        assert_eq!(file["message"], "index out of bounds");
+    }
+
+    #[test]
+    fn test_pending_reports() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("100-1.json"), b"{}").unwrap();
+        // Empty: a run that ended without crashing, or one still running
+        fs::write(dir.path().join("100-4294967294.json"), b"").unwrap();
+        let own = dir.path().join(format!("100-{}.json", std::process::id()));
+        fs::write(&own, b"").unwrap();
+
+        assert_eq!(pending_reports(dir.path()), vec![dir.path().join("100-1.json")]);
+        assert!(!dir.path().join("100-4294967294.json").exists());
+        assert!(own.exists());
+    }
+}
@@ -0,0 +1,110 @@
+//! Short-lived cache of denial verdicts
+//!
+//! Restarting the protected app in a tight loop is a way to race enforcement:
+//! every start runs until the network round trip returns a denial. The last
+//! denial for this license and machine is persisted with a short TTL
+//! (`deny_cache_ttl_secs`), and startup consults it first, so a just-denied
+//! machine is blocked immediately. An authorized result clears it. Only
+//! signed denials are cached: an unsigned one may come from anyone on the
+//! path and must not outlive the check it failed.
+
+use crate::config::Config;
+use crate::utils::state::{DenialState, PersistentState, StateStore};
+use crate::utils::time;
+use super::fingerprint::get_machine_fingerprint;
+use super::network::VerifyResponse;
+
+/// Persist a signed denial for this machine
+pub fn record(config: &Config, denial: &VerifyResponse) {
+    if config.deny_cache_ttl_secs == 0 || !denial.signature_valid {
+        return;
+    }
+    let store = StateStore::for_license(&config.license_id);
+    let mut state = store.load();
+    mark(config, &mut state, denial);
+    if let Err(e) = store.save(&state) {
+        log_warn!("⚠️  {}", e);
+    }
+}
+
+/// Note a signed denial in already loaded state (caller saves it)
+pub fn mark(config: &Config, state: &mut PersistentState, denial: &VerifyResponse) {
+    if config.deny_cache_ttl_secs == 0 || !denial.signature_valid {
+        return;
+    }
+
+    let now = time::unix_now();
+    state.denial = Some(DenialState {
+        machine_fingerprint: get_machine_fingerprint(),
+        denied_at: now,
+        expires_at: time::expires_at(now, config.deny_cache_ttl_secs as i64),
+        message: denial.message.clone(),
+    });
+}
+
+/// Forget a cached denial and a deferral by the enforcement policy (after
+/// an authorized result)
+pub fn clear(config: &Config) {
+    let store = StateStore::for_license(&config.license_id);
+    let mut state = store.load();
+    let denied = state.denial.take().is_some();
+    let deferred = state.deferral.take().is_some();
+    if (denied || deferred)
+        && let Err(e) = store.save(&state)
+    {
+        log_warn!("⚠️  {}", e);
+    }
+}
+
+/// Active cached denial for this machine, if any
+pub fn cached(config: &Config) -> Option<DenialState> {
+    active(config, &StateStore::for_license(&config.license_id).load()).cloned()
+}
+
+/// Active denial in already loaded state
+pub fn active<'a>(config: &Config, state: &'a PersistentState) -> Option<&'a DenialState> {
+    if config.deny_cache_ttl_secs == 0 {
+        return None;
+    }
+
+    state.denial.as_ref()
+        .filter(|denial| is_active(denial, &get_machine_fingerprint(), time::unix_now()))
+}
+
+fn is_active(denial: &DenialState, fingerprint: &str, now: i64) -> bool {
+    denial.machine_fingerprint == fingerprint && !time::is_expired(denial.expires_at, now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_denial_scoped_to_machine_and_ttl() {
+        let denial = DenialState {
+            machine_fingerprint: "fp_a".to_string(),
+            denied_at: 1_000,
+            expires_at: 1_300,
+            message: "revoked".to_string(),
+        };
+
+        assert!(is_active(&denial, "fp_a", 1_100));
+        assert!(!is_active(&denial, "fp_b", 1_100));
+        assert!(!is_active(&denial, "fp_a", 1_300));
+    }
+
+    #[test]
+    fn test_only_signed_denials_are_cached() {
+        let config: Config = serde_json::from_str(
+            r#"{"license_id": "lic", "server_url": "http://localhost", "shared_secret": "s"}"#,
+        )
+        .unwrap();
+        let mut state = PersistentState::default();
+        let unsigned = VerifyResponse { message: "revoked".to_string(), ..Default::default() };
+        mark(&config, &mut state, &unsigned);
+        assert!(state.denial.is_none());
+
+        mark(&config, &mut state, &VerifyResponse { signature_valid: true, ..unsigned });
+        assert_eq!(state.denial.unwrap().message, "revoked");
+    }
+}
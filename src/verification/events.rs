@@ -0,0 +1,260 @@
+//! Remote shipping of security events
+//!
+//! stderr on a customer machine is lost to us, so significant events (tamper
+//! detections, kills, config load failures, fingerprint drift) are appended
+//! to a disk queue (`events.jsonl` in the license's state directory) and
+//! shipped as signed batches to `event_log_url` (https only; default: the
+//! license server). The queue survives offline periods and restarts, and is
+//! shared with other overloads of the license through a file lock. Events
+//! recorded before the config loaded go to the queue of the license embedded
+//! in this binary, so only a run under that license ships them; without a
+//! license ID there is nobody to ship them to and they are not queued.
+//!
+//! Events also go to the customer's local webhook when one is configured
+//! (`webhook`), together with every check result - those the license server
+//! already knows, so they are not queued.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::config::{embedded, Config};
+use crate::utils::{redact, secure_fs};
+use crate::utils::state::{namespace, namespace_of, state_dir};
+use crate::utils::tasks;
+use crate::utils::time;
+use super::fingerprint::get_machine_fingerprint;
+use super::network::{post_signed, VerifyError, VerifyResponse};
+use super::webhook;
+
+/// API path of the event log endpoint
+const EVENTS_PATH: &str = "/api/v1/events";
+
+/// File name of the event queue inside the state directory
+pub const EVENTS_FILE: &str = "events.jsonl";
+
+/// Events kept while offline (the oldest are dropped beyond this)
+const MAX_QUEUED: usize = 1000;
+
+/// Events per shipped batch
+const BATCH_SIZE: usize = 100;
+
+/// Serializes queue rewrites within this process (the file lock only
+/// excludes other processes)
+static QUEUE_LOCK: Mutex<()> = Mutex::new(());
+
+/// One queued security event
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SecurityEvent {
+    /// e.g. "tamper", "kill", "config_load_failure", "fingerprint_drift"
+    pub kind: String,
+    pub detail: String,
+    pub timestamp: i64,
+}
+
+/// Signed batch payload
+#[derive(Debug, Serialize)]
+struct EventBatch<'a> {
+    license_id: &'a str,
+    machine_fingerprint: String,
+    events: &'a [SecurityEvent],
+}
+
+/// Queue an event for shipping (never fails the caller)
+pub fn record(kind: &str, detail: &str) {
+    let event = SecurityEvent {
+        kind: kind.to_string(),
+        detail: detail.to_string(),
+        timestamp: time::unix_now(),
+    };
+
+    webhook::deliver(&event);
+
+    let Some(path) = recording_path() else {
+        log_debug!("🔍 No license ID - security event not queued");
+        return;
+    };
+    if let Err(e) = with_queue(&path, |path| append(path, &event)) {
+        log_warn!("⚠️  Failed to queue security event: {}", e);
+    }
+}
+
+/// Publish a check result to the local sinks (never queued for shipping)
+pub fn record_check(result: &Result<VerifyResponse, VerifyError>, stalled: bool) {
+    let (kind, detail) = match result {
+        Ok(response) if response.authorized => ("check_authorized", response.message.clone()),
+        Ok(response) => ("check_unauthorized", response.message.clone()),
+        Err(e) if stalled => ("check_stalled", redact::scrub(&e.message)),
+        Err(e) => ("check_error", redact::scrub(&e.message)),
+    };
+    webhook::deliver(&SecurityEvent {
+        kind: kind.to_string(),
+        detail,
+        timestamp: time::unix_now(),
+    });
+}
+
+/// Ship all queued events now
+///
+/// Shipped batches are removed from the queue; on failure the rest stays
+/// queued for the next attempt. Returns at once if another thread or
+/// process is already shipping this queue. The queue is only locked while
+/// it is read and rewritten, never during the upload, so recording an event
+/// does not wait for the network.
+pub fn flush(config: &Config) {
+    let path = queue_path(&config.license_id);
+    let _shipping = match secure_fs::try_lock_file(&path.with_extension("flush.lock")) {
+        Ok(Some(lock)) => lock,
+        Ok(None) => return,
+        Err(e) => {
+            log_warn!("⚠️  Cannot ship events: {}", e);
+            return;
+        }
+    };
+    let queued = match with_queue(&path, |path| Ok(read_queue(path))) {
+        Ok(queued) if !queued.is_empty() => queued,
+        Ok(_) => return,
+        Err(e) => {
+            log_warn!("⚠️  Cannot read event queue: {}", e);
+            return;
+        }
+    };
+
+    let server_url = config.event_log_url.clone().unwrap_or_else(|| config.get_server_url());
+    let mut shipped = 0;
+    for batch in queued.chunks(BATCH_SIZE) {
+        let payload = EventBatch {
+            license_id: &config.license_id,
+            machine_fingerprint: get_machine_fingerprint(),
+            events: batch,
+        };
+        match post_signed(&server_url, EVENTS_PATH, &config.license_id, &config.shared_secret, &payload) {
+            Ok(status) if status == 200 || status == 202 => shipped += batch.len(),
+            Ok(status) => {
+                log_warn!("⚠️  Event batch rejected with HTTP {} - keeping {} event(s) queued", status, queued.len() - shipped);
+                break;
+            }
+            Err(e) => {
+                log_warn!("⚠️  Failed to ship events ({}) - keeping {} event(s) queued", e, queued.len() - shipped);
+                break;
+            }
+        }
+    }
+
+    if shipped > 0 {
+        log_info!("📤 Shipped {} security event(s)", shipped);
+        // Events may have been queued (and the oldest dropped) meanwhile
+        let removed = with_queue(&path, |path| {
+            let mut current = read_queue(path);
+            remove_shipped(&mut current, &queued[..shipped]);
+            rewrite(path, &current)
+        });
+        if let Err(e) = removed {
+            log_warn!("⚠️  Failed to update event queue: {}", e);
+        }
+    }
+}
+
+/// Run `f` on the queue at `path` with it locked against this process's
+/// threads and other processes
+fn with_queue<T>(path: &Path, f: impl FnOnce(&Path) -> Result<T, String>) -> Result<T, String> {
+    let _guard = QUEUE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let _lock = secure_fs::lock_file(&path.with_extension("lock"))?;
+    f(path)
+}
+
+/// Drop the shipped events from the front of the queue; those the bound
+/// dropped meanwhile are skipped
+fn remove_shipped(queued: &mut Vec<SecurityEvent>, shipped: &[SecurityEvent]) {
+    let mut matched = 0;
+    for event in shipped {
+        if queued.get(matched) == Some(event) {
+            matched += 1;
+        }
+    }
+    queued.drain(..matched);
+}
+
+/// Ship queued events on a background task
+pub fn flush_in_background(config: &Config) {
+    let config = config.clone();
+    tasks::spawn("event_shipping", move || flush(&config));
+}
+
+/// Path of the event queue of `license_id`
+pub fn queue_path(license_id: &str) -> PathBuf {
+    state_dir().join(namespace_of(license_id)).join(EVENTS_FILE)
+}
+
+/// Queue events of this process go to: its license's, or before the config
+/// loaded that of the license embedded in the binary
+fn recording_path() -> Option<PathBuf> {
+    let namespace = match namespace() {
+        Some(namespace) => namespace.to_string(),
+        None => namespace_of(&embedded::embedded_license_id()?),
+    };
+    Some(state_dir().join(namespace).join(EVENTS_FILE))
+}
+
+fn read_queue(path: &Path) -> Vec<SecurityEvent> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+fn append(path: &Path, event: &SecurityEvent) -> Result<(), String> {
+    let mut queued = read_queue(path);
+    if queued.len() >= MAX_QUEUED {
+        queued.drain(..=queued.len() - MAX_QUEUED);
+        queued.push(event.clone());
+        return rewrite(path, &queued);
+    }
+
+    let line = serde_json::to_string(event).map_err(|e| e.to_string())?;
+    secure_fs::append_private(path, &line)
+}
+
+fn rewrite(path: &Path, events: &[SecurityEvent]) -> Result<(), String> {
+    let mut content = String::new();
+    for event in events {
+        content.push_str(&serde_json::to_string(event).map_err(|e| e.to_string())?);
+        content.push('\n');
+    }
+    secure_fs::write_private(path, content.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn event(n: usize) -> SecurityEvent {
+        SecurityEvent { kind: "tamper".to_string(), detail: n.to_string(), timestamp: n as i64 }
+    }
+
+    #[test]
+    fn test_queue_is_bounded_and_keeps_newest() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(EVENTS_FILE);
+
+        for n in 0..MAX_QUEUED + 5 {
+            append(&path, &event(n)).unwrap();
+        }
+
+        let queued = read_queue(&path);
+        assert_eq!(queued.len(), MAX_QUEUED);
+        assert_eq!(queued[0], event(5));
+        assert_eq!(queued.last(), Some(&event(MAX_QUEUED + 4)));
+    }
+
+    #[test]
+    fn test_remove_shipped_keeps_events_queued_meanwhile() {
+        // Shipped 0..3; meanwhile 0 was dropped by the bound and 3, 4 queued
+        let mut queued: Vec<_> = (1..5).map(event).collect();
+        remove_shipped(&mut queued, &[event(0), event(1), event(2)]);
+        assert_eq!(queued, [event(3), event(4)]);
+    }
+}
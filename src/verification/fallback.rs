@@ -0,0 +1,152 @@
+//! Break-glass fallback verification endpoint
+//!
+//! During a primary API incident every seat would otherwise sit in the
+//! network-error path. When `fallback_server_url` is configured it is tried
+//! only after the primary failed `fallback_after_failures` consecutive times,
+//! and only responses with a nonce-bound signature are accepted from it (see
+//! `network::verify_license_strict`), so the normal trust model is unchanged.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use super::network::{verify_license, verify_license_strict, ErrorClass, VerifyError, VerifyResponse};
+use super::{broadcast, licenses, offline, worker};
+use crate::config::{ActivationMode, Config};
+use crate::utils::{audit, redact};
+
+/// Consecutive failed checks against the primary endpoint
+static PRIMARY_FAILURES: AtomicU32 = AtomicU32::new(0);
+
+/// Attempts of a one-shot check before a retryable error is final
+const ONE_SHOT_ATTEMPTS: u32 = 3;
+
+/// Longest wait between one-shot attempts; a longer `Retry-After` ends them
+const ONE_SHOT_MAX_WAIT: Duration = Duration::from_secs(10);
+
+/// Verify the license, falling back to the break-glass endpoint if due
+///
+/// With offline activation the license file is validated instead. Further
+/// `licenses` are tried in priority order (see `licenses`). A license on the
+/// revocation list is denied (see `broadcast`).
+pub fn verify(config: &Config, first_check: bool) -> Result<VerifyResponse, VerifyError> {
+    if config.activation_mode == ActivationMode::Offline {
+        return offline::verify(config)
+            .map(|response| broadcast::apply_revocations(config, response))
+            .map_err(VerifyError::from);
+    }
+
+    licenses::verify(config, |license| {
+        verify_online(license, first_check).map(|response| broadcast::apply_revocations(license, response))
+    })
+}
+
+fn verify_online(config: &Config, first_check: bool) -> Result<VerifyResponse, VerifyError> {
+    let primary_error = match verify_license(
+        &config.license_id,
+        &config.get_server_url(),
+        &config.shared_secret,
+        0, // grace_period removed from config
+        first_check,
+    ) {
+        Ok(response) => {
+            PRIMARY_FAILURES.store(0, Ordering::Relaxed);
+            return Ok(response);
+        }
+        Err(e) => e,
+    };
+
+    if worker::is_abandoned() {
+        return Err(primary_error);
+    }
+    let failures = PRIMARY_FAILURES.fetch_add(1, Ordering::Relaxed) + 1;
+    let Some(fallback_url) = config.fallback_server_url.as_deref() else {
+        return Err(primary_error);
+    };
+    if !fallback_due(failures, config.fallback_after_failures) {
+        return Err(primary_error);
+    }
+
+    log_info!("🚑 Primary verification failed {} times in a row - trying fallback endpoint", failures);
+    match verify_license_strict(&config.license_id, fallback_url, &config.shared_secret, first_check) {
+        Ok(response) => {
+            audit::record(
+                "fallback_verification",
+                &format!("primary failed {} times ({}); fallback answered authorized={}", failures, primary_error, response.authorized),
+            );
+            Ok(response)
+        }
+        Err(e) => Err(VerifyError { message: format!("{} (fallback: {})", primary_error, e), ..e }),
+    }
+}
+
+/// Verify for a one-shot mode (sync, CLI, async start): retryable errors
+/// (server errors, rate limits, no answer) are retried a few times first
+pub fn verify_one_shot(config: &Config, first_check: bool) -> Result<VerifyResponse, VerifyError> {
+    let mut attempt = 1;
+    loop {
+        let error = match verify(config, first_check) {
+            Err(e) => e,
+            result => return result,
+        };
+        let Some(delay) = one_shot_retry_delay(error.class, attempt) else {
+            return Err(error);
+        };
+        log_warn!("⏳ Retryable verification error ({}) - retrying in {}s", redact::scrub(&error.message), delay.as_secs());
+        std::thread::sleep(delay);
+        attempt += 1;
+    }
+}
+
+/// Wait before retrying one-shot attempt number `attempt` (1-based) that
+/// failed with `class`; None when it should not be retried
+pub fn one_shot_retry_delay(class: ErrorClass, attempt: u32) -> Option<Duration> {
+    if !class.is_retryable() || attempt >= ONE_SHOT_ATTEMPTS {
+        return None;
+    }
+    let delay = class.retry_after().unwrap_or(Duration::from_secs(attempt as u64));
+    (delay <= ONE_SHOT_MAX_WAIT).then_some(delay)
+}
+
+/// Whether the fallback endpoint is tried after `failures` consecutive failures
+pub fn fallback_due(failures: u32, threshold: u32) -> bool {
+    failures >= threshold.max(1)
+}
+
+/// Whether `failures` consecutive failed checks exhaust the tolerance
+/// (`max_consecutive_failures`, 0 = unlimited)
+pub fn failure_limit_reached(failures: u32, limit: u32) -> bool {
+    limit > 0 && failures >= limit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fallback_threshold() {
+        assert!(!fallback_due(2, 3));
+        assert!(fallback_due(3, 3));
+        // 0 means "immediately", never "before any failure"
+        assert!(fallback_due(1, 0));
+
+        // ... whereas a tolerance of 0 never runs out
+        assert!(!failure_limit_reached(1_000, 0));
+        assert!(!failure_limit_reached(4, 5));
+        assert!(failure_limit_reached(5, 5));
+    }
+
+    #[test]
+    fn test_one_shot_retry_delay() {
+        let unavailable = ErrorClass::Retryable { retry_after: None };
+        assert_eq!(one_shot_retry_delay(unavailable, 1), Some(Duration::from_secs(1)));
+        assert_eq!(one_shot_retry_delay(unavailable, 2), Some(Duration::from_secs(2)));
+        assert_eq!(one_shot_retry_delay(unavailable, ONE_SHOT_ATTEMPTS), None);
+
+        let rate_limited = |secs| ErrorClass::Retryable { retry_after: Some(Duration::from_secs(secs)) };
+        assert_eq!(one_shot_retry_delay(rate_limited(5), 1), Some(Duration::from_secs(5)));
+        // Not worth holding up a launch for
+        assert_eq!(one_shot_retry_delay(rate_limited(120), 1), None);
+
+        assert_eq!(one_shot_retry_delay(ErrorClass::Invalid, 1), None);
+    }
+}
@@ -0,0 +1,157 @@
+//! Conditional, compressed downloads of fleet-wide artifacts
+//!
+//! Revocation lists and policy bundles (see `broadcast`) are fetched by every
+//! install on every check but rarely change. `fetch` keeps the last body
+//! together with its `ETag`/`Last-Modified` in the state directory and
+//! revalidates it with `If-None-Match`/`If-Modified-Since`, so an unchanged
+//! artifact costs one bodiless 304. With the default HTTP backend bodies are requested
+//! gzip-encoded and decoded transparently (see `http`).
+//!
+//! The cache only saves bandwidth and is never a source of trust: callers
+//! verify what they get (see `security::trust`) whether it came from the
+//! network or from disk.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::http::{self, HttpRequest};
+use crate::utils::secure_fs;
+use crate::utils::state::namespace_dir;
+use crate::utils::time;
+
+/// Subdirectory of the license's state directory holding cached artifacts
+const FETCH_DIR: &str = "fetch";
+
+/// Largest accepted artifact (after decompression)
+const MAX_BODY: usize = 16 * 1024 * 1024;
+
+/// Validators of a cached artifact
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+struct CacheMeta {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Unix time of the last 200 response
+    fetched_at: i64,
+}
+
+/// A fetched artifact
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fetched {
+    pub body: Vec<u8>,
+    /// false when the server confirmed the cached copy (304)
+    pub changed: bool,
+}
+
+/// Fetch `url`, revalidating the copy cached under `name` (a plain file name)
+pub fn fetch(url: &str, name: &str) -> Result<Fetched, String> {
+    fetch_in(&namespace_dir().join(FETCH_DIR), url, name)
+}
+
+fn fetch_in(dir: &Path, url: &str, name: &str) -> Result<Fetched, String> {
+    secure_fs::check_name(name)?;
+
+    // A copy of another URL under the same name is not a validator for this one
+    let cached = load(dir, name).filter(|(meta, _)| meta.url == url);
+
+    let mut request = HttpRequest::get(url).max_body(MAX_BODY as u64);
+    if let Some((meta, _)) = &cached {
+        for (header, value) in conditional_headers(meta) {
+            request = request.header(header, value);
+        }
+    }
+    let response = http::client()?
+        .send(request)
+        .map_err(|e| format!("Fetch of {} failed: {}", name, e))?;
+
+    let status = response.status;
+    if status == 304 {
+        let Some((_, body)) = cached else {
+            return Err(format!("Fetch of {} answered 304 without a cached copy", name));
+        };
+        log_debug!("📦 {} unchanged (304)", name);
+        return Ok(Fetched { body, changed: false });
+    }
+    if !response.is_success() {
+        return Err(format!("Fetch of {} failed with HTTP {}", name, status));
+    }
+
+    let meta = CacheMeta {
+        url: url.to_string(),
+        etag: response.header("ETag").map(str::to_string),
+        last_modified: response.header("Last-Modified").map(str::to_string),
+        fetched_at: time::unix_now(),
+    };
+
+    let body = response.body;
+    if body.len() > MAX_BODY {
+        return Err(format!("Fetch of {} exceeds {} bytes", name, MAX_BODY));
+    }
+
+    log_debug!("📦 {} downloaded ({} bytes)", name, body.len());
+    if let Err(e) = store(dir, name, &meta, &body) {
+        log_warn!("⚠️  Failed to cache {}: {}", name, e);
+    }
+    Ok(Fetched { body, changed: true })
+}
+
+/// Revalidation headers for a cached copy
+fn conditional_headers(meta: &CacheMeta) -> Vec<(&'static str, String)> {
+    let mut headers = Vec::new();
+    if let Some(etag) = &meta.etag {
+        headers.push(("If-None-Match", etag.clone()));
+    }
+    if let Some(last_modified) = &meta.last_modified {
+        headers.push(("If-Modified-Since", last_modified.clone()));
+    }
+    headers
+}
+
+fn paths(dir: &Path, name: &str) -> (PathBuf, PathBuf) {
+    (dir.join(name), dir.join(format!("{}.meta.json", name)))
+}
+
+fn load(dir: &Path, name: &str) -> Option<(CacheMeta, Vec<u8>)> {
+    let (body_path, meta_path) = paths(dir, name);
+    let meta = serde_json::from_str(&fs::read_to_string(meta_path).ok()?).ok()?;
+    Some((meta, fs::read(body_path).ok()?))
+}
+
+/// Write body, then validators (each replaced atomically)
+fn store(dir: &Path, name: &str, meta: &CacheMeta, body: &[u8]) -> Result<(), String> {
+    let (body_path, meta_path) = paths(dir, name);
+    let meta_json = serde_json::to_vec(meta).map_err(|e| e.to_string())?;
+    secure_fs::write_private(&body_path, body)?;
+    secure_fs::write_private(&meta_path, &meta_json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_round_trip_and_validators() {
+        let dir = tempfile::tempdir().unwrap();
+        let meta = CacheMeta {
+            url: "https://api.example.com/revocations".to_string(),
+            etag: Some("\"v42\"".to_string()),
+            last_modified: Some("Wed, 21 Oct 2026 07:28:00 GMT".to_string()),
+            fetched_at: 1_000,
+        };
+
+        assert!(load(dir.path(), "revocations").is_none());
+        store(dir.path(), "revocations", &meta, b"{\"revoked\":[]}").unwrap();
+
+        let (loaded, body) = load(dir.path(), "revocations").unwrap();
+        assert_eq!(loaded, meta);
+        assert_eq!(body, b"{\"revoked\":[]}");
+
+        let headers = conditional_headers(&loaded);
+        assert_eq!(headers[0], ("If-None-Match", "\"v42\"".to_string()));
+        assert_eq!(headers[1].0, "If-Modified-Since");
+        assert!(conditional_headers(&CacheMeta::default()).is_empty());
+
+        assert!(fetch_in(dir.path(), &meta.url, "../escape").is_err());
+    }
+}
@@ -1,6 +1,20 @@
 /// Machine fingerprinting for license verification
+use serde::Serialize;
 use sha2::{Digest, Sha256};
 use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set while the server asks for per-component diagnostics
+static DIAGNOSTICS_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// One individually hashed fingerprint input, for server-side drift diagnosis
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FingerprintComponent {
+    /// Component label, e.g. "hostname", "mac0", "machine_id"
+    pub label: String,
+    /// SHA256 of label and value (raw values never leave the machine)
+    pub hash: String,
+}
 
 /// Generate machine fingerprint
 /// 
@@ -12,10 +26,7 @@ use std::fs;
 /// SHA256 hash of the combined identifiers
 pub fn get_machine_fingerprint() -> String {
     // Get hostname
-    let hostname = hostname::get()
-        .ok()
-        .and_then(|h| h.into_string().ok())
-        .unwrap_or_else(|| "unknown".to_string());
+    let hostname = get_hostname();
 
     // Get MAC address (simplified - in production use more robust method)
     let mac = get_mac_address().unwrap_or_else(|| "00:00:00:00:00:00".to_string());
@@ -27,6 +38,98 @@ pub fn get_machine_fingerprint() -> String {
     hex::encode(hasher.finalize())
 }
 
+/// Individually hashed fingerprint components
+///
+/// Includes inputs that are not (yet) part of the fingerprint, such as the
+/// OS machine id, so support can tell a renamed host from a swapped NIC.
+pub fn fingerprint_components() -> Vec<FingerprintComponent> {
+    let components = [
+        ("hostname", Some(get_hostname())),
+        ("mac0", get_mac_address()),
+        ("machine_id", get_machine_id()),
+    ];
+
+    components
+        .into_iter()
+        .map(|(label, value)| FingerprintComponent {
+            label: label.to_string(),
+            hash: match value {
+                Some(value) => hex::encode(Sha256::digest(format!("{}|{}", label, value).as_bytes())),
+                None => "unavailable".to_string(),
+            },
+        })
+        .collect()
+}
+
+/// Enable or disable component diagnostics (server-requested)
+pub fn set_diagnostics_requested(requested: bool) {
+    if DIAGNOSTICS_REQUESTED.swap(requested, Ordering::Relaxed) != requested {
+        log_info!("🩺 Fingerprint diagnostics {}", if requested { "enabled by server" } else { "disabled" });
+    }
+}
+
+/// Whether the server asked for component diagnostics
+pub fn diagnostics_requested() -> bool {
+    DIAGNOSTICS_REQUESTED.load(Ordering::Relaxed)
+}
+
+fn get_hostname() -> String {
+    hostname::get()
+        .ok()
+        .and_then(|h| h.into_string().ok())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Get the OS installation id (machine-id / IOPlatformUUID / MachineGuid)
+fn get_machine_id() -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+            .iter()
+            .find_map(|path| fs::read_to_string(path).ok())
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let output = std::process::Command::new("ioreg")
+            .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find(|line| line.contains("\"IOPlatformUUID\""))
+            .and_then(|line| line.rsplit('"').nth(1))
+            .map(|id| id.to_string())
+    }
+
+    #[cfg(windows)]
+    {
+        let output = std::process::Command::new("reg")
+            .args(["query", r"HKLM\SOFTWARE\Microsoft\Cryptography", "/v", "MachineGuid"])
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find(|line| line.contains("MachineGuid"))
+            .and_then(|line| line.split_whitespace().last())
+            .map(|id| id.to_string())
+    }
+
+    #[cfg(target_os = "freebsd")]
+    {
+        let uuid = crate::utils::process::sysctl(&[libc::CTL_KERN, libc::KERN_HOSTUUID])?;
+        let uuid = String::from_utf8_lossy(uuid.split(|&byte| byte == 0).next()?).trim().to_string();
+        (!uuid.is_empty()).then_some(uuid)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "freebsd", windows)))]
+    {
+        None
+    }
+}
+
 /// Get MAC address of first network interface
 /// 
 /// # Returns
@@ -53,8 +156,16 @@ fn get_mac_address() -> Option<String> {
         }
     }
     
-    // Fallback for non-Linux platforms
-    #[cfg(not(target_os = "linux"))]
+    // Link-level addresses of the interfaces (BSD)
+    #[cfg(any(target_os = "freebsd", target_os = "openbsd"))]
+    {
+        if let Some(mac) = bsd_mac_address() {
+            return Some(mac);
+        }
+    }
+
+    // Fallback for other platforms
+    #[cfg(not(any(target_os = "linux", target_os = "freebsd", target_os = "openbsd")))]
     {
         // TODO: Add Windows and macOS support
         // For now, return None for these platforms
@@ -63,6 +174,42 @@ fn get_mac_address() -> Option<String> {
     None
 }
 
+/// MAC address of the first non-loopback Ethernet-style interface (getifaddrs)
+#[cfg(any(target_os = "freebsd", target_os = "openbsd"))]
+fn bsd_mac_address() -> Option<String> {
+    let mut addrs: *mut libc::ifaddrs = std::ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut addrs) } != 0 {
+        return None;
+    }
+
+    let mut mac = None;
+    let mut current = addrs;
+    while !current.is_null() && mac.is_none() {
+        let ifa = unsafe { &*current };
+        current = ifa.ifa_next;
+        if ifa.ifa_addr.is_null()
+            || ifa.ifa_flags & libc::IFF_LOOPBACK as libc::c_uint != 0
+            || libc::c_int::from(unsafe { (*ifa.ifa_addr).sa_family }) != libc::AF_LINK
+        {
+            continue;
+        }
+        // sdl_data holds the interface name, then the address
+        let sdl = ifa.ifa_addr as *const libc::sockaddr_dl;
+        let (name_len, addr_len) = unsafe { ((*sdl).sdl_nlen as usize, (*sdl).sdl_alen as usize) };
+        if addr_len != 6 {
+            continue;
+        }
+        let data = unsafe { std::ptr::addr_of!((*sdl).sdl_data) as *const u8 };
+        let bytes = unsafe { std::slice::from_raw_parts(data.add(name_len), addr_len) };
+        if bytes.iter().any(|&byte| byte != 0) {
+            mac = Some(bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<Vec<_>>().join(":"));
+        }
+    }
+
+    unsafe { libc::freeifaddrs(addrs) };
+    mac
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -81,4 +228,15 @@ mod tests {
         let fp2 = get_machine_fingerprint();
         assert_eq!(fp, fp2);
     }
+    
+    #[test]
+    fn test_fingerprint_components_are_hashed() {
+        let components = fingerprint_components();
+        let labels: Vec<&str> = components.iter().map(|c| c.label.as_str()).collect();
+        assert_eq!(labels, ["hostname", "mac0", "machine_id"]);
+        
+        let hostname = components.iter().find(|c| c.label == "hostname").unwrap();
+        assert_eq!(hostname.hash.len(), 64);
+        assert!(!hostname.hash.contains(&get_hostname()));
+    }
 }
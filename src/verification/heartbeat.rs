@@ -0,0 +1,86 @@
+//! Periodic liveness heartbeat, independent of license verification
+//!
+//! With long check intervals the server otherwise hears nothing for hours;
+//! fleet operators get uptime and check statistics every `heartbeat_interval_ms`.
+
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::Serialize;
+
+use crate::config::snapshot;
+use crate::utils::tasks;
+use crate::utils::time;
+use super::fingerprint::get_machine_fingerprint;
+use super::network::post_signed;
+
+/// API path of the heartbeat endpoint
+const HEARTBEAT_PATH: &str = "/api/v1/heartbeat";
+
+static STARTED: OnceLock<Instant> = OnceLock::new();
+static CHECK_COUNT: AtomicU64 = AtomicU64::new(0);
+static CONSECUTIVE_FAILURES: AtomicU32 = AtomicU32::new(0);
+
+/// Heartbeat payload
+#[derive(Debug, Serialize)]
+struct Heartbeat<'a> {
+    license_id: &'a str,
+    machine_fingerprint: String,
+    uptime_secs: u64,
+    check_count: u64,
+    consecutive_failures: u32,
+    platform: &'static str,
+    arch: &'static str,
+    overload_version: &'static str,
+    timestamp: i64,
+}
+
+/// Count a verification attempt (network errors count as failures)
+pub fn record_check(success: bool) {
+    CHECK_COUNT.fetch_add(1, Ordering::Relaxed);
+    if success {
+        CONSECUTIVE_FAILURES.store(0, Ordering::Relaxed);
+    } else {
+        CONSECUTIVE_FAILURES.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Start the heartbeat thread if `heartbeat_interval_ms` is set
+///
+/// The interval is re-read from the current config snapshot every beat; the
+/// thread stops once it is patched to 0.
+pub fn spawn() {
+    STARTED.get_or_init(Instant::now);
+    if snapshot::current().heartbeat_interval_ms == 0 {
+        return;
+    }
+
+    tasks::spawn("heartbeat", || loop {
+        let config = snapshot::current();
+        if config.heartbeat_interval_ms == 0 {
+            log_info!("💓 Heartbeat disabled");
+            return;
+        }
+        thread::sleep(Duration::from_millis(config.heartbeat_interval_ms));
+
+        let config = super::licenses::active(&snapshot::current());
+        let heartbeat = Heartbeat {
+            license_id: &config.license_id,
+            machine_fingerprint: get_machine_fingerprint(),
+            uptime_secs: STARTED.get_or_init(Instant::now).elapsed().as_secs(),
+            check_count: CHECK_COUNT.load(Ordering::Relaxed),
+            consecutive_failures: CONSECUTIVE_FAILURES.load(Ordering::Relaxed),
+            platform: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            overload_version: env!("CARGO_PKG_VERSION"),
+            timestamp: time::protocol_now(),
+        };
+
+        match post_signed(&config.get_server_url(), HEARTBEAT_PATH, &config.license_id, &config.shared_secret, &heartbeat) {
+            Ok(status) if status == 200 || status == 202 || status == 204 => {}
+            Ok(status) => log_warn!("⚠️  Heartbeat rejected with HTTP {}", status),
+            Err(e) => log_warn!("⚠️  Failed to send heartbeat: {}", e),
+        }
+    });
+}
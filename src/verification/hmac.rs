@@ -42,7 +42,7 @@ mod tests {
     
     #[test]
     fn test_create_signature() {
-        let data = "lic_12345" + "1234567890";
+        let data = format!("{}{}", "lic_12345", "1234567890");
         let secret = "my_secret_key";
         
         let sig1 = create_signature(&data, secret);
@@ -0,0 +1,301 @@
+//! HTTP backends
+//!
+//! All server communication goes through `HttpClient`, so the backend is a
+//! build choice:
+//! - `http-reqwest` (default): reqwest's blocking client; responses may be
+//!   gzip-encoded and are decoded transparently
+//! - `http-minimal`: ureq, a plain HTTP/1.1 client on the same rustls stack
+//!   without tokio, hyper or h2 (about 0.5 MB less on Linux x86_64); bodies
+//!   are not requested compressed. Wins if both features are enabled.
+//!
+//! Both verify certificates against the bundled webpki roots, prefer
+//! ChaCha20-Poly1305 on CPUs without AES, never follow redirects (a
+//! redirected POST turns into a GET on whatever page it lands on; callers
+//! report them as infrastructure errors) and honor the usual proxy variables.
+
+use std::io::Read;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::network::REQUEST_TIMEOUT;
+use crate::utils::platform;
+
+#[cfg(not(any(feature = "http-reqwest", feature = "http-minimal")))]
+compile_error!("enable an HTTP backend: feature \"http-reqwest\" or \"http-minimal\"");
+
+/// Largest response body read unless set otherwise
+const DEFAULT_MAX_BODY: u64 = 64 * 1024 * 1024;
+
+/// Request to send
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: &'static str,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Vec<u8>>,
+    pub timeout: Duration,
+    /// Bodies are cut after `max_body + 1` bytes, so callers can tell an
+    /// oversized one
+    pub max_body: u64,
+}
+
+impl HttpRequest {
+    pub fn get(url: &str) -> Self {
+        Self {
+            method: "GET",
+            url: url.to_string(),
+            headers: Vec::new(),
+            body: None,
+            timeout: REQUEST_TIMEOUT,
+            max_body: DEFAULT_MAX_BODY,
+        }
+    }
+
+    pub fn post(url: &str, body: impl Into<Vec<u8>>) -> Self {
+        Self { method: "POST", body: Some(body.into()), ..Self::get(url) }
+    }
+
+    pub fn header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.push((name.to_string(), value.into()));
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn max_body(mut self, max_body: u64) -> Self {
+        self.max_body = max_body;
+        self
+    }
+}
+
+/// Response of any status
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: u16,
+    /// Header names in lower case
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Value of header `name` (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        let name = name.to_ascii_lowercase();
+        self.headers.iter().find(|(header, _)| *header == name).map(|(_, value)| value.as_str())
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Body as text (invalid UTF-8 replaced)
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// HTTP backend
+pub trait HttpClient {
+    /// Send `request`; any HTTP status is a response
+    ///
+    /// # Returns
+    /// Err with the error and its causes (never the URL) if no response arrived
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse, String>;
+}
+
+/// Client of the configured backend
+pub fn client() -> Result<Box<dyn HttpClient>, String> {
+    #[cfg(feature = "http-minimal")]
+    return minimal::client();
+    #[cfg(all(feature = "http-reqwest", not(feature = "http-minimal")))]
+    return reqwest_backend::client();
+}
+
+/// Name of the configured backend (diagnostics)
+pub fn backend() -> &'static str {
+    if cfg!(feature = "http-minimal") { "ureq" } else { "reqwest" }
+}
+
+/// rustls config with the bundled webpki roots; on CPUs without AES
+/// instructions (most ARM32 boards) AES-GCM falls back to slow software,
+/// so ChaCha20-Poly1305 suites are offered first there
+pub fn tls_config() -> Result<rustls::ClientConfig, String> {
+    if platform::cpu_features().aes {
+        build_tls_config(rustls::crypto::ring::default_provider())
+    } else {
+        chacha_first_tls_config()
+    }
+}
+
+/// rustls config (webpki roots) offering ChaCha20-Poly1305 suites first
+pub fn chacha_first_tls_config() -> Result<rustls::ClientConfig, String> {
+    use rustls::CipherSuite;
+
+    let mut provider = rustls::crypto::ring::default_provider();
+    provider.cipher_suites.sort_by_key(|suite| {
+        !matches!(
+            suite.suite(),
+            CipherSuite::TLS13_CHACHA20_POLY1305_SHA256
+                | CipherSuite::TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256
+                | CipherSuite::TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256
+        )
+    });
+    build_tls_config(provider)
+}
+
+fn build_tls_config(provider: rustls::crypto::CryptoProvider) -> Result<rustls::ClientConfig, String> {
+    let roots = rustls::RootCertStore { roots: webpki_roots::TLS_SERVER_ROOTS.to_vec() };
+    Ok(rustls::ClientConfig::builder_with_provider(Arc::new(provider))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| format!("Failed to configure TLS: {}", e))?
+        .with_root_certificates(roots)
+        .with_no_client_auth())
+}
+
+/// `error` and its causes, outermost first
+#[cfg(all(feature = "http-reqwest", not(feature = "http-minimal")))]
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut chain = vec![error.to_string()];
+    let mut source = error.source();
+    while let Some(cause) = source {
+        chain.push(cause.to_string());
+        source = cause.source();
+    }
+    chain.join(": ")
+}
+
+fn read_body(reader: impl Read, max_body: u64) -> Result<Vec<u8>, String> {
+    let mut body = Vec::new();
+    reader
+        .take(max_body.saturating_add(1))
+        .read_to_end(&mut body)
+        .map_err(|e| format!("Failed to read response: {}", e))?;
+    Ok(body)
+}
+
+#[cfg(all(feature = "http-reqwest", not(feature = "http-minimal")))]
+mod reqwest_backend {
+    use super::{error_chain, read_body, HttpClient, HttpRequest, HttpResponse, REQUEST_TIMEOUT};
+    use crate::utils::platform;
+
+    struct ReqwestClient(reqwest::blocking::Client);
+
+    pub fn client() -> Result<Box<dyn HttpClient>, String> {
+        let mut builder = reqwest::blocking::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .danger_accept_invalid_certs(false) // Enforce SSL verification
+            .redirect(reqwest::redirect::Policy::none());
+        // reqwest's own TLS setup unless the suite order matters
+        if !platform::cpu_features().aes {
+            builder = builder.use_preconfigured_tls(super::chacha_first_tls_config()?);
+        }
+        let client = builder.build().map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        Ok(Box::new(ReqwestClient(client)))
+    }
+
+    impl HttpClient for ReqwestClient {
+        fn send(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+            let method = reqwest::Method::from_bytes(request.method.as_bytes()).map_err(|e| e.to_string())?;
+            let mut builder = self.0.request(method, &request.url).timeout(request.timeout);
+            for (name, value) in &request.headers {
+                builder = builder.header(name.as_str(), value.as_str());
+            }
+            if let Some(body) = request.body {
+                builder = builder.body(body);
+            }
+            let response = builder.send().map_err(|e| error_chain(&e.without_url()))?;
+
+            let status = response.status().as_u16();
+            let headers = response
+                .headers()
+                .iter()
+                .filter_map(|(name, value)| Some((name.as_str().to_string(), value.to_str().ok()?.to_string())))
+                .collect();
+            let body = read_body(response, request.max_body)?;
+            Ok(HttpResponse { status, headers, body })
+        }
+    }
+}
+
+#[cfg(feature = "http-minimal")]
+mod minimal {
+    use std::sync::Arc;
+
+    use super::{read_body, HttpClient, HttpRequest, HttpResponse, REQUEST_TIMEOUT};
+
+    struct UreqClient(ureq::Agent);
+
+    pub fn client() -> Result<Box<dyn HttpClient>, String> {
+        let agent = ureq::AgentBuilder::new()
+            .timeout(REQUEST_TIMEOUT)
+            .redirects(0)
+            .try_proxy_from_env(true)
+            .tls_config(Arc::new(super::tls_config()?))
+            .build();
+        Ok(Box::new(UreqClient(agent)))
+    }
+
+    impl HttpClient for UreqClient {
+        fn send(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+            let mut builder = self.0.request(request.method, &request.url).timeout(request.timeout);
+            for (name, value) in &request.headers {
+                builder = builder.set(name, value);
+            }
+            let result = match &request.body {
+                Some(body) => builder.send_bytes(body),
+                None => builder.call(),
+            };
+            let response = match result {
+                Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+                // The transport error's own Display leads with the URL
+                Err(ureq::Error::Transport(transport)) => {
+                    return Err(match transport.message() {
+                        Some(message) => format!("{}: {}", transport.kind(), message),
+                        None => transport.kind().to_string(),
+                    });
+                }
+            };
+
+            let status = response.status();
+            let headers = response
+                .headers_names()
+                .into_iter()
+                .filter_map(|name| {
+                    let value = response.header(&name)?.to_string();
+                    Some((name.to_ascii_lowercase(), value))
+                })
+                .collect();
+            let body = read_body(response.into_reader(), request.max_body)?;
+            Ok(HttpResponse { status, headers, body })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chacha_first_tls_config() {
+        let config = chacha_first_tls_config().unwrap();
+        let suites = &config.crypto_provider().cipher_suites;
+        assert_eq!(suites[0].suite(), rustls::CipherSuite::TLS13_CHACHA20_POLY1305_SHA256);
+        // Nothing is dropped, only reordered
+        assert_eq!(suites.len(), rustls::crypto::ring::default_provider().cipher_suites.len());
+    }
+
+    #[test]
+    fn test_request_and_response_helpers() {
+        let request = HttpRequest::post("http://127.0.0.1/x", "{}").header("X-Test", "1").max_body(10);
+        assert_eq!((request.method, request.body.as_deref()), ("POST", Some(b"{}".as_slice())));
+        assert_eq!(request.headers, [("X-Test".to_string(), "1".to_string())]);
+
+        let response = HttpResponse { status: 204, headers: vec![("retry-after".to_string(), "5".to_string())], body: Vec::new() };
+        assert_eq!(response.header("Retry-After"), Some("5"));
+        assert!(response.is_success());
+        assert_eq!(read_body(&b"0123456789abc"[..], 10).unwrap().len(), 11);
+    }
+}
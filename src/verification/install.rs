@@ -0,0 +1,266 @@
+//! Install identity that survives legitimate binary updates
+//!
+//! A self-updating app gets a new binary hash (and sometimes a new path) with
+//! every release, which on its own is indistinguishable from a copied or
+//! patched install. Each install therefore keeps a random `install_id` in the
+//! state file, and an update hands it over explicitly: before replacing itself
+//! the old version runs `killer prepare-update` with the SHA-256 of its
+//! successor, which writes a handoff file signed with the shared secret. The
+//! first run of exactly that binary consumes it and reports the change as a
+//! verified handoff instead of an unexplained one.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::security::{identity, memexec};
+use crate::utils::audit;
+use crate::utils::secure_fs;
+use crate::utils::time::{self, unix_now};
+use crate::utils::state::{InstallState, StateStore};
+use super::events;
+use super::fingerprint::get_machine_fingerprint;
+use super::hmac::{create_signature, verify_signature};
+
+/// How long a handoff file stays valid (seconds)
+const HANDOFF_TTL_SECS: i64 = 7 * 24 * 3600;
+
+/// Identity resolved once per process
+static IDENTITY: OnceLock<InstallIdentity> = OnceLock::new();
+
+/// How the running binary relates to the previously seen one
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Continuity {
+    /// First run of this install
+    New,
+    /// Same binary as last run
+    Unchanged,
+    /// Binary changed and a valid handoff from the previous version was found
+    Handoff,
+    /// Binary changed without a handoff
+    Unverified,
+}
+
+/// Install identity sent with each verification
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InstallIdentity {
+    pub install_id: String,
+    pub binary_hash: String,
+    pub binary_path: String,
+    pub continuity: Continuity,
+    /// Hash of the binary seen before this one, if it changed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_binary_hash: Option<String>,
+}
+
+/// Signed handoff written by the old version before an update
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Handoff {
+    pub install_id: String,
+    pub from_binary_hash: String,
+    /// Hash of the binary the handoff is for
+    pub to_binary_hash: String,
+    pub issued_at: i64,
+    pub expires_at: i64,
+    /// HMAC over the fields above
+    pub mac: String,
+}
+
+impl Handoff {
+    fn signing_data(&self) -> String {
+        format!(
+            "{}|{}|{}|{}|{}",
+            self.install_id, self.from_binary_hash, self.to_binary_hash, self.issued_at, self.expires_at
+        )
+    }
+
+    /// Whether the handoff is authentic, unexpired and continues `state`
+    /// with the binary hashing to `binary_hash`
+    fn continues(&self, state: &InstallState, binary_hash: &str, shared_secret: &str, now: i64) -> bool {
+        verify_signature(&self.signing_data(), shared_secret, &self.mac)
+            && !time::is_expired(self.expires_at, now)
+            && state.install_id.as_deref() == Some(self.install_id.as_str())
+            && state.binary_hash.as_deref() == Some(self.from_binary_hash.as_str())
+            && self.to_binary_hash.eq_ignore_ascii_case(binary_hash)
+    }
+}
+
+/// Install identity of this process (resolved and persisted on first call)
+pub fn current(license_id: &str, shared_secret: &str) -> InstallIdentity {
+    IDENTITY.get_or_init(|| resolve(license_id, shared_secret)).clone()
+}
+
+/// Write a handoff for the next version of this binary
+///
+/// # Arguments
+/// * `to_binary_hash` - Hex SHA-256 of the next version; no other binary
+///   can claim the handoff
+///
+/// # Returns
+/// Path of the written handoff file
+pub fn prepare_update(license_id: &str, shared_secret: &str, to_binary_hash: &str) -> Result<PathBuf, String> {
+    if to_binary_hash.len() != 64 || !to_binary_hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err("the next version's hash must be a hex SHA-256".to_string());
+    }
+    let identity = current(license_id, shared_secret);
+    let now = unix_now();
+    let mut handoff = Handoff {
+        install_id: identity.install_id,
+        from_binary_hash: identity.binary_hash,
+        to_binary_hash: to_binary_hash.to_ascii_lowercase(),
+        issued_at: now,
+        expires_at: now + HANDOFF_TTL_SECS,
+        mac: String::new(),
+    };
+    handoff.mac = create_signature(&handoff.signing_data(), shared_secret);
+
+    let path = handoff_path(&StateStore::for_license(license_id));
+    let json = serde_json::to_string(&handoff).map_err(|e| format!("Failed to serialize handoff: {}", e))?;
+    secure_fs::write_private(&path, json.as_bytes()).map_err(|e| format!("Failed to write handoff: {}", e))?;
+    Ok(path)
+}
+
+fn resolve(license_id: &str, shared_secret: &str) -> InstallIdentity {
+    let binary_path = memexec::executable_path()
+        .map(|p| p.display().to_string())
+        .unwrap_or_default();
+    let image = memexec::image_path().unwrap_or_default();
+    let binary_hash = identity::sha256_file(&image).unwrap_or_else(|e| {
+        log_warn!("⚠️  Failed to hash own binary: {}", e);
+        String::new()
+    });
+
+    let store = StateStore::for_license(license_id);
+    let mut state = store.load();
+    let handoff_file = handoff_path(&store);
+    let handoff = fs::read_to_string(&handoff_file)
+        .ok()
+        .and_then(|json| serde_json::from_str::<Handoff>(&json).ok());
+
+    let identity = reconcile(&mut state.install, binary_hash, binary_path, handoff.as_ref(), shared_secret, unix_now());
+
+    match identity.continuity {
+        Continuity::Handoff => {
+            log_info!("🔁 Binary updated - install identity handed over");
+            let _ = fs::remove_file(&handoff_file);
+        }
+        Continuity::Unverified => audit::record(
+            "install_binary_changed",
+            &format!("{} changed without a valid update handoff", identity.binary_path),
+        ),
+        Continuity::New | Continuity::Unchanged => {}
+    }
+
+    let fingerprint = get_machine_fingerprint();
+    if let Some(previous) = state.install.machine_fingerprint.replace(fingerprint.clone())
+        && previous != fingerprint
+    {
+        events::record("fingerprint_drift", &format!("{} -> {}", previous, fingerprint));
+    }
+
+    if let Err(e) = store.save(&state) {
+        log_warn!("⚠️  {}", e);
+    }
+    identity
+}
+
+/// Compare the running binary with the persisted install state and update it
+fn reconcile(
+    install: &mut InstallState,
+    binary_hash: String,
+    binary_path: String,
+    handoff: Option<&Handoff>,
+    shared_secret: &str,
+    now: i64,
+) -> InstallIdentity {
+    let (continuity, previous_binary_hash) = match &install.install_id {
+        None => {
+            install.install_id = Some(hex::encode(rand::random::<[u8; 16]>()));
+            (Continuity::New, None)
+        }
+        Some(_) if install.binary_hash.as_deref() == Some(binary_hash.as_str()) => (Continuity::Unchanged, None),
+        Some(_) => {
+            let continuity = match handoff {
+                Some(handoff) if handoff.continues(install, &binary_hash, shared_secret, now) => Continuity::Handoff,
+                _ => Continuity::Unverified,
+            };
+            (continuity, install.binary_hash.clone())
+        }
+    };
+
+    install.binary_hash = Some(binary_hash.clone());
+    install.binary_path = Some(binary_path.clone());
+
+    InstallIdentity {
+        install_id: install.install_id.clone().unwrap_or_default(),
+        binary_hash,
+        binary_path,
+        continuity,
+        previous_binary_hash,
+    }
+}
+
+fn handoff_path(store: &StateStore) -> PathBuf {
+    store.path().with_extension("handoff")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed_handoff(install_id: &str, from: &str, to: &str, secret: &str, now: i64) -> Handoff {
+        let mut handoff = Handoff {
+            install_id: install_id.to_string(),
+            from_binary_hash: from.to_string(),
+            to_binary_hash: to.to_string(),
+            issued_at: now,
+            expires_at: now + HANDOFF_TTL_SECS,
+            mac: String::new(),
+        };
+        handoff.mac = create_signature(&handoff.signing_data(), secret);
+        handoff
+    }
+
+    #[test]
+    fn test_reconcile_new_and_unchanged() {
+        let mut install = InstallState::default();
+        let first = reconcile(&mut install, "h1".into(), "/app".into(), None, "secret", 100);
+        assert_eq!(first.continuity, Continuity::New);
+
+        let second = reconcile(&mut install, "h1".into(), "/app".into(), None, "secret", 200);
+        assert_eq!(second.continuity, Continuity::Unchanged);
+        assert_eq!(second.install_id, first.install_id);
+    }
+
+    #[test]
+    fn test_reconcile_update_with_and_without_handoff() {
+        let mut install = InstallState::default();
+        let id = reconcile(&mut install, "h1".into(), "/app".into(), None, "secret", 100).install_id;
+
+        // Forged handoff (wrong secret) does not count
+        let forged = signed_handoff(&id, "h1", "h2", "other", 100);
+        let mut copy = install.clone();
+        let changed = reconcile(&mut copy, "h2".into(), "/app".into(), Some(&forged), "secret", 150);
+        assert_eq!(changed.continuity, Continuity::Unverified);
+
+        // A handoff for another successor does not count
+        let elsewhere = signed_handoff(&id, "h1", "h9", "secret", 100);
+        let mut copy = install.clone();
+        let changed = reconcile(&mut copy, "h2".into(), "/app".into(), Some(&elsewhere), "secret", 150);
+        assert_eq!(changed.continuity, Continuity::Unverified);
+
+        let handoff = signed_handoff(&id, "h1", "h2", "secret", 100);
+        let updated = reconcile(&mut install, "h2".into(), "/new/app".into(), Some(&handoff), "secret", 150);
+        assert_eq!(updated.continuity, Continuity::Handoff);
+        assert_eq!(updated.install_id, id);
+        assert_eq!(updated.previous_binary_hash.as_deref(), Some("h1"));
+
+        // Expired handoff
+        let mut later = install.clone();
+        let stale = signed_handoff(&id, "h2", "h3", "secret", 0);
+        let expired = reconcile(&mut later, "h3".into(), "/new/app".into(), Some(&stale), "secret", HANDOFF_TTL_SECS + 1);
+        assert_eq!(expired.continuity, Continuity::Unverified);
+    }
+}
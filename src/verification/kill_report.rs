@@ -0,0 +1,57 @@
+//! Kill reports sent to the license server before enforcement destroys
+//! anything, and again with the result where killer outlives the kill
+
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::utils::time;
+use super::fingerprint::get_machine_fingerprint;
+use super::events;
+use super::network::post_signed;
+
+/// API path of the kill report endpoint
+const KILL_REPORT_PATH: &str = "/api/v1/kill-report";
+
+/// Kill report payload
+#[derive(Debug, Serialize)]
+struct KillReport<'a> {
+    license_id: &'a str,
+    machine_fingerprint: String,
+    /// Kill method about to run, e.g. "shred" or "self_destruct"
+    kill_method: &'a str,
+    /// "initiated" before enforcement; afterwards "succeeded" or "failed".
+    /// A self-destruct only ever reports "initiated": nothing is left to
+    /// report its result
+    outcome: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<&'a str>,
+    timestamp: i64,
+}
+
+/// Report a kill event to the server
+///
+/// The "initiated" report is sent before anything is destroyed (the binary
+/// may not survive to report afterwards). Best effort: failures are logged
+/// and never prevent enforcement.
+pub fn report_kill(config: &Config, kill_method: &str, outcome: &str, detail: Option<&str>) {
+    log_info!("📨 Reporting kill event: {} ({})", kill_method, outcome);
+
+    let report = KillReport {
+        license_id: &config.license_id,
+        machine_fingerprint: get_machine_fingerprint(),
+        kill_method,
+        outcome,
+        detail,
+        timestamp: time::protocol_now(),
+    };
+
+    match post_signed(&config.get_server_url(), KILL_REPORT_PATH, &config.license_id, &config.shared_secret, &report) {
+        Ok(status) if status == 200 || status == 202 => {}
+        Ok(status) => log_warn!("⚠️  Kill report rejected with HTTP {}", status),
+        Err(e) => log_warn!("⚠️  Failed to send kill report: {}", e),
+    }
+
+    // Queued too, so it still reaches the event log if the report was lost
+    events::record("kill", &format!("{} {}{}", kill_method, outcome, detail.map(|d| format!(": {}", d)).unwrap_or_default()));
+    events::flush(config);
+}
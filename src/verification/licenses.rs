@@ -0,0 +1,155 @@
+//! Multi-license configuration with priority fallback
+//!
+//! `licenses` lists further licenses after the top-level one (OEM plus
+//! end-customer licensing, staged migrations to a new license or server).
+//! A check tries them in order until one authorizes. The entry that
+//! authorized is remembered and tried first from then on, so a running
+//! overload keeps rechecking the license it runs under and only moves on
+//! once that one stops authorizing.
+//!
+//! Unless some entry authorizes, a signed denial decides the check: the
+//! server answered and refused. Otherwise a network error on any entry makes
+//! the whole check a network error: an unreachable server must not turn into
+//! an unsigned denial just because another entry was answered.
+
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::network::VerifyResponse;
+use super::worker;
+use crate::config::Config;
+
+/// Entry that authorized last (0 = the top-level license)
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+/// Config with entry `index` as its license (0 = the top-level one)
+pub fn for_entry(config: &Config, index: usize) -> Option<Config> {
+    if index == 0 {
+        return Some(config.clone());
+    }
+    let entry = config.licenses.get(index - 1)?;
+    Some(Config {
+        license_id: entry.license_id.clone(),
+        shared_secret: entry.shared_secret.clone(),
+        server_url: entry.server_url.clone().unwrap_or_else(|| config.server_url.clone()),
+        ..config.clone()
+    })
+}
+
+/// Entry of the license in use (0 = the top-level one)
+pub fn active_index() -> usize {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+/// Config of the license in use (reports, heartbeats and enforcement go out
+/// under it)
+pub fn active(config: &Config) -> Config {
+    for_entry(config, ACTIVE.load(Ordering::Relaxed)).unwrap_or_else(|| config.clone())
+}
+
+/// Verify each license with `verify_one`, the remembered one first, until
+/// one authorizes
+pub fn verify<E: fmt::Display + From<String>>(
+    config: &Config,
+    mut verify_one: impl FnMut(&Config) -> Result<VerifyResponse, E>,
+) -> Result<VerifyResponse, E> {
+    if config.licenses.is_empty() {
+        return verify_one(config);
+    }
+
+    let previous = ACTIVE.load(Ordering::Relaxed).min(config.licenses.len());
+    let order = std::iter::once(previous).chain((0..=config.licenses.len()).filter(|&index| index != previous));
+    let mut signed_denial = None;
+    let mut denial = None;
+    let mut error = None;
+    for index in order {
+        let Some(license) = for_entry(config, index) else {
+            continue;
+        };
+        match verify_one(&license) {
+            Ok(response) if response.authorized => {
+                if index != previous && !worker::is_abandoned() {
+                    log_info!("🔑 Now running under license {} (entry {})", license.license_id, index + 1);
+                    ACTIVE.store(index, Ordering::Relaxed);
+                }
+                return Ok(response);
+            }
+            Ok(response) => {
+                log_warn!("⚠️  License {} not authorized: {}", license.license_id, response.message);
+                if response.signature_valid {
+                    signed_denial.get_or_insert(response);
+                } else {
+                    denial.get_or_insert(response);
+                }
+            }
+            Err(e) => {
+                log_warn!("⚠️  License {} could not be verified: {}", license.license_id, e);
+                error.get_or_insert(e);
+            }
+        }
+    }
+
+    match (signed_denial, error, denial) {
+        (Some(denial), _, _) => Ok(denial),
+        (None, Some(e), _) => Err(e),
+        (None, None, Some(denial)) => Ok(denial),
+        (None, None, None) => Err(E::from("no license to verify".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LicenseEntry;
+
+    fn response(authorized: bool) -> VerifyResponse {
+        VerifyResponse { authorized, message: "test".to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn test_priority_fallback_remembers_authorized_entry() {
+        let mut config: Config = serde_json::from_str(
+            r#"{"license_id": "oem", "server_url": "https://a.example", "shared_secret": "s1"}"#,
+        )
+        .unwrap();
+        config.licenses = vec![LicenseEntry {
+            license_id: "customer".to_string(),
+            shared_secret: "s2".into(),
+            server_url: None,
+        }];
+        assert_eq!(for_entry(&config, 1).unwrap().server_url, "https://a.example");
+        assert!(for_entry(&config, 2).is_none());
+
+        // Only the second license authorizes: it is found, then tried first
+        let mut tried = Vec::new();
+        let result = verify(&config, |license| {
+            tried.push(license.license_id.clone());
+            Ok::<_, String>(response(license.license_id == "customer"))
+        });
+        assert!(result.unwrap().authorized);
+        assert_eq!(tried, ["oem", "customer"]);
+        assert_eq!(active(&config).license_id, "customer");
+
+        tried.clear();
+        let _ = verify(&config, |license| {
+            tried.push(license.license_id.clone());
+            Ok::<_, String>(response(true))
+        });
+        assert_eq!(tried, ["customer"]);
+
+        // An unanswered license makes a denial elsewhere a network error
+        let result = verify(&config, |license| match license.license_id.as_str() {
+            "customer" => Err("timeout".to_string()),
+            _ => Ok(response(false)),
+        });
+        assert_eq!(result.unwrap_err(), "timeout");
+
+        // ...unless the denial is signed
+        let result = verify(&config, |license| match license.license_id.as_str() {
+            "customer" => Err("timeout".to_string()),
+            _ => Ok(VerifyResponse { signature_valid: true, ..response(false) }),
+        });
+        assert!(result.unwrap().signature_valid);
+        ACTIVE.store(0, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,92 @@
+//! Usage metering in verification requests
+//!
+//! With `usage_metering` set, every verification request carries
+//! consumption data for the vendor's analytics: how long this overload has
+//! been running, how many checks it sent, how often the protected binary was
+//! launched on this machine and the platform it runs on. It travels with the
+//! verification round-trip; there is no separate reporting channel.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use crate::config::Config;
+use crate::utils::platform;
+use crate::utils::state::StateStore;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// When this overload started metering
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// Verification requests sent by this process
+static CHECKS: AtomicU64 = AtomicU64::new(0);
+
+/// Launches of the protected binary, as of this one
+static LAUNCHES: AtomicU64 = AtomicU64::new(0);
+
+/// Metering data of one verification request
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Metering {
+    /// Seconds since the overload started
+    pub uptime_secs: u64,
+    /// Verification requests of this overload, this one included
+    pub checks: u64,
+    /// Launches of the protected binary on this machine, this one included
+    pub launches: u64,
+    pub os: &'static str,
+    pub arch: &'static str,
+}
+
+/// Count this launch of the protected binary and enable metering if
+/// configured
+pub fn record_launch(config: &Config) {
+    let _ = STARTED.set(Instant::now());
+    if !config.usage_metering {
+        return;
+    }
+
+    let store = StateStore::for_license(&config.license_id);
+    let mut state = store.load();
+    state.metering.launches += 1;
+    if let Err(e) = store.save(&state) {
+        log_debug!("  Launch count not persisted: {}", e);
+    }
+    LAUNCHES.store(state.metering.launches, Ordering::Relaxed);
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Count a verification request, returning its metering data if enabled
+pub fn next_request() -> Option<Metering> {
+    let checks = CHECKS.fetch_add(1, Ordering::Relaxed) + 1;
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+    let (os, arch) = platform::detect_platform().os_arch();
+    Some(Metering {
+        uptime_secs: STARTED.get().map(|started| started.elapsed().as_secs()).unwrap_or(0),
+        checks,
+        launches: LAUNCHES.load(Ordering::Relaxed),
+        os,
+        arch,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metering_counts_requests() {
+        let before = CHECKS.load(Ordering::Relaxed);
+        ENABLED.store(true, Ordering::Relaxed);
+        let metering = next_request().unwrap();
+        assert!(metering.checks > before);
+        assert_eq!(metering.os, std::env::consts::OS);
+
+        let json = serde_json::to_value(&metering).unwrap();
+        assert!(json["uptime_secs"].is_u64() && json["launches"].is_u64());
+        assert!(!json["arch"].as_str().unwrap().is_empty());
+    }
+}
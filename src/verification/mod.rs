@@ -2,7 +2,32 @@
 pub mod hmac;
 pub mod fingerprint;
 pub mod network;
+pub mod http;
+pub mod cache;
+pub mod usage;
+pub mod tamper;
+pub mod kill_report;
+pub mod install;
+pub mod denial;
+pub mod heartbeat;
+pub mod events;
+pub mod webhook;
+pub mod canonical;
+pub mod fallback;
+pub mod offline;
+pub mod seat;
+pub mod licenses;
+pub mod patch;
+pub mod rotation;
+pub mod ratelimit;
+pub mod metering;
+pub mod crash;
+pub mod pause;
+pub mod self_update;
+pub mod worker;
+pub mod fetch;
+pub mod broadcast;
 
 pub use hmac::{create_signature, verify_signature};
 pub use fingerprint::get_machine_fingerprint;
-pub use network::{verify_license, VerifyResponse};
+pub use network::{verify_license, VerifyError, VerifyResponse};
@@ -1,9 +1,124 @@
 /// Network communication for license verification
 use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::fmt;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::Duration;
 
-use super::hmac::create_signature;
-use super::fingerprint::get_machine_fingerprint;
+use super::cache;
+use super::canonical;
+use super::hmac::{create_signature, verify_signature};
+use super::http::{self, HttpRequest, HttpResponse};
+use super::fingerprint::{self, get_machine_fingerprint, FingerprintComponent};
+use super::install::{self, InstallIdentity};
+use super::metering::{self, Metering};
+use super::pause;
+use super::ratelimit;
+use super::rotation::{self, SecretRotation};
+use super::seat;
+use super::worker;
+use super::self_update::OverloadUpdate;
+use crate::security::capabilities::{self, Capabilities};
+use crate::security::scheduler::{self, OverheadReport};
+use crate::security::trust::{self, SuccessorKey};
+use crate::utils::{redact, session, time};
+
+/// API path of the verification endpoint
+pub const VERIFY_PATH: &str = "/api/v1/verify";
+
+/// Header carrying the HMAC of the response body
+const RESPONSE_SIGNATURE_HEADER: &str = "X-Response-Signature";
+
+/// Header carrying the request nonce a strict response signature must cover
+const REQUEST_NONCE_HEADER: &str = "X-Request-Nonce";
+
+/// Header carrying the HMAC of the canonical JSON request body
+const BODY_SIGNATURE_HEADER: &str = "X-Body-Signature";
+
+/// Header carrying the seat lease token while one is held (see `seat`)
+const LEASE_TOKEN_HEADER: &str = "X-Lease-Token";
+
+/// Header announcing how `X-Signature` was computed
+const PROTOCOL_VERSION_HEADER: &str = "X-Protocol-Version";
+
+/// Request signing scheme: version 1 signed only license ID and timestamp
+/// (leaving the fingerprint spoofable), version 2 signs the whole canonical
+/// body. The server tells them apart by `X-Protocol-Version` during rollout.
+const PROTOCOL_VERSION: u32 = 2;
+
+/// Timeout of every request to the server
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// HTTP status of the latest verification response (0 = no response)
+static LAST_HTTP_STATUS: AtomicU16 = AtomicU16::new(0);
+
+/// Longest `Retry-After` honored: a hostile or broken gateway must not be
+/// able to suspend verification
+pub const MAX_RETRY_AFTER: Duration = Duration::from_secs(600);
+
+/// Longest kill grace taken from the server (signed denials and runtime
+/// patches): a grace must not postpone enforcement indefinitely
+pub const MAX_KILL_GRACE_MS: u64 = 60 * 60 * 1000;
+
+/// How a verification error should be handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// No answer, a server error (5xx), timeout or rate limit (408/429):
+    /// the license was not judged - try again, not before `retry_after`
+    Retryable { retry_after: Option<Duration> },
+    /// An answer that cannot be trusted or used (redirect, unsigned or
+    /// malformed error body, inconsistent response)
+    Invalid,
+}
+
+impl ErrorClass {
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, ErrorClass::Retryable { .. })
+    }
+
+    /// Server-requested delay before the next attempt
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            ErrorClass::Retryable { retry_after } => *retry_after,
+            ErrorClass::Invalid => None,
+        }
+    }
+}
+
+/// A failed verification and how to handle it
+///
+/// Definitive answers - including signed 401/403 denials - are not errors:
+/// they arrive as an unauthorized `VerifyResponse`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyError {
+    pub class: ErrorClass,
+    pub message: String,
+}
+
+impl VerifyError {
+    /// No answer that judged the license
+    pub fn retryable(message: String, retry_after: Option<Duration>) -> Self {
+        Self { class: ErrorClass::Retryable { retry_after }, message }
+    }
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// An answer that cannot be used
+impl From<String> for VerifyError {
+    fn from(message: String) -> Self {
+        Self { class: ErrorClass::Invalid, message }
+    }
+}
+
+impl From<VerifyError> for String {
+    fn from(error: VerifyError) -> Self {
+        error.message
+    }
+}
 
 /// Verification request payload
 #[derive(Serialize)]
@@ -11,16 +126,117 @@ struct VerifyRequest {
     license_id: String,
     machine_fingerprint: String,
     timestamp: i64,
+    /// Login session, so concurrent users of one machine count as separate seats
+    session_id: String,
+    user: String,
+    /// CPU overhead of security checks, for telemetry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    security_overhead: Option<OverheadReport>,
+    /// What this platform can enforce, so the server can pick a valid kill method
+    capabilities: Capabilities,
+    /// Hashed fingerprint inputs, only while the server requests diagnostics
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fingerprint_components: Option<Vec<FingerprintComponent>>,
+    /// Install identity, so updated binaries are not mistaken for new installs
+    install: InstallIdentity,
+    /// Secret rotation this request is signed under, until the server saw it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secret_rotated: Option<String>,
+    /// Consumption data, with `usage_metering`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metering: Option<Metering>,
 }
 
 /// Verification response from server
-#[derive(Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct VerifyResponse {
     pub authorized: bool,
     pub message: String,
     pub expires_in: Option<i64>,
+    /// Server time (unix seconds) the license lapses; preferred over `expires_in`
+    #[serde(default)]
+    pub expires_at: Option<i64>,
     pub check_interval_ms: Option<u64>,
     pub kill_method: Option<String>,
+    /// New verification endpoint (runtime patch, see `verification::patch`)
+    #[serde(default)]
+    pub server_url: Option<String>,
+    /// New log level (runtime patch)
+    #[serde(default)]
+    pub log_level: Option<String>,
+    /// Features granted by the license (empty if the server sends none)
+    #[serde(default)]
+    pub entitlements: Vec<String>,
+    /// Server wall-clock time (unix seconds) when the response was produced
+    #[serde(default)]
+    pub server_time: Option<i64>,
+    /// Server asks for hashed fingerprint components in following requests
+    #[serde(default)]
+    pub fingerprint_diagnostics: bool,
+    /// Grace period before an unauthorized result is enforced (overrides config)
+    #[serde(default)]
+    pub kill_grace_ms: Option<u64>,
+    /// Enforce this denial regardless of `enforcement_policy`
+    #[serde(default)]
+    pub enforcement_required: bool,
+    /// The seat lease sent with the request is no longer valid
+    #[serde(default)]
+    pub lease_expired: bool,
+    /// Failure tolerance before enforcement (overrides config, 0 = unlimited)
+    #[serde(default)]
+    pub max_consecutive_failures: Option<u32>,
+    /// Primary failures before the fallback endpoint is consulted (overrides
+    /// config)
+    #[serde(default)]
+    pub fallback_after_failures: Option<u32>,
+    /// Next trust root key, signed by the current one (see `security::trust`)
+    #[serde(default)]
+    pub successor_key: Option<SuccessorKey>,
+    /// Server time (unix seconds) until which enforcement is paused for
+    /// maintenance (see `verification::pause`)
+    #[serde(default)]
+    pub paused_until: Option<i64>,
+    /// Newer overload to install (see `verification::self_update`)
+    #[serde(default)]
+    pub overload_update: Option<OverloadUpdate>,
+    /// New shared secret (see `verification::rotation`); never serialized
+    /// back out (status snapshots)
+    #[serde(default, skip_serializing)]
+    pub secret_rotation: Option<SecretRotation>,
+    /// Whether the response body carried a valid `X-Response-Signature`
+    /// (set locally, never taken from the body)
+    #[serde(skip)]
+    pub signature_valid: bool,
+}
+
+impl VerifyResponse {
+    /// Grace before enforcing this denial: the server's, if signed, capped at
+    /// `MAX_KILL_GRACE_MS`, otherwise `configured`
+    pub fn kill_grace_or(&self, configured: u64) -> u64 {
+        // An unsigned grace could postpone the kill indefinitely
+        self.kill_grace_ms
+            .filter(|_| self.signature_valid)
+            .map_or(configured, |grace| grace.min(MAX_KILL_GRACE_MS))
+    }
+}
+
+/// HTTP status of the latest verification response, if one arrived
+pub fn last_http_status() -> Option<u16> {
+    match LAST_HTTP_STATUS.load(Ordering::Relaxed) {
+        0 => None,
+        status => Some(status),
+    }
+}
+
+/// Statuses that say nothing about the license: gateway and server errors,
+/// timeouts and rate limits
+pub fn retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 429) || (500..600).contains(&status)
+}
+
+/// `Retry-After` in seconds, capped at `MAX_RETRY_AFTER` (HTTP dates are ignored)
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse::<u64>().ok().map(|secs| Duration::from_secs(secs).min(MAX_RETRY_AFTER))
 }
 
 /// Verify license with server
@@ -33,120 +249,512 @@ pub struct VerifyResponse {
 /// * `first_check` - Whether this is the first check (startup) or interval check
 /// 
 /// # Returns
-/// Result<VerifyResponse, String> - VerifyResponse if successful, Err on failure
+/// VerifyResponse if the server answered, Err (classified) otherwise
 pub fn verify_license(
     license_id: &str,
     server_url: &str,
     shared_secret: &str,
     grace_period: u32,
     first_check: bool,
-) -> Result<VerifyResponse, String> {
-    // Get current timestamp
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map_err(|e| format!("System time error: {}", e))?
-        .as_secs() as i64;
+) -> Result<VerifyResponse, VerifyError> {
+    verify_at(license_id, server_url, shared_secret, grace_period, first_check, None)
+}
+
+/// Verify against an endpoint that must prove freshness (break-glass fallback)
+///
+/// A random nonce is sent with the request and the response must carry an
+/// HMAC over nonce + body. Unlike the primary endpoint, unsigned answers and
+/// HTTP errors are never trusted, not even as "unauthorized".
+pub fn verify_license_strict(
+    license_id: &str,
+    server_url: &str,
+    shared_secret: &str,
+    first_check: bool,
+) -> Result<VerifyResponse, VerifyError> {
+    let nonce = hex::encode(rand::random::<[u8; 16]>());
+    verify_at(license_id, server_url, shared_secret, 0, first_check, Some(&nonce))
+}
+
+fn verify_at(
+    license_id: &str,
+    server_url: &str,
+    shared_secret: &str,
+    grace_period: u32,
+    first_check: bool,
+    nonce: Option<&str>,
+) -> Result<VerifyResponse, VerifyError> {
+    ratelimit::acquire();
+    if !worker::is_abandoned() {
+        LAST_HTTP_STATUS.store(0, Ordering::Relaxed);
+    }
+
+    // Server-aligned timestamp (see utils::time)
+    let timestamp = time::protocol_now();
 
     // Get machine fingerprint
     let machine_fingerprint = get_machine_fingerprint();
 
-    // Create HMAC signature
-    let signature_data = format!("{}{}", license_id, timestamp);
-    let signature = create_signature(&signature_data, shared_secret);
-
     // Build request
+    let session = session::current_session();
     let payload = VerifyRequest {
         license_id: license_id.to_string(),
         machine_fingerprint: machine_fingerprint.clone(),
         timestamp,
+        session_id: session.session_id,
+        user: session.user,
+        security_overhead: scheduler::latest_overhead(),
+        capabilities: capabilities::current(),
+        fingerprint_components: fingerprint::diagnostics_requested().then(fingerprint::fingerprint_components),
+        install: install::current(license_id, shared_secret),
+        secret_rotated: rotation::pending_confirmation(license_id),
+        metering: metering::next_request(),
     };
+    // Canonical body, so the server can verify the signatures byte for byte
+    let body = canonical::to_string(&payload)?;
+
+    // Create HMAC signature
+    let lease_token = seat::token();
+    let signature = request_signature(license_id, timestamp, &body, shared_secret, lease_token.as_deref());
 
     // Append API path to base URL
-    let clean_url = server_url.trim_end_matches('/');
-    let url = if clean_url.ends_with("/api/v1/verify") {
-        clean_url.to_string()
-    } else {
-        format!("{}/api/v1/verify", clean_url)
-    };
+    let url = endpoint_url(server_url, VERIFY_PATH);
 
     // Make HTTP request with timeout
-    let client = reqwest::blocking::Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .danger_accept_invalid_certs(false) // Enforce SSL verification
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let client = http::client()?;
 
-    eprintln!("🌐 POST {} with signature: {}", url, signature);
+    log_debug!("🌐 POST {} with signature: {}", redact::url(&url), redact::secret(&signature));
     
-    let response = client
-        .post(&url)
+    let body_signature = create_signature(&body, shared_secret);
+    let mut request = HttpRequest::post(&url, body)
         .header("Content-Type", "application/json")
         .header("X-License-ID", license_id)
         .header("X-Timestamp", timestamp.to_string())
         .header("X-Signature", signature.as_str())
-        .header("X-First-Check", if first_check { "true" } else { "false" })
-        .json(&payload)
-        .send();
+        .header(PROTOCOL_VERSION_HEADER, PROTOCOL_VERSION.to_string())
+        .header("X-First-Check", if first_check { "true" } else { "false" });
+    if let Some(nonce) = nonce {
+        request = request.header(REQUEST_NONCE_HEADER, nonce);
+    }
+    if let Some(lease_token) = &lease_token {
+        request = request.header(LEASE_TOKEN_HEADER, lease_token.as_str());
+    }
+    let response = client.send(request.header(BODY_SIGNATURE_HEADER, body_signature));
     
     // Handle network errors with grace period
     let response = match response {
         Ok(resp) => resp,
         Err(e) => {
             if grace_period > 0 {
-                eprintln!("⚠️  Network error: {}. Grace period: {}s. Allowing offline access.", e, grace_period);
+                log_warn!("⚠️  Network error: {}. Grace period: {}s. Allowing offline access.", e, grace_period);
                 // TODO: Implement grace period tracking (store last successful verification time)
                 return Ok(VerifyResponse {
                     authorized: true,
                     message: "Offline access granted".to_string(),
-                    expires_in: None,
-                    check_interval_ms: None,
-                    kill_method: None,
+                    ..Default::default()
                 }); // Allow offline access during grace period
             } else {
-                return Err(format!("HTTP request failed: {}", e));
+                return Err(VerifyError::retryable(format!("HTTP request failed: {}", e), None));
             }
         }
     };
 
     // Check response status
-    eprintln!("📡 Response status: {}", response.status());
+    log_debug!("📡 Response status: {}", response.status);
+    if worker::is_abandoned() {
+        return Err("answer arrived after the watchdog abandoned this check".to_string().into());
+    }
+    LAST_HTTP_STATUS.store(response.status, Ordering::Relaxed);
     
-    if response.status() != 200 {
-        // Print error response body
-        if let Ok(text) = response.text() {
-            eprintln!("❌ Server response: {}", text);
+    if response.status != 200 {
+        let status = response.status;
+        let class = if retryable_status(status) {
+            ErrorClass::Retryable { retry_after: response.header("Retry-After").and_then(parse_retry_after) }
+        } else {
+            ErrorClass::Invalid
+        };
+        if nonce.is_some() {
+            return Err(VerifyError { class, message: format!("Strict endpoint returned HTTP {}", status) });
+        }
+        let body = response.text();
+        log_error!("❌ Server response: {}", redact::scrub(&body.chars().take(512).collect::<String>()));
+
+        if let Some(location) = response.header("Location") {
+            log_debug!("↪️  Redirected to {} (not followed)", redact::url(location));
         }
-        return Ok(VerifyResponse {
-            authorized: false,
-            message: "HTTP error".to_string(),
-            expires_in: None,
-            check_interval_ms: None,
-            kill_method: None,
-        });
+        return classify_error_response(
+            status,
+            response.header("Content-Type"),
+            &body,
+            response.header(RESPONSE_SIGNATURE_HEADER),
+            shared_secret,
+        )
+        .map_err(|message| VerifyError { class, message })
+        .inspect(|denial| pause::observe(license_id, shared_secret, denial));
     }
 
+    // The server signs the raw body with the shared secret; only signed
+    // responses are trusted for security-relevant fields (e.g. server_time)
+    let response_signature = response.header(RESPONSE_SIGNATURE_HEADER);
+    let body = response.text();
+
     // Parse response
-    let verify_response: VerifyResponse = response
-        .json()
+    let mut verify_response: VerifyResponse = serde_json::from_str(&body)
         .map_err(|e| format!("Failed to parse response: {}", e))?;
+    
+    verify_response.signature_valid = response_signature
+        .is_some_and(|signature| body_signature_valid(nonce.unwrap_or(""), &body, shared_secret, signature));
+    if nonce.is_some() && !verify_response.signature_valid {
+        return Err("Strict endpoint response lacks a valid nonce-bound signature".to_string().into());
+    }
+
+    // Only a signed server_time may move our request timestamps
+    if verify_response.signature_valid
+        && let Some(server_time) = verify_response.server_time
+        && let Err(e) = time::record_server_time(server_time, time::unix_now())
+    {
+        log_warn!("⚠️  Clock skew not compensated: {}", e);
+    }
+
+    // Diagnostics expose extra (hashed) machine data: only on signed request
+    fingerprint::set_diagnostics_requested(verify_response.fingerprint_diagnostics && verify_response.signature_valid);
+
+    // Key rotation only travels on signed responses; the certificate itself
+    // is checked against the trust chain
+    if let Some(successor) = verify_response.successor_key.as_ref().filter(|_| verify_response.signature_valid)
+        && let Err(e) = trust::accept_successor(license_id, successor)
+    {
+        log_warn!("⚠️  Rejected successor trust key: {}", e);
+    }
+
+    pause::observe(license_id, shared_secret, &verify_response);
+
+    // Keep the latest server answer for local (network-free) queries
+    cache::store(&verify_response);
 
     Ok(verify_response)
 }
 
+/// Classify a non-200 verification response
+///
+/// Only a signed JSON denial is an authoritative "unauthorized". Redirects,
+/// gateway/rate-limit errors, HTML error pages (S3, load balancers, captive
+/// portals) and unsigned bodies are infrastructure failures: Err, so callers
+/// retry instead of enforcing.
+fn classify_error_response(
+    status: u16,
+    content_type: Option<&str>,
+    body: &str,
+    signature: Option<&str>,
+    shared_secret: &str,
+) -> Result<VerifyResponse, String> {
+    if (300..400).contains(&status) {
+        return Err(format!("Infrastructure error: HTTP {} redirect", status));
+    }
+    // Even a signed body: an overloaded or failing server has not judged the license
+    if retryable_status(status) {
+        return Err(format!("Infrastructure error: HTTP {} (retryable)", status));
+    }
+
+    let is_html = content_type.is_some_and(|ct| ct.to_ascii_lowercase().contains("html"))
+        || body.trim_start().starts_with('<');
+    if is_html {
+        return Err(format!("Infrastructure error: HTTP {} with HTML body", status));
+    }
+
+    let Ok(mut denial) = serde_json::from_str::<VerifyResponse>(body) else {
+        return Err(format!("Infrastructure error: HTTP {} with non-JSON body", status));
+    };
+    if !signature.is_some_and(|sig| body_signature_valid("", body, shared_secret, sig)) {
+        return Err(format!("Infrastructure error: HTTP {} with unsigned body", status));
+    }
+    if denial.authorized {
+        return Err(format!("Inconsistent response: HTTP {} claiming authorization", status));
+    }
+
+    denial.signature_valid = true;
+    Ok(denial)
+}
+
+/// X-Signature (protocol version 2): HMAC over license ID, timestamp and the
+/// canonical JSON body, followed by the seat lease token while one is held
+fn request_signature(
+    license_id: &str,
+    timestamp: i64,
+    canonical_body: &str,
+    shared_secret: &str,
+    lease_token: Option<&str>,
+) -> String {
+    create_signature(
+        &format!("{}{}{}{}", license_id, timestamp, canonical_body, lease_token.unwrap_or("")),
+        shared_secret,
+    )
+}
+
+/// Whether `signature` covers `prefix` + the body as received, or + its
+/// canonical form (for servers that sign canonical JSON but send it formatted)
+fn body_signature_valid(prefix: &str, body: &str, shared_secret: &str, signature: &str) -> bool {
+    verify_signature(&format!("{}{}", prefix, body), shared_secret, signature)
+        || canonical::canonicalize(body)
+            .is_ok_and(|canonical| verify_signature(&format!("{}{}", prefix, canonical), shared_secret, signature))
+}
+
+/// POST a signed JSON payload to another API endpoint on the license server
+///
+/// Uses the same HMAC headers as verification so the server can authenticate
+/// reports (usage, tamper, ...) with the license's shared secret.
+///
+/// # Returns
+/// HTTP status code of the response
+pub fn post_signed<T: Serialize>(
+    server_url: &str,
+    path: &str,
+    license_id: &str,
+    shared_secret: &str,
+    payload: &T,
+) -> Result<u16, String> {
+    Ok(send_signed(server_url, path, license_id, shared_secret, payload, None)?.status)
+}
+
+/// POST a signed JSON payload and read a response the server must have signed
+/// for this request
+///
+/// `nonce` goes out in a header (the payload should carry it too) and the
+/// response signature must cover it, so an answer to another request cannot
+/// be replayed.
+///
+/// # Returns
+/// HTTP status code and body; Err if the body lacks a valid signature,
+/// retryable if there was no answer or a 5xx/408/429 (see `retryable_status`)
+pub fn post_signed_verified<T: Serialize>(
+    server_url: &str,
+    path: &str,
+    license_id: &str,
+    shared_secret: &str,
+    payload: &T,
+    nonce: &str,
+) -> Result<(u16, String), VerifyError> {
+    let response = send_signed(server_url, path, license_id, shared_secret, payload, Some(nonce))?;
+    let status = response.status;
+    if retryable_status(status) {
+        let retry_after = response.header("Retry-After").and_then(parse_retry_after);
+        return Err(VerifyError::retryable(format!("{} returned HTTP {}", path, status), retry_after));
+    }
+    let body = response.text();
+
+    let signature = response.header(RESPONSE_SIGNATURE_HEADER);
+    if !signature.is_some_and(|signature| body_signature_valid(nonce, &body, shared_secret, signature)) {
+        return Err(format!("Response from {} (HTTP {}) is not signed", path, status).into());
+    }
+    Ok((status, body))
+}
+
+fn send_signed<T: Serialize>(
+    server_url: &str,
+    path: &str,
+    license_id: &str,
+    shared_secret: &str,
+    payload: &T,
+    nonce: Option<&str>,
+) -> Result<HttpResponse, VerifyError> {
+    let timestamp = time::protocol_now();
+
+    let url = endpoint_url(server_url, path);
+    let body = canonical::to_string(payload)?;
+    let lease_token = seat::token();
+    let signature = request_signature(license_id, timestamp, &body, shared_secret, lease_token.as_deref());
+
+    let body_signature = create_signature(&body, shared_secret);
+    let mut request = HttpRequest::post(&url, body)
+        .header("Content-Type", "application/json")
+        .header("X-License-ID", license_id)
+        .header("X-Timestamp", timestamp.to_string())
+        .header("X-Signature", signature.as_str())
+        .header(PROTOCOL_VERSION_HEADER, PROTOCOL_VERSION.to_string());
+    if let Some(lease_token) = &lease_token {
+        request = request.header(LEASE_TOKEN_HEADER, lease_token.as_str());
+    }
+    if let Some(nonce) = nonce {
+        request = request.header(REQUEST_NONCE_HEADER, nonce);
+    }
+    http::client()?
+        .send(request.header(BODY_SIGNATURE_HEADER, body_signature))
+        .map_err(|e| VerifyError::retryable(format!("HTTP request to {} failed: {}", path, e), None))
+}
+
+/// Why the license server could not be reached (`killer doctor`)
+#[derive(Debug)]
+pub enum ProbeFailure {
+    /// DNS, TCP or timeout
+    Unreachable(String),
+    /// Connected, but the TLS handshake or certificate check failed
+    Tls(String),
+}
+
+/// GET the API root of the server
+///
+/// # Returns
+/// HTTP status code (any status means the server is reachable)
+pub fn probe(server_url: &str) -> Result<u16, ProbeFailure> {
+    let client = http::client().map_err(ProbeFailure::Unreachable)?;
+    let url = endpoint_url(server_url, "/");
+    match client.send(HttpRequest::get(&url)) {
+        Ok(response) => Ok(response.status),
+        Err(e) => {
+            let detail = redact::scrub(&e);
+            let lower = detail.to_lowercase();
+            if ["certificate", "tls", "handshake"].iter().any(|needle| lower.contains(needle)) {
+                Err(ProbeFailure::Tls(detail))
+            } else {
+                Err(ProbeFailure::Unreachable(detail))
+            }
+        }
+    }
+}
+
+/// POST an unsigned JSON payload (for flows without access to the shared secret)
+///
+/// # Returns
+/// HTTP status code and response body
+pub fn post_json<T: Serialize>(server_url: &str, path: &str, payload: &T) -> Result<(u16, String), String> {
+    let url = endpoint_url(server_url, path);
+
+    let body = serde_json::to_vec(payload).map_err(|e| format!("Failed to serialize payload: {}", e))?;
+
+    let response = http::client()?
+        .send(HttpRequest::post(&url, body).header("Content-Type", "application/json"))
+        .map_err(|e| format!("HTTP request to {} failed: {}", path, e))?;
+    Ok((response.status, response.text()))
+}
+
+/// Download a file over HTTP(S), refusing more than `max_len` bytes
+pub fn download(url: &str, max_len: u64) -> Result<Vec<u8>, String> {
+    let response = http::client()?
+        .send(HttpRequest::get(url).timeout(Duration::from_secs(600)).max_body(max_len))
+        .map_err(|e| format!("Download of {} failed: {}", url, e))?;
+
+    if !response.is_success() {
+        return Err(format!("Download of {} failed with HTTP {}", url, response.status));
+    }
+    if response.body.len() as u64 > max_len {
+        return Err(format!("Download of {} is larger than {} bytes", url, max_len));
+    }
+    Ok(response.body)
+}
+
+/// Resolve an API path against the configured server URL
+///
+/// The configured URL may be the bare server (`https://api.example.com`) or the
+/// full verification endpoint; both resolve to the same API root.
+pub fn endpoint_url(server_url: &str, path: &str) -> String {
+    let clean_url = server_url.trim_end_matches('/');
+    let base = clean_url.strip_suffix(VERIFY_PATH).unwrap_or(clean_url);
+    format!("{}{}", base, path)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     
+    #[test]
+    fn test_request_signature_covers_payload_and_lease_token() {
+        let body = |fingerprint: &str| {
+            canonical::to_string(&serde_json::json!({
+                "license_id": "lic_test",
+                "machine_fingerprint": fingerprint,
+                "timestamp": 1700000000,
+            }))
+            .unwrap()
+        };
+        let sign = |body: &str, lease_token| request_signature("lic_test", 1700000000, body, "secret", lease_token);
+
+        let plain = sign(&body("fp_a"), None);
+        assert_eq!(plain, create_signature(&format!("lic_test1700000000{}", body("fp_a")), "secret"));
+        // A fingerprint swapped in transit no longer matches
+        assert_ne!(plain, sign(&body("fp_b"), None));
+
+        let leased = sign(&body("fp_a"), Some("seat_1"));
+        assert_eq!(leased, create_signature(&format!("lic_test1700000000{}seat_1", body("fp_a")), "secret"));
+        assert_ne!(leased, sign(&body("fp_a"), Some("seat_2")));
+    }
+    
     #[test]
     fn test_verify_request_serialization() {
         let req = VerifyRequest {
             license_id: "lic_test".to_string(),
             machine_fingerprint: "fp_test".to_string(),
             timestamp: 1234567890,
+            session_id: "3".to_string(),
+            user: "alice".to_string(),
+            security_overhead: None,
+            capabilities: capabilities::current(),
+            fingerprint_components: Some(fingerprint::fingerprint_components()),
+            install: InstallIdentity {
+                install_id: "inst".to_string(),
+                binary_hash: "h2".to_string(),
+                binary_path: "/app".to_string(),
+                continuity: install::Continuity::Handoff,
+                previous_binary_hash: Some("h1".to_string()),
+            },
+            secret_rotated: None,
+            metering: None,
         };
         
         let json = serde_json::to_string(&req).unwrap();
         assert!(json.contains("lic_test"));
         assert!(json.contains("fp_test"));
+        assert!(json.contains("\"session_id\":\"3\""));
+        assert!(json.contains("\"continuity\":\"handoff\""));
+        assert!(json.contains("\"label\":\"machine_id\""));
+        assert!(!json.contains("secret_rotated"));
+    }
+    
+    #[test]
+    fn test_endpoint_url() {
+        assert_eq!(endpoint_url("https://api.example.com", VERIFY_PATH), "https://api.example.com/api/v1/verify");
+        assert_eq!(endpoint_url("https://api.example.com/", "/api/v1/usage"), "https://api.example.com/api/v1/usage");
+        assert_eq!(
+            endpoint_url("http://localhost:8080/api/v1/verify", "/api/v1/usage"),
+            "http://localhost:8080/api/v1/usage"
+        );
+    }
+    
+    #[test]
+    fn test_classify_error_response() {
+        let html = "<html><body>502 Bad Gateway</body></html>";
+        assert!(classify_error_response(502, Some("text/html"), html, None, "secret").is_err());
+        assert!(classify_error_response(403, Some("text/html; charset=utf-8"), html, None, "secret").is_err());
+        assert!(classify_error_response(302, None, "", None, "secret").is_err());
+
+        let denial = r#"{"authorized":false,"message":"revoked","expires_in":null,"check_interval_ms":null,"kill_method":"shred"}"#;
+        assert!(classify_error_response(403, Some("application/json"), denial, None, "secret").is_err());
+        assert!(classify_error_response(403, Some("application/json"), denial, Some("bad"), "secret").is_err());
+
+        let signature = create_signature(denial, "secret");
+        let response = classify_error_response(403, Some("application/json"), denial, Some(&signature), "secret").unwrap();
+        assert!(!response.authorized);
+        assert!(response.signature_valid);
+        assert_eq!(response.kill_method.as_deref(), Some("shred"));
+
+        // Server errors and rate limits are never denials
+        for status in [429, 500, 502, 503] {
+            let signature = create_signature(denial, "secret");
+            assert!(classify_error_response(status, Some("application/json"), denial, Some(&signature), "secret").is_err());
+            assert!(retryable_status(status));
+        }
+        assert!(!retryable_status(401) && !retryable_status(403) && !retryable_status(404));
+
+        assert_eq!(parse_retry_after(" 30 "), Some(Duration::from_secs(30)));
+        assert_eq!(parse_retry_after("86400"), Some(MAX_RETRY_AFTER));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+    }
+    
+    #[test]
+    fn test_body_signature_accepts_canonical_form() {
+        let formatted = "{\n  \"message\": \"ok\",\n  \"authorized\": true\n}";
+        let signature = create_signature(r#"nonce{"authorized":true,"message":"ok"}"#, "secret");
+        
+        assert!(body_signature_valid("nonce", formatted, "secret", &signature));
+        assert!(!body_signature_valid("other", formatted, "secret", &signature));
+        assert!(!body_signature_valid("nonce", formatted, "wrong", &signature));
     }
 }
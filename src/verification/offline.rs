@@ -0,0 +1,168 @@
+//! Offline activation with signed license files
+//!
+//! Air-gapped sites cannot reach the license server at all. With
+//! `activation_mode: "offline"` every check validates a detached license file
+//! instead of sending a request:
+//!
+//! ```json
+//! {"license": "{\"license_id\":\"lic_123\",\"fingerprints\":[\"...\"],\"expires_at\":1767225600}",
+//!  "signature": "<hex Ed25519 signature of the license string>"}
+//! ```
+//!
+//! The license is kept as the exact string that was signed (context
+//! `offline-license`, see `security::trust`), so no re-serialization can
+//! change its meaning. It must name our license ID, list this machine's
+//! fingerprint (an empty list allows any machine) and not be expired. The
+//! expiry is passed on like a server lease, so renewal warnings and the
+//! clock guard work as in online mode.
+//!
+//! A file that cannot be read is an error (retried like a network error); a
+//! file that is forged, expired or for another license or machine is a
+//! denial.
+
+use serde::Deserialize;
+use std::fs;
+use std::path::PathBuf;
+
+use super::fingerprint::get_machine_fingerprint;
+use super::network::VerifyResponse;
+use crate::config::Config;
+use crate::security::trust;
+use crate::utils::time;
+
+/// Signature context of offline license files
+pub const LICENSE_CONTEXT: &str = "offline-license";
+
+/// License file as distributed
+#[derive(Debug, Deserialize)]
+struct LicenseFile {
+    /// JSON of `OfflineLicense`, exactly as signed
+    license: String,
+    /// Hex Ed25519 signature
+    signature: String,
+}
+
+/// Signed license terms
+#[derive(Debug, Deserialize)]
+struct OfflineLicense {
+    license_id: String,
+    /// Machine fingerprints the license is bound to (empty = any machine)
+    #[serde(default)]
+    fingerprints: Vec<String>,
+    /// Unix time the license lapses (None = perpetual)
+    #[serde(default)]
+    expires_at: Option<i64>,
+}
+
+/// Validate the license file
+pub fn verify(config: &Config) -> Result<VerifyResponse, String> {
+    let path = license_path(config)?;
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read license file {}: {}", path.display(), e))?;
+
+    let response = evaluate(
+        &contents,
+        |payload, signature| trust::verify_signed_blob(&config.license_id, LICENSE_CONTEXT, payload, signature),
+        &config.license_id,
+        &get_machine_fingerprint(),
+        time::unix_now(),
+    );
+    if response.authorized {
+        log_debug!("📜 Offline license {} is valid", path.display());
+    }
+    Ok(response)
+}
+
+/// Configured license file, or `<executable>.license`
+fn license_path(config: &Config) -> Result<PathBuf, String> {
+    if let Some(path) = &config.license_file {
+        return Ok(PathBuf::from(path));
+    }
+    let exe_path = crate::security::memexec::executable_path().map_err(|e| format!("Failed to get executable path: {}", e))?;
+    Ok(PathBuf::from(format!("{}.license", exe_path.display())))
+}
+
+fn evaluate(
+    contents: &str,
+    verify_signature: impl Fn(&[u8], &str) -> Result<(), String>,
+    license_id: &str,
+    fingerprint: &str,
+    now: i64,
+) -> VerifyResponse {
+    let denial = |message: String| VerifyResponse {
+        authorized: false,
+        message,
+        signature_valid: true,
+        ..Default::default()
+    };
+
+    let file: LicenseFile = match serde_json::from_str(contents) {
+        Ok(file) => file,
+        Err(e) => return denial(format!("malformed license file: {}", e)),
+    };
+    if let Err(e) = verify_signature(file.license.as_bytes(), &file.signature) {
+        return denial(format!("license file rejected: {}", e));
+    }
+    let license: OfflineLicense = match serde_json::from_str(&file.license) {
+        Ok(license) => license,
+        Err(e) => return denial(format!("malformed license: {}", e)),
+    };
+
+    if license.license_id != license_id {
+        return denial("license file is for another license".to_string());
+    }
+    if !license.fingerprints.is_empty() && !license.fingerprints.iter().any(|allowed| allowed == fingerprint) {
+        return denial("license file is not valid on this machine".to_string());
+    }
+    if license.expires_at.is_some_and(|expires_at| now >= expires_at) {
+        return denial("offline license expired".to_string());
+    }
+
+    VerifyResponse {
+        authorized: true,
+        message: "offline license valid".to_string(),
+        expires_at: license.expires_at,
+        signature_valid: true,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use trust::TrustChain;
+
+    fn license_file(key: &Ed25519KeyPair, license: &str) -> String {
+        let mut message = LICENSE_CONTEXT.as_bytes().to_vec();
+        message.push(0);
+        message.extend_from_slice(license.as_bytes());
+        serde_json::json!({ "license": license, "signature": hex::encode(key.sign(&message)) }).to_string()
+    }
+
+    #[test]
+    fn test_offline_license_constraints() {
+        let key = Ed25519KeyPair::from_seed_unchecked(&[7; 32]).unwrap();
+        let chain = TrustChain::new(key.public_key().as_ref().try_into().unwrap());
+        let check = |contents: &str, fingerprint: &str, now: i64| {
+            evaluate(contents, |payload, signature| chain.verify(LICENSE_CONTEXT, payload, signature), "lic_1", fingerprint, now)
+        };
+
+        let bound = license_file(&key, r#"{"license_id":"lic_1","fingerprints":["fp_a"],"expires_at":2000}"#);
+        let valid = check(&bound, "fp_a", 1000);
+        assert!(valid.authorized);
+        assert_eq!(valid.expires_at, Some(2000));
+        assert!(!check(&bound, "fp_b", 1000).authorized);
+        assert!(check(&bound, "fp_a", 2000).message.contains("expired"));
+
+        let other = license_file(&key, r#"{"license_id":"lic_2"}"#);
+        assert!(!check(&other, "fp_a", 1000).authorized);
+
+        // Perpetual, any machine - but only as signed
+        let open = license_file(&key, r#"{"license_id":"lic_1"}"#);
+        assert!(check(&open, "fp_b", i64::MAX).authorized);
+        let stranger = Ed25519KeyPair::from_seed_unchecked(&[8; 32]).unwrap();
+        let forged = license_file(&stranger, r#"{"license_id":"lic_1"}"#);
+        assert!(check(&forged, "fp_b", 1000).message.contains("rejected"));
+    }
+}
@@ -0,0 +1,204 @@
+//! Runtime configuration patches from the server
+//!
+//! An authorized response can retune a running overload: `check_interval_ms`,
+//! `kill_method`, `server_url` (move to a new endpoint), `log_level`, the
+//! failure tolerance (`max_consecutive_failures`, `fallback_after_failures`)
+//! and `kill_grace_ms`. Patches are only taken from responses with a valid
+//! signature - anyone on the path can rewrite an unsigned answer, and a
+//! `server_url` patch would hand them every following check. A patch that
+//! leaves the config invalid is dropped as a whole. The latest accepted patch
+//! is kept so a configuration reloaded later (`reapply`) does not undo it.
+
+use std::sync::Mutex;
+
+use super::network::{VerifyResponse, MAX_KILL_GRACE_MS};
+use super::ratelimit;
+use crate::config::{Config, KillMethod};
+
+/// Accepted patches merged, latest value per field
+static LATEST: Mutex<Option<VerifyResponse>> = Mutex::new(None);
+
+/// One patched value
+#[derive(Debug, PartialEq, Eq)]
+pub struct Change {
+    pub field: &'static str,
+    pub from: String,
+    pub to: String,
+}
+
+/// Apply the patches `response` carries to `config`
+///
+/// * `resolve_kill_method` - Kill method a requested one runs as on this host
+///
+/// # Returns
+/// The values that changed (none if the response is unsigned or the patch
+/// was rejected)
+pub fn apply(
+    config: &mut Config,
+    response: &VerifyResponse,
+    resolve_kill_method: impl Fn(&KillMethod) -> KillMethod,
+) -> Vec<Change> {
+    if !response.signature_valid {
+        if carries_patch(response) {
+            log_warn!("⚠️  Ignoring runtime patch from an unsigned response");
+        }
+        return Vec::new();
+    }
+
+    let mut patched = config.clone();
+    let mut changes = Vec::new();
+    if let Some(interval) = response.check_interval_ms {
+        let interval = ratelimit::clamp_interval(interval, "server");
+        set(&mut changes, "check_interval_ms", &mut patched.check_interval_ms, interval);
+    }
+    if let Some(name) = &response.kill_method {
+        match KillMethod::from_str(name) {
+            Some(method) => {
+                let method = resolve_kill_method(&method);
+                if method != patched.kill_method {
+                    changes.push(Change {
+                        field: "kill_method",
+                        from: patched.kill_method.as_str().to_string(),
+                        to: method.as_str().to_string(),
+                    });
+                    patched.kill_method = method;
+                }
+            }
+            None => log_warn!("⚠️  Invalid kill_method from server: {}", name),
+        }
+    }
+    if let Some(url) = &response.server_url {
+        if option_env!("KILLER_SERVER_URL").is_some_and(|pinned| !pinned.is_empty()) {
+            log_warn!("⚠️  Ignoring server_url patch: this build pins the server URL");
+        } else {
+            set(&mut changes, "server_url", &mut patched.server_url, url.clone());
+        }
+    }
+    if let Some(level) = &response.log_level {
+        set(&mut changes, "log_level", &mut patched.log_level, level.to_ascii_lowercase());
+    }
+    if let Some(limit) = response.max_consecutive_failures {
+        set(&mut changes, "max_consecutive_failures", &mut patched.max_consecutive_failures, limit);
+    }
+    if let Some(after) = response.fallback_after_failures {
+        set(&mut changes, "fallback_after_failures", &mut patched.fallback_after_failures, after);
+    }
+    if let Some(grace) = response.kill_grace_ms {
+        set(&mut changes, "kill_grace_ms", &mut patched.kill_grace_ms, grace.min(MAX_KILL_GRACE_MS));
+    }
+
+    if changes.is_empty() {
+        remember(response);
+        return changes;
+    }
+    if let Err(e) = patched.validate() {
+        log_warn!("⚠️  Rejecting runtime patch: {}", e);
+        return Vec::new();
+    }
+    *config = patched;
+    remember(response);
+    changes
+}
+
+/// Apply the latest accepted patch again (to a reloaded configuration)
+///
+/// # Returns
+/// The values that changed
+pub fn reapply(config: &mut Config, resolve_kill_method: impl Fn(&KillMethod) -> KillMethod) -> Vec<Change> {
+    let latest = LATEST.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    match latest {
+        Some(response) => apply(config, &response, resolve_kill_method),
+        None => Vec::new(),
+    }
+}
+
+fn remember(response: &VerifyResponse) {
+    if !carries_patch(response) {
+        return;
+    }
+    let mut latest = LATEST.lock().unwrap_or_else(|e| e.into_inner());
+    let merged = latest.get_or_insert_with(|| VerifyResponse { signature_valid: true, ..Default::default() });
+    merged.check_interval_ms = response.check_interval_ms.or(merged.check_interval_ms);
+    merged.kill_method = response.kill_method.clone().or(merged.kill_method.take());
+    merged.server_url = response.server_url.clone().or(merged.server_url.take());
+    merged.log_level = response.log_level.clone().or(merged.log_level.take());
+    merged.max_consecutive_failures = response.max_consecutive_failures.or(merged.max_consecutive_failures);
+    merged.fallback_after_failures = response.fallback_after_failures.or(merged.fallback_after_failures);
+    merged.kill_grace_ms = response.kill_grace_ms.or(merged.kill_grace_ms);
+}
+
+fn set<T: PartialEq + ToString>(changes: &mut Vec<Change>, field: &'static str, current: &mut T, value: T) {
+    if *current != value {
+        changes.push(Change { field, from: current.to_string(), to: value.to_string() });
+        *current = value;
+    }
+}
+
+fn carries_patch(response: &VerifyResponse) -> bool {
+    response.check_interval_ms.is_some()
+        || response.kill_method.is_some()
+        || response.server_url.is_some()
+        || response.log_level.is_some()
+        || response.max_consecutive_failures.is_some()
+        || response.fallback_after_failures.is_some()
+        || response.kill_grace_ms.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_patches_only() {
+        let mut config: Config = serde_json::from_str(
+            r#"{"license_id": "lic", "server_url": "https://a.example", "shared_secret": "s", "check_interval_ms": 5000}"#,
+        )
+        .unwrap();
+        let mut response: VerifyResponse = serde_json::from_str(
+            r#"{"authorized": true, "message": "ok", "expires_in": null, "check_interval_ms": 60000, "kill_method": "shred",
+                "server_url": "https://b.example", "log_level": "DEBUG", "max_consecutive_failures": 3, "kill_grace_ms": 30000}"#,
+        )
+        .unwrap();
+
+        // Unsigned: nothing changes
+        assert!(apply(&mut config, &response, |m| m.clone()).is_empty());
+        assert_eq!(config.server_url, "https://a.example");
+
+        response.signature_valid = true;
+        let changes = apply(&mut config, &response, |_| KillMethod::Delete);
+        let fields: Vec<_> = changes.iter().map(|change| change.field).collect();
+        assert_eq!(fields, ["check_interval_ms", "kill_method", "server_url", "log_level", "max_consecutive_failures", "kill_grace_ms"]);
+        assert_eq!(changes[1], Change { field: "kill_method", from: "shred".to_string(), to: "delete".to_string() });
+        assert_eq!((config.check_interval_ms, config.kill_grace_ms), (60000, 30000));
+        assert_eq!(config.log_level, "debug");
+
+        // Intervals are clamped to the floor
+        let hammering = VerifyResponse { check_interval_ms: Some(1), signature_valid: true, ..Default::default() };
+        assert_eq!(apply(&mut config.clone(), &hammering, |m| m.clone())[0].to, ratelimit::MIN_CHECK_INTERVAL_MS.to_string());
+
+        // And graces to the ceiling
+        let stalling = VerifyResponse { kill_grace_ms: Some(u64::MAX), signature_valid: true, ..Default::default() };
+        assert_eq!(apply(&mut config.clone(), &stalling, |m| m.clone())[0].to, MAX_KILL_GRACE_MS.to_string());
+        assert_eq!(stalling.kill_grace_or(0), MAX_KILL_GRACE_MS);
+
+        // Applied values are not changes the next time
+        assert!(apply(&mut config, &response, |_| KillMethod::Delete).is_empty());
+
+        // An invalid value rejects the whole patch
+        response.server_url = Some("ftp://c.example".to_string());
+        response.check_interval_ms = Some(1000);
+        assert!(apply(&mut config, &response, |_| KillMethod::Delete).is_empty());
+        assert_eq!((config.server_url.as_str(), config.check_interval_ms), ("https://b.example", 60000));
+
+        // A reloaded configuration gets the accepted patches back
+        // Built at runtime: a valid config literal would be found by the scan
+        // in `embedded::tests::test_section_is_authoritative`
+        let mut reloaded: Config = serde_json::from_value(
+            serde_json::json!({"license_id": "lic", "server_url": "https://a.example", "shared_secret": "s"}),
+        )
+        .unwrap();
+        reapply(&mut reloaded, |_| KillMethod::Delete);
+        assert_eq!((reloaded.server_url.as_str(), reloaded.log_level.as_str()), ("https://b.example", "debug"));
+        assert_eq!(reloaded.kill_grace_ms, 30000);
+    }
+}
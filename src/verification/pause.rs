@@ -0,0 +1,122 @@
+//! Server-driven pause of enforcement (maintenance mode)
+//!
+//! Before planned license-server maintenance the server answers with
+//! `paused_until` (server time, unix seconds). Until then verification errors
+//! and denials are logged but not enforced - the protected app is neither
+//! killed nor withheld - and checks continue at a reduced rate. Violations
+//! found by the security checks (tampering, debuggers...) are still enforced.
+//!
+//! Only signed responses start or end a pause, and one answer pauses for at
+//! most `MAX_PAUSE_SECS`; the server extends it by answering again. The pause
+//! is persisted so a restart during the outage stays paused, sealed with the
+//! shared secret: the state file is writable by the user, and an edited
+//! `paused_until` would switch enforcement off for good.
+
+use crate::config::Config;
+use crate::utils::state::{PauseState, StateStore};
+use crate::utils::time;
+use super::hmac::{create_signature, verify_signature};
+use super::network::VerifyResponse;
+
+/// Longest pause one response can grant
+pub const MAX_PAUSE_SECS: i64 = 24 * 60 * 60;
+
+/// While paused, checks run this many times less often...
+const PAUSED_INTERVAL_FACTOR: u64 = 4;
+
+/// ...but at least this far apart
+const MIN_PAUSED_INTERVAL_MS: u64 = 60_000;
+
+/// Take the pause (or its end) from a verification response
+pub fn observe(license_id: &str, shared_secret: &str, response: &VerifyResponse) {
+    if !response.signature_valid {
+        return;
+    }
+
+    let now = time::protocol_now();
+    let pause = response
+        .paused_until
+        .filter(|&until| until > now)
+        .map(|until| seal(license_id, shared_secret, until.min(now + MAX_PAUSE_SECS), now));
+
+    let store = StateStore::for_license(license_id);
+    let mut state = store.load();
+    let was_paused = state.pause.as_ref().is_some_and(|pause| is_active(license_id, shared_secret, pause, now));
+    match &pause {
+        Some(pause) if !was_paused => {
+            log_warn!("⏸️  Server paused enforcement for maintenance ({}s)", pause.until - now);
+        }
+        None if was_paused => log_info!("▶️  Server ended the enforcement pause"),
+        _ => {}
+    }
+    if state.pause.is_none() && pause.is_none() {
+        return;
+    }
+    state.pause = pause;
+    if let Err(e) = store.save(&state) {
+        log_warn!("⚠️  {}", e);
+    }
+}
+
+/// End of the active pause (server time), if enforcement is paused
+pub fn active(config: &Config) -> Option<i64> {
+    let pause = StateStore::for_license(&config.license_id).load().pause?;
+    is_active(&config.license_id, &config.shared_secret, &pause, time::protocol_now()).then_some(pause.until)
+}
+
+/// Check interval to use: `interval_ms`, reduced while paused
+pub fn check_interval_ms(config: &Config, interval_ms: u64) -> u64 {
+    match active(config) {
+        Some(_) => paused_interval_ms(interval_ms),
+        None => interval_ms,
+    }
+}
+
+/// Reduced check interval while paused
+pub fn paused_interval_ms(interval_ms: u64) -> u64 {
+    interval_ms.saturating_mul(PAUSED_INTERVAL_FACTOR).max(MIN_PAUSED_INTERVAL_MS)
+}
+
+fn seal(license_id: &str, shared_secret: &str, until: i64, granted_at: i64) -> PauseState {
+    PauseState { until, granted_at, signature: create_signature(&sealed_message(license_id, until, granted_at), shared_secret) }
+}
+
+/// A pause is active from when it was granted until it ends: one granted "in
+/// the future" (a rewound clock) does not count
+fn is_active(license_id: &str, shared_secret: &str, pause: &PauseState, now: i64) -> bool {
+    (pause.granted_at..pause.until).contains(&now)
+        && pause.until <= pause.granted_at + MAX_PAUSE_SECS
+        && verify_signature(&sealed_message(license_id, pause.until, pause.granted_at), shared_secret, &pause.signature)
+}
+
+fn sealed_message(license_id: &str, until: i64, granted_at: i64) -> String {
+    format!("pause:{}:{}:{}", license_id, until, granted_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pause_sealed_and_bounded() {
+        let pause = seal("lic_a", "secret", 2_000, 1_000);
+        assert!(is_active("lic_a", "secret", &pause, 1_500));
+        assert!(!is_active("lic_a", "secret", &pause, 2_000));
+        assert!(!is_active("lic_a", "secret", &pause, 999));
+        assert!(!is_active("lic_b", "secret", &pause, 1_500));
+        assert!(!is_active("lic_a", "other", &pause, 1_500));
+
+        // Edited state: a longer pause no longer matches the seal
+        let edited = PauseState { until: 9_999_999, ..pause.clone() };
+        assert!(!is_active("lic_a", "secret", &edited, 1_500));
+        // Sealed, but longer than one response may grant
+        let too_long = seal("lic_a", "secret", 1_000 + MAX_PAUSE_SECS + 1, 1_000);
+        assert!(!is_active("lic_a", "secret", &too_long, 1_500));
+    }
+
+    #[test]
+    fn test_paused_interval() {
+        assert_eq!(paused_interval_ms(5_000), MIN_PAUSED_INTERVAL_MS);
+        assert_eq!(paused_interval_ms(300_000), 1_200_000);
+    }
+}
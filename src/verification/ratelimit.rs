@@ -0,0 +1,105 @@
+//! Local rate limiting of license requests
+//!
+//! A `check_interval_ms` of 1, a runtime patch asking for one, or a wrapper
+//! spamming re-checks must not turn every installed overload into a client
+//! hammering the license server. Two limits apply, both clamping rather than
+//! failing:
+//! - check intervals below `MIN_CHECK_INTERVAL_MS` are raised to it
+//!   (0, the single check, stays)
+//! - verification requests draw from a token bucket (`BURST` requests, one
+//!   more per `MIN_CHECK_INTERVAL_MS`); a request finding it empty is delayed
+//!   until a token is available
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Shortest effective check interval
+pub const MIN_CHECK_INTERVAL_MS: u64 = 1_000;
+
+/// Requests allowed back to back
+const BURST: f64 = 5.0;
+
+static BUCKET: Mutex<Option<TokenBucket>> = Mutex::new(None);
+
+/// Token bucket refilled at one token per `MIN_CHECK_INTERVAL_MS`
+#[derive(Debug)]
+struct TokenBucket {
+    /// Negative while requests wait for tokens not yet refilled
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(now: Instant) -> Self {
+        Self { tokens: BURST, updated: now }
+    }
+
+    /// Take a token, returning how long to wait before using it
+    fn take(&mut self, now: Instant) -> Duration {
+        let refilled = now.saturating_duration_since(self.updated).as_secs_f64() * 1000.0 / MIN_CHECK_INTERVAL_MS as f64;
+        self.tokens = (self.tokens + refilled).min(BURST) - 1.0;
+        self.updated = now;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens * MIN_CHECK_INTERVAL_MS as f64 / 1000.0)
+        }
+    }
+}
+
+/// Wait until the next license request is within the rate limit
+pub fn acquire() {
+    let wait = {
+        let mut bucket = BUCKET.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        bucket.get_or_insert_with(|| TokenBucket::new(now)).take(now)
+    };
+    if !wait.is_zero() {
+        log_warn!("🚦 License requests exceed the local rate limit - delaying this one {}ms", wait.as_millis());
+        std::thread::sleep(wait);
+    }
+}
+
+/// `interval_ms` raised to the floor (0 = single check stays)
+///
+/// * `source` - Where the interval came from (for the log)
+pub fn clamp_interval(interval_ms: u64, source: &str) -> u64 {
+    if interval_ms == 0 || interval_ms >= MIN_CHECK_INTERVAL_MS {
+        return interval_ms;
+    }
+    log_warn!(
+        "🚦 check_interval_ms = {} from {} is below the minimum - using {}",
+        interval_ms,
+        source,
+        MIN_CHECK_INTERVAL_MS
+    );
+    MIN_CHECK_INTERVAL_MS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_and_floor() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(start);
+        for _ in 0..BURST as usize {
+            assert_eq!(bucket.take(start), Duration::ZERO);
+        }
+        // Empty: each further request waits one more refill
+        assert_eq!(bucket.take(start), Duration::from_millis(MIN_CHECK_INTERVAL_MS));
+        assert_eq!(bucket.take(start), Duration::from_millis(2 * MIN_CHECK_INTERVAL_MS));
+
+        // Idle time refills up to the burst, not beyond
+        let later = start + Duration::from_secs(3600);
+        for _ in 0..BURST as usize {
+            assert_eq!(bucket.take(later), Duration::ZERO);
+        }
+        assert!(bucket.take(later) > Duration::ZERO);
+
+        assert_eq!(clamp_interval(1, "test"), MIN_CHECK_INTERVAL_MS);
+        assert_eq!(clamp_interval(0, "test"), 0);
+        assert_eq!(clamp_interval(60_000, "test"), 60_000);
+    }
+}
@@ -0,0 +1,265 @@
+//! Shared secret rotation
+//!
+//! A response signed with the current secret can carry `secret_rotation`: a
+//! rotation ID and the new secret. The overload then
+//! 1. records it in the license's state file and writes it to where the
+//!    current secret came from: the `.config` file next to the binary when
+//!    that sets `shared_secret`, otherwise the `.license` section of the
+//!    binary on disk (written to a staging file renamed over the binary)
+//! 2. switches the running configuration to it in one snapshot update
+//! 3. names the rotation in `secret_rotated` of its requests, now signed with
+//!    the new secret, until a response signed with the new secret arrives
+//!
+//! The server keeps accepting the old secret until it has seen the new one in
+//! use. Nothing switches unless the new secret was persisted somewhere: after
+//! a restart the overload would come back with a secret the server may have
+//! retired. The state record covers sources that cannot be written (an image
+//! run from memory, a binary extracted from a merged one): at load it
+//! replaces every secret it was rotated from. It holds each new secret only
+//! encrypted (ChaCha20-Poly1305) under a key derived from the secret it
+//! replaced, so the state file alone does not reveal any secret.
+//!
+//! Secrets set through `KILLER_SHARED_SECRET` and those of further `licenses`
+//! entries are not rotated.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use super::hmac::create_signature;
+use super::network::VerifyResponse;
+use super::self_update;
+use crate::config::{embedded, snapshot, Config};
+use crate::security::secrets::SecretString;
+use crate::utils::secure_fs;
+use crate::utils::state::StateStore;
+
+/// Variable setting the secret from the environment (see `config::loader`)
+const SECRET_ENV: &str = "KILLER_SHARED_SECRET";
+
+/// New shared secret delivered by the server
+#[derive(Debug, Clone, Deserialize)]
+pub struct SecretRotation {
+    pub rotation_id: String,
+    pub shared_secret: SecretString,
+}
+
+/// Rotated secret as recorded in the state file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RotatedSecret {
+    pub rotation_id: String,
+    /// Every rotation so far, each new secret sealed under the one it replaced
+    #[serde(default)]
+    pub chain: Vec<SealedSecret>,
+}
+
+/// A new secret, encrypted under the secret it replaced
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SealedSecret {
+    /// SHA-256 of the secret it replaces
+    pub replaces: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+impl RotatedSecret {
+    /// Record of `rotation`, replacing `current` and everything `previous`
+    /// replaced
+    fn new(previous: Option<RotatedSecret>, current: &str, rotation: &SecretRotation) -> Result<Self, String> {
+        let mut chain = previous.map(|previous| previous.chain).unwrap_or_default();
+        chain.push(seal(current, &rotation.shared_secret)?);
+        Ok(Self { rotation_id: rotation.rotation_id.clone(), chain })
+    }
+
+    /// The latest secret `secret` was rotated to, if it was
+    fn resolve(&self, secret: &str) -> Option<SecretString> {
+        let mut current = SecretString::from(secret);
+        let mut rotated = false;
+        // Each link is followed at most once, even in a corrupt chain
+        for _ in 0..self.chain.len() {
+            let Some(next) = self.chain.iter().find(|link| link.replaces == digest(&current)).and_then(|link| open(link, &current)) else {
+                break;
+            };
+            current = next;
+            rotated = true;
+        }
+        rotated.then_some(current)
+    }
+}
+
+/// Encrypt `secret` under `replaced`
+fn seal(replaced: &str, secret: &str) -> Result<SealedSecret, String> {
+    let nonce: [u8; 12] = rand::random();
+    let replaces = digest(replaced);
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&sealing_key(replaced)))
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: secret.as_bytes(), aad: replaces.as_bytes() })
+        .map_err(|_| "Failed to seal the rotated secret".to_string())?;
+    Ok(SealedSecret { replaces, nonce: hex::encode(nonce), ciphertext: hex::encode(ciphertext) })
+}
+
+/// Decrypt the secret `link` seals under `replaced`
+fn open(link: &SealedSecret, replaced: &str) -> Option<SecretString> {
+    let nonce = hex::decode(&link.nonce).ok().filter(|nonce| nonce.len() == 12)?;
+    let ciphertext = hex::decode(&link.ciphertext).ok()?;
+    let plaintext = Zeroizing::new(
+        ChaCha20Poly1305::new(Key::from_slice(&sealing_key(replaced)))
+            .decrypt(Nonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: link.replaces.as_bytes() })
+            .ok()?,
+    );
+    Some(SecretString::from(std::str::from_utf8(&plaintext).ok()?))
+}
+
+/// Key sealing the successor of `secret` (not its digest, which is stored)
+fn sealing_key(secret: &str) -> Zeroizing<Vec<u8>> {
+    Zeroizing::new(hex::decode(create_signature("killer-secret-rotation", secret)).unwrap_or_default())
+}
+
+/// (license ID, rotation ID) not yet confirmed by the server
+static PENDING: Mutex<Option<(String, String)>> = Mutex::new(None);
+
+/// Rotation to name in the requests of `license_id`, if one is unconfirmed
+pub fn pending_confirmation(license_id: &str) -> Option<String> {
+    let pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+    pending.as_ref().filter(|(license, _)| license == license_id).map(|(_, rotation)| rotation.clone())
+}
+
+/// Act on the rotation fields of an authorized response
+pub fn handle(response: &VerifyResponse) {
+    if !response.signature_valid || super::licenses::active_index() != 0 {
+        return;
+    }
+    let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+    let rotation = response.secret_rotation.as_ref();
+    if let Some((_, rotation_id)) = pending.as_ref() {
+        // Repeated until the server has seen a request under the new secret
+        if rotation.is_some_and(|rotation| rotation.rotation_id == *rotation_id) {
+            return;
+        }
+        log_info!("🔑 Secret rotation {} confirmed", rotation_id);
+        *pending = None;
+    }
+
+    let Some(rotation) = rotation else {
+        return;
+    };
+    let config = snapshot::current();
+    if rotation.shared_secret.is_empty() || rotation.shared_secret == config.shared_secret {
+        return;
+    }
+    if let Err(e) = persist(&config, rotation) {
+        log_warn!("⚠️  Secret rotation {} not applied: {}", rotation.rotation_id, e);
+        return;
+    }
+    snapshot::update(|c| c.shared_secret = rotation.shared_secret.clone());
+    *pending = Some((config.license_id.clone(), rotation.rotation_id.clone()));
+    log_info!("🔑 Shared secret rotated ({})", rotation.rotation_id);
+}
+
+/// Use the secret the server rotated `config`'s secret to, if any
+pub fn apply_persisted(config: &mut Config) {
+    let Some(rotated) = StateStore::for_license(&config.license_id).load().rotated_secret else {
+        return;
+    };
+    if let Some(secret) = rotated.resolve(&config.shared_secret) {
+        log_info!("🔑 Using the shared secret of rotation {}", rotated.rotation_id);
+        config.shared_secret = secret;
+    }
+}
+
+/// Record the new secret in the state file and its source; one of them must
+/// succeed
+fn persist(config: &Config, rotation: &SecretRotation) -> Result<(), String> {
+    if std::env::var_os(SECRET_ENV).is_some() {
+        return Err(format!("the secret is set through {}", SECRET_ENV));
+    }
+
+    let store = StateStore::for_license(&config.license_id);
+    let mut state = store.load();
+    state.rotated_secret = Some(RotatedSecret::new(state.rotated_secret.take(), &config.shared_secret, rotation)?);
+    match (store.save(&state), write_source(&rotation.shared_secret)) {
+        (Ok(()), Ok(())) => Ok(()),
+        (Ok(()), Err(e)) => {
+            log_warn!("⚠️  Rotated secret kept in the state file only: {}", e);
+            Ok(())
+        }
+        (Err(e), Ok(())) => {
+            log_warn!("⚠️  Rotated secret not recorded in the state file: {}", e);
+            Ok(())
+        }
+        (Err(state), Err(source)) => Err(format!("{}; {}", state, source)),
+    }
+}
+
+/// Write the secret to the `.config` file if that sets it, otherwise to the
+/// embedded config
+fn write_source(secret: &str) -> Result<(), String> {
+    let exe = crate::security::memexec::executable_path().map_err(|e| format!("Failed to get executable path: {}", e))?;
+    let config_path = PathBuf::from(format!("{}.config", exe.display()));
+    let embedded = embedded::load_embedded_config().ok();
+
+    // Over an embedded config the file cannot set the secret
+    if embedded.is_none() && config_path.exists() {
+        let contents = secure_fs::read_config(&config_path)?;
+        let mut file: serde_json::Value =
+            serde_json::from_str(&contents).map_err(|e| format!("Failed to parse config: {}", e))?;
+        if file.get("shared_secret").is_some() {
+            file["shared_secret"] = secret.into();
+            let json = Zeroizing::new(
+                serde_json::to_string_pretty(&file).map_err(|e| format!("Failed to serialize config: {}", e))?,
+            );
+            return secure_fs::write_private(&config_path, json.as_bytes());
+        }
+    }
+    if embedded.is_none() {
+        return Err("no config source sets the shared secret".to_string());
+    }
+    rewrite_executable(&exe, secret)
+}
+
+/// Re-patch the `.license` section of the binary on disk
+fn rewrite_executable(exe: &Path, secret: &str) -> Result<(), String> {
+    let mut image = Zeroizing::new(std::fs::read(exe).map_err(|e| format!("Failed to read {}: {}", exe.display(), e))?);
+    embedded::update_config(&mut image, |config| config["shared_secret"] = secret.into())?;
+    // The running image cannot be written in place; a rename replaces it
+    self_update::replace_file(exe, |staged| staged.write_all(&image))
+}
+
+fn digest(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotated_secret_replaces_its_predecessors() {
+        let rotation = |id: &str, secret: &str| SecretRotation { rotation_id: id.to_string(), shared_secret: secret.into() };
+
+        let first = RotatedSecret::new(None, "s1", &rotation("r1", "s2")).unwrap();
+        assert_eq!(first.resolve("s1").unwrap().expose(), "s2");
+        assert!(first.resolve("s2").is_none());
+
+        // A binary that still carries s1 (source not writable) skips to s3
+        let second = RotatedSecret::new(Some(first), "s2", &rotation("r2", "s3")).unwrap();
+        assert_eq!(second.resolve("s1").unwrap().expose(), "s3");
+        assert_eq!(second.resolve("s2").unwrap().expose(), "s3");
+        assert!(second.resolve("other").is_none());
+
+        // No secret is stored in the clear
+        let json = serde_json::to_string(&second).unwrap();
+        assert!(!["\"s1\"", "\"s2\"", "\"s3\""].iter().any(|secret| json.contains(secret)));
+
+        // A tampered link does not decrypt
+        let mut tampered = second.clone();
+        tampered.chain[1].ciphertext.replace_range(..2, "00");
+        assert_eq!(tampered.resolve("s1").unwrap().expose(), "s2");
+        assert!(pending_confirmation("lic_none").is_none());
+    }
+}
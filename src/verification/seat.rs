@@ -0,0 +1,217 @@
+//! Floating license seats
+//!
+//! With `seat_lease` set, a license is good for a limited number of
+//! concurrent seats instead of a number of machines. Before the first check
+//! killer acquires a seat lease from `/api/v1/lease/acquire`; every regular
+//! check then carries the lease token (header and signature) and renews the
+//! lease on the server. The seat is given back through `/api/v1/lease/release`
+//! when the process is told to shut down and when the protected app exits.
+//! Kills, denials and crashes do not wait for the server: the lease expires
+//! there.
+//!
+//! Acquire requests carry a random nonce the signed answer must cover, so a
+//! recorded grant cannot be replayed. A signed "no seat available" answer is
+//! a denial like any other; a lease
+//! the server reports as expired (`lease_expired`) is re-acquired right away
+//! instead of counting against the license.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use super::fallback;
+use super::network::{self, VerifyError, VerifyResponse};
+use super::worker;
+use crate::config::Config;
+use crate::security::lineage;
+use crate::utils::shutdown;
+use crate::utils::tasks;
+use crate::utils::time;
+
+pub const ACQUIRE_PATH: &str = "/api/v1/lease/acquire";
+pub const RELEASE_PATH: &str = "/api/v1/lease/release";
+
+/// How often the protected app is checked for having exited
+const PARENT_POLL_MS: u64 = 2000;
+
+/// Token of the seat lease currently held
+static TOKEN: Mutex<Option<String>> = Mutex::new(None);
+
+#[derive(Debug, Serialize)]
+struct LeaseRequest<'a> {
+    license_id: &'a str,
+    timestamp: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lease_token: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nonce: Option<&'a str>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AcquireResponse {
+    granted: bool,
+    #[serde(default)]
+    lease_token: Option<String>,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    kill_grace_ms: Option<u64>,
+}
+
+/// Token of the seat lease currently held, if any
+pub fn token() -> Option<String> {
+    TOKEN.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+fn set_token(token: Option<String>) -> Option<String> {
+    // The lease of an abandoned check belongs to nobody; it expires unrenewed
+    if worker::is_abandoned() {
+        return None;
+    }
+    std::mem::replace(&mut *TOKEN.lock().unwrap_or_else(|e| e.into_inner()), token)
+}
+
+/// Verify the license, holding a seat lease first when `seat_lease` is set
+pub fn verify(config: &Config, first_check: bool) -> Result<VerifyResponse, VerifyError> {
+    if let Some(denial) = ensure(config)? {
+        return Ok(denial);
+    }
+    let response = fallback::verify(config, first_check)?;
+    if !config.seat_lease || !response.lease_expired {
+        return Ok(response);
+    }
+
+    log_warn!("🎟️  Seat lease expired on the server - acquiring a new one");
+    set_token(None);
+    if let Some(denial) = ensure(config)? {
+        return Ok(denial);
+    }
+    fallback::verify(config, first_check)
+}
+
+/// Acquire a seat lease unless one is held (or leases are off)
+///
+/// # Returns
+/// None when a lease is held, a denial when no seat is available; a
+/// retryable error when the server did not answer
+fn ensure(config: &Config) -> Result<Option<VerifyResponse>, VerifyError> {
+    if !config.seat_lease || token().is_some() {
+        return Ok(None);
+    }
+
+    let nonce = hex::encode(rand::random::<[u8; 16]>());
+    let request = LeaseRequest {
+        license_id: &config.license_id,
+        timestamp: time::protocol_now(),
+        lease_token: None,
+        nonce: Some(&nonce),
+    };
+    let (status, body) = network::post_signed_verified(
+        &config.get_server_url(),
+        ACQUIRE_PATH,
+        &config.license_id,
+        &config.shared_secret,
+        &request,
+        &nonce,
+    )?;
+    let response = parse_acquire(status, &body)?;
+
+    match response.lease_token {
+        Some(lease_token) if response.granted => {
+            log_info!("🎟️  Seat lease acquired");
+            set_token(Some(lease_token));
+            Ok(None)
+        }
+        _ => Ok(Some(VerifyResponse {
+            authorized: false,
+            message: format!("no seat available: {}", response.message),
+            kill_grace_ms: response.kill_grace_ms,
+            signature_valid: true,
+            ..Default::default()
+        })),
+    }
+}
+
+fn parse_acquire(status: u16, body: &str) -> Result<AcquireResponse, String> {
+    // A full pool is answered 409 with a signed body
+    if !(200..300).contains(&status) && status != 409 {
+        return Err(format!("Seat lease request failed: HTTP {}", status));
+    }
+    serde_json::from_str(body).map_err(|e| format!("Invalid seat lease response: {}", e))
+}
+
+/// Give the seat back (best effort; the server expires it otherwise)
+pub fn release(config: &Config) {
+    let Some(lease_token) = set_token(None) else {
+        return;
+    };
+    let request = LeaseRequest {
+        license_id: &config.license_id,
+        timestamp: time::protocol_now(),
+        lease_token: Some(&lease_token),
+        nonce: None,
+    };
+    match network::post_signed(&config.get_server_url(), RELEASE_PATH, &config.license_id, &config.shared_secret, &request) {
+        Ok(status) if (200..300).contains(&status) => log_info!("🎟️  Seat lease released"),
+        Ok(status) => log_warn!("⚠️  Seat lease release failed: HTTP {}", status),
+        Err(e) => log_warn!("⚠️  Seat lease release failed: {}", e),
+    }
+}
+
+/// Release the seat and exit once the protected app exits (a shutdown on a
+/// termination signal releases it itself)
+pub fn release_when_app_exits(config: &Config) {
+    if !config.seat_lease {
+        return;
+    }
+    let config = config.clone();
+
+    let Some(parent) = lineage::original_parent().or_else(crate::utils::process::get_parent_pid) else {
+        return;
+    };
+    tasks::spawn("seat_release_parent", move || loop {
+        std::thread::sleep(std::time::Duration::from_millis(PARENT_POLL_MS));
+        if !parent_alive(parent) {
+            log_info!("👋 Protected app exited - releasing seat lease");
+            release(&config);
+            shutdown::exit(0);
+        }
+    });
+}
+
+/// Whether the protected app `parent` is still running
+pub fn parent_alive(parent: u32) -> bool {
+    #[cfg(unix)]
+    {
+        // Orphans are re-parented, so a changed parent PID means it exited -
+        // unless we detached from it on purpose (daemon mode)
+        if lineage::original_parent() == Some(parent) && crate::utils::process::get_parent_pid() != Some(parent) {
+            return crate::utils::process::process_table().iter().any(|(pid, _)| *pid == parent);
+        }
+        crate::utils::process::get_parent_pid() == Some(parent)
+    }
+
+    #[cfg(not(unix))]
+    {
+        crate::utils::process::process_table().iter().any(|(pid, _)| *pid == parent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_acquire_responses() {
+        let granted = parse_acquire(200, r#"{"granted":true,"lease_token":"seat_1","message":"ok"}"#).unwrap();
+        assert!(granted.granted);
+        assert_eq!(granted.lease_token.as_deref(), Some("seat_1"));
+
+        // A full pool answers 409 with a body that still counts
+        let full = parse_acquire(409, r#"{"granted":false,"message":"5 of 5 seats in use","kill_grace_ms":60000}"#).unwrap();
+        assert!(!full.granted);
+        assert_eq!(full.kill_grace_ms, Some(60000));
+
+        assert!(parse_acquire(500, r#"{"granted":true}"#).is_err());
+        assert!(parse_acquire(200, "not json").is_err());
+    }
+}
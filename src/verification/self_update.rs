@@ -0,0 +1,379 @@
+//! Signed self-update of the overload
+//!
+//! A fix to the enforcement logic used to mean re-shipping every protected
+//! binary. A signed verification response may advertise a newer overload
+//! (`overload_update`); with `self_update` on it is downloaded, checked
+//! against its SHA-256 and an Ed25519 signature of the trust chain (see
+//! `security::trust`), given the `.license` section of the running overload
+//! and installed by the periodic loop (never by a one-shot check: the loader
+//! waits for its answer):
+//! - standalone overload: the executable is replaced atomically (the new one
+//!   is written next to it and renamed over it)
+//! - merged binary: the overload's region in the merged binary (our parent)
+//!   is overwritten in a copy that then replaces the merged binary. Only
+//!   possible when our image is found there verbatim and the new overload
+//!   fits into its region; the rest is zero-padded. Refused while
+//!   `expected_parent_sha256` is set: the transplanted license would pin the
+//!   merged binary as it was before the update
+//! - `run_from_memory`: nothing is left on disk, the new image only runs
+//!
+//! The loop then re-executes the new overload (same PID, arguments and
+//! environment); where that is not possible (Windows, a merged binary
+//! outside Linux) it runs from the next start. An update hands the install
+//! identity over like any other (see `install::prepare_update`).
+//!
+//! The version never goes down. A version that fails verification or cannot
+//! be installed here is remembered and not downloaded again; network errors
+//! are retried with the next check.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::http::{self, HttpRequest};
+use super::install;
+use super::network::VerifyResponse;
+use crate::config::embedded;
+use crate::config::Config;
+use crate::security::{kill_parent, lineage, memexec, trust};
+use crate::utils::process;
+use crate::utils::state::StateStore;
+
+/// Signature context of overload updates
+pub const UPDATE_CONTEXT: &str = "overload-update";
+
+/// Largest accepted overload binary
+const MAX_UPDATE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Download timeout (the binary is much larger than an API answer)
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Leading bytes of our image searched for in the merged binary
+const PROBE_LEN: usize = 4096;
+
+/// New overload advertised by the server
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct OverloadUpdate {
+    pub version: String,
+    /// `<os>-<arch>` of the build, e.g. "linux-x86_64"
+    pub target: String,
+    /// Download location (not signed: the hash is)
+    pub url: String,
+    /// Hex SHA-256 of the binary
+    pub sha256: String,
+    /// Hex Ed25519 signature of `version:target:sha256`
+    pub signature: String,
+}
+
+impl OverloadUpdate {
+    fn signed_payload(&self) -> String {
+        format!("{}:{}:{}", self.version, self.target, self.sha256.to_ascii_lowercase())
+    }
+}
+
+/// Where the running overload lives on disk
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    /// Standalone executable
+    Executable(PathBuf),
+    /// Region of a merged binary
+    Merged { path: PathBuf, offset: usize, len: usize },
+    /// Running from memory, nothing on disk
+    Memory,
+}
+
+/// `<os>-<arch>` of this build
+pub fn current_target() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+/// Install the overload update a verification response advertises, if any,
+/// and re-execute the new overload (does not return then)
+pub fn handle(config: &Config, response: &VerifyResponse) {
+    let Some(update) = response.overload_update.as_ref().filter(|_| response.signature_valid) else {
+        return;
+    };
+    if !config.self_update || !is_newer(&update.version, env!("CARGO_PKG_VERSION")) {
+        return;
+    }
+    let store = StateStore::for_license(&config.license_id);
+    if store.load().skipped_update.as_deref() == Some(update.version.as_str()) {
+        return;
+    }
+
+    let skip = |reason: String| {
+        log_warn!("⚠️  Overload update {} skipped: {}", update.version, reason);
+        let mut state = store.load();
+        state.skipped_update = Some(update.version.clone());
+        if let Err(e) = store.save(&state) {
+            log_warn!("⚠️  {}", e);
+        }
+    };
+
+    let (own, target) = match own_image().and_then(|own| Ok((locate(&own)?, own))) {
+        Ok((target, own)) => (own, target),
+        Err(e) => return skip(e),
+    };
+
+    log_info!("⬆️  Overload {} available (running {})", update.version, env!("CARGO_PKG_VERSION"));
+    let image = match download(update) {
+        Ok(image) => image,
+        Err(e) => {
+            log_warn!("⚠️  Overload update not downloaded: {}", e);
+            return;
+        }
+    };
+    let image = match verify(config, update, &image).and_then(|_| prepare(config, &own, image, &target)) {
+        Ok(image) => image,
+        Err(e) => return skip(e),
+    };
+
+    if let Err(e) = write(&target, &image) {
+        log_warn!("⚠️  Overload update not installed: {}", e);
+        return;
+    }
+    log_info!("✅ Overload {} installed", update.version);
+    if let Err(e) = install::prepare_update(&config.license_id, &config.shared_secret, &installed_hash(&target, &image)) {
+        log_warn!("⚠️  Install identity not handed over: {}", e);
+    }
+
+    let error = relaunch_into(&target, &image);
+    log_warn!("⚠️  Overload {} runs from the next start: {}", update.version, error);
+}
+
+/// Whether dotted version `candidate` is newer than `current` (pre-release
+/// suffixes are ignored, unparseable versions never are)
+fn is_newer(candidate: &str, current: &str) -> bool {
+    let parse = |version: &str| -> Option<Vec<u64>> {
+        let release = version.trim_start_matches('v').split(['-', '+']).next()?;
+        release.split('.').map(|part| part.parse().ok()).collect()
+    };
+    match (parse(candidate), parse(current)) {
+        (Some(candidate), Some(current)) => candidate > current,
+        _ => false,
+    }
+}
+
+fn download(update: &OverloadUpdate) -> Result<Vec<u8>, String> {
+    let request = HttpRequest::get(&update.url).timeout(DOWNLOAD_TIMEOUT).max_body(MAX_UPDATE_SIZE);
+    let response = http::client()?.send(request)?;
+    if !response.is_success() {
+        return Err(format!("HTTP {}", response.status));
+    }
+    if response.body.len() as u64 > MAX_UPDATE_SIZE {
+        return Err(format!("larger than {} bytes", MAX_UPDATE_SIZE));
+    }
+    Ok(response.body)
+}
+
+fn verify(config: &Config, update: &OverloadUpdate, image: &[u8]) -> Result<(), String> {
+    if update.target != current_target() {
+        return Err(format!("built for {}, this is {}", update.target, current_target()));
+    }
+    if !hex::encode(Sha256::digest(image)).eq_ignore_ascii_case(&update.sha256) {
+        return Err("SHA-256 mismatch".to_string());
+    }
+    trust::verify_signed_blob(&config.license_id, UPDATE_CONTEXT, update.signed_payload().as_bytes(), &update.signature)
+}
+
+fn own_image() -> Result<Vec<u8>, String> {
+    fs::read(memexec::image_path().map_err(|e| e.to_string())?).map_err(|e| format!("Failed to read own image: {}", e))
+}
+
+/// Give the new overload our license and check it fits `target`
+fn prepare(config: &Config, own: &[u8], mut image: Vec<u8>, target: &Target) -> Result<Vec<u8>, String> {
+    // Patching the hash in would change the merged binary it is the hash of
+    if matches!(target, Target::Merged { .. }) && config.expected_parent_sha256.is_some() {
+        return Err("expected_parent_sha256 pins the merged binary the update would change".to_string());
+    }
+    embedded::transplant_license(own, &mut image)?;
+    if let Target::Merged { len, .. } = target
+        && image.len() > *len
+    {
+        return Err(format!("new overload ({} bytes) does not fit the merged binary's {}", image.len(), len));
+    }
+    Ok(image)
+}
+
+fn locate(own: &[u8]) -> Result<Target, String> {
+    if memexec::from_memory() {
+        return Ok(Target::Memory);
+    }
+
+    // The loader of a merged binary runs us from an extracted copy
+    let parent = lineage::original_parent().or_else(process::get_parent_pid).and_then(kill_parent::get_parent_binary_path);
+    if let Some(path) = parent
+        && let Ok(merged) = fs::read(&path)
+        && let Some(offset) = find_image(&merged, own)
+    {
+        return Ok(Target::Merged { path, offset, len: own.len() });
+    }
+
+    let exe = memexec::executable_path().map_err(|e| format!("Failed to get executable path: {}", e))?;
+    match fs::read(&exe) {
+        Ok(disk) if disk == own => Ok(Target::Executable(exe)),
+        _ => Err("neither a standalone overload nor found in the merged binary".to_string()),
+    }
+}
+
+/// Offset of `image` inside `haystack`
+fn find_image(haystack: &[u8], image: &[u8]) -> Option<usize> {
+    let probe = &image[..image.len().min(PROBE_LEN)];
+    if probe.is_empty() || haystack.len() < image.len() {
+        return None;
+    }
+    (0..=haystack.len() - image.len())
+        .filter(|&offset| haystack[offset..].starts_with(probe))
+        .find(|&offset| haystack[offset..offset + image.len()] == *image)
+}
+
+fn write(target: &Target, image: &[u8]) -> Result<(), String> {
+    match target {
+        Target::Executable(path) => replace_file(path, |staged| staged.write_all(image)),
+        Target::Merged { path, offset, len } => replace_file(path, |staged| {
+            // Streamed: the merged binary can be large
+            io::copy(&mut File::open(path)?, staged)?;
+            staged.seek(SeekFrom::Start(*offset as u64))?;
+            staged.write_all(image)?;
+            staged.write_all(&vec![0; len - image.len()])
+        }),
+        Target::Memory => Ok(()),
+    }
+}
+
+/// Hex SHA-256 of the overload `write` installed, as the next run hashes it
+fn installed_hash(target: &Target, image: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(image);
+    if let Target::Merged { len, .. } = target {
+        hasher.update(vec![0; len - image.len()]);
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Replace executable `path` by a file `fill` writes, with the same
+/// permissions, atomically
+///
+/// A running executable cannot be replaced on Windows, only renamed: it is
+/// moved aside to `<path>.old` (removed by the next update) and moved back
+/// if the new one cannot take its place.
+pub fn replace_file(path: &Path, fill: impl FnOnce(&mut File) -> io::Result<()>) -> Result<(), String> {
+    let sibling = |extension: &str| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(".");
+        name.push(extension);
+        PathBuf::from(name)
+    };
+    let permissions = fs::metadata(path)
+        .map_err(|e| format!("Failed to stat {}: {}", path.display(), e))?
+        .permissions();
+
+    let staged = sibling("update");
+    let staged_result = File::create(&staged)
+        .and_then(|mut file| {
+            fill(&mut file)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::set_permissions(&staged, permissions));
+    if let Err(e) = staged_result {
+        let _ = fs::remove_file(&staged);
+        return Err(format!("Failed to write {}: {}", staged.display(), e));
+    }
+
+    #[cfg(windows)]
+    let old = sibling("old");
+    #[cfg(windows)]
+    {
+        let _ = fs::remove_file(&old);
+        if let Err(e) = fs::rename(path, &old) {
+            let _ = fs::remove_file(&staged);
+            return Err(format!("Failed to move {} aside: {}", path.display(), e));
+        }
+    }
+    fs::rename(&staged, path).map_err(|e| {
+        let _ = fs::remove_file(&staged);
+        #[cfg(windows)]
+        let _ = fs::rename(&old, path);
+        format!("Failed to replace {}: {}", path.display(), e)
+    })
+}
+
+/// Re-execute the installed overload
+///
+/// # Returns
+/// Why that failed (it does not return otherwise)
+fn relaunch_into(target: &Target, image: &[u8]) -> String {
+    match target {
+        #[cfg(unix)]
+        Target::Executable(path) => {
+            use std::os::unix::process::CommandExt;
+
+            log_info!("🔁 Re-executing {}", path.display());
+            let mut command = std::process::Command::new(path);
+            if let Some(arg0) = std::env::args_os().next() {
+                command.arg0(arg0);
+            }
+            command.args(std::env::args_os().skip(1)).exec().to_string()
+        }
+        #[cfg(target_os = "linux")]
+        Target::Merged { .. } | Target::Memory => {
+            log_info!("🔁 Re-executing the new overload from memory");
+            let disk = memexec::from_memory().then(|| memexec::executable_path().ok()).flatten();
+            memexec::exec_image(image, disk.as_deref().map(Path::as_os_str))
+        }
+        #[cfg(not(target_os = "linux"))]
+        _ => {
+            let _ = image;
+            "re-execution not supported here".to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_ordering() {
+        assert!(is_newer("1.10.0", "1.9.3"));
+        assert!(is_newer("v2.0", "1.99.99"));
+        assert!(!is_newer("1.2.0", "1.2.0"));
+        assert!(!is_newer("1.2.0-rc1", "1.2.0"));
+        assert!(!is_newer("1.1.9", "1.2.0"));
+        assert!(!is_newer("latest", "1.0.0"));
+    }
+
+    #[test]
+    fn test_merged_region_found_and_replaced() {
+        let own = b"\x7fELF overload image".to_vec();
+        let mut merged = b"\x7fELF loader ... \x7fELF base".to_vec();
+        let offset = merged.len();
+        merged.extend_from_slice(&own);
+        merged.extend_from_slice(b"trailer");
+        assert_eq!(find_image(&merged, &own), Some(offset));
+        assert_eq!(find_image(&merged, b"\x7fELF other"), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app");
+        fs::write(&path, &merged).unwrap();
+        let target = Target::Merged { path: path.clone(), offset, len: own.len() };
+        write(&target, b"\x7fELF new").unwrap();
+
+        let replaced = fs::read(&path).unwrap();
+        assert_eq!(replaced.len(), merged.len());
+        assert!(replaced[offset..].starts_with(b"\x7fELF new\0\0"));
+        assert!(replaced.ends_with(b"trailer"));
+        assert!(!dir.path().join("app.update").exists());
+
+        // A pinned merged binary is not changed under its own pin
+        let config: Config = serde_json::from_str(&format!(
+            r#"{{"license_id": "lic", "server_url": "https://a.example", "shared_secret": "s", "expected_parent_sha256": "{}"}}"#,
+            "ab".repeat(32)
+        ))
+        .unwrap();
+        assert!(prepare(&config, &own, b"\x7fELF new".to_vec(), &target).unwrap_err().contains("expected_parent_sha256"));
+    }
+}
@@ -0,0 +1,44 @@
+//! Tamper reports sent to the license server
+
+use serde::Serialize;
+
+use super::fingerprint::get_machine_fingerprint;
+use super::events;
+use super::network::post_signed;
+use crate::utils::time;
+
+/// API path of the tamper report endpoint
+const TAMPER_PATH: &str = "/api/v1/tamper-report";
+
+/// Tamper report payload
+#[derive(Debug, Serialize)]
+struct TamperReport<'a> {
+    license_id: &'a str,
+    machine_fingerprint: String,
+    /// Detection kind, e.g. "integrity"
+    kind: &'a str,
+    detail: &'a str,
+    timestamp: i64,
+}
+
+/// Report a tamper detection to the server
+///
+/// Best effort: failures are logged and never prevent enforcement.
+pub fn report_tamper(server_url: &str, license_id: &str, shared_secret: &str, kind: &str, detail: &str) {
+    log_info!("📨 Reporting tamper event: {} ({})", kind, detail);
+    events::record("tamper", &format!("{}: {}", kind, detail));
+
+    let report = TamperReport {
+        license_id,
+        machine_fingerprint: get_machine_fingerprint(),
+        kind,
+        detail,
+        timestamp: time::protocol_now(),
+    };
+
+    match post_signed(server_url, TAMPER_PATH, license_id, shared_secret, &report) {
+        Ok(status) if status == 200 || status == 202 => {}
+        Ok(status) => log_warn!("⚠️  Tamper report rejected with HTTP {}", status),
+        Err(e) => log_warn!("⚠️  Failed to send tamper report: {}", e),
+    }
+}
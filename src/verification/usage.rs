@@ -0,0 +1,59 @@
+//! Usage metering events reported to the license server
+
+use serde::Serialize;
+
+use super::fingerprint::get_machine_fingerprint;
+use super::network::post_signed;
+use crate::utils::{session, time};
+
+/// API path of the usage endpoint
+const USAGE_PATH: &str = "/api/v1/usage";
+
+/// Single usage event (one protected-binary invocation)
+#[derive(Debug, Serialize)]
+pub struct UsageEvent {
+    pub license_id: String,
+    pub machine_fingerprint: String,
+    /// Login session the invocation ran in (seats are counted per session)
+    pub session_id: String,
+    pub user: String,
+    /// Event kind, e.g. "base_exit"
+    pub event: String,
+    /// Verification outcome: "authorized", "unauthorized", "error", "timeout" or "cancelled"
+    pub verification: String,
+    pub base_exit_code: Option<i32>,
+    pub runtime_ms: u64,
+    /// Number of invocations this event accounts for
+    pub invocations: u64,
+    pub timestamp: i64,
+}
+
+impl UsageEvent {
+    pub fn new(license_id: &str, event: &str, verification: &str) -> Self {
+        let session = session::current_session();
+        Self {
+            license_id: license_id.to_string(),
+            machine_fingerprint: get_machine_fingerprint(),
+            session_id: session.session_id,
+            user: session.user,
+            event: event.to_string(),
+            verification: verification.to_string(),
+            base_exit_code: None,
+            runtime_ms: 0,
+            invocations: 1,
+            timestamp: time::protocol_now(),
+        }
+    }
+}
+
+/// Send a usage event to the server
+pub fn report_usage(server_url: &str, shared_secret: &str, event: &UsageEvent) -> Result<(), String> {
+    log_info!("📈 Reporting usage event: {} ({})", event.event, event.verification);
+
+    let status = post_signed(server_url, USAGE_PATH, &event.license_id, shared_secret, event)?;
+    if status != 200 && status != 202 {
+        return Err(format!("Usage report rejected with HTTP {}", status));
+    }
+
+    Ok(())
+}
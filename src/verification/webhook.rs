@@ -0,0 +1,137 @@
+//! Local webhook sink of the event system
+//!
+//! Air-gapped sites cannot see what we ship to the license server, but still
+//! want on-prem visibility. With `webhook_url` set, every check result and
+//! every security event is POSTed as JSON to that customer-run endpoint
+//! (typically localhost or the intranet), signed with the customer's own
+//! `webhook_key`: `X-Killer-Signature` is the hex HMAC-SHA256 of the body.
+//!
+//! Deliveries are fire-and-forget on a background task: nothing is queued or
+//! retried, and a slow or missing receiver never delays a check. Only a
+//! graceful shutdown waits briefly (`drain`) so the last results are not
+//! lost; kills and crashes do not wait for the network.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use super::events::SecurityEvent;
+use super::hmac::create_signature;
+use super::http::{self, HttpRequest};
+use crate::config::Config;
+use crate::security::secrets::SecretString;
+use crate::utils::state::namespace;
+use crate::utils::tasks;
+
+/// Header carrying the HMAC of the body under `webhook_key`
+pub const SIGNATURE_HEADER: &str = "X-Killer-Signature";
+
+static WEBHOOK: OnceLock<Webhook> = OnceLock::new();
+
+/// How long a graceful shutdown waits for deliveries still in flight
+pub const EXIT_DRAIN: Duration = Duration::from_secs(2);
+
+/// Deliveries still in flight
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+struct Webhook {
+    url: String,
+    key: SecretString,
+}
+
+/// Delivered body
+#[derive(Debug, Serialize)]
+struct Delivery<'a> {
+    #[serde(flatten)]
+    event: &'a SecurityEvent,
+    /// License namespace (hashed license ID)
+    namespace: Option<&'a str>,
+    pid: u32,
+    killer_version: &'static str,
+}
+
+/// Enable the sink if `webhook_url` is configured
+pub fn configure(config: &Config) {
+    let (Some(url), Some(key)) = (&config.webhook_url, &config.webhook_key) else {
+        return;
+    };
+    if WEBHOOK.set(Webhook { url: url.clone(), key: key.clone() }).is_ok() {
+        log_info!("🪝 Delivering check results to webhook {}", url);
+    }
+}
+
+/// Deliver `event` to the webhook in the background (no-op when unset)
+pub fn deliver(event: &SecurityEvent) {
+    let Some(webhook) = WEBHOOK.get() else {
+        return;
+    };
+    let body = match signed_body(event, &webhook.key) {
+        Ok(body) => body,
+        Err(e) => {
+            log_warn!("⚠️  Webhook delivery skipped: {}", e);
+            return;
+        }
+    };
+
+    IN_FLIGHT.fetch_add(1, Ordering::SeqCst);
+    tasks::spawn("webhook_delivery", move || {
+        let (body, signature) = body.clone();
+        let result = http::client().and_then(|client| {
+            client.send(
+                HttpRequest::post(&webhook.url, body)
+                    .header("Content-Type", "application/json")
+                    .header(SIGNATURE_HEADER, signature),
+            )
+        });
+        match result {
+            Ok(response) if response.is_success() => {}
+            Ok(response) => log_debug!("🪝 Webhook answered HTTP {}", response.status),
+            Err(e) => log_debug!("🪝 Webhook delivery failed: {}", e),
+        }
+        IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+    });
+}
+
+/// Wait up to `timeout` for deliveries still in flight
+pub fn drain(timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    while IN_FLIGHT.load(Ordering::SeqCst) > 0 && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// Body and its signature
+fn signed_body(event: &SecurityEvent, key: &str) -> Result<(String, String), String> {
+    let delivery = Delivery {
+        event,
+        namespace: namespace(),
+        pid: std::process::id(),
+        killer_version: env!("CARGO_PKG_VERSION"),
+    };
+    let body = serde_json::to_string(&delivery).map_err(|e| e.to_string())?;
+    let signature = create_signature(&body, key);
+    Ok((body, signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verification::verify_signature;
+
+    #[test]
+    fn test_delivery_is_signed_with_customer_key() {
+        let event = SecurityEvent {
+            kind: "check_authorized".to_string(),
+            detail: "ok".to_string(),
+            timestamp: 1700000000,
+        };
+        let (body, signature) = signed_body(&event, "customer-key").unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["kind"], "check_authorized");
+        assert_eq!(json["timestamp"], 1700000000);
+        assert!(verify_signature(&body, "customer-key", &signature));
+        assert!(!verify_signature(&body, "other-key", &signature));
+    }
+}
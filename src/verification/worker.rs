@@ -0,0 +1,60 @@
+//! Generations of the verification worker
+//!
+//! A worker the watchdog abandoned keeps running until its hung call
+//! returns. Whatever it learns afterwards is stale: the cached response, the
+//! server clock offset, the failure counters, the active license and the
+//! seat lease belong to its replacement. Each worker thread is tagged with
+//! the generation it was started for, and the writers of that state skip
+//! their update once `is_abandoned` says the calling worker was replaced.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Generation of the worker currently in charge
+static CURRENT: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// Generation of the worker running on this thread (None: not a worker)
+    static GENERATION: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// Generation a new worker is started for
+pub fn current() -> u64 {
+    CURRENT.load(Ordering::SeqCst)
+}
+
+/// Tag the calling thread as the worker of `generation`
+pub fn enter(generation: u64) {
+    GENERATION.with(|tag| tag.set(Some(generation)));
+}
+
+/// Retire the current worker (before starting its replacement)
+pub fn abandon() {
+    CURRENT.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Whether the calling thread is a worker that was replaced
+pub fn is_abandoned() -> bool {
+    GENERATION.with(|tag| tag.get()).is_some_and(|generation| generation != current())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_abandoned_worker() {
+        // Threads that are not workers are never abandoned
+        assert!(!is_abandoned());
+
+        let generation = current();
+        let worker = std::thread::spawn(move || {
+            enter(generation);
+            let before = is_abandoned();
+            abandon();
+            (before, is_abandoned())
+        });
+        assert_eq!(worker.join().unwrap(), (false, true));
+        assert!(!is_abandoned());
+    }
+}
@@ -0,0 +1,80 @@
+//! The overload binary against the mock license server (`testing` feature)
+//!
+//! Each run starts the binary in single-check mode from a wrapper shell that
+//! stands in for the protected app, so the kill path has a parent to stop.
+#![cfg(unix)]
+
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+use kc_killer::testing::{MockServer, Reply};
+use kc_killer::utils::exit_status::STATUS_FD_ENV;
+use kc_killer::utils::state::STATE_DIR_ENV;
+use serde_json::{json, Value};
+
+const SECRET: &str = "mock-secret";
+
+/// Overload installed in `dir` with a `.config` pointing at `server`
+fn install(dir: &Path, server: &MockServer) -> PathBuf {
+    let exe = dir.join("kc-killer");
+    std::fs::copy(env!("CARGO_BIN_EXE_kc-killer"), &exe).unwrap();
+    let config = json!({
+        "license_id": "lic_e2e",
+        "server_url": server.url(),
+        "shared_secret": SECRET,
+        "check_interval_ms": 0,
+        "kill_method": "stop",
+        "self_destruct": false,
+        "log_level": "debug",
+    });
+    std::fs::write(dir.join("kc-killer.config"), config.to_string()).unwrap();
+    exe
+}
+
+/// Run `exe` under a wrapper shell
+///
+/// # Returns
+/// The wrapper's output and the exit status line the overload wrote
+fn run(dir: &Path, exe: &Path) -> (Output, Value) {
+    let status_path = dir.join("status.json");
+    let output = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(r#""$0" 3>"$1"; echo "app survived: $?""#)
+        .arg(exe)
+        .arg(&status_path)
+        .env(STATE_DIR_ENV, dir.join("state"))
+        .env(STATUS_FD_ENV, "3")
+        .output()
+        .unwrap();
+    let status = std::fs::read_to_string(&status_path).unwrap();
+    (output, serde_json::from_str(&status).unwrap())
+}
+
+#[test]
+fn test_authorized_single_check() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = MockServer::start(SECRET).unwrap();
+    let exe = install(dir.path(), &server);
+
+    let (output, status) = run(dir.path(), &exe);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("app survived: 0"));
+    assert_eq!((status["status"].as_str(), status["exit_code"].as_i64()), (Some("authorized"), Some(0)));
+    assert_eq!(server.verify_requests()[0].header("X-First-Check"), Some("true"));
+}
+
+#[test]
+fn test_revoked_license_stops_the_app() {
+    let dir = tempfile::tempdir().unwrap();
+    let server = MockServer::start(SECRET).unwrap();
+    server.script([Reply::Revoked]);
+    let exe = install(dir.path(), &server);
+
+    let (output, status) = run(dir.path(), &exe);
+    assert!(!output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("app survived"));
+    assert_eq!((status["status"].as_str(), status["exit_code"].as_i64()), (Some("unauthorized"), Some(10)));
+
+    // The server hears about the kill before it happens
+    let report = server.requests().into_iter().find(|request| request.path == "/api/v1/kill-report").unwrap();
+    assert_eq!(report.json().unwrap()["kill_method"], "stop");
+}
@@ -0,0 +1,71 @@
+//! Verification and runtime patches against the mock license server
+//! (`testing` feature)
+
+use std::time::{Duration, Instant};
+
+use kc_killer::testing::{MockServer, Reply};
+use kc_killer::utils::state::STATE_DIR_ENV;
+use kc_killer::verification::{patch, verify_license, verify_signature};
+use kc_killer::{Config, KillMethod};
+use serde_json::json;
+
+const SECRET: &str = "mock-secret";
+
+// One test: the network module keeps the latest error class globally, and
+// the state directory is set through the environment
+#[test]
+fn test_scripted_replies() {
+    let state = tempfile::tempdir().unwrap();
+    unsafe { std::env::set_var(STATE_DIR_ENV, state.path()) };
+
+    let server = MockServer::start(SECRET).unwrap();
+    let slow = Duration::from_millis(300);
+    server.script([
+        Reply::Authorized,
+        Reply::Revoked,
+        Reply::RateLimited { retry_after_secs: 7 },
+        Reply::Malformed,
+        Reply::Slow { delay: slow, reply: Box::new(Reply::Authorized) },
+    ]);
+    let verify = || verify_license("lic_mock", &server.url(), SECRET, 0, true);
+
+    let authorized = verify().unwrap();
+    assert!(authorized.authorized && authorized.signature_valid);
+
+    // A signed denial is an answer, not an error
+    let revoked = verify().unwrap();
+    assert!(!revoked.authorized && revoked.signature_valid);
+    assert_eq!(revoked.message, "License revoked");
+
+    assert_eq!(verify().unwrap_err().class.retry_after(), Some(Duration::from_secs(7)));
+
+    let malformed = verify().unwrap_err();
+    assert!(malformed.message.contains("Failed to parse"), "{}", malformed);
+    assert!(!malformed.class.is_retryable());
+
+    let started = Instant::now();
+    assert!(verify().unwrap().authorized);
+    assert!(started.elapsed() >= slow);
+
+    // Requests are signed the way the server checks them
+    let requests = server.verify_requests();
+    assert_eq!(requests.len(), 5);
+    assert_eq!(requests[0].header("X-License-ID"), Some("lic_mock"));
+    assert!(verify_signature(&requests[0].body, SECRET, requests[0].header("X-Body-Signature").unwrap()));
+    assert_eq!(requests[0].json().unwrap()["license_id"], "lic_mock");
+
+    // Only a signed reply retunes the running configuration
+    let patched = json!({ "authorized": true, "message": "ok", "expires_in": null, "check_interval_ms": 60000, "kill_method": "delete" });
+    server.script([
+        Reply::Json { status: 200, body: patched.clone(), signed: false },
+        Reply::Json { status: 200, body: patched, signed: true },
+    ]);
+    let mut config: Config = serde_json::from_value(json!({
+        "license_id": "lic_mock", "server_url": server.url(), "shared_secret": SECRET, "check_interval_ms": 5000
+    }))
+    .unwrap();
+    assert!(patch::apply(&mut config, &verify().unwrap(), |method| method.clone()).is_empty());
+    let changes = patch::apply(&mut config, &verify().unwrap(), |method| method.clone());
+    assert_eq!(changes.len(), 2);
+    assert_eq!((config.check_interval_ms, config.kill_method), (60000, KillMethod::Delete));
+}